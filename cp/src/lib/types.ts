@@ -179,6 +179,7 @@ export type ProviderType =
   | 'openai' | 'anthropic' | 'gemini' | 'custom_dual'
   | 'deepseek' | 'moonshot' | 'minimax' | 'ollama'
   | 'jina' | 'openrouter' | 'zai'
+  | 'groq' | 'mistral' | 'cohere' | 'together' | 'custom'
 
 export interface AdminChannel {
   name: string
@@ -19,6 +19,7 @@ const PROVIDER_TYPES: ProviderType[] = [
   'openai', 'anthropic', 'gemini', 'custom_dual',
   'deepseek', 'moonshot', 'minimax', 'ollama',
   'jina', 'openrouter', 'zai',
+  'groq', 'mistral', 'cohere', 'together', 'custom',
 ]
 
 function EndpointLine({ label, url }: { label: string; url: string }) {
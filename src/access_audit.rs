@@ -0,0 +1,210 @@
+//! Persistent `AccessAudit` sinks selected by the `access_audit` config section.
+
+use crate::config::{AccessAuditConfig, AccessAuditSink, ProviderType};
+use crate::database::Database;
+use crate::providers::{AccessAudit, AuditEvent, NoOpAccessAudit, RouteKind};
+use anyhow::Result;
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Build the audit sink for the given config (`None` = audit disabled).
+pub fn from_config(
+    config: Option<&AccessAuditConfig>,
+    data_dir: &str,
+    database: Arc<Database>,
+) -> Result<Arc<dyn AccessAudit>> {
+    let Some(config) = config else {
+        return Ok(Arc::new(NoOpAccessAudit));
+    };
+    Ok(match config.sink {
+        AccessAuditSink::Sqlite => Arc::new(SqliteAccessAudit { database }),
+        AccessAuditSink::File => Arc::new(FileAccessAudit::new(file_sink_dir(config, data_dir))?),
+    })
+}
+
+/// Drop events older than `retention_days` from whichever sink is configured.
+pub fn prune(config: &AccessAuditConfig, data_dir: &str, database: &Database) -> Result<u64> {
+    match config.sink {
+        AccessAuditSink::Sqlite => database.cleanup_access_audit(config.retention_days),
+        AccessAuditSink::File => {
+            prune_files(&file_sink_dir(config, data_dir), config.retention_days)
+        }
+    }
+}
+
+/// Writes events to the `access_audit` table.
+pub struct SqliteAccessAudit {
+    database: Arc<Database>,
+}
+
+impl AccessAudit for SqliteAccessAudit {
+    fn audit(&self, provider: &ProviderType, route: RouteKind, success: bool) {
+        self.record(&AuditEvent {
+            request_id: None,
+            team_id: "unknown",
+            api_key: None,
+            provider,
+            channel: "",
+            route,
+            success,
+        });
+    }
+
+    fn record(&self, event: &AuditEvent<'_>) {
+        let timestamp = chrono::Local::now().format(TIMESTAMP_FORMAT).to_string();
+        if let Err(err) = self.database.log_access_audit(
+            &timestamp,
+            event.request_id,
+            event.team_id,
+            event.api_key,
+            &provider_label(event.provider),
+            event.channel,
+            event.route.as_str(),
+            event.success,
+        ) {
+            tracing::error!("Access audit write failed: {}", err);
+        }
+    }
+}
+
+/// Appends JSON lines to `<dir>/audit-YYYY-MM-DD.jsonl`.
+pub struct FileAccessAudit {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileAccessAudit {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+}
+
+impl AccessAudit for FileAccessAudit {
+    fn audit(&self, provider: &ProviderType, route: RouteKind, success: bool) {
+        self.record(&AuditEvent {
+            request_id: None,
+            team_id: "unknown",
+            api_key: None,
+            provider,
+            channel: "",
+            route,
+            success,
+        });
+    }
+
+    fn record(&self, event: &AuditEvent<'_>) {
+        let now = chrono::Local::now();
+        let line = json!({
+            "timestamp": now.format(TIMESTAMP_FORMAT).to_string(),
+            "request_id": event.request_id,
+            "team_id": event.team_id,
+            "api_key": event.api_key,
+            "provider": provider_label(event.provider),
+            "channel": event.channel,
+            "route": event.route.as_str(),
+            "success": event.success,
+        });
+        let path = self
+            .dir
+            .join(format!("audit-{}.jsonl", now.format("%Y-%m-%d")));
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(err) = result {
+            tracing::error!("Access audit write to {:?} failed: {}", path, err);
+        }
+    }
+}
+
+fn file_sink_dir(config: &AccessAuditConfig, data_dir: &str) -> PathBuf {
+    match config.path.as_deref() {
+        Some(path) => expand_home(path),
+        None => expand_home(data_dir).join("audit"),
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn prune_files(dir: &Path, retention_days: u64) -> Result<u64> {
+    if retention_days == 0 || !dir.exists() {
+        return Ok(0);
+    }
+    let cutoff = (chrono::Local::now() - chrono::Duration::days(retention_days as i64))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(date) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("audit-"))
+            .and_then(|name| name.strip_suffix(".jsonl"))
+        else {
+            continue;
+        };
+        // ISO dates compare correctly as strings.
+        if date < cutoff.as_str() {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn provider_label(provider: &ProviderType) -> String {
+    serde_json::to_value(provider)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{provider:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn file_sink_appends_json_lines_and_prunes_old_days() {
+        let dir = tempdir().unwrap();
+        let audit = FileAccessAudit::new(dir.path().to_path_buf()).unwrap();
+        audit.record(&AuditEvent {
+            request_id: Some("req-1"),
+            team_id: "team-a",
+            api_key: Some("sk-…abcd"),
+            provider: &ProviderType::Openai,
+            channel: "primary",
+            route: RouteKind::Openai,
+            success: true,
+        });
+
+        let today = chrono::Local::now().format("%Y-%m-%d");
+        let content =
+            std::fs::read_to_string(dir.path().join(format!("audit-{today}.jsonl"))).unwrap();
+        let event: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(event["team_id"], "team-a");
+        assert_eq!(event["provider"], "openai");
+        assert_eq!(event["success"], true);
+
+        std::fs::write(dir.path().join("audit-2000-01-01.jsonl"), "{}\n").unwrap();
+        assert_eq!(prune_files(dir.path(), 30).unwrap(), 1);
+        assert!(dir.path().join(format!("audit-{today}.jsonl")).exists());
+    }
+}
@@ -0,0 +1,213 @@
+//! Checks the `model` of a JSON request body as it streams past. Requests
+//! whose body is streamed to the upstream unread are routed, authorized and
+//! billed by a model given up front (`x-apex-model`); the body must name the
+//! same model, or the upload is cut short before the upstream has all of it.
+
+/// Longest top-level key or `model` value kept for comparison.
+const MAX_CAPTURE: usize = 1024;
+
+/// Where the scan is inside the top-level object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the opening `{`.
+    Start,
+    /// Expecting a key (or the closing `}`).
+    Key,
+    /// Inside a key.
+    InKey,
+    /// Between a key and its `:`.
+    Colon,
+    /// Expecting a value.
+    Value,
+    /// Inside a string value (or any string nested deeper).
+    InString,
+    /// After a value, expecting `,` or `}`.
+    AfterValue,
+    /// Past the closing `}`.
+    Done,
+}
+
+/// Incremental scan of a JSON object's top-level `model` fields (every one of
+/// them, since parsers disagree on which duplicate wins) against the model
+/// the request was routed by.
+#[derive(Debug)]
+pub struct ModelScan {
+    expected: Vec<u8>,
+    state: State,
+    /// Nesting below the top-level object while inside a value.
+    depth: usize,
+    escaped: bool,
+    /// Key or `model` value read so far; `None` once it can't match.
+    capture: Option<Vec<u8>>,
+    /// Whether the value being read belongs to a `model` key.
+    model_value: bool,
+    seen_model: bool,
+    failed: bool,
+}
+
+impl ModelScan {
+    pub fn new(expected: &str) -> Self {
+        Self {
+            expected: expected.as_bytes().to_vec(),
+            state: State::Start,
+            depth: 0,
+            escaped: false,
+            capture: None,
+            model_value: false,
+            seen_model: false,
+            failed: false,
+        }
+    }
+
+    /// Scan the next chunk; false once the body can no longer match: a
+    /// `model` that differs or isn't a plain string, an escaped top-level
+    /// key, or a body that isn't a JSON object.
+    pub fn feed(&mut self, chunk: &[u8]) -> bool {
+        for &byte in chunk {
+            if self.failed {
+                break;
+            }
+            self.step(byte);
+        }
+        !self.failed
+    }
+
+    /// Whether the whole object has been seen and named the expected model.
+    pub fn matched(&self) -> bool {
+        !self.failed && self.state == State::Done && self.seen_model
+    }
+
+    fn step(&mut self, byte: u8) {
+        match self.state {
+            State::Start => match byte {
+                b'{' => self.state = State::Key,
+                _ if byte.is_ascii_whitespace() => {}
+                _ => self.failed = true,
+            },
+            State::Key => match byte {
+                b'"' => {
+                    self.capture = Some(Vec::new());
+                    self.state = State::InKey;
+                }
+                b'}' => self.state = State::Done,
+                _ if byte.is_ascii_whitespace() => {}
+                _ => self.failed = true,
+            },
+            State::InKey => match byte {
+                // An escaped key may spell `model` to the upstream.
+                b'\\' => self.failed = true,
+                b'"' => {
+                    self.model_value = self.capture.take().as_deref() == Some(b"model");
+                    self.state = State::Colon;
+                }
+                _ => self.push(byte),
+            },
+            State::Colon => match byte {
+                b':' => self.state = State::Value,
+                _ if byte.is_ascii_whitespace() => {}
+                _ => self.failed = true,
+            },
+            State::Value => match byte {
+                _ if byte.is_ascii_whitespace() => {}
+                b'"' => {
+                    self.capture = self.model_value.then(Vec::new);
+                    self.state = State::InString;
+                }
+                _ if self.model_value => self.failed = true,
+                b'{' | b'[' => {
+                    self.depth = 1;
+                    self.state = State::AfterValue;
+                }
+                _ => self.state = State::AfterValue,
+            },
+            State::InString => {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    // Models are compared as written.
+                    if self.depth == 0 && self.model_value {
+                        self.failed = true;
+                    }
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    if self.depth == 0 && self.model_value {
+                        let model = self.capture.take();
+                        self.failed = model.as_deref() != Some(self.expected.as_slice());
+                        self.seen_model = true;
+                        self.model_value = false;
+                    }
+                    self.state = State::AfterValue;
+                } else if self.depth == 0 && self.model_value {
+                    self.push(byte);
+                }
+            }
+            State::AfterValue => match byte {
+                b'"' if self.depth > 0 => self.state = State::InString,
+                b'{' | b'[' if self.depth > 0 => self.depth += 1,
+                b'}' | b']' if self.depth > 0 => self.depth -= 1,
+                _ if self.depth > 0 => {}
+                b',' => self.state = State::Key,
+                b'}' => self.state = State::Done,
+                _ => {}
+            },
+            State::Done => {
+                if !byte.is_ascii_whitespace() {
+                    self.failed = true;
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if let Some(capture) = self.capture.as_mut() {
+            if capture.len() < MAX_CAPTURE {
+                capture.push(byte);
+            } else {
+                self.capture = None;
+                // An oversized model can't be the expected one.
+                self.failed = self.model_value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(expected: &str, chunks: &[&str]) -> bool {
+        let mut scan = ModelScan::new(expected);
+        chunks.iter().all(|chunk| scan.feed(chunk.as_bytes())) && scan.matched()
+    }
+
+    #[test]
+    fn matching_models_pass_across_chunk_boundaries() {
+        let body = r#" {"messages": [{"role": "user", "content": "say \"model\": {x}"}], "mo"#;
+        assert!(scan(
+            "gpt-4",
+            &[body, r#"del": "gp"#, r#"t-4", "stream": true} "#]
+        ));
+        assert!(scan(
+            "gpt-4",
+            &[r#"{"metadata": {"model": "other"}, "model": "gpt-4"}"#]
+        ));
+    }
+
+    #[test]
+    fn other_or_hidden_models_fail() {
+        assert!(!scan("cheap", &[r#"{"model": "expensive"}"#]));
+        assert!(!scan(
+            "cheap",
+            &[r#"{"model": "cheap", "model": "expensive"}"#]
+        ));
+        assert!(!scan(
+            "cheap",
+            &[r#"{"mod\u0065l": "expensive", "model": "cheap"}"#]
+        ));
+        assert!(!scan("cheap", &[r#"{"model": "che\u0061p"}"#]));
+        assert!(!scan("cheap", &[r#"{"model": ["cheap"]}"#]));
+        assert!(!scan("cheap", &[r#"{"messages": []}"#]));
+        assert!(!scan("cheap", &[r#"{"model": "cheap""#]));
+        assert!(!scan("cheap", &[r#"[{"model": "cheap"}]"#]));
+    }
+}
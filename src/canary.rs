@@ -0,0 +1,195 @@
+//! Canary rollouts (`router.canary`).
+//!
+//! A router with a canary sends its configured share of requests to the
+//! canary channel and watches the outcomes of those requests over a sliding
+//! window. Once the window holds `min_requests` outcomes and the error rate
+//! or p95 latency exceeds its threshold, the canary is rolled back: it gets
+//! no more traffic, `apex_canary_rollbacks_total` is incremented and
+//! `apex_canary_percent` drops to 0. The rollback lasts until the router's
+//! canary settings change (a config reload with the same settings keeps it).
+
+use crate::config::Canary;
+use crate::metrics::MetricsState;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct CanaryState {
+    /// Settings the state was built for; changed settings start afresh.
+    settings: Canary,
+    outcomes: VecDeque<(Instant, bool, f64)>,
+    rolled_back: bool,
+}
+
+impl CanaryState {
+    fn new(settings: &Canary) -> Self {
+        Self {
+            settings: settings.clone(),
+            outcomes: VecDeque::new(),
+            rolled_back: false,
+        }
+    }
+
+    /// Why the window breaches the thresholds, if it does.
+    fn breach(&self) -> Option<String> {
+        let settings = &self.settings;
+        if self.outcomes.len() < settings.min_requests.max(1) {
+            return None;
+        }
+        let failures = self.outcomes.iter().filter(|(_, ok, _)| !ok).count();
+        let error_rate = failures as f64 / self.outcomes.len() as f64;
+        if error_rate > settings.max_error_rate {
+            return Some(format!(
+                "error rate {:.1}% over limit {:.1}%",
+                error_rate * 100.0,
+                settings.max_error_rate * 100.0
+            ));
+        }
+        let limit = settings.max_latency_ms?;
+        let mut latencies: Vec<f64> = self.outcomes.iter().map(|(_, _, ms)| *ms).collect();
+        latencies.sort_by(f64::total_cmp);
+        let p95 = latencies[(latencies.len() * 95).div_ceil(100) - 1];
+        (p95 > limit).then(|| format!("p95 latency {p95:.0}ms over limit {limit:.0}ms"))
+    }
+}
+
+#[derive(Default)]
+pub struct CanaryTracker {
+    routers: Mutex<HashMap<String, CanaryState>>,
+}
+
+impl CanaryTracker {
+    /// Whether this request of `router` goes to the canary channel.
+    pub fn route_to_canary(&self, router: &str, canary: &Canary, metrics: &MetricsState) -> bool {
+        let mut routers = self.routers.lock().unwrap_or_else(|e| e.into_inner());
+        let state = routers
+            .entry(router.to_string())
+            .or_insert_with(|| CanaryState::new(canary));
+        if state.settings != *canary {
+            *state = CanaryState::new(canary);
+        }
+        let percent = if state.rolled_back {
+            0.0
+        } else {
+            canary.percent
+        };
+        metrics
+            .canary_percent
+            .with_label_values(&[router, &canary.channel])
+            .set(percent);
+        percent > 0.0 && (percent >= 100.0 || rand::random::<f64>() * 100.0 < percent)
+    }
+
+    /// Record the outcome of a request sent to the canary, rolling it back
+    /// when the window breaches a threshold.
+    pub fn record(
+        &self,
+        router: &str,
+        canary: &Canary,
+        success: bool,
+        latency_ms: f64,
+        metrics: &MetricsState,
+    ) {
+        let now = Instant::now();
+        let window = Duration::from_secs(canary.window_secs);
+        let mut routers = self.routers.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = routers
+            .get_mut(router)
+            .filter(|state| state.settings == *canary && !state.rolled_back)
+        else {
+            return;
+        };
+        while let Some((at, _, _)) = state.outcomes.front() {
+            if now.duration_since(*at) > window {
+                state.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+        state.outcomes.push_back((now, success, latency_ms));
+        if let Some(reason) = state.breach() {
+            state.rolled_back = true;
+            state.outcomes.clear();
+            tracing::warn!(
+                "Canary Rolled Back: router '{}' channel '{}': {}",
+                router,
+                canary.channel,
+                reason
+            );
+            metrics
+                .canary_rollbacks_total
+                .with_label_values(&[router, &canary.channel])
+                .inc();
+            metrics
+                .canary_percent
+                .with_label_values(&[router, &canary.channel])
+                .set(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolled_back(tracker: &CanaryTracker, router: &str) -> bool {
+        tracker.routers.lock().unwrap()[router].rolled_back
+    }
+
+    fn canary() -> Canary {
+        Canary {
+            channel: "next".to_string(),
+            percent: 100.0,
+            max_error_rate: 0.25,
+            max_latency_ms: Some(500.0),
+            window_secs: 60,
+            min_requests: 4,
+        }
+    }
+
+    #[test]
+    fn rolls_back_on_error_rate_once_the_window_is_full() {
+        let metrics = MetricsState::new().unwrap();
+        let tracker = CanaryTracker::default();
+        let canary = canary();
+        assert!(tracker.route_to_canary("r1", &canary, &metrics));
+        for _ in 0..3 {
+            tracker.record("r1", &canary, false, 10.0, &metrics);
+        }
+        // Too few requests to judge yet.
+        assert!(!rolled_back(&tracker, "r1"));
+        tracker.record("r1", &canary, true, 10.0, &metrics);
+        assert!(rolled_back(&tracker, "r1"));
+        assert!(!tracker.route_to_canary("r1", &canary, &metrics));
+        assert_eq!(
+            metrics
+                .canary_rollbacks_total
+                .with_label_values(&["r1", "next"])
+                .get(),
+            1
+        );
+
+        // New settings start a fresh rollout.
+        let retuned = Canary {
+            percent: 100.0,
+            max_error_rate: 0.5,
+            ..canary
+        };
+        assert!(tracker.route_to_canary("r1", &retuned, &metrics));
+    }
+
+    #[test]
+    fn rolls_back_on_p95_latency() {
+        let metrics = MetricsState::new().unwrap();
+        let tracker = CanaryTracker::default();
+        let canary = canary();
+        tracker.route_to_canary("r1", &canary, &metrics);
+        for latency in [100.0, 120.0, 90.0] {
+            tracker.record("r1", &canary, true, latency, &metrics);
+        }
+        tracker.record("r1", &canary, true, 110.0, &metrics);
+        assert!(!rolled_back(&tracker, "r1"));
+        tracker.record("r1", &canary, true, 2_000.0, &metrics);
+        assert!(rolled_back(&tracker, "r1"));
+    }
+}
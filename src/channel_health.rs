@@ -0,0 +1,277 @@
+//! Rolling per-channel outcome stats for `apex status` and
+//! `GET /admin/channels/health`.
+//!
+//! Every upstream attempt (retries and fallbacks included) is recorded, so
+//! the numbers reflect what the gateway actually sees from each provider.
+//! With a `health` config section, channels are also *ejected*: after
+//! `unhealthy_threshold` consecutive failed requests or probes, until
+//! `healthy_threshold` probes in a row succeed. `RouterSelector` skips
+//! ejected channels.
+
+use crate::config::HealthCheckConfig;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Window used for request rate and error rate.
+const WINDOW: Duration = Duration::from_secs(60);
+/// Consecutive failures after which a channel is reported `unhealthy`.
+const UNHEALTHY_AFTER_FAILURES: u32 = 5;
+/// Error rate (within the window) above which a channel is `degraded`.
+const DEGRADED_ERROR_RATE: f64 = 0.2;
+
+#[derive(Default)]
+struct ChannelStats {
+    outcomes: VecDeque<(Instant, bool)>,
+    consecutive_failures: u32,
+    total_requests: u64,
+    total_failures: u64,
+    last_success: Option<chrono::DateTime<chrono::Local>>,
+    last_failure: Option<chrono::DateTime<chrono::Local>>,
+    ejected: bool,
+    probe_successes: u32,
+    probe_failures: u32,
+    last_probe: Option<chrono::DateTime<chrono::Local>>,
+    last_probe_error: Option<String>,
+}
+
+impl ChannelStats {
+    fn evict(&mut self, now: Instant) {
+        while let Some((at, _)) = self.outcomes.front() {
+            if now.duration_since(*at) > WINDOW {
+                self.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelHealthSnapshot {
+    pub channel: String,
+    /// `healthy`, `degraded` or `unhealthy`.
+    pub state: &'static str,
+    pub rps: f64,
+    pub error_rate: f64,
+    pub requests_last_minute: usize,
+    pub consecutive_failures: u32,
+    pub total_requests: u64,
+    pub total_failures: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<String>,
+    /// Skipped by routing until probes succeed again.
+    pub ejected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_probe: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_probe_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ChannelHealth {
+    channels: Mutex<HashMap<String, ChannelStats>>,
+    /// Consecutive live failures that eject a channel; `0` disables ejection.
+    eject_after: AtomicU32,
+}
+
+impl ChannelHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, channel: &str, success: bool) {
+        let now = Instant::now();
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = channels.entry(channel.to_string()).or_default();
+        stats.evict(now);
+        stats.outcomes.push_back((now, success));
+        stats.total_requests += 1;
+        if success {
+            stats.consecutive_failures = 0;
+            stats.last_success = Some(chrono::Local::now());
+            stats.ejected = false;
+        } else {
+            stats.consecutive_failures += 1;
+            stats.total_failures += 1;
+            stats.last_failure = Some(chrono::Local::now());
+            let eject_after = self.eject_after.load(Ordering::Relaxed);
+            if eject_after > 0 && stats.consecutive_failures >= eject_after && !stats.ejected {
+                tracing::warn!(
+                    "Channel Ejected: '{}' after {} consecutive failures",
+                    channel,
+                    stats.consecutive_failures
+                );
+                stats.ejected = true;
+                stats.probe_successes = 0;
+            }
+        }
+    }
+
+    /// Apply the `health` section; `None` turns ejection off and readmits
+    /// every channel.
+    pub fn configure(&self, settings: Option<&HealthCheckConfig>) {
+        let eject_after = settings.map_or(0, |s| s.unhealthy_threshold.max(1));
+        self.eject_after.store(eject_after, Ordering::Relaxed);
+        if settings.is_none() {
+            let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+            channels
+                .values_mut()
+                .for_each(|stats| stats.ejected = false);
+        }
+    }
+
+    /// Record the outcome of an active probe.
+    pub fn record_probe(
+        &self,
+        channel: &str,
+        result: Result<(), String>,
+        settings: &HealthCheckConfig,
+    ) {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = channels.entry(channel.to_string()).or_default();
+        stats.last_probe = Some(chrono::Local::now());
+        match result {
+            Ok(()) => {
+                stats.probe_failures = 0;
+                stats.probe_successes += 1;
+                stats.last_probe_error = None;
+                if stats.ejected && stats.probe_successes >= settings.healthy_threshold {
+                    tracing::info!("Channel Readmitted: '{}' passed health probes", channel);
+                    stats.ejected = false;
+                    stats.consecutive_failures = 0;
+                }
+            }
+            Err(error) => {
+                stats.probe_successes = 0;
+                stats.probe_failures += 1;
+                if !stats.ejected && stats.probe_failures >= settings.unhealthy_threshold.max(1) {
+                    tracing::warn!(
+                        "Channel Ejected: '{}' failed health probes: {}",
+                        channel,
+                        error
+                    );
+                    stats.ejected = true;
+                }
+                stats.last_probe_error = Some(error);
+            }
+        }
+    }
+
+    pub fn is_ejected(&self, channel: &str) -> bool {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.get(channel).is_some_and(|stats| stats.ejected)
+    }
+
+    /// Stats for `names` (in that order); channels without traffic report
+    /// as healthy with zero counts.
+    pub fn snapshot<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<ChannelHealthSnapshot> {
+        let now = Instant::now();
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        names
+            .into_iter()
+            .map(|name| {
+                let stats = channels.entry(name.to_string()).or_default();
+                stats.evict(now);
+                let recent = stats.outcomes.len();
+                let failures = stats.outcomes.iter().filter(|(_, ok)| !ok).count();
+                let error_rate = if recent == 0 {
+                    0.0
+                } else {
+                    failures as f64 / recent as f64
+                };
+                let state =
+                    if stats.ejected || stats.consecutive_failures >= UNHEALTHY_AFTER_FAILURES {
+                        "unhealthy"
+                    } else if error_rate > DEGRADED_ERROR_RATE {
+                        "degraded"
+                    } else {
+                        "healthy"
+                    };
+                let format = |at: &chrono::DateTime<chrono::Local>| {
+                    at.format("%Y-%m-%d %H:%M:%S").to_string()
+                };
+                ChannelHealthSnapshot {
+                    channel: name.to_string(),
+                    state,
+                    rps: recent as f64 / WINDOW.as_secs_f64(),
+                    error_rate,
+                    requests_last_minute: recent,
+                    consecutive_failures: stats.consecutive_failures,
+                    total_requests: stats.total_requests,
+                    total_failures: stats.total_failures,
+                    last_success: stats.last_success.as_ref().map(format),
+                    last_failure: stats.last_failure.as_ref().map(format),
+                    ejected: stats.ejected,
+                    last_probe: stats.last_probe.as_ref().map(format),
+                    last_probe_error: stats.last_probe_error.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_follows_failures_and_error_rate() {
+        let health = ChannelHealth::new();
+        health.record("a", true);
+        for _ in 0..UNHEALTHY_AFTER_FAILURES {
+            health.record("b", false);
+        }
+        health.record("c", true);
+        health.record("c", false);
+
+        let snapshot = health.snapshot(["a", "b", "c", "idle"]);
+        let states: Vec<&str> = snapshot.iter().map(|s| s.state).collect();
+        assert_eq!(states, ["healthy", "unhealthy", "degraded", "healthy"]);
+        assert_eq!(snapshot[2].error_rate, 0.5);
+        assert_eq!(snapshot[3].total_requests, 0);
+    }
+
+    #[test]
+    fn ejection_follows_failures_and_probes() {
+        let settings = HealthCheckConfig {
+            interval_secs: 30,
+            timeout_ms: 1000,
+            path: "models".to_string(),
+            unhealthy_threshold: 2,
+            healthy_threshold: 2,
+        };
+        let health = ChannelHealth::new();
+        health.record("a", false);
+        health.record("a", false);
+        assert!(!health.is_ejected("a"), "ejection is off until configured");
+
+        health.configure(Some(&settings));
+        health.record("a", false);
+        assert!(health.is_ejected("a"));
+        health.record_probe("a", Ok(()), &settings);
+        assert!(health.is_ejected("a"));
+        health.record_probe("a", Ok(()), &settings);
+        assert!(!health.is_ejected("a"));
+
+        health.record_probe("b", Err("connection refused".to_string()), &settings);
+        health.record_probe("b", Err("connection refused".to_string()), &settings);
+        let snapshot = health.snapshot(["b"]);
+        assert!(snapshot[0].ejected);
+        assert_eq!(snapshot[0].state, "unhealthy");
+        assert_eq!(
+            snapshot[0].last_probe_error.as_deref(),
+            Some("connection refused")
+        );
+
+        health.configure(None);
+        assert!(!health.is_ejected("b"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-channel `max_concurrent_requests` (see `config::Channel`).
+//!
+//! Each limited channel gets a semaphore. `process_request` takes a permit
+//! before calling the channel and keeps it until the response body has been
+//! sent, so long streams count for their whole duration. A full channel is
+//! skipped: `RouterSelector` avoids it while the rule has another target,
+//! and requests that still land on it move on to the next channel or the
+//! router's fallbacks — unless the channel has a `queue_timeout_ms`, in which
+//! case up to `max_queued_requests` of them wait that long for a slot first.
+
+use crate::config::Channel;
+use axum::body::Body;
+use axum::http::Response;
+use futures::StreamExt;
+use prometheus::IntGauge;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Default bound on requests waiting for one channel.
+const DEFAULT_MAX_QUEUED: usize = 100;
+
+#[derive(Default)]
+pub struct ChannelLimits {
+    slots: Mutex<HashMap<String, Slots>>,
+}
+
+#[derive(Clone)]
+struct Slots {
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    queue_timeout: Option<Duration>,
+    max_queued: usize,
+    waiting: Arc<AtomicUsize>,
+}
+
+/// A held slot on a channel (`None` inside for unlimited channels).
+pub struct ChannelPermit(Option<OwnedSemaphorePermit>);
+
+impl ChannelLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the channels' limits (at startup and on every config change).
+    /// Channels whose limit is unchanged keep their semaphore, so requests
+    /// already in flight still count.
+    pub fn configure(&self, channels: &[Channel]) {
+        let mut slots = self.slots.lock().unwrap();
+        let previous = std::mem::take(&mut *slots);
+        for channel in channels {
+            let Some(limit) = channel.max_concurrent_requests else {
+                continue;
+            };
+            let (semaphore, waiting) = match previous.get(&channel.name) {
+                Some(old) if old.limit == limit => (old.semaphore.clone(), old.waiting.clone()),
+                _ => (Arc::new(Semaphore::new(limit)), Arc::default()),
+            };
+            slots.insert(
+                channel.name.clone(),
+                Slots {
+                    limit,
+                    semaphore,
+                    queue_timeout: channel.queue_timeout_ms.map(Duration::from_millis),
+                    max_queued: channel.max_queued_requests.unwrap_or(DEFAULT_MAX_QUEUED),
+                    waiting,
+                },
+            );
+        }
+    }
+
+    /// Take a slot on `channel`, or `None` when it is full.
+    pub fn try_acquire(&self, channel: &str) -> Option<ChannelPermit> {
+        let Some(slots) = self.slots(channel) else {
+            return Some(ChannelPermit(None));
+        };
+        slots
+            .semaphore
+            .try_acquire_owned()
+            .ok()
+            .map(|p| ChannelPermit(Some(p)))
+    }
+
+    /// Take a slot on `channel`, queueing for up to its `queue_timeout_ms`
+    /// when it is full. `None` when no slot freed up in time or the queue is
+    /// already at `max_queued_requests`. `depth` tracks the queue length.
+    pub async fn acquire(&self, channel: &str, depth: IntGauge) -> Option<ChannelPermit> {
+        if let Some(permit) = self.try_acquire(channel) {
+            return Some(permit);
+        }
+        let slots = self.slots(channel)?;
+        let timeout = slots.queue_timeout?;
+        if slots.waiting.fetch_add(1, Ordering::AcqRel) >= slots.max_queued {
+            slots.waiting.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+        depth.inc();
+        let _waiting = Waiting(slots.waiting.clone(), depth);
+        match tokio::time::timeout(timeout, slots.semaphore.acquire_owned()).await {
+            Ok(Ok(permit)) => Some(ChannelPermit(Some(permit))),
+            _ => None,
+        }
+    }
+
+    fn slots(&self, channel: &str) -> Option<Slots> {
+        self.slots.lock().unwrap().get(channel).cloned()
+    }
+
+    /// Requests currently waiting for a slot, across all channels.
+    pub fn queued(&self) -> usize {
+        self.slots
+            .lock()
+            .unwrap()
+            .values()
+            .map(|slots| slots.waiting.load(Ordering::Acquire))
+            .sum()
+    }
+
+    /// Whether `channel` has no free slot right now.
+    pub fn is_saturated(&self, channel: &str) -> bool {
+        self.slots(channel)
+            .is_some_and(|slots| slots.semaphore.available_permits() == 0)
+    }
+}
+
+/// One queued request: leaves the queue (and the depth gauge) on drop.
+struct Waiting(Arc<AtomicUsize>, IntGauge);
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+        self.1.dec();
+    }
+}
+
+impl ChannelPermit {
+    /// Keep the slot until `response`'s body is finished or dropped.
+    pub fn hold(self, response: Response<Body>) -> Response<Body> {
+        if self.0.is_none() {
+            return response;
+        }
+        let (parts, body) = response.into_parts();
+        let body = body.into_data_stream().map(move |chunk| {
+            let _slot = &self;
+            chunk
+        });
+        Response::from_parts(parts, Body::from_stream(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, limit: Option<usize>) -> Channel {
+        let mut channel: Channel = serde_json::from_value(serde_json::json!({
+            "name": name, "provider_type": "openai", "base_url": "http://x", "api_key": "k"
+        }))
+        .unwrap();
+        channel.max_concurrent_requests = limit;
+        channel
+    }
+
+    #[tokio::test]
+    async fn permits_are_held_until_the_body_is_dropped() {
+        let limits = ChannelLimits::new();
+        limits.configure(&[channel("small", Some(1)), channel("big", None)]);
+
+        assert!(limits.try_acquire("big").is_some());
+        let permit = limits.try_acquire("small").unwrap();
+        assert!(limits.is_saturated("small"));
+        assert!(limits.try_acquire("small").is_none());
+
+        let response = permit.hold(Response::new(Body::from("ok")));
+        // Reconfiguring with the same limit keeps the in-flight count.
+        limits.configure(&[channel("small", Some(1))]);
+        assert!(limits.try_acquire("small").is_none());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "ok");
+        assert!(!limits.is_saturated("small"));
+        assert!(limits.try_acquire("small").is_some());
+    }
+
+    #[tokio::test]
+    async fn full_channels_queue_up_to_their_timeout_and_bound() {
+        let mut queued = channel("queued", Some(1));
+        queued.queue_timeout_ms = Some(200);
+        queued.max_queued_requests = Some(1);
+        let limits = Arc::new(ChannelLimits::new());
+        limits.configure(&[queued, channel("plain", Some(1))]);
+        let depth = IntGauge::new("depth", "test").unwrap();
+
+        // Without a queue a full channel is refused at once.
+        let _plain = limits.try_acquire("plain").unwrap();
+        assert!(limits.acquire("plain", depth.clone()).await.is_none());
+
+        // A waiter gets the slot as soon as it is released.
+        let held = limits.try_acquire("queued").unwrap();
+        let waiter = {
+            let limits = limits.clone();
+            let depth = depth.clone();
+            tokio::spawn(async move { limits.acquire("queued", depth).await.is_some() })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(depth.get(), 1);
+        // The queue holds one request; the next is turned away.
+        assert!(limits.acquire("queued", depth.clone()).await.is_none());
+        drop(held);
+        assert!(waiter.await.unwrap());
+        assert_eq!(depth.get(), 0);
+
+        // Nobody releases: the wait gives up after the timeout.
+        let _held = limits.try_acquire("queued").unwrap();
+        assert!(limits.acquire("queued", depth.clone()).await.is_none());
+        assert_eq!(depth.get(), 0);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::{Compliance, PiiAction, PiiRule};
+use crate::config::{Compliance, PiiAction, PiiRule, TeamPiiPolicy};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use tracing::warn;
@@ -45,6 +45,9 @@ static BUILTIN_PATTERNS: Lazy<Vec<PiiRule>> = Lazy::new(|| {
     ]
 });
 
+/// Names of the built-in detectors, as selectable in `TeamPiiPolicy::builtin`.
+pub const BUILTIN_RULE_NAMES: &[&str] = &["email", "phone", "credit_card", "ip_address"];
+
 /// PII detection result
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // Used in audit logging and external callers
@@ -107,8 +110,38 @@ impl PiiProcessor {
         Self { compiled_rules }
     }
 
+    /// Processor for a team's own policy: the selected built-in detectors
+    /// followed by the team's rules.
+    pub fn for_team(policy: &TeamPiiPolicy) -> Self {
+        if !policy.enabled {
+            return Self {
+                compiled_rules: Vec::new(),
+            };
+        }
+        let builtin = BUILTIN_PATTERNS.iter().filter(|rule| {
+            policy
+                .builtin
+                .as_ref()
+                .is_none_or(|names| names.contains(&rule.name))
+        });
+        let compiled_rules = builtin
+            .chain(&policy.rules)
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some((rule.clone(), regex)),
+                Err(e) => {
+                    warn!(
+                        rule = %rule.name,
+                        error = %e,
+                        "Failed to compile PII regex pattern, skipping rule"
+                    );
+                    None
+                }
+            })
+            .collect();
+        Self { compiled_rules }
+    }
+
     /// Check if PII masking is enabled
-    #[allow(dead_code)] // May be used for conditional logic in future
     pub fn is_enabled(&self) -> bool {
         !self.compiled_rules.is_empty()
     }
@@ -179,6 +212,25 @@ impl PiiProcessor {
     }
 }
 
+/// `(rule, action, matches)` per rule and action, for the redaction audit.
+pub fn summarize(detections: &[PiiDetection]) -> Vec<(String, &'static str, i64)> {
+    let mut summary: Vec<(String, &'static str, i64)> = Vec::new();
+    for detection in detections {
+        let action = match detection.action {
+            PiiAction::Mask => "mask",
+            PiiAction::Block => "block",
+        };
+        match summary
+            .iter_mut()
+            .find(|(rule, a, _)| *rule == detection.rule_name && *a == action)
+        {
+            Some(entry) => entry.2 += 1,
+            None => summary.push((detection.rule_name.clone(), action, 1)),
+        }
+    }
+    summary
+}
+
 /// Process JSON content and mask PII in all string values
 pub fn process_json_content(
     processor: &PiiProcessor,
@@ -363,6 +415,40 @@ mod tests {
         assert!(!result.contains("sk-abcdefghijklmnopqrstuvwxyz123456"));
     }
 
+    #[test]
+    fn test_team_policy_selects_builtin_detectors() {
+        let policy = TeamPiiPolicy {
+            enabled: true,
+            builtin: Some(vec!["phone".to_string()]),
+            rules: vec![PiiRule {
+                name: "employee_id".to_string(),
+                pattern: r"EMP\d{5}".to_string(),
+                action: PiiAction::Mask,
+                mask_char: '#',
+                replace_with: None,
+            }],
+        };
+        let processor = PiiProcessor::for_team(&policy);
+        let (result, detections) =
+            processor.process("EMP12345 at john@example.com, (555) 123-4567 or (555) 765-4321");
+
+        assert!(result.starts_with("######## at john@example.com"));
+        assert!(!result.contains("(555) 123-4567"));
+        assert_eq!(
+            summarize(&detections),
+            vec![
+                ("phone".to_string(), "mask", 2),
+                ("employee_id".to_string(), "mask", 1)
+            ]
+        );
+
+        let disabled = TeamPiiPolicy {
+            enabled: false,
+            ..policy
+        };
+        assert!(!PiiProcessor::for_team(&disabled).is_enabled());
+    }
+
     #[test]
     fn test_compliance_validation() {
         // Valid: enabled with built-in rules only
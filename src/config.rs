@@ -1,9 +1,11 @@
+use anyhow::Context;
 use glob::{MatchOptions, Pattern};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -29,6 +31,440 @@ pub struct Config {
     pub compliance: Option<Compliance>,
     #[serde(default)]
     pub retention: Retention,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_shipping: Option<UsageShipping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_collector: Option<UsageCollector>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage_webhooks: Vec<UsageWebhook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_injection: Option<FaultInjection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_audit: Option<AccessAuditConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<Tenant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthCheckConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<WarmupConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pricing: Vec<ModelPrice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<SecretsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_discovery: Option<ModelDiscoveryConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrails: Option<Guardrails>,
+    /// References behind resolved channel secrets, restored on save.
+    #[serde(skip)]
+    pub secret_refs: crate::secrets::SecretRefs,
+    /// Include files the channels, routers and teams were loaded from,
+    /// written back on save.
+    #[serde(skip)]
+    pub includes: crate::config_includes::IncludeOrigins,
+    /// `APEX_*` environment overrides applied on load, undone on save.
+    #[serde(skip)]
+    pub env_overrides: crate::env_overrides::EnvOverrides,
+}
+
+/// An isolated organization served by the same gateway. Its channels, routers
+/// and teams live in their own namespace: at load time they are merged into
+/// the top-level lists under `<tenant>/<name>`, so usage, metrics and logs are
+/// naturally keyed per tenant. Requests reach a tenant through one of its
+/// `hosts` or the `/t/<tenant>/...` path prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    /// `Host` header values (port ignored) that select this tenant.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+    #[serde(default)]
+    pub routers: Vec<Router>,
+    #[serde(default)]
+    pub teams: Vec<Team>,
+}
+
+/// Fully qualified name of a tenant-owned channel, router or team.
+pub fn tenant_scoped_name(tenant: &str, name: &str) -> String {
+    format!("{tenant}/{name}")
+}
+
+impl Config {
+    /// Model an alias stands for: `router`'s `model_aliases` first, then the
+    /// global ones. `None` when `model` is not an alias.
+    pub fn model_alias<'a>(&'a self, router: Option<&'a Router>, model: &str) -> Option<&'a str> {
+        router
+            .and_then(|router| router.model_aliases.get(model))
+            .or_else(|| self.global.model_aliases.get(model))
+            .map(String::as_str)
+    }
+
+    /// Tenant owning a (qualified) channel, router or team name.
+    pub fn tenant_of(&self, name: &str) -> Option<&str> {
+        let (tenant, _) = name.split_once('/')?;
+        self.tenants
+            .iter()
+            .find(|t| t.id == tenant)
+            .map(|t| t.id.as_str())
+    }
+
+    /// Tenant selected by a `Host` header value.
+    pub fn tenant_for_host(&self, host: &str) -> Option<&Tenant> {
+        let host = host.rsplit_once(':').map_or(host, |(name, port)| {
+            if port.chars().all(|c| c.is_ascii_digit()) {
+                name
+            } else {
+                host
+            }
+        });
+        self.tenants
+            .iter()
+            .find(|t| t.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)))
+    }
+
+    /// Merge every tenant's resources into the top-level lists under
+    /// qualified names. Idempotent: previously merged entries are replaced.
+    pub fn expand_tenants(&mut self) {
+        if self.tenants.is_empty() {
+            return;
+        }
+        self.strip_tenant_resources();
+
+        let mut channels = Vec::new();
+        let mut routers = Vec::new();
+        let mut teams = Vec::new();
+        for tenant in &self.tenants {
+            let scoped = |name: &str| tenant_scoped_name(&tenant.id, name);
+            for channel in &tenant.channels {
+                let mut channel = channel.clone();
+                channel.name = scoped(&channel.name);
+                channels.push(channel);
+            }
+            for router in &tenant.routers {
+                let mut router = router.clone();
+                router.name = scoped(&router.name);
+                for target in router
+                    .rules
+                    .iter_mut()
+                    .flat_map(|rule| rule.channels.iter_mut())
+                    .chain(router.channels.iter_mut())
+                    .chain(
+                        router
+                            .fallback_tiers
+                            .iter_mut()
+                            .flat_map(|tier| tier.channels.iter_mut()),
+                    )
+                {
+                    target.name = scoped(&target.name);
+                }
+                for name in router
+                    .fallback_channels
+                    .iter_mut()
+                    .chain(router.mirror_channel.as_mut())
+                    .chain(router.canary.as_mut().map(|canary| &mut canary.channel))
+                    .chain(
+                        router
+                            .experiment
+                            .iter_mut()
+                            .flat_map(|experiment| experiment.variants.iter_mut())
+                            .filter_map(|variant| variant.channel.as_mut()),
+                    )
+                {
+                    *name = scoped(name);
+                }
+                if let Some(metadata) = router.metadata.as_mut() {
+                    for name in metadata.model_matcher.values_mut() {
+                        *name = scoped(name);
+                    }
+                }
+                routers.push(router);
+            }
+            for team in &tenant.teams {
+                let mut team = team.clone();
+                team.id = scoped(&team.id);
+                for name in team.policy.allowed_routers.iter_mut() {
+                    *name = scoped(name);
+                }
+                teams.push(team);
+            }
+        }
+        Arc::make_mut(&mut self.channels).extend(channels);
+        Arc::make_mut(&mut self.routers).extend(routers);
+        Arc::make_mut(&mut self.teams).extend(teams);
+    }
+
+    /// Copy of the config without the entries produced by `expand_tenants`,
+    /// i.e. the shape that belongs on disk.
+    pub fn without_tenant_resources(&self) -> Config {
+        let mut config = self.clone();
+        config.strip_tenant_resources();
+        config
+    }
+
+    fn strip_tenant_resources(&mut self) {
+        if self.tenants.is_empty() {
+            return;
+        }
+        let prefixes: Vec<String> = self.tenants.iter().map(|t| format!("{}/", t.id)).collect();
+        let owned = |name: &str| prefixes.iter().any(|p| name.starts_with(p.as_str()));
+        Arc::make_mut(&mut self.channels).retain(|c| !owned(&c.name));
+        Arc::make_mut(&mut self.routers).retain(|r| !owned(&r.name));
+        Arc::make_mut(&mut self.teams).retain(|t| !owned(&t.id));
+    }
+}
+
+/// Persistent record of every upstream access attempt (see `access_audit`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessAuditConfig {
+    #[serde(default)]
+    pub sink: AccessAuditSink,
+    /// Directory for the `file` sink. Defaults to `<data_dir>/audit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Days of audit events to keep. `0` keeps them forever.
+    #[serde(default = "default_access_audit_retention_days")]
+    pub retention_days: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessAuditSink {
+    /// `access_audit` table in the gateway's SQLite database.
+    #[default]
+    Sqlite,
+    /// One JSON-lines file per day (`audit-YYYY-MM-DD.jsonl`).
+    File,
+}
+
+fn default_access_audit_retention_days() -> u64 {
+    365
+}
+
+/// Remote backends for `vault:` / `aws:` channel secrets (see
+/// `secret_providers`). Unset settings fall back to the usual `VAULT_*` /
+/// `AWS_*` environment variables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    /// How often remote secrets are fetched again.
+    #[serde(default = "default_secrets_refresh_secs")]
+    pub refresh_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<VaultConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws: Option<AwsSecretsConfig>,
+}
+
+fn default_secrets_refresh_secs() -> u64 {
+    300
+}
+
+/// How `/v1/models` asks channels for their models (see `model_catalog`).
+/// Discovery is on with these defaults when the section is absent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelDiscoveryConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How long a channel's model list is reused before it is fetched again.
+    #[serde(default = "default_model_discovery_ttl_secs")]
+    pub ttl_secs: u64,
+    #[serde(default = "default_model_discovery_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for ModelDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: default_model_discovery_ttl_secs(),
+            timeout_ms: default_model_discovery_timeout_ms(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_model_discovery_ttl_secs() -> u64 {
+    300
+}
+
+fn default_model_discovery_timeout_ms() -> u64 {
+    5000
+}
+
+/// HashiCorp Vault KV secrets engine. String settings accept `${VAR}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultConfig {
+    /// Defaults to `VAULT_ADDR`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Defaults to `VAULT_TOKEN`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Defaults to `VAULT_NAMESPACE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// KV engine version, `2` unless set to `1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kv_version: Option<u8>,
+}
+
+/// AWS Secrets Manager. String settings accept `${VAR}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AwsSecretsConfig {
+    /// Defaults to `AWS_REGION` / `AWS_DEFAULT_REGION`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Defaults to `AWS_ACCESS_KEY_ID`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    /// Defaults to `AWS_SECRET_ACCESS_KEY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+    /// Defaults to `AWS_SESSION_TOKEN`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    /// Overrides `https://secretsmanager.<region>.amazonaws.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// Active probing and passive ejection of channels (see `channel_health`).
+/// Ejected channels are skipped by rule selection while another target of
+/// the same rule is still available.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    #[serde(default = "default_health_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_health_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Probed with `GET`, relative to the channel's base URL.
+    #[serde(default = "default_health_path")]
+    pub path: String,
+    /// Consecutive failed probes (or live requests) that eject a channel.
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+    /// Consecutive successful probes that bring an ejected channel back.
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+}
+
+fn default_health_interval_secs() -> u64 {
+    30
+}
+
+fn default_health_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_health_path() -> String {
+    "models".to_string()
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+fn default_healthy_threshold() -> u32 {
+    2
+}
+
+/// Pre-resolved DNS and open connections to every channel host (see
+/// `warmup`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    /// Hosts are re-resolved and connections refreshed this often; keep it
+    /// under the 90 s after which idle pooled connections close.
+    #[serde(default = "default_warmup_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_warmup_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_warmup_interval_secs() -> u64 {
+    60
+}
+
+fn default_warmup_timeout_ms() -> u64 {
+    5000
+}
+
+/// Price of one model, in dollars per 1K tokens. `model` is matched like
+/// router rule patterns (exact or glob, case-insensitive); first match wins.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub model: String,
+    #[serde(default)]
+    pub input_per_1k: f64,
+    #[serde(default)]
+    pub output_per_1k: f64,
+    /// Price per generated image (`/v1/images/*`).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub per_image: f64,
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+impl ModelPrice {
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64, images: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_1k + output_tokens as f64 * self.output_per_1k)
+            / 1000.0
+            + images as f64 * self.per_image
+    }
+}
+
+/// Chaos mode: injects upstream errors, latency or truncated streams on the
+/// listed channels so retry / fallback settings can be exercised on purpose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultInjection {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Channels to disrupt. Empty = every channel.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Probability (0.0–1.0) of answering with `error_status` instead of
+    /// calling the upstream.
+    #[serde(default)]
+    pub error_rate: f64,
+    #[serde(default = "default_fault_error_status")]
+    pub error_status: u16,
+    /// Probability of delaying the upstream call by `latency_ms`.
+    #[serde(default)]
+    pub latency_rate: f64,
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Probability of cutting the response body off after its first chunk.
+    #[serde(default)]
+    pub truncate_rate: f64,
+}
+
+fn default_fault_error_status() -> u16 {
+    503
+}
+
+impl Default for FaultInjection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channels: Vec::new(),
+            error_rate: 0.0,
+            error_status: default_fault_error_status(),
+            latency_rate: 0.0,
+            latency_ms: 0,
+            truncate_rate: 0.0,
+        }
+    }
+}
+
+impl FaultInjection {
+    pub fn applies_to(&self, channel: &str) -> bool {
+        self.enabled && (self.channels.is_empty() || self.channels.iter().any(|c| c == channel))
+    }
 }
 
 /// Controls pruning of usage history and request/error/latency metrics so the
@@ -52,6 +488,96 @@ fn default_retention_interval_hours() -> u64 {
     24
 }
 
+/// Sends every usage record to a central collector so reports on it cover
+/// the whole fleet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageShipping {
+    #[serde(flatten)]
+    pub target: UsageShippingTarget,
+    /// Name recorded with each record; defaults to the host name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Also write records to this instance's own database.
+    #[serde(default = "default_true")]
+    pub keep_local: bool,
+    #[serde(default = "default_usage_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_usage_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UsageShippingTarget {
+    /// POSTs batches to a collector gateway's `/admin/usage/ingest`.
+    Http {
+        url: String,
+        /// Sent as a bearer token; one of the collector's `auth_keys`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_key: Option<String>,
+    },
+    /// Appends each record to a Redis stream read by `usage_collector`.
+    RedisStream {
+        url: String,
+        #[serde(default = "default_usage_stream")]
+        stream: String,
+        /// Approximate cap on the stream length (`XADD MAXLEN ~`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_len: Option<u64>,
+    },
+}
+
+/// Reads usage records other instances appended to a Redis stream into this
+/// instance's database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageCollector {
+    pub url: String,
+    #[serde(default = "default_usage_stream")]
+    pub stream: String,
+    /// Consumer group; collectors sharing one split the stream between them.
+    #[serde(default = "default_usage_collector_group")]
+    pub group: String,
+}
+
+/// An endpoint that receives every usage record as a JSON POST, e.g. for an
+/// external billing system.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageWebhook {
+    pub url: String,
+    /// Signs each body with HMAC-SHA256 (`X-Apex-Signature`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Further attempts after a failed delivery before the record is dropped.
+    #[serde(default = "default_webhook_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_webhook_max_retries() -> u32 {
+    5
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_usage_batch_size() -> usize {
+    100
+}
+
+fn default_usage_flush_interval_ms() -> u64 {
+    1000
+}
+
+fn default_usage_stream() -> String {
+    "apex:usage".to_string()
+}
+
+fn default_usage_collector_group() -> String {
+    "apex-collector".to_string()
+}
+
 impl Default for Retention {
     fn default() -> Self {
         Self {
@@ -71,10 +597,15 @@ fn default_web_dir() -> String {
     "target/web".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Team {
     pub id: String,
+    /// Legacy single plaintext key. Still accepted; new keys go to `keys`.
+    #[serde(default)]
     pub api_key: String,
+    /// Managed keys (`apex key create`), stored hashed. See `key_store`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<TeamKey>,
     pub policy: TeamPolicy,
     /// Optional group label used by the control plane to organize teams in
     /// the UI. Free-form string (e.g. "engineering", "data-platform").
@@ -89,6 +620,30 @@ pub struct Team {
     pub enabled: Option<bool>,
 }
 
+/// One managed team key. Only the hash is kept; the plaintext is shown once
+/// when the key is created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TeamKey {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `sha256:<hex>` of the plaintext key.
+    pub hash: String,
+    /// Leading characters of the plaintext, to recognise the key in listings.
+    pub prefix: String,
+    pub created_at: String,
+    /// RFC 3339; the key is rejected from this instant on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Model patterns this key may call, within the team policy. Empty means
+    /// whatever the team may call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Set by `apex key revoke`; the key stays listed but is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
 impl Team {
     /// True when the team is paused (`enabled == Some(false)`). Missing /
     /// `Some(true)` are both considered active.
@@ -97,13 +652,102 @@ impl Team {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TeamPolicy {
     pub allowed_routers: Vec<String>,
     #[serde(default)]
     pub allowed_models: Option<Vec<String>>,
     #[serde(default)]
     pub rate_limit: Option<TeamRateLimit>,
+    /// Allow this team to use `x-apex-channel` / `x-apex-model-override`
+    /// request headers to pin a channel or substitute the model.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_routing_overrides: bool,
+    /// Limits applied to each end user (request `user` field) within the team.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_user_rate_limit: Option<TeamRateLimit>,
+    /// Reject models that only a catch-all rule would match (see
+    /// `Router::reject_unknown_models`), whatever the router says.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reject_unknown_models: bool,
+    /// Write full prompt/response transcripts for this team. Off unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcripts: Option<TranscriptSettings>,
+    /// PII scrubbing of this team's prompts, replacing the top-level
+    /// `compliance` section for its requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pii: Option<TeamPiiPolicy>,
+    /// Largest completion a request may ask for (`max_tokens`,
+    /// `max_completion_tokens`, `max_output_tokens` or Gemini
+    /// `generationConfig.maxOutputTokens`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens_per_request: Option<u64>,
+    /// Limit on the estimated prompt plus the requested completion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<u64>,
+    /// Lower an oversized completion limit to what the team allows instead
+    /// of rejecting the request. Prompts over `max_context_tokens` are
+    /// always rejected.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clamp_max_tokens: bool,
+    /// Standing under `global.load_shedding`: `low` is shed first, `high`
+    /// never.
+    #[serde(default, skip_serializing_if = "TeamPriority::is_normal")]
+    pub priority: TeamPriority,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TeamPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl TeamPriority {
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+}
+
+/// Per-team PII scrubbing (see `compliance`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamPiiPolicy {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Built-in detectors to apply (`email`, `phone`, `credit_card`,
+    /// `ip_address`); all of them when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<Vec<String>>,
+    /// Extra rules applied after the built-in ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PiiRule>,
+}
+
+/// Opt-in transcript export (`<data_dir>/transcripts/<team>/YYYY-MM-DD.jsonl`).
+/// Built-in PII patterns are always redacted before anything hits disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptSettings {
+    /// Days of transcripts to keep, independent of `retention.days`.
+    /// `0` keeps them forever.
+    #[serde(default = "default_transcript_retention_days")]
+    pub retention_days: u64,
+    /// Extra redaction rules applied on top of the built-in patterns.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<PiiRule>,
+}
+
+fn default_transcript_retention_days() -> u64 {
+    30
 }
 
 impl TeamPolicy {
@@ -114,30 +758,33 @@ impl TeamPolicy {
                 if patterns.is_empty() {
                     return true;
                 }
-                patterns.iter().any(|pattern_str| {
-                    // 1. Exact match (case-insensitive)
-                    if pattern_str.eq_ignore_ascii_case(model) {
-                        return true;
-                    }
-                    // 2. Glob match (case-insensitive)
-                    if let Ok(pattern) = Pattern::new(pattern_str) {
-                        pattern.matches_with(
-                            model,
-                            MatchOptions {
-                                case_sensitive: false,
-                                require_literal_separator: false,
-                                require_literal_leading_dot: false,
-                            },
-                        )
-                    } else {
-                        false
-                    }
-                })
+                patterns
+                    .iter()
+                    .any(|pattern_str| model_pattern_matches(pattern_str, model))
             }
         }
     }
 }
 
+/// Case-insensitive exact or glob match of a model name against a pattern.
+pub fn model_pattern_matches(pattern_str: &str, model: &str) -> bool {
+    // 1. Exact match (case-insensitive)
+    if pattern_str.eq_ignore_ascii_case(model) {
+        return true;
+    }
+    // 2. Glob match (case-insensitive)
+    Pattern::new(pattern_str).is_ok_and(|pattern| {
+        pattern.matches_with(
+            model,
+            MatchOptions {
+                case_sensitive: false,
+                require_literal_separator: false,
+                require_literal_leading_dot: false,
+            },
+        )
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TeamRateLimit {
     pub rpm: Option<i32>,
@@ -180,6 +827,169 @@ pub struct Global {
     pub gemini_replay: GeminiReplay,
     #[serde(default)]
     pub cors_allowed_origins: Vec<String>,
+    /// Proxies (IPs or CIDRs) whose `X-Forwarded-For` header is trusted when
+    /// resolving the client IP. Empty = always use the socket peer address.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<String>,
+    /// Per-client-IP limit for anonymous requests when the gateway is open
+    /// (no `auth_keys` configured).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_rate_limit: Option<IpRateLimit>,
+    /// Gateway-wide request limit shared by every caller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<GlobalRateLimit>,
+    /// Request limits per provider type, across all channels of that type.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub provider_rate_limits: HashMap<ProviderType, ProviderRateLimit>,
+    /// Where rate-limit buckets are kept; in memory (per instance) unless set.
+    /// Changing it needs a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_store: Option<RateLimitStore>,
+    /// Global in-flight request ceiling; excess requests get 503 + Retry-After.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_shedding: Option<LoadShedding>,
+    /// Blocklisted API keys. Checked before team / global key lookup so a
+    /// leaked key can be killed without touching the team that owns it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revoked_keys: Vec<String>,
+    /// Terminate TLS on the main listener instead of serving plain HTTP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+    /// Abstract model names clients may request (e.g. `fast`), mapped to the
+    /// model actually routed. Routers can override entries.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub model_aliases: HashMap<String, String>,
+    /// Cap on the end-to-end deadline clients ask for with
+    /// `x-request-timeout-ms`; unset honors the header as sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_timeout_ms: Option<u64>,
+    /// Request and response body size caps; unset keeps the built-in
+    /// defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_limits: Option<BodyLimits>,
+    /// Compress responses to clients that accept gzip or brotli; read at
+    /// startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+}
+
+/// Response compression to clients. Event streams, images and audio are sent
+/// as they are.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Compression {
+    /// Smallest response body compressed, in bytes.
+    #[serde(default = "default_compression_min_bytes")]
+    pub min_bytes: u16,
+}
+
+fn default_compression_min_bytes() -> u16 {
+    1024
+}
+
+/// Body size caps in bytes. Globally, unset fields fall back to 10 MB for
+/// JSON requests, 25 MB for multipart uploads and 10 MB for buffered
+/// responses; on a channel, unset fields add no cap of their own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BodyLimits {
+    /// JSON (and other non-multipart) request bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<usize>,
+    /// Multipart uploads: audio transcriptions, image edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_upload_bytes: Option<usize>,
+    /// Non-streamed upstream response bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+}
+
+impl BodyLimits {
+    /// Why these caps are unusable (a zero cap rejects every body), if they
+    /// are.
+    fn problem(&self, at: &str) -> Option<String> {
+        [
+            ("max_request_bytes", self.max_request_bytes),
+            ("max_upload_bytes", self.max_upload_bytes),
+            ("max_response_bytes", self.max_response_bytes),
+        ]
+        .into_iter()
+        .find(|(_, limit)| *limit == Some(0))
+        .map(|(field, _)| format!("{at}.body_limits.{field} must be greater than 0"))
+    }
+}
+
+/// PEM certificate chain and private key for the gateway listener. The files
+/// are re-read when they change, so renewed certificates apply without a
+/// restart; switching TLS on or off still needs one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Tls {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+impl Global {
+    pub fn is_key_revoked(&self, key: &str) -> bool {
+        self.revoked_keys.iter().any(|k| k == key)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadShedding {
+    /// Maximum concurrent proxied requests (streams count until they finish).
+    pub max_in_flight: usize,
+    #[serde(default = "default_load_shedding_retry_after_secs")]
+    pub retry_after_secs: u64,
+    /// Teams that are never shed (their requests still count toward the total).
+    #[serde(default)]
+    pub exempt_teams: Vec<String>,
+    /// Requests waiting for a channel slot (`queue_timeout_ms`), across all
+    /// channels, beyond which new requests are shed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_queued: Option<usize>,
+    /// Share of each limit at which `low` priority teams are already shed,
+    /// keeping the rest for everyone else.
+    #[serde(default = "default_low_priority_fraction")]
+    pub low_priority_fraction: f64,
+}
+
+fn default_low_priority_fraction() -> f64 {
+    0.75
+}
+
+fn default_load_shedding_retry_after_secs() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpRateLimit {
+    pub rpm: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalRateLimit {
+    pub rpm: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderRateLimit {
+    pub rpm: u32,
+}
+
+/// Backend for team, IP, gateway and provider rate-limit buckets.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RateLimitStore {
+    Memory,
+    /// Buckets shared by every instance pointing at the same Redis. If Redis
+    /// is unreachable each instance falls back to its own buckets.
+    Redis {
+        url: String,
+        #[serde(default = "default_rate_limit_key_prefix")]
+        key_prefix: String,
+    },
+}
+
+fn default_rate_limit_key_prefix() -> String {
+    "apex:ratelimit:".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -212,23 +1022,250 @@ pub struct Retries {
     pub max_attempts: u32,
     pub backoff_ms: u64,
     pub retry_on_status: Vec<u16>,
+    /// Double the delay after every attempt instead of waiting `backoff_ms`
+    /// each time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exponential: bool,
+    /// Randomize each delay between half and all of its nominal value.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub jitter: bool,
+    /// Wait as long as the upstream's `Retry-After` asks when it exceeds the
+    /// computed backoff.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub respect_retry_after: bool,
+    /// Upper bound for any single delay (default 30s).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const DEFAULT_MAX_BACKOFF_MS: u64 = 30_000;
+
+impl Retries {
+    /// Delay before retry number `retry` (0 for the first retry), given the
+    /// upstream's `Retry-After` if it sent one.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let cap = self.max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS);
+        let mut ms = if self.exponential {
+            self.backoff_ms.saturating_mul(1u64 << retry.min(32))
+        } else {
+            self.backoff_ms
+        };
+        ms = ms.min(cap);
+        if self.jitter && ms > 1 {
+            ms = ms / 2 + rand::random::<u64>() % (ms - ms / 2 + 1);
+        }
+        let mut delay = Duration::from_millis(ms);
+        if self.respect_retry_after
+            && let Some(requested) = retry_after
+        {
+            delay = delay.max(requested.min(Duration::from_millis(cap)));
+        }
+        delay
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Channel {
     pub name: String,
     pub provider_type: ProviderType,
     pub base_url: String,
     pub api_key: String,
+    /// Read `api_key` from this file instead (see `secrets`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    /// Extra keys rotated together with `api_key` (see `key_pool`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<String>,
+    /// How the key pool picks a key; round-robin when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_strategy: Option<KeyStrategy>,
     pub anthropic_base_url: Option<String>,
     pub headers: Option<HashMap<String, String>>,
     pub model_map: Option<HashMap<String, String>>,
     pub timeouts: Option<Timeouts>,
+    /// Gemini only: send chat traffic to the native `generateContent`
+    /// endpoints instead of Google's OpenAI-compatible bridge.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub native_api: bool,
+    /// Jina only: `task` sent with embeddings requests that set neither
+    /// `task` nor `input_type` (e.g. `"retrieval.passage"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_task: Option<String>,
+    /// Vertex only: project and region (see `vertex`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertex: Option<VertexSettings>,
+    /// Custom only: how the upstream deviates from plain OpenAI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<ChannelQuirks>,
+    /// Rewrites chat parameters for model families that reject the usual
+    /// ones, applied to chat completions bodies after `model_map`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub param_profile: Option<ParamProfile>,
+    /// Keep, strip or add prompt caching markers and betas (see `prompt_cache`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_cache: Option<PromptCache>,
+    /// Name of an adapter registered with `ProviderRegistry::register` by a
+    /// program embedding apex; it replaces the provider type's own handling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adapter: Option<String>,
+    /// Behaviour of a `mock` channel; ignored for every other provider type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock: Option<MockSettings>,
+    /// Requests this channel may have in flight at once; when it is full,
+    /// requests go to the next channel or the router's fallbacks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<usize>,
+    /// With `max_concurrent_requests`: how long a request waits for a free
+    /// slot before moving on. Unset = never wait.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_timeout_ms: Option<u64>,
+    /// Most requests waiting for a slot at once; further ones move on
+    /// immediately (default 100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_queued_requests: Option<usize>,
+    /// Body size caps for this channel, applied on top of the global ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_limits: Option<BodyLimits>,
+}
+
+impl Channel {
+    /// A channel with only `anthropic_base_url` set speaks nothing but the
+    /// Messages API; OpenAI chat traffic to it is converted both ways.
+    pub fn anthropic_only(&self) -> bool {
+        self.base_url.trim().is_empty()
+            && self
+                .anthropic_base_url
+                .as_deref()
+                .is_some_and(|url| !url.trim().is_empty())
+    }
+
+    /// The channel's `timeouts` override, or the global timeouts.
+    pub fn effective_timeouts<'a>(&'a self, global: &'a Timeouts) -> &'a Timeouts {
+        self.timeouts.as_ref().unwrap_or(global)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStrategy {
+    #[default]
+    RoundRobin,
+    /// The key whose last 401/403/429 is oldest (never-limited keys first).
+    LeastRecentlyLimited,
+}
+
+/// Settings for the in-process `mock` provider (see `mock_provider`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockSettings {
+    /// Artificial delay before each response.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Fraction of requests (0.0–1.0) answered with `failure_status`.
+    #[serde(default)]
+    pub failure_rate: f64,
+    #[serde(default = "default_mock_failure_status")]
+    pub failure_status: u16,
+    /// Fixed completion text. Defaults to echoing the last user message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+fn default_mock_failure_status() -> u16 {
+    500
+}
+
+/// Behaviour of a `custom` channel: an OpenAI-compatible server
+/// (vLLM, LM Studio, llama.cpp, …) described by flags instead of code.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelQuirks {
+    /// Header carrying `api_key`. `authorization` (the default) sends
+    /// `Bearer <key>`; any other header gets the bare key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_header: Option<String>,
+    /// Prepended to every upstream path, e.g. `openai` for
+    /// `{base_url}/openai/v1/chat/completions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// Forward Anthropic Messages requests unchanged (to
+    /// `anthropic_base_url`, else `base_url`) instead of converting them to
+    /// chat completions.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub supports_anthropic: bool,
+    /// Drop the client's query string.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_query: bool,
+    /// Ask for usage on streams (`stream_options.include_usage`) so streamed
+    /// requests are metered.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub force_stream_usage: bool,
+}
+
+/// Prompt caching controls for a channel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PromptCache {
+    #[serde(default)]
+    pub mode: PromptCacheMode,
+    /// `anthropic-beta` values added to Messages requests, e.g.
+    /// `extended-cache-ttl-2025-04-11`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub beta: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptCacheMode {
+    /// Forward the client's `cache_control` markers untouched.
+    #[default]
+    Preserve,
+    /// Remove every `cache_control` marker and prompt caching beta.
+    Strip,
+    /// Mark the system prompt and tools when the client set no markers.
+    Inject,
+}
+
+/// Chat parameter normalization for a model family.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamProfile {
+    /// OpenAI o-series / gpt-5 style reasoning models: `max_tokens` becomes
+    /// `max_completion_tokens`, sampling parameters they reject are dropped
+    /// and `reasoning.effort` is sent as `reasoning_effort`.
+    Reasoning {
+        /// Upstream models (after `model_map`) the profile applies to.
+        #[serde(default = "default_reasoning_models")]
+        models: Vec<String>,
+        /// `reasoning_effort` for requests that set none.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reasoning_effort: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+fn default_reasoning_models() -> Vec<String> {
+    ["o1*", "o3*", "o4*", "gpt-5*"]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Project and region of a `vertex` channel.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VertexSettings {
+    /// Defaults to the `project_id` of the service-account key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    /// Region such as `us-central1`, or `global`.
+    #[serde(default = "default_vertex_location")]
+    pub location: String,
+}
+
+fn default_vertex_location() -> String {
+    crate::vertex::DEFAULT_LOCATION.to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum ProviderType {
+    #[default]
     Openai,
     Anthropic,
     Gemini,
@@ -240,6 +1277,17 @@ pub enum ProviderType {
     Jina,
     Openrouter,
     Zai,
+    Groq,
+    Mistral,
+    /// Cohere's OpenAI compatibility API.
+    Cohere,
+    Together,
+    /// OpenAI-compatible server described by the channel's `quirks`.
+    Custom,
+    /// Google Vertex AI, authenticated with a service account.
+    Vertex,
+    /// In-process fake upstream for tests and benchmarks.
+    Mock,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -253,15 +1301,403 @@ pub struct Router {
     // Legacy fields (kept for backward compatibility, will be migrated to rules)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub channels: Vec<TargetChannel>,
-    #[serde(
-        default = "default_strategy",
-        skip_serializing_if = "is_default_strategy"
-    )]
+    #[serde(
+        default = "default_strategy",
+        skip_serializing_if = "is_default_strategy"
+    )]
+    pub strategy: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RouterMetadata>,
+    /// Tried in order when the selected channel fails; the first tier
+    /// ahead of `fallback_tiers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_channels: Vec<String>,
+    /// Further fallback tiers, each tried only once the tiers before it
+    /// have failed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_tiers: Vec<FallbackTier>,
+    /// Which failures move a request on to the next fallback tier (default:
+    /// error statuses, timeouts and connection errors).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_on: Option<FallbackOn>,
+    /// Reject (400) models that only a catch-all rule (`*`) would match,
+    /// instead of silently routing typos to the default channel.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reject_unknown_models: bool,
+    /// Largest request body (bytes) accepted for this router; larger ones get 413.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<usize>,
+    /// Largest non-streaming upstream response (bytes); larger ones are cut
+    /// off with a 502 instead of being buffered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Serve identical non-streaming requests from memory (see `response_cache`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<ResponseCacheConfig>,
+    /// Forward `/v1/*` paths apex has no handler for to the selected channel
+    /// as-is (auth applied, body untouched).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub passthrough: bool,
+    /// Where rules with the `sticky` strategy read the conversation key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sticky: Option<StickyConfig>,
+    /// Replaces `global.retries` for this router's requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<Retries>,
+    /// When the current channel has not answered within this many ms, send
+    /// the same request to the next channel too and use whichever succeeds
+    /// first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hedge_after_ms: Option<u64>,
+    /// Also send a share of requests to this channel in the background
+    /// (shadow traffic); its responses are discarded and its usage is
+    /// recorded with `matched_rule = "mirror"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_channel: Option<String>,
+    /// Percentage (0–100) of requests mirrored; all of them when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_percent: Option<f64>,
+    /// A/B experiment splitting this router's traffic into variants.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<Experiment>,
+    /// Gradual rollout of a new channel, withdrawn automatically when it
+    /// misbehaves (see `canary`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<Canary>,
+    /// Model aliases of this router, taking precedence over
+    /// `global.model_aliases`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub model_aliases: HashMap<String, String>,
+    /// Model injected into requests that name none, instead of routing
+    /// them as `default`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    /// Reject (400) requests that name no model.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reject_missing_model: bool,
+    /// Stream request bodies to the upstream without buffering them when
+    /// nothing on the way needs to read or rewrite them (see
+    /// `stream_requests` in the config reference).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream_requests: bool,
+    /// Virtual key: a caller presenting it is routed through this router
+    /// only, without a team.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vkey: Option<String>,
+    /// Body rewrites for this router's requests and responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transforms: Option<RouterTransforms>,
+}
+
+/// Rewrites of a router's JSON bodies, applied in order (see `transforms`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterTransforms {
+    /// Applied to the request before it is sent to any channel.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request: Vec<Transform>,
+    /// Applied to non-streaming responses before they reach the client.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response: Vec<Transform>,
+}
+
+/// One body rewrite; `path` selects fields as in `metadata.user_id` or
+/// `messages[*].name`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Transform {
+    /// Replace the value at `path`, creating missing objects on the way.
+    Set {
+        path: String,
+        value: serde_json::Value,
+    },
+    /// Set `path` only when it is missing or null.
+    Default {
+        path: String,
+        value: serde_json::Value,
+    },
+    /// Lower a number at `path` to `max` when it is larger.
+    Cap {
+        path: String,
+        max: serde_json::Number,
+    },
+    /// Delete the field or array element at `path`.
+    Remove { path: String },
+    /// Add `text` before (or with `append`, after) the system prompt.
+    SystemPrompt {
+        text: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        append: bool,
+    },
+}
+
+/// Sends `percent` of a router's requests to `channel` until its error
+/// rate or p95 latency over the last `window_secs` crosses a threshold;
+/// from then on it gets no traffic until the canary settings change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Canary {
+    pub channel: String,
+    /// Share of requests (0–100) sent to the canary.
+    pub percent: f64,
+    /// Failed share (0–1) of the window's canary requests that rolls back.
+    #[serde(default = "default_canary_max_error_rate")]
+    pub max_error_rate: f64,
+    /// p95 latency to response headers (ms) that rolls back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_latency_ms: Option<f64>,
+    #[serde(default = "default_canary_window_secs")]
+    pub window_secs: u64,
+    /// Canary requests the window needs before thresholds are checked.
+    #[serde(default = "default_canary_min_requests")]
+    pub min_requests: usize,
+}
+
+fn default_canary_max_error_rate() -> f64 {
+    0.1
+}
+
+fn default_canary_window_secs() -> u64 {
+    300
+}
+
+fn default_canary_min_requests() -> usize {
+    20
+}
+
+/// Variants of a router's traffic (see `experiments`). Requests not drawn
+/// into any variant form the `control` cohort and are routed as usual.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub variants: Vec<ExperimentVariant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentVariant {
+    pub name: String,
+    /// Share of the router's requests (0–100) assigned to this variant.
+    pub percent: f64,
+    /// Send this variant's requests to this channel instead of the one the
+    /// router rules select.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Rewrites of the request `model` (requested → upstream) for this
+    /// variant, applied before the channel's own `model_map`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_map: Option<HashMap<String, String>>,
+}
+
+/// Conversation key and assignment lifetime for the `sticky` strategy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StickyConfig {
+    /// Request header carrying the key; without it (or when the header is
+    /// absent) the OpenAI `user` / Anthropic `metadata.user_id` field is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Idle seconds after which a key may be assigned a new channel.
+    #[serde(default = "default_sticky_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for StickyConfig {
+    fn default() -> Self {
+        Self {
+            header: None,
+            ttl_secs: default_sticky_ttl_secs(),
+        }
+    }
+}
+
+fn default_sticky_ttl_secs() -> u64 {
+    3600
+}
+
+/// Per-router response cache settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseCacheConfig {
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Most responses kept for the router; least recently used are evicted.
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: u64,
+    /// Also answer requests whose last user message is merely similar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic: Option<SemanticCacheConfig>,
+}
+
+/// Embedding-similarity matching for the response cache.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SemanticCacheConfig {
+    /// Channel that serves `POST embeddings` for the comparison.
+    pub channel: String,
+    /// Embedding model sent to that channel.
+    pub model: String,
+    /// Minimum cosine similarity for a cached response to be reused.
+    #[serde(default = "default_semantic_threshold")]
+    pub threshold: f64,
+    #[serde(default = "default_semantic_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_semantic_threshold() -> f64 {
+    0.95
+}
+
+fn default_semantic_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_cache_max_entries() -> u64 {
+    1000
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            rules: Vec::new(),
+            channels: Vec::new(),
+            strategy: default_strategy(),
+            metadata: None,
+            fallback_channels: Vec::new(),
+            fallback_tiers: Vec::new(),
+            fallback_on: None,
+            reject_unknown_models: false,
+            max_request_bytes: None,
+            max_response_bytes: None,
+            cache: None,
+            passthrough: false,
+            sticky: None,
+            retries: None,
+            hedge_after_ms: None,
+            mirror_channel: None,
+            mirror_percent: None,
+            experiment: None,
+            canary: None,
+            model_aliases: HashMap::new(),
+            default_model: None,
+            reject_missing_model: false,
+            stream_requests: false,
+            vkey: None,
+            transforms: None,
+        }
+    }
+}
+
+impl Router {
+    /// Model patterns from rules that are not pure catch-alls.
+    pub fn known_model_patterns(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            for pattern in &rule.match_spec.models {
+                if !is_catch_all_pattern(pattern) && !patterns.contains(pattern) {
+                    patterns.push(pattern.clone());
+                }
+            }
+        }
+        patterns
+    }
+
+    /// True when some non-catch-all rule matches the model.
+    pub fn recognizes_model(&self, model: &str) -> bool {
+        self.rules.iter().any(|rule| {
+            rule.match_spec
+                .models
+                .iter()
+                .any(|p| !is_catch_all_pattern(p) && model_pattern_matches(p, model))
+        })
+    }
+
+    /// True when some rule only matches after looking into the request body
+    /// (`stream`, `min_prompt_tokens`, `max_prompt_tokens`) or places
+    /// requests by the body's end user (`sticky`).
+    pub fn rules_read_body(&self) -> bool {
+        self.strategy == "sticky"
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.strategy == "sticky" || rule.match_spec.reads_body())
+    }
+
+    /// Fallback tiers in the order they are tried: `fallback_channels` as a
+    /// `priority` tier, then `fallback_tiers`.
+    pub fn fallback_tier_list(&self) -> Vec<FallbackTier> {
+        let legacy = (!self.fallback_channels.is_empty()).then(|| FallbackTier {
+            channels: self
+                .fallback_channels
+                .iter()
+                .map(|name| TargetChannel {
+                    name: name.clone(),
+                    weight: default_weight(),
+                })
+                .collect(),
+            strategy: "priority".to_string(),
+        });
+        legacy
+            .into_iter()
+            .chain(self.fallback_tiers.iter().cloned())
+            .collect()
+    }
+
+    /// Every fallback channel name, tier by tier.
+    pub fn fallback_channel_names(&self) -> impl Iterator<Item = &String> {
+        self.fallback_channels.iter().chain(
+            self.fallback_tiers
+                .iter()
+                .flat_map(|tier| tier.channels.iter().map(|target| &target.name)),
+        )
+    }
+}
+
+/// One level of a router's fallbacks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackTier {
+    pub channels: Vec<TargetChannel>,
+    /// Picks the channel tried first; the rest follow in listed order.
+    #[serde(default = "default_strategy")]
     pub strategy: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<RouterMetadata>,
+}
+
+/// Failures that trigger the next fallback tier. Others are returned to
+/// the client as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackOn {
+    /// Upstream statuses that fall back; empty means any error status.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub fallback_channels: Vec<String>,
+    pub statuses: Vec<u16>,
+    #[serde(default = "default_true")]
+    pub timeouts: bool,
+    /// Connect failures and other transport errors short of a timeout.
+    #[serde(default = "default_true")]
+    pub connection_errors: bool,
+    /// Streams that fail or end before sending their first chunk. Checking
+    /// holds each stream back until that chunk arrives.
+    #[serde(default)]
+    pub stream_aborts: bool,
+}
+
+impl Default for FallbackOn {
+    fn default() -> Self {
+        Self {
+            statuses: Vec::new(),
+            timeouts: true,
+            connection_errors: true,
+            stream_aborts: false,
+        }
+    }
+}
+
+impl FallbackOn {
+    /// Whether an upstream error `status` falls back.
+    pub fn status(&self, status: u16) -> bool {
+        self.statuses.is_empty() || self.statuses.contains(&status)
+    }
+}
+
+fn is_catch_all_pattern(pattern: &str) -> bool {
+    !pattern.is_empty() && pattern.chars().all(|c| c == '*')
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -271,12 +1707,194 @@ pub struct RouterRule {
     pub channels: Vec<TargetChannel>,
     #[serde(default = "default_strategy")]
     pub strategy: String,
+    /// Replaces the router's (or global) retries for requests matching this rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<Retries>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// When a rule applies. Every condition given must hold; `any` / `all`
+/// nest further specs for OR / AND composition. Empty `models` means any
+/// model when some other condition is set, and no match otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MatchSpec {
     #[serde(default, deserialize_with = "string_or_vec", alias = "model")]
     pub models: Vec<String>,
+    /// Header name → glob its value must match (case-insensitive).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Request path globs, e.g. `/v1/embeddings`.
+    #[serde(
+        default,
+        deserialize_with = "string_or_vec",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub paths: Vec<String>,
+    /// Team ids (`global` for requests without a team).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teams: Vec<String>,
+    /// Streaming (`true`) or non-streaming (`false`) requests only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Bounds on the estimated request tokens (prompt plus requested output).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_prompt_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<u64>,
+    /// At least one of these specs must match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<MatchSpec>,
+    /// Each of these specs must match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<MatchSpec>,
+    /// Only while the clock is inside this window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<TimeWindow>,
+}
+
+impl MatchSpec {
+    /// Whether the spec looks at more than the model.
+    pub fn has_conditions(&self) -> bool {
+        self.has_request_conditions() || self.time.is_some()
+    }
+
+    /// Whether the spec looks at the request beyond its model.
+    pub fn has_request_conditions(&self) -> bool {
+        !self.headers.is_empty()
+            || !self.paths.is_empty()
+            || !self.teams.is_empty()
+            || self.stream.is_some()
+            || self.min_prompt_tokens.is_some()
+            || self.max_prompt_tokens.is_some()
+            || !self.any.is_empty()
+            || !self.all.is_empty()
+    }
+
+    /// Whether the spec (or one it nests) needs the request body to match.
+    pub fn reads_body(&self) -> bool {
+        self.stream.is_some()
+            || self.min_prompt_tokens.is_some()
+            || self.max_prompt_tokens.is_some()
+            || self.any.iter().chain(&self.all).any(MatchSpec::reads_body)
+    }
+
+    /// Problems with the time windows in this spec and the specs it nests.
+    fn time_problems(&self, at: &str, problems: &mut Vec<String>) {
+        if let Some(problem) = self.time.as_ref().and_then(TimeWindow::problem) {
+            problems.push(format!("{at}.time: {problem}"));
+        }
+        for (kind, specs) in [("any", &self.any), ("all", &self.all)] {
+            for (index, spec) in specs.iter().enumerate() {
+                spec.time_problems(&format!("{at}.{kind}[{index}]"), problems);
+            }
+        }
+    }
+}
+
+/// Days and local times during which a rule applies, e.g. to send
+/// off-peak traffic to cheaper channels.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// `HH:MM-HH:MM` ranges, start inclusive and end exclusive. A range
+    /// ending before it starts runs past midnight (`22:00-06:00`). Empty
+    /// means all day.
+    #[serde(
+        default,
+        deserialize_with = "string_or_vec",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub hours: Vec<String>,
+    /// Weekdays (`mon` … `sun`) or ranges such as `mon-fri`, checked
+    /// against the local date. Empty means every day.
+    #[serde(
+        default,
+        deserialize_with = "string_or_vec",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub days: Vec<String>,
+    /// IANA time zone, e.g. `Asia/Shanghai` (default `UTC`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl TimeWindow {
+    /// Whether `now` falls inside the window. A window that does not parse
+    /// never matches.
+    pub fn contains(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        use chrono::{Datelike, Timelike};
+
+        let Ok(zone) = self.zone() else {
+            return false;
+        };
+        let local = now.with_timezone(&zone);
+        let minute = local.hour() * 60 + local.minute();
+        let weekday = local.weekday().num_days_from_monday();
+        let in_days = self.days.is_empty()
+            || self
+                .days
+                .iter()
+                .any(|days| parse_days(days).is_ok_and(|(from, to)| in_cycle(weekday, from, to)));
+        let in_hours = self.hours.is_empty()
+            || self.hours.iter().any(|range| {
+                parse_hours(range).is_ok_and(|(start, end)| {
+                    start == end || in_cycle(minute, start, end.checked_sub(1).unwrap_or(1439))
+                })
+            });
+        in_days && in_hours
+    }
+
+    /// What is wrong with the window, if anything.
+    pub fn problem(&self) -> Option<String> {
+        self.zone()
+            .err()
+            .or_else(|| self.hours.iter().find_map(|range| parse_hours(range).err()))
+            .or_else(|| self.days.iter().find_map(|days| parse_days(days).err()))
+    }
+
+    fn zone(&self) -> Result<chrono_tz::Tz, String> {
+        let name = self.timezone.as_deref().unwrap_or("UTC");
+        name.parse()
+            .map_err(|_| format!("timezone {name:?} is not a known IANA time zone"))
+    }
+}
+
+/// Whether `value` lies in `from..=to`, wrapping around when `to < from`.
+fn in_cycle(value: u32, from: u32, to: u32) -> bool {
+    if from <= to {
+        (from..=to).contains(&value)
+    } else {
+        value >= from || value <= to
+    }
+}
+
+/// `HH:MM-HH:MM` as minutes since midnight.
+fn parse_hours(range: &str) -> Result<(u32, u32), String> {
+    let minutes = |time: &str| {
+        chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .map(|time| {
+                use chrono::Timelike;
+                time.hour() * 60 + time.minute()
+            })
+            .ok()
+    };
+    range
+        .split_once('-')
+        .and_then(|(start, end)| Some((minutes(start)?, minutes(end)?)))
+        .ok_or_else(|| format!("hours {range:?} is not an HH:MM-HH:MM range"))
+}
+
+/// `mon` or `mon-fri` as days from Monday.
+fn parse_days(days: &str) -> Result<(u32, u32), String> {
+    let day = |day: &str| {
+        day.trim()
+            .parse::<chrono::Weekday>()
+            .map(|day| day.num_days_from_monday())
+            .ok()
+    };
+    match days.split_once('-') {
+        Some((from, to)) => day(from).zip(day(to)),
+        None => day(days).map(|day| (day, day)),
+    }
+    .ok_or_else(|| format!("days {days:?} is not a weekday or a range such as mon-fri"))
 }
 
 fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
@@ -324,12 +1942,76 @@ pub struct RouterMetadata {
 pub struct Metrics {
     pub enabled: bool,
     pub path: String,
+    /// Export `apex_end_user_requests_total` labelled by end user. Only enable
+    /// when the set of end-user ids is small (it becomes a label value).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub end_user_label: bool,
+    /// Serve `path` on this separate address (e.g. `127.0.0.1:9090`) instead
+    /// of the main listener. Takes effect at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+    /// Bearer token required by the dedicated metrics listener; open when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Histogram bucket boundaries; unset histograms keep the built-in
+    /// millisecond defaults. Takes effect at startup.
+    #[serde(default, skip_serializing_if = "MetricsBuckets::is_empty")]
+    pub buckets: MetricsBuckets,
+}
+
+/// Upper bounds (strictly increasing) for the latency and throughput
+/// histograms; all in ms except `stream_tokens_per_second`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetricsBuckets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_latency_ms: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_ttfb_ms: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_ttft_ms: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_tokens_per_second: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_duration_ms: Option<Vec<f64>>,
+}
+
+impl MetricsBuckets {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotReload {
     pub config_path: String,
     pub watch: bool,
+    /// Central store the config document is loaded from and watched in,
+    /// instead of the local file. Per-instance: never part of the document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<ConfigStore>,
+}
+
+/// Where a fleet of gateways keeps its shared config document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConfigStore {
+    /// A Redis string; changes are picked up through keyspace notifications
+    /// and a periodic re-read.
+    Redis {
+        url: String,
+        #[serde(default = "default_config_store_key")]
+        key: String,
+    },
+    /// An etcd v3 key, read and watched through etcd's JSON gateway.
+    Etcd {
+        endpoints: Vec<String>,
+        #[serde(default = "default_config_store_key")]
+        key: String,
+    },
+}
+
+fn default_config_store_key() -> String {
+    "apex/config".to_string()
 }
 
 /// PII action type
@@ -392,9 +2074,89 @@ impl Compliance {
     }
 }
 
-pub fn load_config(path: &Path) -> anyhow::Result<Config> {
+/// Content filtering of prompts and responses (see `guardrails`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Guardrails {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<GuardrailRule>,
+    /// Ask a channel's `/v1/moderations` endpoint as well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation: Option<ModerationSettings>,
+}
+
+/// Block list: a request or response matching any pattern or keyword is
+/// rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardrailRule {
+    pub name: String,
+    /// Regular expressions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// Case-insensitive substrings.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub applies_to: GuardrailScope,
+}
+
+/// Which side of the exchange a guardrail inspects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailScope {
+    #[default]
+    Request,
+    Response,
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationSettings {
+    /// Channel the moderation requests are sent to.
+    pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub applies_to: GuardrailScope,
+    /// Reject requests when the moderation call fails, instead of letting
+    /// them through.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fail_closed: bool,
+}
+
+/// Parse the config file at `path`, apply `APEX_*` environment overrides
+/// (`env_overrides`) and merge its include directories (`config_includes`).
+pub fn read_config(path: &Path) -> anyhow::Result<Config> {
     let content = fs::read_to_string(path)?;
-    let mut config = serde_json::from_str::<Config>(&content)?;
+    let mut config = parse_config_document(&content)?;
+    crate::config_includes::merge(&mut config, path)?;
+    Ok(config)
+}
+
+/// A config document with `APEX_*` environment overrides applied; shared by
+/// the config file and `hot_reload.store`.
+pub fn parse_config_document(content: &str) -> anyhow::Result<Config> {
+    let mut raw: serde_json::Value = serde_json::from_str(content)?;
+    let env_overrides = crate::env_overrides::apply(&mut raw)?;
+    let mut config = if env_overrides.is_empty() {
+        // Parse the text itself so errors keep their line and column.
+        serde_json::from_str::<Config>(content)?
+    } else {
+        serde_json::from_value::<Config>(raw)
+            .context("invalid config after applying APEX_* environment overrides")?
+    };
+    config.env_overrides = env_overrides;
+    Ok(config)
+}
+
+pub fn load_config(path: &Path) -> anyhow::Result<Config> {
+    prepare_config(read_config(path)?, path.parent())
+}
+
+/// Expand tenants, resolve secrets (file references relative to `base_dir`),
+/// validate and migrate a freshly read config.
+pub fn prepare_config(mut config: Config, base_dir: Option<&Path>) -> anyhow::Result<Config> {
+    config.expand_tenants();
+    crate::secrets::resolve(&mut config, base_dir);
 
     // Validate compliance configuration if present
     if let Some(ref compliance) = config.compliance {
@@ -403,6 +2165,15 @@ pub fn load_config(path: &Path) -> anyhow::Result<Config> {
             .map_err(|e| anyhow::anyhow!("Invalid compliance config: {}", e))?;
     }
 
+    for tenant in &config.tenants {
+        if tenant.id.is_empty() || tenant.id.contains('/') {
+            anyhow::bail!(
+                "Invalid tenant id {:?}: must be non-empty and contain no '/'",
+                tenant.id
+            );
+        }
+    }
+
     // Migrate legacy configuration to rules
     for router in std::sync::Arc::make_mut(&mut config.routers) {
         if router.rules.is_empty() {
@@ -412,12 +2183,14 @@ pub fn load_config(path: &Path) -> anyhow::Result<Config> {
                     router.rules.push(RouterRule {
                         match_spec: MatchSpec {
                             models: vec![pattern.clone()],
+                            ..Default::default()
                         },
                         channels: vec![TargetChannel {
                             name: target_channel_name.clone(),
                             weight: 1,
                         }],
-                        strategy: "priority".to_string(), // Single channel implies priority/direct
+                        strategy: "priority".to_string(), // Single channel implies priority/direct,
+                        retries: None,
                     });
                 }
             }
@@ -427,9 +2200,11 @@ pub fn load_config(path: &Path) -> anyhow::Result<Config> {
                 router.rules.push(RouterRule {
                     match_spec: MatchSpec {
                         models: vec!["*".to_string()],
+                        ..Default::default()
                     },
                     channels: router.channels.clone(),
                     strategy: router.strategy.clone(),
+                    retries: None,
                 });
             }
         }
@@ -483,21 +2258,408 @@ pub fn check_no_placeholder_credentials(config: &Config) -> anyhow::Result<()> {
     ))
 }
 
+/// Load-balancing strategies understood by `RouterSelector` (rule and
+/// router `strategy`).
+pub const ROUTING_STRATEGIES: &[&str] = &[
+    "round_robin",
+    "priority",
+    "random",
+    "weighted",
+    "least_cost",
+    "sticky",
+    "split",
+];
+
+/// Structural checks that would otherwise surface as misrouting at request
+/// time: unknown strategies, references to missing channels / routers,
+/// duplicate names, unparsable listen addresses and virtual keys that collide
+/// with each other or with team / admin keys. Returns every problem found in
+/// one multi-line error. Called by `apex config validate`, at gateway start
+/// and on hot reload (tenant resources are checked in their scoped form).
+pub fn validate_config(config: &Config) -> anyhow::Result<()> {
+    use std::collections::{HashMap, HashSet};
+
+    let mut config = config.clone();
+    config.expand_tenants();
+    let mut problems: Vec<String> = Vec::new();
+
+    if config
+        .global
+        .listen
+        .parse::<std::net::SocketAddr>()
+        .is_err()
+    {
+        problems.push(format!(
+            "global.listen = {:?} is not a valid address; use host:port, e.g. \"0.0.0.0:12356\"",
+            config.global.listen
+        ));
+    }
+    if let Some(listen) = &config.metrics.listen
+        && listen.parse::<std::net::SocketAddr>().is_err()
+    {
+        problems.push(format!(
+            "metrics.listen = {listen:?} is not a valid address; use host:port, e.g. \"127.0.0.1:9090\""
+        ));
+    }
+    if let Some(shedding) = &config.global.load_shedding
+        && !(shedding.low_priority_fraction > 0.0 && shedding.low_priority_fraction <= 1.0)
+    {
+        problems.push(format!(
+            "global.load_shedding.low_priority_fraction = {} must be greater than 0 and at most 1",
+            shedding.low_priority_fraction
+        ));
+    }
+    let buckets = &config.metrics.buckets;
+    for (name, bounds) in [
+        ("upstream_latency_ms", &buckets.upstream_latency_ms),
+        ("stream_ttfb_ms", &buckets.stream_ttfb_ms),
+        ("stream_ttft_ms", &buckets.stream_ttft_ms),
+        (
+            "stream_tokens_per_second",
+            &buckets.stream_tokens_per_second,
+        ),
+        ("request_duration_ms", &buckets.request_duration_ms),
+    ] {
+        if let Some(bounds) = bounds
+            && (bounds.is_empty()
+                || bounds.iter().any(|b| !b.is_finite() || *b <= 0.0)
+                || bounds.windows(2).any(|pair| pair[0] >= pair[1]))
+        {
+            problems.push(format!(
+                "metrics.buckets.{name} = {bounds:?} must be a non-empty list of positive, strictly increasing bounds"
+            ));
+        }
+    }
+
+    let mut duplicates = |kind: &str, names: Vec<&str>| {
+        let mut seen = HashSet::new();
+        for name in names {
+            if !seen.insert(name) {
+                problems.push(format!("duplicate {kind} {name:?}; names must be unique"));
+            }
+        }
+    };
+    duplicates(
+        "channel",
+        config.channels.iter().map(|c| c.name.as_str()).collect(),
+    );
+    duplicates(
+        "router",
+        config.routers.iter().map(|r| r.name.as_str()).collect(),
+    );
+    duplicates("team", config.teams.iter().map(|t| t.id.as_str()).collect());
+    duplicates(
+        "tenant",
+        config.tenants.iter().map(|t| t.id.as_str()).collect(),
+    );
+
+    problems.extend(
+        config
+            .global
+            .body_limits
+            .as_ref()
+            .and_then(|limits| limits.problem("global")),
+    );
+    for channel in config.channels.iter() {
+        if channel.provider_type == ProviderType::Vertex && !channel.api_keys.is_empty() {
+            problems.push(format!(
+                "channels[name={}].api_keys is not supported for vertex channels; use one service account in api_key or api_key_file",
+                channel.name
+            ));
+        }
+        if let Some(problem) = channel
+            .body_limits
+            .as_ref()
+            .and_then(|limits| limits.problem(&format!("channels[name={}]", channel.name)))
+        {
+            problems.push(problem);
+        }
+    }
+
+    let alias_problem = |at: &str, aliases: &HashMap<String, String>| {
+        aliases
+            .iter()
+            .any(|(alias, model)| alias.trim().is_empty() || model.trim().is_empty())
+            .then(|| format!("{at}.model_aliases entries need a non-empty alias and model"))
+    };
+    problems.extend(alias_problem("global", &config.global.model_aliases));
+
+    let channels: HashSet<&str> = config.channels.iter().map(|c| c.name.as_str()).collect();
+    let strategy_problem = |at: String, strategy: &str| {
+        (!ROUTING_STRATEGIES.contains(&strategy)).then(|| {
+            format!(
+                "{at}.strategy = {strategy:?} is not a known strategy (expected one of: {})",
+                ROUTING_STRATEGIES.join(", ")
+            )
+        })
+    };
+    // `split` targets carry percentages in their weights.
+    let split_problem = |at: String, strategy: &str, targets: &[TargetChannel]| {
+        let total: u32 = targets.iter().map(|target| target.weight).sum();
+        (strategy == "split" && total != 100).then(|| {
+            format!("{at}.channels weights must sum to 100 for the split strategy (got {total})")
+        })
+    };
+    for router in config.routers.iter() {
+        let at = format!("routers[name={}]", router.name);
+        if !router.channels.is_empty() {
+            problems.extend(strategy_problem(at.clone(), &router.strategy));
+            problems.extend(split_problem(
+                at.clone(),
+                &router.strategy,
+                &router.channels,
+            ));
+        }
+        for (index, tier) in router.fallback_tiers.iter().enumerate() {
+            let at = format!("{at}.fallback_tiers[{index}]");
+            if tier.channels.is_empty() {
+                problems.push(format!("{at}.channels must not be empty"));
+            }
+            problems.extend(strategy_problem(at.clone(), &tier.strategy));
+            problems.extend(split_problem(at, &tier.strategy, &tier.channels));
+        }
+        problems.extend(alias_problem(&at, &router.model_aliases));
+        if router.reject_missing_model && router.default_model.is_some() {
+            problems.push(format!(
+                "{at} sets both default_model and reject_missing_model; choose one"
+            ));
+        }
+        if router
+            .default_model
+            .as_ref()
+            .is_some_and(|model| model.trim().is_empty())
+        {
+            problems.push(format!("{at}.default_model must not be empty"));
+        }
+        if let Some(percent) = router.mirror_percent
+            && !(0.0..=100.0).contains(&percent)
+        {
+            problems.push(format!(
+                "{at}.mirror_percent = {percent} must be between 0 and 100"
+            ));
+        }
+        if let Some(canary) = &router.canary
+            && (!(0.0..=100.0).contains(&canary.percent)
+                || !(0.0..=1.0).contains(&canary.max_error_rate))
+        {
+            problems.push(format!(
+                "{at}.canary needs percent between 0 and 100 and max_error_rate between 0 and 1"
+            ));
+        }
+        if let Some(experiment) = &router.experiment {
+            let total: f64 = experiment.variants.iter().map(|v| v.percent).sum();
+            if experiment.variants.iter().any(|v| v.percent < 0.0) || total > 100.0 {
+                problems.push(format!(
+                    "{at}.experiment variant percents must be non-negative and sum to at most 100 (got {total})"
+                ));
+            }
+            let mut names = HashSet::new();
+            for variant in &experiment.variants {
+                if variant.name == "control" || !names.insert(variant.name.as_str()) {
+                    problems.push(format!(
+                        "{at}.experiment variant name {:?} is reserved or duplicated",
+                        variant.name
+                    ));
+                }
+            }
+        }
+        let mut missing = |field: String, channel: &str| {
+            if !channels.contains(channel) {
+                problems.push(format!(
+                    "{at}.{field} references channel {channel:?}, which is not defined in channels"
+                ));
+            }
+        };
+        for target in &router.channels {
+            missing("channels".to_string(), &target.name);
+        }
+        for name in &router.fallback_channels {
+            missing("fallback_channels".to_string(), name);
+        }
+        for (index, tier) in router.fallback_tiers.iter().enumerate() {
+            for target in &tier.channels {
+                missing(format!("fallback_tiers[{index}].channels"), &target.name);
+            }
+        }
+        if let Some(name) = &router.mirror_channel {
+            missing("mirror_channel".to_string(), name);
+        }
+        if let Some(canary) = &router.canary {
+            missing("canary.channel".to_string(), &canary.channel);
+        }
+        if let Some(experiment) = &router.experiment {
+            for variant in &experiment.variants {
+                if let Some(name) = &variant.channel {
+                    missing(
+                        format!("experiment.variants[{}].channel", variant.name),
+                        name,
+                    );
+                }
+            }
+        }
+        if let Some(metadata) = &router.metadata {
+            for name in metadata.model_matcher.values() {
+                missing("metadata.model_matcher".to_string(), name);
+            }
+        }
+        for (index, rule) in router.rules.iter().enumerate() {
+            for target in &rule.channels {
+                missing(format!("rules[{index}].channels"), &target.name);
+            }
+        }
+        for (index, rule) in router.rules.iter().enumerate() {
+            problems.extend(strategy_problem(
+                format!("{at}.rules[{index}]"),
+                &rule.strategy,
+            ));
+            problems.extend(split_problem(
+                format!("{at}.rules[{index}]"),
+                &rule.strategy,
+                &rule.channels,
+            ));
+            rule.match_spec
+                .time_problems(&format!("{at}.rules[{index}].match"), &mut problems);
+        }
+        if let Some(transforms) = &router.transforms {
+            for (side, list) in [
+                ("request", &transforms.request),
+                ("response", &transforms.response),
+            ] {
+                problems.extend(
+                    crate::transforms::check(list)
+                        .into_iter()
+                        .map(|problem| format!("{at}.transforms.{side}: {problem}")),
+                );
+            }
+        }
+    }
+
+    if let Some(guardrails) = &config.guardrails {
+        problems.extend(crate::guardrails::check(guardrails));
+        if let Some(moderation) = &guardrails.moderation
+            && !channels.contains(moderation.channel.as_str())
+        {
+            problems.push(format!(
+                "guardrails.moderation.channel references channel {:?}, which is not defined in channels",
+                moderation.channel
+            ));
+        }
+    }
+
+    let routers: HashSet<&str> = config.routers.iter().map(|r| r.name.as_str()).collect();
+    for team in config.teams.iter() {
+        for name in &team.policy.allowed_routers {
+            if !routers.contains(name.as_str()) {
+                problems.push(format!(
+                    "teams[id={}].policy.allowed_routers references router {name:?}, which is not defined in routers",
+                    team.id
+                ));
+            }
+        }
+        for key in &team.keys {
+            if let Some(at) = &key.expires_at
+                && chrono::DateTime::parse_from_rfc3339(at).is_err()
+            {
+                problems.push(format!(
+                    "teams[id={}].keys[id={}].expires_at = {at:?} is not an RFC 3339 timestamp, e.g. \"2026-12-31T00:00:00Z\"",
+                    team.id, key.id
+                ));
+            }
+        }
+    }
+
+    for team in config.teams.iter() {
+        let Some(pii) = &team.policy.pii else {
+            continue;
+        };
+        for name in pii.builtin.iter().flatten() {
+            if !crate::compliance::BUILTIN_RULE_NAMES.contains(&name.as_str()) {
+                problems.push(format!(
+                    "teams[id={}].policy.pii.builtin has unknown detector {name:?} (expected one of: {})",
+                    team.id,
+                    crate::compliance::BUILTIN_RULE_NAMES.join(", ")
+                ));
+            }
+        }
+        for rule in &pii.rules {
+            if let Err(e) = regex::Regex::new(&rule.pattern) {
+                problems.push(format!(
+                    "teams[id={}].policy.pii.rules[name={}] has an invalid pattern: {e}",
+                    team.id, rule.name
+                ));
+            }
+        }
+    }
+
+    let mut vkeys: HashMap<&str, &str> = HashMap::new();
+    for router in config.routers.iter() {
+        let Some(vkey) = router.vkey.as_deref() else {
+            continue;
+        };
+        if let Some(other) = vkeys.insert(vkey, router.name.as_str()) {
+            problems.push(format!(
+                "routers[name={}].vkey is also the vkey of router {other:?}; rotate one with `apex router update --rotate-vkey`",
+                router.name
+            ));
+        }
+        if let Some(team) = config.teams.iter().find(|t| t.api_key == vkey) {
+            problems.push(format!(
+                "routers[name={}].vkey equals the api_key of team {:?}; the team key would win",
+                router.name, team.id
+            ));
+        }
+        if config.global.auth_keys.iter().any(|k| k == vkey) {
+            problems.push(format!(
+                "routers[name={}].vkey is also listed in global.auth_keys",
+                router.name
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "invalid config:\n  - {}",
+        problems.join("\n  - ")
+    ))
+}
+
 pub fn save_config(path: &Path, config: &Config) -> anyhow::Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    let content = serde_json::to_string_pretty(config)?;
+    let config = crate::secrets::with_references(&config.without_tenant_resources());
+    let (config, includes) = crate::config_includes::split(&config)?;
+    for (include, content) in includes {
+        fs::write(include, content)?;
+    }
+    let mut raw = serde_json::to_value(&config)?;
+    crate::env_overrides::restore(&config.env_overrides, &mut raw);
+    let content = serde_json::to_string_pretty(&raw)?;
     fs::write(path, content)?;
     Ok(())
 }
 
+/// The document `hot_reload.store` holds for `config`: everything
+/// `save_config` would write, in one piece, without the store itself.
+pub fn config_document(config: &Config) -> anyhow::Result<String> {
+    let config = crate::secrets::with_references(&config.without_tenant_resources());
+    let mut raw = serde_json::to_value(&config)?;
+    crate::env_overrides::restore(&config.env_overrides, &mut raw);
+    if let Some(hot_reload) = raw.get_mut("hot_reload").and_then(|v| v.as_object_mut()) {
+        hot_reload.remove("store");
+    }
+    Ok(serde_json::to_string_pretty(&raw)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        Config, PLACEHOLDER_AUTH_KEYS, PLACEHOLDER_TEAM_KEYS, ProviderType,
-        check_no_placeholder_credentials,
+        Config, PLACEHOLDER_AUTH_KEYS, PLACEHOLDER_TEAM_KEYS, ProviderType, Retries, Router,
+        TimeWindow, check_no_placeholder_credentials, validate_config,
     };
+    use std::time::Duration;
 
     fn parse_config(json: &str) -> Config {
         serde_json::from_str(json).unwrap()
@@ -540,6 +2702,110 @@ mod tests {
         parse_config(&json)
     }
 
+    #[test]
+    fn validate_config_accepts_consistent_config() {
+        let mut cfg = config_with(&[], &[("acme", "sk-ap-acme")]);
+        cfg.channels = std::sync::Arc::new(
+            serde_json::from_value(serde_json::json!([
+                {"name": "openai", "provider_type": "openai", "base_url": "https://api.openai.com", "api_key": "sk"}
+            ]))
+            .unwrap(),
+        );
+        cfg.routers = std::sync::Arc::new(
+            serde_json::from_value(serde_json::json!([{
+                "name": "default",
+                "rules": [{"match": {"models": ["*"]}, "channels": [{"name": "openai"}], "strategy": "priority"}],
+                "vkey": "vk_default"
+            }]))
+            .unwrap(),
+        );
+        std::sync::Arc::make_mut(&mut cfg.teams)[0]
+            .policy
+            .allowed_routers
+            .push("default".to_string());
+        assert!(
+            validate_config(&cfg).is_ok(),
+            "{:#}",
+            validate_config(&cfg).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn validate_config_reports_every_problem() {
+        let mut cfg = config_with(
+            &["vk_shared"],
+            &[("acme", "sk-ap-acme"), ("acme", "sk-ap-2")],
+        );
+        cfg.global.listen = "localhost".to_string();
+        cfg.metrics.buckets.upstream_latency_ms = Some(vec![100.0, 50.0]);
+        cfg.channels = std::sync::Arc::new(
+            serde_json::from_value(serde_json::json!([
+                {"name": "openai", "provider_type": "openai", "base_url": "https://api.openai.com", "api_key": "sk"},
+                {"name": "openai", "provider_type": "openai", "base_url": "https://api.openai.com", "api_key": "sk"},
+                {"name": "gcp", "provider_type": "vertex", "base_url": "", "api_key": "ya29.a", "api_keys": ["ya29.b"], "body_limits": {"max_response_bytes": 0}}
+            ]))
+            .unwrap(),
+        );
+        cfg.routers = std::sync::Arc::new(
+            serde_json::from_value(serde_json::json!([
+                {
+                    "name": "r1",
+                    "rules": [
+                        {"match": {"models": ["*"]}, "channels": [{"name": "opneai"}], "strategy": "fastest"},
+                        {"match": {"any": [{"time": {"hours": "25:00-26:00"}}]}, "channels": [{"name": "openai"}]},
+                        {"match": {"models": ["gpt-*"]}, "channels": [{"name": "openai", "weight": 90}], "strategy": "split"}
+                    ],
+                    "fallback_channels": ["backup"],
+                    "fallback_tiers": [{"channels": [{"name": "spare"}], "strategy": "split"}],
+                    "vkey": "vk_shared"
+                },
+                {
+                    "name": "r2",
+                    "rules": [],
+                    "vkey": "vk_shared",
+                    "transforms": {"request": [{"op": "remove", "path": "messages[last]"}]}
+                }
+            ]))
+            .unwrap(),
+        );
+        std::sync::Arc::make_mut(&mut cfg.teams)[0]
+            .policy
+            .allowed_routers
+            .push("r3".to_string());
+        let mut key = crate::key_store::new_key(None, None, vec![]).0;
+        key.expires_at = Some("2026-12-31".to_string());
+        std::sync::Arc::make_mut(&mut cfg.teams)[0].keys.push(key);
+        cfg.guardrails = Some(
+            serde_json::from_value(serde_json::json!({"moderation": {"channel": "moderator"}}))
+                .unwrap(),
+        );
+
+        let msg = format!("{:#}", validate_config(&cfg).unwrap_err());
+        for expected in [
+            "global.listen = \"localhost\"",
+            "metrics.buckets.upstream_latency_ms = [100.0, 50.0]",
+            "duplicate channel \"openai\"",
+            "duplicate team \"acme\"",
+            "routers[name=r1].rules[0].channels references channel \"opneai\"",
+            "routers[name=r1].fallback_channels references channel \"backup\"",
+            "routers[name=r1].fallback_tiers[0].channels references channel \"spare\"",
+            "routers[name=r1].fallback_tiers[0].channels weights must sum to 100",
+            "routers[name=r1].rules[0].strategy = \"fastest\"",
+            "routers[name=r1].rules[1].match.any[0].time: hours \"25:00-26:00\"",
+            "routers[name=r1].rules[2].channels weights must sum to 100 for the split strategy (got 90)",
+            "teams[id=acme].policy.allowed_routers references router \"r3\"",
+            "expires_at = \"2026-12-31\" is not an RFC 3339 timestamp",
+            "routers[name=r2].vkey is also the vkey of router \"r1\"",
+            "routers[name=r1].vkey is also listed in global.auth_keys",
+            "channels[name=gcp].api_keys is not supported for vertex channels",
+            "channels[name=gcp].body_limits.max_response_bytes must be greater than 0",
+            "routers[name=r2].transforms.request: invalid path \"messages[last]\"",
+            "guardrails.moderation.channel references channel \"moderator\"",
+        ] {
+            assert!(msg.contains(expected), "missing {expected:?} in:\n{msg}");
+        }
+    }
+
     #[test]
     fn placeholder_check_accepts_empty_auth_keys() {
         let cfg = config_with(&[], &[]);
@@ -638,6 +2904,37 @@ mod tests {
         assert!(PLACEHOLDER_TEAM_KEYS.contains(&"sk-team-demo-key"));
     }
 
+    #[test]
+    fn retry_delay_grows_caps_and_honors_retry_after() {
+        let mut retries = Retries {
+            max_attempts: 5,
+            backoff_ms: 100,
+            retry_on_status: vec![429],
+            exponential: false,
+            jitter: false,
+            respect_retry_after: false,
+            max_backoff_ms: Some(1_000),
+        };
+        let ms = |d: Duration| d.as_millis() as u64;
+        assert_eq!(ms(retries.delay(3, Some(Duration::from_secs(5)))), 100);
+
+        retries.exponential = true;
+        let delays: Vec<u64> = (0..5).map(|n| ms(retries.delay(n, None))).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1_000]);
+
+        retries.respect_retry_after = true;
+        assert_eq!(ms(retries.delay(0, Some(Duration::from_millis(700)))), 700);
+        assert_eq!(ms(retries.delay(0, Some(Duration::from_secs(60)))), 1_000);
+        assert_eq!(ms(retries.delay(2, Some(Duration::from_millis(10)))), 400);
+
+        retries.respect_retry_after = false;
+        retries.jitter = true;
+        for _ in 0..50 {
+            let jittered = ms(retries.delay(2, None));
+            assert!((200..=400).contains(&jittered), "{jittered}");
+        }
+    }
+
     #[test]
     fn provider_type_zai_round_trips_as_snake_case() {
         let serialized = serde_json::to_string(&ProviderType::Zai).unwrap();
@@ -691,4 +2988,100 @@ mod tests {
         let serialized = serde_json::to_string(&config).unwrap();
         assert!(!serialized.contains("web_dir"));
     }
+
+    #[test]
+    fn router_recognizes_only_non_catch_all_rules() {
+        let router: Router = serde_json::from_str(
+            r#"{
+              "name": "r1",
+              "reject_unknown_models": true,
+              "rules": [
+                {"match": {"models": ["gpt-4o", "claude-*"]}, "channels": [{"name": "a", "weight": 1}]},
+                {"match": {"models": ["*"]}, "channels": [{"name": "b", "weight": 1}]}
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(router.recognizes_model("GPT-4o"));
+        assert!(router.recognizes_model("claude-3-5-sonnet"));
+        assert!(!router.recognizes_model("gpt-4o-mni"));
+        assert_eq!(router.known_model_patterns(), vec!["gpt-4o", "claude-*"]);
+    }
+
+    #[test]
+    fn time_window_matches_local_days_and_overnight_hours() {
+        let window: TimeWindow = serde_json::from_value(serde_json::json!({
+            "days": ["mon-fri"],
+            "hours": ["22:00-06:00", "12:00-13:00"],
+            "timezone": "Asia/Shanghai"
+        }))
+        .unwrap();
+        let at = |utc: &str| window.contains(utc.parse().unwrap());
+
+        // 2026-10-16 is a Friday; Shanghai is UTC+8.
+        assert!(at("2026-10-16T04:30:00Z"));
+        assert!(!at("2026-10-16T05:00:00Z"));
+        assert!(at("2026-10-15T14:00:00Z"));
+        assert!(at("2026-10-15T21:59:00Z"));
+        assert!(!at("2026-10-15T22:00:00Z"));
+        // Saturday 02:00 local is outside `mon-fri`.
+        assert!(!at("2026-10-16T18:00:00Z"));
+        assert!(window.problem().is_none());
+
+        let everyday: TimeWindow = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(everyday.contains(chrono::Utc::now()));
+        let weekend: TimeWindow =
+            serde_json::from_value(serde_json::json!({"days": "sat-sun"})).unwrap();
+        assert!(weekend.contains("2026-10-18T23:59:00Z".parse().unwrap()));
+        assert!(!weekend.contains("2026-10-19T00:00:00Z".parse().unwrap()));
+
+        for (window, expected) in [
+            (serde_json::json!({"timezone": "Mars/Olympus"}), "timezone"),
+            (serde_json::json!({"hours": "9-17"}), "hours"),
+            (serde_json::json!({"days": "weekdays"}), "days"),
+        ] {
+            let window: TimeWindow = serde_json::from_value(window).unwrap();
+            assert!(window.problem().unwrap().contains(expected));
+            assert!(!window.contains(chrono::Utc::now()));
+        }
+    }
+
+    #[test]
+    fn tenant_resources_expand_under_qualified_names_and_strip_on_save() {
+        let mut config = config_with(&[], &[("root-team", "sk-root")]);
+        config.tenants.push(
+            serde_json::from_str(
+                r#"{
+                  "id": "acme",
+                  "hosts": ["llm.acme.test"],
+                  "channels": [{"name": "main", "provider_type": "mock", "base_url": "mock://local", "api_key": ""}],
+                  "routers": [{"name": "default", "rules": [{"match": {"models": ["*"]}, "channels": [{"name": "main"}]}]}],
+                  "teams": [{"id": "eng", "api_key": "sk-acme", "policy": {"allowed_routers": ["default"]}}]
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        config.expand_tenants();
+        config.expand_tenants();
+        assert_eq!(config.teams.len(), 2);
+        assert_eq!(config.routers[0].name, "acme/default");
+        assert_eq!(config.routers[0].rules[0].channels[0].name, "acme/main");
+        let team = config.teams.iter().find(|t| t.id == "acme/eng").unwrap();
+        assert_eq!(team.policy.allowed_routers, vec!["acme/default"]);
+        assert_eq!(config.tenant_of("acme/eng"), Some("acme"));
+        assert_eq!(config.tenant_of("root-team"), None);
+        assert_eq!(
+            config
+                .tenant_for_host("LLM.acme.test:8443")
+                .map(|t| t.id.as_str()),
+            Some("acme")
+        );
+
+        let on_disk = config.without_tenant_resources();
+        assert_eq!(on_disk.teams.len(), 1);
+        assert!(on_disk.channels.is_empty());
+        assert_eq!(on_disk.tenants.len(), 1);
+    }
 }
@@ -0,0 +1,254 @@
+//! Split config directories (conf.d layout).
+//!
+//! Next to the config file, `channels/*.json`, `routers/*.json` and
+//! `teams/*.json` each hold one entry or an array of entries. They are
+//! appended to the matching top-level list when the config is loaded, in
+//! file name order, and the file each entry came from is remembered in
+//! `Config::includes` so `save_config` writes it back there instead of
+//! into the main file. Entries added later (CLI, admin API) go to the main
+//! file; deleting an entry removes it from its file.
+
+use crate::config::{Channel, Config, Router, Team};
+use anyhow::Context;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Include directories, relative to the config file's directory.
+pub const INCLUDE_DIRS: &[&str] = &["channels", "routers", "teams"];
+
+/// Where each included entry was loaded from.
+#[derive(Debug, Clone, Default)]
+pub struct IncludeOrigins {
+    files: Arc<Vec<IncludeFile>>,
+}
+
+#[derive(Debug, Clone)]
+struct IncludeFile {
+    path: PathBuf,
+    kind: Kind,
+    /// Names (team ids) of the entries the file held.
+    names: Vec<String>,
+    /// The file held a single object rather than an array.
+    single: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Channels,
+    Routers,
+    Teams,
+}
+
+/// Include directories of the config at `config_path`, existing or not.
+pub fn include_dirs(config_path: &Path) -> Vec<PathBuf> {
+    let base = config_path.parent().unwrap_or_else(|| Path::new("."));
+    INCLUDE_DIRS.iter().map(|dir| base.join(dir)).collect()
+}
+
+/// `*.json` files of `dir`, sorted by name; none when `dir` does not exist.
+fn json_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Read one include file: a single entry or an array of them.
+fn read_entries<T: DeserializeOwned>(path: &Path) -> anyhow::Result<(Vec<T>, bool)> {
+    let parse = || -> anyhow::Result<(Vec<T>, bool)> {
+        let value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(match value {
+            Value::Array(_) => (serde_json::from_value(value)?, false),
+            value => (vec![serde_json::from_value(value)?], true),
+        })
+    };
+    parse().with_context(|| format!("invalid include file {}", path.display()))
+}
+
+/// Append the entries of the include directories next to `config_path` to
+/// `config` and record where they came from.
+pub fn merge(config: &mut Config, config_path: &Path) -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    let dirs = include_dirs(config_path);
+    for (kind, dir) in [Kind::Channels, Kind::Routers, Kind::Teams]
+        .into_iter()
+        .zip(&dirs)
+    {
+        for path in json_files(dir)? {
+            let (names, single) = match kind {
+                Kind::Channels => {
+                    let (entries, single) = read_entries::<Channel>(&path)?;
+                    let names = entries.iter().map(|c| c.name.clone()).collect();
+                    Arc::make_mut(&mut config.channels).extend(entries);
+                    (names, single)
+                }
+                Kind::Routers => {
+                    let (entries, single) = read_entries::<Router>(&path)?;
+                    let names = entries.iter().map(|r| r.name.clone()).collect();
+                    Arc::make_mut(&mut config.routers).extend(entries);
+                    (names, single)
+                }
+                Kind::Teams => {
+                    let (entries, single) = read_entries::<Team>(&path)?;
+                    let names = entries.iter().map(|t| t.id.clone()).collect();
+                    Arc::make_mut(&mut config.teams).extend(entries);
+                    (names, single)
+                }
+            };
+            files.push(IncludeFile {
+                path,
+                kind,
+                names,
+                single,
+            });
+        }
+    }
+    config.includes = IncludeOrigins {
+        files: Arc::new(files),
+    };
+    Ok(())
+}
+
+/// Split `config` into the main file's content and the content of each
+/// include file it was loaded with. Included entries that no longer exist
+/// are dropped from their file.
+pub fn split(config: &Config) -> anyhow::Result<(Config, Vec<(PathBuf, String)>)> {
+    let mut main = config.clone();
+    let mut outputs = Vec::new();
+    for file in main.includes.files.clone().iter() {
+        let content = match file.kind {
+            Kind::Channels => take(&mut main.channels, file, |c| &c.name)?,
+            Kind::Routers => take(&mut main.routers, file, |r| &r.name)?,
+            Kind::Teams => take(&mut main.teams, file, |t| &t.id)?,
+        };
+        outputs.push((file.path.clone(), content));
+    }
+    Ok((main, outputs))
+}
+
+/// Remove the entries `file` holds from `list` and render them.
+fn take<T: Clone + Serialize>(
+    list: &mut Arc<Vec<T>>,
+    file: &IncludeFile,
+    name: impl Fn(&T) -> &String,
+) -> anyhow::Result<String> {
+    let (taken, kept): (Vec<T>, Vec<T>) = list
+        .iter()
+        .cloned()
+        .partition(|entry| file.names.contains(name(entry)));
+    *list = Arc::new(kept);
+    let content = match taken.as_slice() {
+        [entry] if file.single => serde_json::to_string_pretty(entry)?,
+        _ => serde_json::to_string_pretty(&taken)?,
+    };
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config, save_config};
+
+    const MAIN: &str = r#"{
+        "version": "1",
+        "global": {
+            "listen": "127.0.0.1:0",
+            "auth": {"mode": "none"},
+            "timeouts": {"connect_ms": 1000, "request_ms": 1000, "response_ms": 1000},
+            "retries": {"max_attempts": 1, "backoff_ms": 0, "retry_on_status": []}
+        },
+        "metrics": {"enabled": false, "path": "/metrics"},
+        "hot_reload": {"config_path": "", "watch": false},
+        "channels": [
+            {"name": "main", "provider_type": "openai", "base_url": "https://a", "api_key": "sk-main"}
+        ]
+    }"#;
+
+    fn channel(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "provider_type": "openai",
+            "base_url": "https://a",
+            "api_key": format!("sk-{name}")
+        })
+    }
+
+    #[test]
+    fn merges_include_dirs_and_saves_entries_back_to_their_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, MAIN).unwrap();
+        std::fs::create_dir(dir.path().join("channels")).unwrap();
+        std::fs::create_dir(dir.path().join("teams")).unwrap();
+        let one = dir.path().join("channels/10-one.json");
+        let pair = dir.path().join("channels/20-pair.json");
+        std::fs::write(&one, channel("one").to_string()).unwrap();
+        std::fs::write(
+            &pair,
+            serde_json::json!([channel("two"), channel("three")]).to_string(),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("channels/notes.txt"), "ignored").unwrap();
+        std::fs::write(
+            dir.path().join("teams/acme.json"),
+            r#"{"id": "acme", "api_key": "sk-ap-acme", "policy": {"allowed_routers": []}}"#,
+        )
+        .unwrap();
+
+        let mut config = load_config(&path).unwrap();
+        let names: Vec<_> = config.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["main", "one", "two", "three"]);
+        assert_eq!(config.teams[0].id, "acme");
+
+        // Drop an included channel and add a new one.
+        Arc::make_mut(&mut config.channels).retain(|c| c.name != "two");
+        Arc::make_mut(&mut config.channels).push(serde_json::from_value(channel("added")).unwrap());
+        save_config(&path, &config).unwrap();
+
+        let main: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let main_names: Vec<_> = main["channels"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(main_names, ["main", "added"]);
+        assert!(main["teams"].as_array().unwrap().is_empty());
+        let one: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&one).unwrap()).unwrap();
+        assert_eq!(one["name"], "one");
+        let pair: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&pair).unwrap()).unwrap();
+        assert_eq!(pair.as_array().unwrap().len(), 1);
+        assert_eq!(pair[0]["name"], "three");
+
+        let reloaded = load_config(&path).unwrap();
+        assert_eq!(reloaded.channels.len(), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_include_file_with_its_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, MAIN).unwrap();
+        std::fs::create_dir(dir.path().join("routers")).unwrap();
+        std::fs::write(dir.path().join("routers/broken.json"), "{").unwrap();
+
+        let err = format!("{:#}", load_config(&path).unwrap_err());
+        assert!(err.contains("routers/broken.json"), "{err}");
+    }
+}
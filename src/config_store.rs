@@ -0,0 +1,319 @@
+//! Central config store (`hot_reload.store`).
+//!
+//! A fleet of gateways can keep one config document in Redis or etcd instead
+//! of a `config.json` per host. Each instance still starts from its local
+//! file, which only needs `hot_reload.store`: the document in the store
+//! replaces it at startup, and later changes to the key are applied like a
+//! file reload. Admin API changes are written back to the store so the rest
+//! of the fleet follows. When the store is unreachable at startup the local
+//! file is served until the store answers again.
+
+use crate::config::{Config, ConfigStore, HotReload};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Longest a read or write may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Wait before re-establishing a watch that failed or ended.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Config generation last written by `publish`, so a slow write never
+/// overwrites a newer one.
+static PUBLISHED_GENERATION: tokio::sync::Mutex<u64> = tokio::sync::Mutex::const_new(0);
+
+/// The document stored under the key, or `None` when the key does not exist.
+pub async fn fetch(store: &ConfigStore) -> anyhow::Result<Option<String>> {
+    match store {
+        ConfigStore::Redis { url, key } => {
+            let mut connection = redis_connection(url).await?;
+            let document: Option<String> = tokio::time::timeout(
+                REQUEST_TIMEOUT,
+                redis::cmd("GET").arg(key).query_async(&mut connection),
+            )
+            .await??;
+            Ok(document)
+        }
+        ConfigStore::Etcd { endpoints, key } => {
+            let response = etcd_call(
+                endpoints,
+                "/v3/kv/range",
+                json!({"key": STANDARD.encode(key)}),
+            )
+            .await?;
+            match response["kvs"][0]["value"].as_str() {
+                Some(value) => Ok(Some(String::from_utf8(STANDARD.decode(value)?)?)),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+/// Write `config` as the store's document, unless a config newer than
+/// `generation` was already written.
+pub async fn publish(store: &ConfigStore, config: &Config, generation: u64) -> anyhow::Result<()> {
+    let mut published = PUBLISHED_GENERATION.lock().await;
+    if generation != 0 && generation <= *published {
+        return Ok(());
+    }
+    let document = crate::config::config_document(config)?;
+    let result = match store {
+        ConfigStore::Redis { url, key } => {
+            let mut connection = redis_connection(url).await?;
+            tokio::time::timeout(
+                REQUEST_TIMEOUT,
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(&document)
+                    .query_async::<()>(&mut connection),
+            )
+            .await?
+            .map_err(anyhow::Error::from)
+        }
+        ConfigStore::Etcd { endpoints, key } => etcd_call(
+            endpoints,
+            "/v3/kv/put",
+            json!({"key": STANDARD.encode(key), "value": STANDARD.encode(&document)}),
+        )
+        .await
+        .map(|_| ()),
+    };
+    if result.is_ok() {
+        *published = generation;
+    }
+    result
+}
+
+/// A store document as this instance's config: `hot_reload` (and with it
+/// the store) stays the local one.
+pub fn parse(document: &str, local: &HotReload) -> anyhow::Result<Config> {
+    let mut config = crate::config::parse_config_document(document)?;
+    config.hot_reload = local.clone();
+    Ok(config)
+}
+
+/// The config to start with: the store's document when there is one, else
+/// `local`, which also seeds an empty store.
+pub async fn load_initial(store: &ConfigStore, local: Config) -> Config {
+    match fetch(store).await {
+        Ok(Some(document)) => match parse(&document, &local.hot_reload) {
+            Ok(config) => {
+                tracing::info!("Loaded config from {}", describe(store));
+                return config;
+            }
+            Err(e) => tracing::error!(
+                "Config in {} is invalid, starting from the local file: {:#}",
+                describe(store),
+                e
+            ),
+        },
+        Ok(None) => match publish(store, &local, 0).await {
+            Ok(()) => tracing::info!("Seeded {} from the local config file", describe(store)),
+            Err(e) => tracing::error!("Failed to seed {}: {:#}", describe(store), e),
+        },
+        Err(e) => tracing::error!(
+            "Config store {} unreachable, starting from the local file: {:#}",
+            describe(store),
+            e
+        ),
+    }
+    local
+}
+
+/// Signal `changed` whenever the store reports a change to the key, for as
+/// long as the receiver lives. Also signals after every reconnect, since a
+/// change may have been missed in between.
+pub async fn watch(store: ConfigStore, changed: mpsc::Sender<()>) {
+    while !changed.is_closed() {
+        let result = match &store {
+            ConfigStore::Redis { url, key } => watch_redis(url, key, &changed).await,
+            ConfigStore::Etcd { endpoints, key } => watch_etcd(endpoints, key, &changed).await,
+        };
+        if let Err(e) = result {
+            tracing::warn!("Config store watch on {} failed: {:#}", describe(&store), e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+        let _ = changed.try_send(());
+    }
+}
+
+/// Subscribes to the key's keyspace notifications, which the server only
+/// sends with `notify-keyspace-events` including `K` and `$` (or `A`).
+async fn watch_redis(url: &str, key: &str, changed: &mpsc::Sender<()>) -> anyhow::Result<()> {
+    let mut pubsub = redis::Client::open(url)?.get_async_pubsub().await?;
+    pubsub.psubscribe(format!("__keyspace@*__:{key}")).await?;
+    let mut messages = pubsub.on_message();
+    while messages.next().await.is_some() {
+        let _ = changed.try_send(());
+    }
+    anyhow::bail!("subscription closed")
+}
+
+async fn watch_etcd(
+    endpoints: &[String],
+    key: &str,
+    changed: &mpsc::Sender<()>,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::new();
+    let request = json!({"create_request": {"key": STANDARD.encode(key)}});
+    let mut last_error = anyhow::anyhow!("no etcd endpoints configured");
+    for endpoint in endpoints {
+        let url = format!("{}/v3/watch", endpoint.trim_end_matches('/'));
+        let response = match client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => response,
+            Err(e) => {
+                last_error = e.into();
+                continue;
+            }
+        };
+        // The first message only confirms the watch; a spurious signal
+        // costs one read.
+        let mut messages = response.bytes_stream();
+        while let Some(message) = messages.next().await {
+            message?;
+            let _ = changed.try_send(());
+        }
+        anyhow::bail!("watch stream from {endpoint} ended");
+    }
+    Err(last_error)
+}
+
+async fn redis_connection(url: &str) -> anyhow::Result<redis::aio::MultiplexedConnection> {
+    let client = redis::Client::open(url)?;
+    Ok(tokio::time::timeout(REQUEST_TIMEOUT, client.get_multiplexed_async_connection()).await??)
+}
+
+/// POST `body` to the first etcd endpoint that answers.
+async fn etcd_call(endpoints: &[String], path: &str, body: Value) -> anyhow::Result<Value> {
+    let client = reqwest::Client::new();
+    let mut last_error = anyhow::anyhow!("no etcd endpoints configured");
+    for endpoint in endpoints {
+        let url = format!("{}{}", endpoint.trim_end_matches('/'), path);
+        match client
+            .post(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => return Ok(response.json().await?),
+            Err(e) => last_error = e.into(),
+        }
+    }
+    Err(last_error)
+}
+
+/// The store and key, for logs (never the Redis URL, which may hold a
+/// password).
+pub fn describe(store: &ConfigStore) -> String {
+    match store {
+        ConfigStore::Redis { key, .. } => format!("redis key '{key}'"),
+        ConfigStore::Etcd { key, .. } => format!("etcd key '{key}'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use axum::routing::post;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// The slice of etcd's JSON gateway the store uses.
+    async fn spawn_etcd() -> String {
+        let kv: Arc<Mutex<HashMap<String, String>>> = Arc::default();
+        let range = {
+            let kv = kv.clone();
+            move |Json(body): Json<Value>| async move {
+                let key = body["key"].as_str().unwrap_or_default();
+                let kvs: Vec<Value> = kv
+                    .lock()
+                    .unwrap()
+                    .get(key)
+                    .map(|value| json!({"key": key, "value": value}))
+                    .into_iter()
+                    .collect();
+                Json(json!({"kvs": kvs}))
+            }
+        };
+        let put = move |Json(body): Json<Value>| async move {
+            kv.lock().unwrap().insert(
+                body["key"].as_str().unwrap().to_string(),
+                body["value"].as_str().unwrap().to_string(),
+            );
+            Json(json!({}))
+        };
+        let app = axum::Router::new()
+            .route("/v3/kv/range", post(range))
+            .route("/v3/kv/put", post(put));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn local_config(store: &ConfigStore) -> Config {
+        let mut config: Config = serde_json::from_value(json!({
+            "version": "1.0",
+            "global": {
+                "listen": "127.0.0.1:12356",
+                "auth_keys": [],
+                "timeouts": {"connect_ms": 1000, "request_ms": 1000, "response_ms": 1000},
+                "retries": {"max_attempts": 1, "backoff_ms": 100, "retry_on_status": [500]},
+                "cors_allowed_origins": []
+            },
+            "metrics": {"enabled": true, "path": "/metrics"},
+            "hot_reload": {"config_path": "/etc/apex/config.json", "watch": false}
+        }))
+        .unwrap();
+        config.hot_reload.store = Some(store.clone());
+        config
+    }
+
+    #[tokio::test]
+    async fn seeds_empty_etcd_key_and_loads_later_changes() {
+        let store = ConfigStore::Etcd {
+            endpoints: vec!["http://127.0.0.1:1".to_string(), spawn_etcd().await],
+            key: "apex/config".to_string(),
+        };
+        assert_eq!(fetch(&store).await.unwrap(), None);
+
+        let local = local_config(&store);
+        let loaded = load_initial(&store, local.clone()).await;
+        assert_eq!(loaded.global.listen, "127.0.0.1:12356");
+        let document = fetch(&store).await.unwrap().expect("store seeded");
+        assert!(!document.contains("\"store\""), "{document}");
+
+        // Another instance publishes a change; this one keeps its own
+        // hot_reload settings when applying it.
+        let mut changed = local.clone();
+        changed.global.listen = "0.0.0.0:8080".to_string();
+        changed.hot_reload.config_path = "/srv/other/config.json".to_string();
+        publish(&store, &changed, u64::MAX).await.unwrap();
+        let config = parse(&fetch(&store).await.unwrap().unwrap(), &local.hot_reload).unwrap();
+        assert_eq!(config.global.listen, "0.0.0.0:8080");
+        assert_eq!(config.hot_reload.config_path, "/etc/apex/config.json");
+        assert_eq!(config.hot_reload.store, Some(store.clone()));
+
+        // Older generations never overwrite a newer document.
+        publish(&store, &local, 1).await.unwrap();
+        assert!(
+            fetch(&store)
+                .await
+                .unwrap()
+                .unwrap()
+                .contains("0.0.0.0:8080")
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use axum::body::Bytes;
 use futures::{Stream, StreamExt, stream};
 use serde_json::{Map, Value, json};
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::io;
 
 /// Converts an OpenAI-compatible JSON response body to Anthropic's format.
@@ -68,7 +68,8 @@ pub fn convert_openai_response_to_anthropic(body: Bytes) -> Bytes {
             let stop_reason = match finish_reason {
                 "stop" => "end_turn",
                 "length" => "max_tokens",
-                "tool_calls" => "tool_use",
+                "tool_calls" | "function_call" => "tool_use",
+                "content_filter" => "refusal",
                 _ => "stop_sequence",
             };
             new_body.insert(
@@ -106,11 +107,12 @@ pub fn convert_openai_response_to_anthropic(body: Bytes) -> Bytes {
 /// - Converting delta content to Anthropic content blocks
 /// - Mapping finish_reason to stop_reason
 /// - Generating necessary Anthropic events (message_start, content_block_start, etc.)
-pub fn convert_openai_stream_to_anthropic<S>(
+pub fn convert_openai_stream_to_anthropic<S, E>(
     stream: S,
 ) -> impl Stream<Item = Result<Bytes, io::Error>> + Send
 where
-    S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin + Send + 'static,
+    S: Stream<Item = Result<Bytes, E>> + Unpin + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
     let state: (S, Vec<u8>, StreamConversionState) =
         (stream, Vec::new(), StreamConversionState::default());
@@ -276,6 +278,23 @@ where
                                             raw_index + usize::from(state.saw_text_block);
 
                                         if !state.tool_blocks_started.contains(&block_index) {
+                                            // Anthropic blocks are strictly sequential: finish
+                                            // the previous tool call before opening the next.
+                                            let open_tool_blocks: Vec<usize> = state
+                                                .tool_blocks_started
+                                                .difference(&state.tool_blocks_closed)
+                                                .copied()
+                                                .collect();
+                                            for open_index in open_tool_blocks {
+                                                state.tool_blocks_closed.insert(open_index);
+                                                events.push(format!(
+                                                    "event: content_block_stop\ndata: {}\n\n",
+                                                    serde_json::json!({
+                                                        "type": "content_block_stop",
+                                                        "index": open_index
+                                                    })
+                                                ));
+                                            }
                                             let id = tool_call
                                                 .get("id")
                                                 .and_then(Value::as_str)
@@ -315,6 +334,7 @@ where
                                             .get("function")
                                             .and_then(|function| function.get("arguments"))
                                             .and_then(Value::as_str)
+                                            .filter(|arguments| !arguments.is_empty())
                                         {
                                             events.push(format!(
                                                 "event: content_block_delta\ndata: {}\n\n",
@@ -364,7 +384,8 @@ where
                                     match finish_reason {
                                         "stop" => "end_turn",
                                         "length" => "max_tokens",
-                                        "tool_calls" => "tool_use",
+                                        "tool_calls" | "function_call" => "tool_use",
+                                        "content_filter" => "refusal",
                                         _ => "stop_sequence",
                                     }
                                     .to_string(),
@@ -413,11 +434,23 @@ struct StreamConversionState {
 
 fn map_openai_usage_to_anthropic(usage: &Value) -> Option<Value> {
     let mut mapped = Map::new();
+    let cached = usage
+        .pointer("/prompt_tokens_details/cached_tokens")
+        .and_then(Value::as_u64)
+        .filter(|cached| *cached > 0);
     if let Some(input_tokens) = usage
         .get("prompt_tokens")
         .or_else(|| usage.get("input_tokens"))
     {
-        mapped.insert("input_tokens".to_string(), input_tokens.clone());
+        // Anthropic counts cache reads apart from `input_tokens`.
+        let input_tokens = match (input_tokens.as_u64(), cached) {
+            (Some(total), Some(cached)) => json!(total.saturating_sub(cached)),
+            _ => input_tokens.clone(),
+        };
+        mapped.insert("input_tokens".to_string(), input_tokens);
+    }
+    if let Some(cached) = cached {
+        mapped.insert("cache_read_input_tokens".to_string(), json!(cached));
     }
     if let Some(output_tokens) = usage
         .get("completion_tokens")
@@ -532,6 +565,7 @@ fn append_openai_message_content_as_anthropic_blocks(
         }
         Some(Value::Array(parts)) => {
             for part in parts {
+                let count = blocks.len();
                 match part.get("type").and_then(Value::as_str) {
                     Some("text") => {
                         if let Some(text) = part.get("text").and_then(Value::as_str)
@@ -547,8 +581,35 @@ fn append_openai_message_content_as_anthropic_blocks(
                             blocks.push(json!({"type": "text", "text": text}));
                         }
                     }
+                    Some("image_url" | "input_image") => {
+                        if let Some(block) = convert_openai_image_part_to_anthropic_block(part) {
+                            blocks.push(block);
+                        }
+                    }
+                    Some("file") => {
+                        if let Some((media_type, data)) = part
+                            .pointer("/file/file_data")
+                            .and_then(Value::as_str)
+                            .and_then(parse_data_url)
+                            .filter(|(media_type, _)| media_type == "application/pdf")
+                        {
+                            let mut block = json!({
+                                "type": "document",
+                                "source": {"type": "base64", "media_type": media_type, "data": data}
+                            });
+                            if let Some(filename) = part.pointer("/file/filename") {
+                                block["title"] = filename.clone();
+                            }
+                            blocks.push(block);
+                        }
+                    }
                     _ => {}
                 }
+                if blocks.len() > count
+                    && let Some(block) = blocks.last_mut()
+                {
+                    copy_cache_control(part, block);
+                }
             }
         }
         _ => {}
@@ -672,6 +733,10 @@ fn convert_anthropic_user_message_to_openai_messages(content: &Value) -> Vec<Val
         return vec![json!({"role": "user", "content": content.clone()})];
     };
 
+    // OpenAI requires every `tool` message to directly follow the assistant
+    // turn that issued the calls, so tool results go first and the rest of
+    // the turn (text, images, images returned by tools) follows as one user
+    // message.
     let mut messages = Vec::new();
     let mut pending_parts = Vec::new();
 
@@ -680,17 +745,18 @@ fn convert_anthropic_user_message_to_openai_messages(content: &Value) -> Vec<Val
             block.get("type").and_then(Value::as_str),
             Some("tool_result")
         ) {
-            if let Some(content) =
-                parts_to_openai_message_content(std::mem::take(&mut pending_parts))
-            {
-                messages.push(json!({
-                    "role": "user",
-                    "content": content
-                }));
-            }
             if let Some(tool_message) = convert_anthropic_tool_result_block(block) {
                 messages.push(tool_message);
             }
+            pending_parts.extend(
+                block
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter(|inner| inner.get("type").and_then(Value::as_str) == Some("image"))
+                    .filter_map(convert_anthropic_image_block_to_openai_part),
+            );
             continue;
         }
 
@@ -710,6 +776,23 @@ fn convert_anthropic_user_message_to_openai_messages(content: &Value) -> Vec<Val
 }
 
 fn convert_anthropic_block_to_openai_part(block: &Value) -> Option<Value> {
+    let mut part = convert_anthropic_block_content_to_openai_part(block)?;
+    copy_cache_control(block, &mut part);
+    Some(part)
+}
+
+/// Carry a prompt caching marker (`cache_control`) over to a converted
+/// block or part; OpenAI-compatible upstreams that cache (OpenRouter, …)
+/// read it from content parts.
+fn copy_cache_control(from: &Value, to: &mut Value) {
+    if let Some(cache_control) = from.get("cache_control")
+        && let Some(map) = to.as_object_mut()
+    {
+        map.insert("cache_control".to_string(), cache_control.clone());
+    }
+}
+
+fn convert_anthropic_block_content_to_openai_part(block: &Value) -> Option<Value> {
     match block.get("type").and_then(Value::as_str) {
         Some("text") => block.get("text").and_then(Value::as_str).map(|text| {
             json!({
@@ -718,6 +801,19 @@ fn convert_anthropic_block_to_openai_part(block: &Value) -> Option<Value> {
             })
         }),
         Some("image") => convert_anthropic_image_block_to_openai_part(block),
+        Some("document") => {
+            let source = block.get("source")?;
+            if source.get("type").and_then(Value::as_str) != Some("base64") {
+                return None;
+            }
+            let media_type = source.get("media_type").and_then(Value::as_str)?;
+            let data = source.get("data").and_then(Value::as_str)?;
+            let mut file = json!({"file_data": format!("data:{media_type};base64,{data}")});
+            if let Some(title) = block.get("title") {
+                file["filename"] = title.clone();
+            }
+            Some(json!({"type": "file", "file": file}))
+        }
         _ => None,
     }
 }
@@ -747,6 +843,60 @@ fn convert_anthropic_image_block_to_openai_part(block: &Value) -> Option<Value>
     }
 }
 
+fn convert_openai_image_part_to_anthropic_block(part: &Value) -> Option<Value> {
+    let url = part
+        .pointer("/image_url/url")
+        .or_else(|| part.get("image_url"))
+        .and_then(Value::as_str)?;
+    if !url.starts_with("data:") {
+        return Some(json!({"type": "image", "source": {"type": "url", "url": url}}));
+    }
+    let omitted =
+        |reason: &str| Some(json!({"type": "text", "text": format!("[image omitted: {reason}]")}));
+    let Some((media_type, data)) = parse_data_url(url) else {
+        return omitted("not a base64 data URL");
+    };
+    if !ANTHROPIC_IMAGE_TYPES.contains(&media_type.as_str()) {
+        return omitted(&format!("unsupported type {media_type}"));
+    }
+    if data.len() / 4 * 3 > MAX_ANTHROPIC_IMAGE_BYTES {
+        return omitted("larger than 5 MB");
+    }
+    Some(json!({
+        "type": "image",
+        "source": {"type": "base64", "media_type": media_type, "data": data}
+    }))
+}
+
+/// Image types the Messages API accepts inline.
+const ANTHROPIC_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// The Messages API rejects inline images over 5 MB (decoded).
+const MAX_ANTHROPIC_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Media type and payload of a `data:<type>[;param…];base64,<data>` URL.
+/// The type is lowercased (`image/jpg` becomes `image/jpeg`) and whitespace
+/// inside the payload is removed; URL-encoded (non-base64) data is `None`.
+pub(crate) fn parse_data_url(url: &str) -> Option<(String, String)> {
+    let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mut params = meta.split(';');
+    let media_type = params
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if !params.any(|param| param.trim().eq_ignore_ascii_case("base64")) {
+        return None;
+    }
+    let media_type = match media_type.as_str() {
+        "" => "text/plain".to_string(),
+        "image/jpg" => "image/jpeg".to_string(),
+        _ => media_type,
+    };
+    let data = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    Some((media_type, data))
+}
+
 fn convert_anthropic_tool_use_block(block: &Value) -> Option<Value> {
     let id = block.get("id").and_then(Value::as_str)?;
     let name = block.get("name").and_then(Value::as_str)?;
@@ -807,7 +957,14 @@ fn anthropic_tool_result_content_to_string(content: Option<&Value>, is_error: bo
                     _ => None,
                 })
                 .collect();
-            if text_blocks.is_empty() {
+            let only_images = !blocks.is_empty()
+                && blocks
+                    .iter()
+                    .all(|block| block.get("type").and_then(Value::as_str) == Some("image"));
+            if only_images {
+                // Images travel in the following user message.
+                "(image)".to_string()
+            } else if text_blocks.is_empty() {
                 serde_json::to_string(content).unwrap_or_default()
             } else {
                 text_blocks.join("\n\n")
@@ -866,6 +1023,10 @@ fn parts_to_openai_message_content(parts: Vec<Value>) -> Option<Value> {
     if parts.is_empty() {
         return None;
     }
+    // Caching markers live on parts, so marked content stays an array.
+    if parts.iter().any(|part| part.get("cache_control").is_some()) {
+        return Some(Value::Array(parts));
+    }
 
     let text_parts: Option<Vec<&str>> = parts
         .iter()
@@ -890,6 +1051,484 @@ fn serialize_tool_arguments(input: &Value) -> String {
     }
 }
 
+/// Converts an OpenAI chat completion request body to Anthropic's Messages
+/// format, for channels that only expose an Anthropic endpoint.
+pub fn convert_openai_to_anthropic(body: &Bytes) -> Bytes {
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return body.clone();
+    };
+
+    let mut new_body = Map::new();
+    if let Some(model) = value.get("model") {
+        new_body.insert("model".to_string(), model.clone());
+    }
+
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    for message in value
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        let mut blocks = Vec::new();
+        let role = match role {
+            "system" | "developer" => {
+                append_openai_message_content_as_anthropic_blocks(
+                    message.get("content"),
+                    &mut system,
+                );
+                continue;
+            }
+            "assistant" => {
+                append_openai_message_content_as_anthropic_blocks(
+                    message.get("content"),
+                    &mut blocks,
+                );
+                append_openai_tool_calls_as_anthropic_blocks(
+                    message.get("tool_calls"),
+                    &mut blocks,
+                );
+                "assistant"
+            }
+            "tool" => {
+                // Screenshots and other images returned by tools stay blocks.
+                let mut content = Vec::new();
+                if matches!(message.get("content"), Some(Value::Array(_))) {
+                    append_openai_message_content_as_anthropic_blocks(
+                        message.get("content"),
+                        &mut content,
+                    );
+                }
+                let content = if content.iter().any(|block| block["type"] == "image") {
+                    Value::Array(content)
+                } else {
+                    Value::String(openai_message_text(message.get("content")))
+                };
+                blocks.push(json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.get("tool_call_id").cloned().unwrap_or(Value::Null),
+                    "content": content,
+                }));
+                "user"
+            }
+            _ => {
+                append_openai_message_content_as_anthropic_blocks(
+                    message.get("content"),
+                    &mut blocks,
+                );
+                "user"
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Anthropic requires alternating roles; fold consecutive turns
+        // (e.g. several tool results) into one message.
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => messages.push(json!({"role": role, "content": blocks})),
+        }
+    }
+
+    if !system.is_empty() {
+        new_body.insert("system".to_string(), Value::Array(system));
+    }
+    new_body.insert("messages".to_string(), Value::Array(messages));
+
+    let max_tokens = value
+        .get("max_completion_tokens")
+        .or_else(|| value.get("max_tokens"))
+        .cloned()
+        .unwrap_or_else(|| json!(DEFAULT_ANTHROPIC_MAX_TOKENS));
+    new_body.insert("max_tokens".to_string(), max_tokens);
+
+    for key in ["temperature", "top_p", "top_k", "stream"] {
+        if let Some(v) = value.get(key) {
+            new_body.insert(key.to_string(), v.clone());
+        }
+    }
+
+    match value.get("stop") {
+        Some(Value::String(stop)) => {
+            new_body.insert("stop_sequences".to_string(), json!([stop]));
+        }
+        Some(Value::Array(stops)) => {
+            new_body.insert("stop_sequences".to_string(), Value::Array(stops.clone()));
+        }
+        _ => {}
+    }
+
+    if let Some(tools) = value.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(convert_openai_tool_to_anthropic_tool)
+            .collect();
+        if !tools.is_empty() {
+            new_body.insert("tools".to_string(), Value::Array(tools));
+        }
+    }
+
+    let mut tool_choice = value.get("tool_choice").and_then(|choice| match choice {
+        Value::String(mode) => match mode.as_str() {
+            "auto" => Some(json!({"type": "auto"})),
+            "none" => Some(json!({"type": "none"})),
+            "required" => Some(json!({"type": "any"})),
+            _ => None,
+        },
+        Value::Object(_) => choice
+            .pointer("/function/name")
+            .map(|name| json!({"type": "tool", "name": name})),
+        _ => None,
+    });
+    if value.get("parallel_tool_calls").and_then(Value::as_bool) == Some(false)
+        && new_body.contains_key("tools")
+    {
+        let choice = tool_choice.get_or_insert_with(|| json!({"type": "auto"}));
+        choice["disable_parallel_tool_use"] = json!(true);
+    }
+    if let Some(tool_choice) = tool_choice {
+        new_body.insert("tool_choice".to_string(), tool_choice);
+    }
+
+    if let Some(user) = value.get("user").and_then(Value::as_str) {
+        new_body.insert("metadata".to_string(), json!({"user_id": user}));
+    }
+
+    match serde_json::to_vec(&new_body) {
+        Ok(vec) => Bytes::from(vec),
+        Err(_) => body.clone(),
+    }
+}
+
+/// Anthropic rejects requests without `max_tokens`; OpenAI callers often omit it.
+const DEFAULT_ANTHROPIC_MAX_TOKENS: u64 = 4096;
+
+fn convert_openai_tool_to_anthropic_tool(tool: &Value) -> Option<Value> {
+    let function = tool.get("function")?;
+    let mut mapped = json!({
+        "name": function.get("name")?,
+        "input_schema": function
+            .get("parameters")
+            .cloned()
+            .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+    });
+    if let Some(description) = function.get("description") {
+        mapped["description"] = description.clone();
+    }
+    Some(mapped)
+}
+
+fn openai_message_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Converts an Anthropic Messages JSON response body to an OpenAI chat completion.
+pub fn convert_anthropic_response_to_openai(body: Bytes) -> Bytes {
+    let Ok(val) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+
+    let converted = if let Some(error) = val.get("error") {
+        json!({
+            "error": {
+                "message": error.get("message").and_then(Value::as_str).unwrap_or("Unknown error"),
+                "type": error.get("type").and_then(Value::as_str).unwrap_or("api_error"),
+            }
+        })
+    } else {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in val
+            .get("content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            match block.get("type").and_then(Value::as_str) {
+                Some("text") => {
+                    text.push_str(block.get("text").and_then(Value::as_str).unwrap_or(""));
+                }
+                Some("tool_use") => tool_calls.push(json!({
+                    "id": block.get("id").cloned().unwrap_or(Value::Null),
+                    "type": "function",
+                    "function": {
+                        "name": block.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": serialize_tool_arguments(
+                            block.get("input").unwrap_or(&Value::Null),
+                        ),
+                    }
+                })),
+                _ => {}
+            }
+        }
+
+        let mut message = json!({"role": "assistant", "content": text});
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = Value::Array(tool_calls);
+        }
+        let mut response = json!({
+            "id": val.get("id").cloned().unwrap_or_else(|| json!("chatcmpl-anthropic")),
+            "object": "chat.completion",
+            "created": chrono::Utc::now().timestamp(),
+            "model": val.get("model").cloned().unwrap_or(Value::Null),
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": map_anthropic_stop_reason(
+                    val.get("stop_reason").and_then(Value::as_str),
+                ),
+            }],
+        });
+        if let Some(usage) = val.get("usage") {
+            response["usage"] = map_anthropic_usage_to_openai(usage);
+        }
+        response
+    };
+
+    match serde_json::to_vec(&converted) {
+        Ok(vec) => Bytes::from(vec),
+        Err(_) => body,
+    }
+}
+
+/// Converts an Anthropic Messages SSE stream to OpenAI `chat.completion.chunk`
+/// events.
+///
+/// Text deltas become `content` deltas, `tool_use` blocks become indexed
+/// `tool_calls` (arguments streamed from `input_json_delta`), and the stream
+/// ends with a usage-only chunk followed by `data: [DONE]`.
+pub fn convert_anthropic_stream_to_openai<S, E>(
+    stream: S,
+) -> impl Stream<Item = Result<Bytes, io::Error>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let state = (stream, Vec::new(), AnthropicStreamState::default(), false);
+    stream::unfold(
+        state,
+        |(mut stream, mut buffer, mut state, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                let mut output = String::new();
+                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    output.push_str(&state.convert_line(&String::from_utf8_lossy(&line)));
+                }
+                if !output.is_empty() {
+                    return Some((Ok(Bytes::from(output)), (stream, buffer, state, false)));
+                }
+
+                match stream.next().await {
+                    Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                    Some(Err(err)) => {
+                        return Some((Err(io::Error::other(err)), (stream, buffer, state, true)));
+                    }
+                    None => {
+                        let rest = String::from_utf8_lossy(&buffer).to_string();
+                        let mut output = state.convert_line(&rest);
+                        output.push_str(&state.finish());
+                        return Some((Ok(Bytes::from(output)), (stream, Vec::new(), state, true)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[derive(Default)]
+struct AnthropicStreamState {
+    id: String,
+    model: Value,
+    created: i64,
+    input_usage: Option<Value>,
+    output_tokens: Option<Value>,
+    /// Anthropic content block index -> OpenAI tool call index.
+    tool_indexes: HashMap<u64, usize>,
+    done: bool,
+}
+
+impl AnthropicStreamState {
+    fn convert_line(&mut self, line: &str) -> String {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return String::new();
+        };
+        let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+            return String::new();
+        };
+
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => {
+                let message = event.get("message").cloned().unwrap_or(Value::Null);
+                self.id = message
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or("chatcmpl-anthropic")
+                    .to_string();
+                self.model = message.get("model").cloned().unwrap_or(Value::Null);
+                self.created = chrono::Utc::now().timestamp();
+                self.input_usage = message.get("usage").cloned();
+                self.chunk(json!({"role": "assistant", "content": ""}), Value::Null)
+            }
+            Some("content_block_start") => {
+                let block = event.get("content_block").cloned().unwrap_or(Value::Null);
+                if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                    return String::new();
+                }
+                let tool_index = self.tool_indexes.len();
+                let block_index = event.get("index").and_then(Value::as_u64).unwrap_or(0);
+                self.tool_indexes.insert(block_index, tool_index);
+                self.chunk(
+                    json!({"tool_calls": [{
+                        "index": tool_index,
+                        "id": block.get("id").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": block.get("name").cloned().unwrap_or(Value::Null),
+                            "arguments": "",
+                        },
+                    }]}),
+                    Value::Null,
+                )
+            }
+            Some("content_block_delta") => {
+                let delta = event.get("delta").cloned().unwrap_or(Value::Null);
+                match delta.get("type").and_then(Value::as_str) {
+                    Some("text_delta") => {
+                        self.chunk(json!({"content": delta["text"]}), Value::Null)
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = event.get("index").and_then(Value::as_u64).unwrap_or(0);
+                        let Some(&tool_index) = self.tool_indexes.get(&block_index) else {
+                            return String::new();
+                        };
+                        let arguments = delta
+                            .get("partial_json")
+                            .and_then(Value::as_str)
+                            .unwrap_or("");
+                        if arguments.is_empty() {
+                            return String::new();
+                        }
+                        self.chunk(
+                            json!({"tool_calls": [{
+                                "index": tool_index,
+                                "function": {"arguments": arguments},
+                            }]}),
+                            Value::Null,
+                        )
+                    }
+                    _ => String::new(),
+                }
+            }
+            Some("message_delta") => {
+                if let Some(output_tokens) = event.pointer("/usage/output_tokens") {
+                    self.output_tokens = Some(output_tokens.clone());
+                }
+                let stop_reason = event.pointer("/delta/stop_reason").and_then(Value::as_str);
+                self.chunk(json!({}), json!(map_anthropic_stop_reason(stop_reason)))
+            }
+            Some("message_stop") => self.finish(),
+            Some("error") => {
+                let error = event.get("error").cloned().unwrap_or(Value::Null);
+                format!(
+                    "data: {}\n\n",
+                    json!({"error": {
+                        "message": error.get("message").and_then(Value::as_str).unwrap_or("Unknown error"),
+                        "type": error.get("type").and_then(Value::as_str).unwrap_or("api_error"),
+                    }})
+                )
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Value) -> String {
+        format!(
+            "data: {}\n\n",
+            json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": self.created,
+                "model": self.model,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+            })
+        )
+    }
+
+    fn finish(&mut self) -> String {
+        if self.done {
+            return String::new();
+        }
+        self.done = true;
+        let mut output = String::new();
+        if let Some(mut usage) = self.input_usage.clone() {
+            if let Some(output_tokens) = &self.output_tokens {
+                usage["output_tokens"] = output_tokens.clone();
+            }
+            output.push_str(&format!(
+                "data: {}\n\n",
+                json!({
+                    "id": self.id,
+                    "object": "chat.completion.chunk",
+                    "created": self.created,
+                    "model": self.model,
+                    "choices": [],
+                    "usage": map_anthropic_usage_to_openai(&usage),
+                })
+            ));
+        }
+        output.push_str("data: [DONE]\n\n");
+        output
+    }
+}
+
+fn map_anthropic_stop_reason(stop_reason: Option<&str>) -> &'static str {
+    match stop_reason {
+        Some("max_tokens") => "length",
+        Some("tool_use") => "tool_calls",
+        Some("refusal") => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// Anthropic reports cached prompt tokens separately from `input_tokens`;
+/// OpenAI folds them into `prompt_tokens`.
+fn map_anthropic_usage_to_openai(usage: &Value) -> Value {
+    let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+    let cache_read = count("cache_read_input_tokens");
+    let prompt_tokens = count("input_tokens") + cache_read + count("cache_creation_input_tokens");
+    let completion_tokens = count("output_tokens");
+    let mut mapped = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    });
+    if cache_read > 0 {
+        mapped["prompt_tokens_details"] = json!({"cached_tokens": cache_read});
+    }
+    mapped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1152,6 +1791,101 @@ mod tests {
         assert!(output.contains("event: message_stop"));
     }
 
+    #[test]
+    fn test_convert_openai_stream_to_anthropic_parallel_tool_calls_are_sequential() {
+        let chunk = |delta: serde_json::Value| {
+            Ok::<Bytes, std::io::Error>(Bytes::from(format!(
+                "data: {}\n\n",
+                json!({"id": "c", "model": "m", "choices": [{"index": 0, "delta": delta}]})
+            )))
+        };
+        let chunks = vec![
+            chunk(
+                json!({"tool_calls": [{"index": 0, "id": "call_a", "function": {"name": "read", "arguments": ""}}]}),
+            ),
+            chunk(json!({"tool_calls": [{"index": 0, "function": {"arguments": "{\"path\":"}}]})),
+            chunk(json!({"tool_calls": [{"index": 0, "function": {"arguments": "\"a\"}"}}]})),
+            chunk(
+                json!({"tool_calls": [{"index": 1, "id": "call_b", "function": {"name": "read", "arguments": "{}"}}]}),
+            ),
+            Ok(Bytes::from(
+                "data: {\"id\":\"c\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\ndata: [DONE]\n\n",
+            )),
+        ];
+
+        let output = futures::executor::block_on(
+            convert_openai_stream_to_anthropic(stream::iter(chunks))
+                .map(|item| String::from_utf8(item.unwrap().to_vec()).unwrap())
+                .collect::<String>(),
+        );
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        let sequence: Vec<(String, u64)> = events
+            .iter()
+            .filter(|event| event.get("index").is_some())
+            .map(|event| {
+                (
+                    event["type"].as_str().unwrap().to_string(),
+                    event["index"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        let expected = [
+            ("content_block_start", 0),
+            ("content_block_delta", 0),
+            ("content_block_delta", 0),
+            ("content_block_stop", 0),
+            ("content_block_start", 1),
+            ("content_block_delta", 1),
+            ("content_block_stop", 1),
+        ];
+        assert_eq!(
+            sequence,
+            expected
+                .iter()
+                .map(|(kind, index)| (kind.to_string(), *index))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_convert_anthropic_tool_results_precede_user_content() {
+        let anthropic_req = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "t1", "name": "screenshot", "input": {}},
+                    {"type": "tool_use", "id": "t2", "name": "ls", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": [
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBOR"}}
+                    ]},
+                    {"type": "text", "text": "see above"},
+                    {"type": "tool_result", "tool_use_id": "t2", "content": "a.txt"}
+                ]}
+            ]
+        });
+
+        let body = Bytes::from(serde_json::to_vec(&anthropic_req).unwrap());
+        let val: serde_json::Value =
+            serde_json::from_slice(&convert_anthropic_to_openai(&body)).unwrap();
+        let messages = val["messages"].as_array().unwrap();
+        let roles: Vec<&str> = messages
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["assistant", "tool", "tool", "user"]);
+        assert_eq!(messages[1]["content"], "(image)");
+        assert_eq!(messages[2]["content"], "a.txt");
+        let parts = messages[3]["content"].as_array().unwrap();
+        assert_eq!(parts[0]["image_url"]["url"], "data:image/png;base64,iVBOR");
+        assert_eq!(parts[1]["text"], "see above");
+    }
+
     #[test]
     fn test_convert_openai_stream_to_anthropic_text_without_finish_reason_chunk() {
         let chunks = vec![Ok::<Bytes, reqwest::Error>(Bytes::from(concat!(
@@ -1210,4 +1944,227 @@ mod tests {
         assert!(output.contains("\"stop_reason\":\"end_turn\""));
         assert!(output.contains("event: message_stop"));
     }
+
+    #[test]
+    fn converts_openai_request_to_anthropic_messages() {
+        let body = Bytes::from(
+            json!({
+                "model": "claude",
+                "messages": [
+                    {"role": "system", "content": "be brief"},
+                    {"role": "user", "content": [
+                        {"type": "text", "text": "what is this?"},
+                        {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                    ]},
+                    {"role": "assistant", "content": null, "tool_calls": [
+                        {"id": "call_1", "type": "function", "function": {"name": "a", "arguments": "{}"}},
+                        {"id": "call_2", "type": "function", "function": {"name": "b", "arguments": "{\"x\":1}"}}
+                    ]},
+                    {"role": "tool", "tool_call_id": "call_1", "content": "one"},
+                    {"role": "tool", "tool_call_id": "call_2", "content": "two"}
+                ],
+                "stop": "END",
+                "tool_choice": "required",
+                "parallel_tool_calls": false,
+                "tools": [{"type": "function", "function": {"name": "a"}}]
+            })
+            .to_string(),
+        );
+
+        let converted: Value = serde_json::from_slice(&convert_openai_to_anthropic(&body)).unwrap();
+        assert_eq!(converted["system"][0]["text"], "be brief");
+        assert_eq!(converted["max_tokens"], DEFAULT_ANTHROPIC_MAX_TOKENS);
+        assert_eq!(converted["stop_sequences"], json!(["END"]));
+        assert_eq!(
+            converted["tool_choice"],
+            json!({"type": "any", "disable_parallel_tool_use": true})
+        );
+        assert_eq!(converted["tools"][0]["input_schema"]["type"], "object");
+
+        let messages = converted["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][1]["source"]["media_type"],
+            "image/png"
+        );
+        assert_eq!(messages[1]["content"][1]["input"], json!({"x": 1}));
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(messages[2]["content"][1]["content"], "two");
+    }
+
+    #[test]
+    fn converts_openai_images_to_anthropic_blocks_within_limits() {
+        let big = "A".repeat(MAX_ANTHROPIC_IMAGE_BYTES / 3 * 4 + 8);
+        let body = Bytes::from(
+            json!({
+                "model": "claude",
+                "messages": [
+                    {"role": "user", "content": [
+                        {"type": "image_url", "image_url": {"url": "data:image/JPG;name=a.jpg;base64,AA\nAA", "detail": "high"}},
+                        {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}},
+                        {"type": "input_image", "image_url": "data:image/webp;base64,BBBB"},
+                        {"type": "image_url", "image_url": {"url": "data:image/svg+xml,%3Csvg%3E"}},
+                        {"type": "image_url", "image_url": {"url": "data:image/bmp;base64,CCCC"}},
+                        {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{big}")}}
+                    ]},
+                    {"role": "assistant", "content": null, "tool_calls": [
+                        {"id": "call_1", "type": "function", "function": {"name": "screenshot", "arguments": "{}"}}
+                    ]},
+                    {"role": "tool", "tool_call_id": "call_1", "content": [
+                        {"type": "text", "text": "done"},
+                        {"type": "image_url", "image_url": {"url": "data:image/png;base64,DDDD"}}
+                    ]}
+                ]
+            })
+            .to_string(),
+        );
+
+        let converted: Value = serde_json::from_slice(&convert_openai_to_anthropic(&body)).unwrap();
+        let blocks = converted["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(
+            blocks[0]["source"],
+            json!({"type": "base64", "media_type": "image/jpeg", "data": "AAAA"})
+        );
+        assert_eq!(
+            blocks[1]["source"],
+            json!({"type": "url", "url": "https://example.com/cat.png"})
+        );
+        assert_eq!(blocks[2]["source"]["media_type"], "image/webp");
+        assert_eq!(blocks[3]["text"], "[image omitted: not a base64 data URL]");
+        assert_eq!(
+            blocks[4]["text"],
+            "[image omitted: unsupported type image/bmp]"
+        );
+        assert_eq!(blocks[5]["text"], "[image omitted: larger than 5 MB]");
+
+        let tool_result = &converted["messages"][2]["content"][0];
+        assert_eq!(tool_result["type"], "tool_result");
+        assert_eq!(tool_result["content"][0]["text"], "done");
+        assert_eq!(tool_result["content"][1]["source"]["data"], "DDDD");
+    }
+
+    #[test]
+    fn converts_structured_system_prompts_with_cache_markers() {
+        let anthropic = Bytes::from(
+            json!({
+                "model": "gpt-4o",
+                "max_tokens": 16,
+                "system": [
+                    {"type": "text", "text": "You are terse."},
+                    {"type": "text", "text": "<long reference>", "cache_control": {"type": "ephemeral"}}
+                ],
+                "messages": [
+                    {"role": "user", "content": [
+                        {"type": "text", "text": "part one"},
+                        {"type": "text", "text": "part two"}
+                    ]}
+                ]
+            })
+            .to_string(),
+        );
+        let openai = convert_anthropic_to_openai(&anthropic);
+        let value: Value = serde_json::from_slice(&openai).unwrap();
+        assert_eq!(
+            value["messages"][0],
+            json!({"role": "system", "content": [
+                {"type": "text", "text": "You are terse."},
+                {"type": "text", "text": "<long reference>", "cache_control": {"type": "ephemeral"}}
+            ]})
+        );
+        assert_eq!(value["messages"][1]["content"], "part one\n\npart two");
+
+        let back: Value = serde_json::from_slice(&convert_openai_to_anthropic(&openai)).unwrap();
+        assert_eq!(
+            back["system"],
+            json!([
+                {"type": "text", "text": "You are terse."},
+                {"type": "text", "text": "<long reference>", "cache_control": {"type": "ephemeral"}}
+            ])
+        );
+    }
+
+    #[test]
+    fn converts_pdf_documents_between_protocols() {
+        let anthropic = Bytes::from(
+            json!({
+                "model": "gpt-4o",
+                "max_tokens": 16,
+                "messages": [{"role": "user", "content": [
+                    {"type": "document", "title": "spec.pdf", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBE"}},
+                    {"type": "text", "text": "summarize"}
+                ]}]
+            })
+            .to_string(),
+        );
+        let openai = convert_anthropic_to_openai(&anthropic);
+        let value: Value = serde_json::from_slice(&openai).unwrap();
+        assert_eq!(
+            value["messages"][0]["content"][0],
+            json!({"type": "file", "file": {"filename": "spec.pdf", "file_data": "data:application/pdf;base64,JVBE"}})
+        );
+
+        let back: Value = serde_json::from_slice(&convert_openai_to_anthropic(&openai)).unwrap();
+        assert_eq!(
+            back["messages"][0]["content"][0],
+            json!({"type": "document", "title": "spec.pdf", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBE"}})
+        );
+    }
+
+    #[test]
+    fn converts_anthropic_stream_to_openai_chunks() {
+        let chunks = vec![
+            Ok::<Bytes, io::Error>(Bytes::from(concat!(
+                "event: message_start\n",
+                "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude\",\"usage\":{\"input_tokens\":9,\"cache_read_input_tokens\":2,\"output_tokens\":1}}}\n\n",
+                "event: content_block_start\n",
+                "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+                "event: content_block_delta\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n",
+            ))),
+            Ok(Bytes::from(concat!(
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n",
+                "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"lookup\",\"input\":{}}}\n\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"q\\\":1}\"}}\n\n",
+                "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":5}}\n\n",
+                "data: {\"type\":\"message_stop\"}\n\n",
+            ))),
+        ];
+
+        let converted = convert_anthropic_stream_to_openai(stream::iter(chunks));
+        let output = futures::executor::block_on(async move {
+            converted
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .map(|item| String::from_utf8(item.unwrap().to_vec()).unwrap())
+                .collect::<String>()
+        });
+
+        let events: Vec<Value> = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(events[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(events[1]["choices"][0]["delta"]["content"], "Hel");
+        assert_eq!(
+            events[3]["choices"][0]["delta"]["tool_calls"][0]["id"],
+            "toolu_1"
+        );
+        assert_eq!(
+            events[4]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{\"q\":1}"
+        );
+        assert_eq!(events[5]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(events[6]["usage"]["prompt_tokens"], 11);
+        assert_eq!(events[6]["usage"]["completion_tokens"], 5);
+        assert_eq!(
+            events[6]["usage"]["prompt_tokens_details"]["cached_tokens"],
+            2
+        );
+        assert!(output.ends_with("data: [DONE]\n\n"));
+        assert_eq!(output.matches("[DONE]").count(), 1);
+    }
 }
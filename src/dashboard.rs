@@ -0,0 +1,36 @@
+//! Built-in operations dashboard served at `/dashboard`.
+//!
+//! A single static page, compiled into the binary, that polls the
+//! `/api/stats/*` endpoints and `/admin/channels/health` to show live request
+//! rates, latency, channel health, per-team spend and recent errors. Unlike
+//! the Control Plane it needs no web build; the page asks for the global API
+//! key and sends it with every call, so the assets themselves are public.
+
+use axum::body::Body;
+use axum::http::{StatusCode, header};
+use axum::response::Response;
+
+const INDEX_HTML: &str = include_str!("dashboard/index.html");
+const APP_JS: &str = include_str!("dashboard/app.js");
+const APP_CSS: &str = include_str!("dashboard/app.css");
+
+/// The dashboard asset at `path` (relative to `/dashboard/`).
+pub fn asset(path: &str) -> Response<Body> {
+    let (body, content_type) = match path {
+        "" | "index.html" => (INDEX_HTML, "text/html; charset=utf-8"),
+        "app.js" => (APP_JS, "text/javascript; charset=utf-8"),
+        "app.css" => (APP_CSS, "text/css; charset=utf-8"),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from("Not found"))
+                .unwrap();
+        }
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from(body))
+        .unwrap()
+}
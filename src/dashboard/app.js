@@ -0,0 +1,224 @@
+// Polls the gateway's stats endpoints and renders them. The global API key
+// is kept in localStorage and sent as a bearer token.
+(function () {
+    "use strict";
+
+    const POLL_MS = 5000;
+    const KEY_STORAGE = "apex.dashboard.key";
+    const LIVE_ROWS = 50;
+    let timer = null;
+    let live = null;
+
+    const $ = (id) => document.getElementById(id);
+
+    function apiKey() {
+        return localStorage.getItem(KEY_STORAGE) || "";
+    }
+
+    function authHeaders() {
+        return apiKey() ? { Authorization: "Bearer " + apiKey() } : {};
+    }
+
+    async function get(path) {
+        const response = await fetch(path, { headers: authHeaders() });
+        if (!response.ok) {
+            throw new Error(path + ": HTTP " + response.status);
+        }
+        return response.json();
+    }
+
+    function text(value) {
+        return value === null || value === undefined ? "" : String(value);
+    }
+
+    function ms(value) {
+        return value === null || value === undefined ? "-" : Math.round(value) + " ms";
+    }
+
+    function percent(value) {
+        return (value * 100).toFixed(1) + "%";
+    }
+
+    function fillTable(id, rows, columns) {
+        const body = $(id);
+        body.replaceChildren();
+        if (rows.length === 0) {
+            const row = body.insertRow();
+            const cell = row.insertCell();
+            cell.colSpan = columns.length;
+            cell.className = "empty";
+            cell.textContent = "Nothing yet";
+            return;
+        }
+        for (const item of rows) {
+            const row = body.insertRow();
+            for (const column of columns) {
+                const cell = row.insertCell();
+                cell.textContent = text(column(item));
+            }
+        }
+    }
+
+    function renderTraffic(traffic) {
+        $("rps").textContent = traffic.requests_per_sec.toFixed(2);
+        $("error-rate").textContent = percent(traffic.error_rate);
+        $("in-flight").textContent = traffic.in_flight;
+        $("p50").textContent = ms(traffic.latency_ms.p50);
+        $("p95").textContent = ms(traffic.latency_ms.p95);
+        $("p99").textContent = ms(traffic.latency_ms.p99);
+    }
+
+    function renderSeries(traffic) {
+        const bars = $("series");
+        bars.replaceChildren();
+        const peak = Math.max(1, ...traffic.series.map((m) => m.requests));
+        for (const minute of traffic.series) {
+            const bar = document.createElement("div");
+            bar.className = "bar";
+            bar.style.height = (minute.requests / peak) * 100 + "%";
+            bar.title = minute.minute + ": " + minute.requests + " requests, " + minute.errors + " errors";
+            const errors = document.createElement("div");
+            errors.className = "bar-errors";
+            errors.style.height = minute.requests ? (minute.errors / minute.requests) * 100 + "%" : "0";
+            bar.appendChild(errors);
+            bars.appendChild(bar);
+        }
+    }
+
+    async function refresh() {
+        try {
+            const [traffic, hour, health, spend, errors] = await Promise.all([
+                get("/api/stats/traffic?window=300"),
+                get("/api/stats/traffic?window=3600"),
+                get("/admin/channels/health"),
+                get("/api/stats/spend"),
+                get("/api/stats/errors?limit=20"),
+            ]);
+            renderTraffic(traffic);
+            renderSeries(hour);
+            fillTable("channels", health.data, [
+                (c) => c.channel,
+                (c) => (c.ejected ? "ejected" : c.state),
+                (c) => c.rps.toFixed(2),
+                (c) => percent(c.error_rate),
+                (c) => c.consecutive_failures,
+                (c) => c.last_failure,
+            ]);
+            fillTable("spend", spend.data, [
+                (t) => t.key,
+                (t) => t.requests,
+                (t) => t.input_tokens,
+                (t) => t.output_tokens,
+                (t) => "$" + t.cost.toFixed(4),
+            ]);
+            fillTable("errors", errors.data, [
+                (e) => e.timestamp,
+                (e) => e.team_id,
+                (e) => e.router,
+                (e) => e.channel,
+                (e) => e.model,
+                (e) => e.status_code,
+                (e) => e.error_message,
+            ]);
+            $("error").hidden = true;
+            $("updated").textContent = "Updated " + new Date().toLocaleTimeString();
+        } catch (err) {
+            $("error").textContent = err.message;
+            $("error").hidden = false;
+        }
+    }
+
+    function addLiveRow(event) {
+        const body = $("live");
+        const placeholder = body.querySelector("td.empty");
+        if (placeholder) {
+            placeholder.parentElement.remove();
+        }
+        const row = body.insertRow(0);
+        for (const value of [
+            event.timestamp,
+            event.team_id,
+            event.router,
+            event.channel,
+            event.model,
+            event.status + (event.status_code ? " " + event.status_code : ""),
+            ms(event.latency_ms),
+            event.input_tokens + " / " + event.output_tokens,
+        ]) {
+            row.insertCell().textContent = text(value);
+        }
+        if (event.status.endsWith("error")) {
+            row.className = "failed";
+        }
+        while (body.rows.length > LIVE_ROWS) {
+            body.deleteRow(-1);
+        }
+    }
+
+    // `/admin/events` is server-sent events; EventSource cannot send the
+    // Authorization header, so the stream is read with fetch.
+    async function streamEvents(signal) {
+        const response = await fetch("/admin/events", { headers: authHeaders(), signal });
+        if (!response.ok) {
+            throw new Error("/admin/events: HTTP " + response.status);
+        }
+        const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+        let buffer = "";
+        for (;;) {
+            const { value, done } = await reader.read();
+            if (done) {
+                return;
+            }
+            buffer += value;
+            let end;
+            while ((end = buffer.indexOf("\n\n")) >= 0) {
+                const frame = buffer.slice(0, end);
+                buffer = buffer.slice(end + 2);
+                const lines = frame.split("\n");
+                const data = lines
+                    .filter((line) => line.startsWith("data:"))
+                    .map((line) => line.slice(5).trim())
+                    .join("\n");
+                if (lines.includes("event: request") && data) {
+                    addLiveRow(JSON.parse(data));
+                }
+            }
+        }
+    }
+
+    function startLive() {
+        if (live) {
+            live.abort();
+        }
+        const controller = new AbortController();
+        live = controller;
+        fillTable("live", [], new Array(8).fill(() => ""));
+        (async () => {
+            while (!controller.signal.aborted) {
+                try {
+                    await streamEvents(controller.signal);
+                } catch (err) {
+                    if (controller.signal.aborted) {
+                        return;
+                    }
+                }
+                await new Promise((resolve) => setTimeout(resolve, POLL_MS));
+            }
+        })();
+    }
+
+    function start() {
+        clearInterval(timer);
+        refresh();
+        timer = setInterval(refresh, POLL_MS);
+        startLive();
+    }
+
+    $("key").value = apiKey();
+    $("auth").addEventListener("submit", (event) => {
+        event.preventDefault();
+        localStorage.setItem(KEY_STORAGE, $("key").value.trim());
+        start();
+    });
+    start();
+})();
@@ -1,10 +1,12 @@
+use crate::middleware::ratelimit::BucketSnapshot;
 use anyhow::Result;
 use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::Duration;
-use tracing::info;
+use tracing::debug;
 
 pub struct Database {
     /// Single writer connection: all INSERT/DELETE and the gemini replay
@@ -16,6 +18,75 @@ pub struct Database {
     read_conn: Mutex<Connection>,
 }
 
+/// One `usage_records` row as written locally, and as shipped to (or
+/// ingested by) a usage collector.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageEntry {
+    pub timestamp: String,
+    pub request_id: Option<String>,
+    pub team_id: String,
+    pub router: String,
+    pub matched_rule: Option<String>,
+    pub channel: String,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub images: i64,
+    pub cost: Option<f64>,
+    pub latency_ms: Option<f64>,
+    pub fallback_triggered: bool,
+    pub status: String,
+    pub status_code: Option<i64>,
+    pub error_message: Option<String>,
+    pub provider_trace_id: Option<String>,
+    pub provider_error_body: Option<String>,
+    pub client: Option<String>,
+    pub user_agent: Option<String>,
+    pub end_user: Option<String>,
+    /// Gateway instance that served the request (`usage_shipping.instance`).
+    pub instance: Option<String>,
+    /// Prompt tokens read from / written to the provider's prompt cache.
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    /// Sent to the client as a stream.
+    pub stream: bool,
+    /// Upstream stop reason (`stop`, `length`, `end_turn`, …).
+    pub finish_reason: Option<String>,
+}
+
+impl UsageEntry {
+    /// A record stamped now, with no tokens and no status yet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        request_id: Option<&str>,
+        team_id: &str,
+        router: &str,
+        matched_rule: Option<&str>,
+        channel: &str,
+        model: &str,
+        latency_ms: Option<f64>,
+        fallback_triggered: bool,
+        client_info: &crate::utils::ClientInfo,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            request_id: request_id.map(str::to_string),
+            team_id: team_id.to_string(),
+            router: router.to_string(),
+            matched_rule: matched_rule.map(str::to_string),
+            channel: channel.to_string(),
+            model: model.to_string(),
+            latency_ms,
+            fallback_triggered,
+            client: client_info.client.clone(),
+            user_agent: client_info.user_agent.clone(),
+            end_user: client_info.end_user.clone(),
+            ..Self::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct UsageRecordQuery {
     pub team_id: Option<String>,
@@ -28,6 +99,23 @@ pub struct UsageRecordQuery {
     pub end_time: Option<String>,
 }
 
+impl UsageRecordQuery {
+    /// Timestamp filters compare against full timestamps; widen a bare
+    /// `end_time` date so `2026-01-31` covers the whole day.
+    pub fn with_inclusive_end_date(self) -> Self {
+        Self {
+            end_time: self.end_time.map(|end| {
+                if end.len() == 10 {
+                    format!("{end} 23:59:59")
+                } else {
+                    end
+                }
+            }),
+            ..self
+        }
+    }
+}
+
 impl Database {
     pub fn new(data_dir: Option<String>) -> Result<Self> {
         let dir = if let Some(d) = data_dir {
@@ -55,7 +143,7 @@ impl Database {
         }
 
         let db_path = dir.join("apex.db");
-        info!("Database initialized at: {:?}", db_path);
+        debug!("Database initialized at: {:?}", db_path);
 
         let conn = Connection::open(&db_path)?;
 
@@ -159,6 +247,59 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_metrics_fallbacks_timestamp ON metrics_fallbacks(timestamp);
             CREATE INDEX IF NOT EXISTS idx_metrics_latency_timestamp ON metrics_latency(timestamp);
             CREATE INDEX IF NOT EXISTS idx_gemini_replay_expires_at ON gemini_replay_turns(expires_at);
+
+            CREATE TABLE IF NOT EXISTS message_batches (
+                batch_id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                team_id TEXT NOT NULL,
+                router TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                model TEXT NOT NULL,
+                usage_logged INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_message_batches_team ON message_batches(team_id, timestamp);
+
+            CREATE TABLE IF NOT EXISTS access_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                request_id TEXT,
+                team_id TEXT NOT NULL,
+                api_key TEXT,
+                provider TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                route TEXT NOT NULL,
+                success INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_access_audit_timestamp ON access_audit(timestamp);
+
+            CREATE TABLE IF NOT EXISTS pii_redactions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                request_id TEXT,
+                team_id TEXT NOT NULL,
+                rule TEXT NOT NULL,
+                action TEXT NOT NULL,
+                matches INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_pii_redactions_team ON pii_redactions(team_id, timestamp);
+
+            CREATE TABLE IF NOT EXISTS key_usage (
+                key_fingerprint TEXT PRIMARY KEY,
+                team_id TEXT NOT NULL,
+                request_count INTEGER NOT NULL DEFAULT 0,
+                first_used TEXT NOT NULL,
+                last_used TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS rate_limit_state (
+                bucket_key TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                tokens REAL NOT NULL,
+                capacity REAL NOT NULL,
+                refill_rate REAL NOT NULL,
+                saved_at INTEGER NOT NULL,
+                PRIMARY KEY (bucket_key, kind)
+            );
             ",
         )?;
 
@@ -192,6 +333,39 @@ impl Database {
         // Client/tool attribution (Claude Code, Codex, SDKs, …) from request headers.
         let _ = conn.execute("ALTER TABLE usage_records ADD COLUMN client TEXT", []);
         let _ = conn.execute("ALTER TABLE usage_records ADD COLUMN user_agent TEXT", []);
+        // End user behind a shared team key (OpenAI `user` / Anthropic `metadata.user_id`).
+        let _ = conn.execute("ALTER TABLE usage_records ADD COLUMN end_user TEXT", []);
+        // Dollar cost from the `pricing` table; NULL when the model had no price.
+        let _ = conn.execute("ALTER TABLE usage_records ADD COLUMN cost REAL", []);
+        // Images returned by `/v1/images/*` requests.
+        let _ = conn.execute(
+            "ALTER TABLE usage_records ADD COLUMN images INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        // Fleet instance, for records shipped to or collected from other gateways.
+        let _ = conn.execute("ALTER TABLE usage_records ADD COLUMN instance TEXT", []);
+        // Prompt caching token counts.
+        let _ = conn.execute(
+            "ALTER TABLE usage_records ADD COLUMN cache_read_tokens INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE usage_records ADD COLUMN cache_creation_tokens INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        // Response shape, for joining usage with access logs.
+        let _ = conn.execute(
+            "ALTER TABLE usage_records ADD COLUMN stream INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE usage_records ADD COLUMN finish_reason TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_end_user ON usage_records(end_user)",
+            [],
+        );
         let _ = conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_usage_client ON usage_records(client)",
             [],
@@ -231,6 +405,7 @@ impl Database {
             "metrics_errors",
             "metrics_fallbacks",
             "metrics_latency",
+            "pii_redactions",
         ] {
             let removed = conn.execute(
                 &format!("DELETE FROM {table} WHERE timestamp < ?1"),
@@ -247,7 +422,7 @@ impl Database {
         Ok(deleted)
     }
 
-    #[allow(clippy::too_many_arguments)]
+    #[allow(dead_code, clippy::too_many_arguments)]
     pub fn log_usage(
         &self,
         request_id: Option<&str>,
@@ -258,6 +433,8 @@ impl Database {
         model: &str,
         input_tokens: i64,
         output_tokens: i64,
+        images: i64,
+        cost: Option<f64>,
         latency_ms: Option<f64>,
         fallback_triggered: bool,
         status: &str,
@@ -267,38 +444,366 @@ impl Database {
         provider_error_body: Option<&str>,
         client: Option<&str>,
         user_agent: Option<&str>,
+        end_user: Option<&str>,
     ) {
+        let _ = self.insert_usage(&[UsageEntry {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            request_id: request_id.map(str::to_string),
+            team_id: team_id.to_string(),
+            router: router.to_string(),
+            matched_rule: matched_rule.map(str::to_string),
+            channel: channel.to_string(),
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            images,
+            cost,
+            latency_ms,
+            fallback_triggered,
+            status: status.to_string(),
+            status_code,
+            error_message: error_message.map(str::to_string),
+            provider_trace_id: provider_trace_id.map(str::to_string),
+            provider_error_body: provider_error_body.map(str::to_string),
+            client: client.map(str::to_string),
+            user_agent: user_agent.map(str::to_string),
+            end_user: end_user.map(str::to_string),
+            instance: None,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            stream: false,
+            finish_reason: None,
+        }]);
+    }
+
+    /// Write usage rows in one transaction (local logging and collector
+    /// ingestion).
+    pub fn insert_usage(&self, entries: &[UsageEntry]) -> Result<()> {
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO usage_records (timestamp, request_id, team_id, router, matched_rule, channel, model, input_tokens, output_tokens, latency_ms, fallback_triggered, status, status_code, error_message, provider_trace_id, provider_error_body, client, user_agent, end_user, cost, images, instance, cache_read_tokens, cache_creation_tokens, stream, finish_reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26)",
+            )?;
+            for entry in entries {
+                stmt.execute(params![
+                    entry.timestamp,
+                    entry.request_id,
+                    entry.team_id,
+                    entry.router,
+                    entry.matched_rule,
+                    entry.channel,
+                    entry.model.to_lowercase(),
+                    entry.input_tokens,
+                    entry.output_tokens,
+                    entry.latency_ms,
+                    if entry.fallback_triggered { 1 } else { 0 },
+                    entry.status,
+                    entry.status_code,
+                    entry.error_message,
+                    entry.provider_trace_id,
+                    entry.provider_error_body,
+                    entry.client,
+                    entry.user_agent,
+                    entry.end_user,
+                    entry.cost,
+                    entry.images,
+                    entry.instance,
+                    entry.cache_read_tokens,
+                    entry.cache_creation_tokens,
+                    if entry.stream { 1 } else { 0 },
+                    entry.finish_reason,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remember which channel served an Anthropic message batch so follow-up
+    /// calls (retrieve/results/cancel) reach the same upstream account.
+    pub fn record_message_batch(
+        &self,
+        batch_id: &str,
+        team_id: &str,
+        router: &str,
+        channel: &str,
+        model: &str,
+    ) -> Result<()> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO message_batches (batch_id, timestamp, team_id, router, channel, model, usage_logged)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)",
+            params![batch_id, timestamp, team_id, router, channel, model],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_message_batch(&self, batch_id: &str) -> Result<Option<MessageBatchRecord>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let record = conn
+            .query_row(
+                "SELECT batch_id, team_id, router, channel, model FROM message_batches WHERE batch_id = ?1",
+                params![batch_id],
+                Self::map_message_batch,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Most recently created batch for a team; used to pick the channel for
+    /// `GET /v1/messages/batches` (listing is per upstream account).
+    pub fn latest_message_batch_for_team(
+        &self,
+        team_id: &str,
+    ) -> Result<Option<MessageBatchRecord>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let record = conn
+            .query_row(
+                "SELECT batch_id, team_id, router, channel, model FROM message_batches \
+                 WHERE team_id = ?1 ORDER BY timestamp DESC, rowid DESC LIMIT 1",
+                params![team_id],
+                Self::map_message_batch,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Ids of the team's batches on one channel; `GET /v1/messages/batches`
+    /// shows only these out of the upstream account's list.
+    pub fn message_batch_ids_for_team(
+        &self,
+        team_id: &str,
+        channel: &str,
+    ) -> Result<std::collections::HashSet<String>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn
+            .prepare("SELECT batch_id FROM message_batches WHERE team_id = ?1 AND channel = ?2")?;
+        let ids = stmt
+            .query_map(params![team_id, channel], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    /// Flip the batch's `usage_logged` flag. Returns `true` only for the first
+    /// caller, so results downloaded repeatedly are accounted once.
+    pub fn mark_message_batch_usage_logged(&self, batch_id: &str) -> Result<bool> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let changed = conn.execute(
+            "UPDATE message_batches SET usage_logged = 1 WHERE batch_id = ?1 AND usage_logged = 0",
+            params![batch_id],
+        )?;
+        Ok(changed == 1)
+    }
+
+    fn map_message_batch(row: &rusqlite::Row<'_>) -> rusqlite::Result<MessageBatchRecord> {
+        Ok(MessageBatchRecord {
+            batch_id: row.get(0)?,
+            team_id: row.get(1)?,
+            router: row.get(2)?,
+            channel: row.get(3)?,
+            model: row.get(4)?,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_access_audit(
+        &self,
+        timestamp: &str,
+        request_id: Option<&str>,
+        team_id: &str,
+        api_key: Option<&str>,
+        provider: &str,
+        channel: &str,
+        route: &str,
+        success: bool,
+    ) -> Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT INTO access_audit (timestamp, request_id, team_id, api_key, provider, channel, route, success)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![timestamp, request_id, team_id, api_key, provider, channel, route, success],
+        )?;
+        Ok(())
+    }
+
+    /// Delete audit events older than `retention_days` (`0` = keep forever).
+    pub fn cleanup_access_audit(&self, retention_days: u64) -> Result<u64> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = (chrono::Local::now() - chrono::Duration::days(retention_days as i64))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let removed = conn.execute(
+            "DELETE FROM access_audit WHERE timestamp < ?1",
+            params![cutoff],
+        )?;
+        Ok(removed as u64)
+    }
+
+    /// Record the PII rules that fired for one request as
+    /// `(rule, action, matches)`; the matched text is never stored.
+    pub fn log_pii_redactions(
+        &self,
+        request_id: Option<&str>,
+        team_id: &str,
+        redactions: &[(String, &str, i64)],
+    ) -> Result<()> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        for (rule, action, matches) in redactions {
+            conn.execute(
+                "INSERT INTO pii_redactions (timestamp, request_id, team_id, rule, action, matches)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![timestamp, request_id, team_id, rule, action, matches],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// A team's PII redaction records between two optional dates (inclusive),
+    /// newest first.
+    pub fn get_pii_redactions(
+        &self,
+        team_id: &str,
+        start_time: Option<&str>,
+        end_time: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<PiiRedaction>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, request_id, rule, action, matches FROM pii_redactions
+             WHERE team_id = ?1
+               AND (?2 IS NULL OR date(timestamp) >= date(?2))
+               AND (?3 IS NULL OR date(timestamp) <= date(?3))
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?4",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![team_id, start_time, end_time, limit as i64],
+                |row| {
+                    Ok(PiiRedaction {
+                        timestamp: row.get(0)?,
+                        request_id: row.get(1)?,
+                        rule: row.get(2)?,
+                        action: row.get(3)?,
+                        matches: row.get(4)?,
+                    })
+                },
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(rows)
+    }
+
+    /// Bump the request counter and last-used time for an API key.
+    pub fn touch_key_usage(&self, key_fingerprint: &str, team_id: &str) {
         let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let model_lower = model.to_lowercase();
 
         if let Ok(conn) = self.conn.lock() {
             let _ = conn.execute(
-                "INSERT INTO usage_records (timestamp, request_id, team_id, router, matched_rule, channel, model, input_tokens, output_tokens, latency_ms, fallback_triggered, status, status_code, error_message, provider_trace_id, provider_error_body, client, user_agent)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
-                params![
-                    timestamp,
-                    request_id,
-                    team_id,
-                    router,
-                    matched_rule,
-                    channel,
-                    model_lower,
-                    input_tokens,
-                    output_tokens,
-                    latency_ms,
-                    if fallback_triggered { 1 } else { 0 },
-                    status,
-                    status_code,
-                    error_message,
-                    provider_trace_id,
-                    provider_error_body,
-                    client,
-                    user_agent,
-                ],
+                "INSERT INTO key_usage (key_fingerprint, team_id, request_count, first_used, last_used)
+                 VALUES (?1, ?2, 1, ?3, ?3)
+                 ON CONFLICT(key_fingerprint) DO UPDATE SET
+                    team_id = excluded.team_id,
+                    request_count = request_count + 1,
+                    last_used = excluded.last_used",
+                params![key_fingerprint, team_id, timestamp],
             );
         }
     }
 
+    /// Replace the persisted rate limiter buckets with `buckets`.
+    pub fn save_rate_limit_state(&self, buckets: &[BucketSnapshot]) -> Result<()> {
+        let saved_at = chrono::Utc::now().timestamp();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM rate_limit_state", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO rate_limit_state (bucket_key, kind, tokens, capacity, refill_rate, saved_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for bucket in buckets {
+                stmt.execute(params![
+                    bucket.key,
+                    bucket.kind,
+                    bucket.tokens,
+                    bucket.capacity,
+                    bucket.refill_rate,
+                    saved_at
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Persisted rate limiter buckets with their age.
+    pub fn load_rate_limit_state(&self) -> Result<Vec<(BucketSnapshot, Duration)>> {
+        let now = chrono::Utc::now().timestamp();
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT bucket_key, kind, tokens, capacity, refill_rate, saved_at FROM rate_limit_state",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let saved_at: i64 = row.get(5)?;
+            Ok((
+                BucketSnapshot {
+                    key: row.get(0)?,
+                    kind: row.get(1)?,
+                    tokens: row.get(2)?,
+                    capacity: row.get(3)?,
+                    refill_rate: row.get(4)?,
+                },
+                Duration::from_secs(now.saturating_sub(saved_at).max(0) as u64),
+            ))
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn get_key_usage(&self, key_fingerprint: &str) -> Result<Option<KeyUsage>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let usage = conn
+            .query_row(
+                "SELECT team_id, request_count, first_used, last_used FROM key_usage WHERE key_fingerprint = ?1",
+                params![key_fingerprint],
+                |row| {
+                    Ok(KeyUsage {
+                        team_id: row.get(0)?,
+                        request_count: row.get(1)?,
+                        first_used: row.get(2)?,
+                        last_used: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(usage)
+    }
+
     pub fn log_request(&self, route: &str, router: &str) {
         let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
@@ -425,7 +930,7 @@ impl Database {
 
     /// Column list for `usage_records` reads, kept in lock-step with
     /// [`Self::map_usage_record`]'s positional `row.get(N)` indices.
-    const USAGE_RECORD_COLUMNS: &'static str = "id, timestamp, request_id, team_id, router, matched_rule, channel, model, input_tokens, output_tokens, latency_ms, fallback_triggered, status, status_code, error_message, provider_trace_id, provider_error_body, client, user_agent";
+    const USAGE_RECORD_COLUMNS: &'static str = "id, timestamp, request_id, team_id, router, matched_rule, channel, model, input_tokens, output_tokens, latency_ms, fallback_triggered, status, status_code, error_message, provider_trace_id, provider_error_body, client, user_agent, end_user, cost, images, cache_read_tokens, cache_creation_tokens, stream, finish_reason";
 
     /// Map one `usage_records` row (selected via [`Self::USAGE_RECORD_COLUMNS`])
     /// into a [`UsageRecord`]. Single source of truth for column ordering.
@@ -452,6 +957,13 @@ impl Database {
             provider_error_body: row.get(16)?,
             client: row.get(17)?,
             user_agent: row.get(18)?,
+            end_user: row.get(19)?,
+            cost: row.get(20)?,
+            images: row.get(21)?,
+            cache_read_tokens: row.get(22)?,
+            cache_creation_tokens: row.get(23)?,
+            stream: row.get::<_, i64>(24)? > 0,
+            finish_reason: row.get(25)?,
         })
     }
 
@@ -678,6 +1190,156 @@ impl Database {
         Ok(agg)
     }
 
+    /// Requests per minute since `since`, and the latencies behind them, for
+    /// the dashboard's traffic panel.
+    pub fn get_traffic(&self, since: &str) -> Result<Traffic> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 16), \
+               COUNT(*), \
+               COALESCE(SUM(CASE WHEN status IN ('error', 'fallback_error') THEN 1 ELSE 0 END), 0) \
+             FROM usage_records WHERE timestamp >= ?1 \
+             GROUP BY 1 ORDER BY 1",
+        )?;
+        let minutes = stmt
+            .query_map(params![since], |row| {
+                Ok(TrafficMinute {
+                    minute: row.get(0)?,
+                    requests: row.get(1)?,
+                    errors: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut stmt = conn.prepare(
+            "SELECT latency_ms FROM usage_records \
+             WHERE timestamp >= ?1 AND latency_ms IS NOT NULL ORDER BY latency_ms",
+        )?;
+        let latencies_ms = stmt
+            .query_map(params![since], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<f64>>>()?;
+        Ok(Traffic {
+            minutes,
+            latencies_ms,
+        })
+    }
+
+    /// The latest failed requests, newest first.
+    pub fn get_recent_errors(&self, limit: i64) -> Result<Vec<RecentError>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, request_id, team_id, router, channel, model, status_code, error_message \
+             FROM usage_records WHERE status IN ('error', 'fallback_error') \
+             ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit], |row| {
+                Ok(RecentError {
+                    timestamp: row.get(0)?,
+                    request_id: row.get(1)?,
+                    team_id: row.get(2)?,
+                    router: row.get(3)?,
+                    channel: row.get(4)?,
+                    model: row.get(5)?,
+                    status_code: row.get(6)?,
+                    error_message: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Per-end-user totals within a window (requests without an end user are
+    /// skipped). Backs the per-seat billing view; heaviest users first.
+    pub fn get_end_user_usage(&self, query: &UsageRecordQuery) -> Result<Vec<EndUserUsage>> {
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let (where_clause, params_vec) = Self::build_usage_record_filters(query, true);
+        let refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT end_user, \
+               COUNT(*), \
+               COALESCE(SUM(max(input_tokens, 0)), 0), \
+               COALESCE(SUM(max(output_tokens, 0)), 0), \
+               COALESCE(SUM(cost), 0), \
+               COALESCE(SUM(CASE WHEN status IN ('error', 'fallback_error') THEN 1 ELSE 0 END), 0) \
+             FROM usage_records \
+             WHERE end_user IS NOT NULL AND end_user != ''{where_clause} \
+             GROUP BY end_user \
+             ORDER BY SUM(max(input_tokens, 0) + max(output_tokens, 0)) DESC, end_user"
+        ))?;
+        let rows = stmt
+            .query_map(refs.as_slice(), |row| {
+                Ok(EndUserUsage {
+                    end_user: row.get(0)?,
+                    requests: row.get(1)?,
+                    input_tokens: row.get(2)?,
+                    output_tokens: row.get(3)?,
+                    cost: row.get(4)?,
+                    error_count: row.get(5)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(rows)
+    }
+
+    /// Spend per `group_by` column (`model`, `team_id` or `channel`) within a
+    /// window, most expensive first. Rows logged without a price count toward
+    /// `unpriced_requests` instead of the total.
+    pub fn get_usage_cost(
+        &self,
+        query: &UsageRecordQuery,
+        group_by: &str,
+    ) -> Result<Vec<UsageCost>> {
+        let column = match group_by {
+            "model" | "team_id" | "channel" => group_by,
+            // Records logged before usage shipping have no instance.
+            "instance" => "COALESCE(instance, '')",
+            other => anyhow::bail!("cannot group usage cost by '{other}'"),
+        };
+        let conn = self
+            .read_conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let (where_clause, params_vec) = Self::build_usage_record_filters(query, false);
+        let refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {column}, \
+               COUNT(*), \
+               COALESCE(SUM(max(input_tokens, 0)), 0), \
+               COALESCE(SUM(max(output_tokens, 0)), 0), \
+               COALESCE(SUM(images), 0), \
+               COALESCE(SUM(cost), 0), \
+               COALESCE(SUM(CASE WHEN cost IS NULL AND (input_tokens > 0 OR output_tokens > 0 OR images > 0) THEN 1 ELSE 0 END), 0) \
+             FROM usage_records WHERE 1=1{where_clause} \
+             GROUP BY {column} \
+             ORDER BY COALESCE(SUM(cost), 0) DESC, {column}"
+        ))?;
+        let rows = stmt
+            .query_map(refs.as_slice(), |row| {
+                Ok(UsageCost {
+                    key: row.get(0)?,
+                    requests: row.get(1)?,
+                    input_tokens: row.get(2)?,
+                    output_tokens: row.get(3)?,
+                    images: row.get(4)?,
+                    cost: row.get(5)?,
+                    unpriced_requests: row.get(6)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(rows)
+    }
+
     /// Distinct filter values present in a window, for the dashboard's filter
     /// dropdowns — `SELECT DISTINCT` per column instead of loading every row.
     pub fn get_filter_options(&self, query: &UsageRecordQuery) -> Result<FilterOptions> {
@@ -1132,6 +1794,13 @@ pub struct UsageRecord {
     pub provider_error_body: Option<String>,
     pub client: Option<String>,
     pub user_agent: Option<String>,
+    pub end_user: Option<String>,
+    pub cost: Option<f64>,
+    pub images: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub stream: bool,
+    pub finish_reason: Option<String>,
 }
 
 /// A bounded page of usage records plus the counts the dashboard records view
@@ -1152,6 +1821,99 @@ pub struct UsageAggregate {
     pub avg_latency_ms: f64,
 }
 
+/// Traffic since a point in time, from [`Database::get_traffic`].
+pub struct Traffic {
+    pub minutes: Vec<TrafficMinute>,
+    /// Ascending.
+    pub latencies_ms: Vec<f64>,
+}
+
+impl Traffic {
+    /// Nearest-rank percentile (`p` in 0..=100) of the request latencies.
+    pub fn latency_percentile(&self, p: f64) -> Option<f64> {
+        if self.latencies_ms.is_empty() {
+            return None;
+        }
+        let rank = (p / 100.0 * self.latencies_ms.len() as f64).ceil() as usize;
+        Some(self.latencies_ms[rank.clamp(1, self.latencies_ms.len()) - 1])
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrafficMinute {
+    /// `YYYY-MM-DD HH:MM`, local time.
+    pub minute: String,
+    pub requests: i64,
+    pub errors: i64,
+}
+
+/// A failed request from [`Database::get_recent_errors`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecentError {
+    pub timestamp: String,
+    pub request_id: Option<String>,
+    pub team_id: String,
+    pub router: String,
+    pub channel: String,
+    pub model: String,
+    pub status_code: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+/// Persisted per-key counters from [`Database::touch_key_usage`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KeyUsage {
+    pub team_id: String,
+    pub request_count: i64,
+    pub first_used: String,
+    pub last_used: String,
+}
+
+/// Channel affinity for an Anthropic message batch.
+#[derive(Debug, Clone)]
+pub struct MessageBatchRecord {
+    pub batch_id: String,
+    pub team_id: String,
+    pub router: String,
+    pub channel: String,
+    pub model: String,
+}
+
+/// One row of [`Database::get_pii_redactions`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PiiRedaction {
+    pub timestamp: String,
+    pub request_id: Option<String>,
+    pub rule: String,
+    /// `mask` or `block`.
+    pub action: String,
+    pub matches: i64,
+}
+
+/// Per-end-user totals from [`Database::get_end_user_usage`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EndUserUsage {
+    pub end_user: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Dollar cost of the priced requests (see `pricing`).
+    pub cost: f64,
+    pub error_count: i64,
+}
+
+/// Spend for one group from [`Database::get_usage_cost`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UsageCost {
+    pub key: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub images: i64,
+    pub cost: f64,
+    pub unpriced_requests: i64,
+}
+
 /// Distinct filter values from [`Database::get_filter_options`].
 pub struct FilterOptions {
     pub teams: Vec<String>,
@@ -1208,11 +1970,67 @@ pub struct RankingItem {
 
 #[cfg(test)]
 mod tests {
-    use super::Database;
+    use super::{BucketSnapshot, Database};
     use crate::database::UsageRecordQuery;
     use rusqlite::params;
+    use std::time::Duration;
     use tempfile::tempdir;
 
+    #[test]
+    fn inclusive_end_date_widens_only_bare_dates() {
+        let widen = |end: &str| {
+            UsageRecordQuery {
+                end_time: Some(end.to_string()),
+                ..Default::default()
+            }
+            .with_inclusive_end_date()
+            .end_time
+            .unwrap()
+        };
+        assert_eq!(widen("2026-01-31"), "2026-01-31 23:59:59");
+        assert_eq!(widen("2026-01-31 12:00:00"), "2026-01-31 12:00:00");
+        assert!(
+            UsageRecordQuery::default()
+                .with_inclusive_end_date()
+                .end_time
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn end_user_usage_groups_by_user_within_team() {
+        let dir = tempdir().expect("create temp dir");
+        let db = Database::new(Some(dir.path().to_string_lossy().into_owned())).expect("create db");
+        for (team, user, input, cost, status) in [
+            ("team-a", Some("alice"), 10, Some(0.25), "success"),
+            ("team-a", Some("alice"), 5, None, "error"),
+            ("team-a", Some("bob"), 100, Some(1.5), "success"),
+            ("team-a", None, 7, Some(0.1), "success"),
+            ("team-b", Some("alice"), 1000, Some(9.0), "success"),
+        ] {
+            db.log_usage(
+                None, team, "r", None, "c", "m", input, 1, 0, cost, None, false, status, None,
+                None, None, None, None, None, user,
+            );
+        }
+
+        let rows = db
+            .get_end_user_usage(&UsageRecordQuery {
+                team_id: Some("team-a".to_string()),
+                ..Default::default()
+            })
+            .expect("aggregate");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].end_user, "bob");
+        assert_eq!(rows[1].end_user, "alice");
+        assert_eq!(rows[1].requests, 2);
+        assert_eq!(rows[1].input_tokens, 15);
+        assert_eq!(rows[1].output_tokens, 2);
+        assert_eq!(rows[1].cost, 0.25);
+        assert_eq!(rows[0].cost, 1.5);
+        assert_eq!(rows[1].error_count, 1);
+    }
+
     #[test]
     fn usage_records_are_sorted_by_latest_timestamp_first() {
         let dir = tempdir().expect("create temp dir");
@@ -1269,6 +2087,42 @@ mod tests {
         assert_eq!(records[1].request_id.as_deref(), Some("req-error"));
     }
 
+    #[test]
+    fn key_usage_counts_requests_and_tracks_last_use() {
+        let dir = tempdir().expect("create temp dir");
+        let db = Database::new(Some(dir.path().to_string_lossy().into_owned())).expect("create db");
+        assert!(db.get_key_usage("abc").unwrap().is_none());
+
+        db.touch_key_usage("abc", "team-a");
+        db.touch_key_usage("abc", "team-a");
+        let usage = db.get_key_usage("abc").unwrap().expect("usage recorded");
+        assert_eq!(usage.team_id, "team-a");
+        assert_eq!(usage.request_count, 2);
+        assert!(usage.last_used >= usage.first_used);
+    }
+
+    #[test]
+    fn rate_limit_state_round_trips() {
+        let dir = tempdir().expect("create temp dir");
+        let db = Database::new(Some(dir.path().to_string_lossy().into_owned())).expect("create db");
+        let bucket = BucketSnapshot {
+            key: "team-a".to_string(),
+            kind: "rpm".to_string(),
+            tokens: 1.5,
+            capacity: 10.0,
+            refill_rate: 10.0 / 60.0,
+        };
+        db.save_rate_limit_state(std::slice::from_ref(&bucket))
+            .expect("save");
+        db.save_rate_limit_state(std::slice::from_ref(&bucket))
+            .expect("save again");
+
+        let loaded = db.load_rate_limit_state().expect("load");
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0, bucket);
+        assert!(loaded[0].1 < Duration::from_secs(5));
+    }
+
     #[test]
     fn pragmas_enable_wal_and_incremental_autovacuum() {
         let dir = tempdir().expect("create temp dir");
@@ -98,6 +98,21 @@ pub fn build_config(env: &E2eEnv, config_path: &Path) -> Config {
             headers: upstream.headers.clone(),
             model_map: upstream.model_map.clone(),
             timeouts: upstream.timeouts.clone(),
+            mock: None,
+            native_api: false,
+            max_concurrent_requests: None,
+            max_queued_requests: None,
+            queue_timeout_ms: None,
+            api_keys: vec![],
+            key_strategy: None,
+            api_key_file: None,
+            embedding_task: None,
+            vertex: None,
+            quirks: None,
+            param_profile: None,
+            prompt_cache: None,
+            adapter: None,
+            body_limits: None,
         })
         .collect::<Vec<_>>();
 
@@ -131,9 +146,25 @@ pub fn build_config(env: &E2eEnv, config_path: &Path) -> Config {
                 max_attempts: 2,
                 backoff_ms: 200,
                 retry_on_status: vec![429, 500, 502, 503, 504],
+                exponential: false,
+                jitter: false,
+                max_backoff_ms: None,
+                respect_retry_after: false,
             },
             gemini_replay: crate::config::GeminiReplay::default(),
             cors_allowed_origins: vec![],
+            ip_rate_limit: None,
+            trusted_proxies: vec![],
+            load_shedding: None,
+            revoked_keys: vec![],
+            tls: None,
+            model_aliases: Default::default(),
+            max_request_timeout_ms: None,
+            body_limits: None,
+            compression: None,
+            rate_limit: None,
+            provider_rate_limits: Default::default(),
+            rate_limit_store: None,
         },
         logging: Logging {
             level: "info".to_string(),
@@ -147,22 +178,49 @@ pub fn build_config(env: &E2eEnv, config_path: &Path) -> Config {
             rules: vec![RouterRule {
                 match_spec: MatchSpec {
                     models: vec!["*".to_string()],
+                    ..Default::default()
                 },
                 channels: target_channels,
                 strategy: env.router_strategy.clone(),
+                retries: None,
             }],
             channels: vec![],
             strategy: env.router_strategy.clone(),
             metadata: None,
             fallback_channels,
+            fallback_tiers: vec![],
+            fallback_on: None,
+            reject_unknown_models: false,
+            max_request_bytes: None,
+            max_response_bytes: None,
+            cache: None,
+            passthrough: false,
+            sticky: None,
+            retries: None,
+            hedge_after_ms: None,
+            mirror_channel: None,
+            mirror_percent: None,
+            experiment: None,
+            canary: None,
+            model_aliases: Default::default(),
+            default_model: None,
+            reject_missing_model: false,
+            stream_requests: false,
+            vkey: None,
+            transforms: None,
         }]),
         metrics: Metrics {
             enabled: true,
             path: env.metrics_path.clone(),
+            end_user_label: false,
+            auth_token: None,
+            listen: None,
+            buckets: Default::default(),
         },
         hot_reload: HotReload {
             config_path: config_path.to_string_lossy().to_string(),
             watch: false,
+            store: None,
         },
         teams: Arc::new(vec![Team {
             id: env.team_id.clone(),
@@ -171,12 +229,37 @@ pub fn build_config(env: &E2eEnv, config_path: &Path) -> Config {
                 allowed_routers: vec![env.router_name.clone()],
                 allowed_models: Some(vec![env.test_model.clone()]),
                 rate_limit: None,
+                allow_routing_overrides: false,
+                end_user_rate_limit: None,
+                reject_unknown_models: false,
+                transcripts: None,
+                pii: None,
+                max_tokens_per_request: None,
+                max_context_tokens: None,
+                clamp_max_tokens: false,
+                priority: Default::default(),
             },
             group: None,
             enabled: None,
+            keys: vec![],
         }]),
         compliance: None,
         retention: Default::default(),
+        fault_injection: None,
+        access_audit: None,
+        tenants: vec![],
+        health: None,
+        warmup: None,
+        pricing: vec![],
+        secret_refs: Default::default(),
+        secrets: None,
+        includes: Default::default(),
+        env_overrides: Default::default(),
+        model_discovery: None,
+        guardrails: None,
+        usage_collector: None,
+        usage_webhooks: Vec::new(),
+        usage_shipping: None,
     }
 }
 
@@ -307,6 +390,13 @@ fn parse_provider_type(value: &str) -> anyhow::Result<ProviderType> {
         "jina" => Ok(ProviderType::Jina),
         "openrouter" => Ok(ProviderType::Openrouter),
         "zai" => Ok(ProviderType::Zai),
+        "groq" => Ok(ProviderType::Groq),
+        "mistral" => Ok(ProviderType::Mistral),
+        "cohere" => Ok(ProviderType::Cohere),
+        "together" => Ok(ProviderType::Together),
+        "custom" => Ok(ProviderType::Custom),
+        "vertex" => Ok(ProviderType::Vertex),
+        "mock" => Ok(ProviderType::Mock),
         other => bail!("unsupported provider type in .env: {other}"),
     }
 }
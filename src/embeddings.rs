@@ -0,0 +1,220 @@
+//! Provider-specific shapes of OpenAI `/v1/embeddings` requests.
+//!
+//! Providers listed in `adapter_for` get the OpenAI request rewritten for
+//! their own API, and their response converted back, so clients keep
+//! speaking the OpenAI schema and usage is recorded from `prompt_tokens`:
+//!
+//! - Ollama: the native `/api/embed` endpoint (batch input, token counts
+//!   from `prompt_eval_count`) instead of its OpenAI bridge.
+//! - Jina: OpenAI-style `input_type` and `encoding_format` become Jina's
+//!   `task` and `embedding_type`; a channel's `embedding_task` is the
+//!   default `task`.
+//!
+//! Every other provider receives the request unchanged.
+
+use crate::config::{Channel, ProviderType};
+use anyhow::bail;
+use serde_json::{Map, Value, json};
+
+pub trait EmbeddingsAdapter: Send + Sync {
+    /// Base URL requests are built on, from the channel's `base_url`.
+    fn base_url(&self, base_url: &str) -> String {
+        base_url.to_string()
+    }
+
+    /// Path of the embeddings endpoint, relative to `base_url`.
+    fn path(&self) -> &'static str;
+
+    /// Provider request for an OpenAI embeddings body (`model_map` applied).
+    fn transform_request(&self, channel: &Channel, body: Value) -> anyhow::Result<Value>;
+
+    /// OpenAI-shaped response for a successful provider response.
+    fn transform_response(&self, body: Value) -> Value {
+        body
+    }
+}
+
+/// Adapter for `channel`'s provider, or `None` to forward the OpenAI body.
+pub fn adapter_for(channel: &Channel) -> Option<&'static dyn EmbeddingsAdapter> {
+    match channel.provider_type {
+        ProviderType::Ollama => Some(&OllamaEmbeddings),
+        ProviderType::Jina => Some(&JinaEmbeddings),
+        _ => None,
+    }
+}
+
+pub fn is_embeddings_path(path: &str) -> bool {
+    path.trim_matches('/').ends_with("embeddings")
+}
+
+/// Native Ollama embeddings endpoint.
+pub const OLLAMA_EMBED_PATH: &str = "api/embed";
+
+pub struct OllamaEmbeddings;
+
+impl EmbeddingsAdapter for OllamaEmbeddings {
+    fn base_url(&self, base_url: &str) -> String {
+        // Channels usually point at the OpenAI bridge (`…:11434/v1`).
+        let base = base_url.trim_end_matches('/');
+        base.strip_suffix("/v1").unwrap_or(base).to_string()
+    }
+
+    fn path(&self) -> &'static str {
+        OLLAMA_EMBED_PATH
+    }
+
+    fn transform_request(&self, _channel: &Channel, body: Value) -> anyhow::Result<Value> {
+        let input = &body["input"];
+        let is_text = match input {
+            Value::String(_) => true,
+            Value::Array(items) => items.iter().all(Value::is_string),
+            _ => false,
+        };
+        if !is_text {
+            bail!("Ollama embeddings take a string or an array of strings as input");
+        }
+        let mut request = Map::new();
+        request.insert("model".to_string(), body["model"].clone());
+        request.insert("input".to_string(), input.clone());
+        for field in ["dimensions", "truncate", "keep_alive", "options"] {
+            if let Some(value) = body.get(field) {
+                request.insert(field.to_string(), value.clone());
+            }
+        }
+        Ok(Value::Object(request))
+    }
+
+    fn transform_response(&self, body: Value) -> Value {
+        let Some(embeddings) = body["embeddings"].as_array() else {
+            return body;
+        };
+        let data: Vec<Value> = embeddings
+            .iter()
+            .enumerate()
+            .map(|(index, embedding)| {
+                json!({"object": "embedding", "index": index, "embedding": embedding})
+            })
+            .collect();
+        let tokens = body["prompt_eval_count"].as_u64().unwrap_or(0);
+        json!({
+            "object": "list",
+            "data": data,
+            "model": body["model"],
+            "usage": {"prompt_tokens": tokens, "total_tokens": tokens},
+        })
+    }
+}
+
+pub struct JinaEmbeddings;
+
+impl EmbeddingsAdapter for JinaEmbeddings {
+    fn path(&self) -> &'static str {
+        "v1/embeddings"
+    }
+
+    fn transform_request(&self, channel: &Channel, mut body: Value) -> anyhow::Result<Value> {
+        let Some(request) = body.as_object_mut() else {
+            bail!("embeddings request body must be a JSON object");
+        };
+        let input_type = request.remove("input_type");
+        if !request.contains_key("task") {
+            let task = match input_type.as_ref().and_then(Value::as_str) {
+                Some("query") => Some("retrieval.query".to_string()),
+                Some("document" | "passage") => Some("retrieval.passage".to_string()),
+                Some(other) => Some(other.to_string()),
+                None => channel.embedding_task.clone(),
+            };
+            if let Some(task) = task {
+                request.insert("task".to_string(), Value::String(task));
+            }
+        }
+        if let Some(format) = request.remove("encoding_format")
+            && !request.contains_key("embedding_type")
+        {
+            request.insert("embedding_type".to_string(), format);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(provider_type: &str, embedding_task: Option<&str>) -> Channel {
+        serde_json::from_value(json!({
+            "name": "c",
+            "provider_type": provider_type,
+            "base_url": "http://localhost:11434/v1",
+            "api_key": "",
+            "embedding_task": embedding_task
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ollama_uses_native_embed_and_reports_tokens() {
+        let ollama = channel("ollama", None);
+        let adapter = adapter_for(&ollama).unwrap();
+        assert_eq!(adapter.base_url(&ollama.base_url), "http://localhost:11434");
+        assert_eq!(adapter.path(), "api/embed");
+
+        let request = adapter
+            .transform_request(
+                &ollama,
+                json!({"model": "nomic-embed-text", "input": ["a", "b"], "encoding_format": "float", "dimensions": 256}),
+            )
+            .unwrap();
+        assert_eq!(
+            request,
+            json!({"model": "nomic-embed-text", "input": ["a", "b"], "dimensions": 256})
+        );
+        assert!(
+            adapter
+                .transform_request(&ollama, json!({"model": "m", "input": [[1, 2]]}))
+                .is_err()
+        );
+
+        let response = adapter.transform_response(json!({
+            "model": "nomic-embed-text",
+            "embeddings": [[0.1, 0.2], [0.3, 0.4]],
+            "prompt_eval_count": 7
+        }));
+        assert_eq!(response["object"], "list");
+        assert_eq!(response["data"][1]["index"], 1);
+        assert_eq!(response["data"][1]["embedding"], json!([0.3, 0.4]));
+        assert_eq!(response["usage"]["prompt_tokens"], 7);
+    }
+
+    #[test]
+    fn jina_maps_input_type_and_encoding_format() {
+        let jina = channel("jina", Some("text-matching"));
+        let adapter = adapter_for(&jina).unwrap();
+
+        let request = adapter
+            .transform_request(
+                &jina,
+                json!({"model": "jina-embeddings-v3", "input": ["q"], "input_type": "query", "encoding_format": "base64"}),
+            )
+            .unwrap();
+        assert_eq!(request["task"], "retrieval.query");
+        assert_eq!(request["embedding_type"], "base64");
+        assert!(request.get("input_type").is_none());
+        assert!(request.get("encoding_format").is_none());
+
+        let request = adapter
+            .transform_request(&jina, json!({"model": "jina-embeddings-v3", "input": "x"}))
+            .unwrap();
+        assert_eq!(request["task"], "text-matching");
+
+        let request = adapter
+            .transform_request(
+                &jina,
+                json!({"model": "jina-embeddings-v3", "input": "x", "task": "classification"}),
+            )
+            .unwrap();
+        assert_eq!(request["task"], "classification");
+
+        assert!(adapter_for(&channel("openai", None)).is_none());
+    }
+}
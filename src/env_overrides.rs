@@ -0,0 +1,275 @@
+//! Config overrides from the environment.
+//!
+//! `APEX_<PATH>=<value>` sets the config value at `PATH`, where `__`
+//! separates levels and each segment is the lowercased field name:
+//! `APEX_GLOBAL__LISTEN=0.0.0.0:8080` sets `global.listen`,
+//! `APEX_LOGGING__LEVEL=debug` sets `logging.level`. Only variables whose
+//! first segment is a top-level config section are read, so unrelated
+//! `APEX_*` variables (`APEX_CONFIG`, …) are left alone. Numeric segments
+//! index arrays.
+//!
+//! Values are parsed as JSON (`8080`, `true`, `["a","b"]`) and fall back to
+//! a plain string; a value replacing a string is always taken as a string.
+//! Overrides apply to the main config file when it is read. What they
+//! replaced is remembered in `Config::env_overrides`, so `save_config`
+//! writes back the file's own value rather than the override.
+
+use anyhow::{Context, bail};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+const PREFIX: &str = "APEX_";
+const SEPARATOR: &str = "__";
+
+/// Top-level config sections that can be overridden.
+const SECTIONS: &[&str] = &[
+    "version",
+    "global",
+    "logging",
+    "data_dir",
+    "web_dir",
+    "channels",
+    "routers",
+    "metrics",
+    "hot_reload",
+    "teams",
+    "compliance",
+    "retention",
+    "usage_shipping",
+    "usage_collector",
+    "usage_webhooks",
+    "fault_injection",
+    "access_audit",
+    "tenants",
+    "health",
+    "warmup",
+    "pricing",
+    "secrets",
+    "model_discovery",
+    "guardrails",
+];
+
+/// Overrides applied to a loaded config.
+#[derive(Debug, Clone, Default)]
+pub struct EnvOverrides {
+    applied: Arc<Vec<Applied>>,
+}
+
+impl EnvOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Applied {
+    path: Vec<String>,
+    /// Value in the file, `None` when the override added the field.
+    original: Option<Value>,
+    value: Value,
+}
+
+/// `(variable, path, value)` of every override variable in `vars`, sorted by name
+/// so overlapping overrides apply in a stable order.
+fn override_vars(
+    vars: impl Iterator<Item = (String, String)>,
+) -> Vec<(String, Vec<String>, String)> {
+    let mut found: Vec<_> = vars
+        .filter_map(|(name, value)| {
+            let path: Vec<String> = name
+                .strip_prefix(PREFIX)?
+                .split(SEPARATOR)
+                .map(str::to_ascii_lowercase)
+                .collect();
+            (SECTIONS.contains(&path[0].as_str()) && path.iter().all(|s| !s.is_empty()))
+                .then_some((name, path, value))
+        })
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+/// Apply the `APEX_*` overrides of the process environment to a parsed
+/// config file.
+pub fn apply(raw: &mut Value) -> anyhow::Result<EnvOverrides> {
+    apply_vars(raw, std::env::vars())
+}
+
+fn apply_vars(
+    raw: &mut Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> anyhow::Result<EnvOverrides> {
+    let mut applied = Vec::new();
+    for (name, path, text) in override_vars(vars) {
+        let slot = slot(raw, &path).with_context(|| format!("invalid override {name}"))?;
+        let original = (!slot.is_null()).then(|| slot.clone());
+        let value = match (&original, serde_json::from_str::<Value>(&text)) {
+            (Some(Value::String(_)), _) | (_, Err(_)) => Value::String(text),
+            (_, Ok(value)) => value,
+        };
+        *slot = value.clone();
+        applied.push(Applied {
+            path,
+            original,
+            value,
+        });
+    }
+    Ok(EnvOverrides {
+        applied: Arc::new(applied),
+    })
+}
+
+/// The value at `path`, creating missing object fields as `null`.
+fn slot<'a>(mut value: &'a mut Value, path: &[String]) -> anyhow::Result<&'a mut Value> {
+    for segment in path {
+        if value.is_null() {
+            *value = Value::Object(Map::new());
+        }
+        value = match value {
+            Value::Object(map) => map.entry(segment.clone()).or_insert(Value::Null),
+            Value::Array(items) => {
+                let len = items.len();
+                let index: usize = segment
+                    .parse()
+                    .with_context(|| format!("{segment:?} is not an array index"))?;
+                match items.get_mut(index) {
+                    Some(item) => item,
+                    None => bail!("index {index} is out of range ({len} entries)"),
+                }
+            }
+            _ => bail!("{segment:?} is below a value that is not an object"),
+        };
+    }
+    Ok(value)
+}
+
+/// Undo the overrides in a serialized config about to be saved. Values
+/// changed since loading are kept as they are.
+pub fn restore(overrides: &EnvOverrides, raw: &mut Value) {
+    'overrides: for applied in overrides.applied.iter().rev() {
+        let Some((last, parents)) = applied.path.split_last() else {
+            continue;
+        };
+        let mut value = &mut *raw;
+        for segment in parents {
+            let next = match value {
+                Value::Object(map) => map.get_mut(segment),
+                Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index)),
+                _ => None,
+            };
+            let Some(next) = next else {
+                continue 'overrides;
+            };
+            value = next;
+        }
+        let Value::Object(map) = value else {
+            continue;
+        };
+        if map.get(last) != Some(&applied.value) {
+            continue;
+        }
+        match &applied.original {
+            Some(original) => {
+                map.insert(last.clone(), original.clone());
+            }
+            None => {
+                map.remove(last);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn applies_typed_overrides_and_restores_file_values() {
+        let file = json!({
+            "version": "1",
+            "global": {"listen": "127.0.0.1:12356", "auth_keys": []},
+            "metrics": {"enabled": false, "path": "/metrics"},
+            "channels": [{"name": "openai", "api_key": "sk-file"}]
+        });
+        let mut raw = file.clone();
+        let overrides = apply_vars(
+            &mut raw,
+            vars(&[
+                ("APEX_GLOBAL__LISTEN", "0.0.0.0:8080"),
+                ("APEX_METRICS__ENABLED", "true"),
+                ("APEX_LOGGING__LEVEL", "debug"),
+                ("APEX_WARMUP__INTERVAL_SECS", "30"),
+                ("APEX_VERSION", "2"),
+                ("APEX_CHANNELS__0__API_KEY", "sk-env"),
+                ("APEX_CONFIG", "/etc/apex.json"),
+                ("APEX_GLOBAL_LISTEN", "ignored"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(raw["global"]["listen"], "0.0.0.0:8080");
+        assert_eq!(raw["metrics"]["enabled"], true);
+        assert_eq!(raw["logging"]["level"], "debug");
+        assert_eq!(raw["warmup"]["interval_secs"], 30);
+        assert_eq!(raw["version"], "2");
+        assert_eq!(raw["channels"][0]["api_key"], "sk-env");
+        assert!(raw.get("config").is_none());
+
+        // A value edited after loading is saved as edited.
+        raw["metrics"]["enabled"] = json!(false);
+        restore(&overrides, &mut raw);
+        assert_eq!(raw["global"]["listen"], "127.0.0.1:12356");
+        assert_eq!(raw["channels"][0]["api_key"], "sk-file");
+        assert_eq!(raw["version"], "1");
+        assert_eq!(raw["logging"], json!({}));
+        assert_eq!(raw["metrics"]["enabled"], false);
+    }
+
+    #[test]
+    fn every_config_section_can_be_overridden() {
+        // Read the fields of `Config` from its source, so a section added
+        // later fails here until it is listed in `SECTIONS`.
+        let source = include_str!("config.rs");
+        let start = source.find("pub struct Config {").unwrap();
+        let body = &source[start..start + source[start..].find("\n}").unwrap()];
+        let mut skipped = false;
+        let mut fields = Vec::new();
+        for line in body.lines().skip(1).map(str::trim) {
+            if line == "#[serde(skip)]" {
+                skipped = true;
+            } else if let Some(field) = line.strip_prefix("pub ") {
+                let name = field.split(':').next().unwrap();
+                if !std::mem::take(&mut skipped) {
+                    fields.push(name);
+                }
+            }
+        }
+        assert!(fields.contains(&"guardrails"), "{fields:?}");
+        for field in fields {
+            assert!(
+                SECTIONS.contains(&field),
+                "{field} is missing from SECTIONS"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_paths_that_do_not_fit_the_config() {
+        let mut raw = json!({"global": {"listen": "x"}, "channels": []});
+        let err = apply_vars(&mut raw, vars(&[("APEX_CHANNELS__0__NAME", "a")])).unwrap_err();
+        assert!(format!("{err:#}").contains("APEX_CHANNELS__0__NAME"));
+        let err = apply_vars(&mut raw, vars(&[("APEX_GLOBAL__LISTEN__PORT", "1")])).unwrap_err();
+        assert!(format!("{err:#}").contains("not an object"));
+    }
+}
@@ -0,0 +1,163 @@
+//! A/B experiments on a router (`router.experiment`).
+//!
+//! Each request of the router is drawn into one variant by its share of
+//! traffic, or into the `control` cohort when no variant claims it. Callers
+//! with a conversation key (the `sticky` header, or the end user) always land
+//! in the same cohort; others are drawn per request. A variant may pin a
+//! channel and rewrite the requested model. The cohort is echoed in the
+//! `x-apex-variant` response header and labels the `apex_experiment_*`
+//! metrics.
+
+use crate::config::{Experiment, ExperimentVariant};
+use axum::body::Bytes;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+pub const VARIANT_HEADER: &str = "x-apex-variant";
+/// Cohort of requests no variant claimed.
+pub const CONTROL: &str = "control";
+
+/// Experiment cohort a request was assigned to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub router: String,
+    pub variant: String,
+}
+
+tokio::task_local! {
+    /// Cohort of the request being processed, set once its router is known.
+    static ASSIGNMENT: Arc<Mutex<Option<Assignment>>>;
+}
+
+/// Run `request`, returning its output and the cohort it was assigned to.
+pub async fn scope<F: Future>(request: F) -> (F::Output, Option<Assignment>) {
+    let slot = Arc::new(Mutex::new(None));
+    let output = ASSIGNMENT.scope(slot.clone(), request).await;
+    let assignment = slot.lock().unwrap().take();
+    (output, assignment)
+}
+
+/// Cohort of the request running in the current [`scope`], if any.
+pub fn current() -> Option<Assignment> {
+    ASSIGNMENT
+        .try_with(|slot| slot.lock().unwrap().clone())
+        .ok()
+        .flatten()
+}
+
+/// Draw the request into a cohort and record it for the current [`scope`].
+/// Returns the variant, or `None` for the control cohort.
+pub fn assign<'a>(
+    router: &str,
+    experiment: &'a Experiment,
+    cohort_key: Option<&str>,
+) -> Option<&'a ExperimentVariant> {
+    let point = match cohort_key {
+        Some(key) => {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            router.hash(&mut hasher);
+            key.hash(&mut hasher);
+            (hasher.finish() % 10_000) as f64 / 100.0
+        }
+        None => rand::random::<f64>() * 100.0,
+    };
+    let variant = pick(experiment, point);
+    let assignment = Assignment {
+        router: router.to_string(),
+        variant: variant.map_or(CONTROL, |v| v.name.as_str()).to_string(),
+    };
+    let _ = ASSIGNMENT.try_with(|slot| *slot.lock().unwrap() = Some(assignment));
+    variant
+}
+
+/// The variant whose share of `[0, 100)` contains `point`.
+fn pick(experiment: &Experiment, point: f64) -> Option<&ExperimentVariant> {
+    let mut upper = 0.0;
+    experiment.variants.iter().find(|variant| {
+        upper += variant.percent;
+        point < upper
+    })
+}
+
+/// Apply the variant's `model_map` to the body's `model` field.
+pub fn rewrite_model(variant: &ExperimentVariant, body: Bytes) -> Bytes {
+    let Some(map) = &variant.model_map else {
+        return body;
+    };
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return body;
+    };
+    let Some(model) = value
+        .get("model")
+        .and_then(|model| model.as_str())
+        .and_then(|model| map.get(model))
+    else {
+        return body;
+    };
+    value["model"] = serde_json::Value::String(model.clone());
+    serde_json::to_vec(&value).map(Bytes::from).unwrap_or(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn experiment() -> Experiment {
+        let variant = |name: &str, percent| ExperimentVariant {
+            name: name.to_string(),
+            percent,
+            channel: None,
+            model_map: Some(HashMap::from([(
+                "gpt-4o".to_string(),
+                "gpt-4.1".to_string(),
+            )])),
+        };
+        Experiment {
+            variants: vec![variant("a", 10.0), variant("b", 30.0)],
+        }
+    }
+
+    #[test]
+    fn variants_take_consecutive_shares_and_the_rest_is_control() {
+        let experiment = experiment();
+        let name = |point| pick(&experiment, point).map(|v| v.name.as_str());
+        assert_eq!(name(0.0), Some("a"));
+        assert_eq!(name(9.99), Some("a"));
+        assert_eq!(name(10.0), Some("b"));
+        assert_eq!(name(39.99), Some("b"));
+        assert_eq!(name(40.0), None);
+    }
+
+    #[tokio::test]
+    async fn keyed_callers_keep_their_cohort() {
+        let experiment = experiment();
+        let ((), first) = scope(async {
+            assign("r1", &experiment, Some("user-7"));
+        })
+        .await;
+        for _ in 0..5 {
+            let ((), again) = scope(async {
+                assign("r1", &experiment, Some("user-7"));
+            })
+            .await;
+            assert_eq!(again, first);
+        }
+        assert_eq!(first.unwrap().router, "r1");
+        // Outside a scope nothing is recorded.
+        assign("r1", &experiment, None);
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn model_map_rewrites_only_mapped_models() {
+        let variant = &experiment().variants[0];
+        let body = rewrite_model(variant, Bytes::from(r#"{"model":"gpt-4o","n":1}"#));
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["model"], "gpt-4.1");
+        assert_eq!(value["n"], 1);
+
+        let other = Bytes::from(r#"{"model":"o3"}"#);
+        assert_eq!(rewrite_model(variant, other.clone()), other);
+    }
+}
@@ -0,0 +1,130 @@
+//! Chaos mode for upstream calls (see `config::FaultInjection`).
+//!
+//! Faults are injected at the point where the gateway talks to the upstream,
+//! so everything above it — retries, fallback channels, usage logging — sees
+//! them exactly like real provider failures.
+
+use crate::config::FaultInjection;
+use axum::http::StatusCode;
+use futures::StreamExt;
+use std::future::Future;
+use std::time::Duration;
+
+/// Run `send` under the configured faults for `channel`.
+pub async fn apply<F>(
+    settings: &FaultInjection,
+    channel: &str,
+    send: F,
+) -> reqwest::Result<reqwest::Response>
+where
+    F: Future<Output = reqwest::Result<reqwest::Response>>,
+{
+    if roll(settings.latency_rate) && settings.latency_ms > 0 {
+        tracing::warn!(
+            "Fault Injected: +{}ms latency on channel '{}'",
+            settings.latency_ms,
+            channel
+        );
+        tokio::time::sleep(Duration::from_millis(settings.latency_ms)).await;
+    }
+
+    if roll(settings.error_rate) {
+        let status =
+            StatusCode::from_u16(settings.error_status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+        tracing::warn!(
+            "Fault Injected: {} error on channel '{}'",
+            status.as_u16(),
+            channel
+        );
+        let body = serde_json::json!({
+            "error": {"message": "fault injected by apex", "type": "fault_injection"}
+        });
+        let response = axum::http::Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(reqwest::Body::from(body.to_string()))
+            .unwrap();
+        return Ok(reqwest::Response::from(response));
+    }
+
+    let response = send.await?;
+    if response.status().is_success() && roll(settings.truncate_rate) {
+        tracing::warn!(
+            "Fault Injected: truncated response on channel '{}'",
+            channel
+        );
+        return Ok(truncate(response));
+    }
+    Ok(response)
+}
+
+/// Keep status and headers but end the body with an error after one chunk.
+fn truncate(response: reqwest::Response) -> reqwest::Response {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let stream = response
+        .bytes_stream()
+        .take(1)
+        .map(|chunk| chunk.map_err(std::io::Error::other))
+        .chain(futures::stream::once(async {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "fault injection: stream truncated",
+            ))
+        }));
+
+    let mut builder = axum::http::Response::builder().status(status);
+    for (name, value) in headers.iter() {
+        if name != axum::http::header::CONTENT_LENGTH {
+            builder = builder.header(name, value);
+        }
+    }
+    reqwest::Response::from(builder.body(reqwest::Body::wrap_stream(stream)).unwrap())
+}
+
+fn roll(probability: f64) -> bool {
+    probability > 0.0 && (probability >= 1.0 || rand::random::<f64>() < probability)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok_response() -> reqwest::Result<reqwest::Response> {
+        let response = axum::http::Response::builder()
+            .status(StatusCode::OK)
+            .body(reqwest::Body::from("hello"))
+            .unwrap();
+        Ok(reqwest::Response::from(response))
+    }
+
+    #[tokio::test]
+    async fn certain_error_skips_upstream() {
+        let settings = FaultInjection {
+            enabled: true,
+            error_rate: 1.0,
+            error_status: 502,
+            ..Default::default()
+        };
+        let resp = apply(&settings, "a", async {
+            panic!("upstream must not be called")
+        })
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn truncated_body_ends_with_error() {
+        let settings = FaultInjection {
+            enabled: true,
+            truncate_rate: 1.0,
+            ..Default::default()
+        };
+        let resp = apply(&settings, "a", async { ok_response() })
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.bytes().await.is_err());
+    }
+}
@@ -256,7 +256,8 @@ impl GeminiAnthropicReplayCache {
             };
             Response::from_parts(parts, Body::from_stream(wrapped))
         } else {
-            let bytes = match axum::body::to_bytes(body, 10 * 1024 * 1024).await {
+            // Already held under the response size cap.
+            let bytes = match axum::body::to_bytes(body, usize::MAX).await {
                 Ok(bytes) => bytes,
                 Err(_) => return Response::from_parts(parts, Body::empty()),
             };
@@ -0,0 +1,855 @@
+//! Conversion between OpenAI chat completions and Gemini's native
+//! `generateContent` / `streamGenerateContent` API.
+//!
+//! Used by Gemini channels with `native_api: true`. Anthropic callers are
+//! chained through the OpenAI shape (`converters`), so this module only has
+//! to speak OpenAI on one side and Gemini on the other.
+
+use axum::body::Bytes;
+use futures::{Stream, StreamExt, stream};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::io;
+
+/// A chat request rewritten for the native Gemini endpoints.
+#[derive(Debug)]
+pub struct GenerateContentRequest {
+    pub model: String,
+    pub stream: bool,
+    pub body: Bytes,
+}
+
+impl GenerateContentRequest {
+    /// Upstream path relative to the `v1beta` root.
+    pub fn path(&self) -> String {
+        format!("v1beta/models/{}:{}", self.model, self.method())
+    }
+
+    pub fn method(&self) -> &'static str {
+        if self.stream {
+            "streamGenerateContent"
+        } else {
+            "generateContent"
+        }
+    }
+
+    pub fn query(&self) -> Option<&'static str> {
+        self.stream.then_some("alt=sse")
+    }
+}
+
+/// Whether an upstream URL is a native `generateContent` call.
+pub fn is_generate_content_url(url: &reqwest::Url) -> bool {
+    let path = url.path();
+    path.ends_with(":generateContent") || path.ends_with(":streamGenerateContent")
+}
+
+/// Converts an OpenAI chat completion request into a `generateContent` body.
+pub fn convert_openai_to_gemini(body: &Bytes) -> anyhow::Result<GenerateContentRequest> {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|e| anyhow::anyhow!("invalid chat request body: {}", e))?;
+    let model = value
+        .get("model")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("chat request has no model"))?;
+    let model = model.strip_prefix("models/").unwrap_or(model).to_string();
+    let stream = value
+        .get("stream")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let mut system_parts = Vec::new();
+    let mut contents: Vec<Value> = Vec::new();
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+
+    for message in value
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        match role {
+            "system" | "developer" => {
+                system_parts.extend(openai_content_to_parts(message.get("content")));
+            }
+            "assistant" => {
+                let mut parts = openai_content_to_parts(message.get("content"));
+                for call in message
+                    .get("tool_calls")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let name = call["function"]["name"].as_str().unwrap_or_default();
+                    if let Some(id) = call.get("id").and_then(Value::as_str) {
+                        tool_names.insert(id.to_string(), name.to_string());
+                    }
+                    let args = call["function"]["arguments"]
+                        .as_str()
+                        .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+                        .unwrap_or_else(|| json!({}));
+                    let mut part = json!({"functionCall": {"name": name, "args": args}});
+                    if let Some(signature) = call
+                        .pointer("/extra_content/google/thought_signature")
+                        .and_then(Value::as_str)
+                    {
+                        part["thoughtSignature"] = json!(signature);
+                    }
+                    parts.push(part);
+                }
+                push_content(&mut contents, "model", parts);
+            }
+            "tool" | "function" => {
+                let name = message
+                    .get("tool_call_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| tool_names.get(id).cloned())
+                    .or_else(|| {
+                        message
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .map(str::to_string)
+                    })
+                    .unwrap_or_default();
+                let text = content_text(message.get("content"));
+                let response = match serde_json::from_str::<Value>(&text) {
+                    Ok(Value::Object(object)) => Value::Object(object),
+                    _ => json!({"content": text}),
+                };
+                push_content(
+                    &mut contents,
+                    "user",
+                    vec![json!({"functionResponse": {"name": name, "response": response}})],
+                );
+            }
+            _ => {
+                push_content(
+                    &mut contents,
+                    "user",
+                    openai_content_to_parts(message.get("content")),
+                );
+            }
+        }
+    }
+
+    let mut request = Map::new();
+    request.insert("contents".to_string(), Value::Array(contents));
+    if !system_parts.is_empty() {
+        request.insert(
+            "systemInstruction".to_string(),
+            json!({"parts": system_parts}),
+        );
+    }
+
+    let declarations: Vec<Value> = value
+        .get("tools")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| tool.get("function"))
+        .map(|function| {
+            let mut declaration = json!({"name": function["name"]});
+            if let Some(description) = function.get("description") {
+                declaration["description"] = description.clone();
+            }
+            if let Some(parameters) = function.get("parameters") {
+                declaration["parameters"] = sanitize_schema(parameters);
+            }
+            declaration
+        })
+        .collect();
+    if !declarations.is_empty() {
+        request.insert(
+            "tools".to_string(),
+            json!([{"functionDeclarations": declarations}]),
+        );
+    }
+    if let Some(tool_config) = value.get("tool_choice").and_then(convert_tool_choice) {
+        request.insert("toolConfig".to_string(), tool_config);
+    }
+
+    let generation_config = generation_config(&value);
+    if !generation_config.is_empty() {
+        request.insert(
+            "generationConfig".to_string(),
+            Value::Object(generation_config),
+        );
+    }
+
+    Ok(GenerateContentRequest {
+        model,
+        stream,
+        body: Bytes::from(serde_json::to_vec(&request)?),
+    })
+}
+
+/// Converts a `generateContent` response (or Google error) to OpenAI's shape.
+pub fn convert_gemini_response_to_openai(body: Bytes) -> Bytes {
+    let Ok(value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    if let Some(error) = value.get("error") {
+        return Bytes::from(convert_error(error).to_string());
+    }
+
+    let mut tool_index = 0;
+    let candidate = value
+        .pointer("/candidates/0")
+        .cloned()
+        .unwrap_or(Value::Null);
+    let (text, tool_calls) = candidate_parts(&candidate, &mut tool_index);
+    let mut message = json!({"role": "assistant", "content": text});
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    let mut response = json!({
+        "id": response_id(&value),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": value.get("modelVersion").cloned().unwrap_or(Value::Null),
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason(&value, &candidate, tool_index > 0)
+                .unwrap_or("stop"),
+        }],
+    });
+    if let Some(usage) = value.get("usageMetadata").map(convert_usage) {
+        response["usage"] = usage;
+    }
+    Bytes::from(response.to_string())
+}
+
+/// Whether an upstream URL is a native Imagen `predict` call.
+pub fn is_predict_url(url: &reqwest::Url) -> bool {
+    url.path().ends_with(":predict")
+}
+
+/// Converts an OpenAI `images/generations` request into an Imagen
+/// `predict` call; returns the upstream path (relative to the `v1beta` root)
+/// and body. Imagen always answers with base64 data, so `response_format`
+/// is ignored.
+pub fn convert_openai_image_request_to_imagen(body: &Bytes) -> anyhow::Result<(String, Bytes)> {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|e| anyhow::anyhow!("invalid image request body: {}", e))?;
+    let model = value
+        .get("model")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("image request has no model"))?;
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    let prompt = value
+        .get("prompt")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("image request has no prompt"))?;
+
+    let mut parameters = Map::new();
+    parameters.insert(
+        "sampleCount".to_string(),
+        json!(value.get("n").and_then(Value::as_u64).unwrap_or(1)),
+    );
+    if let Some(ratio) = value
+        .get("size")
+        .and_then(Value::as_str)
+        .and_then(imagen_aspect_ratio)
+    {
+        parameters.insert("aspectRatio".to_string(), json!(ratio));
+    }
+    let request = json!({
+        "instances": [{"prompt": prompt}],
+        "parameters": parameters,
+    });
+    Ok((
+        format!("v1beta/models/{model}:predict"),
+        Bytes::from(request.to_string()),
+    ))
+}
+
+/// Closest Imagen aspect ratio for an OpenAI `WIDTHxHEIGHT` size.
+fn imagen_aspect_ratio(size: &str) -> Option<&'static str> {
+    let (width, height) = size.split_once('x')?;
+    let ratio = width.trim().parse::<f64>().ok()? / height.trim().parse::<f64>().ok()?;
+    [
+        ("1:1", 1.0),
+        ("4:3", 4.0 / 3.0),
+        ("3:4", 3.0 / 4.0),
+        ("16:9", 16.0 / 9.0),
+        ("9:16", 9.0 / 16.0),
+    ]
+    .into_iter()
+    .min_by(|a, b| (a.1 - ratio).abs().total_cmp(&(b.1 - ratio).abs()))
+    .map(|(name, _)| name)
+}
+
+/// Converts an Imagen `predict` response (or Google error) to OpenAI's
+/// images response.
+pub fn convert_imagen_response_to_openai(body: Bytes) -> Bytes {
+    let Ok(value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    if let Some(error) = value.get("error") {
+        return Bytes::from(convert_error(error).to_string());
+    }
+    let data: Vec<Value> = value
+        .get("predictions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|prediction| {
+            let mut image = json!({"b64_json": prediction.get("bytesBase64Encoded")?});
+            if let Some(prompt) = prediction.get("prompt") {
+                image["revised_prompt"] = prompt.clone();
+            }
+            Some(image)
+        })
+        .collect();
+    Bytes::from(
+        json!({
+            "created": chrono::Utc::now().timestamp(),
+            "data": data,
+        })
+        .to_string(),
+    )
+}
+
+/// Converts a `streamGenerateContent?alt=sse` stream to OpenAI chat chunks.
+///
+/// Gemini repeats cumulative `usageMetadata` on every event; the last one is
+/// emitted as a trailing `choices: []` usage chunk before `[DONE]`, matching
+/// `stream_options.include_usage`.
+pub fn convert_gemini_stream_to_openai<S, E>(
+    stream: S,
+) -> impl Stream<Item = Result<Bytes, io::Error>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let state = (stream, Vec::new(), GeminiStreamState::default(), false);
+    stream::unfold(
+        state,
+        |(mut stream, mut buffer, mut state, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                let mut output = String::new();
+                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    output.push_str(&state.convert_line(&String::from_utf8_lossy(&line)));
+                }
+                if !output.is_empty() {
+                    return Some((Ok(Bytes::from(output)), (stream, buffer, state, false)));
+                }
+
+                match stream.next().await {
+                    Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                    Some(Err(err)) => {
+                        return Some((Err(io::Error::other(err)), (stream, buffer, state, true)));
+                    }
+                    None => {
+                        let rest = String::from_utf8_lossy(&buffer).to_string();
+                        let mut output = state.convert_line(&rest);
+                        output.push_str(&state.finish());
+                        return Some((Ok(Bytes::from(output)), (stream, Vec::new(), state, true)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[derive(Default)]
+struct GeminiStreamState {
+    id: Option<String>,
+    model: Value,
+    sent_role: bool,
+    tool_index: usize,
+    usage: Option<Value>,
+}
+
+impl GeminiStreamState {
+    fn convert_line(&mut self, line: &str) -> String {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return String::new();
+        };
+        let Ok(value) = serde_json::from_str::<Value>(data.trim()) else {
+            return String::new();
+        };
+        if let Some(error) = value.get("error") {
+            return sse_data(&convert_error(error));
+        }
+
+        let id = self.id.get_or_insert_with(|| response_id(&value)).clone();
+        if let Some(model) = value.get("modelVersion") {
+            self.model = model.clone();
+        }
+        if let Some(usage) = value.get("usageMetadata") {
+            self.usage = Some(convert_usage(usage));
+        }
+
+        let candidate = value
+            .pointer("/candidates/0")
+            .cloned()
+            .unwrap_or(Value::Null);
+        let had_tools = self.tool_index > 0;
+        let (text, tool_calls) = candidate_parts(&candidate, &mut self.tool_index);
+        let finish = finish_reason(&value, &candidate, had_tools || self.tool_index > 0);
+
+        let mut delta = Map::new();
+        if !self.sent_role {
+            delta.insert("role".to_string(), json!("assistant"));
+            self.sent_role = true;
+        }
+        if let Some(text) = text {
+            delta.insert("content".to_string(), json!(text));
+        }
+        if !tool_calls.is_empty() {
+            let base = self.tool_index - tool_calls.len();
+            let calls = tool_calls
+                .into_iter()
+                .enumerate()
+                .map(|(offset, mut call)| {
+                    call["index"] = json!(base + offset);
+                    call
+                })
+                .collect();
+            delta.insert("tool_calls".to_string(), Value::Array(calls));
+        }
+        if delta.is_empty() && finish.is_none() {
+            return String::new();
+        }
+
+        sse_data(&json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": chrono::Utc::now().timestamp(),
+            "model": self.model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish}],
+        }))
+    }
+
+    fn finish(&mut self) -> String {
+        let mut output = String::new();
+        if let Some(usage) = self.usage.take() {
+            output.push_str(&sse_data(&json!({
+                "id": self.id.clone().unwrap_or_else(|| "chatcmpl-gemini".to_string()),
+                "object": "chat.completion.chunk",
+                "created": chrono::Utc::now().timestamp(),
+                "model": self.model,
+                "choices": [],
+                "usage": usage,
+            })));
+        }
+        output.push_str("data: [DONE]\n\n");
+        output
+    }
+}
+
+fn sse_data(value: &Value) -> String {
+    format!("data: {}\n\n", value)
+}
+
+fn response_id(value: &Value) -> String {
+    value
+        .get("responseId")
+        .and_then(Value::as_str)
+        .map(|id| format!("chatcmpl-{id}"))
+        .unwrap_or_else(|| "chatcmpl-gemini".to_string())
+}
+
+/// Visible text and OpenAI `tool_calls` of a candidate. Thought parts are
+/// dropped; function call signatures ride along in `extra_content` so they
+/// can be replayed on the next turn.
+fn candidate_parts(candidate: &Value, tool_index: &mut usize) -> (Option<String>, Vec<Value>) {
+    let mut text: Option<String> = None;
+    let mut tool_calls = Vec::new();
+    for part in candidate
+        .pointer("/content/parts")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(call) = part.get("functionCall") {
+            let id = call
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("call_{:016x}", rand::random::<u64>()));
+            let arguments = call.get("args").cloned().unwrap_or_else(|| json!({}));
+            let mut tool_call = json!({
+                "id": id,
+                "type": "function",
+                "function": {"name": call["name"], "arguments": arguments.to_string()},
+            });
+            if let Some(signature) = part.get("thoughtSignature") {
+                tool_call["extra_content"] = json!({"google": {"thought_signature": signature}});
+            }
+            tool_calls.push(tool_call);
+            *tool_index += 1;
+        } else if let Some(part_text) = part.get("text").and_then(Value::as_str)
+            && !part
+                .get("thought")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        {
+            text.get_or_insert_with(String::new).push_str(part_text);
+        }
+    }
+    (text, tool_calls)
+}
+
+fn finish_reason(
+    response: &Value,
+    candidate: &Value,
+    has_tool_calls: bool,
+) -> Option<&'static str> {
+    if response.pointer("/promptFeedback/blockReason").is_some() && candidate.is_null() {
+        return Some("content_filter");
+    }
+    let reason = candidate.get("finishReason").and_then(Value::as_str)?;
+    Some(match reason {
+        "STOP" if has_tool_calls => "tool_calls",
+        "STOP" | "FINISH_REASON_UNSPECIFIED" => "stop",
+        "MAX_TOKENS" => "length",
+        "MALFORMED_FUNCTION_CALL" | "UNEXPECTED_TOOL_CALL" => "tool_calls",
+        _ => "content_filter",
+    })
+}
+
+fn convert_usage(usage: &Value) -> Value {
+    let prompt = usage["promptTokenCount"].as_u64().unwrap_or(0);
+    let candidates = usage["candidatesTokenCount"].as_u64().unwrap_or(0);
+    let thoughts = usage["thoughtsTokenCount"].as_u64().unwrap_or(0);
+    let completion = candidates + thoughts;
+    let mut mapped = json!({
+        "prompt_tokens": prompt,
+        "completion_tokens": completion,
+        "total_tokens": usage["totalTokenCount"].as_u64().unwrap_or(prompt + completion),
+    });
+    if let Some(cached) = usage.get("cachedContentTokenCount") {
+        mapped["prompt_tokens_details"] = json!({"cached_tokens": cached});
+    }
+    if thoughts > 0 {
+        mapped["completion_tokens_details"] = json!({"reasoning_tokens": thoughts});
+    }
+    mapped
+}
+
+fn convert_error(error: &Value) -> Value {
+    let status = error.get("status").and_then(Value::as_str).unwrap_or("");
+    let type_ = match status {
+        "INVALID_ARGUMENT" | "FAILED_PRECONDITION" | "NOT_FOUND" => "invalid_request_error",
+        "UNAUTHENTICATED" | "PERMISSION_DENIED" => "authentication_error",
+        "RESOURCE_EXHAUSTED" => "rate_limit_error",
+        _ => "api_error",
+    };
+    json!({
+        "error": {
+            "message": error.get("message").cloned().unwrap_or_else(|| json!("Unknown error")),
+            "type": type_,
+            "code": error.get("code").cloned().unwrap_or(Value::Null),
+        }
+    })
+}
+
+/// Appends parts to `contents`, merging consecutive turns of the same role
+/// (Gemini expects tool results for parallel calls in a single turn).
+fn push_content(contents: &mut Vec<Value>, role: &str, parts: Vec<Value>) {
+    if parts.is_empty() {
+        return;
+    }
+    if let Some(last) = contents.last_mut()
+        && last["role"] == role
+        && let Some(existing) = last["parts"].as_array_mut()
+    {
+        existing.extend(parts);
+        return;
+    }
+    contents.push(json!({"role": role, "parts": parts}));
+}
+
+fn openai_content_to_parts(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => vec![json!({"text": text})],
+        Some(Value::Array(items)) => items.iter().filter_map(openai_part_to_gemini).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn openai_part_to_gemini(part: &Value) -> Option<Value> {
+    match part.get("type").and_then(Value::as_str) {
+        Some("text") => part
+            .get("text")
+            .and_then(Value::as_str)
+            .map(|text| json!({"text": text})),
+        Some("image_url") => {
+            let url = part
+                .pointer("/image_url/url")
+                .or_else(|| part.get("image_url"))
+                .and_then(Value::as_str)?;
+            if !url.starts_with("data:") {
+                return Some(json!({"fileData": {"fileUri": url}}));
+            }
+            let (mime_type, data) = crate::converters::parse_data_url(url)?;
+            Some(json!({"inlineData": {"mimeType": mime_type, "data": data}}))
+        }
+        _ => None,
+    }
+}
+
+fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    let (mode, allowed) = match choice {
+        Value::String(mode) => match mode.as_str() {
+            "auto" => ("AUTO", None),
+            "none" => ("NONE", None),
+            "required" => ("ANY", None),
+            _ => return None,
+        },
+        Value::Object(_) => (
+            "ANY",
+            Some(choice.pointer("/function/name")?.as_str()?.to_string()),
+        ),
+        _ => return None,
+    };
+    let mut config = json!({"mode": mode});
+    if let Some(name) = allowed {
+        config["allowedFunctionNames"] = json!([name]);
+    }
+    Some(json!({"functionCallingConfig": config}))
+}
+
+fn generation_config(request: &Value) -> Map<String, Value> {
+    let mut config = Map::new();
+    for (from, to) in [
+        ("temperature", "temperature"),
+        ("top_p", "topP"),
+        ("top_k", "topK"),
+        ("n", "candidateCount"),
+        ("presence_penalty", "presencePenalty"),
+        ("frequency_penalty", "frequencyPenalty"),
+        ("seed", "seed"),
+        ("max_tokens", "maxOutputTokens"),
+        ("max_completion_tokens", "maxOutputTokens"),
+    ] {
+        if let Some(value) = request.get(from).filter(|v| !v.is_null()) {
+            config.insert(to.to_string(), value.clone());
+        }
+    }
+    match request.get("stop") {
+        Some(Value::String(stop)) => {
+            config.insert("stopSequences".to_string(), json!([stop]));
+        }
+        Some(Value::Array(stops)) => {
+            config.insert("stopSequences".to_string(), Value::Array(stops.clone()));
+        }
+        _ => {}
+    }
+    match request
+        .pointer("/response_format/type")
+        .and_then(Value::as_str)
+    {
+        Some("json_object") => {
+            config.insert("responseMimeType".to_string(), json!("application/json"));
+        }
+        Some("json_schema") => {
+            config.insert("responseMimeType".to_string(), json!("application/json"));
+            if let Some(schema) = request.pointer("/response_format/json_schema/schema") {
+                config.insert("responseJsonSchema".to_string(), schema.clone());
+            }
+        }
+        _ => {}
+    }
+    config
+}
+
+/// Drops JSON Schema keywords `functionDeclarations.parameters` rejects.
+fn sanitize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "additionalProperties"))
+                .map(|(key, value)| (key.clone(), sanitize_schema(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_generation_round_trips_through_imagen() {
+        let (path, body) = convert_openai_image_request_to_imagen(&Bytes::from(
+            r#"{"model":"imagen-3.0-generate-002","prompt":"a cat","n":2,"size":"1792x1024"}"#,
+        ))
+        .unwrap();
+        assert_eq!(path, "v1beta/models/imagen-3.0-generate-002:predict");
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["instances"][0]["prompt"], "a cat");
+        assert_eq!(body["parameters"]["sampleCount"], 2);
+        assert_eq!(body["parameters"]["aspectRatio"], "16:9");
+
+        let response: Value = serde_json::from_slice(&convert_imagen_response_to_openai(
+            Bytes::from(
+                r#"{"predictions":[{"bytesBase64Encoded":"AAA","mimeType":"image/png"},{"bytesBase64Encoded":"BBB","mimeType":"image/png"}]}"#,
+            ),
+        ))
+        .unwrap();
+        assert_eq!(response["data"][1]["b64_json"], "BBB");
+    }
+
+    #[test]
+    fn chat_request_maps_roles_tools_and_generation_config() {
+        let body = Bytes::from(
+            json!({
+                "model": "gemini-2.5-pro",
+                "stream": true,
+                "max_tokens": 64,
+                "stop": "END",
+                "messages": [
+                    {"role": "system", "content": "be brief"},
+                    {"role": "user", "content": "weather?"},
+                    {"role": "assistant", "content": null, "tool_calls": [{
+                        "id": "call_1", "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"},
+                        "extra_content": {"google": {"thought_signature": "sig"}}
+                    }]},
+                    {"role": "tool", "tool_call_id": "call_1", "content": "sunny"}
+                ],
+                "tools": [{"type": "function", "function": {
+                    "name": "get_weather",
+                    "parameters": {"type": "object", "additionalProperties": false,
+                        "properties": {"city": {"type": "string"}}}
+                }}],
+                "tool_choice": "required"
+            })
+            .to_string(),
+        );
+        let request = convert_openai_to_gemini(&body).unwrap();
+        assert_eq!(
+            request.path(),
+            "v1beta/models/gemini-2.5-pro:streamGenerateContent"
+        );
+        assert_eq!(request.query(), Some("alt=sse"));
+
+        let converted: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            converted["systemInstruction"]["parts"][0]["text"],
+            "be brief"
+        );
+        assert_eq!(converted["contents"][0]["role"], "user");
+        assert_eq!(converted["contents"][1]["role"], "model");
+        let call = &converted["contents"][1]["parts"][0];
+        assert_eq!(call["functionCall"]["args"]["city"], "Paris");
+        assert_eq!(call["thoughtSignature"], "sig");
+        let result = &converted["contents"][2]["parts"][0]["functionResponse"];
+        assert_eq!(result["name"], "get_weather");
+        assert_eq!(result["response"]["content"], "sunny");
+        let declaration = &converted["tools"][0]["functionDeclarations"][0];
+        assert!(
+            declaration["parameters"]
+                .get("additionalProperties")
+                .is_none()
+        );
+        assert_eq!(
+            converted["toolConfig"]["functionCallingConfig"]["mode"],
+            "ANY"
+        );
+        assert_eq!(converted["generationConfig"]["maxOutputTokens"], 64);
+        assert_eq!(converted["generationConfig"]["stopSequences"][0], "END");
+        assert!(converted.get("model").is_none());
+    }
+
+    #[test]
+    fn response_maps_function_calls_and_usage() {
+        let body = Bytes::from(
+            json!({
+                "responseId": "abc",
+                "modelVersion": "gemini-2.5-pro",
+                "candidates": [{
+                    "content": {"role": "model", "parts": [
+                        {"text": "thinking", "thought": true},
+                        {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}},
+                         "thoughtSignature": "sig"}
+                    ]},
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5,
+                    "thoughtsTokenCount": 3, "totalTokenCount": 18}
+            })
+            .to_string(),
+        );
+        let converted: Value =
+            serde_json::from_slice(&convert_gemini_response_to_openai(body)).unwrap();
+        let choice = &converted["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert!(choice["message"]["content"].is_null());
+        let call = &choice["message"]["tool_calls"][0];
+        assert_eq!(call["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(call["extra_content"]["google"]["thought_signature"], "sig");
+        assert_eq!(converted["usage"]["completion_tokens"], 8);
+        assert_eq!(converted["usage"]["total_tokens"], 18);
+    }
+
+    #[test]
+    fn error_response_is_openai_shaped() {
+        let body = Bytes::from(
+            r#"{"error":{"code":429,"message":"quota","status":"RESOURCE_EXHAUSTED"}}"#,
+        );
+        let converted: Value =
+            serde_json::from_slice(&convert_gemini_response_to_openai(body)).unwrap();
+        assert_eq!(converted["error"]["type"], "rate_limit_error");
+        assert_eq!(converted["error"]["message"], "quota");
+    }
+
+    #[tokio::test]
+    async fn stream_is_converted_to_openai_chunks() {
+        let events = vec![
+            Ok::<_, io::Error>(Bytes::from(
+                "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hel\"}]}}],\"modelVersion\":\"g\"}\r\n\r\ndata: {\"candidates\":[{\"content\":",
+            )),
+            Ok(Bytes::from(
+                "{\"parts\":[{\"text\":\"lo\"}]},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":2,\"candidatesTokenCount\":1,\"totalTokenCount\":3}}\r\n\r\n",
+            )),
+        ];
+        let output: Vec<Bytes> = convert_gemini_stream_to_openai(stream::iter(events))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let text = String::from_utf8(output.concat()).unwrap();
+        let chunks: Vec<Value> = text
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[0]["choices"][0]["delta"]["content"], "Hel");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "lo");
+        assert_eq!(chunks[1]["choices"][0]["finish_reason"], "stop");
+        assert_eq!(chunks[2]["usage"]["total_tokens"], 3);
+        assert!(text.ends_with("data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Content guardrails (`guardrails`).
+//!
+//! Prompts, and non-streaming responses for rules that ask for it, are
+//! checked against the configured block lists (regular expressions and
+//! case-insensitive keywords) and optionally against a moderation endpoint
+//! reached through one of the gateway's channels. Only the text a model
+//! reads or writes is inspected: message contents, system prompts, `prompt`
+//! / `input` fields, and the text parts of responses.
+
+use crate::config::{Channel, GuardrailRule, GuardrailScope, Guardrails, ModerationSettings};
+use crate::providers::{ProviderRegistry, RouteKind};
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+
+/// Object keys whose string values are model input or output.
+const TEXT_KEYS: &[&str] = &[
+    "content",
+    "text",
+    "system",
+    "prompt",
+    "input",
+    "instructions",
+];
+
+/// Side of the exchange being checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Request,
+    Response,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Request => "request",
+            Stage::Response => "response",
+        }
+    }
+}
+
+/// Why content was blocked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Rule name, or `moderation`.
+    pub rule: String,
+    pub stage: Stage,
+    /// Moderation categories that were flagged (empty for block lists).
+    pub categories: Vec<String>,
+}
+
+impl Violation {
+    pub fn message(&self) -> String {
+        let mut message = format!(
+            "{} blocked by guardrail '{}'",
+            match self.stage {
+                Stage::Request => "Request",
+                Stage::Response => "Response",
+            },
+            self.rule
+        );
+        if !self.categories.is_empty() {
+            message.push_str(&format!(" ({})", self.categories.join(", ")));
+        }
+        message
+    }
+
+    /// Details returned to the client next to the error message.
+    pub fn details(&self) -> Value {
+        json!({
+            "rule": self.rule,
+            "stage": self.stage.as_str(),
+            "categories": self.categories,
+        })
+    }
+}
+
+fn applies(scope: GuardrailScope, stage: Stage) -> bool {
+    matches!(
+        (scope, stage),
+        (GuardrailScope::Both, _)
+            | (GuardrailScope::Request, Stage::Request)
+            | (GuardrailScope::Response, Stage::Response)
+    )
+}
+
+/// Whether any rule or the moderation endpoint checks `stage`.
+pub fn inspects(guardrails: &Guardrails, stage: Stage) -> bool {
+    guardrails
+        .rules
+        .iter()
+        .any(|rule| applies(rule.applies_to, stage))
+        || guardrails
+            .moderation
+            .as_ref()
+            .is_some_and(|m| applies(m.applies_to, stage))
+}
+
+/// Problems with the guardrail config, for `validate_config`.
+pub fn check(guardrails: &Guardrails) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+    for rule in &guardrails.rules {
+        if !names.insert(rule.name.as_str()) {
+            problems.push(format!("duplicate guardrail rule {:?}", rule.name));
+        }
+        for pattern in &rule.patterns {
+            if let Err(e) = Regex::new(pattern) {
+                problems.push(format!(
+                    "guardrails.rules[name={}] has an invalid pattern: {e}",
+                    rule.name
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Text of a JSON request or response body; empty for other bodies.
+pub fn body_text(body: &[u8]) -> String {
+    serde_json::from_slice::<Value>(body)
+        .map(|value| value_text(&value))
+        .unwrap_or_default()
+}
+
+/// Model input or output text found anywhere in `value`.
+pub fn value_text(value: &Value) -> String {
+    let mut texts = Vec::new();
+    collect_text(value, false, &mut texts);
+    texts.join("\n")
+}
+
+fn collect_text<'a>(value: &'a Value, is_text: bool, texts: &mut Vec<&'a str>) {
+    match value {
+        Value::String(text) if is_text => texts.push(text),
+        Value::Array(items) => {
+            for item in items {
+                collect_text(item, is_text, texts);
+            }
+        }
+        Value::Object(object) => {
+            for (key, item) in object {
+                collect_text(item, TEXT_KEYS.contains(&key.as_str()), texts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// First block-list rule for `stage` that `text` matches.
+pub fn match_rules(rules: &[GuardrailRule], stage: Stage, text: &str) -> Option<Violation> {
+    if text.is_empty() {
+        return None;
+    }
+    let lowered = text.to_lowercase();
+    rules
+        .iter()
+        .filter(|rule| applies(rule.applies_to, stage))
+        .find(|rule| {
+            rule.keywords
+                .iter()
+                .any(|keyword| !keyword.is_empty() && lowered.contains(&keyword.to_lowercase()))
+                || rule
+                    .patterns
+                    .iter()
+                    .filter_map(|pattern| Regex::new(pattern).ok())
+                    .any(|regex| regex.is_match(text))
+        })
+        .map(|rule| Violation {
+            rule: rule.name.clone(),
+            stage,
+            categories: Vec::new(),
+        })
+}
+
+/// Block lists first, then the moderation endpoint when one applies.
+pub async fn evaluate(
+    guardrails: &Guardrails,
+    channels: &[Channel],
+    registry: &ProviderRegistry,
+    client: &reqwest::Client,
+    stage: Stage,
+    body: &[u8],
+) -> Option<Violation> {
+    let text = body_text(body);
+    if let Some(violation) = match_rules(&guardrails.rules, stage, &text) {
+        return Some(violation);
+    }
+    let moderation = guardrails
+        .moderation
+        .as_ref()
+        .filter(|m| applies(m.applies_to, stage) && !text.is_empty())?;
+    let Some(channel) = channels.iter().find(|c| c.name == moderation.channel) else {
+        tracing::warn!(
+            "Guardrails: moderation channel '{}' not found",
+            moderation.channel
+        );
+        return fail_closed(moderation, stage);
+    };
+    match moderate(registry, client, channel, moderation, &text).await {
+        Ok(Some(categories)) => Some(Violation {
+            rule: "moderation".to_string(),
+            stage,
+            categories,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Guardrails: moderation request failed: {e:#}");
+            fail_closed(moderation, stage)
+        }
+    }
+}
+
+fn fail_closed(moderation: &ModerationSettings, stage: Stage) -> Option<Violation> {
+    moderation.fail_closed.then(|| Violation {
+        rule: "moderation_unavailable".to_string(),
+        stage,
+        categories: Vec::new(),
+    })
+}
+
+/// Flagged categories, or `None` when the moderation endpoint lets `text`
+/// through.
+async fn moderate(
+    registry: &ProviderRegistry,
+    client: &reqwest::Client,
+    channel: &Channel,
+    moderation: &ModerationSettings,
+    text: &str,
+) -> anyhow::Result<Option<Vec<String>>> {
+    let mut request = json!({"input": text});
+    if let Some(model) = &moderation.model {
+        request["model"] = json!(model);
+    }
+    let prepared = crate::providers::prepare_request(
+        registry,
+        channel,
+        RouteKind::Openai,
+        &channel.base_url,
+        "/v1/moderations",
+        None,
+        &HeaderMap::new(),
+        &Bytes::from(request.to_string()),
+    )?;
+    let response = client
+        .post(prepared.url)
+        .headers(prepared.headers)
+        .header("content-type", "application/json")
+        .body(prepared.body)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("moderation endpoint returned {status}");
+    }
+    let body: Value = response.json().await?;
+    let results = body["results"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("moderation response has no results"))?;
+    if !results.iter().any(|r| r["flagged"].as_bool() == Some(true)) {
+        return Ok(None);
+    }
+    let mut categories: Vec<String> = results
+        .iter()
+        .filter_map(|r| r["categories"].as_object())
+        .flat_map(|c| c.iter())
+        .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+        .map(|(name, _)| name.clone())
+        .collect();
+    categories.sort();
+    categories.dedup();
+    Ok(Some(categories))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(value: Value) -> GuardrailRule {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn collects_only_model_text() {
+        let body = json!({
+            "model": "secret-model",
+            "system": "Be nice",
+            "messages": [
+                {"role": "user", "content": "hello"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "look"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]}
+            ]
+        });
+        assert_eq!(
+            body_text(body.to_string().as_bytes()),
+            "hello\nlook\nBe nice"
+        );
+        assert_eq!(body_text(b"--multipart--"), "");
+    }
+
+    #[test]
+    fn matches_keywords_and_patterns_for_their_stage() {
+        let rules = vec![
+            rule(json!({"name": "secrets", "patterns": ["sk-[A-Za-z0-9]{8,}"]})),
+            rule(json!({"name": "projects", "keywords": ["Project Falcon"], "applies_to": "both"})),
+            rule(json!({"name": "leaks", "keywords": ["internal only"], "applies_to": "response"})),
+        ];
+        let hit = match_rules(&rules, Stage::Request, "my key is sk-abcdef123456").unwrap();
+        assert_eq!(hit.rule, "secrets");
+        assert_eq!(
+            match_rules(&rules, Stage::Response, "about project falcon")
+                .unwrap()
+                .rule,
+            "projects"
+        );
+        assert!(match_rules(&rules, Stage::Request, "INTERNAL ONLY").is_none());
+        assert_eq!(
+            match_rules(&rules, Stage::Response, "INTERNAL ONLY")
+                .unwrap()
+                .rule,
+            "leaks"
+        );
+        assert!(match_rules(&rules, Stage::Request, "hello").is_none());
+    }
+
+    #[test]
+    fn reports_invalid_patterns_and_duplicate_names() {
+        let guardrails = Guardrails {
+            rules: vec![
+                rule(json!({"name": "a", "patterns": ["("]})),
+                rule(json!({"name": "a", "keywords": ["x"]})),
+            ],
+            moderation: None,
+        };
+        let problems = check(&guardrails);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("invalid pattern"));
+        assert!(problems[1].contains("duplicate guardrail rule"));
+    }
+}
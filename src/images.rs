@@ -0,0 +1,126 @@
+//! Helpers for the image endpoints (`/v1/images/generations`, `edits`,
+//! `variations`).
+//!
+//! Generations are JSON and route like chat. Edits and variations are
+//! `multipart/form-data` uploads, so the model used for routing (and rewritten
+//! by a channel's `model_map`) lives in a form field instead of a JSON key.
+//! Audio transcriptions and translations upload the same way and reuse the
+//! multipart helpers.
+//! The boundary is read from the body's first line, which keeps these helpers
+//! usable where only the body is at hand (provider adapters).
+
+use axum::body::Bytes;
+use serde_json::Value;
+
+/// Value of a text field in a multipart form body.
+pub fn multipart_field(body: &[u8], name: &str) -> Option<String> {
+    let (start, end) = multipart_field_range(body, name)?;
+    String::from_utf8(body[start..end].to_vec()).ok()
+}
+
+/// Copy of a multipart form body with the text field `name` set to `value`,
+/// or `None` when the body has no such field.
+pub fn replace_multipart_field(body: &Bytes, name: &str, value: &str) -> Option<Bytes> {
+    let (start, end) = multipart_field_range(body, name)?;
+    let mut replaced = Vec::with_capacity(body.len() + value.len());
+    replaced.extend_from_slice(&body[..start]);
+    replaced.extend_from_slice(value.as_bytes());
+    replaced.extend_from_slice(&body[end..]);
+    Some(Bytes::from(replaced))
+}
+
+/// Byte range of the value of field `name`.
+fn multipart_field_range(body: &[u8], name: &str) -> Option<(usize, usize)> {
+    let first_line_end = find(body, b"\r\n", 0)?;
+    let boundary = body[..first_line_end].strip_prefix(b"--")?;
+    if boundary.is_empty() {
+        return None;
+    }
+    let delimiter = [b"\r\n--".as_slice(), boundary].concat();
+    let disposition = format!("name=\"{name}\"");
+
+    let mut part_start = first_line_end + 2;
+    loop {
+        let part_end = find(body, &delimiter, part_start)?;
+        let headers_end = find(&body[..part_end], b"\r\n\r\n", part_start)?;
+        let headers = String::from_utf8_lossy(&body[part_start..headers_end]);
+        let is_field = headers.lines().any(|line| {
+            line.to_ascii_lowercase()
+                .starts_with("content-disposition:")
+                && line.split(';').any(|param| param.trim() == disposition)
+        });
+        if is_field {
+            return Some((headers_end + 4, part_end));
+        }
+        // Skip past the delimiter and its trailing CRLF (or final `--`).
+        part_start = part_end + delimiter.len() + 2;
+        if part_start >= body.len() {
+            return None;
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
+/// Number of images in an OpenAI images response (`data[]` entries carrying
+/// `b64_json` or `url`).
+pub fn generated_images(response: &Value) -> u64 {
+    response
+        .get("data")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter(|item| item.get("b64_json").is_some() || item.get("url").is_some())
+                .count() as u64
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "--XyZ\r\nContent-Disposition: form-data; name=\"image\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n\x00PNG\r\n--XyZ\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\ngpt-image-1\r\n--XyZ\r\nContent-Disposition: form-data; name=\"prompt\"\r\n\r\nadd a hat\r\n--XyZ--\r\n";
+
+    #[test]
+    fn reads_and_rewrites_multipart_fields() {
+        assert_eq!(
+            multipart_field(BODY.as_bytes(), "model").as_deref(),
+            Some("gpt-image-1")
+        );
+        assert_eq!(
+            multipart_field(BODY.as_bytes(), "prompt").as_deref(),
+            Some("add a hat")
+        );
+        assert!(multipart_field(BODY.as_bytes(), "size").is_none());
+        assert!(multipart_field(b"{\"model\":\"m\"}", "model").is_none());
+
+        let rewritten = replace_multipart_field(&Bytes::from(BODY), "model", "dall-e-2").unwrap();
+        assert_eq!(
+            multipart_field(&rewritten, "model").as_deref(),
+            Some("dall-e-2")
+        );
+        assert_eq!(
+            multipart_field(&rewritten, "prompt").as_deref(),
+            Some("add a hat")
+        );
+    }
+
+    #[test]
+    fn counts_generated_images() {
+        let response = serde_json::json!({
+            "created": 1,
+            "data": [{"b64_json": "aaa"}, {"url": "https://example.com/a.png"}]
+        });
+        assert_eq!(generated_images(&response), 2);
+        let embeddings = serde_json::json!({"data": [{"embedding": [0.1]}]});
+        assert_eq!(generated_images(&embeddings), 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Per-channel API key rotation (`api_keys` / `key_strategy`, see
+//! `config::Channel`).
+//!
+//! A channel with more than one key gets a pool. Every upstream call picks a
+//! key by the channel's strategy, and the upstream status is reported back:
+//! `401`/`403` quarantine the key for a while, `429` for the upstream's
+//! `Retry-After` (or a minute). Quarantined keys are skipped until they
+//! expire; when every key is quarantined the one due back soonest is used,
+//! so a pool never blocks traffic on its own.
+
+use crate::config::{Channel, KeyStrategy};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a key rejected as unauthorized stays out of rotation.
+const AUTH_QUARANTINE: Duration = Duration::from_secs(300);
+/// Quarantine after a `429` without `Retry-After`.
+const RATE_LIMIT_QUARANTINE: Duration = Duration::from_secs(60);
+/// Longest quarantine a `Retry-After` can ask for.
+const MAX_QUARANTINE: Duration = Duration::from_secs(600);
+
+#[derive(Default)]
+pub struct KeyPools {
+    pools: Mutex<HashMap<String, Pool>>,
+}
+
+#[derive(Default)]
+struct Pool {
+    cursor: usize,
+    keys: HashMap<String, KeyState>,
+}
+
+#[derive(Default)]
+struct KeyState {
+    quarantined_until: Option<Instant>,
+    last_limited: Option<Instant>,
+}
+
+impl KeyPools {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `channel` with the key to use for this call; borrowed unchanged when
+    /// the channel has a single key.
+    pub fn keyed<'a>(&self, channel: &'a Channel) -> Cow<'a, Channel> {
+        match self.pick(channel) {
+            Some(key) if key != channel.api_key => Cow::Owned(Channel {
+                api_key: key,
+                ..channel.clone()
+            }),
+            _ => Cow::Borrowed(channel),
+        }
+    }
+
+    /// Next key for `channel`, or `None` when it has no pool.
+    pub fn pick(&self, channel: &Channel) -> Option<String> {
+        let keys = pool_keys(channel);
+        if keys.len() < 2 {
+            return None;
+        }
+        let now = Instant::now();
+        let mut pools = self.pools.lock().unwrap();
+        let pool = pools.entry(channel.name.clone()).or_default();
+        let start = pool.cursor % keys.len();
+        // Rotation order from the cursor, so ties go round-robin.
+        let rotation = (0..keys.len()).map(|offset| (start + offset) % keys.len());
+        let available = |idx: &usize| {
+            pool.keys
+                .get(keys[*idx])
+                .and_then(|state| state.quarantined_until)
+                .is_none_or(|until| until <= now)
+        };
+
+        let chosen = match channel.key_strategy.unwrap_or_default() {
+            KeyStrategy::RoundRobin => rotation.clone().find(available),
+            KeyStrategy::LeastRecentlyLimited => rotation
+                .clone()
+                .filter(available)
+                .min_by_key(|idx| pool.keys.get(keys[*idx]).and_then(|s| s.last_limited)),
+        };
+        // Every key is quarantined: fail open with the one due back first.
+        let chosen = chosen.unwrap_or_else(|| {
+            rotation
+                .min_by_key(|idx| {
+                    pool.keys
+                        .get(keys[*idx])
+                        .and_then(|state| state.quarantined_until)
+                })
+                .unwrap_or(start)
+        });
+        pool.cursor = chosen + 1;
+        Some(keys[chosen].to_string())
+    }
+
+    /// Feed back the upstream status for a call made with `key`.
+    pub fn report(&self, channel: &Channel, key: &str, status: u16, retry_after: Option<Duration>) {
+        let quarantine = match status {
+            401 | 403 => AUTH_QUARANTINE,
+            429 => retry_after
+                .unwrap_or(RATE_LIMIT_QUARANTINE)
+                .min(MAX_QUARANTINE),
+            _ => return,
+        };
+        if pool_keys(channel).len() < 2 {
+            return;
+        }
+        tracing::warn!(
+            "Key Quarantined: channel '{}' key {} got {} ({}s)",
+            channel.name,
+            crate::utils::mask_secret(key),
+            status,
+            quarantine.as_secs()
+        );
+        let now = Instant::now();
+        let mut pools = self.pools.lock().unwrap();
+        let state = pools
+            .entry(channel.name.clone())
+            .or_default()
+            .keys
+            .entry(key.to_string())
+            .or_default();
+        state.quarantined_until = Some(now + quarantine);
+        state.last_limited = Some(now);
+    }
+}
+
+/// `api_key` (when set) followed by `api_keys`, without duplicates.
+fn pool_keys(channel: &Channel) -> Vec<&str> {
+    let mut keys: Vec<&str> = Vec::with_capacity(channel.api_keys.len() + 1);
+    for key in std::iter::once(&channel.api_key).chain(channel.api_keys.iter()) {
+        let key = key.as_str();
+        if !key.trim().is_empty() && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderType;
+
+    fn channel(strategy: Option<KeyStrategy>) -> Channel {
+        Channel {
+            name: "pooled".to_string(),
+            provider_type: ProviderType::Openai,
+            base_url: "https://api.example.com".to_string(),
+            api_key: "k1".to_string(),
+            api_keys: vec!["k2".to_string(), "k3".to_string(), "k1".to_string()],
+            key_strategy: strategy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_robin_skips_quarantined_keys_and_fails_open() {
+        let pools = KeyPools::new();
+        let channel = channel(None);
+        let picks: Vec<String> = (0..4).filter_map(|_| pools.pick(&channel)).collect();
+        assert_eq!(picks, vec!["k1", "k2", "k3", "k1"]);
+
+        pools.report(&channel, "k2", 429, Some(Duration::from_secs(30)));
+        pools.report(&channel, "k3", 500, None);
+        let picks: Vec<String> = (0..3).filter_map(|_| pools.pick(&channel)).collect();
+        assert_eq!(picks, vec!["k3", "k1", "k3"]);
+
+        pools.report(&channel, "k1", 401, None);
+        pools.report(&channel, "k3", 403, None);
+        // All quarantined: the key due back first (k2, 30s) keeps traffic flowing.
+        assert_eq!(pools.pick(&channel).as_deref(), Some("k2"));
+    }
+
+    #[test]
+    fn least_recently_limited_prefers_keys_never_limited() {
+        let pools = KeyPools::new();
+        let channel = channel(Some(KeyStrategy::LeastRecentlyLimited));
+        pools.report(&channel, "k1", 429, Some(Duration::ZERO));
+        pools.report(&channel, "k2", 429, Some(Duration::ZERO));
+        for _ in 0..3 {
+            assert_eq!(pools.pick(&channel).as_deref(), Some("k3"));
+        }
+        pools.report(&channel, "k3", 429, Some(Duration::ZERO));
+        assert_eq!(pools.pick(&channel).as_deref(), Some("k1"));
+    }
+
+    #[test]
+    fn single_key_channels_have_no_pool() {
+        let pools = KeyPools::new();
+        let mut channel = channel(None);
+        channel.api_keys = vec!["k1".to_string()];
+        assert_eq!(pools.pick(&channel), None);
+        assert!(matches!(pools.keyed(&channel), Cow::Borrowed(_)));
+    }
+}
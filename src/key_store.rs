@@ -0,0 +1,345 @@
+//! Managed team keys (`Team::keys`, see `config::TeamKey`).
+//!
+//! Keys are created by `apex key create` (and for new teams by `apex team
+//! add` and the admin API), which print the plaintext once and store only a
+//! SHA-256 hash. Each key may expire, may be narrowed to a subset of the
+//! team's models, and is soft-revoked (kept, but rejected) rather than
+//! deleted. The legacy plaintext `Team::api_key` is deprecated: it still
+//! works, and `apex key migrate` turns it into a managed key.
+
+use crate::config::{Config, Team, TeamKey, model_pattern_matches};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use ring::digest;
+
+/// Plaintext characters kept in `TeamKey::prefix` (at most half the key).
+const PREFIX_LEN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+impl KeyStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyStatus::Active => "active",
+            KeyStatus::Expired => "expired",
+            KeyStatus::Revoked => "revoked",
+        }
+    }
+}
+
+/// The team a presented key belongs to, and the managed key it matched
+/// (`None` for the legacy `api_key`).
+pub struct KeyMatch<'a> {
+    pub team: &'a Team,
+    pub key: Option<&'a TeamKey>,
+}
+
+impl TeamKey {
+    pub fn status(&self, now: DateTime<Utc>) -> KeyStatus {
+        if self.revoked_at.is_some() {
+            return KeyStatus::Revoked;
+        }
+        // An unreadable expiry fails closed; `validate_config` reports it.
+        let expired = self
+            .expires_at
+            .as_deref()
+            .is_some_and(|at| !DateTime::parse_from_rfc3339(at).is_ok_and(|at| at > now));
+        if expired {
+            KeyStatus::Expired
+        } else {
+            KeyStatus::Active
+        }
+    }
+}
+
+/// Whether a key scope (`TeamKey::models`) admits `model`; the team policy
+/// is checked separately. An empty scope admits every model; otherwise one of the patterns must
+/// match, as in router rules.
+pub fn scope_allows(models: &[String], model: &str) -> bool {
+    models.is_empty()
+        || models
+            .iter()
+            .any(|pattern| model_pattern_matches(pattern, model))
+}
+
+pub fn hash_key(plaintext: &str) -> String {
+    let digest = digest::digest(&digest::SHA256, plaintext.as_bytes());
+    let hex: String = digest.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256:{hex}")
+}
+
+/// Build a key for a team. Returns the stored record and the plaintext,
+/// which is not kept anywhere else.
+pub fn new_key(
+    name: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    models: Vec<String>,
+) -> (TeamKey, String) {
+    let secret = format!("sk-ap-{}", random_alnum(32));
+    let mut key = import_key(&secret, name);
+    key.expires_at = expires_at.map(|at| at.to_rfc3339());
+    key.models = models;
+    (key, secret)
+}
+
+/// Managed record of an existing plaintext key (one chosen by an operator,
+/// or a legacy `Team::api_key` being migrated).
+pub fn import_key(secret: &str, name: Option<String>) -> TeamKey {
+    TeamKey {
+        id: format!("key_{}", random_alnum(12).to_lowercase()),
+        name,
+        hash: hash_key(secret),
+        // Never most of a short, operator-chosen secret.
+        prefix: secret
+            .chars()
+            .take(PREFIX_LEN.min(secret.chars().count() / 2))
+            .collect(),
+        created_at: Utc::now().to_rfc3339(),
+        expires_at: None,
+        models: Vec::new(),
+        revoked_at: None,
+    }
+}
+
+/// Id the team's key usage is recorded under: the legacy key's fingerprint,
+/// or the id of its first managed key (the one issued with the team).
+pub fn usage_id(team: &Team) -> Option<String> {
+    if !team.api_key.is_empty() {
+        return Some(crate::utils::key_fingerprint(&team.api_key));
+    }
+    team.keys.first().map(|key| key.id.clone())
+}
+
+/// How a team's key is shown outside of its creation: the masked legacy key,
+/// or the prefix of its first managed key.
+pub fn masked_key(team: &Team) -> String {
+    if !team.api_key.is_empty() {
+        return crate::utils::mask_secret(&team.api_key);
+    }
+    team.keys
+        .first()
+        .map(|key| format!("{}…", key.prefix))
+        .unwrap_or_default()
+}
+
+/// Ids of the teams that still have a plaintext `api_key`.
+pub fn legacy_key_teams(config: &Config) -> Vec<&str> {
+    config
+        .teams
+        .iter()
+        .filter(|team| !team.api_key.is_empty())
+        .map(|team| team.id.as_str())
+        .collect()
+}
+
+/// Replace every team's plaintext `api_key` with a managed key (named
+/// `legacy`) that accepts the same secret. Returns the migrated team ids.
+pub fn migrate_legacy_keys(config: &mut Config) -> Vec<String> {
+    let teams = std::sync::Arc::make_mut(&mut config.teams);
+    let mut migrated = Vec::new();
+    for team in teams.iter_mut().filter(|team| !team.api_key.is_empty()) {
+        let secret = std::mem::take(&mut team.api_key);
+        let hash = hash_key(&secret);
+        if !team.keys.iter().any(|key| key.hash == hash) {
+            team.keys
+                .push(import_key(&secret, Some("legacy".to_string())));
+        }
+        migrated.push(team.id.clone());
+    }
+    migrated
+}
+
+/// Owner of `presented`, whether it is one of a team's managed keys (in any
+/// status) or a deprecated legacy key.
+pub fn find<'a>(config: &'a Config, presented: &str) -> Option<KeyMatch<'a>> {
+    if let Some(team) = config
+        .teams
+        .iter()
+        .find(|t| !t.api_key.is_empty() && t.api_key == presented)
+    {
+        return Some(KeyMatch { team, key: None });
+    }
+    let hash = hash_key(presented);
+    config.teams.iter().find_map(|team| {
+        team.keys
+            .iter()
+            .find(|key| key.hash == hash)
+            .map(|key| KeyMatch {
+                team,
+                key: Some(key),
+            })
+    })
+}
+
+/// Soft-revoke the managed key whose id or plaintext is `id_or_key`.
+/// Returns the owning team and the key id, or `None` when no managed key
+/// matches (the caller then falls back to the `revoked_keys` blocklist).
+pub fn revoke(config: &mut Config, id_or_key: &str) -> Option<(String, String)> {
+    let hash = hash_key(id_or_key);
+    let teams = std::sync::Arc::make_mut(&mut config.teams);
+    teams.iter_mut().find_map(|team| {
+        let key = team
+            .keys
+            .iter_mut()
+            .find(|k| k.id == id_or_key || k.hash == hash)?;
+        key.revoked_at
+            .get_or_insert_with(|| Utc::now().to_rfc3339());
+        Some((team.id.clone(), key.id.clone()))
+    })
+}
+
+/// Parse `--expires`: a duration from now (`30d`, `12h`, `45m`), a date
+/// (`2026-12-31`, end of that day UTC) or an RFC 3339 timestamp.
+pub fn parse_expiry(value: &str, now: DateTime<Utc>) -> anyhow::Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let end_of_day = date.and_hms_opt(23, 59, 59).expect("valid time");
+        return Ok(end_of_day.and_utc());
+    }
+    let split = value.char_indices().last().map_or(0, |(idx, _)| idx);
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| invalid_expiry(value))?;
+    let duration = match unit {
+        "d" => TimeDelta::try_days(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        _ => return Err(invalid_expiry(value)),
+    };
+    duration
+        .and_then(|duration| now.checked_add_signed(duration))
+        .ok_or_else(|| invalid_expiry(value))
+}
+
+fn invalid_expiry(value: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Invalid expiry '{}': use a duration (30d, 12h, 45m), a date (YYYY-MM-DD) or an RFC 3339 timestamp",
+        value
+    )
+}
+
+fn random_alnum(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_keys_store_only_the_hash() {
+        let (key, secret) = new_key(None, None, vec![]);
+        assert!(secret.starts_with("sk-ap-"));
+        assert!(key.id.starts_with("key_"));
+        assert_eq!(key.hash, hash_key(&secret));
+        assert_ne!(key.hash, secret);
+        assert!(secret.starts_with(&key.prefix));
+        assert_eq!(key.status(Utc::now()), KeyStatus::Active);
+    }
+
+    #[test]
+    fn status_reports_expiry_and_revocation() {
+        let now = Utc::now();
+        let (mut key, _) = new_key(Some("ci".to_string()), None, vec![]);
+        key.expires_at = Some((now - TimeDelta::minutes(1)).to_rfc3339());
+        assert_eq!(key.status(now), KeyStatus::Expired);
+        key.expires_at = Some((now + TimeDelta::days(1)).to_rfc3339());
+        assert_eq!(key.status(now), KeyStatus::Active);
+        key.revoked_at = Some(now.to_rfc3339());
+        assert_eq!(key.status(now), KeyStatus::Revoked);
+    }
+
+    #[test]
+    fn unreadable_expiry_counts_as_expired() {
+        let (mut key, _) = new_key(None, None, vec![]);
+        for at in ["2999-12-31", "2999-12-31 00:00", "never"] {
+            key.expires_at = Some(at.to_string());
+            assert_eq!(key.status(Utc::now()), KeyStatus::Expired, "{at}");
+        }
+    }
+
+    #[test]
+    fn model_scope_uses_router_patterns() {
+        assert!(scope_allows(&[], "gpt-4o"));
+        let scope = vec!["gpt-4o-mini".to_string(), "claude-*".to_string()];
+        assert!(scope_allows(&scope, "gpt-4o-mini"));
+        assert!(scope_allows(&scope, "claude-3-haiku"));
+        assert!(!scope_allows(&scope, "gpt-4o"));
+    }
+
+    #[test]
+    fn parse_expiry_accepts_durations_dates_and_timestamps() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_expiry("30d", now).unwrap().to_rfc3339(),
+            "2026-01-31T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_expiry("12h", now).unwrap().to_rfc3339(),
+            "2026-01-01T12:00:00+00:00"
+        );
+        assert_eq!(
+            parse_expiry("2026-03-01", now).unwrap().to_rfc3339(),
+            "2026-03-01T23:59:59+00:00"
+        );
+        assert_eq!(
+            parse_expiry("2026-02-01T08:00:00+08:00", now)
+                .unwrap()
+                .to_rfc3339(),
+            "2026-02-01T00:00:00+00:00"
+        );
+        assert!(parse_expiry("0d", now).is_err());
+        assert!(parse_expiry("soon", now).is_err());
+        for huge in ["99999999d", "9999999999999h", "9223372036854775807m"] {
+            assert!(parse_expiry(huge, now).is_err(), "{huge}");
+        }
+    }
+
+    #[test]
+    fn migration_hashes_legacy_keys() {
+        let mut config: Config = serde_json::from_value(serde_json::json!({
+            "version": "1.0",
+            "global": {
+                "listen": "127.0.0.1:12356",
+                "auth_keys": [],
+                "timeouts": {"connect_ms": 1000, "request_ms": 1000, "response_ms": 1000},
+                "retries": {"max_attempts": 1, "backoff_ms": 100, "retry_on_status": [500]},
+                "cors_allowed_origins": []
+            },
+            "metrics": {"enabled": false, "path": "/metrics"},
+            "hot_reload": {"config_path": "config.json", "watch": false},
+            "teams": [
+                {"id": "old", "api_key": "sk-legacy", "policy": {"allowed_routers": []}},
+                {"id": "new", "api_key": "", "policy": {"allowed_routers": []}}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(legacy_key_teams(&config), ["old"]);
+        assert_eq!(migrate_legacy_keys(&mut config), ["old"]);
+        assert!(legacy_key_teams(&config).is_empty());
+        let found = find(&config, "sk-legacy").unwrap();
+        assert_eq!(found.team.id, "old");
+        let key = found.key.unwrap();
+        assert_eq!(key.name.as_deref(), Some("legacy"));
+        assert_eq!(key.status(Utc::now()), KeyStatus::Active);
+        assert!(migrate_legacy_keys(&mut config).is_empty());
+    }
+}
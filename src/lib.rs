@@ -1,14 +1,49 @@
+pub mod access_audit;
+pub mod body_model;
+pub mod canary;
+pub mod channel_health;
+pub mod channel_limits;
 pub mod compliance;
 pub mod config;
+pub mod config_includes;
+pub mod config_store;
 pub mod converters;
+pub mod dashboard;
 pub mod database;
 pub mod e2e;
+pub mod embeddings;
+pub mod env_overrides;
+pub mod experiments;
+pub mod fault_injection;
 pub mod gemini_compat;
+pub mod gemini_native;
+pub mod guardrails;
+pub mod images;
+pub mod key_pool;
+pub mod key_store;
 pub mod metrics;
 pub mod middleware;
+pub mod mock_provider;
+pub mod model_catalog;
+pub mod prompt_cache;
 pub mod providers;
+pub mod realtime;
+pub mod response_cache;
+pub mod responses_api;
+pub mod route_explain;
 pub mod router_selector;
+pub mod secret_providers;
+pub mod secrets;
 pub mod server;
+pub mod systemd;
+pub mod tls;
+pub mod tokens;
+pub mod transcripts;
+pub mod transforms;
 pub mod usage;
+pub mod usage_shipping;
+pub mod usage_webhooks;
 pub mod utils;
+pub mod vertex;
+pub mod warmup;
 pub mod web_assets;
@@ -1,21 +1,37 @@
+use crate::usage::RequestEvent;
+use anyhow::Context;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use colored::*;
 use regex::Regex;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Lines shown from the current file before following it, like `tail -f`.
+const TAIL_LINES: usize = 10;
+/// How often a followed file is checked for new lines and rotation.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
 
 static LOG_REGEX: OnceLock<Regex> = OnceLock::new();
 static KV_REGEX: OnceLock<Regex> = OnceLock::new();
 static REQ_CTX_REGEX: OnceLock<Regex> = OnceLock::new();
 
-pub fn highlight_line(line: &str) -> String {
-    let re = LOG_REGEX.get_or_init(|| {
+fn log_regex() -> &'static Regex {
+    LOG_REGEX.get_or_init(|| {
         // Matches standard tracing output:
         // 2024-03-20T10:00:00.123456Z  INFO request{...}: target: message
         // or
         // 2024-03-20T10:00:00.123456Z  INFO target: message
         Regex::new(r"^([\d\-:T\.Z]+)\s+([A-Z]+)\s+(?:(request\{.*?\})[:\s]+)?(.*?):\s+(.*)$")
             .unwrap()
-    });
+    })
+}
+
+pub fn highlight_line(line: &str) -> String {
+    let re = log_regex();
 
     if let Some(caps) = re.captures(line) {
         let timestamp = caps.get(1).map_or("", |m| m.as_str());
@@ -52,6 +68,250 @@ pub fn highlight_line(line: &str) -> String {
     }
 }
 
+/// Which log lines `apex logs` prints.
+pub struct LogFilter {
+    /// Least severe level shown (`--level warn` shows WARN and ERROR).
+    pub level: Option<String>,
+    pub request_id: Option<String>,
+    pub grep: Option<Regex>,
+    pub since: Option<DateTime<Utc>>,
+    /// Lines without a timestamp (e.g. the rest of a multi-line message)
+    /// follow the line they belong to.
+    last_matched: bool,
+}
+
+impl LogFilter {
+    pub fn new(
+        level: Option<&str>,
+        request_id: Option<&str>,
+        grep: Option<&str>,
+        since: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let level = level
+            .map(|level| {
+                let level = level.to_ascii_uppercase();
+                if level_rank(&level).is_none() {
+                    anyhow::bail!(
+                        "unknown log level '{level}' (expected trace, debug, info, warn or error)"
+                    );
+                }
+                Ok(level)
+            })
+            .transpose()?;
+        Ok(Self {
+            level,
+            request_id: request_id.map(str::to_string),
+            grep: grep
+                .map(|pattern| Regex::new(pattern).context("invalid --grep pattern"))
+                .transpose()?,
+            since: since.map(parse_since).transpose()?,
+            last_matched: true,
+        })
+    }
+
+    pub fn matches(&mut self, line: &str) -> bool {
+        let Some(caps) = log_regex().captures(line) else {
+            return self.last_matched;
+        };
+        let level = caps.get(2).map_or("", |m| m.as_str());
+        let timestamp = caps
+            .get(1)
+            .and_then(|m| DateTime::parse_from_rfc3339(m.as_str()).ok());
+        self.last_matched = self
+            .level
+            .as_deref()
+            .is_none_or(|min| level_rank(level) >= level_rank(min))
+            && self
+                .since
+                .is_none_or(|since| timestamp.is_none_or(|at| at >= since))
+            && self
+                .request_id
+                .as_deref()
+                .is_none_or(|id| line.contains(id))
+            && self.grep.as_ref().is_none_or(|re| re.is_match(line));
+        self.last_matched
+    }
+}
+
+fn level_rank(level: &str) -> Option<u8> {
+    match level {
+        "TRACE" => Some(0),
+        "DEBUG" => Some(1),
+        "INFO" => Some(2),
+        "WARN" => Some(3),
+        "ERROR" => Some(4),
+        _ => None,
+    }
+}
+
+/// `--since`: a duration back from now (`30s`, `15m`, `2h`, `1d`), an
+/// RFC 3339 timestamp, or a local `YYYY-MM-DD[ HH:MM[:SS]]`.
+pub fn parse_since(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic())
+        && let Ok(amount) = value[..value.len() - 1].parse::<i64>()
+    {
+        let seconds = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            _ => anyhow::bail!("unknown --since unit '{unit}' (expected s, m, h or d)"),
+        };
+        return Ok(Utc::now() - chrono::Duration::seconds(amount * seconds));
+    }
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .with_context(|| format!("cannot parse --since '{value}'"))?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .with_context(|| format!("'{value}' does not exist in the local time zone"))
+}
+
+/// The daily `apex.log.*` files in `dir`, oldest first.
+pub fn log_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read log dir {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("apex.log"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Print matching lines from the logs in `dir`, then keep printing new ones,
+/// moving on to the next day's file when the log rotates. With `since`, every
+/// file is scanned from the start; otherwise only the last few lines of the
+/// newest file are shown first.
+pub fn follow(
+    dir: &Path,
+    filter: &mut LogFilter,
+    mut emit: impl FnMut(&str),
+) -> anyhow::Result<()> {
+    let mut files = log_files(dir)?;
+    let Some(mut current) = files.pop() else {
+        anyhow::bail!("no log files found in {}", dir.display());
+    };
+    if filter.since.is_some() {
+        for path in &files {
+            let mut reader = BufReader::new(File::open(path)?);
+            read_new_lines(&mut reader, filter, &mut emit)?;
+        }
+    }
+    let mut reader = BufReader::new(File::open(&current)?);
+    if filter.since.is_some() {
+        read_new_lines(&mut reader, filter, &mut emit)?;
+    } else {
+        let mut tail = std::collections::VecDeque::with_capacity(TAIL_LINES);
+        read_new_lines(&mut reader, filter, |line| {
+            if tail.len() == TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line.to_string());
+        })?;
+        tail.iter().for_each(|line| emit(line));
+    }
+    loop {
+        std::thread::sleep(FOLLOW_INTERVAL);
+        let position = reader.stream_position()?;
+        if std::fs::metadata(&current).map(|m| m.len()).unwrap_or(0) < position {
+            // Truncated in place: start over.
+            reader.seek(SeekFrom::Start(0))?;
+        }
+        read_new_lines(&mut reader, filter, &mut emit)?;
+        if let Some(newest) = log_files(dir)?.pop()
+            && newest != current
+        {
+            // Lines written to the old file just before the switch.
+            read_new_lines(&mut reader, filter, &mut emit)?;
+            current = newest;
+            reader = BufReader::new(File::open(&current)?);
+        }
+    }
+}
+
+/// Emit every complete line after the reader's position that passes the
+/// filter. A line still being written is left for the next call.
+fn read_new_lines(
+    reader: &mut BufReader<File>,
+    filter: &mut LogFilter,
+    mut emit: impl FnMut(&str),
+) -> anyhow::Result<()> {
+    let mut line = String::new();
+    loop {
+        let start = reader.stream_position()?;
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') {
+            reader.seek(SeekFrom::Start(start))?;
+            return Ok(());
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        if filter.matches(line) {
+            emit(line);
+        }
+    }
+}
+
+/// One line for a request from the gateway's `/admin/events` feed
+/// (`apex logs --live`).
+pub fn format_request_event(event: &RequestEvent) -> String {
+    let status = match event.status.as_str() {
+        "success" => event.status.green(),
+        "fallback" => event.status.yellow(),
+        _ => event.status.red().bold(),
+    };
+    let code = event
+        .status_code
+        .map(|code| format!(" {code}"))
+        .unwrap_or_default();
+    let latency = event
+        .latency_ms
+        .map(|ms| format!("{ms:.0}ms"))
+        .unwrap_or_else(|| "-".to_string());
+    let request_id = event
+        .request_id
+        .as_deref()
+        .map(|id| format!("[{}] ", id.get(..8).unwrap_or(id).yellow()))
+        .unwrap_or_default();
+    format!(
+        "{} {}{}{} {}={} {}={} {}={} {}={} {} tokens={}/{}",
+        event.timestamp.dimmed(),
+        request_id,
+        status,
+        code,
+        "team".purple(),
+        event.team_id.cyan(),
+        "router".purple(),
+        event.router.cyan(),
+        "channel".purple(),
+        event.channel.cyan(),
+        "model".purple(),
+        event.model.cyan(),
+        latency,
+        event.input_tokens,
+        event.output_tokens
+    )
+}
+
 fn format_request_context(context: &str) -> String {
     let re = REQ_CTX_REGEX.get_or_init(|| Regex::new(r"(\w+)=([^\s,}}]+)").unwrap());
 
@@ -161,6 +421,120 @@ mod tests {
         assert!(colored.contains("\x1b[32mINFO\x1b[0m"));
     }
 
+    #[test]
+    fn test_log_filter() {
+        let info = "2024-03-20T10:00:00.123Z  INFO request{request_id=abc-123}: apex::server: Routed to openai-main";
+        let warn = "2024-03-20T10:00:01.123Z  WARN request{request_id=def-456}: apex::server: Retrying openai-main";
+        let error = "2024-03-20T10:00:02.123Z ERROR apex::server: Upstream failed";
+        let continuation = "  caused by: connection reset";
+
+        let mut filter = LogFilter::new(Some("warn"), None, None, None).unwrap();
+        assert!(!filter.matches(info));
+        assert!(filter.matches(warn));
+        assert!(filter.matches(error));
+        assert!(filter.matches(continuation));
+        assert!(!filter.matches(info));
+        assert!(!filter.matches(continuation));
+
+        let mut filter = LogFilter::new(None, Some("abc-123"), None, None).unwrap();
+        assert!(filter.matches(info));
+        assert!(!filter.matches(warn));
+
+        let mut filter = LogFilter::new(None, None, Some("Retry|Upstream"), None).unwrap();
+        assert!(!filter.matches(info));
+        assert!(filter.matches(warn));
+        assert!(filter.matches(error));
+
+        let mut filter = LogFilter::new(None, None, None, Some("2024-03-20T10:00:01Z")).unwrap();
+        assert!(!filter.matches(info));
+        assert!(filter.matches(warn));
+
+        assert!(LogFilter::new(Some("loud"), None, None, None).is_err());
+        assert!(LogFilter::new(None, None, Some("("), None).is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let ago = Utc::now() - parse_since("90m").unwrap();
+        assert!((ago.num_seconds() - 5400).abs() < 5);
+        assert_eq!(
+            parse_since("2024-03-20T10:00:00+08:00").unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 20, 2, 0, 0).unwrap()
+        );
+        let local = Local.with_ymd_and_hms(2024, 3, 20, 9, 30, 0).unwrap();
+        assert_eq!(parse_since("2024-03-20 09:30").unwrap(), local);
+        assert_eq!(
+            parse_since("2024-03-20").unwrap(),
+            Local.with_ymd_and_hms(2024, 3, 20, 0, 0, 0).unwrap()
+        );
+        assert!(parse_since("5w").is_err());
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_follow_across_rotation() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let line = |n: u32| format!("2024-03-20T10:00:{n:02}.000Z  INFO apex::server: line {n}\n");
+        let mut old = File::create(dir.path().join("apex.log.2024-03-20")).unwrap();
+        for n in 0..12 {
+            old.write_all(line(n).as_bytes()).unwrap();
+        }
+        // A line still being written is not shown until it is complete.
+        old.write_all(b"2024-03-20T10:00:12.000Z  INFO apex::server: li")
+            .unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let path = dir.path().to_path_buf();
+        std::thread::spawn(move || {
+            let mut filter = LogFilter::new(None, None, None, None).unwrap();
+            let _ = follow(&path, &mut filter, |line| {
+                let _ = tx.send(line.to_string());
+            });
+        });
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // The last ten complete lines first, like `tail -f`.
+        for n in 2..12 {
+            assert!(next().ends_with(&format!("line {n}")));
+        }
+        old.write_all(b"ne 12\n").unwrap();
+        assert!(next().ends_with("line 12"));
+
+        // Rotation: the rest of the old file, then the new one.
+        old.write_all(line(13).as_bytes()).unwrap();
+        let mut new = File::create(dir.path().join("apex.log.2024-03-21")).unwrap();
+        new.write_all(line(14).as_bytes()).unwrap();
+        assert!(next().ends_with("line 13"));
+        assert!(next().ends_with("line 14"));
+    }
+
+    #[test]
+    fn test_format_request_event() {
+        colored::control::set_override(true);
+        let event: RequestEvent = serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-03-20 10:00:00",
+            "request_id": "1234567890",
+            "team_id": "team_1",
+            "router": "default",
+            "channel": "openai-main",
+            "model": "gpt-4",
+            "status": "error",
+            "status_code": 502,
+            "latency_ms": 812.4,
+            "input_tokens": 0,
+            "output_tokens": 0
+        }))
+        .unwrap();
+        let line = format_request_event(&event);
+        assert!(line.contains("12345678"));
+        assert!(!line.contains("1234567890"));
+        assert!(line.contains("\x1b[1;31merror\x1b[0m 502"));
+        assert!(line.contains("openai-main"));
+        assert!(line.contains("812ms"));
+    }
+
     #[test]
     fn test_highlight_key_value() {
         colored::control::set_override(true);
@@ -1,29 +1,64 @@
 use anyhow::{Context, bail};
 use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
 use rand::{Rng, distributions::Alphanumeric};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::collections::HashMap;
-use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod access_audit;
+mod body_model;
+mod canary;
+mod channel_health;
+mod channel_limits;
 mod compliance;
 mod config;
+mod config_includes;
+mod config_store;
 mod converters;
+mod dashboard;
 mod database;
+mod embeddings;
+mod env_overrides;
+mod experiments;
+mod fault_injection;
 mod gemini_compat;
+mod gemini_native;
+mod guardrails;
+mod images;
 mod install_metadata;
+mod key_pool;
+mod key_store;
 mod logs;
 mod metrics;
 mod middleware;
+mod mock_provider;
+mod model_catalog;
+mod prompt_cache;
 mod providers;
+mod realtime;
+mod response_cache;
+mod responses_api;
+mod route_explain;
 mod router_selector;
+mod secret_providers;
+mod secrets;
 mod server;
 mod service;
+mod systemd;
+mod tls;
+mod tokens;
+mod transcripts;
+mod transforms;
 mod upgrade;
 mod usage;
+mod usage_shipping;
+mod usage_webhooks;
 mod utils;
+mod vertex;
+mod warmup;
 mod web_assets;
 
 use config::{
@@ -174,6 +209,10 @@ enum Commands {
         #[command(subcommand)]
         command: RouterCommand,
     },
+    Route {
+        #[command(subcommand)]
+        command: RouteCommand,
+    },
     Gateway {
         #[command(subcommand)]
         command: GatewayCommand,
@@ -182,8 +221,15 @@ enum Commands {
         #[command(subcommand)]
         command: TeamCommand,
     },
+    Key {
+        #[command(subcommand)]
+        command: KeyCommand,
+    },
     Status,
-    Logs,
+    Logs(LogsArgs),
+    Usage(UsageArgs),
+    /// Send a sample chat completion through a router and show where it went.
+    Test(TestArgs),
     Service {
         #[command(subcommand)]
         command: ServiceCommand,
@@ -199,6 +245,20 @@ enum GatewayCommand {
         daemon: bool,
     },
     Stop,
+    /// Signal the running daemon (via its PID file) to reload its config
+    Reload,
+    /// Write a systemd unit that runs this binary with the current config
+    InstallSystemd(InstallSystemdArgs),
+}
+
+#[derive(Args)]
+struct InstallSystemdArgs {
+    /// Unit name (default: apex)
+    #[arg(long)]
+    name: Option<String>,
+    /// Write the unit here instead of /etc/systemd/system (no daemon-reload)
+    #[arg(long)]
+    output: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -218,6 +278,26 @@ enum ServiceCommand {
     Logs(ServiceNameArgs),
 }
 
+#[derive(Args)]
+struct LogsArgs {
+    /// Stream requests from the running gateway instead of tailing the log file
+    #[arg(long, conflicts_with_all = ["level", "request_id", "grep", "since"])]
+    live: bool,
+    /// Show only this level and more severe ones (trace, debug, info, warn, error)
+    #[arg(long)]
+    level: Option<String>,
+    /// Show only lines mentioning this request ID
+    #[arg(long)]
+    request_id: Option<String>,
+    /// Show only lines matching this regular expression
+    #[arg(long)]
+    grep: Option<String>,
+    /// Start from this time instead of the last lines: `30m`, `2h`, `1d`,
+    /// or a timestamp such as `2026-01-01 09:00`
+    #[arg(long)]
+    since: Option<String>,
+}
+
 #[derive(Args)]
 struct ServiceInstallArgs {
     #[arg(long)]
@@ -258,6 +338,54 @@ enum TeamCommand {
         #[arg(long)]
         json: bool,
     },
+    /// Show a team's policy and key usage (request count, last used).
+    Show {
+        id: String,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyCommand {
+    /// Create a managed team key; the plaintext is printed once.
+    Create(KeyCreateArgs),
+    /// List managed keys (prefix, expiry, status); never the plaintext.
+    List {
+        #[arg(long)]
+        team: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revoke a managed key by id (kept, but rejected), or blocklist any
+    /// other API key; the owning team is left untouched.
+    Revoke {
+        key: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Replace the teams' deprecated plaintext `api_key`s with managed keys
+    /// that accept the same secrets.
+    Migrate {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+struct KeyCreateArgs {
+    #[arg(long)]
+    team: String,
+    #[arg(long)]
+    name: Option<String>,
+    /// `30d`, `12h`, `2026-12-31` or an RFC 3339 timestamp.
+    #[arg(long)]
+    expires: Option<String>,
+    /// Model patterns the key may call, within the team policy.
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    models: Vec<String>,
+    #[arg(long)]
+    json: bool,
 }
 
 #[derive(Args)]
@@ -276,6 +404,227 @@ struct TeamAddArgs {
     json: bool,
 }
 
+#[derive(Args)]
+struct TestArgs {
+    /// Router to pin the request to; defaults to the first serving the model.
+    #[arg(long)]
+    router: Option<String>,
+    #[arg(long)]
+    model: String,
+    #[arg(long, default_value = "hi")]
+    prompt: String,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct UsageArgs {
+    #[command(subcommand)]
+    command: Option<UsageCommand>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    by_user: bool,
+    #[arg(long)]
+    start: Option<String>,
+    #[arg(long)]
+    end: Option<String>,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum UsageCommand {
+    /// Spend per model, team or channel, priced by the `pricing` table.
+    Cost(UsageCostArgs),
+}
+
+#[derive(Args)]
+struct UsageCostArgs {
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    start: Option<String>,
+    #[arg(long)]
+    end: Option<String>,
+    /// Group by `model`, `team`, `channel` or `instance`.
+    #[arg(long, default_value = "model")]
+    by: String,
+    #[arg(long)]
+    json: bool,
+}
+
+fn handle_usage_command(cli: &Cli, args: &UsageArgs) -> anyhow::Result<()> {
+    if let Some(UsageCommand::Cost(cost_args)) = &args.command {
+        return handle_usage_cost_command(cli, cost_args);
+    }
+    let config_path = resolve_config_path(cli.config.as_deref());
+    let action = if args.by_user { "by_user" } else { "summary" };
+    let config = return_or_exit_json(
+        "usage",
+        action,
+        args.json,
+        load_config_or_exit(&config_path),
+    )?;
+    let db = return_or_exit_json(
+        "usage",
+        action,
+        args.json,
+        database::Database::new(Some(config.data_dir.clone())),
+    )?;
+    let query = database::UsageRecordQuery {
+        team_id: args.team.clone(),
+        start_time: args.start.clone(),
+        end_time: args.end.clone(),
+        ..Default::default()
+    }
+    .with_inclusive_end_date();
+
+    if args.by_user {
+        if args.team.is_none() {
+            let err = anyhow::anyhow!("--team is required with --by-user");
+            if args.json {
+                exit_with_json_error("usage", action, &err);
+            }
+            return Err(err);
+        }
+        let rows = return_or_exit_json("usage", action, args.json, db.get_end_user_usage(&query))?;
+        if args.json {
+            print_json_success(
+                "usage",
+                action,
+                "End-user usage aggregated successfully.",
+                serde_json::to_value(&rows)?,
+            )?;
+        } else if rows.is_empty() {
+            println!("No end-user usage recorded.");
+        } else {
+            println!(
+                "{:<32} {:>10} {:>14} {:>14} {:>12} {:>8}",
+                "END USER", "REQUESTS", "INPUT TOKENS", "OUTPUT TOKENS", "COST ($)", "ERRORS"
+            );
+            for row in &rows {
+                println!(
+                    "{:<32} {:>10} {:>14} {:>14} {:>12.4} {:>8}",
+                    row.end_user,
+                    row.requests,
+                    row.input_tokens,
+                    row.output_tokens,
+                    row.cost,
+                    row.error_count
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let agg = return_or_exit_json("usage", action, args.json, db.get_usage_aggregate(&query))?;
+    if args.json {
+        print_json_success(
+            "usage",
+            action,
+            "Usage summarized successfully.",
+            json!({
+                "requests": agg.requests,
+                "total_tokens": agg.total_tokens,
+                "error_count": agg.error_count,
+                "avg_latency_ms": agg.avg_latency_ms,
+            }),
+        )?;
+    } else {
+        println!("Requests:       {}", agg.requests);
+        println!("Total Tokens:   {}", agg.total_tokens);
+        println!("Errors:         {}", agg.error_count);
+        println!("Avg Latency:    {:.1} ms", agg.avg_latency_ms);
+    }
+    Ok(())
+}
+
+fn handle_usage_cost_command(cli: &Cli, args: &UsageCostArgs) -> anyhow::Result<()> {
+    let config_path = resolve_config_path(cli.config.as_deref());
+    let config = return_or_exit_json(
+        "usage",
+        "cost",
+        args.json,
+        load_config_or_exit(&config_path),
+    )?;
+    let db = return_or_exit_json(
+        "usage",
+        "cost",
+        args.json,
+        database::Database::new(Some(config.data_dir.clone())),
+    )?;
+    let group_by = match args.by.as_str() {
+        "team" => "team_id",
+        other => other,
+    };
+    let query = database::UsageRecordQuery {
+        team_id: args.team.clone(),
+        start_time: args.start.clone(),
+        end_time: args.end.clone().map(|end| {
+            if end.len() == 10 {
+                format!("{end} 23:59:59")
+            } else {
+                end
+            }
+        }),
+        ..Default::default()
+    };
+    let rows = return_or_exit_json(
+        "usage",
+        "cost",
+        args.json,
+        db.get_usage_cost(&query, group_by),
+    )?;
+    let total: f64 = rows.iter().map(|row| row.cost).sum();
+    let unpriced: i64 = rows.iter().map(|row| row.unpriced_requests).sum();
+
+    if args.json {
+        return print_json_success(
+            "usage",
+            "cost",
+            "Usage cost summarized successfully.",
+            json!({
+                "by": args.by,
+                "total_cost": total,
+                "unpriced_requests": unpriced,
+                "rows": rows,
+            }),
+        );
+    }
+    if rows.is_empty() {
+        println!("No usage recorded.");
+        return Ok(());
+    }
+    println!(
+        "{:<32} {:>10} {:>14} {:>14} {:>8} {:>12} {:>9}",
+        args.by.to_uppercase(),
+        "REQUESTS",
+        "INPUT TOKENS",
+        "OUTPUT TOKENS",
+        "IMAGES",
+        "COST ($)",
+        "UNPRICED"
+    );
+    for row in &rows {
+        println!(
+            "{:<32} {:>10} {:>14} {:>14} {:>8} {:>12.4} {:>9}",
+            row.key,
+            row.requests,
+            row.input_tokens,
+            row.output_tokens,
+            row.images,
+            row.cost,
+            row.unpriced_requests
+        );
+    }
+    println!("Total:          ${total:.4}");
+    if unpriced > 0 {
+        println!("{unpriced} request(s) had no matching `pricing` entry and are not included.");
+    }
+    Ok(())
+}
+
 fn handle_team_command(cli: &Cli, command: &TeamCommand) -> anyhow::Result<()> {
     let config_path = resolve_config_path(cli.config.as_deref());
 
@@ -291,17 +640,12 @@ fn handle_team_command(cli: &Cli, command: &TeamCommand) -> anyhow::Result<()> {
                 return Err(err);
             }
 
-            // Generate API Key: sk-ap-xxxx
-            let random_part: String = rand::thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(32)
-                .map(char::from)
-                .collect();
-            let api_key = format!("sk-ap-{}", random_part);
+            // The team's first managed key; only its hash is stored.
+            let (key, api_key) = key_store::new_key(Some("default".to_string()), None, vec![]);
 
             let team = config::Team {
                 id: args.id.clone(),
-                api_key: api_key.clone(),
+                api_key: String::new(),
                 policy: config::TeamPolicy {
                     allowed_routers: args.routers.clone(),
                     allowed_models: args.models.clone(),
@@ -313,9 +657,19 @@ fn handle_team_command(cli: &Cli, command: &TeamCommand) -> anyhow::Result<()> {
                     } else {
                         None
                     },
+                    allow_routing_overrides: false,
+                    end_user_rate_limit: None,
+                    reject_unknown_models: false,
+                    transcripts: None,
+                    pii: None,
+                    max_tokens_per_request: None,
+                    max_context_tokens: None,
+                    clamp_max_tokens: false,
+                    priority: Default::default(),
                 },
                 group: None,
                 enabled: None,
+                keys: vec![key],
             };
 
             std::sync::Arc::make_mut(&mut config.teams).push(team.clone());
@@ -326,15 +680,13 @@ fn handle_team_command(cli: &Cli, command: &TeamCommand) -> anyhow::Result<()> {
                 config::save_config(&config_path, &config),
             )?;
             if args.json {
-                print_json_success(
-                    "team",
-                    "add",
-                    "Team added successfully.",
-                    serde_json::to_value(&team)?,
-                )?;
+                let mut data = serde_json::to_value(&team)?;
+                data["api_key"] = json!(api_key);
+                print_json_success("team", "add", "Team added successfully.", data)?;
             } else {
                 println!("Team '{}' added successfully.", args.id);
                 println!("API Key: {}", api_key);
+                println!("Store it now; only its hash is kept.");
             }
         }
         TeamCommand::Remove { id, json } => {
@@ -383,10 +735,379 @@ fn handle_team_command(cli: &Cli, command: &TeamCommand) -> anyhow::Result<()> {
                 println!("{:-<20} {:-<45} {:-<20}", "", "", "");
                 for team in config.teams.iter() {
                     let routers = team.policy.allowed_routers.join(", ");
-                    println!("{:<20} {:<45} {:<20}", team.id, team.api_key, routers);
+                    let key = key_store::masked_key(team);
+                    println!("{:<20} {:<45} {:<20}", team.id, key, routers);
+                }
+            }
+        }
+        TeamCommand::Show { id, json } => {
+            let config =
+                return_or_exit_json("team", "show", *json, load_config_or_exit(&config_path))?;
+            let Some(team) = config.teams.iter().find(|t| t.id == *id) else {
+                let err = anyhow::anyhow!("Team '{}' not found", id);
+                if *json {
+                    exit_with_json_error("team", "show", &err);
+                }
+                return Err(err);
+            };
+            let db = return_or_exit_json(
+                "team",
+                "show",
+                *json,
+                database::Database::new(Some(config.data_dir.clone())),
+            )?;
+            let usage = match key_store::usage_id(team) {
+                Some(id) => return_or_exit_json("team", "show", *json, db.get_key_usage(&id))?,
+                None => None,
+            };
+
+            if *json {
+                print_json_success(
+                    "team",
+                    "show",
+                    "Team loaded successfully.",
+                    json!({
+                        "id": team.id,
+                        "api_key": key_store::masked_key(team),
+                        "enabled": !team.is_paused(),
+                        "policy": team.policy,
+                        "key_usage": usage.as_ref().map(|u| json!({
+                            "request_count": u.request_count,
+                            "first_used_at": u.first_used,
+                            "last_used_at": u.last_used,
+                        })),
+                    }),
+                )?;
+            } else {
+                println!("Team:            {}", team.id);
+                println!("API Key:         {}", key_store::masked_key(team));
+                println!(
+                    "Status:          {}",
+                    if team.is_paused() { "paused" } else { "active" }
+                );
+                println!(
+                    "Allowed Routers: {}",
+                    team.policy.allowed_routers.join(", ")
+                );
+                match usage {
+                    Some(usage) => {
+                        println!("Requests:        {}", usage.request_count);
+                        println!("First Used:      {}", usage.first_used);
+                        println!("Last Used:       {}", usage.last_used);
+                    }
+                    None => println!("Last Used:       never"),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_route_command(cli: &Cli, command: &RouteCommand) -> anyhow::Result<()> {
+    let config_path = resolve_config_path(cli.config.as_deref());
+
+    match command {
+        RouteCommand::Explain {
+            model,
+            router,
+            team,
+            json,
+        } => {
+            let config =
+                return_or_exit_json("route", "explain", *json, load_config_or_exit(&config_path))?;
+            let selector = router_selector::RouterSelector::new();
+            selector.set_pricing(&config.channels, &config.pricing);
+            let explanation = return_or_exit_json(
+                "route",
+                "explain",
+                *json,
+                route_explain::explain(
+                    &selector,
+                    &config,
+                    model,
+                    router.as_deref(),
+                    team.as_deref(),
+                ),
+            )?;
+            if *json {
+                return print_json_success(
+                    "route",
+                    "explain",
+                    "Route explained.",
+                    serde_json::to_value(&explanation)?,
+                );
+            }
+
+            println!("Model:    {}", explanation.model);
+            println!("Router:   {}", explanation.router.as_deref().unwrap_or("-"));
+            println!("Rule:     {}", explanation.rule.as_deref().unwrap_or("-"));
+            println!(
+                "Channel:  {}",
+                explanation
+                    .channel
+                    .as_deref()
+                    .unwrap_or(if explanation.router.is_some() {
+                        "(random by weight)"
+                    } else {
+                        "-"
+                    })
+            );
+            println!("Reason:   {}", explanation.reason);
+            for router in &explanation.routers {
+                println!();
+                let Some(index) = router.matched_rule else {
+                    let patterns: Vec<&str> = router
+                        .rules
+                        .iter()
+                        .flat_map(|rule| rule.patterns.iter().map(String::as_str))
+                        .collect();
+                    println!(
+                        "Router '{}': no rule matches (patterns: {})",
+                        router.router,
+                        patterns.join(", ")
+                    );
+                    continue;
+                };
+                let rule = &router.rules[index];
+                println!(
+                    "Router '{}': rule #{} [{}] matched by '{}' ({}), strategy {}",
+                    router.router,
+                    index,
+                    rule.patterns.join(", "),
+                    rule.matched_pattern.as_deref().unwrap_or_default(),
+                    rule.match_kind.unwrap_or_default(),
+                    router.strategy.as_deref().unwrap_or_default()
+                );
+                println!("  {:<24} {:>6} {:>7}  STATE", "CHANNEL", "WEIGHT", "SHARE");
+                for candidate in &router.candidates {
+                    let state = match (candidate.ejected, candidate.saturated) {
+                        (true, _) => "ejected",
+                        (false, true) => "saturated",
+                        (false, false) => "ok",
+                    };
+                    println!(
+                        "  {:<24} {:>6} {:>6.1}%  {}",
+                        candidate.channel, candidate.weight, candidate.share, state
+                    );
+                }
+                if !router.fallback_channels.is_empty() {
+                    println!("  fallback: {}", router.fallback_channels.join(", "));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_key_command(cli: &Cli, command: &KeyCommand) -> anyhow::Result<()> {
+    let config_path = resolve_config_path(cli.config.as_deref());
+
+    match command {
+        KeyCommand::Create(args) => {
+            let mut config = return_or_exit_json(
+                "key",
+                "create",
+                args.json,
+                load_config_or_exit(&config_path),
+            )?;
+            let expires_at = match args.expires.as_deref() {
+                Some(value) => Some(return_or_exit_json(
+                    "key",
+                    "create",
+                    args.json,
+                    key_store::parse_expiry(value, chrono::Utc::now()),
+                )?),
+                None => None,
+            };
+            let Some(team) = std::sync::Arc::make_mut(&mut config.teams)
+                .iter_mut()
+                .find(|t| t.id == args.team)
+            else {
+                let err = anyhow::anyhow!("Team '{}' not found", args.team);
+                if args.json {
+                    exit_with_json_error("key", "create", &err);
+                }
+                return Err(err);
+            };
+            let (key, secret) =
+                key_store::new_key(args.name.clone(), expires_at, args.models.clone());
+            team.keys.push(key.clone());
+            return_or_exit_json(
+                "key",
+                "create",
+                args.json,
+                config::save_config(&config_path, &config),
+            )?;
+            if args.json {
+                print_json_success(
+                    "key",
+                    "create",
+                    "Key created successfully.",
+                    json!({
+                        "team": args.team,
+                        "key": secret,
+                        "record": key,
+                    }),
+                )?;
+            } else {
+                println!("Key '{}' created for team '{}'.", key.id, args.team);
+                println!("API Key: {}", secret);
+                println!("Store it now; only its hash is kept.");
+            }
+        }
+        KeyCommand::List { team, json } => {
+            let config =
+                return_or_exit_json("key", "list", *json, load_config_or_exit(&config_path))?;
+            let now = chrono::Utc::now();
+            let keys: Vec<(&config::Team, &config::TeamKey)> = config
+                .teams
+                .iter()
+                .filter(|t| team.as_deref().is_none_or(|id| t.id == id))
+                .flat_map(|t| t.keys.iter().map(move |k| (t, k)))
+                .collect();
+            if *json {
+                let data: Vec<serde_json::Value> = keys
+                    .iter()
+                    .map(|(team, key)| {
+                        json!({
+                            "id": key.id,
+                            "team": team.id,
+                            "name": key.name,
+                            "prefix": key.prefix,
+                            "created_at": key.created_at,
+                            "expires_at": key.expires_at,
+                            "revoked_at": key.revoked_at,
+                            "models": key.models,
+                            "status": key.status(now).as_str(),
+                        })
+                    })
+                    .collect();
+                print_json_success("key", "list", "Keys listed successfully.", json!(data))?;
+            } else if keys.is_empty() {
+                println!("No managed keys configured.");
+            } else {
+                println!(
+                    "{:<18} {:<16} {:<14} {:<26} {:<8} {:<20}",
+                    "ID", "Team", "Prefix", "Expires", "Status", "Models"
+                );
+                println!(
+                    "{:-<18} {:-<16} {:-<14} {:-<26} {:-<8} {:-<20}",
+                    "", "", "", "", "", ""
+                );
+                for (team, key) in keys {
+                    let models = if key.models.is_empty() {
+                        "*".to_string()
+                    } else {
+                        key.models.join(", ")
+                    };
+                    println!(
+                        "{:<18} {:<16} {:<14} {:<26} {:<8} {:<20}",
+                        key.id,
+                        team.id,
+                        format!("{}…", key.prefix),
+                        key.expires_at.as_deref().unwrap_or("never"),
+                        key.status(now).as_str(),
+                        models
+                    );
+                }
+            }
+        }
+        KeyCommand::Revoke { key, json } => {
+            let mut config =
+                return_or_exit_json("key", "revoke", *json, load_config_or_exit(&config_path))?;
+            let key = key.trim().to_string();
+            if key.is_empty() {
+                let err = anyhow::anyhow!("Key must not be empty");
+                if *json {
+                    exit_with_json_error("key", "revoke", &err);
+                }
+                return Err(err);
+            }
+            if let Some((team, key_id)) = key_store::revoke(&mut config, &key) {
+                return_or_exit_json(
+                    "key",
+                    "revoke",
+                    *json,
+                    config::save_config(&config_path, &config),
+                )?;
+                if *json {
+                    print_json_success(
+                        "key",
+                        "revoke",
+                        "Key revoked successfully.",
+                        json!({ "revoked": key_id, "team": team }),
+                    )?;
+                } else {
+                    println!("Key '{}' of team '{}' revoked.", key_id, team);
+                }
+                return Ok(());
+            }
+            if !config.global.is_key_revoked(&key) {
+                config.global.revoked_keys.push(key.clone());
+                return_or_exit_json(
+                    "key",
+                    "revoke",
+                    *json,
+                    config::save_config(&config_path, &config),
+                )?;
+            }
+            let owner = config
+                .teams
+                .iter()
+                .find(|t| t.api_key == key)
+                .map(|t| t.id.clone());
+            if *json {
+                print_json_success(
+                    "key",
+                    "revoke",
+                    "Key revoked successfully.",
+                    json!({
+                        "revoked": utils::mask_secret(&key),
+                        "team": owner,
+                    }),
+                )?;
+            } else {
+                match owner {
+                    Some(team) => println!(
+                        "Key revoked. It belonged to team '{}'; rotate that team's key to restore access.",
+                        team
+                    ),
+                    None => println!("Key revoked."),
                 }
             }
         }
+        KeyCommand::Migrate { json } => {
+            let mut config =
+                return_or_exit_json("key", "migrate", *json, load_config_or_exit(&config_path))?;
+            let migrated = key_store::migrate_legacy_keys(&mut config);
+            if !migrated.is_empty() {
+                return_or_exit_json(
+                    "key",
+                    "migrate",
+                    *json,
+                    config::save_config(&config_path, &config),
+                )?;
+            }
+            if *json {
+                print_json_success(
+                    "key",
+                    "migrate",
+                    "Keys migrated successfully.",
+                    json!({ "teams": migrated }),
+                )?;
+            } else if migrated.is_empty() {
+                println!("No plaintext team keys to migrate.");
+            } else {
+                println!(
+                    "Migrated the plaintext keys of {} team(s): {}",
+                    migrated.len(),
+                    migrated.join(", ")
+                );
+                println!("The same keys keep working; only their hashes are stored now.");
+            }
+        }
     }
 
     Ok(())
@@ -458,6 +1179,28 @@ enum ChannelCommand {
         #[arg(long)]
         json: bool,
     },
+    /// Live health of each channel, as seen by the running gateway.
+    Health {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RouteCommand {
+    /// Show which router, rule and channel a model would be routed to,
+    /// without sending anything upstream.
+    Explain {
+        #[arg(long)]
+        model: String,
+        #[arg(long)]
+        router: Option<String>,
+        /// Route as this team (its allowed routers and model policy).
+        #[arg(long)]
+        team: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -547,6 +1290,9 @@ struct RouterAddArgs {
     model_matchers: Vec<String>,
     #[arg(long = "fallback", value_delimiter = ',', num_args = 0..)]
     fallback_channels: Vec<String>,
+    /// Generate a virtual key that routes through this router only.
+    #[arg(long)]
+    vkey: bool,
     #[arg(long)]
     json: bool,
 }
@@ -565,6 +1311,11 @@ struct RouterUpdateArgs {
     fallback_channels: Vec<String>,
     #[arg(long)]
     clear_fallbacks: bool,
+    /// Generate a new virtual key, replacing the current one.
+    #[arg(long, conflicts_with = "clear_vkey")]
+    rotate_vkey: bool,
+    #[arg(long)]
+    clear_vkey: bool,
     #[arg(long)]
     json: bool,
 }
@@ -630,6 +1381,16 @@ fn main() -> anyhow::Result<()> {
             .init();
 
         Some(guard)
+    } else if matches!(cli.command, Commands::Test(_)) {
+        // `apex test` prints its report on stdout; the routing log goes to stderr.
+        tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| env_filter.into()),
+            )
+            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+            .init();
+        None
     } else {
         // Setup standard logging
         tracing_subscriber::registry()
@@ -655,8 +1416,9 @@ async fn async_main(cli: Cli) -> anyhow::Result<()> {
             init_config(&path)?;
         }
         Commands::Config { command } => handle_config_command(&cli, command)?,
-        Commands::Channel { command } => handle_channel_command(&cli, command)?,
+        Commands::Channel { command } => handle_channel_command(&cli, command).await?,
         Commands::Router { command } => handle_router_command(&cli, command)?,
+        Commands::Route { command } => handle_route_command(&cli, command)?,
         Commands::Gateway { command } => match command {
             GatewayCommand::Run => {
                 let path = resolve_config_path(cli.config.as_deref());
@@ -667,10 +1429,21 @@ async fn async_main(cli: Cli) -> anyhow::Result<()> {
                 server::run_server(path).await?;
             }
             GatewayCommand::Stop => handle_stop_command(&cli)?,
+            GatewayCommand::Reload => handle_reload_command(&cli)?,
+            GatewayCommand::InstallSystemd(args) => handle_install_systemd_command(&cli, args)?,
         },
-        Commands::Status => handle_status_command(&cli)?,
-        Commands::Logs => handle_logs_command(&cli)?,
+        Commands::Status => handle_status_command(&cli).await?,
+        Commands::Logs(args) => {
+            if args.live {
+                handle_live_logs_command(&cli).await?
+            } else {
+                handle_logs_command(&cli, args)?
+            }
+        }
+        Commands::Usage(args) => handle_usage_command(&cli, args)?,
+        Commands::Test(args) => handle_test_command(&cli, args).await?,
         Commands::Team { command } => handle_team_command(&cli, command)?,
+        Commands::Key { command } => handle_key_command(&cli, command)?,
         Commands::Service { command } => handle_service_command(&cli, command)?,
         Commands::Upgrade(args) => {
             upgrade::run_upgrade(upgrade::UpgradeOptions {
@@ -695,6 +1468,7 @@ fn handle_config_command(cli: &Cli, command: &ConfigCommand) -> anyhow::Result<(
         ConfigCommand::Validate => {
             let cfg = load_config_or_exit(&resolved.path)?;
             config::check_no_placeholder_credentials(&cfg)?;
+            config::validate_config(&cfg)?;
             println!(
                 "Config is valid: {} (source: {})",
                 resolved.path.display(),
@@ -888,76 +1662,112 @@ fn service_definition_from_args(
     ))
 }
 
-fn handle_logs_command(cli: &Cli) -> anyhow::Result<()> {
+fn handle_install_systemd_command(cli: &Cli, args: &InstallSystemdArgs) -> anyhow::Result<()> {
+    let config_path = resolve_config_path(cli.config.as_deref());
+    let config_path = std::fs::canonicalize(&config_path).with_context(|| {
+        format!(
+            "config file {} not found; run `apex init` first",
+            config_path.display()
+        )
+    })?;
+    let binary = std::env::current_exe()
+        .and_then(std::fs::canonicalize)
+        .context("failed to locate the apex binary")?;
+    let working_dir = config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("/"));
+    let name = args.name.clone().unwrap_or_else(|| {
+        service::default_service_name_for(service::ServiceManager::Systemd).to_string()
+    });
+    let definition = service::ServiceDefinition::new(
+        working_dir,
+        config_path,
+        name,
+        service::ServiceManager::Systemd,
+    )
+    .with_binary(binary);
+    let path = service::write_systemd_unit(&definition, args.output.as_deref())?;
+    println!("Wrote systemd unit: {}", path.display());
+    if args.output.is_none() {
+        println!(
+            "Enable and start it with: systemctl enable --now {}",
+            definition.service_name.trim_end_matches(".service")
+        );
+    }
+    Ok(())
+}
+
+fn handle_logs_command(cli: &Cli, args: &LogsArgs) -> anyhow::Result<()> {
     let path = resolve_config_path(cli.config.as_deref());
     let config = config::load_config(&path).ok();
     let log_dir_override = config.as_ref().and_then(|c| c.logging.dir.clone());
     let log_dir = get_log_dir(log_dir_override);
-
-    // Find the latest log file.
-    // tracing_appender::rolling::daily creates files like "apex.log.YYYY-MM-DD"
-    // But the symlink/current might be different.
-    // Wait, rolling appender usually creates files with dates suffix.
-    // Let's list files in log_dir and find the most recent one matching "apex.log.*"
+    let mut filter = logs::LogFilter::new(
+        args.level.as_deref(),
+        args.request_id.as_deref(),
+        args.grep.as_deref(),
+        args.since.as_deref(),
+    )?;
 
     println!("Log directory: {}", log_dir.display());
+    if logs::log_files(&log_dir)?.is_empty() {
+        println!("No log files found in {}", log_dir.display());
+        return Ok(());
+    }
+    // Follows the daily `apex.log.YYYY-MM-DD` files across rotation until
+    // interrupted.
+    logs::follow(&log_dir, &mut filter, |line| {
+        println!("{}", logs::highlight_line(line))
+    })
+}
 
-    // For simplicity, we assume standard rolling naming.
-    // However, tracing_appender doesn't create a "current" symlink by default unless configured?
-    // Actually rolling appender creates `apex.log.YYYY-MM-DD`.
-    // Let's try to find the newest file.
-
-    let entries = std::fs::read_dir(&log_dir).context("failed to read log dir")?;
-    let mut logs: Vec<PathBuf> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| {
-            if let Some(name) = p.file_name().and_then(|n| n.to_str()) {
-                name.starts_with("apex.log")
-            } else {
-                false
-            }
-        })
-        .collect();
-
-    logs.sort();
-
-    if let Some(latest) = logs.last() {
-        println!("Tailing log file: {}", latest.display());
-        // Use tail -f
-        let mut child = std::process::Command::new("tail")
-            .arg("-f")
-            .arg(latest)
-            .stdout(std::process::Stdio::piped())
-            .spawn()
-            .context("failed to execute tail")?;
-
-        if let Some(stdout) = child.stdout.take() {
-            let reader = std::io::BufReader::new(stdout);
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        let colored_line = logs::highlight_line(&line);
-                        println!("{}", colored_line);
-                    }
-                    Err(e) => {
-                        eprintln!("Error reading log line: {}", e);
-                        break;
-                    }
+/// Print every request the running gateway finishes, from `/admin/events`.
+async fn handle_live_logs_command(cli: &Cli) -> anyhow::Result<()> {
+    let path = resolve_config_path(cli.config.as_deref());
+    let config = config::load_config(&path)?;
+    let url = format!("{}/admin/events", local_gateway_url(&config.global.listen));
+    let mut request = reqwest::Client::new().get(&url);
+    if let Some(key) = config.global.auth_keys.first() {
+        request = request.bearer_auth(key);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("failed to connect to {url}; is the gateway running?"))?;
+    if !response.status().is_success() {
+        bail!("{} returned {}", url, response.status());
+    }
+    println!("Streaming requests from {url} (Ctrl-C to stop)");
+
+    let mut stream = response.bytes_stream();
+    let mut buffer = String::new();
+    let mut event_name = String::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk?));
+        while let Some(end) = buffer.find('\n') {
+            let line = buffer[..end].trim_end_matches('\r').to_string();
+            buffer.drain(..=end);
+            if let Some(name) = line.strip_prefix("event:") {
+                event_name = name.trim().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                match event_name.as_str() {
+                    "request" => match serde_json::from_str::<usage::RequestEvent>(data.trim()) {
+                        Ok(event) => println!("{}", logs::format_request_event(&event)),
+                        Err(e) => eprintln!("Skipping malformed event: {e}"),
+                    },
+                    "lagged" => eprintln!("... {} requests skipped", data.trim()),
+                    _ => {}
                 }
+            } else if line.is_empty() {
+                event_name.clear();
             }
         }
-
-        // Wait for child process if loop exits
-        let _ = child.wait();
-    } else {
-        println!("No log files found in {}", log_dir.display());
     }
-
-    Ok(())
+    bail!("gateway closed the event stream")
 }
 
-fn handle_status_command(cli: &Cli) -> anyhow::Result<()> {
+async fn handle_status_command(cli: &Cli) -> anyhow::Result<()> {
     // Load config to find log dir
     let path = resolve_config_path(cli.config.as_deref());
     let config = config::load_config(&path).ok();
@@ -997,6 +1807,13 @@ fn handle_status_command(cli: &Cli) -> anyhow::Result<()> {
                 println!("Listen Address: {}", config.global.listen);
                 println!("\nChannels:");
                 print_channel_table(&config.channels);
+                if status == "Running" {
+                    println!("\nChannel Health (last minute):");
+                    match fetch_channel_health(&config).await {
+                        Ok(health) => print_channel_health_table(&health),
+                        Err(e) => println!("Unable to query running gateway: {}", e),
+                    }
+                }
                 println!("\nRouters:");
                 print_router_table(&config.routers);
             }
@@ -1011,13 +1828,144 @@ fn handle_status_command(cli: &Cli) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn handle_stop_command(cli: &Cli) -> anyhow::Result<()> {
+async fn handle_test_command(cli: &Cli, args: &TestArgs) -> anyhow::Result<()> {
+    let config_path = resolve_config_path(cli.config.as_deref());
+    let config = return_or_exit_json("test", "send", args.json, load_config_or_exit(&config_path))?;
+    let body = json!({
+        "model": args.model,
+        "messages": [{ "role": "user", "content": args.prompt }],
+    });
+    let outcome = return_or_exit_json(
+        "test",
+        "send",
+        args.json,
+        server::send_test_request(config, args.router.clone(), body).await,
+    )?;
+    let response: Option<Value> = serde_json::from_slice(&outcome.body).ok();
+    let latency_ms = outcome.latency.as_millis() as u64;
+
+    if args.json {
+        return print_json_success(
+            "test",
+            "send",
+            "Test request completed.",
+            json!({
+                "router": outcome.router,
+                "channel": outcome.hops.last().map(|hop| &hop.channel),
+                "upstream_url": outcome.hops.last().map(|hop| &hop.url),
+                "attempts": outcome.hops,
+                "status": outcome.status.as_u16(),
+                "latency_ms": latency_ms,
+                "response": response
+                    .unwrap_or_else(|| Value::String(String::from_utf8_lossy(&outcome.body).into())),
+            }),
+        );
+    }
+
+    println!("Router:        {}", outcome.router);
+    match outcome.hops.last() {
+        Some(hop) => {
+            println!("Channel:       {}", hop.channel);
+            println!("Upstream URL:  {}", hop.url);
+        }
+        None => println!("Channel:       none (no upstream call was made)"),
+    }
+    if outcome.hops.len() > 1 {
+        println!("Attempts:");
+        for hop in &outcome.hops {
+            let status = hop
+                .status
+                .map_or_else(|| "error".to_string(), |status| status.to_string());
+            println!("  {:<20} {:<6} {}", hop.channel, status, hop.url);
+        }
+    }
+    println!("Status:        {}", outcome.status);
+    println!("Latency:       {} ms", latency_ms);
+    let content = response
+        .as_ref()
+        .and_then(|r| r.pointer("/choices/0/message/content"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| String::from_utf8_lossy(&outcome.body).into_owned());
+    println!("Response:\n{}", content);
+    if !outcome.status.is_success() {
+        bail!("Test request failed with status {}", outcome.status);
+    }
+    Ok(())
+}
+
+/// Ask the running gateway for live per-channel stats.
+async fn fetch_channel_health(config: &Config) -> anyhow::Result<Vec<serde_json::Value>> {
+    let url = format!(
+        "{}/admin/channels/health",
+        local_gateway_url(&config.global.listen)
+    );
+    let mut request = reqwest::Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(3));
+    if let Some(key) = config.global.auth_keys.first() {
+        request = request.bearer_auth(key);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        bail!("{} returned {}", url, response.status());
+    }
+    let body: serde_json::Value = response.json().await?;
+    Ok(body["data"].as_array().cloned().unwrap_or_default())
+}
+
+/// Loopback URL for a listen address (`0.0.0.0:12356` -> `http://127.0.0.1:12356`).
+fn local_gateway_url(listen: &str) -> String {
+    let (host, port) = listen.rsplit_once(':').unwrap_or((listen, "80"));
+    let host = match host {
+        "0.0.0.0" | "" => "127.0.0.1",
+        "[::]" => "[::1]",
+        other => other,
+    };
+    format!("http://{}:{}", host, port)
+}
+
+fn print_channel_health_table(health: &[serde_json::Value]) {
+    println!(
+        "{:<20} {:<10} {:>8} {:>8} {:>8} {:>12}  PROBE",
+        "NAME", "STATE", "RPS", "ERRORS", "REQ/1M", "CONSEC_FAIL"
+    );
+    for channel in health {
+        let probe = match (
+            channel["last_probe"].as_str(),
+            channel["last_probe_error"].as_str(),
+        ) {
+            (_, Some(error)) => error.to_string(),
+            (Some(_), None) => "ok".to_string(),
+            (None, None) => "-".to_string(),
+        };
+        let state = if channel["ejected"].as_bool().unwrap_or(false) {
+            "ejected"
+        } else {
+            channel["state"].as_str().unwrap_or("-")
+        };
+        println!(
+            "{:<20} {:<10} {:>8.2} {:>7.1}% {:>8} {:>12}  {}",
+            channel["channel"].as_str().unwrap_or("-"),
+            state,
+            channel["rps"].as_f64().unwrap_or(0.0),
+            channel["error_rate"].as_f64().unwrap_or(0.0) * 100.0,
+            channel["requests_last_minute"].as_u64().unwrap_or(0),
+            channel["consecutive_failures"].as_u64().unwrap_or(0),
+            probe,
+        );
+    }
+}
+
+fn daemon_pid_path(cli: &Cli) -> PathBuf {
     let path = resolve_config_path(cli.config.as_deref());
     let config = config::load_config(&path).ok();
     let log_dir_override = config.as_ref().and_then(|c| c.logging.dir.clone());
-    let log_dir = get_log_dir(log_dir_override);
+    get_log_dir(log_dir_override).join("apex.pid")
+}
 
-    let pid_path = log_dir.join("apex.pid");
+fn handle_stop_command(cli: &Cli) -> anyhow::Result<()> {
+    let pid_path = daemon_pid_path(cli);
 
     if !pid_path.exists() {
         println!("⚠️  PID file not found at {}", pid_path.display());
@@ -1065,6 +2013,34 @@ fn handle_stop_command(cli: &Cli) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn handle_reload_command(cli: &Cli) -> anyhow::Result<()> {
+    let pid_path = daemon_pid_path(cli);
+    if !pid_path.exists() {
+        anyhow::bail!(
+            "PID file not found at {}; is the daemon running?",
+            pid_path.display()
+        );
+    }
+
+    let pid_str = std::fs::read_to_string(&pid_path).context("failed to read pid file")?;
+    let pid: i32 = pid_str.trim().parse().context("invalid pid in file")?;
+
+    // The daemon re-reads its config file on SIGHUP.
+    let output = std::process::Command::new("kill")
+        .arg("-HUP")
+        .arg(pid.to_string())
+        .output()
+        .context("failed to execute kill command")?;
+
+    if output.status.success() {
+        println!("✅ Sent reload signal to daemon (PID: {})", pid);
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("failed to signal daemon: {}", stderr);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ConfigPathSource {
     Flag,
@@ -1155,19 +2131,40 @@ fn init_config(path: &std::path::Path) -> anyhow::Result<()> {
                 max_attempts: 2,
                 backoff_ms: 200,
                 retry_on_status: vec![429, 500, 502, 503, 504],
+                exponential: false,
+                jitter: false,
+                max_backoff_ms: None,
+                respect_retry_after: false,
             },
             gemini_replay: crate::config::GeminiReplay::default(),
             cors_allowed_origins: vec![],
+            ip_rate_limit: None,
+            trusted_proxies: vec![],
+            load_shedding: None,
+            revoked_keys: vec![],
+            tls: None,
+            model_aliases: Default::default(),
+            max_request_timeout_ms: None,
+            body_limits: None,
+            compression: None,
+            rate_limit: None,
+            provider_rate_limits: Default::default(),
+            rate_limit_store: None,
         },
         channels: std::sync::Arc::new(Vec::new()),
         routers: std::sync::Arc::new(Vec::new()),
         metrics: Metrics {
             enabled: true,
             path: "/metrics".to_string(),
+            end_user_label: false,
+            auth_token: None,
+            listen: None,
+            buckets: Default::default(),
         },
         hot_reload: HotReload {
             config_path: path.display().to_string(),
             watch: true,
+            store: None,
         },
         logging: config::Logging {
             level: "info".to_string(),
@@ -1180,6 +2177,21 @@ fn init_config(path: &std::path::Path) -> anyhow::Result<()> {
         teams: std::sync::Arc::new(Vec::new()),
         compliance: None,
         retention: Default::default(),
+        fault_injection: None,
+        access_audit: None,
+        tenants: vec![],
+        health: None,
+        warmup: None,
+        pricing: vec![],
+        secret_refs: Default::default(),
+        secrets: None,
+        includes: Default::default(),
+        env_overrides: Default::default(),
+        model_discovery: None,
+        guardrails: None,
+        usage_collector: None,
+        usage_webhooks: Vec::new(),
+        usage_shipping: None,
     };
     config::save_config(path, &config)
         .with_context(|| format!("failed to write config: {}", path.display()))?;
@@ -1197,7 +2209,7 @@ fn load_config_or_exit(path: &std::path::Path) -> anyhow::Result<Config> {
     config::load_config(path).with_context(|| format!("failed to load config: {}", path.display()))
 }
 
-fn handle_channel_command(cli: &Cli, command: &ChannelCommand) -> anyhow::Result<()> {
+async fn handle_channel_command(cli: &Cli, command: &ChannelCommand) -> anyhow::Result<()> {
     let path = resolve_config_path(cli.config.as_deref());
     match command {
         ChannelCommand::Add(args) => {
@@ -1280,6 +2292,21 @@ fn handle_channel_command(cli: &Cli, command: &ChannelCommand) -> anyhow::Result
                 headers,
                 model_map,
                 timeouts,
+                mock: None,
+                native_api: false,
+                max_concurrent_requests: None,
+                max_queued_requests: None,
+                queue_timeout_ms: None,
+                api_keys: vec![],
+                key_strategy: None,
+                api_key_file: None,
+                embedding_task: None,
+                vertex: None,
+                quirks: None,
+                param_profile: None,
+                prompt_cache: None,
+                adapter: None,
+                body_limits: None,
             };
             std::sync::Arc::make_mut(&mut config.channels).push(channel.clone());
             return_or_exit_json(
@@ -1449,6 +2476,12 @@ fn handle_channel_command(cli: &Cli, command: &ChannelCommand) -> anyhow::Result
             for router in std::sync::Arc::make_mut(&mut config.routers) {
                 router.channels.retain(|c| c.name != *name);
                 router.fallback_channels.retain(|c| c != name);
+                for tier in &mut router.fallback_tiers {
+                    tier.channels.retain(|c| c.name != *name);
+                }
+                router
+                    .fallback_tiers
+                    .retain(|tier| !tier.channels.is_empty());
             }
             // Remove routers that have no channels left
             std::sync::Arc::make_mut(&mut config.routers).retain(|r| !r.channels.is_empty());
@@ -1526,6 +2559,28 @@ fn handle_channel_command(cli: &Cli, command: &ChannelCommand) -> anyhow::Result
                 print_channel_table(&config.channels);
             }
         }
+        ChannelCommand::Health { json } => {
+            let config =
+                return_or_exit_json("channel", "health", *json, load_config_or_exit(&path))?;
+            let health = return_or_exit_json(
+                "channel",
+                "health",
+                *json,
+                fetch_channel_health(&config)
+                    .await
+                    .context("gateway is not reachable; is it running?"),
+            )?;
+            if *json {
+                print_json_success(
+                    "channel",
+                    "health",
+                    "Channel health fetched successfully.",
+                    Value::Array(health),
+                )?;
+            } else {
+                print_channel_health_table(&health);
+            }
+        }
     }
     Ok(())
 }
@@ -1617,12 +2672,14 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                     rules.push(config::RouterRule {
                         match_spec: config::MatchSpec {
                             models: vec![pattern],
+                            ..Default::default()
                         },
                         channels: vec![config::TargetChannel {
                             name: channel_name,
                             weight: 1,
                         }],
                         strategy: "round_robin".to_string(),
+                        retries: None,
                     });
                 }
             }
@@ -1632,9 +2689,11 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                 rules.push(config::RouterRule {
                     match_spec: config::MatchSpec {
                         models: vec!["*".to_string()],
+                        ..Default::default()
                     },
                     channels: target_channels.clone(),
                     strategy: args.strategy.clone(),
+                    retries: None,
                 });
             }
 
@@ -1646,6 +2705,26 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                 strategy: args.strategy.clone(),
                 metadata: None,
                 fallback_channels: args.fallback_channels.clone(),
+                fallback_tiers: vec![],
+                fallback_on: None,
+                reject_unknown_models: false,
+                max_request_bytes: None,
+                max_response_bytes: None,
+                cache: None,
+                passthrough: false,
+                sticky: None,
+                retries: None,
+                hedge_after_ms: None,
+                mirror_channel: None,
+                mirror_percent: None,
+                experiment: None,
+                canary: None,
+                model_aliases: Default::default(),
+                default_model: None,
+                reject_missing_model: false,
+                stream_requests: false,
+                vkey: args.vkey.then(generate_vkey),
+                transforms: None,
             };
             std::sync::Arc::make_mut(&mut config.routers).push(router.clone());
             return_or_exit_json(
@@ -1663,6 +2742,9 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                 )?;
             } else {
                 println!("✅ 已添加 router: {}", args.name);
+                if let Some(vkey) = &router.vkey {
+                    println!("Virtual Key:     {}", vkey);
+                }
             }
         }
         RouterCommand::Update(args) => {
@@ -1689,7 +2771,9 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                 && args.fallback_channels.is_empty()
                 && !args.clear_fallbacks
                 && args.strategy.is_none()
-                && args.model_matchers.is_empty();
+                && args.model_matchers.is_empty()
+                && !args.rotate_vkey
+                && !args.clear_vkey;
 
             let mut new_channels = return_or_exit_json(
                 "router",
@@ -1790,6 +2874,12 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
             } else if !args.fallback_channels.is_empty() {
                 router.fallback_channels = args.fallback_channels.clone();
             }
+
+            if args.clear_vkey {
+                router.vkey = None;
+            } else if args.rotate_vkey {
+                router.vkey = Some(generate_vkey());
+            }
             return_or_exit_json(
                 "router",
                 "update",
@@ -1806,6 +2896,11 @@ fn handle_router_command(cli: &Cli, command: &RouterCommand) -> anyhow::Result<(
                 )?;
             } else {
                 println!("✅ 已更新 router: {}", args.name);
+                if args.rotate_vkey
+                    && let Some(vkey) = &updated.vkey
+                {
+                    println!("Virtual Key:     {}", vkey);
+                }
             }
         }
         RouterCommand::Delete { name, json } => {
@@ -1888,6 +2983,13 @@ fn parse_provider_type(value: &str) -> anyhow::Result<ProviderType> {
         "jina" => Ok(ProviderType::Jina),
         "openrouter" => Ok(ProviderType::Openrouter),
         "zai" => Ok(ProviderType::Zai),
+        "groq" => Ok(ProviderType::Groq),
+        "mistral" => Ok(ProviderType::Mistral),
+        "cohere" => Ok(ProviderType::Cohere),
+        "together" => Ok(ProviderType::Together),
+        "custom" => Ok(ProviderType::Custom),
+        "vertex" => Ok(ProviderType::Vertex),
+        "mock" => Ok(ProviderType::Mock),
         _ => bail!("unsupported provider: {}", value),
     }
 }
@@ -1905,6 +3007,13 @@ fn provider_choices() -> Vec<&'static str> {
         "jina",
         "openrouter",
         "zai",
+        "groq",
+        "mistral",
+        "cohere",
+        "together",
+        "custom",
+        "vertex",
+        "mock",
     ]
 }
 
@@ -1942,6 +3051,13 @@ fn get_default_base_url(provider: &ProviderType) -> &'static str {
         ProviderType::Jina => "https://api.jina.ai/v1",
         ProviderType::Openrouter => "https://openrouter.ai/api/v1",
         ProviderType::Zai => "https://api.z.ai/api/coding/paas/v4",
+        ProviderType::Groq => "https://api.groq.com/openai/v1",
+        ProviderType::Mistral => "https://api.mistral.ai/v1",
+        ProviderType::Cohere => "https://api.cohere.ai/compatibility/v1",
+        ProviderType::Together => "https://api.together.xyz/v1",
+        ProviderType::Custom => "http://localhost:8000/v1",
+        ProviderType::Vertex => "https://us-central1-aiplatform.googleapis.com",
+        ProviderType::Mock => "mock://local",
     }
 }
 
@@ -2022,7 +3138,6 @@ fn load_provider_templates() -> anyhow::Result<Vec<ProviderTemplate>> {
     Ok(config.provider_templates)
 }
 
-#[cfg(test)]
 fn generate_vkey() -> String {
     let rand: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -2054,7 +3169,10 @@ fn ensure_channels_exist(config: &Config, channels: &[String]) -> anyhow::Result
 }
 
 fn print_router_table(routers: &[Router]) {
-    println!("{:<20} {:<20} {:<20}", "NAME", "CHANNELS", "FALLBACKS");
+    println!(
+        "{:<20} {:<20} {:<20} {:<6}",
+        "NAME", "CHANNELS", "FALLBACKS", "VKEY"
+    );
     for router in routers {
         let channels_display = if !router.channels.is_empty() {
             router
@@ -2068,10 +3186,11 @@ fn print_router_table(routers: &[Router]) {
         };
 
         println!(
-            "{:<20} {:<20} {:<20}",
+            "{:<20} {:<20} {:<20} {:<6}",
             router.name,
             channels_display,
-            router.fallback_channels.join(",")
+            router.fallback_channels.join(","),
+            if router.vkey.is_some() { "yes" } else { "no" }
         );
     }
 }
@@ -2086,10 +3205,17 @@ mod tests {
         assert!(choices.contains(&"openai"));
     }
 
+    #[test]
+    fn local_gateway_url_uses_loopback_for_wildcard_listen() {
+        assert_eq!(local_gateway_url("0.0.0.0:12356"), "http://127.0.0.1:12356");
+        assert_eq!(local_gateway_url("[::]:8080"), "http://[::1]:8080");
+        assert_eq!(local_gateway_url("10.0.0.5:9000"), "http://10.0.0.5:9000");
+    }
+
     #[test]
     fn provider_choices_count() {
         let choices = provider_choices();
-        assert_eq!(choices.len(), 11);
+        assert_eq!(choices.len(), 18);
     }
 
     #[test]
@@ -2102,6 +3228,20 @@ mod tests {
         assert_eq!(provider, ProviderType::Zai);
     }
 
+    #[test]
+    fn openai_compatible_presets_have_default_base_urls() {
+        for (name, base_url) in [
+            ("groq", "https://api.groq.com/openai/v1"),
+            ("mistral", "https://api.mistral.ai/v1"),
+            ("cohere", "https://api.cohere.ai/compatibility/v1"),
+            ("together", "https://api.together.xyz/v1"),
+        ] {
+            let provider = parse_provider_type(name).unwrap();
+            assert_eq!(get_default_base_url(&provider), base_url);
+            assert!(get_default_anthropic_base_url(&provider).is_none());
+        }
+    }
+
     #[test]
     fn provider_choices_contains_zai() {
         let choices = provider_choices();
@@ -1,5 +1,23 @@
 use anyhow::Context;
-use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Registry, TextEncoder};
+use axum::{body::Body, http::Response};
+use futures::StreamExt;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Registry, TextEncoder,
+};
+use std::time::Instant;
+
+use crate::config::MetricsBuckets;
+
+/// Default bounds for `apex_stream_tokens_per_second`.
+pub const DEFAULT_TOKENS_PER_SECOND_BUCKETS: &[f64] = &[
+    1.0, 5.0, 10.0, 20.0, 30.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0, 500.0,
+];
+
+/// Default bounds for the millisecond histograms: 10ms to 60s.
+pub const DEFAULT_LATENCY_BUCKETS_MS: &[f64] = &[
+    10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0, 60000.0,
+];
 
 #[derive(Clone)]
 pub struct MetricsState {
@@ -8,11 +26,66 @@ pub struct MetricsState {
     pub error_total: IntCounterVec,
     pub token_total: IntCounterVec,
     pub upstream_latency_ms: HistogramVec,
+    pub request_duration_ms: HistogramVec,
     pub fallback_total: IntCounterVec,
+    pub upstream_responses_total: IntCounterVec,
+    pub upstream_retries_total: IntCounterVec,
+    pub stream_ttfb_ms: HistogramVec,
+    pub stream_ttft_ms: HistogramVec,
+    pub stream_tokens_per_second: HistogramVec,
+    pub hedge_total: IntCounterVec,
+    pub mirror_total: IntCounterVec,
+    pub experiment_requests_total: IntCounterVec,
+    pub experiment_latency_ms: HistogramVec,
+    pub experiment_tokens_total: IntCounterVec,
+    pub canary_percent: GaugeVec,
+    pub canary_rollbacks_total: IntCounterVec,
+    pub vkey_requests_total: IntCounterVec,
+    pub end_user_request_total: IntCounterVec,
+    pub in_flight_requests: IntGauge,
+    pub load_shed_total: IntCounterVec,
+    pub size_limit_exceeded_total: IntCounterVec,
+    pub guardrail_blocked_total: IntCounterVec,
+    pub response_cache_total: IntCounterVec,
+    pub channel_saturated_total: IntCounterVec,
+    pub channel_queue_depth: IntGaugeVec,
+    pub active_streams: IntGauge,
+    pub upstream_requests_in_flight: IntGauge,
+    process_resident_memory_bytes: IntGauge,
+    process_cpu_seconds_total: Gauge,
+    process_open_fds: IntGauge,
+    tokio_workers: IntGauge,
+    tokio_alive_tasks: IntGauge,
+}
+
+/// Increments a gauge for as long as it is alive.
+pub struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    pub fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
 }
 
 impl MetricsState {
+    #[allow(dead_code)] // The binary always builds from config
     pub fn new() -> anyhow::Result<Self> {
+        Self::with_buckets(&MetricsBuckets::default())
+    }
+
+    pub fn with_buckets(buckets: &MetricsBuckets) -> anyhow::Result<Self> {
+        let bounds = |configured: &Option<Vec<f64>>| {
+            configured
+                .clone()
+                .unwrap_or_else(|| DEFAULT_LATENCY_BUCKETS_MS.to_vec())
+        };
         let registry = Registry::new();
         let request_total = IntCounterVec::new(
             prometheus::Opts::new("apex_requests_total", "Gateway requests total"),
@@ -30,15 +103,229 @@ impl MetricsState {
         )
         .context("create token_total")?;
         let upstream_latency_ms = HistogramVec::new(
-            HistogramOpts::new("apex_upstream_latency_ms", "Upstream latency in ms"),
+            HistogramOpts::new("apex_upstream_latency_ms", "Upstream latency in ms")
+                .buckets(bounds(&buckets.upstream_latency_ms)),
             &["route", "router", "channel"],
         )
         .context("create upstream_latency_ms")?;
+        let request_duration_ms = HistogramVec::new(
+            HistogramOpts::new(
+                "apex_request_duration_ms",
+                "Time from receiving a request to sending response headers, retries and fallbacks included, in ms",
+            )
+            .buckets(bounds(&buckets.request_duration_ms)),
+            &["route", "status_class"],
+        )
+        .context("create request_duration_ms")?;
         let fallback_total = IntCounterVec::new(
             prometheus::Opts::new("apex_fallback_total", "Gateway fallback total"),
             &["router", "channel"],
         )
         .context("create fallback_total")?;
+        let upstream_responses_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_upstream_responses_total",
+                "Upstream attempts by HTTP status (status_class 'error' for transport failures)",
+            ),
+            &["router", "channel", "model", "status_class", "code"],
+        )
+        .context("create upstream_responses_total")?;
+        let upstream_retries_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_upstream_retries_total",
+                "Upstream attempts retried on the same channel",
+            ),
+            &["router", "channel", "reason"],
+        )
+        .context("create upstream_retries_total")?;
+        let stream_ttfb_ms = HistogramVec::new(
+            HistogramOpts::new(
+                "apex_stream_ttfb_ms",
+                "Time from sending the upstream request to the first streamed body chunk in ms",
+            )
+            .buckets(bounds(&buckets.stream_ttfb_ms)),
+            &["router", "channel"],
+        )
+        .context("create stream_ttfb_ms")?;
+        let stream_ttft_ms = HistogramVec::new(
+            HistogramOpts::new(
+                "apex_stream_ttft_ms",
+                "Time from sending the upstream request to the first streamed output token in ms",
+            )
+            .buckets(bounds(&buckets.stream_ttft_ms)),
+            &["router", "channel", "model"],
+        )
+        .context("create stream_ttft_ms")?;
+        let stream_tokens_per_second = HistogramVec::new(
+            HistogramOpts::new(
+                "apex_stream_tokens_per_second",
+                "Output tokens per second between the first and last streamed output events",
+            )
+            .buckets(
+                buckets
+                    .stream_tokens_per_second
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_TOKENS_PER_SECOND_BUCKETS.to_vec()),
+            ),
+            &["router", "channel", "model"],
+        )
+        .context("create stream_tokens_per_second")?;
+        let hedge_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_hedge_total",
+                "Hedged requests sent to a second channel, by whether the hedge won",
+            ),
+            &["router", "channel", "result"],
+        )
+        .context("create hedge_total")?;
+        let mirror_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_mirror_total",
+                "Requests mirrored to a router's mirror_channel, by outcome",
+            ),
+            &["router", "channel", "result"],
+        )
+        .context("create mirror_total")?;
+        let experiment_requests_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_experiment_requests_total",
+                "Requests of routers running an experiment, by variant and response status class",
+            ),
+            &["router", "variant", "status_class"],
+        )
+        .context("create experiment_requests_total")?;
+        let experiment_latency_ms = HistogramVec::new(
+            HistogramOpts::new(
+                "apex_experiment_latency_ms",
+                "Time to response headers of experiment requests, by variant, in ms",
+            )
+            .buckets(bounds(&buckets.request_duration_ms)),
+            &["router", "variant"],
+        )
+        .context("create experiment_latency_ms")?;
+        let experiment_tokens_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_experiment_tokens_total",
+                "Tokens of experiment requests, by variant and type (input/output)",
+            ),
+            &["router", "variant", "type"],
+        )
+        .context("create experiment_tokens_total")?;
+        let canary_percent = GaugeVec::new(
+            prometheus::Opts::new(
+                "apex_canary_percent",
+                "Share of a router's requests currently sent to its canary channel",
+            ),
+            &["router", "channel"],
+        )
+        .context("create canary_percent")?;
+        let canary_rollbacks_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_canary_rollbacks_total",
+                "Canary rollouts withdrawn for exceeding their error rate or latency limit",
+            ),
+            &["router", "channel"],
+        )
+        .context("create canary_rollbacks_total")?;
+        let vkey_requests_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_vkey_requests_total",
+                "Requests authenticated with a router's virtual key",
+            ),
+            &["router"],
+        )
+        .context("create vkey_requests_total")?;
+        let end_user_request_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_end_user_requests_total",
+                "Gateway requests total by end user",
+            ),
+            &["router", "end_user"],
+        )
+        .context("create end_user_request_total")?;
+        let in_flight_requests = IntGauge::new(
+            "apex_in_flight_requests",
+            "Proxied requests currently in flight",
+        )
+        .context("create in_flight_requests")?;
+        let load_shed_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_load_shed_total",
+                "Requests rejected by global load shedding, by team priority",
+            ),
+            &["priority"],
+        )
+        .context("create load_shed_total")?;
+        let size_limit_exceeded_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_size_limit_exceeded_total",
+                "Requests or responses rejected by router size limits",
+            ),
+            &["router", "direction"],
+        )
+        .context("create size_limit_exceeded_total")?;
+        let guardrail_blocked_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_guardrail_blocked_total",
+                "Requests or responses blocked by guardrails",
+            ),
+            &["team", "rule", "stage"],
+        )
+        .context("create guardrail_blocked_total")?;
+        let response_cache_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_response_cache_total",
+                "Response cache lookups by result (hit / miss)",
+            ),
+            &["router", "result"],
+        )
+        .context("create response_cache_total")?;
+        let channel_saturated_total = IntCounterVec::new(
+            prometheus::Opts::new(
+                "apex_channel_saturated_total",
+                "Requests that skipped a channel at its max_concurrent_requests",
+            ),
+            &["router", "channel"],
+        )
+        .context("create channel_saturated_total")?;
+        let channel_queue_depth = IntGaugeVec::new(
+            prometheus::Opts::new(
+                "apex_channel_queue_depth",
+                "Requests waiting for a channel's concurrency slot",
+            ),
+            &["channel"],
+        )
+        .context("create channel_queue_depth")?;
+        let active_streams = IntGauge::new(
+            "apex_active_streams",
+            "Streaming (SSE) responses currently being sent",
+        )
+        .context("create active_streams")?;
+        let upstream_requests_in_flight = IntGauge::new(
+            "apex_upstream_requests_in_flight",
+            "Upstream requests waiting for response headers (connection pool pressure)",
+        )
+        .context("create upstream_requests_in_flight")?;
+        let process_resident_memory_bytes = IntGauge::new(
+            "apex_process_resident_memory_bytes",
+            "Resident memory size in bytes",
+        )
+        .context("create process_resident_memory_bytes")?;
+        let process_cpu_seconds_total = Gauge::new(
+            "apex_process_cpu_seconds_total",
+            "Total user and system CPU time in seconds",
+        )
+        .context("create process_cpu_seconds_total")?;
+        let process_open_fds = IntGauge::new(
+            "apex_process_open_fds",
+            "Open file descriptors (sockets included)",
+        )
+        .context("create process_open_fds")?;
+        let tokio_workers = IntGauge::new("apex_tokio_workers", "Tokio runtime worker threads")
+            .context("create tokio_workers")?;
+        let tokio_alive_tasks =
+            IntGauge::new("apex_tokio_alive_tasks", "Tokio tasks currently alive")
+                .context("create tokio_alive_tasks")?;
 
         registry
             .register(Box::new(request_total.clone()))
@@ -52,9 +339,96 @@ impl MetricsState {
         registry
             .register(Box::new(upstream_latency_ms.clone()))
             .context("register upstream_latency_ms")?;
+        registry
+            .register(Box::new(request_duration_ms.clone()))
+            .context("register request_duration_ms")?;
         registry
             .register(Box::new(fallback_total.clone()))
             .context("register fallback_total")?;
+        registry
+            .register(Box::new(upstream_responses_total.clone()))
+            .context("register upstream_responses_total")?;
+        registry
+            .register(Box::new(upstream_retries_total.clone()))
+            .context("register upstream_retries_total")?;
+        registry
+            .register(Box::new(stream_ttfb_ms.clone()))
+            .context("register stream_ttfb_ms")?;
+        registry
+            .register(Box::new(stream_ttft_ms.clone()))
+            .context("register stream_ttft_ms")?;
+        registry
+            .register(Box::new(stream_tokens_per_second.clone()))
+            .context("register stream_tokens_per_second")?;
+        registry
+            .register(Box::new(hedge_total.clone()))
+            .context("register hedge_total")?;
+        registry
+            .register(Box::new(mirror_total.clone()))
+            .context("register mirror_total")?;
+        registry
+            .register(Box::new(experiment_requests_total.clone()))
+            .context("register experiment_requests_total")?;
+        registry
+            .register(Box::new(experiment_latency_ms.clone()))
+            .context("register experiment_latency_ms")?;
+        registry
+            .register(Box::new(experiment_tokens_total.clone()))
+            .context("register experiment_tokens_total")?;
+        registry
+            .register(Box::new(canary_percent.clone()))
+            .context("register canary_percent")?;
+        registry
+            .register(Box::new(canary_rollbacks_total.clone()))
+            .context("register canary_rollbacks_total")?;
+        registry
+            .register(Box::new(vkey_requests_total.clone()))
+            .context("register vkey_requests_total")?;
+        registry
+            .register(Box::new(end_user_request_total.clone()))
+            .context("register end_user_request_total")?;
+        registry
+            .register(Box::new(in_flight_requests.clone()))
+            .context("register in_flight_requests")?;
+        registry
+            .register(Box::new(load_shed_total.clone()))
+            .context("register load_shed_total")?;
+        registry
+            .register(Box::new(size_limit_exceeded_total.clone()))
+            .context("register size_limit_exceeded_total")?;
+        registry
+            .register(Box::new(guardrail_blocked_total.clone()))
+            .context("register guardrail_blocked_total")?;
+        registry
+            .register(Box::new(response_cache_total.clone()))
+            .context("register response_cache_total")?;
+        registry
+            .register(Box::new(channel_saturated_total.clone()))
+            .context("register channel_saturated_total")?;
+        registry
+            .register(Box::new(channel_queue_depth.clone()))
+            .context("register channel_queue_depth")?;
+        registry
+            .register(Box::new(active_streams.clone()))
+            .context("register active_streams")?;
+        registry
+            .register(Box::new(upstream_requests_in_flight.clone()))
+            .context("register upstream_requests_in_flight")?;
+        registry
+            .register(Box::new(process_resident_memory_bytes.clone()))
+            .context("register process_resident_memory_bytes")?;
+        registry
+            .register(Box::new(process_cpu_seconds_total.clone()))
+            .context("register process_cpu_seconds_total")?;
+        registry
+            .register(Box::new(process_open_fds.clone()))
+            .context("register process_open_fds")?;
+        registry
+            .register(Box::new(tokio_workers.clone()))
+            .context("register tokio_workers")?;
+        registry
+            .register(Box::new(tokio_alive_tasks.clone()))
+            .context("register tokio_alive_tasks")?;
 
         Ok(Self {
             registry,
@@ -62,11 +436,67 @@ impl MetricsState {
             error_total,
             token_total,
             upstream_latency_ms,
+            request_duration_ms,
             fallback_total,
+            upstream_responses_total,
+            upstream_retries_total,
+            stream_ttfb_ms,
+            stream_ttft_ms,
+            stream_tokens_per_second,
+            hedge_total,
+            mirror_total,
+            experiment_requests_total,
+            experiment_latency_ms,
+            experiment_tokens_total,
+            canary_percent,
+            canary_rollbacks_total,
+            vkey_requests_total,
+            end_user_request_total,
+            in_flight_requests,
+            load_shed_total,
+            size_limit_exceeded_total,
+            guardrail_blocked_total,
+            response_cache_total,
+            channel_saturated_total,
+            channel_queue_depth,
+            active_streams,
+            upstream_requests_in_flight,
+            process_resident_memory_bytes,
+            process_cpu_seconds_total,
+            process_open_fds,
+            tokio_workers,
+            tokio_alive_tasks,
         })
     }
 
+    /// Count one upstream attempt by status; `None` is a transport failure
+    /// (connect error, timeout) that never produced a status.
+    pub fn record_upstream_status(
+        &self,
+        router: &str,
+        channel: &str,
+        model: &str,
+        status: Option<u16>,
+    ) {
+        let (class, code) = match status {
+            Some(code) => (format!("{}xx", code / 100), code.to_string()),
+            None => ("error".to_string(), "none".to_string()),
+        };
+        self.upstream_responses_total
+            .with_label_values(&[router, channel, model, &class, &code])
+            .inc();
+    }
+
+    /// Observe a client request's total duration, labelled by route and the
+    /// status class of the response it got.
+    pub fn observe_request_duration(&self, route: &str, status: u16, start: Instant) {
+        self.request_duration_ms
+            .with_label_values(&[route, &format!("{}xx", status / 100)])
+            .observe(start.elapsed().as_secs_f64() * 1000.0);
+    }
+
     pub fn render(&self) -> anyhow::Result<String> {
+        self.refresh_process_metrics();
         let encoder = TextEncoder::new();
         let metric_families = self.registry.gather();
         let mut buffer = Vec::new();
@@ -75,4 +505,63 @@ impl MetricsState {
             .context("encode metrics")?;
         String::from_utf8(buffer).context("metrics utf8")
     }
+
+    /// Sample process and runtime gauges; called on every scrape.
+    fn refresh_process_metrics(&self) {
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let runtime = handle.metrics();
+            self.tokio_workers.set(runtime.num_workers() as i64);
+            self.tokio_alive_tasks.set(runtime.num_alive_tasks() as i64);
+        }
+        if let Some(rss) = read_rss_bytes() {
+            self.process_resident_memory_bytes.set(rss);
+        }
+        if let Some(cpu) = read_cpu_seconds() {
+            self.process_cpu_seconds_total.set(cpu);
+        }
+        if let Ok(fds) = std::fs::read_dir("/proc/self/fd") {
+            self.process_open_fds.set(fds.count() as i64);
+        }
+    }
+}
+
+/// Observe the time from `start` until the first body chunk of `response`
+/// is polled, i.e. when a streaming client sees its first bytes.
+pub fn observe_first_chunk(
+    response: Response<Body>,
+    histogram: Histogram,
+    start: Instant,
+) -> Response<Body> {
+    let (parts, body) = response.into_parts();
+    let mut pending = Some(histogram);
+    let stream = body.into_data_stream().inspect(move |_| {
+        if let Some(histogram) = pending.take() {
+            histogram.observe(start.elapsed().as_secs_f64() * 1000.0);
+        }
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+/// `VmRSS` from `/proc/self/status` (Linux only; `None` elsewhere).
+fn read_rss_bytes() -> Option<i64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib: i64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// utime + stime from `/proc/self/stat`, in USER_HZ (always 100 on Linux).
+fn read_cpu_seconds() -> Option<f64> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces; fields resume after its ')'.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: f64 = fields.get(11)?.parse().ok()?;
+    let stime: f64 = fields.get(12)?.parse().ok()?;
+    Some((utime + stime) / 100.0)
 }
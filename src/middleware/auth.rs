@@ -1,3 +1,4 @@
+use crate::middleware::tenant::TenantContext;
 use crate::server::AppState;
 use axum::{
     body::Body,
@@ -11,6 +12,23 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct TeamContext {
     pub team_id: String,
+    /// Model scope of the managed key used (`TeamKey::models`); empty when
+    /// the key is unscoped or the legacy team key.
+    pub key_models: Vec<String>,
+}
+
+impl TeamContext {
+    /// Whether the presented key's own scope admits `model`.
+    pub fn allows_model(&self, model: &str) -> bool {
+        crate::key_store::scope_allows(&self.key_models, model)
+    }
+}
+
+/// Set when the caller presented a router's `vkey`; routing is confined to
+/// that router.
+#[derive(Clone)]
+pub struct RouterKeyContext {
+    pub router: String,
 }
 
 pub async fn team_auth(
@@ -36,10 +54,51 @@ pub async fn team_auth(
         }
     }
 
+    let api_key_seen = api_key_opt.clone();
+    let request_tenant = req
+        .extensions()
+        .get::<TenantContext>()
+        .map(|ctx| ctx.tenant_id.clone());
+    let mut vkey_router = None;
+    let mut key_scope = (None, Vec::new());
     let team_id = if let Some(api_key) = api_key_opt {
         let config = state.config.read().unwrap();
-        // 1. Check Teams
-        if let Some(team) = config.teams.iter().find(|t| t.api_key == api_key) {
+        // 0. Revoked keys are rejected outright, whoever owns them.
+        if config.global.is_key_revoked(&api_key) {
+            tracing::warn!("Auth Failed: Revoked API Key used in {:?}", source_opt);
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"error": "API key has been revoked"}"#))
+                .unwrap();
+        }
+        // 1. Check Teams (legacy key or a managed key)
+        if let Some(found) = crate::key_store::find(&config, &api_key) {
+            let team = found.team;
+            if let Some(key) = found.key {
+                let error = match key.status(chrono::Utc::now()) {
+                    crate::key_store::KeyStatus::Active => None,
+                    crate::key_store::KeyStatus::Revoked => {
+                        Some(r#"{"error": "API key has been revoked"}"#)
+                    }
+                    crate::key_store::KeyStatus::Expired => {
+                        Some(r#"{"error": "API key has expired"}"#)
+                    }
+                };
+                if let Some(body) = error {
+                    tracing::warn!(
+                        "Auth Failed: Key '{}' of team '{}' is no longer active",
+                        key.id,
+                        team.id
+                    );
+                    return Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap();
+                }
+                key_scope = (Some(key.id.clone()), key.models.clone());
+            }
             // Paused team: reject before any upstream work happens.
             if team.is_paused() {
                 tracing::warn!("Auth Failed: Team '{}' is paused (enabled=false)", team.id);
@@ -51,19 +110,57 @@ pub async fn team_auth(
                     ))
                     .unwrap();
             }
+            // Tenant keys only work at their own tenant's address, and
+            // top-level keys only outside of any tenant.
+            if config.tenant_of(&team.id) != request_tenant.as_deref() {
+                tracing::warn!(
+                    "Auth Failed: Team '{}' used outside its tenant (request tenant: {:?})",
+                    team.id,
+                    request_tenant
+                );
+                return Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error": "Invalid Team API Key"}"#))
+                    .unwrap();
+            }
             Some(team.id.clone())
+        } else if let Some(router) = config
+            .routers
+            .iter()
+            .find(|r| r.vkey.as_deref() == Some(api_key.as_str()))
+        {
+            // 2. Router virtual key, bound to its tenant like team keys.
+            if config.tenant_of(&router.name) != request_tenant.as_deref() {
+                tracing::warn!(
+                    "Auth Failed: Virtual key of router '{}' used outside its tenant",
+                    router.name
+                );
+                return Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error": "Invalid virtual key"}"#))
+                    .unwrap();
+            }
+            vkey_router = Some(router.name.clone());
+            None
         } else {
-            // 2. Invalid Key -> Reject (Global keys are NOT allowed for model requests)
+            // 3. Invalid Key -> Reject (Global keys are NOT allowed for model requests)
             let source = source_opt.unwrap_or_else(|| "unknown".to_string());
             tracing::warn!(
                 "Auth Failed: Invalid Team API Key '{}' provided in {}",
                 api_key,
                 source
             );
+            let body = if api_key.starts_with("vk_") {
+                r#"{"error": "Invalid virtual key"}"#
+            } else {
+                r#"{"error": "Invalid Team API Key"}"#
+            };
             return Response::builder()
                 .status(StatusCode::UNAUTHORIZED)
                 .header("content-type", "application/json")
-                .body(Body::from(r#"{"error": "Invalid Team API Key"}"#))
+                .body(Body::from(body))
                 .unwrap();
         }
     } else {
@@ -71,15 +168,28 @@ pub async fn team_auth(
     };
 
     if let Some(id) = team_id {
+        // Managed keys are tracked by id, as their plaintext is not stored.
+        let (key_id, key_models) = key_scope;
+        let fingerprint =
+            key_id.or_else(|| api_key_seen.as_deref().map(crate::utils::key_fingerprint));
+        if let Some(fingerprint) = fingerprint {
+            state.database.touch_key_usage(&fingerprint, &id);
+        }
+
         // Inject Team Context into Request Extensions
         req.extensions_mut().insert(TeamContext {
             team_id: id.clone(),
+            key_models,
         });
 
         // Record in tracing span
         tracing::Span::current().record("team_id", &id);
         tracing::info!("Team Resolved: {}", id);
     }
+    if let Some(router) = vkey_router {
+        tracing::info!("Virtual Key Resolved: router {}", router);
+        req.extensions_mut().insert(RouterKeyContext { router });
+    }
 
     next.run(req).await
 }
@@ -103,9 +213,12 @@ pub async fn global_auth(State(state): State<Arc<AppState>>, req: Request, next:
         }
     }
 
-    let auth_keys = {
+    let (auth_keys, revoked_keys) = {
         let config = state.config.read().unwrap();
-        config.global.auth_keys.clone()
+        (
+            config.global.auth_keys.clone(),
+            config.global.revoked_keys.clone(),
+        )
     };
 
     // If no auth_keys configured, skip validation
@@ -114,7 +227,9 @@ pub async fn global_auth(State(state): State<Arc<AppState>>, req: Request, next:
     }
 
     // Check if provided key is authorized
-    let authorized = api_key.map(|k| auth_keys.contains(&k)).unwrap_or(false);
+    let authorized = api_key
+        .map(|k| auth_keys.contains(&k) && !revoked_keys.contains(&k))
+        .unwrap_or(false);
 
     if authorized {
         next.run(req).await
@@ -129,6 +244,32 @@ pub async fn global_auth(State(state): State<Arc<AppState>>, req: Request, next:
     }
 }
 
+/// Guards the dedicated metrics listener with `metrics.auth_token`, read per
+/// request so a reload rotates the token.
+pub async fn metrics_auth(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let token = state.config.read().unwrap().metrics.auth_token.clone();
+    let authorized = match token {
+        None => true,
+        Some(token) => extract_api_key(req.headers()).is_some_and(|key| key == token),
+    };
+
+    if authorized {
+        next.run(req).await
+    } else {
+        Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"error": "Unauthorized: Metrics Token Required"}"#,
+            ))
+            .unwrap()
+    }
+}
+
 fn extract_api_key(headers: &HeaderMap) -> Option<String> {
     // Try Authorization: Bearer <token>
     if let Some(auth_val) = headers.get("authorization").and_then(|v| v.to_str().ok()) {
@@ -1,5 +1,7 @@
-use crate::compliance::{PiiProcessor, process_json_content};
-use crate::server::{AppState, MAX_REQUEST_BODY_BYTES, error_response};
+use crate::compliance::{PiiDetection, PiiProcessor, process_json_content, summarize};
+use crate::middleware::auth::TeamContext;
+use crate::providers::RouteKind;
+use crate::server::{AppState, body_read_error, error_response, request_body_limit};
 use axum::{
     body::Body,
     extract::{Request, State},
@@ -21,30 +23,33 @@ pub async fn compliance_middleware(
         return next.run(req).await;
     }
 
-    let compliance = {
+    let (processor, body_limit) = {
         let config = state.config.read().unwrap();
-        config.compliance.clone()
+        let body_limit = request_body_limit(&config.global, req.headers());
+        let team_pii = req
+            .extensions()
+            .get::<TeamContext>()
+            .and_then(|ctx| config.teams.iter().find(|t| t.id == ctx.team_id))
+            .and_then(|team| team.policy.pii.as_ref());
+        let processor = match (team_pii, config.compliance.as_ref()) {
+            (Some(policy), _) => Some(PiiProcessor::for_team(policy)),
+            (None, Some(compliance)) if compliance.enabled => {
+                Some(PiiProcessor::new(&Some(compliance.clone())))
+            }
+            (None, _) => None,
+        };
+        (processor, body_limit)
     };
-
-    let Some(compliance) = compliance else {
+    let Some(processor) = processor.filter(PiiProcessor::is_enabled) else {
         return next.run(req).await;
     };
 
-    if !compliance.enabled {
-        return next.run(req).await;
-    }
-
-    let processor = PiiProcessor::new(&Some(compliance));
     let (mut parts, body) = req.into_parts();
 
-    let bytes = match axum::body::to_bytes(body, MAX_REQUEST_BODY_BYTES).await {
+    let bytes = match axum::body::to_bytes(body, body_limit).await {
         Ok(bytes) => bytes,
         Err(err) => {
-            tracing::error!(
-                "Request Failed: Failed to read body in compliance middleware: {}",
-                err
-            );
-            return error_response(StatusCode::BAD_REQUEST, &err.to_string());
+            return body_read_error(RouteKind::from_path(parts.uri.path()), body_limit, err);
         }
     };
 
@@ -63,12 +68,21 @@ pub async fn compliance_middleware(
             "Request Blocked: PII detected (rule={}, action=block)",
             detection.rule_name
         );
+        audit_redactions(&state, &parts, std::slice::from_ref(&detection));
         return error_response(
             StatusCode::FORBIDDEN,
             &format!("Request blocked: {} detected", detection.rule_name),
         );
     }
 
+    // Multipart uploads carry binary files; they are checked for blocked
+    // content above but forwarded byte-for-byte rather than masked.
+    if crate::utils::is_multipart(&parts.headers) {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes.clone())))
+            .await;
+    }
+
     let (processed_body, detections) = process_json_content(&processor, &body_str);
 
     if !detections.is_empty() {
@@ -76,6 +90,7 @@ pub async fn compliance_middleware(
             "PII Masking Applied: {} detections in request",
             detections.len()
         );
+        audit_redactions(&state, &parts, &detections);
     }
 
     parts.headers.remove(CONTENT_LENGTH);
@@ -83,3 +98,25 @@ pub async fn compliance_middleware(
     let req = Request::from_parts(parts, Body::from(processed_body));
     next.run(req).await
 }
+
+/// Record which rules fired for this request (never the matched text).
+fn audit_redactions(
+    state: &AppState,
+    parts: &axum::http::request::Parts,
+    detections: &[PiiDetection],
+) {
+    let request_id = parts
+        .extensions
+        .get::<tower_http::request_id::RequestId>()
+        .and_then(|id| id.header_value().to_str().ok());
+    let team_id = parts
+        .extensions
+        .get::<TeamContext>()
+        .map_or("global", |ctx| ctx.team_id.as_str());
+    if let Err(err) = state
+        .database
+        .log_pii_redactions(request_id, team_id, &summarize(detections))
+    {
+        tracing::error!("PII redaction audit write failed: {}", err);
+    }
+}
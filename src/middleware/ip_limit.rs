@@ -0,0 +1,202 @@
+use crate::middleware::auth::TeamContext;
+use crate::middleware::ratelimit::Quota;
+use crate::providers::RouteKind;
+use crate::server::{AppState, rate_limited_response};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Gateway-wide and per-client-IP RPM limits, checked before routing.
+///
+/// `global.rate_limit` covers every proxied request. `global.ip_rate_limit`
+/// only applies when no `global.auth_keys` are configured and the request
+/// carries no team key — authenticated traffic is governed by team policy.
+/// Responses carry `X-RateLimit-*` headers for the tightest limit applied.
+pub async fn ip_rate_limit(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let anonymous = req.extensions().get::<TeamContext>().is_none();
+    let (global_rpm, ip_limit) = {
+        let config = state.config.read().unwrap();
+        let global_rpm = config
+            .global
+            .rate_limit
+            .as_ref()
+            .map(|l| l.rpm)
+            .filter(|&rpm| rpm > 0);
+        let ip_limit = config
+            .global
+            .ip_rate_limit
+            .as_ref()
+            .filter(|l| l.rpm > 0 && anonymous && config.global.auth_keys.is_empty())
+            .map(|l| (l.rpm, config.global.trusted_proxies.clone()));
+        (global_rpm, ip_limit)
+    };
+
+    // Per-IP first, so one abusive client can't drain the shared budget.
+    let mut buckets = Vec::new();
+    if let Some((rpm, trusted_proxies)) = ip_limit {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        if let Some(client_ip) = resolve_client_ip(peer, req.headers(), &trusted_proxies) {
+            buckets.push((format!("ip:{}", client_ip), rpm));
+        }
+    }
+    if let Some(rpm) = global_rpm {
+        buckets.push((GLOBAL_BUCKET.to_string(), rpm));
+    }
+    if buckets.is_empty() {
+        return Ok(next.run(req).await);
+    }
+
+    let mut tightest: Option<Quota> = None;
+    for (key, rpm) in &buckets {
+        match state.team_rate_limiter.check_requests(key, *rpm).await {
+            Ok(quota) => {
+                if tightest.is_none_or(|t| quota.remaining < t.remaining) {
+                    tightest = Some(quota);
+                }
+            }
+            Err(quota) => {
+                tracing::warn!("Rate Limit Exceeded: {}", key);
+                let mut response = rate_limited_response(
+                    RouteKind::from_path(req.uri().path()),
+                    quota.retry_after,
+                    "Rate limit exceeded",
+                );
+                insert_quota_headers(response.headers_mut(), &quota);
+                return Err(response);
+            }
+        }
+    }
+
+    let mut response = next.run(req).await;
+    if let Some(quota) = tightest {
+        insert_quota_headers(response.headers_mut(), &quota);
+    }
+    Ok(response)
+}
+
+/// Bucket key of `global.rate_limit`.
+const GLOBAL_BUCKET: &str = "global:requests";
+
+fn insert_quota_headers(headers: &mut HeaderMap, quota: &Quota) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(quota.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(quota.remaining));
+    headers.insert(
+        "x-ratelimit-reset",
+        HeaderValue::from(quota.reset.as_secs_f64().ceil() as u64),
+    );
+}
+
+/// Resolve the originating client IP.
+///
+/// `X-Forwarded-For` is honored only when the socket peer is a trusted proxy;
+/// the chain is walked right-to-left, skipping trusted hops, so a client can't
+/// spoof its address by prepending entries.
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[String],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !is_trusted(peer, trusted_proxies) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|part| part.trim().parse().ok())
+        .collect();
+
+    Some(
+        forwarded
+            .iter()
+            .rev()
+            .copied()
+            .find(|ip| !is_trusted(*ip, trusted_proxies))
+            .or_else(|| forwarded.first().copied())
+            .unwrap_or(peer),
+    )
+}
+
+fn is_trusted(ip: IpAddr, trusted_proxies: &[String]) -> bool {
+    trusted_proxies.iter().any(|spec| ip_matches(ip, spec))
+}
+
+/// Match an IP against a single address or CIDR block (`10.0.0.0/8`).
+fn ip_matches(ip: IpAddr, spec: &str) -> bool {
+    let (addr, prefix) = match spec.trim().split_once('/') {
+        Some((addr, prefix)) => match prefix.parse::<u32>() {
+            Ok(prefix) => (addr, Some(prefix)),
+            Err(_) => return false,
+        },
+        None => (spec.trim(), None),
+    };
+    let Ok(network) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let prefix = prefix.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let prefix = prefix.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xff(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarded_header() {
+        let peer = "203.0.113.9".parse().ok();
+        let ip = resolve_client_ip(peer, &xff("1.1.1.1"), &[]);
+        assert_eq!(ip, peer);
+    }
+
+    #[test]
+    fn trusted_peer_uses_rightmost_untrusted_hop() {
+        let trusted = vec!["10.0.0.0/8".to_string()];
+        let peer = "10.0.0.2".parse().ok();
+        let ip = resolve_client_ip(peer, &xff("6.6.6.6, 198.51.100.7, 10.1.2.3"), &trusted);
+        assert_eq!(ip, "198.51.100.7".parse().ok());
+    }
+
+    #[test]
+    fn cidr_matching() {
+        let ip: IpAddr = "192.168.1.20".parse().unwrap();
+        assert!(ip_matches(ip, "192.168.1.0/24"));
+        assert!(ip_matches(ip, "192.168.1.20"));
+        assert!(!ip_matches(ip, "192.168.2.0/24"));
+        assert!(ip_matches(ip, "0.0.0.0/0"));
+        assert!(ip_matches("::1".parse().unwrap(), "::1/128"));
+        assert!(!ip_matches(ip, "not-an-ip"));
+    }
+}
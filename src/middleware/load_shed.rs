@@ -0,0 +1,105 @@
+use crate::config::TeamPriority;
+use crate::middleware::auth::TeamContext;
+use crate::server::AppState;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Decrements the in-flight counter when dropped.
+struct InFlightGuard {
+    counter: Arc<AtomicUsize>,
+    gauge: prometheus::IntGauge,
+}
+
+impl InFlightGuard {
+    fn acquire(counter: Arc<AtomicUsize>, gauge: prometheus::IntGauge) -> (Self, usize) {
+        let current = counter.fetch_add(1, Ordering::AcqRel) + 1;
+        gauge.inc();
+        (Self { counter, gauge }, current)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::AcqRel);
+        self.gauge.dec();
+    }
+}
+
+/// Global load shedding: tracks in-flight proxied requests and rejects new
+/// ones with 503 + `Retry-After` once `global.load_shedding.max_in_flight` is
+/// exceeded, or once more than `max_queued` requests wait for channel slots.
+/// A request stays in flight until its response body is fully sent, so
+/// stalled upstream streams keep counting against the ceiling. Teams with
+/// `priority: low` are shed at `low_priority_fraction` of each limit, so the
+/// remaining capacity is kept for the others; `high` teams (and
+/// `exempt_teams`) are never shed.
+pub async fn load_shed(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let team = req.extensions().get::<TeamContext>();
+    let (settings, priority) = {
+        let config = state.config.read().unwrap();
+        let priority = team
+            .and_then(|ctx| config.teams.iter().find(|t| t.id == ctx.team_id))
+            .map(|team| team.policy.priority)
+            .unwrap_or_default();
+        (config.global.load_shedding.clone(), priority)
+    };
+    let (guard, current) = InFlightGuard::acquire(
+        state.in_flight.clone(),
+        state.metrics.in_flight_requests.clone(),
+    );
+
+    if let Some(settings) = settings {
+        let exempt = priority == TeamPriority::High
+            || team.is_some_and(|ctx| settings.exempt_teams.contains(&ctx.team_id));
+        let scale = |limit: usize| match priority {
+            TeamPriority::Low => (limit as f64 * settings.low_priority_fraction) as usize,
+            _ => limit,
+        };
+        let overload = if settings.max_in_flight > 0 && current > scale(settings.max_in_flight) {
+            Some(format!(
+                "{} requests in flight (max {})",
+                current - 1,
+                scale(settings.max_in_flight)
+            ))
+        } else {
+            settings.max_queued.and_then(|max_queued| {
+                let queued = state.channel_limits.queued();
+                (queued > scale(max_queued))
+                    .then(|| format!("{queued} requests queued (max {})", scale(max_queued)))
+            })
+        };
+        if let Some(overload) = overload.filter(|_| !exempt) {
+            drop(guard);
+            state
+                .metrics
+                .load_shed_total
+                .with_label_values(&[priority.as_str()])
+                .inc();
+            tracing::warn!("Load Shed: {} (priority {})", overload, priority.as_str());
+            return Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header("content-type", "application/json")
+                .header("retry-after", settings.retry_after_secs.to_string())
+                .body(Body::from(
+                    r#"{"error": "Gateway overloaded, retry later"}"#,
+                ))
+                .unwrap();
+        }
+    }
+
+    let response = next.run(req).await;
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
@@ -1,4 +1,8 @@
 pub mod auth;
 pub mod compliance;
+pub mod ip_limit;
+pub mod load_shed;
 pub mod policy;
 pub mod ratelimit;
+pub mod redis_buckets;
+pub mod tenant;
@@ -1,9 +1,15 @@
 use crate::middleware::auth::TeamContext;
+use crate::providers::RouteKind;
 use crate::server::AppState;
+use crate::server::{
+    body_read_error, insert_remaining_headers, protocol_error_response, rate_limited_response,
+    request_body_limit,
+};
+use crate::tokens::TokenLimits;
 use axum::{
     body::Body,
     extract::{Request, State},
-    http::StatusCode,
+    http::{StatusCode, header::CONTENT_LENGTH},
     middleware::Next,
     response::Response,
 };
@@ -11,14 +17,15 @@ use std::sync::Arc;
 
 pub async fn team_policy(
     State(state): State<Arc<AppState>>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<Response, Response> {
     let team_ctx = req.extensions().get::<TeamContext>().cloned();
 
     if let Some(ctx) = team_ctx {
-        let (rpm_limit, tpm_limit, team_id) = {
+        let (rpm_limit, tpm_limit, team_id, token_limits, body_limit) = {
             let config = state.config.read().unwrap();
+            let body_limit = request_body_limit(&config.global, req.headers());
             if let Some(team) = config.teams.iter().find(|t| t.id == ctx.team_id) {
                 let policy = &team.policy;
                 let rpm = policy
@@ -33,29 +40,115 @@ pub async fn team_policy(
                     .and_then(|l| l.tpm)
                     .filter(|&v| v > 0)
                     .map(|v| v as u32);
-                (rpm, tpm, Some(team.id.clone()))
+                let token_limits = TokenLimits {
+                    max_tokens: policy.max_tokens_per_request,
+                    max_context: policy.max_context_tokens,
+                    clamp: policy.clamp_max_tokens,
+                };
+                (rpm, tpm, Some(team.id.clone()), token_limits, body_limit)
             } else {
-                (None, None, None)
+                (None, None, None, TokenLimits::default(), body_limit)
             }
         };
 
-        let limit_exceeded = if let Some(id) = &team_id {
-            (rpm_limit.is_some() || tpm_limit.is_some())
-                && !state.team_rate_limiter.check(id, rpm_limit, tpm_limit, 100)
-        } else {
-            false
+        let Some(team_id) = team_id else {
+            return Ok(next.run(req).await);
         };
 
-        if limit_exceeded {
-            let id = team_id.as_ref().unwrap();
-            tracing::warn!("Rate Limit Exceeded: Team '{}'", id);
-            return Err(Response::builder()
-                .status(StatusCode::TOO_MANY_REQUESTS)
-                .header("content-type", "application/json")
-                .body(Body::from(r#"{"error": "Rate limit exceeded"}"#))
-                .unwrap());
+        // Read the body only when a limit depends on its size.
+        let mut estimated = 0;
+        if (token_limits.is_set() || tpm_limit.is_some())
+            && !crate::utils::is_multipart(req.headers())
+        {
+            let (checked, tokens) = check_body(&token_limits, &team_id, body_limit, req).await?;
+            req = checked;
+            estimated = tokens.min(u32::MAX as u64) as u32;
+        }
+
+        if rpm_limit.is_some() || tpm_limit.is_some() {
+            if let Err(retry_after) = state
+                .team_rate_limiter
+                .check_with_retry(&team_id, rpm_limit, tpm_limit, estimated)
+                .await
+            {
+                tracing::warn!(
+                    "Rate Limit Exceeded: Team '{}' (retry after {:?})",
+                    team_id,
+                    retry_after
+                );
+                let mut response = rate_limited_response(
+                    RouteKind::from_path(req.uri().path()),
+                    retry_after,
+                    "Rate limit exceeded",
+                );
+                insert_remaining_headers(
+                    response.headers_mut(),
+                    &state.team_rate_limiter,
+                    &team_id,
+                )
+                .await;
+                return Err(response);
+            }
+            // Replaced by the real token count once usage is logged.
+            if tpm_limit.is_some()
+                && let Some(request_id) = request_id(&req)
+            {
+                state
+                    .team_rate_limiter
+                    .reserve(&request_id, &team_id, estimated);
+            }
         }
     }
 
     Ok(next.run(req).await)
 }
+
+fn request_id(req: &Request) -> Option<String> {
+    req.extensions()
+        .get::<tower_http::request_id::RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .map(str::to_string)
+}
+
+/// Reject (or clamp) JSON requests over the team's completion and context
+/// limits before they reach an upstream, and estimate the tokens the request
+/// will use for the TPM bucket.
+async fn check_body(
+    limits: &TokenLimits,
+    team_id: &str,
+    body_limit: usize,
+    req: Request,
+) -> Result<(Request, u64), Response> {
+    let route = RouteKind::from_path(req.uri().path());
+    let (mut parts, body) = req.into_parts();
+    let bytes = axum::body::to_bytes(body, body_limit)
+        .await
+        .map_err(|err| body_read_error(route, body_limit, err))?;
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Ok((Request::from_parts(parts, Body::from(bytes)), 0));
+    };
+    let path = parts.uri.path().to_string();
+    let body = match crate::tokens::enforce(limits, &path, &mut value) {
+        Ok(false) => Body::from(bytes),
+        Ok(true) => {
+            tracing::info!(
+                "Token Limit: clamped completion limit for team '{}'",
+                team_id
+            );
+            parts.headers.remove(CONTENT_LENGTH);
+            serde_json::to_vec(&value)
+                .map(Body::from)
+                .unwrap_or(Body::from(bytes))
+        }
+        Err(message) => {
+            tracing::warn!("Token Limit Exceeded: Team '{}': {}", team_id, message);
+            return Err(protocol_error_response(
+                route,
+                StatusCode::BAD_REQUEST,
+                &message,
+            ));
+        }
+    };
+    let estimated = crate::tokens::estimate_request(&path, &value);
+    Ok((Request::from_parts(parts, body), estimated))
+}
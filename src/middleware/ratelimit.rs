@@ -1,7 +1,11 @@
+use crate::config::RateLimitStore;
+use crate::middleware::redis_buckets::RedisBuckets;
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone, Copy)]
 struct TokenBucket {
     tokens: f64,
     last_refill: Instant,
@@ -19,11 +23,15 @@ impl TokenBucket {
         }
     }
 
-    fn consume(&mut self, amount: f64) -> bool {
+    fn refill(&mut self) {
         let now = Instant::now();
         let elapsed = now.duration_since(self.last_refill).as_secs_f64();
         self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
         self.last_refill = now;
+    }
+
+    fn consume(&mut self, amount: f64) -> bool {
+        self.refill();
 
         if self.tokens >= amount {
             self.tokens -= amount;
@@ -32,12 +40,60 @@ impl TokenBucket {
             false
         }
     }
+
+    /// Time until `amount` tokens (capped at capacity) will be available.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.tokens;
+        if missing <= 0.0 || self.refill_rate <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(missing / self.refill_rate)
+    }
+}
+
+/// State of a request bucket after a check, for `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the bucket is full again.
+    pub reset: Duration,
+    /// Time until the next request is admitted; zero when one is now.
+    pub retry_after: Duration,
+}
+
+/// Point-in-time copy of one bucket, persisted so restarts don't hand every
+/// team a fresh burst allowance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketSnapshot {
+    pub key: String,
+    /// `rpm` or `tpm`.
+    pub kind: String,
+    pub tokens: f64,
+    pub capacity: f64,
+    pub refill_rate: f64,
+}
+
+/// How long an unsettled TPM reservation is kept before it is dropped (and
+/// its estimate stays charged).
+const RESERVATION_TTL: Duration = Duration::from_secs(600);
+
+/// Tokens charged up front for one request, settled against real usage.
+struct Reservation {
+    keys: Vec<String>,
+    estimated: f64,
+    at: Instant,
 }
 
 pub struct TeamRateLimiter {
     // Map<TeamID, Map<Type, Bucket>>
     // Type: "rpm", "tpm"
     buckets: Mutex<HashMap<String, HashMap<String, TokenBucket>>>,
+    /// Request id -> TPM estimate charged at admission.
+    reservations: Mutex<HashMap<String, Reservation>>,
+    /// Shared buckets; the local ones above stand in while Redis is down.
+    redis: Option<Arc<RedisBuckets>>,
+    redis_down: AtomicBool,
 }
 
 impl Default for TeamRateLimiter {
@@ -46,54 +102,356 @@ impl Default for TeamRateLimiter {
     }
 }
 
+/// A bucket after taking from it.
+#[derive(Debug, Clone, Copy)]
+struct Taken {
+    admitted: bool,
+    bucket: TokenBucket,
+}
+
 impl TeamRateLimiter {
     pub fn new() -> Self {
         Self {
             buckets: Mutex::new(HashMap::new()),
+            reservations: Mutex::new(HashMap::new()),
+            redis: None,
+            redis_down: AtomicBool::new(false),
+        }
+    }
+
+    /// Buckets in `global.rate_limit_store`: in memory unless Redis is set.
+    pub fn from_config(store: Option<&RateLimitStore>) -> anyhow::Result<Self> {
+        let mut limiter = Self::new();
+        if let Some(RateLimitStore::Redis { url, key_prefix }) = store {
+            limiter.redis = Some(Arc::new(RedisBuckets::new(url, key_prefix)?));
+        }
+        Ok(limiter)
+    }
+
+    /// Whether buckets are shared through Redis (and not persisted locally).
+    pub fn is_shared(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    /// Log once per outage rather than once per request.
+    fn note_redis(&self, result: &anyhow::Result<impl Sized>) {
+        match result {
+            Ok(_) if self.redis_down.swap(false, Ordering::Relaxed) => {
+                tracing::info!("Rate limit store: Redis reachable again");
+            }
+            Err(e) if !self.redis_down.swap(true, Ordering::Relaxed) => {
+                tracing::warn!(
+                    "Rate limit store: Redis unavailable, using local buckets: {:#}",
+                    e
+                );
+            }
+            _ => {}
+        }
+    }
+
+    async fn take(&self, key: &str, kind: &str, limit: u32, amount: f64) -> Taken {
+        let (capacity, refill_rate) = (limit as f64, limit as f64 / 60.0);
+        if let Some(redis) = &self.redis {
+            let result = redis.take(key, kind, capacity, refill_rate, amount).await;
+            self.note_redis(&result);
+            if let Ok(remote) = result {
+                return Taken {
+                    admitted: remote.admitted,
+                    bucket: TokenBucket {
+                        tokens: remote.tokens,
+                        last_refill: Instant::now(),
+                        capacity: remote.capacity,
+                        refill_rate: remote.refill_rate,
+                    },
+                };
+            }
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_default()
+            .entry(kind.to_string())
+            .or_insert_with(|| TokenBucket::new(capacity, refill_rate));
+        // Update rate if config changed
+        if (bucket.capacity - capacity).abs() > 0.1 {
+            *bucket = TokenBucket::new(capacity, refill_rate);
+        }
+        Taken {
+            admitted: bucket.consume(amount),
+            bucket: *bucket,
         }
     }
 
-    pub fn check(
+    async fn peek(&self, key: &str, kind: &str) -> Option<f64> {
+        if let Some(redis) = &self.redis {
+            let result = redis.peek(key, kind).await;
+            self.note_redis(&result);
+            if let Ok(remote) = result {
+                return remote.map(|bucket| bucket.tokens);
+            }
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .get_mut(key)
+            .and_then(|b| b.get_mut(kind))
+            .map(|bucket| {
+                bucket.refill();
+                bucket.tokens
+            })
+    }
+
+    fn adjust_local(&self, key: &str, delta: f64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(bucket) = buckets.get_mut(key).and_then(|b| b.get_mut("tpm")) {
+            bucket.refill();
+            bucket.tokens = (bucket.tokens - delta).min(bucket.capacity);
+        }
+    }
+
+    /// Remember that `estimated` tokens were charged to the TPM bucket of
+    /// `key` for `request_id`, so [`Self::settle`] can correct the charge.
+    pub fn reserve(&self, request_id: &str, key: &str, estimated: u32) {
+        let mut reservations = self.reservations.lock().unwrap();
+        reservations.retain(|_, r| r.at.elapsed() < RESERVATION_TTL);
+        let reservation = reservations
+            .entry(request_id.to_string())
+            .or_insert_with(|| Reservation {
+                keys: Vec::new(),
+                estimated: estimated as f64,
+                at: Instant::now(),
+            });
+        if !reservation.keys.iter().any(|k| k == key) {
+            reservation.keys.push(key.to_string());
+        }
+    }
+
+    /// Replace the estimate charged for `request_id` with the tokens the
+    /// upstream actually reported. Overruns are taken from the bucket (which
+    /// may go into debt); overestimates are refunded up to capacity.
+    pub fn settle(self: &Arc<Self>, request_id: &str, actual: u64) {
+        let Some(reservation) = self.reservations.lock().unwrap().remove(request_id) else {
+            return;
+        };
+        let delta = actual as f64 - reservation.estimated;
+        match (&self.redis, tokio::runtime::Handle::try_current()) {
+            (Some(redis), Ok(runtime)) => {
+                let (limiter, redis) = (self.clone(), redis.clone());
+                runtime.spawn(async move {
+                    for key in &reservation.keys {
+                        let result = redis.adjust(key, "tpm", delta).await;
+                        limiter.note_redis(&result);
+                        if result.is_err() {
+                            limiter.adjust_local(key, delta);
+                        }
+                    }
+                });
+            }
+            _ => {
+                for key in &reservation.keys {
+                    self.adjust_local(key, delta);
+                }
+            }
+        }
+    }
+
+    /// Current state of every local bucket, refilled up to now.
+    pub fn snapshot(&self) -> Vec<BucketSnapshot> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let mut snapshots = Vec::new();
+        for (key, team_buckets) in buckets.iter_mut() {
+            for (kind, bucket) in team_buckets.iter_mut() {
+                bucket.refill();
+                snapshots.push(BucketSnapshot {
+                    key: key.clone(),
+                    kind: kind.clone(),
+                    tokens: bucket.tokens,
+                    capacity: bucket.capacity,
+                    refill_rate: bucket.refill_rate,
+                });
+            }
+        }
+        snapshots
+    }
+
+    /// Load buckets saved `age` ago, crediting the refill they would have
+    /// earned in the meantime. Buckets whose limits have since changed are
+    /// reset by the next `check` as usual.
+    pub fn restore(&self, snapshots: Vec<(BucketSnapshot, Duration)>) {
+        let mut buckets = self.buckets.lock().unwrap();
+        for (snapshot, age) in snapshots {
+            let tokens =
+                (snapshot.tokens + age.as_secs_f64() * snapshot.refill_rate).min(snapshot.capacity);
+            buckets.entry(snapshot.key).or_default().insert(
+                snapshot.kind,
+                TokenBucket {
+                    tokens,
+                    last_refill: Instant::now(),
+                    capacity: snapshot.capacity,
+                    refill_rate: snapshot.refill_rate,
+                },
+            );
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn check(
         &self,
         team_id: &str,
         rpm_limit: Option<u32>,
         tpm_limit: Option<u32>,
         estimated_tokens: u32,
     ) -> bool {
-        let mut buckets = self.buckets.lock().unwrap();
-        let team_buckets = buckets.entry(team_id.to_string()).or_default();
+        self.check_with_retry(team_id, rpm_limit, tpm_limit, estimated_tokens)
+            .await
+            .is_ok()
+    }
 
-        // Check RPM
-        if let Some(rpm) = rpm_limit.filter(|&r| r > 0) {
-            let bucket = team_buckets
-                .entry("rpm".to_string())
-                .or_insert_with(|| TokenBucket::new(rpm as f64, rpm as f64 / 60.0));
+    /// Whole requests and tokens left in the `rpm` / `tpm` buckets of `key`,
+    /// refilled up to now; `None` for a bucket that does not exist.
+    pub async fn remaining(&self, key: &str) -> (Option<u32>, Option<u32>) {
+        let left = |tokens: Option<f64>| tokens.map(|t| t.max(0.0).floor() as u32);
+        (
+            left(self.peek(key, "rpm").await),
+            left(self.peek(key, "tpm").await),
+        )
+    }
 
-            // Update rate if config changed
-            if (bucket.capacity - rpm as f64).abs() > 0.1 {
-                *bucket = TokenBucket::new(rpm as f64, rpm as f64 / 60.0);
-            }
+    /// Take one request from the `rpm` bucket of `key`, reporting what is
+    /// left either way.
+    pub async fn check_requests(&self, key: &str, rpm: u32) -> Result<Quota, Quota> {
+        let taken = self.take(key, "rpm", rpm, 1.0).await;
+        let quota = Quota {
+            limit: rpm,
+            remaining: taken.bucket.tokens.max(0.0).floor() as u32,
+            reset: taken.bucket.wait_for(taken.bucket.capacity),
+            retry_after: taken.bucket.wait_for(1.0),
+        };
+        if taken.admitted {
+            Ok(quota)
+        } else {
+            Err(quota)
+        }
+    }
 
-            if !bucket.consume(1.0) {
-                return false;
+    /// Like `check`, but on rejection returns how long until the exhausted
+    /// bucket would admit the request (for `Retry-After`).
+    pub async fn check_with_retry(
+        &self,
+        team_id: &str,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        estimated_tokens: u32,
+    ) -> Result<(), Duration> {
+        // Check RPM
+        if let Some(rpm) = rpm_limit.filter(|&r| r > 0) {
+            let taken = self.take(team_id, "rpm", rpm, 1.0).await;
+            if !taken.admitted {
+                return Err(taken.bucket.wait_for(1.0));
             }
         }
 
         // Check TPM (estimated)
         if let Some(tpm) = tpm_limit.filter(|&t| t > 0) {
-            let bucket = team_buckets
-                .entry("tpm".to_string())
-                .or_insert_with(|| TokenBucket::new(tpm as f64, tpm as f64 / 60.0));
-
-            if (bucket.capacity - tpm as f64).abs() > 0.1 {
-                *bucket = TokenBucket::new(tpm as f64, tpm as f64 / 60.0);
+            let amount = estimated_tokens as f64;
+            let taken = self.take(team_id, "tpm", tpm, amount).await;
+            if !taken.admitted {
+                return Err(taken.bucket.wait_for(amount));
             }
+        }
 
-            if !bucket.consume(estimated_tokens as f64) {
-                return false;
-            }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejection_reports_time_until_next_token() {
+        let limiter = TeamRateLimiter::new();
+        assert!(
+            limiter
+                .check_with_retry("t", Some(60), None, 0)
+                .await
+                .is_ok()
+        );
+        for _ in 0..59 {
+            let _ = limiter.check("t", Some(60), None, 0).await;
         }
+        let wait = limiter
+            .check_with_retry("t", Some(60), None, 0)
+            .await
+            .unwrap_err();
+        assert!(
+            wait > Duration::ZERO && wait <= Duration::from_secs(1),
+            "{wait:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn request_quota_counts_down_and_reports_reset() {
+        let limiter = TeamRateLimiter::new();
+        let first = limiter.check_requests("g", 2).await.unwrap();
+        assert_eq!((first.limit, first.remaining), (2, 1));
+        assert_eq!(first.retry_after, Duration::ZERO);
+        assert_eq!(limiter.check_requests("g", 2).await.unwrap().remaining, 0);
+        let denied = limiter.check_requests("g", 2).await.unwrap_err();
+        assert_eq!(denied.remaining, 0);
+        assert!(denied.retry_after > Duration::from_secs(25), "{denied:?}");
+        assert!(denied.reset >= denied.retry_after);
+    }
+
+    #[tokio::test]
+    async fn remaining_reports_each_configured_bucket() {
+        let limiter = TeamRateLimiter::new();
+        assert_eq!(limiter.remaining("t").await, (None, None));
+        assert!(limiter.check("t", Some(10), Some(1000), 400).await);
+        assert_eq!(limiter.remaining("t").await, (Some(9), Some(600)));
+        assert!(limiter.check("rpm-only", Some(10), None, 400).await);
+        assert_eq!(limiter.remaining("rpm-only").await, (Some(9), None));
+    }
+
+    #[tokio::test]
+    async fn settle_replaces_the_estimate_with_actual_usage() {
+        let limiter = Arc::new(TeamRateLimiter::new());
+        assert!(limiter.check("t", None, Some(1000), 600).await);
+        limiter.reserve("req-1", "t", 600);
+        // The estimate leaves too little room for another large request...
+        assert!(!limiter.check("t", None, Some(1000), 600).await);
+        // ...until the request turns out to have used far less.
+        limiter.settle("req-1", 50);
+        assert!(limiter.check("t", None, Some(1000), 600).await);
+
+        assert!(limiter.check("u", None, Some(1000), 10).await);
+        limiter.reserve("req-2", "u", 10);
+        limiter.settle("req-2", 2000);
+        let wait = limiter
+            .check_with_retry("u", None, Some(1000), 1)
+            .await
+            .unwrap_err();
+        assert!(wait > Duration::from_secs(30), "{wait:?}");
+        // Settling twice is a no-op.
+        limiter.settle("req-2", 0);
+        assert!(!limiter.check("u", None, Some(1000), 1).await);
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_falls_back_to_local_buckets() {
+        let store = RateLimitStore::Redis {
+            url: "redis://127.0.0.1:1/".to_string(),
+            key_prefix: "apex:rl:".to_string(),
+        };
+        let limiter = TeamRateLimiter::from_config(Some(&store)).unwrap();
+        assert!(limiter.is_shared());
+        assert!(limiter.check("t", Some(1), None, 0).await);
+        assert!(!limiter.check("t", Some(1), None, 0).await);
+        assert_eq!(limiter.remaining("t").await, (Some(0), None));
 
-        true
+        let bad = RateLimitStore::Redis {
+            url: "not a url".to_string(),
+            key_prefix: String::new(),
+        };
+        assert!(TeamRateLimiter::from_config(Some(&bad)).is_err());
     }
 }
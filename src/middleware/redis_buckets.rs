@@ -0,0 +1,179 @@
+//! Token buckets kept in Redis (`global.rate_limit_store`), so every apex
+//! instance behind a load balancer draws from the same budget.
+//!
+//! Each bucket is a hash (`tokens`, `ts`, `capacity`, `rate`) updated by one
+//! Lua script, which refills from the Redis server clock so instances with
+//! skewed clocks agree. Keys expire once a bucket would be full again.
+
+use redis::aio::MultiplexedConnection;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Longest a bucket operation may take before the caller falls back to its
+/// local buckets.
+const OPERATION_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long to stop trying Redis after it failed.
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(5);
+
+const BUCKET_SCRIPT: &str = r#"
+if redis.replicate_commands then redis.replicate_commands() end
+local mode = ARGV[1]
+local now_parts = redis.call('TIME')
+local now = tonumber(now_parts[1]) * 1000 + math.floor(tonumber(now_parts[2]) / 1000)
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts', 'capacity', 'rate')
+local tokens, ts = tonumber(state[1]), tonumber(state[2])
+local capacity, rate = tonumber(state[3]), tonumber(state[4])
+if mode == 'take' then
+  local want_capacity, want_rate = tonumber(ARGV[2]), tonumber(ARGV[3])
+  if tokens == nil or math.abs(capacity - want_capacity) > 0.1 then
+    tokens, ts, capacity, rate = want_capacity, now, want_capacity, want_rate
+  end
+elseif tokens == nil then
+  return false
+end
+tokens = math.min(capacity, tokens + math.max(0, now - ts) / 1000 * rate)
+local admitted = 0
+if mode == 'take' then
+  local amount = tonumber(ARGV[4])
+  if tokens >= amount then
+    tokens = tokens - amount
+    admitted = 1
+  end
+elseif mode == 'adjust' then
+  tokens = math.min(capacity, tokens - tonumber(ARGV[2]))
+end
+if mode ~= 'peek' then
+  redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now,
+    'capacity', tostring(capacity), 'rate', tostring(rate))
+  local refill_ms = (capacity - math.min(tokens, 0)) / math.max(rate, 0.000001) * 1000
+  redis.call('PEXPIRE', KEYS[1], math.ceil(refill_ms) + 60000)
+end
+return {admitted, tostring(tokens), tostring(capacity), tostring(rate)}
+"#;
+
+/// A bucket as the script left it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemoteBucket {
+    pub admitted: bool,
+    pub tokens: f64,
+    pub capacity: f64,
+    pub refill_rate: f64,
+}
+
+pub struct RedisBuckets {
+    client: redis::Client,
+    key_prefix: String,
+    script: redis::Script,
+    connection: Mutex<Option<MultiplexedConnection>>,
+    /// Set after a failure; Redis is skipped until then.
+    backoff_until: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RedisBuckets {
+    /// Parses `url`; the connection is made on first use.
+    pub fn new(url: &str, key_prefix: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            key_prefix: key_prefix.to_string(),
+            script: redis::Script::new(BUCKET_SCRIPT),
+            connection: Mutex::new(None),
+            backoff_until: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// Take `amount` from the `kind` bucket of `key`, creating (or resetting,
+    /// when the limit changed) it with `capacity` and `refill_rate`.
+    pub async fn take(
+        &self,
+        key: &str,
+        kind: &str,
+        capacity: f64,
+        refill_rate: f64,
+        amount: f64,
+    ) -> anyhow::Result<RemoteBucket> {
+        let bucket = self
+            .run(
+                key,
+                kind,
+                &[
+                    "take".to_string(),
+                    capacity.to_string(),
+                    refill_rate.to_string(),
+                    amount.to_string(),
+                ],
+            )
+            .await?;
+        bucket.ok_or_else(|| anyhow::anyhow!("bucket script returned nothing"))
+    }
+
+    /// Remove `delta` tokens (or refund, when negative) from an existing bucket.
+    pub async fn adjust(&self, key: &str, kind: &str, delta: f64) -> anyhow::Result<()> {
+        self.run(key, kind, &["adjust".to_string(), delta.to_string()])
+            .await
+            .map(|_| ())
+    }
+
+    /// The bucket refilled up to now, or `None` when it does not exist.
+    pub async fn peek(&self, key: &str, kind: &str) -> anyhow::Result<Option<RemoteBucket>> {
+        self.run(key, kind, &["peek".to_string()]).await
+    }
+
+    async fn run(
+        &self,
+        key: &str,
+        kind: &str,
+        args: &[String],
+    ) -> anyhow::Result<Option<RemoteBucket>> {
+        if self
+            .backoff_until
+            .lock()
+            .unwrap()
+            .is_some_and(|until| Instant::now() < until)
+        {
+            anyhow::bail!("redis unavailable, retrying shortly");
+        }
+        let result = tokio::time::timeout(OPERATION_TIMEOUT, self.invoke(key, kind, args))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("redis timed out")));
+        if result.is_err() {
+            *self.connection.lock().await = None;
+            *self.backoff_until.lock().unwrap() = Some(Instant::now() + RETRY_AFTER_FAILURE);
+        }
+        result
+    }
+
+    async fn invoke(
+        &self,
+        key: &str,
+        kind: &str,
+        args: &[String],
+    ) -> anyhow::Result<Option<RemoteBucket>> {
+        let mut connection = {
+            let mut cached = self.connection.lock().await;
+            match cached.as_ref() {
+                Some(connection) => connection.clone(),
+                None => {
+                    let connection = self.client.get_multiplexed_async_connection().await?;
+                    *cached = Some(connection.clone());
+                    connection
+                }
+            }
+        };
+        let mut invocation = self
+            .script
+            .key(format!("{}{}:{}", self.key_prefix, key, kind));
+        for arg in args {
+            invocation.arg(arg);
+        }
+        let reply: Option<(i64, String, String, String)> =
+            invocation.invoke_async(&mut connection).await?;
+        Ok(
+            reply.map(|(admitted, tokens, capacity, rate)| RemoteBucket {
+                admitted: admitted == 1,
+                tokens: tokens.parse().unwrap_or_default(),
+                capacity: capacity.parse().unwrap_or_default(),
+                refill_rate: rate.parse().unwrap_or_default(),
+            }),
+        )
+    }
+}
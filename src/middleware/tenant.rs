@@ -0,0 +1,81 @@
+use crate::server::AppState;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Tenant the request is addressed to (see `config::Tenant`).
+#[derive(Clone)]
+pub struct TenantContext {
+    pub tenant_id: String,
+}
+
+/// Tenant id taken from a `/t/<tenant>/...` path before routing.
+#[derive(Clone)]
+struct TenantPath(String);
+
+/// Strip a `/t/<tenant>` prefix so the rest of the path routes normally.
+/// Runs in front of the router; `tenant_scope` validates the id later.
+pub fn strip_tenant_prefix(mut req: Request) -> Request {
+    let Some(rest) = req.uri().path().strip_prefix("/t/") else {
+        return req;
+    };
+    let Some((tenant, path)) = rest.split_once('/') else {
+        return req;
+    };
+    let tenant = tenant.to_string();
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("/{path}?{query}"),
+        None => format!("/{path}"),
+    };
+    let mut parts = req.uri().clone().into_parts();
+    let Ok(path_and_query) = path_and_query.parse() else {
+        return req;
+    };
+    parts.path_and_query = Some(path_and_query);
+    if let Ok(uri) = axum::http::Uri::from_parts(parts) {
+        *req.uri_mut() = uri;
+        req.extensions_mut().insert(TenantPath(tenant));
+    }
+    req
+}
+
+/// Resolve the tenant from the path prefix or the `Host` header and attach a
+/// `TenantContext`. Must run before `team_auth`, which uses it to keep each
+/// tenant's keys inside its own namespace.
+pub async fn tenant_scope(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let tenant_id = {
+        let config = state.config.read().unwrap();
+        if let Some(TenantPath(id)) = req.extensions().get::<TenantPath>() {
+            if !config.tenants.iter().any(|t| &t.id == id) {
+                tracing::warn!("Tenant Resolution Failed: unknown tenant '{}'", id);
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error": "Unknown tenant"}"#))
+                    .unwrap();
+            }
+            Some(id.clone())
+        } else {
+            req.headers()
+                .get(axum::http::header::HOST)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|host| config.tenant_for_host(host))
+                .map(|t| t.id.clone())
+        }
+    };
+
+    if let Some(tenant_id) = tenant_id {
+        tracing::debug!("Tenant Resolved: {}", tenant_id);
+        req.extensions_mut().insert(TenantContext { tenant_id });
+    }
+    next.run(req).await
+}
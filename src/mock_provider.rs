@@ -0,0 +1,275 @@
+//! In-process upstream for `provider_type: "mock"` channels.
+//!
+//! Requests are answered without touching the network: chat completions
+//! (plain and streaming), embeddings and model listing are synthesized from
+//! the request body, so integration tests and load runs don't need a real
+//! provider or a hand-rolled listener.
+
+use crate::config::{Channel, MockSettings};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const DEFAULT_CONTENT: &str = "This is a mock response.";
+const DEFAULT_EMBEDDING_DIMENSIONS: usize = 8;
+
+/// Answer an upstream request for a mock channel.
+pub async fn respond(channel: &Channel, request: reqwest::Request) -> reqwest::Response {
+    let settings = channel.mock.clone().unwrap_or_default();
+    if settings.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(settings.latency_ms)).await;
+    }
+
+    if settings.failure_rate > 0.0 && rand::random::<f64>() < settings.failure_rate {
+        let status =
+            StatusCode::from_u16(settings.failure_status).unwrap_or(StatusCode::BAD_GATEWAY);
+        return json_response(
+            status,
+            json!({"error": {"message": "mock upstream failure", "type": "mock_error"}}),
+        );
+    }
+
+    let body = request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .and_then(|b| serde_json::from_slice::<Value>(b).ok())
+        .unwrap_or(Value::Null);
+    let path = request.url().path().trim_end_matches('/');
+
+    if path.ends_with("chat/completions") {
+        chat_completion(&settings, &body)
+    } else if path.ends_with("embeddings") {
+        json_response(StatusCode::OK, embeddings(&body))
+    } else if path.ends_with("models") {
+        json_response(StatusCode::OK, models(channel))
+    } else {
+        json_response(
+            StatusCode::NOT_FOUND,
+            json!({"error": {"message": format!("mock provider has no route for '{path}'"), "type": "not_found"}}),
+        )
+    }
+}
+
+fn chat_completion(settings: &MockSettings, body: &Value) -> reqwest::Response {
+    let model = body.get("model").and_then(Value::as_str).unwrap_or("mock");
+    let content = settings
+        .content
+        .clone()
+        .or_else(|| last_user_text(body).map(|text| format!("Echo: {text}")))
+        .unwrap_or_else(|| DEFAULT_CONTENT.to_string());
+    let prompt_tokens = count_tokens(&prompt_text(body));
+    let completion_tokens = count_tokens(&content);
+    let usage = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    });
+
+    let stream = body.get("stream").and_then(Value::as_bool).unwrap_or(false);
+    if !stream {
+        return json_response(
+            StatusCode::OK,
+            json!({
+                "id": "chatcmpl-mock",
+                "object": "chat.completion",
+                "created": 0,
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": content},
+                    "finish_reason": "stop",
+                }],
+                "usage": usage,
+            }),
+        );
+    }
+
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        })
+    };
+    let mut events = vec![chunk(
+        json!({"role": "assistant", "content": ""}),
+        Value::Null,
+    )];
+    let words: Vec<&str> = content.split_inclusive(' ').collect();
+    for word in words {
+        events.push(chunk(json!({"content": word}), Value::Null));
+    }
+    events.push(chunk(json!({}), json!("stop")));
+    events.push(json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": model,
+        "choices": [],
+        "usage": usage,
+    }));
+
+    let mut sse: Vec<String> = events
+        .iter()
+        .map(|event| format!("data: {event}\n\n"))
+        .collect();
+    sse.push("data: [DONE]\n\n".to_string());
+    let stream = futures::stream::iter(sse.into_iter().map(Ok::<_, std::io::Error>));
+
+    let response = axum::http::Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .body(reqwest::Body::wrap_stream(stream))
+        .unwrap();
+    reqwest::Response::from(response)
+}
+
+fn embeddings(body: &Value) -> Value {
+    let model = body.get("model").and_then(Value::as_str).unwrap_or("mock");
+    let inputs: Vec<String> = match body.get("input") {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map_or_else(|| item.to_string(), str::to_string)
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let dimensions = body
+        .get("dimensions")
+        .and_then(Value::as_u64)
+        .map(|d| d as usize)
+        .filter(|&d| d > 0)
+        .unwrap_or(DEFAULT_EMBEDDING_DIMENSIONS);
+    let prompt_tokens: u64 = inputs.iter().map(|text| count_tokens(text)).sum();
+
+    let data: Vec<Value> = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, text)| {
+            json!({
+                "object": "embedding",
+                "index": index,
+                "embedding": embedding_vector(text, dimensions),
+            })
+        })
+        .collect();
+
+    json!({
+        "object": "list",
+        "data": data,
+        "model": model,
+        "usage": {"prompt_tokens": prompt_tokens, "total_tokens": prompt_tokens},
+    })
+}
+
+fn models(channel: &Channel) -> Value {
+    let mut ids: Vec<String> = channel
+        .model_map
+        .as_ref()
+        .map(|map| map.keys().cloned().collect())
+        .unwrap_or_default();
+    ids.sort();
+    if ids.is_empty() {
+        ids.push("mock".to_string());
+    }
+    let data: Vec<Value> = ids
+        .into_iter()
+        .map(|id| json!({"id": id, "object": "model", "created": 0, "owned_by": "apex-mock"}))
+        .collect();
+    json!({"object": "list", "data": data})
+}
+
+/// Deterministic unit-range vector derived from an FNV-1a hash of the text.
+fn embedding_vector(text: &str, dimensions: usize) -> Vec<f64> {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (0..dimensions)
+        .map(|_| {
+            hash ^= hash << 13;
+            hash ^= hash >> 7;
+            hash ^= hash << 17;
+            (hash % 2001) as f64 / 1000.0 - 1.0
+        })
+        .collect()
+}
+
+fn message_text(message: &Value) -> String {
+    match message.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+fn last_user_text(body: &Value) -> Option<String> {
+    body.get("messages")?
+        .as_array()?
+        .iter()
+        .rev()
+        .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))
+        .map(message_text)
+        .filter(|text| !text.is_empty())
+}
+
+fn prompt_text(body: &Value) -> String {
+    body.get("messages")
+        .and_then(Value::as_array)
+        .map(|messages| {
+            messages
+                .iter()
+                .map(message_text)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+fn count_tokens(text: &str) -> u64 {
+    text.split_whitespace().count() as u64
+}
+
+fn json_response(status: StatusCode, body: Value) -> reqwest::Response {
+    let response = axum::http::Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(reqwest::Body::from(body.to_string()))
+        .unwrap();
+    reqwest::Response::from(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeddings_are_deterministic() {
+        let body = json!({"model": "m", "input": ["a", "b"], "dimensions": 4});
+        let first = embeddings(&body);
+        assert_eq!(first, embeddings(&body));
+        assert_eq!(first["data"][0]["embedding"].as_array().unwrap().len(), 4);
+        assert_ne!(first["data"][0]["embedding"], first["data"][1]["embedding"]);
+    }
+
+    #[test]
+    fn echoes_last_user_message() {
+        let body = json!({"messages": [
+            {"role": "user", "content": "first"},
+            {"role": "assistant", "content": "ok"},
+            {"role": "user", "content": [{"type": "text", "text": "second"}]},
+        ]});
+        assert_eq!(last_user_text(&body).as_deref(), Some("second"));
+        assert_eq!(count_tokens(&prompt_text(&body)), 3);
+    }
+}
@@ -0,0 +1,216 @@
+//! Models reported by the channels themselves, for `GET /v1/models`.
+//!
+//! Each channel is asked for `GET /v1/models` with its own URL and
+//! credentials, the same way a passthrough request would be sent. The ids
+//! are mapped back through the channel's `model_map` (an upstream id becomes
+//! the client-facing name(s) mapped to it) and cached per channel for
+//! `model_discovery.ttl_secs`. A channel that cannot be listed contributes
+//! nothing and is asked again after at most `FAILURE_TTL`.
+
+use crate::config::{Channel, ModelDiscoveryConfig, ProviderType};
+use crate::providers::{ProviderRegistry, prepare_passthrough_request};
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Longest a failed listing is remembered.
+const FAILURE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct ModelCatalog {
+    /// Keyed by channel name and base URL, so an edited channel is refetched.
+    entries: RwLock<HashMap<(String, String), Entry>>,
+}
+
+struct Entry {
+    fetched_at: Instant,
+    ttl: Duration,
+    models: Arc<Vec<String>>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Client-facing model ids of `channel`, from the cache when fresh.
+    pub async fn channel_models(
+        &self,
+        registry: &ProviderRegistry,
+        client: &reqwest::Client,
+        channel: &Channel,
+        settings: &ModelDiscoveryConfig,
+    ) -> Arc<Vec<String>> {
+        let key = (channel.name.clone(), channel.base_url.clone());
+        if let Some(entry) = self.entries.read().unwrap().get(&key)
+            && entry.fetched_at.elapsed() < entry.ttl
+        {
+            return entry.models.clone();
+        }
+
+        let ttl = Duration::from_secs(settings.ttl_secs);
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        let (models, ttl) =
+            match tokio::time::timeout(timeout, fetch(registry, client, channel)).await {
+                Ok(Ok(ids)) => (client_ids(channel, ids), ttl),
+                Ok(Err(e)) => {
+                    warn!("Model Listing Failed: channel={} {:#}", channel.name, e);
+                    (Vec::new(), ttl.min(FAILURE_TTL))
+                }
+                Err(_) => {
+                    warn!("Model Listing Failed: channel={} timed out", channel.name);
+                    (Vec::new(), ttl.min(FAILURE_TTL))
+                }
+            };
+        let models = Arc::new(models);
+        self.entries.write().unwrap().insert(
+            key,
+            Entry {
+                fetched_at: Instant::now(),
+                ttl,
+                models: models.clone(),
+            },
+        );
+        models
+    }
+}
+
+async fn fetch(
+    registry: &ProviderRegistry,
+    client: &reqwest::Client,
+    channel: &Channel,
+) -> anyhow::Result<Vec<String>> {
+    let prepared = prepare_passthrough_request(
+        registry,
+        channel,
+        "/v1/models",
+        None,
+        &HeaderMap::new(),
+        &Bytes::new(),
+    )?;
+    let request = client.get(prepared.url).headers(prepared.headers).build()?;
+    let response = if channel.provider_type == ProviderType::Mock {
+        crate::mock_provider::respond(channel, request).await
+    } else {
+        client.execute(request).await?
+    };
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("upstream returned {status}");
+    }
+    Ok(model_ids(&response.json::<Value>().await?))
+}
+
+/// Ids in an OpenAI / Anthropic (`data[].id`) or Gemini / Ollama
+/// (`models[].name`) model list.
+fn model_ids(body: &Value) -> Vec<String> {
+    let from = |list: &Value, field: &str| -> Vec<String> {
+        list.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item[field].as_str())
+            .map(|id| id.strip_prefix("models/").unwrap_or(id).to_string())
+            .collect()
+    };
+    let ids = from(&body["data"], "id");
+    if ids.is_empty() {
+        from(&body["models"], "name")
+    } else {
+        ids
+    }
+}
+
+/// Map upstream ids back through `model_map`: an id that is the target of
+/// one or more entries is listed under those names instead.
+fn client_ids(channel: &Channel, upstream: Vec<String>) -> Vec<String> {
+    let Some(map) = channel.model_map.as_ref().filter(|map| !map.is_empty()) else {
+        return upstream;
+    };
+    let mut ids = Vec::with_capacity(upstream.len());
+    for id in upstream {
+        let mut aliases: Vec<&String> = map
+            .iter()
+            .filter(|(_, target)| **target == id)
+            .map(|(alias, _)| alias)
+            .collect();
+        if aliases.is_empty() {
+            ids.push(id);
+        } else {
+            aliases.sort();
+            ids.extend(aliases.into_iter().cloned());
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn channel(model_map: Option<HashMap<String, String>>) -> Channel {
+        serde_json::from_value(json!({
+            "name": "c",
+            "provider_type": "openai",
+            "base_url": "http://127.0.0.1:1",
+            "api_key": "sk",
+            "model_map": model_map
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_openai_and_gemini_lists() {
+        let openai = json!({"object": "list", "data": [{"id": "gpt-4o"}, {"id": "gpt-4o-mini"}]});
+        assert_eq!(model_ids(&openai), ["gpt-4o", "gpt-4o-mini"]);
+        let gemini = json!({"models": [{"name": "models/gemini-2.0-flash"}]});
+        assert_eq!(model_ids(&gemini), ["gemini-2.0-flash"]);
+        assert!(model_ids(&json!({"error": "nope"})).is_empty());
+    }
+
+    #[test]
+    fn maps_upstream_ids_back_to_aliases() {
+        let map = HashMap::from([
+            ("fast".to_string(), "gpt-4o-mini".to_string()),
+            ("cheap".to_string(), "gpt-4o-mini".to_string()),
+        ]);
+        let ids = client_ids(
+            &channel(Some(map)),
+            vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
+        );
+        assert_eq!(ids, ["gpt-4o", "cheap", "fast"]);
+    }
+
+    #[tokio::test]
+    async fn caches_listings_and_failures() {
+        let catalog = ModelCatalog::new();
+        let registry = ProviderRegistry::new();
+        let client = reqwest::Client::new();
+        let settings = ModelDiscoveryConfig::default();
+
+        let mut mock = channel(Some(HashMap::from([(
+            "alias".to_string(),
+            "upstream".to_string(),
+        )])));
+        mock.name = "mock".to_string();
+        mock.provider_type = ProviderType::Mock;
+        let models = catalog
+            .channel_models(&registry, &client, &mock, &settings)
+            .await;
+        // The mock lists its model_map keys; "alias" is not a mapped target.
+        assert_eq!(*models, ["alias"]);
+
+        let unreachable = channel(None);
+        let models = catalog
+            .channel_models(&registry, &client, &unreachable, &settings)
+            .await;
+        assert!(models.is_empty());
+        let entries = catalog.entries.read().unwrap();
+        let failed = &entries[&("c".to_string(), "http://127.0.0.1:1".to_string())];
+        assert_eq!(failed.ttl, FAILURE_TTL);
+    }
+}
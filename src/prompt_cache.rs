@@ -0,0 +1,220 @@
+//! Per-channel prompt caching controls (`channel.prompt_cache`).
+//!
+//! Runs on the prepared upstream request. `strip` removes every
+//! `cache_control` marker (and prompt caching `anthropic-beta` values) from
+//! Messages and chat completions bodies, for upstreams that reject them.
+//! `inject` marks the end of the system prompt and of the tool list on
+//! Messages requests that carry no marker of their own, so stable prefixes
+//! are cached without client changes. `beta` values are added to the
+//! `anthropic-beta` header of Messages requests.
+
+use crate::config::{PromptCache, PromptCacheMode};
+use crate::providers::PreparedRequest;
+use axum::body::Bytes;
+use axum::http::HeaderValue;
+use serde_json::{Value, json};
+
+const BETA_HEADER: &str = "anthropic-beta";
+
+pub fn apply(settings: &PromptCache, prepared: &mut PreparedRequest) {
+    let path = prepared.url.path();
+    let messages = path.ends_with("/messages") || path.ends_with("/messages/count_tokens");
+    if !messages && !path.ends_with("/chat/completions") {
+        return;
+    }
+    match settings.mode {
+        PromptCacheMode::Preserve => {}
+        PromptCacheMode::Strip => {
+            prepared.body = rewrite(&prepared.body, strip_markers);
+            set_betas(prepared, |beta| !is_caching_beta(beta), &[]);
+        }
+        PromptCacheMode::Inject if messages => {
+            prepared.body = rewrite(&prepared.body, inject_markers);
+        }
+        PromptCacheMode::Inject => {}
+    }
+    if messages && !settings.beta.is_empty() {
+        set_betas(prepared, |_| true, &settings.beta);
+    }
+}
+
+fn rewrite(body: &Bytes, edit: fn(&mut Value) -> bool) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<Value>(body) else {
+        return body.clone();
+    };
+    if !edit(&mut value) {
+        return body.clone();
+    }
+    serde_json::to_vec(&value)
+        .map(Bytes::from)
+        .unwrap_or_else(|_| body.clone())
+}
+
+/// Remove `cache_control` at any depth; reports whether anything changed.
+fn strip_markers(value: &mut Value) -> bool {
+    match value {
+        Value::Object(map) => {
+            let removed = map.remove("cache_control").is_some();
+            // Count rather than `any`, which would stop at the first change.
+            map.values_mut().map(strip_markers).filter(|c| *c).count() > 0 || removed
+        }
+        Value::Array(items) => items.iter_mut().map(strip_markers).filter(|c| *c).count() > 0,
+        _ => false,
+    }
+}
+
+fn has_marker(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.contains_key("cache_control") || map.values().any(has_marker),
+        Value::Array(items) => items.iter().any(has_marker),
+        _ => false,
+    }
+}
+
+/// Mark the last system block and the last tool, unless the client already
+/// placed its own breakpoints.
+fn inject_markers(value: &mut Value) -> bool {
+    if has_marker(value) {
+        return false;
+    }
+    let marker = json!({"type": "ephemeral"});
+    let mut changed = false;
+    if let Some(system) = value.get_mut("system") {
+        if let Some(text) = system.as_str().filter(|text| !text.is_empty()) {
+            *system = json!([{"type": "text", "text": text}]);
+        }
+        if let Some(block) = system.as_array_mut().and_then(|blocks| blocks.last_mut()) {
+            block["cache_control"] = marker.clone();
+            changed = true;
+        }
+    }
+    if let Some(tool) = value
+        .get_mut("tools")
+        .and_then(Value::as_array_mut)
+        .and_then(|tools| tools.last_mut())
+    {
+        tool["cache_control"] = marker;
+        changed = true;
+    }
+    changed
+}
+
+fn is_caching_beta(beta: &str) -> bool {
+    beta.starts_with("prompt-caching-") || beta.starts_with("extended-cache-ttl-")
+}
+
+/// Rewrite `anthropic-beta` as the kept values plus `extra`, without repeats.
+fn set_betas(prepared: &mut PreparedRequest, keep: impl Fn(&str) -> bool, extra: &[String]) {
+    let mut betas: Vec<String> = prepared
+        .headers
+        .get_all(BETA_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|beta| !beta.is_empty() && keep(beta))
+        .map(str::to_string)
+        .collect();
+    for beta in extra {
+        if !betas.contains(beta) {
+            betas.push(beta.clone());
+        }
+    }
+    prepared.headers.remove(BETA_HEADER);
+    if let Ok(value) = HeaderValue::from_str(&betas.join(","))
+        && !betas.is_empty()
+    {
+        prepared.headers.insert(BETA_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    fn prepared(path: &str, body: Value, beta: Option<&str>) -> PreparedRequest {
+        let mut headers = HeaderMap::new();
+        if let Some(beta) = beta {
+            headers.insert(BETA_HEADER, HeaderValue::from_str(beta).unwrap());
+        }
+        PreparedRequest {
+            url: format!("https://api.anthropic.com{path}").parse().unwrap(),
+            body: Bytes::from(body.to_string()),
+            headers,
+        }
+    }
+
+    fn body(prepared: &PreparedRequest) -> Value {
+        serde_json::from_slice(&prepared.body).unwrap()
+    }
+
+    #[test]
+    fn strip_removes_markers_and_caching_betas() {
+        let mut request = prepared(
+            "/v1/messages",
+            json!({
+                "system": [{"type": "text", "text": "s", "cache_control": {"type": "ephemeral"}}],
+                "messages": [{"role": "user", "content": [
+                    {"type": "text", "text": "hi", "cache_control": {"type": "ephemeral", "ttl": "1h"}}
+                ]}]
+            }),
+            Some("prompt-caching-2024-07-31, extended-cache-ttl-2025-04-11,files-api-2025-04-14"),
+        );
+        let settings = PromptCache {
+            mode: PromptCacheMode::Strip,
+            beta: vec![],
+        };
+        apply(&settings, &mut request);
+
+        assert!(!has_marker(&body(&request)));
+        assert_eq!(request.headers[BETA_HEADER], "files-api-2025-04-14");
+    }
+
+    #[test]
+    fn inject_marks_system_and_tools_and_adds_betas() {
+        let settings = PromptCache {
+            mode: PromptCacheMode::Inject,
+            beta: vec!["extended-cache-ttl-2025-04-11".to_string()],
+        };
+        let mut request = prepared(
+            "/v1/messages",
+            json!({
+                "system": "be brief",
+                "tools": [{"name": "a"}, {"name": "b"}],
+                "messages": [{"role": "user", "content": "hi"}]
+            }),
+            Some("files-api-2025-04-14"),
+        );
+        apply(&settings, &mut request);
+
+        let value = body(&request);
+        assert_eq!(
+            value["system"],
+            json!([{"type": "text", "text": "be brief", "cache_control": {"type": "ephemeral"}}])
+        );
+        assert!(value["tools"][0].get("cache_control").is_none());
+        assert_eq!(value["tools"][1]["cache_control"]["type"], "ephemeral");
+        assert_eq!(
+            request.headers[BETA_HEADER],
+            "files-api-2025-04-14,extended-cache-ttl-2025-04-11"
+        );
+
+        // Client breakpoints win; chat completions bodies are left alone.
+        let own = json!({
+            "system": [{"type": "text", "text": "s"}],
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "hi", "cache_control": {"type": "ephemeral"}}
+            ]}]
+        });
+        let mut request = prepared("/v1/messages", own.clone(), None);
+        apply(&settings, &mut request);
+        assert_eq!(body(&request), own);
+
+        let chat = json!({"messages": [{"role": "system", "content": "s"}]});
+        let mut request = prepared("/v1/chat/completions", chat.clone(), None);
+        apply(&settings, &mut request);
+        assert_eq!(body(&request), chat);
+        assert!(request.headers.get(BETA_HEADER).is_none());
+    }
+}
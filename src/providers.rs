@@ -1,8 +1,12 @@
-use crate::config::{Channel, ProviderType};
+use crate::config::{Channel, ChannelQuirks, ParamProfile, ProviderType};
 use crate::converters::{
+    convert_anthropic_response_to_openai, convert_anthropic_stream_to_openai,
     convert_anthropic_to_openai, convert_openai_response_to_anthropic,
-    convert_openai_stream_to_anthropic,
+    convert_openai_stream_to_anthropic, convert_openai_to_anthropic,
 };
+use crate::embeddings::{self, EmbeddingsAdapter};
+use crate::gemini_native;
+use crate::vertex;
 use axum::body::{Body, Bytes};
 use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
 use axum::response::Response;
@@ -25,6 +29,27 @@ pub enum RouteKind {
     GeminiNative,
 }
 
+impl RouteKind {
+    /// Protocol a client speaks on a gateway path (`/v1/messages` etc.).
+    pub fn from_path(path: &str) -> Self {
+        if path.starts_with("/gemini/") {
+            RouteKind::GeminiNative
+        } else if path.starts_with("/v1/messages") || path.starts_with("/messages") {
+            RouteKind::Anthropic
+        } else {
+            RouteKind::Openai
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteKind::Openai => "openai",
+            RouteKind::Anthropic => "anthropic",
+            RouteKind::GeminiNative => "gemini_native",
+        }
+    }
+}
+
 /// Represents a request prepared for sending to the upstream provider.
 pub struct PreparedRequest {
     pub url: Url,
@@ -32,10 +57,30 @@ pub struct PreparedRequest {
     pub headers: HeaderMap,
 }
 
+/// One upstream access attempt, with the caller it was made for.
+#[derive(Clone, Copy, Debug)]
+pub struct AuditEvent<'a> {
+    pub request_id: Option<&'a str>,
+    /// Team id, or `"global"` for requests without a team key.
+    pub team_id: &'a str,
+    /// Masked caller key (never the raw secret).
+    pub api_key: Option<&'a str>,
+    pub provider: &'a ProviderType,
+    pub channel: &'a str,
+    pub route: RouteKind,
+    pub success: bool,
+}
+
 /// Trait for auditing access to providers.
 pub trait AccessAudit: Send + Sync {
     /// Records an access attempt.
     fn audit(&self, provider: &ProviderType, route: RouteKind, success: bool);
+
+    /// Records an access attempt with caller context. Implementations that
+    /// persist events override this; the default keeps `audit` working.
+    fn record(&self, event: &AuditEvent<'_>) {
+        self.audit(event.provider, event.route, event.success);
+    }
 }
 
 /// A no-op implementation of AccessAudit.
@@ -86,6 +131,13 @@ pub trait ProviderAdapter: Send + Sync {
         model_map: &Option<HashMap<String, String>>,
     ) -> Bytes;
 
+    /// Whether `transform_body` hands `route`'s bodies back unchanged when
+    /// the channel's `model_map` has no entry for the request's model, so
+    /// they can be streamed to the upstream without being read.
+    fn forwards_body(&self, _route: RouteKind) -> bool {
+        false
+    }
+
     /// Applies authentication headers (e.g., Bearer token, x-api-key).
     fn apply_auth_headers(
         &self,
@@ -111,7 +163,10 @@ pub trait ProviderAdapter: Send + Sync {
 pub struct ProviderRegistry {
     adapters: HashMap<ProviderType, Box<dyn ProviderAdapter>>,
     gemini_native: Box<dyn ProviderAdapter>,
+    anthropic_messages: Box<dyn ProviderAdapter>,
     fallback: Box<dyn ProviderAdapter>,
+    /// Adapters registered by name, selected with a channel's `adapter`.
+    plugins: HashMap<String, Box<dyn ProviderAdapter>>,
 }
 
 impl Default for ProviderRegistry {
@@ -136,12 +191,56 @@ impl ProviderRegistry {
         adapters.insert(ProviderType::Jina, Box::new(DefaultAdapter));
         adapters.insert(ProviderType::Openrouter, Box::new(OpenRouterAdapter));
         adapters.insert(ProviderType::Zai, Box::new(CustomDualAdapter));
+        adapters.insert(ProviderType::Groq, Box::new(DefaultAdapter));
+        adapters.insert(ProviderType::Mistral, Box::new(MistralAdapter));
+        adapters.insert(ProviderType::Cohere, Box::new(DefaultAdapter));
+        adapters.insert(ProviderType::Together, Box::new(DefaultAdapter));
+        adapters.insert(ProviderType::Custom, Box::new(CustomAdapter));
+        adapters.insert(ProviderType::Vertex, Box::new(VertexAdapter));
+        // Mock speaks OpenAI; Anthropic callers get the usual conversion.
+        adapters.insert(ProviderType::Mock, Box::new(OpenAiAdapter));
 
         Self {
             adapters,
             gemini_native: Box::new(GeminiNativeAdapter),
+            anthropic_messages: Box::new(AnthropicMessagesAdapter),
             fallback: Box::new(DefaultAdapter),
+            plugins: HashMap::new(),
+        }
+    }
+
+    /// Registers `adapter` under `name` for channels whose `adapter` names
+    /// it. Meant for programs that embed apex and bring their own providers;
+    /// such channels skip every built-in request preparation and go through
+    /// `adapter` alone.
+    #[allow(dead_code)]
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        adapter: impl ProviderAdapter + 'static,
+    ) -> anyhow::Result<()> {
+        let name = name.into();
+        if self.plugins.contains_key(&name) {
+            anyhow::bail!("provider adapter '{name}' is already registered");
+        }
+        self.plugins.insert(name, Box::new(adapter));
+        Ok(())
+    }
+
+    /// Fails when a channel names an adapter that was never registered.
+    pub fn check_channels(&self, channels: &[Channel]) -> anyhow::Result<()> {
+        let unknown: Vec<String> = channels
+            .iter()
+            .filter_map(|channel| {
+                let name = channel.adapter.as_deref()?;
+                (!self.plugins.contains_key(name))
+                    .then(|| format!("channel '{}': unknown adapter '{name}'", channel.name))
+            })
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!("{}", unknown.join("; "));
         }
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -150,10 +249,20 @@ impl ProviderRegistry {
     }
 
     pub fn adapter_for(&self, channel: &Channel, route: RouteKind) -> &dyn ProviderAdapter {
+        if let Some(plugin) = channel
+            .adapter
+            .as_deref()
+            .and_then(|name| self.plugins.get(name))
+        {
+            return plugin.as_ref();
+        }
         if channel.provider_type == ProviderType::Gemini && matches!(route, RouteKind::GeminiNative)
         {
             return self.gemini_native.as_ref();
         }
+        if matches!(route, RouteKind::Openai) && channel.anthropic_only() {
+            return self.anthropic_messages.as_ref();
+        }
 
         self.adapters
             .get(&channel.provider_type)
@@ -174,11 +283,116 @@ pub fn prepare_request(
     headers: &HeaderMap,
     body: &Bytes,
 ) -> anyhow::Result<PreparedRequest> {
+    let mut prepared = prepare_channel_request(
+        registry, channel, route, base_url, path, query, headers, body,
+    )?;
+    if let Some(profile) = &channel.param_profile
+        && prepared.url.path().ends_with("/chat/completions")
+    {
+        prepared.body = apply_param_profile(profile, &prepared.body);
+    }
+    if let Some(settings) = &channel.prompt_cache {
+        crate::prompt_cache::apply(settings, &mut prepared);
+    }
+    Ok(prepared)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_channel_request(
+    registry: &ProviderRegistry,
+    channel: &Channel,
+    route: RouteKind,
+    base_url: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    if channel.adapter.is_some() {
+        return prepare_adapter_request(
+            registry, channel, route, base_url, path, query, headers, body,
+        );
+    }
     if matches!(route, RouteKind::GeminiNative) {
         return prepare_gemini_native_request(channel, base_url, path, query, headers, body);
     }
+    if channel.provider_type == ProviderType::Vertex {
+        return prepare_vertex_request(channel, route, base_url, path, query, headers, body);
+    }
+    if channel.provider_type == ProviderType::Custom {
+        return prepare_custom_request(channel, route, base_url, path, query, headers, body);
+    }
+    if channel.provider_type == ProviderType::Gemini
+        && channel.native_api
+        && is_chat_path(route, path)
+    {
+        return prepare_gemini_generate_content_request(channel, route, base_url, headers, body);
+    }
+    if channel.provider_type == ProviderType::Gemini
+        && channel.native_api
+        && matches!(route, RouteKind::Openai)
+        && path.trim_matches('/').ends_with("images/generations")
+    {
+        return prepare_imagen_request(channel, base_url, headers, body);
+    }
+    if matches!(route, RouteKind::Openai) && channel.anthropic_only() && is_chat_path(route, path) {
+        return prepare_anthropic_messages_request(channel, headers, body);
+    }
+    if matches!(route, RouteKind::Openai)
+        && embeddings::is_embeddings_path(path)
+        && let Some(embeddings) = embeddings::adapter_for(channel)
+    {
+        return prepare_embeddings_request(registry, embeddings, channel, base_url, headers, body);
+    }
+    prepare_adapter_request(
+        registry, channel, route, base_url, path, query, headers, body,
+    )
+}
+
+/// Whether `prepare_request` would send `route`'s request for `model` on
+/// `path` to `channel` with the body untouched, so the body can be streamed
+/// through instead of buffered. Channels prepared outside their adapter
+/// (Vertex, custom quirks, native Gemini, embeddings, Anthropic-only) and
+/// the mock provider, which reads the body itself, never qualify.
+pub fn forwards_body(
+    registry: &ProviderRegistry,
+    channel: &Channel,
+    route: RouteKind,
+    path: &str,
+    model: &str,
+) -> bool {
+    let prepared_elsewhere = channel.adapter.is_none()
+        && (matches!(
+            channel.provider_type,
+            ProviderType::Vertex | ProviderType::Custom | ProviderType::Mock
+        ) || (channel.provider_type == ProviderType::Gemini && channel.native_api)
+            || (matches!(route, RouteKind::Openai) && channel.anthropic_only())
+            || (embeddings::is_embeddings_path(path)
+                && embeddings::adapter_for(channel).is_some()));
+    !prepared_elsewhere
+        && channel.param_profile.is_none()
+        && channel.prompt_cache.is_none()
+        && channel
+            .model_map
+            .as_ref()
+            .is_none_or(|map| !map.contains_key(model))
+        && registry.adapter_for(channel, route).forwards_body(route)
+}
 
-    let base_url = if matches!(route, RouteKind::Anthropic) {
+/// Request built by the channel's adapter alone: its path, query, body and
+/// auth mapping on top of the forwarded client headers.
+#[allow(clippy::too_many_arguments)]
+fn prepare_adapter_request(
+    registry: &ProviderRegistry,
+    channel: &Channel,
+    route: RouteKind,
+    base_url: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let base_url = if matches!(route, RouteKind::Anthropic) || channel.anthropic_only() {
         channel.anthropic_base_url.as_deref().unwrap_or(base_url)
     } else {
         base_url
@@ -194,6 +408,50 @@ pub fn prepare_request(
     Ok(PreparedRequest { url, body, headers })
 }
 
+/// Request for a path apex has no handler for: the channel's URL and
+/// credentials, with the body forwarded byte-for-byte.
+pub fn prepare_passthrough_request(
+    registry: &ProviderRegistry,
+    channel: &Channel,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let route = if channel.anthropic_only() {
+        RouteKind::Anthropic
+    } else {
+        RouteKind::Openai
+    };
+    let base_url = match route {
+        RouteKind::Anthropic => channel
+            .anthropic_base_url
+            .as_deref()
+            .unwrap_or(&channel.base_url),
+        _ => &channel.base_url,
+    };
+    if let Some(quirks) = custom_quirks(channel) {
+        let url = custom_url(quirks, base_url, path, query)?;
+        let mut headers = build_headers(headers, channel);
+        apply_custom_auth(quirks, route, &mut headers, &channel.api_key);
+        return Ok(PreparedRequest {
+            url,
+            body: body.clone(),
+            headers,
+        });
+    }
+    let adapter = registry.adapter_for(channel, route);
+    let mapped_path = adapter.map_path(route, base_url, path.trim_start_matches('/'));
+    let url = build_url(base_url, &mapped_path, query)?;
+    let mut headers = build_headers(headers, channel);
+    adapter.apply_auth_headers(route, &mut headers, &channel.api_key, base_url);
+    Ok(PreparedRequest {
+        url,
+        body: body.clone(),
+        headers,
+    })
+}
+
 pub fn prepare_gemini_native_request(
     channel: &Channel,
     base_url: &str,
@@ -220,6 +478,235 @@ pub fn prepare_gemini_native_request(
     })
 }
 
+/// Rewrites an OpenAI/Anthropic chat request as a native Gemini
+/// `generateContent` (or `streamGenerateContent?alt=sse`) call.
+pub fn prepare_gemini_generate_content_request(
+    channel: &Channel,
+    route: RouteKind,
+    base_url: &str,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let body = if matches!(route, RouteKind::Anthropic) {
+        convert_anthropic_to_openai(body)
+    } else {
+        body.clone()
+    };
+    let body = apply_model_map(&body, &channel.model_map);
+    let request = gemini_native::convert_openai_to_gemini(&body)?;
+    let url = build_url(
+        &gemini_native_base_url(base_url),
+        &request.path(),
+        request.query(),
+    )?;
+    let mut headers = build_headers(headers, channel);
+    apply_bearer_auth(&mut headers, &channel.api_key, "x-goog-api-key");
+
+    Ok(PreparedRequest {
+        url,
+        body: request.body,
+        headers,
+    })
+}
+
+/// Sends chat to Gemini's native `generateContent` on Vertex and every
+/// other OpenAI path to Vertex's OpenAI-compatible endpoint.
+pub fn prepare_vertex_request(
+    channel: &Channel,
+    route: RouteKind,
+    base_url: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let endpoint = vertex::endpoint(channel, base_url);
+    let body = if matches!(route, RouteKind::Anthropic) {
+        convert_anthropic_to_openai(body)
+    } else {
+        body.clone()
+    };
+    let body = apply_model_map(&body, &channel.model_map);
+    let (url, body) = if is_chat_path(route, path) {
+        let request = gemini_native::convert_openai_to_gemini(&body)?;
+        let path = vertex::model_path(channel, &request.model, request.method())?;
+        (build_url(&endpoint, &path, request.query())?, request.body)
+    } else {
+        let path = vertex::openapi_path(channel, path)?;
+        (build_url(&endpoint, &path, query)?, body)
+    };
+    let mut headers = build_headers(headers, channel);
+    VertexAdapter.apply_auth_headers(route, &mut headers, &channel.api_key, &endpoint);
+
+    Ok(PreparedRequest { url, body, headers })
+}
+
+/// Request for a `custom` channel, shaped by its `quirks`: Anthropic
+/// traffic is forwarded natively when `supports_anthropic` is set and
+/// converted to chat completions otherwise.
+pub fn prepare_custom_request(
+    channel: &Channel,
+    route: RouteKind,
+    base_url: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let quirks = custom_quirks(channel).unwrap_or(&DEFAULT_QUIRKS);
+    let native = matches!(route, RouteKind::Anthropic) && quirks.supports_anthropic;
+    let (base_url, path, body) = if native {
+        let base_url = channel.anthropic_base_url.as_deref().unwrap_or(base_url);
+        (
+            base_url,
+            path.to_string(),
+            apply_model_map(body, &channel.model_map),
+        )
+    } else {
+        let body = openai_compatible_body(route, body, &channel.model_map);
+        let body = if quirks.force_stream_usage {
+            ensure_openai_stream_usage(&body)
+        } else {
+            body
+        };
+        (base_url, openai_compatible_path(route, path), body)
+    };
+    // `?beta=true` and the like mean nothing to a chat completions endpoint.
+    let query = if matches!(route, RouteKind::Anthropic) && !native {
+        None
+    } else {
+        query
+    };
+    let url = custom_url(quirks, base_url, &path, query)?;
+    let mut headers = build_headers(headers, channel);
+    apply_custom_auth(quirks, route, &mut headers, &channel.api_key);
+
+    Ok(PreparedRequest { url, body, headers })
+}
+
+static DEFAULT_QUIRKS: ChannelQuirks = ChannelQuirks {
+    auth_header: None,
+    path_prefix: None,
+    supports_anthropic: false,
+    strip_query: false,
+    force_stream_usage: false,
+};
+
+fn custom_quirks(channel: &Channel) -> Option<&ChannelQuirks> {
+    if channel.provider_type != ProviderType::Custom || channel.adapter.is_some() {
+        return None;
+    }
+    Some(channel.quirks.as_ref().unwrap_or(&DEFAULT_QUIRKS))
+}
+
+fn custom_url(
+    quirks: &ChannelQuirks,
+    base_url: &str,
+    path: &str,
+    query: Option<&str>,
+) -> anyhow::Result<Url> {
+    let path = path.trim_start_matches('/');
+    let path = match quirks.path_prefix.as_deref().map(|p| p.trim_matches('/')) {
+        Some(prefix) if !prefix.is_empty() => format!("{prefix}/{path}"),
+        _ => path.to_string(),
+    };
+    let query = if quirks.strip_query { None } else { query };
+    build_url(base_url, &path, query)
+}
+
+fn apply_custom_auth(
+    quirks: &ChannelQuirks,
+    route: RouteKind,
+    headers: &mut HeaderMap,
+    api_key: &str,
+) {
+    let header = quirks
+        .auth_header
+        .as_deref()
+        .unwrap_or("authorization")
+        .to_ascii_lowercase();
+    apply_bearer_auth(headers, api_key, &header);
+    if matches!(route, RouteKind::Anthropic)
+        && quirks.supports_anthropic
+        && !headers.contains_key("anthropic-version")
+    {
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+    }
+}
+
+/// Rewrites an OpenAI `images/generations` request as a native Imagen
+/// `predict` call.
+pub fn prepare_imagen_request(
+    channel: &Channel,
+    base_url: &str,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let body = apply_model_map(body, &channel.model_map);
+    let (path, body) = gemini_native::convert_openai_image_request_to_imagen(&body)?;
+    let url = build_url(&gemini_native_base_url(base_url), &path, None)?;
+    let mut headers = build_headers(headers, channel);
+    apply_bearer_auth(&mut headers, &channel.api_key, "x-goog-api-key");
+
+    Ok(PreparedRequest { url, body, headers })
+}
+
+/// Rewrites an OpenAI chat request as an Anthropic Messages call for
+/// channels that only have `anthropic_base_url`.
+pub fn prepare_anthropic_messages_request(
+    channel: &Channel,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let base_url = channel.anthropic_base_url.as_deref().unwrap_or_default();
+    let body = convert_openai_to_anthropic(&apply_model_map(body, &channel.model_map));
+    let url = build_url(base_url, "v1/messages", None)?;
+    let mut headers = build_headers(headers, channel);
+    AnthropicAdapter.apply_auth_headers(
+        RouteKind::Anthropic,
+        &mut headers,
+        &channel.api_key,
+        base_url,
+    );
+
+    Ok(PreparedRequest { url, body, headers })
+}
+
+/// Rewrites an OpenAI embeddings request for a provider with its own
+/// embeddings shape (see `embeddings::adapter_for`).
+pub fn prepare_embeddings_request(
+    registry: &ProviderRegistry,
+    embeddings: &dyn EmbeddingsAdapter,
+    channel: &Channel,
+    base_url: &str,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> anyhow::Result<PreparedRequest> {
+    let body = apply_model_map(body, &channel.model_map);
+    let request = serde_json::from_slice(&body)
+        .map_err(|e| anyhow::anyhow!("invalid embeddings request: {e}"))?;
+    let body = Bytes::from(serde_json::to_vec(
+        &embeddings.transform_request(channel, request)?,
+    )?);
+    let base_url = embeddings.base_url(base_url);
+    let url = build_url(&base_url, embeddings.path(), None)?;
+    let mut headers = build_headers(headers, channel);
+    registry
+        .adapter_for(channel, RouteKind::Openai)
+        .apply_auth_headers(RouteKind::Openai, &mut headers, &channel.api_key, &base_url);
+
+    Ok(PreparedRequest { url, body, headers })
+}
+
+fn is_chat_path(route: RouteKind, path: &str) -> bool {
+    let path = path.trim_matches('/');
+    match route {
+        RouteKind::Openai => path.ends_with("chat/completions"),
+        RouteKind::Anthropic => path.ends_with("messages"),
+        RouteKind::GeminiNative => false,
+    }
+}
+
 // --- Helper Functions ---
 
 pub fn should_forward_response_header(name: &HeaderName) -> bool {
@@ -227,6 +714,16 @@ pub fn should_forward_response_header(name: &HeaderName) -> bool {
     !matches!(lower.as_str(), "transfer-encoding" | "content-length")
 }
 
+/// Upstream rate-limit headers (`Retry-After`, OpenAI `x-ratelimit-*`,
+/// Anthropic `anthropic-ratelimit-*`), relayed to the client even when the
+/// error body is rewritten for another protocol.
+pub fn is_rate_limit_header(name: &HeaderName) -> bool {
+    let lower = name.as_str().to_ascii_lowercase();
+    matches!(lower.as_str(), "retry-after" | "retry-after-ms")
+        || lower.starts_with("x-ratelimit-")
+        || lower.starts_with("anthropic-ratelimit-")
+}
+
 pub fn error_response(status: StatusCode, message: &str) -> Response<Body> {
     Response::builder()
         .status(status)
@@ -281,6 +778,7 @@ fn should_forward_header(name: &HeaderName) -> bool {
         "host" | "content-length" | "x-api-key" | "authorization" | "accept-encoding"
     ) && !lower.starts_with("anthropic-")
         && !lower.starts_with("x-stainless-")
+        && !lower.starts_with("x-apex-")
 }
 
 pub fn gemini_native_base_url(base_url: &str) -> String {
@@ -348,13 +846,19 @@ fn build_url(base: &str, path: &str, query: Option<&str>) -> anyhow::Result<Url>
     Ok(url)
 }
 
-fn apply_model_map(body: &Bytes, model_map: &Option<HashMap<String, String>>) -> Bytes {
+/// Rewrites the request's `model` through the channel's `model_map`, for
+/// adapters implementing `transform_body`.
+pub fn apply_model_map(body: &Bytes, model_map: &Option<HashMap<String, String>>) -> Bytes {
     // Best-effort model remapping; keep original payload on any parse/lookup failure.
     let Some(model_map) = model_map else {
         return body.clone();
     };
     let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
-        return body.clone();
+        // Image edits / variations are multipart uploads.
+        return crate::images::multipart_field(body, "model")
+            .and_then(|model| model_map.get(&model))
+            .and_then(|mapped| crate::images::replace_multipart_field(body, "model", mapped))
+            .unwrap_or_else(|| body.clone());
     };
     let Some(model) = value.get("model").and_then(|m| m.as_str()) else {
         return body.clone();
@@ -522,6 +1026,10 @@ impl ProviderAdapter for DefaultAdapter {
         openai_compatible_body(route, body, model_map)
     }
 
+    fn forwards_body(&self, route: RouteKind) -> bool {
+        matches!(route, RouteKind::Openai)
+    }
+
     fn apply_auth_headers(
         &self,
         _route: RouteKind,
@@ -542,10 +1050,11 @@ impl ProviderAdapter for DefaultAdapter {
     }
 }
 
-/// Adapter for OpenAI.
-struct OpenAiAdapter;
+/// Adapter for Mistral, which answers `422` to request fields it does not
+/// know instead of ignoring them.
+struct MistralAdapter;
 
-impl ProviderAdapter for OpenAiAdapter {
+impl ProviderAdapter for MistralAdapter {
     fn map_path(&self, route: RouteKind, _base_url: &str, path: &str) -> String {
         openai_compatible_path(route, path)
     }
@@ -556,7 +1065,7 @@ impl ProviderAdapter for OpenAiAdapter {
         body: &Bytes,
         model_map: &Option<HashMap<String, String>>,
     ) -> Bytes {
-        openai_compatible_body(route, body, model_map)
+        strip_mistral_unsupported(&openai_compatible_body(route, body, model_map))
     }
 
     fn apply_auth_headers(
@@ -579,20 +1088,140 @@ impl ProviderAdapter for OpenAiAdapter {
     }
 }
 
-/// Adapter for custom upstreams that natively support both OpenAI and Anthropic protocols.
-///
-/// This is intended for aggregator or proxy platforms where callers provide the
-/// OpenAI and Anthropic base URLs explicitly in channel config.
-struct CustomDualAdapter;
+/// Mistral streams usage on its last chunk unasked and has no
+/// `stream_options`; `max_completion_tokens` is spelled `max_tokens`.
+fn strip_mistral_unsupported(body: &Bytes) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return body.clone();
+    };
+    let Some(object) = value.as_object_mut() else {
+        return body.clone();
+    };
+    let stream_options = object.remove("stream_options");
+    let max_completion_tokens = object.remove("max_completion_tokens");
+    if let Some(max_tokens) = &max_completion_tokens
+        && !object.contains_key("max_tokens")
+    {
+        object.insert("max_tokens".to_string(), max_tokens.clone());
+    }
+    if stream_options.is_none() && max_completion_tokens.is_none() {
+        return body.clone();
+    }
+    serde_json::to_vec(&value)
+        .map(Bytes::from)
+        .unwrap_or_else(|_| body.clone())
+}
 
-impl ProviderAdapter for CustomDualAdapter {
-    fn map_path(&self, _route: RouteKind, _base_url: &str, path: &str) -> String {
-        path.to_string()
+/// Rewrite a chat completions body for `profile` when its model is covered.
+fn apply_param_profile(profile: &ParamProfile, body: &Bytes) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return body.clone();
+    };
+    let Some(object) = value.as_object_mut() else {
+        return body.clone();
+    };
+    let model = object
+        .get("model")
+        .and_then(|model| model.as_str())
+        .unwrap_or_default();
+    match profile {
+        ParamProfile::Reasoning {
+            models,
+            reasoning_effort,
+        } => {
+            if !models
+                .iter()
+                .any(|pattern| crate::config::model_pattern_matches(pattern, model))
+            {
+                return body.clone();
+            }
+            if let Some(max_tokens) = object.remove("max_tokens")
+                && !object.contains_key("max_completion_tokens")
+            {
+                object.insert("max_completion_tokens".to_string(), max_tokens);
+            }
+            for key in [
+                "temperature",
+                "top_p",
+                "presence_penalty",
+                "frequency_penalty",
+                "logprobs",
+                "top_logprobs",
+                "logit_bias",
+            ] {
+                object.remove(key);
+            }
+            let requested = object
+                .remove("reasoning")
+                .and_then(|reasoning| reasoning.get("effort").cloned());
+            if !object.contains_key("reasoning_effort")
+                && let Some(effort) =
+                    requested.or_else(|| reasoning_effort.clone().map(serde_json::Value::String))
+            {
+                object.insert("reasoning_effort".to_string(), effort);
+            }
+        }
+    }
+    serde_json::to_vec(&value)
+        .map(Bytes::from)
+        .unwrap_or_else(|_| body.clone())
+}
+
+/// Adapter for OpenAI.
+struct OpenAiAdapter;
+
+impl ProviderAdapter for OpenAiAdapter {
+    fn map_path(&self, route: RouteKind, _base_url: &str, path: &str) -> String {
+        openai_compatible_path(route, path)
     }
 
     fn transform_body(
         &self,
-        _route: RouteKind,
+        route: RouteKind,
+        body: &Bytes,
+        model_map: &Option<HashMap<String, String>>,
+    ) -> Bytes {
+        openai_compatible_body(route, body, model_map)
+    }
+
+    fn forwards_body(&self, route: RouteKind) -> bool {
+        matches!(route, RouteKind::Openai)
+    }
+
+    fn apply_auth_headers(
+        &self,
+        _route: RouteKind,
+        headers: &mut HeaderMap,
+        api_key: &str,
+        _base_url: &str,
+    ) {
+        apply_bearer_auth(headers, api_key, "authorization");
+    }
+
+    fn handle_response(
+        &self,
+        route: RouteKind,
+        resp: reqwest::Response,
+        timeout: Duration,
+    ) -> Response<Body> {
+        handle_openai_compatible_response(route, resp, timeout)
+    }
+}
+
+/// Adapter for custom upstreams that natively support both OpenAI and Anthropic protocols.
+///
+/// This is intended for aggregator or proxy platforms where callers provide the
+/// OpenAI and Anthropic base URLs explicitly in channel config.
+struct CustomDualAdapter;
+
+impl ProviderAdapter for CustomDualAdapter {
+    fn map_path(&self, _route: RouteKind, _base_url: &str, path: &str) -> String {
+        path.to_string()
+    }
+
+    fn transform_body(
+        &self,
+        _route: RouteKind,
         body: &Bytes,
         model_map: &Option<HashMap<String, String>>,
     ) -> Bytes {
@@ -623,6 +1252,54 @@ impl ProviderAdapter for CustomDualAdapter {
     }
 }
 
+/// Adapter for `custom` channels. Requests are built by
+/// `prepare_custom_request`; an Anthropic response is converted back unless
+/// it came from a native Messages endpoint.
+struct CustomAdapter;
+
+impl ProviderAdapter for CustomAdapter {
+    fn map_path(&self, route: RouteKind, _base_url: &str, path: &str) -> String {
+        openai_compatible_path(route, path)
+    }
+
+    fn transform_body(
+        &self,
+        route: RouteKind,
+        body: &Bytes,
+        model_map: &Option<HashMap<String, String>>,
+    ) -> Bytes {
+        openai_compatible_body(route, body, model_map)
+    }
+
+    fn apply_auth_headers(
+        &self,
+        _route: RouteKind,
+        headers: &mut HeaderMap,
+        api_key: &str,
+        _base_url: &str,
+    ) {
+        apply_bearer_auth(headers, api_key, "authorization");
+    }
+
+    fn handle_response(
+        &self,
+        route: RouteKind,
+        resp: reqwest::Response,
+        timeout: Duration,
+    ) -> Response<Body> {
+        if resp
+            .url()
+            .path()
+            .trim_end_matches('/')
+            .ends_with("/messages")
+        {
+            convert_response(resp, timeout)
+        } else {
+            handle_openai_compatible_response(route, resp, timeout)
+        }
+    }
+}
+
 /// Adapter for Anthropic.
 struct AnthropicAdapter;
 
@@ -640,6 +1317,10 @@ impl ProviderAdapter for AnthropicAdapter {
         apply_model_map(body, model_map)
     }
 
+    fn forwards_body(&self, route: RouteKind) -> bool {
+        matches!(route, RouteKind::Anthropic)
+    }
+
     fn apply_auth_headers(
         &self,
         _route: RouteKind,
@@ -657,6 +1338,91 @@ impl ProviderAdapter for AnthropicAdapter {
     }
 }
 
+/// Adapter for OpenAI traffic on Anthropic-only channels: chat goes to
+/// `/v1/messages` (see `prepare_anthropic_messages_request`) and its response
+/// is converted back; anything else is passed through untouched.
+struct AnthropicMessagesAdapter;
+
+impl ProviderAdapter for AnthropicMessagesAdapter {
+    fn map_path(&self, _route: RouteKind, _base_url: &str, path: &str) -> String {
+        path.to_string()
+    }
+
+    fn transform_body(
+        &self,
+        _route: RouteKind,
+        body: &Bytes,
+        model_map: &Option<HashMap<String, String>>,
+    ) -> Bytes {
+        apply_model_map(body, model_map)
+    }
+
+    fn apply_auth_headers(
+        &self,
+        route: RouteKind,
+        headers: &mut HeaderMap,
+        api_key: &str,
+        base_url: &str,
+    ) {
+        AnthropicAdapter.apply_auth_headers(route, headers, api_key, base_url);
+    }
+
+    fn handle_response(
+        &self,
+        _route: RouteKind,
+        resp: reqwest::Response,
+        timeout: Duration,
+    ) -> Response<Body> {
+        if !resp
+            .url()
+            .path()
+            .trim_end_matches('/')
+            .ends_with("/messages")
+        {
+            return convert_response(resp, timeout);
+        }
+
+        let status = resp.status();
+        let is_stream = resp
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("text/event-stream"));
+        let stream = resp.bytes_stream().timeout(timeout).map(|item| match item {
+            Ok(Ok(bytes)) => Ok(bytes),
+            Ok(Err(err)) => Err(io::Error::other(err)),
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "response timeout")),
+        });
+
+        if is_stream {
+            return Response::builder()
+                .status(status)
+                .header("content-type", "text/event-stream")
+                .header("cache-control", "no-cache")
+                .body(Body::from_stream(convert_anthropic_stream_to_openai(
+                    Box::pin(stream),
+                )))
+                .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"));
+        }
+
+        let future = stream
+            .fold(Vec::new(), |mut acc, item| {
+                if let Ok(bytes) = item {
+                    acc.extend_from_slice(&bytes);
+                }
+                acc
+            })
+            .map(|bytes| {
+                Ok::<_, io::Error>(convert_anthropic_response_to_openai(Bytes::from(bytes)))
+            });
+        Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from_stream(stream::once(future)))
+            .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"))
+    }
+}
+
 /// Adapter for Google Gemini.
 struct GeminiAdapter;
 
@@ -712,7 +1478,8 @@ impl ProviderAdapter for GeminiAdapter {
         api_key: &str,
         _base_url: &str,
     ) {
-        // Gemini adapter always targets the OpenAI compatibility surface.
+        // Chat on `native_api` channels is prepared separately; everything
+        // routed through this adapter targets the OpenAI compatibility surface.
         apply_bearer_auth(headers, api_key, "authorization");
     }
 
@@ -722,10 +1489,174 @@ impl ProviderAdapter for GeminiAdapter {
         resp: reqwest::Response,
         timeout: Duration,
     ) -> Response<Body> {
-        handle_openai_compatible_response(route, resp, timeout)
+        if gemini_native::is_generate_content_url(resp.url()) {
+            handle_generate_content_response(route, resp, timeout)
+        } else if gemini_native::is_predict_url(resp.url()) {
+            handle_imagen_response(resp, timeout)
+        } else {
+            handle_openai_compatible_response(route, resp, timeout)
+        }
     }
 }
 
+/// Adapter for Google Vertex AI. Requests are built by
+/// `prepare_vertex_request`; service-account keys are swapped for access
+/// tokens when the request is sent (see `vertex::TokenCache`).
+struct VertexAdapter;
+
+impl ProviderAdapter for VertexAdapter {
+    fn map_path(&self, _route: RouteKind, _base_url: &str, path: &str) -> String {
+        path.to_string()
+    }
+
+    fn transform_body(
+        &self,
+        _route: RouteKind,
+        body: &Bytes,
+        model_map: &Option<HashMap<String, String>>,
+    ) -> Bytes {
+        apply_model_map(body, model_map)
+    }
+
+    fn apply_auth_headers(
+        &self,
+        _route: RouteKind,
+        headers: &mut HeaderMap,
+        api_key: &str,
+        _base_url: &str,
+    ) {
+        if !vertex::is_service_account(api_key) {
+            apply_bearer_auth(headers, api_key, "authorization");
+        }
+    }
+
+    fn handle_response(
+        &self,
+        route: RouteKind,
+        resp: reqwest::Response,
+        timeout: Duration,
+    ) -> Response<Body> {
+        if gemini_native::is_generate_content_url(resp.url()) {
+            handle_generate_content_response(route, resp, timeout)
+        } else {
+            handle_openai_compatible_response(route, resp, timeout)
+        }
+    }
+}
+
+/// Converts a native `generateContent` response back to the caller's protocol.
+fn handle_generate_content_response(
+    route: RouteKind,
+    resp: reqwest::Response,
+    timeout: Duration,
+) -> Response<Body> {
+    let status = resp.status();
+    let is_stream = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+    let stream = resp.bytes_stream().timeout(timeout).map(|item| match item {
+        Ok(Ok(bytes)) => Ok(bytes),
+        Ok(Err(err)) => Err(io::Error::other(err)),
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "response timeout")),
+    });
+
+    if is_stream {
+        let openai = Box::pin(gemini_native::convert_gemini_stream_to_openai(Box::pin(
+            stream,
+        )));
+        let body = if matches!(route, RouteKind::Anthropic) {
+            Body::from_stream(convert_openai_stream_to_anthropic(openai))
+        } else {
+            Body::from_stream(openai)
+        };
+        return Response::builder()
+            .status(status)
+            .header("content-type", "text/event-stream")
+            .header("cache-control", "no-cache")
+            .body(body)
+            .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"));
+    }
+
+    let future = stream
+        .fold(Vec::new(), |mut acc, item| {
+            if let Ok(bytes) = item {
+                acc.extend_from_slice(&bytes);
+            }
+            acc
+        })
+        .map(move |bytes| {
+            let converted = gemini_native::convert_gemini_response_to_openai(Bytes::from(bytes));
+            let converted = if matches!(route, RouteKind::Anthropic) {
+                convert_openai_response_to_anthropic(converted)
+            } else {
+                converted
+            };
+            Ok::<_, io::Error>(converted)
+        });
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from_stream(stream::once(future)))
+        .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"))
+}
+
+/// Converts a native Imagen `predict` response to OpenAI's images shape.
+fn handle_imagen_response(resp: reqwest::Response, timeout: Duration) -> Response<Body> {
+    let status = resp.status();
+    let future = resp
+        .bytes_stream()
+        .timeout(timeout)
+        .fold(Vec::new(), |mut acc, item| {
+            if let Ok(Ok(bytes)) = item {
+                acc.extend_from_slice(&bytes);
+            }
+            acc
+        })
+        .map(|bytes| {
+            Ok::<_, io::Error>(gemini_native::convert_imagen_response_to_openai(
+                Bytes::from(bytes),
+            ))
+        });
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from_stream(stream::once(future)))
+        .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"))
+}
+
+/// Converts a successful native Ollama `/api/embed` response to OpenAI's
+/// embeddings shape.
+fn handle_ollama_embeddings_response(resp: reqwest::Response, timeout: Duration) -> Response<Body> {
+    let status = resp.status();
+    let future = resp
+        .bytes_stream()
+        .timeout(timeout)
+        .fold(Vec::new(), |mut acc, item| {
+            if let Ok(Ok(bytes)) = item {
+                acc.extend_from_slice(&bytes);
+            }
+            acc
+        })
+        .map(|bytes| {
+            let converted = match serde_json::from_slice(&bytes) {
+                Ok(value) => Bytes::from(
+                    embeddings::OllamaEmbeddings
+                        .transform_response(value)
+                        .to_string(),
+                ),
+                Err(_) => Bytes::from(bytes),
+            };
+            Ok::<_, io::Error>(converted)
+        });
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from_stream(stream::once(future)))
+        .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid response"))
+}
+
 /// Adapter for Gemini native REST pass-through routes.
 struct GeminiNativeAdapter;
 
@@ -839,6 +1770,23 @@ impl ProviderAdapter for OllamaAdapter {
             }
         }
     }
+    fn handle_response(
+        &self,
+        _route: RouteKind,
+        resp: reqwest::Response,
+        timeout: Duration,
+    ) -> Response<Body> {
+        let is_embed = resp
+            .url()
+            .path()
+            .trim_end_matches('/')
+            .ends_with(embeddings::OLLAMA_EMBED_PATH);
+        if is_embed && resp.status().is_success() {
+            handle_ollama_embeddings_response(resp, timeout)
+        } else {
+            convert_response(resp, timeout)
+        }
+    }
 }
 
 /// Adapter that supports both OpenAI and Anthropic protocols natively.
@@ -945,6 +1893,13 @@ impl ProviderAdapter for DualProtocolAdapter {
         }
     }
 
+    fn forwards_body(&self, route: RouteKind) -> bool {
+        match route {
+            RouteKind::Anthropic => self.anthropic.forwards_body(route),
+            RouteKind::Openai | RouteKind::GeminiNative => self.openai.forwards_body(route),
+        }
+    }
+
     fn apply_auth_headers(
         &self,
         route: RouteKind,
@@ -978,10 +1933,7 @@ mod tests {
             provider_type: ProviderType::Openai,
             base_url: "https://example.com".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let adapter = registry.adapter(&channel);
         let mapped = adapter.map_path(
@@ -992,6 +1944,151 @@ mod tests {
         assert_eq!(mapped, "/v1/chat/completions");
     }
 
+    #[test]
+    fn mistral_drops_stream_options_and_renames_max_completion_tokens() {
+        let body = Bytes::from(
+            r#"{"model":"mistral-large-latest","stream":true,"stream_options":{"include_usage":true},"max_completion_tokens":64,"messages":[]}"#,
+        );
+        let value: serde_json::Value =
+            serde_json::from_slice(&MistralAdapter.transform_body(RouteKind::Openai, &body, &None))
+                .unwrap();
+        assert!(value.get("stream_options").is_none());
+        assert!(value.get("max_completion_tokens").is_none());
+        assert_eq!(value["max_tokens"], 64);
+
+        let plain = Bytes::from(r#"{"model":"mistral-small-latest","messages":[]}"#);
+        assert_eq!(
+            MistralAdapter.transform_body(RouteKind::Openai, &plain, &None),
+            plain
+        );
+    }
+
+    #[test]
+    fn reasoning_profile_normalizes_chat_parameters() {
+        let registry = ProviderRegistry::new();
+        let channel: Channel = serde_json::from_value(serde_json::json!({
+            "name": "gateway",
+            "provider_type": "openrouter",
+            "base_url": "https://llm.example.com/v1",
+            "api_key": "secret",
+            "model_map": {"reasoner": "o3-mini"},
+            "param_profile": {"type": "reasoning", "reasoning_effort": "low"}
+        }))
+        .unwrap();
+        let prepare = |body: &str| {
+            let prepared = prepare_request(
+                &registry,
+                &channel,
+                RouteKind::Openai,
+                &channel.base_url,
+                "/v1/chat/completions",
+                None,
+                &HeaderMap::new(),
+                &Bytes::from(body.to_string()),
+            )
+            .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&prepared.body).unwrap()
+        };
+
+        let body = prepare(
+            r#"{"model":"reasoner","max_tokens":64,"temperature":0.2,"top_p":0.9,"messages":[]}"#,
+        );
+        assert_eq!(body["model"], "o3-mini");
+        assert_eq!(body["max_completion_tokens"], 64);
+        assert_eq!(body["reasoning_effort"], "low");
+        for key in ["max_tokens", "temperature", "top_p"] {
+            assert!(body.get(key).is_none(), "{key} kept in {body}");
+        }
+
+        let body = prepare(r#"{"model":"o4-mini","reasoning":{"effort":"high"},"messages":[]}"#);
+        assert_eq!(body["reasoning_effort"], "high");
+        assert!(body.get("reasoning").is_none());
+
+        let body = prepare(r#"{"model":"gpt-4o","max_tokens":64,"temperature":0.2,"messages":[]}"#);
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["temperature"], 0.2);
+        assert!(body.get("reasoning_effort").is_none());
+    }
+
+    #[test]
+    fn custom_channel_follows_its_quirks() {
+        let registry = ProviderRegistry::new();
+        let mut channel: Channel = serde_json::from_value(serde_json::json!({
+            "name": "vllm",
+            "provider_type": "custom",
+            "base_url": "http://gpu-box:8000",
+            "api_key": "secret",
+            "quirks": {
+                "auth_header": "X-Api-Key",
+                "path_prefix": "/openai/",
+                "strip_query": true,
+                "force_stream_usage": true
+            }
+        }))
+        .unwrap();
+        let prepared = prepare_request(
+            &registry,
+            &channel,
+            RouteKind::Openai,
+            &channel.base_url,
+            "/v1/chat/completions",
+            Some("api-version=1"),
+            &HeaderMap::new(),
+            &Bytes::from(r#"{"model":"qwen","stream":true,"messages":[]}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            prepared.url.as_str(),
+            "http://gpu-box:8000/openai/v1/chat/completions"
+        );
+        assert_eq!(prepared.headers["x-api-key"], "secret");
+        assert!(prepared.headers.get("authorization").is_none());
+        let body: serde_json::Value = serde_json::from_slice(&prepared.body).unwrap();
+        assert_eq!(body["stream_options"]["include_usage"], true);
+
+        // Anthropic traffic is converted unless the server speaks Messages.
+        let messages = Bytes::from(
+            r#"{"model":"qwen","max_tokens":8,"messages":[{"role":"user","content":"hi"}]}"#,
+        );
+        channel.base_url = "http://gpu-box:8000/v1".to_string();
+        channel.quirks = None;
+        let converted = prepare_request(
+            &registry,
+            &channel,
+            RouteKind::Anthropic,
+            &channel.base_url,
+            "/v1/messages",
+            Some("beta=true"),
+            &HeaderMap::new(),
+            &messages,
+        )
+        .unwrap();
+        assert_eq!(
+            converted.url.as_str(),
+            "http://gpu-box:8000/v1/chat/completions"
+        );
+        assert_eq!(converted.headers["authorization"], "Bearer secret");
+
+        channel.quirks = Some(ChannelQuirks {
+            supports_anthropic: true,
+            ..ChannelQuirks::default()
+        });
+        let native = prepare_request(
+            &registry,
+            &channel,
+            RouteKind::Anthropic,
+            &channel.base_url,
+            "/v1/messages",
+            None,
+            &HeaderMap::new(),
+            &messages,
+        )
+        .unwrap();
+        assert_eq!(native.url.as_str(), "http://gpu-box:8000/v1/messages");
+        assert_eq!(native.body, messages);
+        assert_eq!(native.headers["anthropic-version"], "2023-06-01");
+    }
+
     #[test]
     fn applies_model_map() {
         let mut model_map = HashMap::new();
@@ -1028,10 +2125,7 @@ mod tests {
             provider_type: ProviderType::Openai,
             base_url: "https://example.com".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1059,10 +2153,7 @@ mod tests {
             provider_type: ProviderType::Openai,
             base_url: "https://api.openai.com/v1".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1106,9 +2197,7 @@ mod tests {
             base_url: "https://api.example.com/v1".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://api.example.com/anthropic".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1151,9 +2240,7 @@ mod tests {
             base_url: "https://api.example.com/v1".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://api.example.com/anthropic".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1185,10 +2272,7 @@ mod tests {
             provider_type: ProviderType::Anthropic,
             base_url: "https://example.com".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1283,10 +2367,7 @@ mod tests {
             provider_type: ProviderType::Anthropic,
             base_url: "https://example.com".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1319,10 +2400,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://example.com".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1347,10 +2425,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1378,10 +2453,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1410,10 +2482,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let body = Bytes::from(
@@ -1445,10 +2514,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta/openai/".to_string(),
             api_key: "gemini-key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let body = Bytes::from_static(br#"{"contents":[]}"#);
@@ -1490,10 +2556,8 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
             api_key: "gemini-key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
             model_map: Some(model_map),
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let body = Bytes::from_static(br#"{"contents":[],"model":"leave-alone"}"#);
@@ -1525,10 +2589,7 @@ mod tests {
             provider_type: ProviderType::Gemini,
             base_url: "https://generativelanguage.googleapis.com/v1beta/openai".to_string(),
             api_key: "gemini-key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
@@ -1561,9 +2622,7 @@ mod tests {
             base_url: "https://openrouter.ai/api/v1".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://openrouter.ai/api".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1605,9 +2664,7 @@ mod tests {
             base_url: "https://openrouter.ai/api/v1".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://openrouter.ai/api".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1638,9 +2695,7 @@ mod tests {
             base_url: "https://api.z.ai/api/coding/paas/v4".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://api.z.ai/api/anthropic".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1676,9 +2731,7 @@ mod tests {
             base_url: "https://api.z.ai/api/coding/paas/v4".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("https://api.z.ai/api/anthropic".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1723,9 +2776,7 @@ mod tests {
             base_url: "http://localhost:11434".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("http://localhost:11434".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1764,9 +2815,7 @@ mod tests {
             base_url: "http://localhost:11434".to_string(),
             api_key: "key".to_string(),
             anthropic_base_url: Some("http://localhost:11434".to_string()),
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
 
@@ -1802,19 +2851,17 @@ mod tests {
         headers.insert("x-api-key", HeaderValue::from_static("a"));
         headers.insert("authorization", HeaderValue::from_static("b"));
         headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers.insert("x-apex-channel", HeaderValue::from_static("c"));
         let channel = Channel {
             name: "c".to_string(),
             provider_type: ProviderType::Openai,
             base_url: "https://example.com".to_string(),
-            api_key: "".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let merged = build_headers(&headers, &channel);
         assert!(merged.get("x-api-key").is_none());
         assert!(merged.get("authorization").is_none());
+        assert!(merged.get("x-apex-channel").is_none());
         assert!(merged.get("content-type").is_some());
     }
 
@@ -1827,11 +2874,8 @@ mod tests {
             name: "c".to_string(),
             provider_type: ProviderType::Openai,
             base_url: "https://example.com".to_string(),
-            api_key: "".to_string(),
-            anthropic_base_url: None,
             headers: Some(extra),
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let merged = build_headers(&headers, &channel);
         assert_eq!(merged.get("x-extra").unwrap(), "1");
@@ -1863,10 +2907,7 @@ mod tests {
             provider_type: ProviderType::Minimax, // Usually uses DefaultAdapter
             base_url: "https://api.minimax.io/anthropic".to_string(),
             api_key: "key".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         };
         let headers = HeaderMap::new();
         let prepared = prepare_request(
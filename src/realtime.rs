@@ -0,0 +1,176 @@
+//! WebSocket relay for the OpenAI Realtime API (`GET /v1/realtime`).
+//!
+//! `server::handle_realtime` authenticates and routes the upgrade like any
+//! other model request; this module dials the chosen channel and shuttles
+//! frames both ways until either side hangs up. Usage is summed from the
+//! `response.done` events the upstream sends and logged once per session.
+
+use axum::extract::ws::{self, WebSocket};
+use axum::http::HeaderMap;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, frame::coding::CloseCode};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub type UpstreamSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Token totals for one realtime session.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RealtimeUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl RealtimeUsage {
+    /// Add the usage of a `response.done` server event; other events are
+    /// ignored.
+    pub fn record_event(&mut self, text: &str) {
+        if !text.contains("response.done") {
+            return;
+        }
+        let Ok(event) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        if event.get("type").and_then(Value::as_str) != Some("response.done") {
+            return;
+        }
+        let Some(usage) = event.pointer("/response/usage") else {
+            return;
+        };
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        self.input_tokens += count("input_tokens");
+        self.output_tokens += count("output_tokens");
+    }
+}
+
+/// Open the upstream socket. `url` is the prepared HTTP(S) URL, rewritten to
+/// `ws`/`wss`; `headers` carry the channel's credentials.
+pub async fn connect_upstream(
+    mut url: url::Url,
+    headers: &HeaderMap,
+) -> anyhow::Result<UpstreamSocket> {
+    let scheme = match url.scheme() {
+        "https" | "wss" => "wss",
+        _ => "ws",
+    };
+    url.set_scheme(scheme)
+        .map_err(|_| anyhow::anyhow!("cannot use {} as a websocket url", url))?;
+    let mut request = url.as_str().into_client_request()?;
+    for (name, value) in headers {
+        if matches!(
+            name.as_str(),
+            "host" | "content-type" | "content-length" | "accept-encoding"
+        ) {
+            continue;
+        }
+        request.headers_mut().insert(name.clone(), value.clone());
+    }
+    let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+    Ok(socket)
+}
+
+/// Relay frames between the caller and upstream until one side closes, then
+/// return the usage the upstream reported.
+pub async fn relay(client: WebSocket, upstream: UpstreamSocket) -> RealtimeUsage {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+    let usage = std::sync::Mutex::new(RealtimeUsage::default());
+
+    let inbound = async {
+        while let Some(Ok(message)) = client_rx.next().await {
+            let closing = matches!(message, ws::Message::Close(_));
+            if upstream_tx.send(to_upstream(message)).await.is_err() || closing {
+                break;
+            }
+        }
+        let _ = upstream_tx.close().await;
+    };
+    let outbound = async {
+        while let Some(Ok(message)) = upstream_rx.next().await {
+            if let Message::Text(text) = &message {
+                usage.lock().unwrap().record_event(text);
+            }
+            let closing = matches!(message, Message::Close(_));
+            let Some(message) = to_client(message) else {
+                continue;
+            };
+            if client_tx.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+        let _ = client_tx.close().await;
+    };
+
+    tokio::select! {
+        _ = inbound => {}
+        _ = outbound => {}
+    }
+    usage.into_inner().unwrap()
+}
+
+fn to_upstream(message: ws::Message) -> Message {
+    match message {
+        ws::Message::Text(text) => Message::Text(text),
+        ws::Message::Binary(data) => Message::Binary(data),
+        ws::Message::Ping(data) => Message::Ping(data),
+        ws::Message::Pong(data) => Message::Pong(data),
+        ws::Message::Close(frame) => Message::Close(frame.map(|frame| CloseFrame {
+            code: CloseCode::from(frame.code),
+            reason: frame.reason,
+        })),
+    }
+}
+
+fn to_client(message: Message) -> Option<ws::Message> {
+    Some(match message {
+        Message::Text(text) => ws::Message::Text(text),
+        Message::Binary(data) => ws::Message::Binary(data),
+        Message::Ping(data) => ws::Message::Ping(data),
+        Message::Pong(data) => ws::Message::Pong(data),
+        Message::Close(frame) => ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+            code: frame.code.into(),
+            reason: frame.reason,
+        })),
+        Message::Frame(_) => return None,
+    })
+}
+
+/// Error text for a failed upstream handshake.
+pub fn describe_connect_error(err: &anyhow::Error) -> String {
+    match err.downcast_ref::<tungstenite::Error>() {
+        Some(tungstenite::Error::Http(response)) => {
+            format!(
+                "upstream rejected realtime session: HTTP {}",
+                response.status()
+            )
+        }
+        _ => format!("upstream realtime connection failed: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_sums_response_done_events() {
+        let mut usage = RealtimeUsage::default();
+        usage.record_event(r#"{"type":"response.created","response":{}}"#);
+        usage.record_event(
+            r#"{"type":"response.done","response":{"usage":{"input_tokens":10,"output_tokens":4}}}"#,
+        );
+        usage.record_event(
+            r#"{"type":"response.done","response":{"usage":{"input_tokens":3,"output_tokens":2}}}"#,
+        );
+        assert_eq!(
+            usage,
+            RealtimeUsage {
+                input_tokens: 13,
+                output_tokens: 6
+            }
+        );
+    }
+}
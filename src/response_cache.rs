@@ -0,0 +1,387 @@
+//! Opt-in cache for identical non-streaming requests (see
+//! `config::ResponseCacheConfig`).
+//!
+//! Every router with a `cache` section gets its own moka cache sized and
+//! expired by that section. Entries are keyed on the caller's team, the
+//! inbound protocol and the request body with its object keys sorted, so
+//! equal JSON that was merely re-serialized still hits. Any config change
+//! drops every cache: a new model map or channel may change the answer.
+//!
+//! With `semantic` configured, an exact miss falls back to the cached
+//! response whose last user message is closest by embedding (cosine
+//! similarity at or above `threshold`), among entries whose request is
+//! otherwise identical — same team, protocol, model, system prompt, history
+//! and parameters.
+
+use crate::config::ResponseCacheConfig;
+use crate::providers::RouteKind;
+use axum::body::{Body, Bytes};
+use axum::http::{HeaderMap, HeaderValue, Response, StatusCode};
+use moka::sync::Cache;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Response header telling clients whether the cache answered.
+pub const CACHE_HEADER: &str = "x-apex-cache";
+/// Cached bodies are buffered in memory; larger responses are passed through.
+const MAX_CACHED_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct ResponseCaches {
+    routers: Mutex<HashMap<String, RouterCache>>,
+}
+
+struct RouterCache {
+    settings: ResponseCacheConfig,
+    entries: Cache<String, Arc<CachedResponse>>,
+}
+
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    semantic: Option<SemanticKey>,
+}
+
+/// Embedded last user message plus everything else the request said.
+pub struct SemanticKey {
+    pub context: String,
+    pub embedding: Vec<f32>,
+}
+
+impl ResponseCaches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop every cached response (called on each config change).
+    pub fn clear(&self) {
+        self.routers.lock().unwrap().clear();
+    }
+
+    /// Cached response for `key`, marked with `x-apex-cache: hit`.
+    pub fn get(
+        &self,
+        router: &str,
+        settings: &ResponseCacheConfig,
+        key: &str,
+    ) -> Option<Response<Body>> {
+        let cached = self.entries(router, settings).get(key)?;
+        replay(&cached, "hit")
+    }
+
+    /// Most similar cached response for the same request context, marked
+    /// with `x-apex-cache: semantic-hit`.
+    pub fn get_similar(
+        &self,
+        router: &str,
+        settings: &ResponseCacheConfig,
+        threshold: f64,
+        probe: &SemanticKey,
+    ) -> Option<Response<Body>> {
+        let best = self
+            .entries(router, settings)
+            .iter()
+            .filter_map(|(_, cached)| {
+                let key = cached.semantic.as_ref()?;
+                if key.context != probe.context {
+                    return None;
+                }
+                let similarity = cosine_similarity(&key.embedding, &probe.embedding)?;
+                (similarity >= threshold).then_some((similarity, cached))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        tracing::debug!("Response Cache: semantic match at similarity {:.4}", best.0);
+        replay(&best.1, "semantic-hit")
+    }
+
+    /// Remember a successful non-streaming response and hand it back to the
+    /// caller, marked with `x-apex-cache: miss`. Binary bodies (generated
+    /// speech) are passed through uncached.
+    pub async fn store(
+        &self,
+        router: &str,
+        settings: &ResponseCacheConfig,
+        key: String,
+        semantic: Option<SemanticKey>,
+        response: Response<Body>,
+    ) -> Response<Body> {
+        if response.status() != StatusCode::OK
+            || crate::utils::is_event_stream(response.headers())
+            || crate::utils::is_binary_body(response.headers())
+        {
+            return response;
+        }
+        let (mut parts, body) = response.into_parts();
+        let body = match axum::body::to_bytes(body, MAX_CACHED_BODY_BYTES).await {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::warn!("Response Cache: response not cached: {}", err);
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+        parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+        self.entries(router, settings).insert(
+            key,
+            Arc::new(CachedResponse {
+                status: parts.status,
+                headers: parts.headers.clone(),
+                body: body.clone(),
+                semantic,
+            }),
+        );
+        parts
+            .headers
+            .insert(CACHE_HEADER, HeaderValue::from_static("miss"));
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    fn entries(
+        &self,
+        router: &str,
+        settings: &ResponseCacheConfig,
+    ) -> Cache<String, Arc<CachedResponse>> {
+        let mut routers = self.routers.lock().unwrap();
+        match routers.get(router) {
+            Some(cache) if cache.settings == *settings => cache.entries.clone(),
+            _ => {
+                let entries = Cache::builder()
+                    .max_capacity(settings.max_entries)
+                    .time_to_live(Duration::from_secs(settings.ttl_secs.max(1)))
+                    .build();
+                routers.insert(
+                    router.to_string(),
+                    RouterCache {
+                        settings: settings.clone(),
+                        entries: entries.clone(),
+                    },
+                );
+                entries
+            }
+        }
+    }
+}
+
+/// Cache key for a request, or `None` when it must not be cached (streaming,
+/// Gemini native routes or a body that is not a JSON object).
+pub fn cache_key(team_id: &str, route: RouteKind, body: &Bytes) -> Option<String> {
+    if matches!(route, RouteKind::GeminiNative) {
+        return None;
+    }
+    let value: Value = serde_json::from_slice(body).ok()?;
+    if !value.is_object() || value.get("stream").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    // `serde_json::Map` is ordered by key, so this is a canonical form.
+    Some(format!("{team_id}\n{}\n{value}", route.as_str()))
+}
+
+/// Text of the last user message and the canonical rest of the request, or
+/// `None` when there is no user text to compare.
+pub fn semantic_parts(team_id: &str, route: RouteKind, body: &Bytes) -> Option<(String, String)> {
+    let mut value: Value = serde_json::from_slice(body).ok()?;
+    let message = value
+        .get_mut("messages")?
+        .as_array_mut()?
+        .iter_mut()
+        .rev()
+        .find(|message| message.get("role").and_then(Value::as_str) == Some("user"))?;
+    let text = match message.get("content")? {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    if text.trim().is_empty() {
+        return None;
+    }
+    message["content"] = Value::Null;
+    Some((text, format!("{team_id}\n{}\n{value}", route.as_str())))
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+fn replay(cached: &CachedResponse, result: &'static str) -> Option<Response<Body>> {
+    let mut builder = Response::builder().status(cached.status);
+    for (name, value) in cached.headers.iter() {
+        builder = builder.header(name, value);
+    }
+    builder
+        .header(CACHE_HEADER, result)
+        .body(Body::from(cached.body.clone()))
+        .ok()
+}
+
+/// `Cache-Control: no-cache` / `no-store` on the request skips the lookup.
+pub fn bypass_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(axum::http::header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| {
+            let directive = directive.trim();
+            directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_field_order_and_skips_streams() {
+        let a = cache_key(
+            "t",
+            RouteKind::Openai,
+            &Bytes::from(r#"{"model":"m","messages":[{"role":"user","content":"hi"}]}"#),
+        );
+        let b = cache_key(
+            "t",
+            RouteKind::Openai,
+            &Bytes::from(r#"{ "messages":[{"content":"hi","role":"user"}], "model":"m" }"#),
+        );
+        assert!(a.is_some());
+        assert_eq!(a, b);
+        assert_ne!(
+            a,
+            cache_key(
+                "other",
+                RouteKind::Openai,
+                &Bytes::from(r#"{"model":"m","messages":[{"role":"user","content":"hi"}]}"#)
+            )
+        );
+        assert!(
+            cache_key(
+                "t",
+                RouteKind::Openai,
+                &Bytes::from(r#"{"model":"m","stream":true}"#)
+            )
+            .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn stores_and_replays_ok_responses() {
+        let caches = ResponseCaches::new();
+        let settings = ResponseCacheConfig {
+            ttl_secs: 60,
+            max_entries: 10,
+            semantic: None,
+        };
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"id":"1"}"#))
+            .unwrap();
+        let response = caches
+            .store("r", &settings, "k".to_string(), None, response)
+            .await;
+        assert_eq!(response.headers()[CACHE_HEADER], "miss");
+
+        let hit = caches.get("r", &settings, "k").unwrap();
+        assert_eq!(hit.headers()[CACHE_HEADER], "hit");
+        assert_eq!(hit.headers()["content-type"], "application/json");
+        let body = axum::body::to_bytes(hit.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, Bytes::from(r#"{"id":"1"}"#));
+
+        caches.clear();
+        assert!(caches.get("r", &settings, "k").is_none());
+    }
+
+    #[tokio::test]
+    async fn similar_prompts_hit_within_the_same_context() {
+        let caches = ResponseCaches::new();
+        let settings = ResponseCacheConfig {
+            ttl_secs: 60,
+            max_entries: 10,
+            semantic: None,
+        };
+        let body = |text: &str, model: &str| {
+            Bytes::from(
+                serde_json::json!({"model": model, "messages": [
+                    {"role": "system", "content": "be brief"},
+                    {"role": "user", "content": text}
+                ]})
+                .to_string(),
+            )
+        };
+        let (text, context) =
+            semantic_parts("t", RouteKind::Openai, &body("What is Rust?", "m")).unwrap();
+        assert_eq!(text, "What is Rust?");
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::from("answer"))
+            .unwrap();
+        caches
+            .store(
+                "r",
+                &settings,
+                "k".to_string(),
+                Some(SemanticKey {
+                    context,
+                    embedding: vec![1.0, 0.0, 0.2],
+                }),
+                response,
+            )
+            .await;
+
+        let (_, same_context) =
+            semantic_parts("t", RouteKind::Openai, &body("what's rust", "m")).unwrap();
+        let (_, other_model) =
+            semantic_parts("t", RouteKind::Openai, &body("what's rust", "m2")).unwrap();
+        let probe = |context: String, embedding: Vec<f32>| SemanticKey { context, embedding };
+
+        let hit = caches
+            .get_similar(
+                "r",
+                &settings,
+                0.95,
+                &probe(same_context.clone(), vec![0.98, 0.05, 0.2]),
+            )
+            .unwrap();
+        assert_eq!(hit.headers()[CACHE_HEADER], "semantic-hit");
+        assert!(
+            caches
+                .get_similar(
+                    "r",
+                    &settings,
+                    0.95,
+                    &probe(same_context, vec![0.0, 1.0, 0.0])
+                )
+                .is_none()
+        );
+        assert!(
+            caches
+                .get_similar(
+                    "r",
+                    &settings,
+                    0.95,
+                    &probe(other_model, vec![1.0, 0.0, 0.2])
+                )
+                .is_none()
+        );
+    }
+}
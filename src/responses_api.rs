@@ -0,0 +1,759 @@
+//! OpenAI Responses API (`/v1/responses`) on top of chat completions.
+//!
+//! Channels that speak the Responses API natively (plain OpenAI) get the
+//! request untouched. For every other channel the request is rewritten as a
+//! `chat/completions` call before the provider adapter sees it, and the
+//! adapter's OpenAI chat response (plain or SSE) is turned back into a
+//! Responses object / event stream. Stateful features (`previous_response_id`,
+//! stored responses) need the native API and are rejected here.
+
+use crate::config::{Channel, ProviderType};
+use axum::body::{Body, Bytes};
+use axum::http::{HeaderValue, Response, header};
+use futures::{Stream, StreamExt, stream};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Chat completions path the rewritten request is sent to.
+pub const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+/// Whether a gateway path is the Responses endpoint.
+pub fn is_responses_path(path: &str) -> bool {
+    matches!(path.trim_end_matches('/'), "/v1/responses" | "/responses")
+}
+
+/// Whether `/v1/responses` can be forwarded to the channel unchanged.
+pub fn channel_supports_responses(channel: &Channel) -> bool {
+    channel.provider_type == ProviderType::Openai && !channel.anthropic_only()
+}
+
+/// Rewrite a Responses request body as a chat completions request.
+pub fn convert_responses_to_chat(body: &Bytes) -> anyhow::Result<Bytes> {
+    let request: Value = serde_json::from_slice(body)?;
+    if !request.is_object() {
+        anyhow::bail!("request body must be a JSON object");
+    }
+    if request
+        .get("previous_response_id")
+        .is_some_and(|v| !v.is_null())
+    {
+        anyhow::bail!("previous_response_id requires a channel with native Responses API support");
+    }
+
+    let mut messages: Vec<Value> = Vec::new();
+    if let Some(instructions) = request.get("instructions").and_then(Value::as_str) {
+        messages.push(json!({"role": "system", "content": instructions}));
+    }
+    match request.get("input") {
+        Some(Value::String(text)) => messages.push(json!({"role": "user", "content": text})),
+        Some(Value::Array(items)) => {
+            for item in items {
+                append_input_item(&mut messages, item);
+            }
+        }
+        _ => {}
+    }
+
+    let mut chat = Map::new();
+    if let Some(model) = request.get("model") {
+        chat.insert("model".to_string(), model.clone());
+    }
+    chat.insert("messages".to_string(), Value::Array(messages));
+    for key in [
+        "temperature",
+        "top_p",
+        "parallel_tool_calls",
+        "user",
+        "stream",
+    ] {
+        if let Some(value) = request.get(key).filter(|v| !v.is_null()) {
+            chat.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(max_tokens) = request.get("max_output_tokens").filter(|v| !v.is_null()) {
+        chat.insert("max_tokens".to_string(), max_tokens.clone());
+    }
+    if let Some(effort) = request
+        .pointer("/reasoning/effort")
+        .filter(|v| !v.is_null())
+    {
+        chat.insert("reasoning_effort".to_string(), effort.clone());
+    }
+    if let Some(format) = request.pointer("/text/format") {
+        match format.get("type").and_then(Value::as_str) {
+            Some("json_schema") => {
+                let mut schema = format.as_object().cloned().unwrap_or_default();
+                schema.remove("type");
+                chat.insert(
+                    "response_format".to_string(),
+                    json!({"type": "json_schema", "json_schema": schema}),
+                );
+            }
+            Some("json_object") => {
+                chat.insert(
+                    "response_format".to_string(),
+                    json!({"type": "json_object"}),
+                );
+            }
+            _ => {}
+        }
+    }
+    if let Some(tools) = request.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter(|tool| tool.get("type").and_then(Value::as_str) == Some("function"))
+            .map(|tool| {
+                let mut function = tool.as_object().cloned().unwrap_or_default();
+                function.remove("type");
+                json!({"type": "function", "function": function})
+            })
+            .collect();
+        if !tools.is_empty() {
+            chat.insert("tools".to_string(), Value::Array(tools));
+        }
+    }
+    match request.get("tool_choice") {
+        Some(Value::String(choice)) => {
+            chat.insert("tool_choice".to_string(), json!(choice));
+        }
+        Some(choice) if choice.get("type").and_then(Value::as_str) == Some("function") => {
+            chat.insert(
+                "tool_choice".to_string(),
+                json!({"type": "function", "function": {"name": choice.get("name")}}),
+            );
+        }
+        _ => {}
+    }
+    if chat.get("stream").and_then(Value::as_bool) == Some(true) {
+        chat.insert("stream_options".to_string(), json!({"include_usage": true}));
+    }
+
+    Ok(Bytes::from(serde_json::to_vec(&Value::Object(chat))?))
+}
+
+fn append_input_item(messages: &mut Vec<Value>, item: &Value) {
+    let kind = item
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or(if item.get("role").is_some() {
+            "message"
+        } else {
+            ""
+        });
+    match kind {
+        "message" => {
+            let role = match item.get("role").and_then(Value::as_str) {
+                Some("developer") | Some("system") => "system",
+                Some("assistant") => "assistant",
+                _ => "user",
+            };
+            let content = match item.get("content") {
+                Some(Value::Array(parts)) if role == "user" => {
+                    Value::Array(parts.iter().filter_map(convert_content_part).collect())
+                }
+                Some(Value::Array(parts)) => Value::String(
+                    parts
+                        .iter()
+                        .filter_map(|part| {
+                            part.get("text")
+                                .or_else(|| part.get("refusal"))
+                                .and_then(Value::as_str)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(""),
+                ),
+                Some(content) => content.clone(),
+                None => Value::Null,
+            };
+            messages.push(json!({"role": role, "content": content}));
+        }
+        "function_call" => {
+            let call = json!({
+                "id": item.get("call_id"),
+                "type": "function",
+                "function": {
+                    "name": item.get("name"),
+                    "arguments": item.get("arguments").and_then(Value::as_str).unwrap_or("{}"),
+                }
+            });
+            match messages.last_mut() {
+                Some(last) if last.get("role").and_then(Value::as_str) == Some("assistant") => {
+                    let calls = last
+                        .as_object_mut()
+                        .unwrap()
+                        .entry("tool_calls")
+                        .or_insert_with(|| Value::Array(Vec::new()));
+                    if let Some(calls) = calls.as_array_mut() {
+                        calls.push(call);
+                    }
+                }
+                _ => messages.push(json!({
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [call],
+                })),
+            }
+        }
+        "function_call_output" => {
+            let output = match item.get("output") {
+                Some(Value::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+                None => String::new(),
+            };
+            messages.push(json!({
+                "role": "tool",
+                "tool_call_id": item.get("call_id"),
+                "content": output,
+            }));
+        }
+        // Reasoning items and references to stored items have no chat
+        // equivalent.
+        _ => {}
+    }
+}
+
+fn convert_content_part(part: &Value) -> Option<Value> {
+    match part.get("type").and_then(Value::as_str)? {
+        "input_text" | "output_text" | "text" => {
+            Some(json!({"type": "text", "text": part.get("text")?}))
+        }
+        "input_image" => {
+            let mut image = Map::new();
+            image.insert("url".to_string(), part.get("image_url")?.clone());
+            if let Some(detail) = part.get("detail") {
+                image.insert("detail".to_string(), detail.clone());
+            }
+            Some(json!({"type": "image_url", "image_url": image}))
+        }
+        "input_file" => {
+            let mut file = part.as_object()?.clone();
+            file.remove("type");
+            Some(json!({"type": "file", "file": file}))
+        }
+        _ => None,
+    }
+}
+
+/// Turn a successful chat completions response from the adapter into its
+/// Responses API equivalent (JSON or event stream).
+pub async fn convert_chat_response(response: Response<Body>) -> Response<Body> {
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if is_stream {
+        let stream = convert_chat_stream_to_responses(body.into_data_stream());
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::warn!("Responses API: failed to read chat response: {}", err);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(convert_chat_completion(bytes)))
+}
+
+/// Non-streaming chat completion -> Responses object. Bodies without
+/// `choices` (errors) are returned unchanged.
+pub fn convert_chat_completion(body: Bytes) -> Bytes {
+    let Ok(chat) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    let Some(choice) = chat.pointer("/choices/0") else {
+        return body;
+    };
+    let id = chat.get("id").and_then(Value::as_str).unwrap_or_default();
+    let message = choice.get("message").cloned().unwrap_or(Value::Null);
+    let mut output = Vec::new();
+    let text = message
+        .get("content")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !text.is_empty() {
+        output.push(message_item(id, text, "completed"));
+    }
+    for call in message
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        output.push(function_call_item(
+            call.get("id").and_then(Value::as_str).unwrap_or_default(),
+            call.pointer("/function/name")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+            call.pointer("/function/arguments")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+            "completed",
+        ));
+    }
+    let response = response_object(
+        id,
+        chat.get("created").and_then(Value::as_i64).unwrap_or(0),
+        chat.get("model").cloned().unwrap_or(Value::Null),
+        choice.get("finish_reason").and_then(Value::as_str),
+        output,
+        chat.get("usage").map(map_usage),
+    );
+    Bytes::from(response.to_string())
+}
+
+fn response_id(chat_id: &str) -> String {
+    format!(
+        "resp_{}",
+        chat_id.strip_prefix("chatcmpl-").unwrap_or(chat_id)
+    )
+}
+
+fn message_item(chat_id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "id": format!("msg_{}", chat_id.strip_prefix("chatcmpl-").unwrap_or(chat_id)),
+        "type": "message",
+        "status": status,
+        "role": "assistant",
+        "content": [{"type": "output_text", "text": text, "annotations": []}],
+    })
+}
+
+fn function_call_item(call_id: &str, name: &str, arguments: &str, status: &str) -> Value {
+    json!({
+        "id": format!("fc_{call_id}"),
+        "type": "function_call",
+        "status": status,
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments,
+    })
+}
+
+fn response_object(
+    chat_id: &str,
+    created: i64,
+    model: Value,
+    finish_reason: Option<&str>,
+    output: Vec<Value>,
+    usage: Option<Value>,
+) -> Value {
+    let incomplete = match finish_reason {
+        Some("length") => Some("max_output_tokens"),
+        Some("content_filter") => Some("content_filter"),
+        _ => None,
+    };
+    json!({
+        "id": response_id(chat_id),
+        "object": "response",
+        "created_at": created,
+        "status": if incomplete.is_some() { "incomplete" } else { "completed" },
+        "incomplete_details": incomplete.map(|reason| json!({"reason": reason})),
+        "model": model,
+        "output": output,
+        "usage": usage,
+    })
+}
+
+fn map_usage(usage: &Value) -> Value {
+    let count = |pointer: &str| usage.pointer(pointer).and_then(Value::as_u64).unwrap_or(0);
+    let input = count("/prompt_tokens");
+    let output = count("/completion_tokens");
+    json!({
+        "input_tokens": input,
+        "input_tokens_details": {"cached_tokens": count("/prompt_tokens_details/cached_tokens")},
+        "output_tokens": output,
+        "output_tokens_details": {
+            "reasoning_tokens": count("/completion_tokens_details/reasoning_tokens")
+        },
+        "total_tokens": input + output,
+    })
+}
+
+/// Chat completion chunks (SSE) -> Responses API events.
+pub fn convert_chat_stream_to_responses<S, E>(
+    stream: S,
+) -> impl Stream<Item = Result<Bytes, io::Error>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let state = (stream, Vec::new(), ResponsesStreamState::default(), false);
+    stream::unfold(
+        state,
+        |(mut stream, mut buffer, mut state, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                let mut output = String::new();
+                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    output.push_str(&state.convert_line(&String::from_utf8_lossy(&line)));
+                }
+                if !output.is_empty() {
+                    return Some((Ok(Bytes::from(output)), (stream, buffer, state, false)));
+                }
+
+                match stream.next().await {
+                    Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                    Some(Err(err)) => {
+                        return Some((Err(io::Error::other(err)), (stream, buffer, state, true)));
+                    }
+                    None => {
+                        let rest = String::from_utf8_lossy(&buffer).to_string();
+                        let mut output = state.convert_line(&rest);
+                        output.push_str(&state.finish());
+                        return Some((Ok(Bytes::from(output)), (stream, Vec::new(), state, true)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[derive(Default)]
+struct ResponsesStreamState {
+    id: String,
+    model: Value,
+    created: i64,
+    started: bool,
+    done: bool,
+    sequence: u64,
+    next_output_index: usize,
+    text: Option<(usize, String)>,
+    /// Chat tool call index -> (output index, call id, name, arguments).
+    tool_calls: BTreeMap<u64, (usize, String, String, String)>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+}
+
+impl ResponsesStreamState {
+    fn convert_line(&mut self, line: &str) -> String {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return String::new();
+        };
+        let data = data.trim();
+        if data == "[DONE]" {
+            return self.finish();
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return String::new();
+        };
+        let mut out = String::new();
+        if !self.started {
+            self.started = true;
+            self.id = chunk
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            self.model = chunk.get("model").cloned().unwrap_or(Value::Null);
+            self.created = chunk.get("created").and_then(Value::as_i64).unwrap_or(0);
+            let mut response = response_object(
+                &self.id,
+                self.created,
+                self.model.clone(),
+                None,
+                vec![],
+                None,
+            );
+            response["status"] = json!("in_progress");
+            out.push_str(&self.event("response.created", json!({"response": response})));
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(map_usage(usage));
+        }
+        let Some(choice) = chunk.pointer("/choices/0") else {
+            return out;
+        };
+        if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+            self.finish_reason = Some(reason.to_string());
+        }
+        let delta = choice.get("delta").cloned().unwrap_or(Value::Null);
+
+        if let Some(text) = delta
+            .get("content")
+            .and_then(Value::as_str)
+            .filter(|t| !t.is_empty())
+        {
+            let item_id = self.message_id();
+            if self.text.is_none() {
+                let output_index = self.next_output_index;
+                self.next_output_index += 1;
+                self.text = Some((output_index, String::new()));
+                let mut item = message_item(&self.id, "", "in_progress");
+                item["content"] = json!([]);
+                out.push_str(&self.event(
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": item}),
+                ));
+                out.push_str(&self.event(
+                    "response.content_part.added",
+                    json!({
+                        "item_id": item_id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": "", "annotations": []},
+                    }),
+                ));
+            }
+            let (output_index, buffered) = self.text.as_mut().unwrap();
+            buffered.push_str(text);
+            let output_index = *output_index;
+            out.push_str(&self.event(
+                "response.output_text.delta",
+                json!({
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "delta": text,
+                }),
+            ));
+        }
+
+        for call in delta
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let index = call.get("index").and_then(Value::as_u64).unwrap_or(0);
+            if !self.tool_calls.contains_key(&index) {
+                let output_index = self.next_output_index;
+                self.next_output_index += 1;
+                let call_id = call
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_{}_{}", self.id, index));
+                let name = call
+                    .pointer("/function/name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let item = function_call_item(&call_id, &name, "", "in_progress");
+                self.tool_calls
+                    .insert(index, (output_index, call_id, name, String::new()));
+                out.push_str(&self.event(
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": item}),
+                ));
+            }
+            if let Some(arguments) = call
+                .pointer("/function/arguments")
+                .and_then(Value::as_str)
+                .filter(|a| !a.is_empty())
+            {
+                let (output_index, call_id, _, buffered) = self.tool_calls.get_mut(&index).unwrap();
+                buffered.push_str(arguments);
+                let (output_index, item_id) = (*output_index, format!("fc_{call_id}"));
+                out.push_str(&self.event(
+                    "response.function_call_arguments.delta",
+                    json!({"item_id": item_id, "output_index": output_index, "delta": arguments}),
+                ));
+            }
+        }
+        out
+    }
+
+    /// Close open output items and emit the terminal `response.completed`
+    /// (or `response.incomplete`) event.
+    fn finish(&mut self) -> String {
+        if !self.started || self.done {
+            return String::new();
+        }
+        self.done = true;
+        let mut items: Vec<(usize, Value)> = Vec::new();
+        let mut out = String::new();
+        if let Some((output_index, text)) = self.text.take() {
+            let item_id = self.message_id();
+            out.push_str(&self.event(
+                "response.output_text.done",
+                json!({
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "text": text,
+                }),
+            ));
+            out.push_str(&self.event(
+                "response.content_part.done",
+                json!({
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": {"type": "output_text", "text": text, "annotations": []},
+                }),
+            ));
+            items.push((output_index, message_item(&self.id, &text, "completed")));
+        }
+        for (output_index, call_id, name, arguments) in
+            std::mem::take(&mut self.tool_calls).into_values()
+        {
+            out.push_str(&self.event(
+                "response.function_call_arguments.done",
+                json!({
+                    "item_id": format!("fc_{call_id}"),
+                    "output_index": output_index,
+                    "arguments": arguments,
+                }),
+            ));
+            items.push((
+                output_index,
+                function_call_item(&call_id, &name, &arguments, "completed"),
+            ));
+        }
+        items.sort_by_key(|(index, _)| *index);
+        for (output_index, item) in &items {
+            out.push_str(&self.event(
+                "response.output_item.done",
+                json!({"output_index": output_index, "item": item}),
+            ));
+        }
+        let response = response_object(
+            &self.id,
+            self.created,
+            self.model.clone(),
+            self.finish_reason.as_deref(),
+            items.into_iter().map(|(_, item)| item).collect(),
+            self.usage.clone(),
+        );
+        let kind = if response["status"] == "incomplete" {
+            "response.incomplete"
+        } else {
+            "response.completed"
+        };
+        out.push_str(&self.event(kind, json!({"response": response})));
+        out
+    }
+
+    fn message_id(&self) -> String {
+        format!(
+            "msg_{}",
+            self.id.strip_prefix("chatcmpl-").unwrap_or(&self.id)
+        )
+    }
+
+    fn event(&mut self, kind: &str, mut data: Value) -> String {
+        data["type"] = json!(kind);
+        data["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        format!("event: {kind}\ndata: {data}\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_maps_input_items_to_chat_messages() {
+        let body = Bytes::from(
+            json!({
+                "model": "m",
+                "instructions": "be brief",
+                "max_output_tokens": 64,
+                "input": [
+                    {"role": "user", "content": [{"type": "input_text", "text": "weather?"}]},
+                    {"type": "function_call", "call_id": "c1", "name": "get_weather", "arguments": "{}"},
+                    {"type": "function_call_output", "call_id": "c1", "output": "sunny"}
+                ],
+                "tools": [{"type": "function", "name": "get_weather", "parameters": {"type": "object"}}],
+                "tool_choice": {"type": "function", "name": "get_weather"},
+                "text": {"format": {"type": "json_schema", "name": "w", "schema": {}}},
+                "stream": true
+            })
+            .to_string(),
+        );
+        let chat: Value =
+            serde_json::from_slice(&convert_responses_to_chat(&body).unwrap()).unwrap();
+        assert_eq!(chat["max_tokens"], 64);
+        assert_eq!(
+            chat["messages"][0],
+            json!({"role": "system", "content": "be brief"})
+        );
+        assert_eq!(chat["messages"][1]["content"][0]["text"], "weather?");
+        assert_eq!(chat["messages"][2]["tool_calls"][0]["id"], "c1");
+        assert_eq!(chat["messages"][3]["role"], "tool");
+        assert_eq!(chat["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(chat["tool_choice"]["function"]["name"], "get_weather");
+        assert_eq!(chat["response_format"]["json_schema"]["name"], "w");
+        assert_eq!(chat["stream_options"]["include_usage"], true);
+
+        let stateful = Bytes::from(r#"{"model":"m","input":"hi","previous_response_id":"resp_1"}"#);
+        assert!(convert_responses_to_chat(&stateful).is_err());
+    }
+
+    #[test]
+    fn completion_maps_to_response_object() {
+        let chat = json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "m",
+            "choices": [{"index": 0, "finish_reason": "stop", "message": {
+                "role": "assistant",
+                "content": "hello",
+                "tool_calls": [{"id": "c1", "type": "function", "function": {"name": "f", "arguments": "{}"}}]
+            }}],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+        });
+        let response: Value =
+            serde_json::from_slice(&convert_chat_completion(Bytes::from(chat.to_string())))
+                .unwrap();
+        assert_eq!(response["id"], "resp_1");
+        assert_eq!(response["status"], "completed");
+        assert_eq!(response["output"][0]["content"][0]["text"], "hello");
+        assert_eq!(response["output"][1]["call_id"], "c1");
+        assert_eq!(response["usage"]["input_tokens"], 3);
+        assert_eq!(response["usage"]["total_tokens"], 5);
+    }
+
+    #[tokio::test]
+    async fn stream_emits_response_events() {
+        let chunks = [
+            r#"data: {"id":"chatcmpl-1","created":1,"model":"m","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"data: {"id":"chatcmpl-1","created":1,"model":"m","choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+            r#"data: {"id":"chatcmpl-1","created":1,"model":"m","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2}}"#,
+            "data: [DONE]",
+        ];
+        let input = stream::iter(
+            chunks
+                .into_iter()
+                .map(|c| Ok::<_, io::Error>(Bytes::from(format!("{c}\n\n")))),
+        );
+        let output: Vec<u8> = convert_chat_stream_to_responses(input)
+            .map(|chunk| chunk.unwrap().to_vec())
+            .concat()
+            .await;
+        let output = String::from_utf8(output).unwrap();
+        let kinds: Vec<&str> = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "response.created",
+                "response.output_item.added",
+                "response.content_part.added",
+                "response.output_text.delta",
+                "response.output_text.delta",
+                "response.output_text.done",
+                "response.content_part.done",
+                "response.output_item.done",
+                "response.completed",
+            ]
+        );
+        assert!(output.contains(r#""text":"Hello""#), "{output}");
+        assert!(output.contains(r#""input_tokens":3"#), "{output}");
+    }
+}
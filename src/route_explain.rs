@@ -0,0 +1,375 @@
+//! Dry-run routing (`apex route explain`, `GET /admin/route/explain`).
+//!
+//! Walks the routers a request for `model` would try, in the same order as
+//! the gateway, and reports for each one which rule patterns match, the
+//! matched rule's targets with their weights and health, and the channel
+//! the next request would get. Model aliases are resolved per router, as
+//! the gateway does. Nothing is sent upstream and no round-robin cursor is
+//! advanced.
+
+use crate::config::{Config, Router};
+use crate::router_selector::{RouterSelector, pattern_match, spec_matches};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct RouteExplanation {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// Router that would serve the request (first one with a match).
+    pub router: Option<String>,
+    /// Matched rule of that router, as `pattern | pattern`.
+    pub rule: Option<String>,
+    /// Channel the next request would get; `None` for random picks.
+    pub channel: Option<String>,
+    pub reason: String,
+    pub routers: Vec<RouterExplanation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouterExplanation {
+    pub router: String,
+    pub rules: Vec<RuleExplanation>,
+    pub matched_rule: Option<usize>,
+    pub strategy: Option<String>,
+    pub candidates: Vec<CandidateExplanation>,
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback_channels: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleExplanation {
+    pub index: usize,
+    pub patterns: Vec<String>,
+    /// First pattern matching the model, and whether it matched `exact` or
+    /// by `glob`.
+    pub matched_pattern: Option<String>,
+    pub match_kind: Option<&'static str>,
+    /// The rule also has request conditions (headers, paths, ...), which a
+    /// dry run cannot check; it is reported as matching when the model does.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub conditional: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CandidateExplanation {
+    pub channel: String,
+    pub weight: u32,
+    /// Percentage of the rule's total weight.
+    pub share: f64,
+    pub ejected: bool,
+    pub saturated: bool,
+}
+
+/// Explain how a request for `model` would be routed, optionally pinned to
+/// `router` or made on behalf of `team` (its `allowed_routers`, in order).
+pub fn explain(
+    selector: &RouterSelector,
+    config: &Config,
+    model: &str,
+    router: Option<&str>,
+    team: Option<&str>,
+) -> anyhow::Result<RouteExplanation> {
+    let mut explanation = RouteExplanation {
+        model: model.to_string(),
+        team: team.map(str::to_string),
+        router: None,
+        rule: None,
+        channel: None,
+        reason: String::new(),
+        routers: Vec::new(),
+    };
+
+    let candidates: Vec<&Router> = match (router, team) {
+        (Some(name), _) => vec![
+            config
+                .routers
+                .iter()
+                .find(|r| r.name == name)
+                .ok_or_else(|| anyhow::anyhow!("Router '{}' not found", name))?,
+        ],
+        (None, Some(id)) => {
+            let team = config
+                .teams
+                .iter()
+                .find(|t| t.id == id)
+                .ok_or_else(|| anyhow::anyhow!("Team '{}' not found", id))?;
+            let policy_model = config.model_alias(None, model).unwrap_or(model);
+            if !team.policy.is_model_allowed(policy_model) {
+                explanation.reason =
+                    format!("Model '{model}' is not allowed by team '{id}' policy");
+                return Ok(explanation);
+            }
+            team.policy
+                .allowed_routers
+                .iter()
+                .filter_map(|name| config.routers.iter().find(|r| r.name == *name))
+                .collect()
+        }
+        (None, None) => config
+            .routers
+            .iter()
+            .filter(|r| config.tenant_of(&r.name).is_none())
+            .collect(),
+    };
+
+    for router in candidates {
+        let router_explanation = explain_router(
+            selector,
+            router,
+            config.model_alias(Some(router), model).unwrap_or(model),
+        );
+        if explanation.router.is_none()
+            && let Some(index) = router_explanation.matched_rule
+            && !router_explanation.candidates.is_empty()
+        {
+            let rule = &router_explanation.rules[index];
+            explanation.router = Some(router.name.clone());
+            explanation.rule = Some(rule.patterns.join(" | "));
+            explanation.channel = router_explanation.channel.clone();
+            let strategy = router_explanation.strategy.as_deref().unwrap_or_default();
+            let pick = match &router_explanation.channel {
+                Some(channel) => format!("{strategy} picks '{channel}'"),
+                None => format!(
+                    "{strategy} picks at random by weight among {} channel(s)",
+                    router_explanation.candidates.len()
+                ),
+            };
+            let matched = match (&rule.matched_pattern, rule.match_kind) {
+                (Some(pattern), Some(kind)) => format!("matched '{pattern}' ({kind})"),
+                _ => "matches any model".to_string(),
+            };
+            let conditions = if rule.conditional {
+                " if its request conditions hold"
+            } else {
+                ""
+            };
+            explanation.reason = format!(
+                "Router '{}' rule #{} {}{}; {}",
+                router.name, index, matched, conditions, pick
+            );
+        }
+        explanation.routers.push(router_explanation);
+    }
+    if explanation.router.is_none() {
+        explanation.reason = format!("No router has a rule matching model '{model}'");
+    }
+    Ok(explanation)
+}
+
+fn explain_router(selector: &RouterSelector, router: &Router, model: &str) -> RouterExplanation {
+    let rules: Vec<RuleExplanation> = router
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let matched = rule
+                .match_spec
+                .models
+                .iter()
+                .find_map(|pattern| Some((pattern.clone(), pattern_match(pattern, model)?)));
+            RuleExplanation {
+                index,
+                patterns: rule.match_spec.models.clone(),
+                matched_pattern: matched.as_ref().map(|(pattern, _)| pattern.clone()),
+                match_kind: matched.map(|(_, kind)| kind),
+                conditional: rule.match_spec.has_request_conditions(),
+            }
+        })
+        .collect();
+    let matched_rule = router
+        .rules
+        .iter()
+        .position(|rule| spec_matches(&rule.match_spec, model, None));
+    let matched = matched_rule.map(|index| &router.rules[index]);
+    let total_weight: u32 = matched.map_or(0, |rule| rule.channels.iter().map(|c| c.weight).sum());
+    let candidates = matched
+        .map(|rule| {
+            rule.channels
+                .iter()
+                .map(|target| {
+                    let (ejected, saturated) = selector.channel_state(&target.name);
+                    CandidateExplanation {
+                        channel: target.name.clone(),
+                        weight: target.weight,
+                        share: if total_weight == 0 {
+                            0.0
+                        } else {
+                            (target.weight as f64 * 1000.0 / total_weight as f64).round() / 10.0
+                        },
+                        ejected,
+                        saturated,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    RouterExplanation {
+        router: router.name.clone(),
+        rules,
+        matched_rule,
+        strategy: matched.map(|rule| rule.strategy.clone()),
+        candidates,
+        channel: matched_rule.and_then(|index| selector.preview_channel(router, index, model)),
+        fallback_channels: router.fallback_channel_names().cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MatchSpec, RouterRule, TargetChannel};
+
+    /// `(pattern, strategy, [(channel, weight)])`
+    type Rule<'a> = (&'a str, &'a str, Vec<(&'a str, u32)>);
+
+    fn router(name: &str, rules: Vec<Rule>) -> Router {
+        Router {
+            name: name.to_string(),
+            rules: rules
+                .into_iter()
+                .map(|(pattern, strategy, channels)| RouterRule {
+                    match_spec: MatchSpec {
+                        models: vec![pattern.to_string()],
+                        ..Default::default()
+                    },
+                    channels: channels
+                        .into_iter()
+                        .map(|(name, weight)| TargetChannel {
+                            name: name.to_string(),
+                            weight,
+                        })
+                        .collect(),
+                    strategy: strategy.to_string(),
+                    retries: None,
+                })
+                .collect(),
+            channels: vec![],
+            strategy: "round_robin".to_string(),
+            metadata: None,
+            fallback_channels: vec![],
+            fallback_tiers: vec![],
+            fallback_on: None,
+            reject_unknown_models: false,
+            max_request_bytes: None,
+            max_response_bytes: None,
+            cache: None,
+            passthrough: false,
+            sticky: None,
+            retries: None,
+            hedge_after_ms: None,
+            mirror_channel: None,
+            mirror_percent: None,
+            experiment: None,
+            canary: None,
+            model_aliases: Default::default(),
+            default_model: None,
+            reject_missing_model: false,
+            stream_requests: false,
+            vkey: None,
+            transforms: None,
+        }
+    }
+
+    fn config(routers: Vec<Router>) -> Config {
+        let mut config: Config = serde_json::from_value(serde_json::json!({
+            "version": "1",
+            "global": {
+                "listen": "127.0.0.1:0",
+                "auth_keys": [],
+                "timeouts": { "connect_ms": 1000, "request_ms": 1000, "response_ms": 1000 },
+                "retries": { "max_attempts": 1, "backoff_ms": 0, "retry_on_status": [] }
+            },
+            "logging": { "level": "info" },
+            "metrics": { "enabled": false, "path": "/metrics" },
+            "hot_reload": { "config_path": "", "watch": false },
+            "channels": [],
+            "routers": [],
+            "teams": []
+        }))
+        .unwrap();
+        config.routers = std::sync::Arc::new(routers);
+        config
+    }
+
+    #[test]
+    fn explains_first_matching_router_without_advancing_round_robin() {
+        let config = config(vec![
+            router("openai", vec![("gpt-*", "priority", vec![("oa", 1)])]),
+            router(
+                "claude",
+                vec![
+                    ("claude-3-opus", "priority", vec![("opus", 1)]),
+                    (
+                        "claude-*",
+                        "round_robin",
+                        vec![("primary", 3), ("backup", 1)],
+                    ),
+                ],
+            ),
+        ]);
+        let selector = RouterSelector::new();
+        let explanation = explain(&selector, &config, "claude-3-5-sonnet", None, None).unwrap();
+
+        assert_eq!(explanation.router.as_deref(), Some("claude"));
+        assert_eq!(explanation.rule.as_deref(), Some("claude-*"));
+        assert_eq!(explanation.channel.as_deref(), Some("primary"));
+        assert!(
+            explanation.reason.contains("(glob)"),
+            "{}",
+            explanation.reason
+        );
+        let claude = &explanation.routers[1];
+        assert_eq!(claude.matched_rule, Some(1));
+        assert_eq!(claude.rules[0].match_kind, None);
+        assert_eq!(claude.candidates[0].share, 75.0);
+        assert_eq!(explanation.routers[0].matched_rule, None);
+
+        // A dry run leaves the live rotation where it was.
+        let second = explain(&selector, &config, "claude-3-5-sonnet", None, None).unwrap();
+        assert_eq!(second.channel.as_deref(), Some("primary"));
+    }
+
+    #[test]
+    fn reports_unknown_router_and_unmatched_model() {
+        let config = config(vec![router(
+            "openai",
+            vec![("gpt-*", "weighted", vec![("a", 1), ("b", 1)])],
+        )]);
+        let selector = RouterSelector::new();
+        assert!(explain(&selector, &config, "gpt-4", Some("missing"), None).is_err());
+
+        let weighted = explain(&selector, &config, "GPT-4", Some("openai"), None).unwrap();
+        assert_eq!(weighted.router.as_deref(), Some("openai"));
+        assert_eq!(weighted.channel, None);
+        assert!(weighted.reason.contains("at random"), "{}", weighted.reason);
+
+        let none = explain(&selector, &config, "llama-3", None, None).unwrap();
+        assert_eq!(none.router, None);
+        assert!(none.reason.contains("No router"), "{}", none.reason);
+    }
+
+    #[test]
+    fn resolves_router_aliases_before_global_ones() {
+        let mut fast = router(
+            "fast",
+            vec![("claude-*", "priority", vec![("anthropic", 1)])],
+        );
+        fast.model_aliases = [("best".to_string(), "claude-3-7-sonnet".to_string())].into();
+        let mut config = config(vec![
+            router("openai", vec![("gpt-*", "priority", vec![("openai", 1)])]),
+            fast,
+        ]);
+        config.global.model_aliases = [("best".to_string(), "gpt-4o".to_string())].into();
+        let selector = RouterSelector::new();
+
+        let global = explain(&selector, &config, "best", Some("openai"), None).unwrap();
+        assert_eq!(global.channel.as_deref(), Some("openai"));
+        assert_eq!(global.model, "best");
+        let routed = explain(&selector, &config, "best", Some("fast"), None).unwrap();
+        assert_eq!(routed.channel.as_deref(), Some("anthropic"));
+        assert!(routed.reason.contains("claude-*"), "{}", routed.reason);
+    }
+}
@@ -1,19 +1,179 @@
-use crate::config::Router;
+use crate::channel_health::ChannelHealth;
+use crate::channel_limits::ChannelLimits;
+use crate::config::{Channel, MatchSpec, ModelPrice, Router, model_pattern_matches};
+use axum::http::HeaderMap;
 use glob::{MatchOptions, Pattern};
 use moka::sync::Cache;
 use rand::seq::SliceRandom;
-use std::time::Duration;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RouteSelection {
     pub channel_name: String,
     pub matched_rule: Option<String>,
+    /// Index of the matched rule in `Router::rules`.
+    pub rule_index: Option<usize>,
+}
+
+/// What rule conditions beyond the model (`match.headers`, `paths`, ...)
+/// are checked against. The body is parsed only when a condition needs it.
+pub struct RequestAttributes<'a> {
+    pub headers: &'a HeaderMap,
+    pub path: &'a str,
+    pub team_id: &'a str,
+    pub body: &'a [u8],
+    /// What `split` rules hash to place the request.
+    pub request_id: Option<&'a str>,
+    json: OnceLock<Option<Value>>,
+}
+
+impl<'a> RequestAttributes<'a> {
+    pub fn new(headers: &'a HeaderMap, path: &'a str, team_id: &'a str, body: &'a [u8]) -> Self {
+        Self {
+            headers,
+            path,
+            team_id,
+            body,
+            request_id: None,
+            json: OnceLock::new(),
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: Option<&'a str>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    fn json(&self) -> Option<&Value> {
+        self.json
+            .get_or_init(|| serde_json::from_slice(self.body).ok())
+            .as_ref()
+    }
+
+    fn stream(&self) -> bool {
+        self.json()
+            .and_then(|body| body.get("stream"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    fn prompt_tokens(&self) -> u64 {
+        self.json()
+            .map_or(0, |body| crate::tokens::estimate_request(self.path, body))
+    }
+}
+
+/// Whether `spec` matches a request for `model` now. Without `request` only
+/// the model patterns and time windows are checked, as when listing or
+/// explaining routes.
+pub fn spec_matches(spec: &MatchSpec, model: &str, request: Option<&RequestAttributes>) -> bool {
+    if spec.models.is_empty() {
+        if !spec.has_conditions() {
+            return false;
+        }
+    } else if !spec
+        .models
+        .iter()
+        .any(|pattern| pattern_match(pattern, model).is_some())
+    {
+        return false;
+    }
+    if !spec.any.is_empty() && !spec.any.iter().any(|s| spec_matches(s, model, request)) {
+        return false;
+    }
+    if !spec.all.iter().all(|s| spec_matches(s, model, request)) {
+        return false;
+    }
+    if spec
+        .time
+        .as_ref()
+        .is_some_and(|window| !window.contains(chrono::Utc::now()))
+    {
+        return false;
+    }
+    let Some(request) = request else {
+        return true;
+    };
+    spec.headers.iter().all(|(name, pattern)| {
+        request
+            .headers
+            .get(name.as_str())
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| pattern_match(pattern, value).is_some())
+    }) && (spec.paths.is_empty()
+        || spec
+            .paths
+            .iter()
+            .any(|pattern| pattern_match(pattern, request.path).is_some()))
+        && (spec.teams.is_empty() || spec.teams.iter().any(|team| team == request.team_id))
+        && spec.stream.is_none_or(|stream| stream == request.stream())
+        && (spec.min_prompt_tokens.is_none() && spec.max_prompt_tokens.is_none() || {
+            let tokens = request.prompt_tokens();
+            spec.min_prompt_tokens.is_none_or(|min| tokens >= min)
+                && spec.max_prompt_tokens.is_none_or(|max| tokens <= max)
+        })
 }
 
 #[derive(Clone)]
 pub struct RouterSelector {
-    // Cache key: "router_name:model_name" -> value: Option<usize> (index of matched rule)
+    // Cache key: "generation:router_name:model_name" -> value: Option<usize> (index of matched rule)
     rule_cache: Cache<String, Option<usize>>,
+    // Config generation shared with `AppState`. Every reload / admin mutation
+    // bumps it, so entries computed against an older config are never hit again
+    // even if an explicit invalidation is missed.
+    generation: Arc<AtomicU64>,
+    // Channels ejected by health checks are skipped while the rule still has
+    // another target to offer.
+    health: Arc<ChannelHealth>,
+    // Channels at their `max_concurrent_requests` are skipped the same way.
+    limits: Arc<ChannelLimits>,
+    // Round-robin position per rule, keyed like the rule cache
+    // ("generation:router_name:rule_index"). Shared by every clone so all
+    // requests advance the same cursor.
+    cursors: Cache<String, Arc<RuleCursor>>,
+    // Pricing and channel model maps consulted by `least_cost`.
+    prices: Arc<RwLock<Arc<PriceBook>>>,
+    // `sticky` assignments: "generation:router_name:rule_index:key" ->
+    // (channel, last use). The router's `sticky.ttl_secs` is checked on read;
+    // the cache itself only bounds memory.
+    sticky: Cache<String, (String, Instant)>,
+}
+
+/// What `least_cost` needs from the config: each channel's `model_map` and
+/// the `pricing` table.
+#[derive(Default)]
+struct PriceBook {
+    model_maps: HashMap<String, HashMap<String, String>>,
+    prices: Vec<ModelPrice>,
+}
+
+impl PriceBook {
+    /// Input plus output price per 1K tokens of `model` on `channel`, after
+    /// the channel's `model_map`; `None` when no pricing entry matches.
+    fn unit_price(&self, channel: &str, model: &str) -> Option<f64> {
+        let resolved = self
+            .model_maps
+            .get(channel)
+            .and_then(|map| map.get(model))
+            .map_or(model, String::as_str);
+        self.prices
+            .iter()
+            .find(|price| model_pattern_matches(&price.model, resolved))
+            .map(|price| price.input_per_1k + price.output_per_1k)
+    }
+}
+
+/// Round-robin state of one rule. `next` drives plain rotation when every
+/// target has the same weight; `current` holds the smooth weighted
+/// round-robin credits (one per target, in rule order) otherwise.
+#[derive(Default)]
+struct RuleCursor {
+    next: AtomicU64,
+    current: Mutex<Vec<i64>>,
 }
 
 impl Default for RouterSelector {
@@ -24,18 +184,68 @@ impl Default for RouterSelector {
 
 impl RouterSelector {
     pub fn new() -> Self {
+        Self::with_generation(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// Build a selector whose rule cache is keyed by the given config
+    /// generation counter.
+    pub fn with_generation(generation: Arc<AtomicU64>) -> Self {
         Self {
             rule_cache: Cache::builder()
                 .max_capacity(10_000)
                 .time_to_live(Duration::from_secs(3600)) // 1 hour TTL
                 .build(),
+            generation,
+            health: Arc::new(ChannelHealth::new()),
+            limits: Arc::new(ChannelLimits::new()),
+            cursors: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_idle(Duration::from_secs(3600))
+                .build(),
+            prices: Arc::new(RwLock::new(Arc::new(PriceBook::default()))),
+            sticky: Cache::builder()
+                .max_capacity(100_000)
+                .time_to_idle(Duration::from_secs(24 * 3600))
+                .build(),
         }
     }
 
+    /// Share the gateway's channel health so ejected channels are skipped.
+    pub fn with_health(mut self, health: Arc<ChannelHealth>) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Share the gateway's concurrency limits so full channels are skipped.
+    pub fn with_limits(mut self, limits: Arc<ChannelLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Swap in the channels and `pricing` table used by `least_cost` (at
+    /// startup and on every config change).
+    pub fn set_pricing(&self, channels: &[Channel], pricing: &[ModelPrice]) {
+        let book = PriceBook {
+            model_maps: channels
+                .iter()
+                .filter_map(|c| Some((c.name.clone(), c.model_map.clone()?)))
+                .collect(),
+            prices: pricing.to_vec(),
+        };
+        *self.prices.write().unwrap() = Arc::new(book);
+    }
+
+    /// Current config generation used to key the rule cache.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
     /// Invalidate the rule cache.
     /// Should be called when configuration is reloaded.
     pub fn invalidate_cache(&self) {
         self.rule_cache.invalidate_all();
+        self.cursors.invalidate_all();
+        self.sticky.invalidate_all();
     }
 
     /// Find the target channel for a given router and model.
@@ -47,48 +257,62 @@ impl RouterSelector {
 
     /// Find the target channel and matched rule descriptor for a given router/model pair.
     pub fn select_channel_with_rule(&self, router: &Router, model: &str) -> Option<RouteSelection> {
-        // Use unified rule-based selection
-        // We cache the index of the matched rule, or None if no rule matches
-        let cache_key = format!("{}:{}", router.name, model);
+        self.select_channel_for(router, model, None, None)
+    }
 
-        let rule_idx: Option<usize> = if let Some(idx) = self.rule_cache.get(&cache_key) {
-            idx
+    /// Like `select_channel_with_rule`, with the conversation key that
+    /// `sticky` rules pin to a channel (`None` = no key; such requests are
+    /// spread round-robin) and the request that rule conditions beyond the
+    /// model are checked against (`None` = model patterns and time windows only).
+    pub fn select_channel_for(
+        &self,
+        router: &Router,
+        model: &str,
+        sticky_key: Option<&str>,
+        request: Option<&RequestAttributes>,
+    ) -> Option<RouteSelection> {
+        let find_rule = || {
+            router
+                .rules
+                .iter()
+                .position(|rule| spec_matches(&rule.match_spec, model, request))
+        };
+        // Use unified rule-based selection
+        // We cache the index of the matched rule, or None if no rule matches;
+        // rules with request or time conditions are matched afresh every time.
+        let rule_idx: Option<usize> = if router
+            .rules
+            .iter()
+            .any(|rule| rule.match_spec.has_conditions())
+        {
+            find_rule()
         } else {
-            // Find matching rule
-            let idx = router.rules.iter().position(|rule| {
-                for pattern_str in &rule.match_spec.models {
-                    // 1. Exact match (case-insensitive)
-                    if pattern_str.eq_ignore_ascii_case(model) {
-                        return true;
-                    }
-                    // 2. Glob match (case-insensitive)
-                    if Pattern::new(pattern_str).is_ok_and(|pattern| {
-                        pattern.matches_with(
-                            model,
-                            MatchOptions {
-                                case_sensitive: false,
-                                require_literal_separator: false,
-                                require_literal_leading_dot: false,
-                            },
-                        )
-                    }) {
-                        return true;
-                    }
-                }
-                false
-            });
-
-            // Cache the result (even if None)
-            self.rule_cache.insert(cache_key, idx);
-            idx
+            let cache_key = format!("{}:{}:{}", self.generation(), router.name, model);
+            if let Some(idx) = self.rule_cache.get(&cache_key) {
+                idx
+            } else {
+                let idx = find_rule();
+                // Cache the result (even if None)
+                self.rule_cache.insert(cache_key, idx);
+                idx
+            }
         };
 
-        if let Some(rule) = rule_idx.and_then(|idx| router.rules.get(idx)) {
+        if let Some((idx, rule)) = rule_idx.and_then(|idx| Some((idx, router.rules.get(idx)?))) {
+            let cursor_key = format!("{}:{}:{}", self.generation(), router.name, idx);
             return self
-                .apply_strategy(&rule.channels, &rule.strategy)
+                .apply_strategy(
+                    &rule.channels,
+                    &rule.strategy,
+                    model,
+                    &cursor_key,
+                    sticky_key.map(|key| (key, router)),
+                    request.and_then(|request| request.request_id),
+                )
                 .map(|channel_name| RouteSelection {
                     channel_name,
                     matched_rule: Some(Self::describe_rule(rule)),
+                    rule_index: Some(idx),
                 });
         }
 
@@ -96,6 +320,42 @@ impl RouterSelector {
         None
     }
 
+    /// Channels of fallback tier `tier_index` in the order to try them: the
+    /// tier strategy's pick first, then the rest as listed.
+    pub fn order_fallback_tier(
+        &self,
+        router: &Router,
+        tier_index: usize,
+        model: &str,
+        sticky_key: Option<&str>,
+        request_id: Option<&str>,
+    ) -> Vec<String> {
+        let tiers = router.fallback_tier_list();
+        let Some(tier) = tiers.get(tier_index) else {
+            return Vec::new();
+        };
+        let cursor_key = format!(
+            "{}:{}:fallback:{}",
+            self.generation(),
+            router.name,
+            tier_index
+        );
+        let first = self.apply_strategy(
+            &tier.channels,
+            &tier.strategy,
+            model,
+            &cursor_key,
+            sticky_key.map(|key| (key, router)),
+            request_id,
+        );
+        let rest = tier
+            .channels
+            .iter()
+            .map(|target| target.name.clone())
+            .filter(|name| first.as_ref() != Some(name));
+        first.clone().into_iter().chain(rest).collect()
+    }
+
     fn describe_rule(rule: &crate::config::RouterRule) -> String {
         if rule.match_spec.models.is_empty() {
             return "*".to_string();
@@ -108,57 +368,281 @@ impl RouterSelector {
         &self,
         channels: &[crate::config::TargetChannel],
         strategy: &str,
+        model: &str,
+        cursor_key: &str,
+        sticky: Option<(&str, &Router)>,
+        request_id: Option<&str>,
     ) -> Option<String> {
         if channels.is_empty() {
             return None;
         }
+        let available = self.available_targets(channels);
 
-        match strategy {
+        let idx = match strategy {
             "random" => {
                 let mut rng = rand::thread_rng();
-                channels.choose(&mut rng).map(|c| c.name.clone())
+                available.choose(&mut rng).copied()
             }
             "priority" => {
                 // Always pick the first one
-                channels.first().map(|c| c.name.clone())
+                available.first().copied()
+            }
+            "sticky" if sticky.is_some() => {
+                let (key, router) = sticky?;
+                let ttl = Duration::from_secs(router.sticky.as_ref().map_or_else(
+                    || crate::config::StickyConfig::default().ttl_secs,
+                    |s| s.ttl_secs,
+                ));
+                Some(self.sticky_target(&format!("{cursor_key}:{key}"), ttl, channels, &available))
+            }
+            "round_robin" | "sticky" => {
+                let cursor = self
+                    .cursors
+                    .get_with(cursor_key.to_string(), || Arc::new(RuleCursor::default()));
+                Some(Self::next_round_robin(&cursor, channels, &available))
+            }
+            "least_cost" => self.cheapest(channels, &available, model),
+            "split" => {
+                // Weights are percentages; the request id fixes the share a
+                // request falls into, so the split holds at any volume.
+                let hash = request_id.map_or_else(rand::random, |id| {
+                    use std::hash::{Hash, Hasher};
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    id.hash(&mut hasher);
+                    hasher.finish()
+                });
+                Some(Self::weighted_point(hash, channels, &available))
             }
-            "round_robin" => {
-                let dist =
-                    rand::distributions::WeightedIndex::new(channels.iter().map(|c| c.weight));
+            _ => {
+                // "weighted" (and unknown strategies): weighted random
+                let dist = rand::distributions::WeightedIndex::new(
+                    available.iter().map(|&i| channels[i].weight),
+                );
 
                 match dist {
                     Ok(dist) => {
                         use rand::distributions::Distribution;
                         let mut rng = rand::thread_rng();
-                        let idx = dist.sample(&mut rng);
-                        channels.get(idx).map(|c| c.name.clone())
+                        available.get(dist.sample(&mut rng)).copied()
                     }
                     Err(_) => {
                         // Fallback if weights are invalid
-                        channels.first().map(|c| c.name.clone())
+                        available.first().copied()
                     }
                 }
             }
-            _ => {
-                // Default to round robin if unknown
-                let dist =
-                    rand::distributions::WeightedIndex::new(channels.iter().map(|c| c.weight));
+        };
+        idx.map(|i| channels[i].name.clone())
+    }
 
-                match dist {
-                    Ok(dist) => {
-                        use rand::distributions::Distribution;
-                        let mut rng = rand::thread_rng();
-                        let idx = dist.sample(&mut rng);
-                        channels.get(idx).map(|c| c.name.clone())
-                    }
-                    Err(_) => {
-                        // Fallback if weights are invalid
-                        channels.first().map(|c| c.name.clone())
-                    }
+    /// Indices (into a rule's channel list) of targets neither ejected nor
+    /// at their concurrency limit. Fails open: with every target skipped,
+    /// all of them are returned.
+    fn available_targets(&self, channels: &[crate::config::TargetChannel]) -> Vec<usize> {
+        let available: Vec<usize> = (0..channels.len())
+            .filter(|&i| {
+                !self.health.is_ejected(&channels[i].name)
+                    && !self.limits.is_saturated(&channels[i].name)
+            })
+            .collect();
+        if available.is_empty() {
+            (0..channels.len()).collect()
+        } else {
+            available
+        }
+    }
+
+    /// Cheapest priced target; unpriced ones only when nothing is priced.
+    /// Ties go to the higher weight, then rule order.
+    fn cheapest(
+        &self,
+        channels: &[crate::config::TargetChannel],
+        available: &[usize],
+        model: &str,
+    ) -> Option<usize> {
+        let book = self.prices.read().unwrap().clone();
+        available.iter().copied().min_by(|&a, &b| {
+            let price_a = book.unit_price(&channels[a].name, model);
+            let price_b = book.unit_price(&channels[b].name, model);
+            match (price_a, price_b) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then(channels[b].weight.cmp(&channels[a].weight))
+        })
+    }
+
+    /// Whether health checks have ejected `channel` / it is at its
+    /// `max_concurrent_requests`.
+    pub fn channel_state(&self, channel: &str) -> (bool, bool) {
+        (
+            self.health.is_ejected(channel),
+            self.limits.is_saturated(channel),
+        )
+    }
+
+    /// Channel the next keyless request matching rule `rule_index` of
+    /// `router` would get, without advancing round-robin cursors. `None`
+    /// for random picks (`random`, `weighted`) or an empty rule.
+    pub fn preview_channel(
+        &self,
+        router: &Router,
+        rule_index: usize,
+        model: &str,
+    ) -> Option<String> {
+        let rule = router.rules.get(rule_index)?;
+        let channels = &rule.channels;
+        if channels.is_empty() {
+            return None;
+        }
+        let available = self.available_targets(channels);
+        let idx = match rule.strategy.as_str() {
+            "priority" => available.first().copied(),
+            "least_cost" => self.cheapest(channels, &available, model),
+            "round_robin" | "sticky" => {
+                let cursor_key = format!("{}:{}:{}", self.generation(), router.name, rule_index);
+                let cursor = self.cursors.get(&cursor_key).unwrap_or_default();
+                if Self::equal_weights(channels, &available) {
+                    let n = cursor.next.load(Ordering::Relaxed);
+                    Some(available[(n % available.len() as u64) as usize])
+                } else {
+                    let mut current = cursor
+                        .current
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .clone();
+                    Some(Self::smooth_weighted_step(
+                        &mut current,
+                        channels,
+                        &available,
+                    ))
                 }
             }
+            _ => None,
+        };
+        idx.map(|i| channels[i].name.clone())
+    }
+
+    /// Channel pinned to `assignment_key`, reusing the previous assignment
+    /// while it is fresh and still available, else hashing the key over the
+    /// available targets (by weight) and remembering the result.
+    fn sticky_target(
+        &self,
+        assignment_key: &str,
+        ttl: Duration,
+        channels: &[crate::config::TargetChannel],
+        available: &[usize],
+    ) -> usize {
+        let now = Instant::now();
+        let pinned = self
+            .sticky
+            .get(assignment_key)
+            .filter(|(_, last_used)| now.duration_since(*last_used) <= ttl)
+            .and_then(|(name, _)| {
+                available
+                    .iter()
+                    .copied()
+                    .find(|&i| channels[i].name == name)
+            });
+        let idx = pinned.unwrap_or_else(|| {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            assignment_key.hash(&mut hasher);
+            Self::weighted_point(hasher.finish(), channels, available)
+        });
+        self.sticky.insert(
+            assignment_key.to_string(),
+            (channels[idx].name.clone(), now),
+        );
+        idx
+    }
+
+    /// The available target whose weight share contains `hash`.
+    fn weighted_point(
+        hash: u64,
+        channels: &[crate::config::TargetChannel],
+        available: &[usize],
+    ) -> usize {
+        let total: u64 = available.iter().map(|&i| channels[i].weight as u64).sum();
+        if total == 0 {
+            return available[(hash % available.len() as u64) as usize];
+        }
+        let mut point = hash % total;
+        for &i in available {
+            let weight = channels[i].weight as u64;
+            if point < weight {
+                return i;
+            }
+            point -= weight;
+        }
+        available[0]
+    }
+
+    /// Next target of a round-robin rule. Equal weights rotate through the
+    /// available targets in order; otherwise smooth weighted round-robin
+    /// spreads each target's share evenly (weights 2:1 give A, B, A).
+    /// Zero-weight targets are only used when every weight is zero.
+    fn next_round_robin(
+        cursor: &RuleCursor,
+        channels: &[crate::config::TargetChannel],
+        available: &[usize],
+    ) -> usize {
+        if Self::equal_weights(channels, available) {
+            let n = cursor.next.fetch_add(1, Ordering::Relaxed);
+            return available[(n % available.len() as u64) as usize];
+        }
+
+        let mut current = cursor.current.lock().unwrap_or_else(|e| e.into_inner());
+        Self::smooth_weighted_step(&mut current, channels, available)
+    }
+
+    fn equal_weights(channels: &[crate::config::TargetChannel], available: &[usize]) -> bool {
+        let first_weight = channels[available[0]].weight;
+        available
+            .iter()
+            .all(|&i| channels[i].weight == first_weight)
+    }
+
+    /// One smooth weighted round-robin step over the per-target `current`
+    /// credits.
+    fn smooth_weighted_step(
+        current: &mut Vec<i64>,
+        channels: &[crate::config::TargetChannel],
+        available: &[usize],
+    ) -> usize {
+        current.resize(channels.len(), 0);
+        let total: i64 = available.iter().map(|&i| channels[i].weight as i64).sum();
+        let mut best = available[0];
+        for &i in available {
+            current[i] += channels[i].weight as i64;
+            if current[i] > current[best] {
+                best = i;
+            }
         }
+        current[best] -= total;
+        best
+    }
+}
+
+/// How `pattern` (a rule's `match.models` entry) matches `model`: `"exact"`
+/// or `"glob"`, both case-insensitive; `None` when it does not.
+pub fn pattern_match(pattern: &str, model: &str) -> Option<&'static str> {
+    if pattern.eq_ignore_ascii_case(model) {
+        return Some("exact");
     }
+    let glob = Pattern::new(pattern).is_ok_and(|pattern| {
+        pattern.matches_with(
+            model,
+            MatchOptions {
+                case_sensitive: false,
+                require_literal_separator: false,
+                require_literal_leading_dot: false,
+            },
+        )
+    });
+    glob.then_some("glob")
 }
 
 #[cfg(test)]
@@ -181,6 +665,26 @@ mod tests {
             strategy: "round_robin".to_string(),
             metadata: None,
             fallback_channels: vec![],
+            fallback_tiers: vec![],
+            fallback_on: None,
+            reject_unknown_models: false,
+            max_request_bytes: None,
+            max_response_bytes: None,
+            cache: None,
+            passthrough: false,
+            sticky: None,
+            retries: None,
+            hedge_after_ms: None,
+            mirror_channel: None,
+            mirror_percent: None,
+            experiment: None,
+            canary: None,
+            model_aliases: Default::default(),
+            default_model: None,
+            reject_missing_model: false,
+            stream_requests: false,
+            vkey: None,
+            transforms: None,
         }
     }
 
@@ -190,9 +694,11 @@ mod tests {
         let rules = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["gpt-4".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("ch1", 1), create_channel("ch2", 1)],
             strategy: "priority".to_string(),
+            retries: None,
         }];
         let router = create_router(rules);
 
@@ -213,9 +719,11 @@ mod tests {
         let rules = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["gpt-*".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("ch1", 1)],
             strategy: "priority".to_string(),
+            retries: None,
         }];
         let router = create_router(rules);
 
@@ -236,9 +744,11 @@ mod tests {
         let rules = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("A", 1), create_channel("B", 1)],
             strategy: "round_robin".to_string(),
+            retries: None,
         }];
         let router = create_router(rules);
 
@@ -254,15 +764,170 @@ mod tests {
         assert!(counts.get("B").unwrap() > &0);
     }
 
+    #[test]
+    fn test_round_robin_rotates_evenly_across_clones() {
+        let selector = RouterSelector::new();
+        let other = selector.clone();
+        let router = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![
+                create_channel("A", 1),
+                create_channel("B", 1),
+                create_channel("C", 1),
+            ],
+            strategy: "round_robin".to_string(),
+            retries: None,
+        }]);
+
+        let picks: Vec<String> = (0..6)
+            .map(|i| {
+                let selector = if i % 2 == 0 { &selector } else { &other };
+                selector.select_channel(&router, "any").unwrap()
+            })
+            .collect();
+        assert_eq!(picks, ["A", "B", "C", "A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_smooth_weighted_round_robin() {
+        let selector = RouterSelector::new();
+        let router = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![create_channel("A", 2), create_channel("B", 1)],
+            strategy: "round_robin".to_string(),
+            retries: None,
+        }]);
+
+        let picks: Vec<String> = (0..6)
+            .map(|_| selector.select_channel(&router, "any").unwrap())
+            .collect();
+        assert_eq!(picks, ["A", "B", "A", "A", "B", "A"]);
+    }
+
+    #[test]
+    fn test_least_cost_uses_mapped_model_price_then_weight() {
+        let selector = RouterSelector::new();
+        let channels: Vec<Channel> = serde_json::from_value(serde_json::json!([
+            {"name": "premium", "provider_type": "openai", "base_url": "http://a", "api_key": "k"},
+            {"name": "mini", "provider_type": "openai", "base_url": "http://b", "api_key": "k",
+             "model_map": {"gpt-4o": "gpt-4o-mini"}},
+            {"name": "mini-2", "provider_type": "openai", "base_url": "http://c", "api_key": "k",
+             "model_map": {"gpt-4o": "gpt-4o-mini"}},
+            {"name": "unpriced", "provider_type": "openai", "base_url": "http://d", "api_key": "k",
+             "model_map": {"gpt-4o": "local-model"}}
+        ]))
+        .unwrap();
+        let price = |model: &str, input: f64, output: f64| ModelPrice {
+            model: model.to_string(),
+            input_per_1k: input,
+            output_per_1k: output,
+            per_image: 0.0,
+        };
+        selector.set_pricing(
+            &channels,
+            &[
+                price("gpt-4o-mini", 0.00015, 0.0006),
+                price("gpt-4o*", 0.0025, 0.01),
+            ],
+        );
+        let router = |targets: Vec<TargetChannel>| {
+            create_router(vec![RouterRule {
+                match_spec: MatchSpec {
+                    models: vec!["*".to_string()],
+                    ..Default::default()
+                },
+                channels: targets,
+                strategy: "least_cost".to_string(),
+                retries: None,
+            }])
+        };
+
+        let cheapest = router(vec![
+            create_channel("unpriced", 1),
+            create_channel("premium", 1),
+            create_channel("mini", 1),
+            create_channel("mini-2", 5),
+        ]);
+        assert_eq!(
+            selector.select_channel(&cheapest, "gpt-4o"),
+            Some("mini-2".to_string())
+        );
+
+        let nothing_priced = router(vec![create_channel("unpriced", 1)]);
+        assert_eq!(
+            selector.select_channel(&nothing_priced, "gpt-4o"),
+            Some("unpriced".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sticky_keeps_conversations_on_one_channel() {
+        let settings = crate::config::HealthCheckConfig {
+            interval_secs: 30,
+            timeout_ms: 1000,
+            path: "models".to_string(),
+            unhealthy_threshold: 1,
+            healthy_threshold: 1,
+        };
+        let health = Arc::new(ChannelHealth::new());
+        health.configure(Some(&settings));
+        let selector = RouterSelector::new().with_health(health.clone());
+        let router = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![
+                create_channel("A", 1),
+                create_channel("B", 1),
+                create_channel("C", 1),
+            ],
+            strategy: "sticky".to_string(),
+            retries: None,
+        }]);
+        let pick = |key: Option<&str>| {
+            selector
+                .select_channel_for(&router, "m", key, None)
+                .unwrap()
+                .channel_name
+        };
+
+        let first = pick(Some("conversation-1"));
+        for _ in 0..10 {
+            assert_eq!(pick(Some("conversation-1")), first);
+        }
+        let assigned: std::collections::HashSet<String> = (0..50)
+            .map(|i| pick(Some(&format!("conversation-{i}"))))
+            .collect();
+        assert!(assigned.len() > 1);
+
+        // An ejected channel loses its conversations, which then stay put.
+        health.record(&first, false);
+        let moved = pick(Some("conversation-1"));
+        assert_ne!(moved, first);
+        assert_eq!(pick(Some("conversation-1")), moved);
+
+        // Without a key the rule falls back to round-robin.
+        assert_ne!(pick(None), pick(None));
+    }
+
     #[test]
     fn test_weighted_round_robin() {
         let selector = RouterSelector::new();
         let rules = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("A", 10), create_channel("B", 0)], // B has 0 weight
             strategy: "round_robin".to_string(),
+            retries: None,
         }];
         let router = create_router(rules);
 
@@ -272,15 +937,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generation_bump_ignores_stale_rule_cache() {
+        let generation = Arc::new(AtomicU64::new(0));
+        let selector = RouterSelector::with_generation(generation.clone());
+        let before = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["gpt-*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![create_channel("old", 1)],
+            strategy: "priority".to_string(),
+            retries: None,
+        }]);
+        assert_eq!(
+            selector.select_channel(&before, "gpt-4"),
+            Some("old".to_string())
+        );
+
+        // Same router name, new rule layout: the cached index (0) would now
+        // point at the wrong rule unless the generation participates in the key.
+        let after = create_router(vec![
+            RouterRule {
+                match_spec: MatchSpec {
+                    models: vec!["claude-*".to_string()],
+                    ..Default::default()
+                },
+                channels: vec![create_channel("claude", 1)],
+                strategy: "priority".to_string(),
+                retries: None,
+            },
+            RouterRule {
+                match_spec: MatchSpec {
+                    models: vec!["gpt-*".to_string()],
+                    ..Default::default()
+                },
+                channels: vec![create_channel("new", 1)],
+                strategy: "priority".to_string(),
+                retries: None,
+            },
+        ]);
+        generation.fetch_add(1, Ordering::AcqRel);
+        assert_eq!(
+            selector.select_channel(&after, "gpt-4"),
+            Some("new".to_string())
+        );
+    }
+
     #[test]
     fn test_case_insensitive_match() {
         let selector = RouterSelector::new();
         let rules = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["GPT-4".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("ch1", 1)],
             strategy: "priority".to_string(),
+            retries: None,
         }];
         let router = create_router(rules);
 
@@ -294,9 +1008,11 @@ mod tests {
         let rules_glob = vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["GPT-*".to_string()],
+                ..Default::default()
             },
             channels: vec![create_channel("ch2", 1)],
             strategy: "priority".to_string(),
+            retries: None,
         }];
         let router_glob = create_router(rules_glob);
 
@@ -305,4 +1021,149 @@ mod tests {
             Some("ch2".to_string())
         );
     }
+
+    #[test]
+    fn ejected_channels_are_skipped_until_all_are_ejected() {
+        let settings = crate::config::HealthCheckConfig {
+            interval_secs: 30,
+            timeout_ms: 1000,
+            path: "models".to_string(),
+            unhealthy_threshold: 1,
+            healthy_threshold: 1,
+        };
+        let health = Arc::new(ChannelHealth::new());
+        health.configure(Some(&settings));
+        let selector = RouterSelector::new().with_health(health.clone());
+        let router = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![create_channel("ch1", 1), create_channel("ch2", 1)],
+            strategy: "priority".to_string(),
+            retries: None,
+        }]);
+
+        health.record("ch1", false);
+        assert_eq!(
+            selector.select_channel(&router, "m"),
+            Some("ch2".to_string())
+        );
+
+        health.record("ch2", false);
+        assert_eq!(
+            selector.select_channel(&router, "m"),
+            Some("ch1".to_string())
+        );
+    }
+
+    #[test]
+    fn split_strategy_places_request_ids_by_percentage() {
+        let selector = RouterSelector::new();
+        let router = create_router(vec![RouterRule {
+            match_spec: MatchSpec {
+                models: vec!["*".to_string()],
+                ..Default::default()
+            },
+            channels: vec![create_channel("A", 80), create_channel("B", 20)],
+            strategy: "split".to_string(),
+            retries: None,
+        }]);
+        let headers = HeaderMap::new();
+        let pick = |id: &str| {
+            let request = RequestAttributes::new(&headers, "/v1/chat/completions", "global", b"")
+                .with_request_id(Some(id));
+            selector
+                .select_channel_for(&router, "m", None, Some(&request))
+                .unwrap()
+                .channel_name
+        };
+
+        let ids: Vec<String> = (0..2000).map(|i| format!("req-{i}")).collect();
+        let first: Vec<String> = ids.iter().map(|id| pick(id)).collect();
+        let again: Vec<String> = ids.iter().map(|id| pick(id)).collect();
+        assert_eq!(first, again);
+        let to_b = first.iter().filter(|name| *name == "B").count();
+        assert!((300..500).contains(&to_b), "{to_b} of 2000 went to B");
+    }
+
+    #[test]
+    fn request_conditions_compose_and_bypass_the_rule_cache() {
+        let selector = RouterSelector::new();
+        let spec: MatchSpec = serde_json::from_value(serde_json::json!({
+            "models": ["gpt-*"],
+            "any": [
+                {"min_prompt_tokens": 1000},
+                {"headers": {"x-tier": "long*"}, "teams": ["acme"]}
+            ]
+        }))
+        .unwrap();
+        let router = create_router(vec![
+            RouterRule {
+                match_spec: spec,
+                channels: vec![create_channel("long", 1)],
+                strategy: "priority".to_string(),
+                retries: None,
+            },
+            RouterRule {
+                match_spec: MatchSpec {
+                    models: vec!["*".to_string()],
+                    stream: Some(true),
+                    ..Default::default()
+                },
+                channels: vec![create_channel("stream", 1)],
+                strategy: "priority".to_string(),
+                retries: None,
+            },
+            RouterRule {
+                match_spec: MatchSpec {
+                    models: vec!["*".to_string()],
+                    ..Default::default()
+                },
+                channels: vec![create_channel("default", 1)],
+                strategy: "priority".to_string(),
+                retries: None,
+            },
+        ]);
+        let select = |headers: &HeaderMap, team: &str, body: &serde_json::Value| {
+            let body = body.to_string();
+            let request =
+                RequestAttributes::new(headers, "/v1/chat/completions", team, body.as_bytes());
+            selector
+                .select_channel_for(&router, "gpt-4o", None, Some(&request))
+                .map(|selection| selection.channel_name)
+        };
+        let short = serde_json::json!({"messages": [{"role": "user", "content": "hi"}]});
+        let long =
+            serde_json::json!({"messages": [{"role": "user", "content": "word ".repeat(5000)}]});
+        let mut tiered = HeaderMap::new();
+        tiered.insert("x-tier", "long-context".parse().unwrap());
+
+        assert_eq!(
+            select(&HeaderMap::new(), "acme", &short).as_deref(),
+            Some("default")
+        );
+        assert_eq!(
+            select(&HeaderMap::new(), "acme", &long).as_deref(),
+            Some("long")
+        );
+        assert_eq!(select(&tiered, "acme", &short).as_deref(), Some("long"));
+        // Both the header and the team are required in that branch.
+        assert_eq!(select(&tiered, "other", &short).as_deref(), Some("default"));
+        let streaming = serde_json::json!({"stream": true, "messages": []});
+        assert_eq!(
+            select(&HeaderMap::new(), "acme", &streaming).as_deref(),
+            Some("stream")
+        );
+
+        // Without a request only the model patterns count.
+        assert_eq!(
+            selector.select_channel(&router, "gpt-4o").as_deref(),
+            Some("long")
+        );
+        assert_eq!(
+            selector.select_channel(&router, "o3").as_deref(),
+            Some("stream")
+        );
+    }
 }
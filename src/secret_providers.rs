@@ -0,0 +1,515 @@
+//! Remote backends for channel secrets (`vault:` / `aws:` references, see
+//! `secrets`).
+//!
+//! Each backend implements `SecretsProvider`. `SecretStore` fetches every
+//! reference the config uses, caches the values and applies them; the
+//! gateway resolves references before a config goes live and refreshes them
+//! every `secrets.refresh_secs`, so a rotated key is picked up without a
+//! restart. A failed fetch keeps the last known value.
+
+use crate::config::{AwsSecretsConfig, Config, SecretsConfig, VaultConfig};
+use crate::secrets::{apply_remote, interpolate, remote_reference, remote_references};
+use anyhow::{Context, anyhow, bail};
+use futures::future::BoxFuture;
+use ring::{digest, hmac};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Timeout for a single fetch from a backend.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A secret backend. `path` is the reference without its scheme, e.g.
+/// `secret/openai#key` for `vault:secret/openai#key`.
+pub trait SecretsProvider: Send + Sync {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, anyhow::Result<String>>;
+}
+
+/// Fetched values by reference, shared by reloads and the refresher.
+pub struct SecretStore {
+    client: reqwest::Client,
+    cache: RwLock<HashMap<String, String>>,
+}
+
+impl Default for SecretStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretStore {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Apply remote secrets to a config about to go live, fetching the
+    /// references that are not cached yet.
+    pub async fn resolve(&self, config: &mut Config) {
+        let missing: Vec<String> = {
+            let cache = self.cache.read().unwrap();
+            remote_references(config)
+                .into_iter()
+                .filter(|reference| !cache.contains_key(reference))
+                .collect()
+        };
+        if !missing.is_empty() {
+            self.fetch_all(config.secrets.as_ref(), missing).await;
+        }
+        apply_remote(config, &self.cache.read().unwrap());
+    }
+
+    /// Fetch every reference `config` uses again. Returns the values to
+    /// apply when any of them changed.
+    pub async fn refresh(&self, config: &Config) -> Option<HashMap<String, String>> {
+        let references = remote_references(config);
+        if references.is_empty() {
+            return None;
+        }
+        let changed = self.fetch_all(config.secrets.as_ref(), references).await;
+        changed.then(|| self.cache.read().unwrap().clone())
+    }
+
+    /// Fetch `references` into the cache; returns whether a value changed.
+    async fn fetch_all(&self, settings: Option<&SecretsConfig>, references: Vec<String>) -> bool {
+        let providers = providers(&self.client, settings);
+        let fetches = references.into_iter().map(|reference| {
+            let providers = &providers;
+            async move {
+                let result = match remote_reference(&reference) {
+                    Some((scheme, path)) => match providers.get(scheme) {
+                        Some(provider) => provider.fetch(path).await,
+                        None => Err(anyhow!("no {scheme} backend configured")),
+                    },
+                    None => Err(anyhow!("not a remote reference")),
+                };
+                (reference, result)
+            }
+        });
+        let mut changed = false;
+        for (reference, result) in futures::future::join_all(fetches).await {
+            match result {
+                Ok(value) => {
+                    let mut cache = self.cache.write().unwrap();
+                    if cache.get(&reference) != Some(&value) {
+                        info!("Secret Fetched: {}", reference);
+                        cache.insert(reference, value);
+                        changed = true;
+                    }
+                }
+                Err(e) => error!("Secret Fetch Failed: {}: {:#}", reference, e),
+            }
+        }
+        changed
+    }
+}
+
+/// Backends by scheme, built from `settings` and the environment.
+fn providers(
+    client: &reqwest::Client,
+    settings: Option<&SecretsConfig>,
+) -> HashMap<&'static str, Box<dyn SecretsProvider>> {
+    let mut providers: HashMap<&'static str, Box<dyn SecretsProvider>> = HashMap::new();
+    let vault = settings.and_then(|s| s.vault.clone()).unwrap_or_default();
+    providers.insert("vault", Box::new(Vault::new(client.clone(), &vault)));
+    let aws = settings.and_then(|s| s.aws.clone()).unwrap_or_default();
+    providers.insert(
+        "aws",
+        Box::new(AwsSecretsManager::new(client.clone(), &aws)),
+    );
+    providers
+}
+
+/// A config value (with `${VAR}` expanded) or the environment variable `env`.
+fn setting(value: Option<&String>, context: &str, env: &[&str]) -> Option<String> {
+    value
+        .map(|value| interpolate(context, value))
+        .or_else(|| env.iter().find_map(|name| std::env::var(name).ok()))
+        .filter(|value| !value.is_empty())
+}
+
+/// `path#field` split at the last `#`.
+fn split_field(path: &str) -> (&str, Option<&str>) {
+    match path.rsplit_once('#') {
+        Some((path, field)) if !field.is_empty() => (path, Some(field)),
+        _ => (path, None),
+    }
+}
+
+/// `field` of a JSON secret, or its only field when none is named.
+fn pick_field(data: &Value, field: Option<&str>) -> anyhow::Result<String> {
+    let object = data
+        .as_object()
+        .ok_or_else(|| anyhow!("secret is not a JSON object"))?;
+    let value = match field {
+        Some(field) => object
+            .get(field)
+            .ok_or_else(|| anyhow!("secret has no field {field:?}"))?,
+        None if object.len() == 1 => object.values().next().unwrap(),
+        None => bail!("secret has several fields; name one with #field"),
+    };
+    Ok(match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// HashiCorp Vault KV engine: `vault:<mount>/<path>#<field>`.
+pub struct Vault {
+    client: reqwest::Client,
+    address: Option<String>,
+    token: Option<String>,
+    namespace: Option<String>,
+    kv_version: u8,
+}
+
+impl Vault {
+    pub fn new(client: reqwest::Client, config: &VaultConfig) -> Self {
+        Self {
+            client,
+            address: setting(config.address.as_ref(), "secrets.vault", &["VAULT_ADDR"]),
+            token: setting(config.token.as_ref(), "secrets.vault", &["VAULT_TOKEN"]),
+            namespace: setting(
+                config.namespace.as_ref(),
+                "secrets.vault",
+                &["VAULT_NAMESPACE"],
+            ),
+            kv_version: config.kv_version.unwrap_or(2),
+        }
+    }
+
+    async fn read(&self, path: &str) -> anyhow::Result<String> {
+        let address = self
+            .address
+            .as_deref()
+            .context("Vault address not set (secrets.vault.address or VAULT_ADDR)")?;
+        let token = self
+            .token
+            .as_deref()
+            .context("Vault token not set (secrets.vault.token or VAULT_TOKEN)")?;
+        let (path, field) = split_field(path);
+        let path = path.trim_matches('/');
+        let api_path = match (self.kv_version, path.split_once('/')) {
+            (1, _) => path.to_string(),
+            (_, Some((mount, rest))) => format!("{mount}/data/{rest}"),
+            (_, None) => bail!("Vault path {path:?} needs a mount, e.g. secret/openai"),
+        };
+        let mut request = self
+            .client
+            .get(format!("{}/v1/{}", address.trim_end_matches('/'), api_path))
+            .header("X-Vault-Token", token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Vault returned {status}");
+        }
+        let body: Value = response.json().await?;
+        let data = if self.kv_version == 1 {
+            &body["data"]
+        } else {
+            &body["data"]["data"]
+        };
+        pick_field(data, field)
+    }
+}
+
+impl SecretsProvider for Vault {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(self.read(path))
+    }
+}
+
+/// AWS Secrets Manager: `aws:<secret-id or ARN>#<field>`. Without a field the
+/// whole `SecretString` is used; with one it is read as JSON.
+pub struct AwsSecretsManager {
+    client: reqwest::Client,
+    region: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    session_token: Option<String>,
+    endpoint: Option<String>,
+}
+
+impl AwsSecretsManager {
+    pub fn new(client: reqwest::Client, config: &AwsSecretsConfig) -> Self {
+        let context = "secrets.aws";
+        Self {
+            client,
+            region: setting(
+                config.region.as_ref(),
+                context,
+                &["AWS_REGION", "AWS_DEFAULT_REGION"],
+            ),
+            access_key_id: setting(
+                config.access_key_id.as_ref(),
+                context,
+                &["AWS_ACCESS_KEY_ID"],
+            ),
+            secret_access_key: setting(
+                config.secret_access_key.as_ref(),
+                context,
+                &["AWS_SECRET_ACCESS_KEY"],
+            ),
+            session_token: setting(
+                config.session_token.as_ref(),
+                context,
+                &["AWS_SESSION_TOKEN"],
+            ),
+            endpoint: config
+                .endpoint
+                .as_ref()
+                .map(|endpoint| interpolate(context, endpoint)),
+        }
+    }
+
+    async fn read(&self, path: &str) -> anyhow::Result<String> {
+        let region = self
+            .region
+            .as_deref()
+            .context("AWS region not set (secrets.aws.region or AWS_REGION)")?;
+        let access_key_id = self
+            .access_key_id
+            .as_deref()
+            .context("AWS credentials not set (secrets.aws or AWS_ACCESS_KEY_ID)")?;
+        let secret_access_key = self
+            .secret_access_key
+            .as_deref()
+            .context("AWS credentials not set (secrets.aws or AWS_SECRET_ACCESS_KEY)")?;
+        let (secret_id, field) = split_field(path);
+        let endpoint = self
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://secretsmanager.{region}.amazonaws.com"));
+        let url = url::Url::parse(&endpoint).context("invalid secrets.aws.endpoint")?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => bail!("invalid secrets.aws.endpoint"),
+        };
+
+        let body = json!({ "SecretId": secret_id }).to_string();
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host),
+            ("x-amz-date", amz_date.clone()),
+            ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.sort();
+        let authorization = sigv4_authorization(
+            &SigningInput {
+                access_key_id,
+                secret_access_key,
+                region,
+                service: "secretsmanager",
+                amz_date: &amz_date,
+            },
+            "POST",
+            url.path(),
+            &headers,
+            body.as_bytes(),
+        );
+
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+        let response = request
+            .header("authorization", authorization)
+            .send()
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let message = body["message"]
+                .as_str()
+                .or(body["Message"].as_str())
+                .unwrap_or_default();
+            bail!("Secrets Manager returned {status} {message}");
+        }
+        let secret = body["SecretString"]
+            .as_str()
+            .context("secret has no SecretString")?;
+        match field {
+            Some(_) => pick_field(
+                &serde_json::from_str(secret).context("SecretString is not JSON")?,
+                field,
+            ),
+            None => Ok(secret.to_string()),
+        }
+    }
+}
+
+impl SecretsProvider for AwsSecretsManager {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(self.read(path))
+    }
+}
+
+struct SigningInput<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+    /// `YYYYMMDDTHHMMSSZ`.
+    amz_date: &'a str,
+}
+
+/// `Authorization` header for an AWS Signature Version 4 request. `headers`
+/// are the signed headers, lowercase and sorted by name.
+fn sigv4_authorization(
+    input: &SigningInput,
+    method: &str,
+    path: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> String {
+    let date = &input.amz_date[..8];
+    let scope = format!("{date}/{}/{}/aws4_request", input.region, input.service);
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{method}\n{}\n\n{canonical_headers}\n{signed_headers}\n{}",
+        if path.is_empty() { "/" } else { path },
+        hex(digest::digest(&digest::SHA256, body).as_ref())
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        input.amz_date,
+        hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+    let key = signing_key(input.secret_access_key, date, input.region, input.service);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &key);
+    let signature = hex(hmac::sign(&key, string_to_sign.as_bytes()).as_ref());
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        input.access_key_id
+    )
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let sign = |key: &[u8], data: &str| {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+    };
+    let k_date = sign(format!("AWS4{secret}").as_bytes(), date);
+    let k_region = sign(k_date.as_ref(), region);
+    let k_service = sign(k_region.as_ref(), service);
+    let k_signing = sign(k_service.as_ref(), "aws4_request");
+    k_signing.as_ref().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    async fn serve(app: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn derives_the_documented_sigv4_signing_key() {
+        // Example from the AWS Signature Version 4 documentation.
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_from_vault_and_secrets_manager() {
+        let vault = serve(axum::Router::new().route(
+            "/v1/secret/data/openai",
+            axum::routing::get(|headers: HeaderMap| async move {
+                assert_eq!(headers["x-vault-token"], "vault-token");
+                axum::Json(json!({"data": {"data": {"key": "sk-vault"}, "metadata": {}}}))
+            }),
+        ))
+        .await;
+        let aws = serve(axum::Router::new().route(
+            "/",
+            axum::routing::post(|headers: HeaderMap, body: String| async move {
+                assert_eq!(headers["x-amz-target"], "secretsmanager.GetSecretValue");
+                let auth = headers["authorization"].to_str().unwrap();
+                assert!(auth.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+                assert!(auth.contains("/eu-west-1/secretsmanager/aws4_request"));
+                let id: Value = serde_json::from_str(&body).unwrap();
+                assert_eq!(id["SecretId"], "prod/openai");
+                axum::Json(json!({"SecretString": "{\"key\":\"sk-aws\"}"}))
+            }),
+        ))
+        .await;
+
+        let mut config: Config = serde_json::from_value(json!({
+            "version": "1",
+            "global": {
+                "listen": "127.0.0.1:0",
+                "auth": {"mode": "none"},
+                "timeouts": {"connect_ms": 1000, "request_ms": 1000, "response_ms": 1000},
+                "retries": {"max_attempts": 1, "backoff_ms": 0, "retry_on_status": []}
+            },
+            "metrics": {"enabled": false, "path": "/metrics"},
+            "hot_reload": {"config_path": "config.json", "watch": false},
+            "secrets": {
+                "vault": {"address": vault, "token": "vault-token"},
+                "aws": {
+                    "region": "eu-west-1",
+                    "access_key_id": "AKID",
+                    "secret_access_key": "secret",
+                    "endpoint": aws
+                }
+            },
+            "channels": [{
+                "name": "remote",
+                "provider_type": "openai",
+                "base_url": "https://api.example.com",
+                "api_key": "vault:secret/openai#key",
+                "api_keys": ["aws:prod/openai#key", "vault:secret/missing#key"]
+            }]
+        }))
+        .unwrap();
+
+        let store = SecretStore::new();
+        store.resolve(&mut config).await;
+        assert_eq!(config.channels[0].api_key, "sk-vault");
+        assert_eq!(
+            config.channels[0].api_keys,
+            vec!["sk-aws", "vault:secret/missing#key"]
+        );
+        // Nothing rotated upstream: the refresh reports no change.
+        assert!(store.refresh(&config).await.is_none());
+    }
+}
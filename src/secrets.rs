@@ -0,0 +1,361 @@
+//! Channel secrets kept out of `config.json`.
+//!
+//! `api_key`, `api_keys` and `headers` values may reference environment
+//! variables as `${NAME}`, and `api_key_file` reads the key from a file
+//! (relative paths are taken from the config file's directory). References
+//! are resolved when the config is loaded; the original text is remembered in
+//! `Config::secret_refs` so `save_config` writes the reference back instead of
+//! the resolved secret, as long as the value was not changed in between.
+//!
+//! Values of the form `vault:<path>#<field>` or `aws:<secret-id>#<field>` name
+//! a secret in a remote backend. Loading leaves them as is; the gateway
+//! fetches them through `secret_providers` and applies them with
+//! `apply_remote`, recorded the same way.
+
+use crate::config::{Channel, Config, tenant_scoped_name};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Prefixes of references served by a remote backend.
+pub const REMOTE_SCHEMES: &[&str] = &["vault", "aws"];
+
+/// Unresolved text of every secret that was resolved at load time.
+#[derive(Debug, Clone, Default)]
+pub struct SecretRefs {
+    refs: Arc<Vec<SecretRef>>,
+}
+
+#[derive(Debug, Clone)]
+struct SecretRef {
+    channel: String,
+    field: Field,
+    raw: String,
+    resolved: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    ApiKey,
+    ApiKeys(usize),
+    Header(String),
+}
+
+/// Every value of `channel` that may hold a secret reference.
+fn secret_fields(channel: &mut Channel) -> Vec<(Field, &mut String)> {
+    let mut fields = vec![(Field::ApiKey, &mut channel.api_key)];
+    fields.extend(
+        channel
+            .api_keys
+            .iter_mut()
+            .enumerate()
+            .map(|(idx, key)| (Field::ApiKeys(idx), key)),
+    );
+    if let Some(headers) = channel.headers.as_mut() {
+        fields.extend(
+            headers
+                .iter_mut()
+                .map(|(name, value)| (Field::Header(name.clone()), value)),
+        );
+    }
+    fields
+}
+
+/// Call `f` with the qualified name of every channel, tenant channels included.
+fn for_each_channel(config: &mut Config, mut f: impl FnMut(&str, &mut Channel)) {
+    for channel in Arc::make_mut(&mut config.channels) {
+        let owner = channel.name.clone();
+        f(&owner, channel);
+    }
+    for tenant in &mut config.tenants {
+        for channel in &mut tenant.channels {
+            let owner = tenant_scoped_name(&tenant.id, &channel.name);
+            f(&owner, channel);
+        }
+    }
+}
+
+/// Resolve the secrets of every channel in `config`, tenant channels
+/// included, in place. Missing variables and unreadable key files are logged
+/// and resolve to an empty value, so CLI commands keep working on a machine
+/// without the secrets.
+pub fn resolve(config: &mut Config, base_dir: Option<&Path>) {
+    let mut refs = Vec::new();
+    for_each_channel(config, |owner, channel| {
+        let context = format!("channel '{}'", channel.name);
+        let key_file = channel.api_key_file.clone();
+        for (field, value) in secret_fields(channel) {
+            let resolved = match (&field, key_file.as_deref()) {
+                (Field::ApiKey, Some(file)) => read_key_file(&context, file, base_dir),
+                _ => interpolate(&context, value),
+            };
+            if resolved != *value {
+                let raw = std::mem::replace(value, resolved.clone());
+                refs.push(SecretRef {
+                    channel: owner.to_string(),
+                    field,
+                    raw,
+                    resolved,
+                });
+            }
+        }
+    });
+    config.secret_refs = SecretRefs {
+        refs: Arc::new(refs),
+    };
+}
+
+/// `config` with each resolved secret replaced by the reference it came from.
+/// Values changed since loading (e.g. through the admin API) are kept as is.
+pub fn with_references(config: &Config) -> Config {
+    let mut config = config.clone();
+    if config.secret_refs.refs.is_empty() {
+        return config;
+    }
+    let refs = config.secret_refs.refs.clone();
+    for_each_channel(&mut config, |owner, channel| {
+        for (field, value) in secret_fields(channel) {
+            if let Some(secret) = find_ref(&refs, owner, &field, value) {
+                *value = secret.raw.clone();
+            }
+        }
+    });
+    config
+}
+
+/// The reference behind `value`, unless `value` was changed after resolving.
+fn find_ref<'a>(
+    refs: &'a [SecretRef],
+    owner: &str,
+    field: &Field,
+    value: &str,
+) -> Option<&'a SecretRef> {
+    refs.iter()
+        .find(|r| r.channel == owner && r.field == *field && r.resolved == value)
+}
+
+/// `(scheme, path)` of a remote reference such as `vault:secret/openai#key`.
+pub fn remote_reference(value: &str) -> Option<(&str, &str)> {
+    let (scheme, path) = value.split_once(':')?;
+    (REMOTE_SCHEMES.contains(&scheme) && !path.is_empty()).then_some((scheme, path))
+}
+
+/// Remote references used by any channel of `config`, resolved or not.
+pub fn remote_references(config: &Config) -> Vec<String> {
+    let mut config = config.clone();
+    let refs = config.secret_refs.refs.clone();
+    let mut found = Vec::new();
+    for_each_channel(&mut config, |owner, channel| {
+        for (field, value) in secret_fields(channel) {
+            let raw = find_ref(&refs, owner, &field, value).map_or(value.as_str(), |r| &r.raw);
+            if remote_reference(raw).is_some() && !found.iter().any(|f| f == raw) {
+                found.push(raw.to_string());
+            }
+        }
+    });
+    found
+}
+
+/// Replace remote references (and values previously fetched for them) with
+/// the fetched `values`, keyed by reference. Returns whether anything changed.
+pub fn apply_remote(config: &mut Config, values: &HashMap<String, String>) -> bool {
+    let mut refs = config.secret_refs.refs.as_ref().clone();
+    let mut changed = false;
+    for_each_channel(config, |owner, channel| {
+        for (field, value) in secret_fields(channel) {
+            let existing = refs
+                .iter()
+                .position(|r| r.channel == owner && r.field == field && r.resolved == *value);
+            let raw = existing.map_or(value.as_str(), |idx| &refs[idx].raw);
+            if remote_reference(raw).is_none() {
+                continue;
+            }
+            let Some(fetched) = values.get(raw) else {
+                continue;
+            };
+            if *value != *fetched {
+                changed = true;
+            }
+            match existing {
+                Some(idx) => refs[idx].resolved = fetched.clone(),
+                None => refs.push(SecretRef {
+                    channel: owner.to_string(),
+                    field,
+                    raw: value.clone(),
+                    resolved: fetched.clone(),
+                }),
+            }
+            *value = fetched.clone();
+        }
+    });
+    config.secret_refs = SecretRefs {
+        refs: Arc::new(refs),
+    };
+    changed
+}
+
+/// Replace every `${NAME}` in `raw` with the environment variable `NAME`.
+/// Text without a complete `${...}` is returned unchanged; `context` names
+/// the setting in the warning for an unset variable.
+pub fn interpolate(context: &str, raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(value) => out.push_str(&value),
+            Err(_) => warn!(
+                "Secret Unresolved: {} references unset environment variable {}",
+                context, name
+            ),
+        }
+        rest = &rest[start + 2 + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn read_key_file(context: &str, file: &str, base_dir: Option<&Path>) -> String {
+    let path = match base_dir {
+        Some(dir) if Path::new(file).is_relative() => dir.join(file),
+        _ => Path::new(file).to_path_buf(),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => content.trim().to_string(),
+        Err(e) => {
+            warn!(
+                "Secret Unresolved: {} api_key_file {}: {}",
+                context,
+                path.display(),
+                e
+            );
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(channels: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "version": "1",
+            "global": {
+                "listen": "127.0.0.1:0",
+                "auth": {"mode": "none"},
+                "timeouts": {"connect_ms": 1000, "request_ms": 1000, "response_ms": 1000},
+                "retries": {"max_attempts": 1, "backoff_ms": 0, "retry_on_status": []}
+            },
+            "metrics": {"enabled": false, "path": "/metrics"},
+            "hot_reload": {"config_path": "config.json", "watch": false},
+            "channels": channels
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn resolves_env_and_file_secrets_and_saves_references() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("openai.key"), "sk-from-file\n").unwrap();
+        // SAFETY: the variable name is unique to this test.
+        unsafe { std::env::set_var("APEX_SECRETS_TEST_KEY", "sk-from-env") };
+
+        let mut config = config(serde_json::json!([
+            {
+                "name": "env",
+                "provider_type": "openai",
+                "base_url": "https://api.example.com",
+                "api_key": "${APEX_SECRETS_TEST_KEY}",
+                "api_keys": ["plain", "${APEX_SECRETS_TEST_MISSING}"],
+                "headers": {"x-org": "org-${APEX_SECRETS_TEST_KEY}"}
+            },
+            {
+                "name": "file",
+                "provider_type": "openai",
+                "base_url": "https://api.example.com",
+                "api_key": "",
+                "api_key_file": "openai.key"
+            }
+        ]));
+        resolve(&mut config, Some(dir.path()));
+        let env = &config.channels[0];
+        assert_eq!(env.api_key, "sk-from-env");
+        assert_eq!(env.api_keys, vec!["plain", ""]);
+        assert_eq!(env.headers.as_ref().unwrap()["x-org"], "org-sk-from-env");
+        assert_eq!(config.channels[1].api_key, "sk-from-file");
+
+        // An edited value is saved as edited; untouched ones keep their reference.
+        Arc::make_mut(&mut config.channels)[0].api_keys[1] = "sk-new".to_string();
+        let saved = serde_json::to_value(with_references(&config)).unwrap();
+        assert_eq!(saved["channels"][0]["api_key"], "${APEX_SECRETS_TEST_KEY}");
+        assert_eq!(
+            saved["channels"][0]["api_keys"],
+            serde_json::json!(["plain", "sk-new"])
+        );
+        assert_eq!(
+            saved["channels"][0]["headers"]["x-org"],
+            "org-${APEX_SECRETS_TEST_KEY}"
+        );
+        assert_eq!(saved["channels"][1]["api_key"], "");
+        assert_eq!(saved["channels"][1]["api_key_file"], "openai.key");
+    }
+
+    #[test]
+    fn remote_references_are_applied_refreshed_and_saved_as_references() {
+        let mut config = config(serde_json::json!([
+            {
+                "name": "vaulted",
+                "provider_type": "openai",
+                "base_url": "https://api.example.com",
+                "api_key": "vault:secret/openai#key",
+                "api_keys": ["aws:prod/openai#key", "sk-plain"]
+            }
+        ]));
+        resolve(&mut config, None);
+        assert_eq!(
+            remote_references(&config),
+            vec!["vault:secret/openai#key", "aws:prod/openai#key"]
+        );
+
+        let mut values = HashMap::from([
+            ("vault:secret/openai#key".to_string(), "sk-v1".to_string()),
+            ("aws:prod/openai#key".to_string(), "sk-a1".to_string()),
+        ]);
+        assert!(apply_remote(&mut config, &values));
+        assert_eq!(config.channels[0].api_key, "sk-v1");
+        assert_eq!(config.channels[0].api_keys, vec!["sk-a1", "sk-plain"]);
+        assert!(!apply_remote(&mut config, &values));
+
+        // A rotated secret replaces the previously fetched value.
+        values.insert("vault:secret/openai#key".to_string(), "sk-v2".to_string());
+        assert!(apply_remote(&mut config, &values));
+        assert_eq!(config.channels[0].api_key, "sk-v2");
+        assert_eq!(remote_references(&config).len(), 2);
+
+        let saved = serde_json::to_value(with_references(&config)).unwrap();
+        assert_eq!(saved["channels"][0]["api_key"], "vault:secret/openai#key");
+        assert_eq!(
+            saved["channels"][0]["api_keys"],
+            serde_json::json!(["aws:prod/openai#key", "sk-plain"])
+        );
+    }
+
+    #[test]
+    fn leaves_text_without_references_alone() {
+        assert_eq!(interpolate("c", "sk-plain"), "sk-plain");
+        assert_eq!(interpolate("c", "a${b"), "a${b");
+        assert_eq!(interpolate("c", "${not valid}"), "${not valid}");
+        assert_eq!(interpolate("c", "$HOME"), "$HOME");
+    }
+}
@@ -4,6 +4,8 @@
 // fundamentally at odds with that design, so allow it module-wide.
 #![allow(clippy::result_large_err)]
 
+use crate::channel_health::ChannelHealth;
+use crate::channel_limits::ChannelLimits;
 use crate::config::Config;
 use crate::converters::convert_openai_response_to_anthropic;
 use crate::database::{
@@ -12,32 +14,42 @@ use crate::database::{
 };
 use crate::gemini_compat::{GeminiAnthropicReplayCache, gemini_replay_missing_signature};
 use crate::metrics::MetricsState;
-use crate::middleware::auth::{TeamContext, global_auth, team_auth};
+use crate::middleware::auth::{
+    RouterKeyContext, TeamContext, global_auth, metrics_auth, team_auth,
+};
 use crate::middleware::compliance::{OriginalModelName, compliance_middleware};
+use crate::middleware::ip_limit::ip_rate_limit;
+use crate::middleware::load_shed::load_shed;
 use crate::middleware::policy::team_policy;
 use crate::middleware::ratelimit::TeamRateLimiter;
+use crate::middleware::tenant::{TenantContext, strip_tenant_prefix, tenant_scope};
 use crate::providers::{
-    AccessAudit, NoOpAccessAudit, NoOpRateLimiter, ProviderRegistry, RateLimiter, RouteKind,
+    AccessAudit, AuditEvent, NoOpRateLimiter, ProviderRegistry, RateLimiter, RouteKind,
     prepare_request,
 };
-use crate::router_selector::RouterSelector;
+use crate::response_cache::ResponseCaches;
+use crate::router_selector::{RequestAttributes, RouterSelector};
 use crate::usage::UsageLogger;
 use crate::web_assets::{WebAssetError, load_web_asset};
 use axum::Router;
 use axum::body::{Body, Bytes};
 use axum::extract::{OriginalUri, State};
 use axum::http::{HeaderMap, HeaderValue, Method, Request, Response as HttpResponse, StatusCode};
-use axum::response::{Redirect, Response};
-use axum::routing::{delete, get, patch, post};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post};
 use chrono::{Duration as ChronoDuration, Local, NaiveDateTime};
 use notify::{Config as NotifyConfig, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use serde_json::json;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
 use tower_http::cors::CorsLayer;
 use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::{self, TraceLayer};
@@ -52,18 +64,170 @@ pub struct AppState {
     pub rate_limiter: Arc<dyn RateLimiter>,
     pub team_rate_limiter: Arc<TeamRateLimiter>,
     pub selector: Arc<RouterSelector>,
+    /// Monotonic config generation, bumped on every reload / admin mutation.
+    /// Shared with `selector` so cached rule matches are keyed by it.
+    pub config_generation: Arc<AtomicU64>,
+    /// Proxied requests currently in flight (see `middleware::load_shed`).
+    pub in_flight: Arc<AtomicUsize>,
+    /// Rolling per-channel outcomes (see `channel_health`).
+    pub channel_health: Arc<ChannelHealth>,
+    pub canary: Arc<crate::canary::CanaryTracker>,
+    /// Per-channel `max_concurrent_requests` slots (see `channel_limits`).
+    pub channel_limits: Arc<ChannelLimits>,
+    /// Per-channel API key rotation and quarantine (see `key_pool`).
+    pub key_pools: Arc<crate::key_pool::KeyPools>,
+    /// Values fetched for `vault:` / `aws:` channel secrets.
+    pub secret_store: Arc<crate::secret_providers::SecretStore>,
+    /// Per-router cache of identical non-streaming responses.
+    pub response_cache: Arc<ResponseCaches>,
+    /// Model lists fetched from channels for `/v1/models`.
+    pub model_catalog: Arc<crate::model_catalog::ModelCatalog>,
+    /// Access tokens of Vertex service accounts.
+    pub vertex_tokens: Arc<crate::vertex::TokenCache>,
+    /// Channel hosts resolved ahead of time (see `warmup`).
+    pub dns_cache: Arc<crate::warmup::DnsCache>,
     pub gemini_replay: Arc<GeminiAnthropicReplayCache>,
     pub client: reqwest::Client,
+    /// Clients for channels whose `timeouts.connect_ms` differs from the
+    /// global one, keyed by that timeout so equal overrides share a pool.
+    pub channel_clients: moka::sync::Cache<u64, reqwest::Client>,
     pub usage_logger: Arc<UsageLogger>,
     pub database: Arc<Database>,
     pub web_dir: String,
 }
 
+impl AppState {
+    /// Upstream client honoring the channel's connect timeout.
+    pub fn client_for(&self, channel: &crate::config::Channel) -> reqwest::Client {
+        let global_connect_ms = self.config.read().unwrap().global.timeouts.connect_ms;
+        let connect_ms = match &channel.timeouts {
+            Some(timeouts) if timeouts.connect_ms != global_connect_ms => timeouts.connect_ms,
+            _ => return self.client.clone(),
+        };
+        self.channel_clients.get_with(connect_ms, || {
+            upstream_client(connect_ms, &self.dns_cache).unwrap_or_else(|e| {
+                error!(
+                    "Failed to build client for channel '{}': {}",
+                    channel.name, e
+                );
+                self.client.clone()
+            })
+        })
+    }
+
+    /// Mark the live config as changed: bump the generation and drop any
+    /// cached routing decisions. Call after every config swap.
+    pub fn bump_config_generation(&self) -> u64 {
+        let generation = self.config_generation.fetch_add(1, Ordering::AcqRel) + 1;
+        self.selector.invalidate_cache();
+        let config = self.config.read().unwrap();
+        self.channel_health.configure(config.health.as_ref());
+        self.channel_limits.configure(&config.channels);
+        self.usage_logger.set_pricing(config.pricing.clone());
+        self.selector.set_pricing(&config.channels, &config.pricing);
+        self.response_cache.clear();
+        generation
+    }
+}
+
 pub(crate) const MAX_REQUEST_BODY_BYTES: usize = 10 * 1024 * 1024;
+/// Multipart uploads (audio transcriptions, image edits) follow OpenAI's
+/// 25 MB file limit.
+pub(crate) const MAX_UPLOAD_BODY_BYTES: usize = 25 * 1024 * 1024;
+/// Non-streamed upstream responses the gateway buffers.
+pub(crate) const MAX_RESPONSE_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Largest client request body accepted under `global.body_limits`.
+pub(crate) fn request_body_limit(global: &crate::config::Global, headers: &HeaderMap) -> usize {
+    let limits = global.body_limits.as_ref();
+    if crate::utils::is_multipart(headers) {
+        limits
+            .and_then(|limits| limits.max_upload_bytes)
+            .unwrap_or(MAX_UPLOAD_BODY_BYTES)
+    } else {
+        limits
+            .and_then(|limits| limits.max_request_bytes)
+            .unwrap_or(MAX_REQUEST_BODY_BYTES)
+    }
+}
+
+/// The channel's own cap on a request body, if it sets one.
+fn channel_request_limit(channel: &crate::config::Channel, headers: &HeaderMap) -> Option<usize> {
+    let limits = channel.body_limits.as_ref()?;
+    if crate::utils::is_multipart(headers) {
+        limits.max_upload_bytes
+    } else {
+        limits.max_request_bytes
+    }
+}
+
+/// Cap on a non-streamed upstream response: the tightest of the router's and
+/// channel's `max_response_bytes` and, for bodies the gateway buffers (not
+/// binary), the global one. `None` leaves the response unbounded.
+fn response_body_limit(
+    global: &crate::config::Global,
+    router: Option<&crate::config::Router>,
+    channel: &crate::config::Channel,
+    headers: &HeaderMap,
+) -> Option<usize> {
+    let global_limit = (!crate::utils::is_binary_body(headers)).then(|| {
+        global
+            .body_limits
+            .as_ref()
+            .and_then(|limits| limits.max_response_bytes)
+            .unwrap_or(MAX_RESPONSE_BODY_BYTES)
+    });
+    [
+        router.and_then(|router| router.max_response_bytes),
+        channel
+            .body_limits
+            .as_ref()
+            .and_then(|limits| limits.max_response_bytes),
+        global_limit,
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// Response to a client body that could not be read: 413 when it ran past
+/// `limit`, 400 for any other failure.
+pub(crate) fn body_read_error(route: RouteKind, limit: usize, err: axum::Error) -> Response<Body> {
+    let err = err.into_inner();
+    if err.is::<http_body_util::LengthLimitError>() {
+        tracing::warn!("Request Rejected: body exceeds {} bytes", limit);
+        return protocol_error_response(
+            route,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            &format!("Request body exceeds {} bytes", limit),
+        );
+    }
+    tracing::error!("Request Failed: Failed to read body: {}", err);
+    protocol_error_response(route, StatusCode::BAD_REQUEST, &err.to_string())
+}
+/// Remote secret refresh period when `secrets` is not configured.
+const DEFAULT_SECRETS_REFRESH: Duration = Duration::from_secs(300);
+/// How often the `hot_reload.store` document is re-read even without a
+/// change notification.
+const CONFIG_STORE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How often team token buckets are written to SQLite.
+const RATE_LIMIT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
 
 pub async fn run_server(path: PathBuf) -> anyhow::Result<()> {
-    let content = std::fs::read_to_string(&path)?;
-    let mut config: Config = serde_json::from_str(&content)?;
+    run_server_with_providers(path, ProviderRegistry::new()).await
+}
+
+/// `run_server` for programs embedding apex: channels can use the adapters
+/// registered in `providers`.
+pub async fn run_server_with_providers(
+    path: PathBuf,
+    providers: ProviderRegistry,
+) -> anyhow::Result<()> {
+    let mut config = crate::config::read_config(&path)?;
+    if let Some(store) = config.hot_reload.store.clone() {
+        config = crate::config_store::load_initial(&store, config).await;
+    }
+    crate::secrets::resolve(&mut config, path.parent());
 
     // Store config path for potential hot reload
     config.hot_reload.config_path = path.to_string_lossy().to_string();
@@ -73,12 +237,79 @@ pub async fn run_server(path: PathBuf) -> anyhow::Result<()> {
     // by the auth middleware. Fail closed so an unfinished setup never goes
     // live on 0.0.0.0:12356.
     crate::config::check_no_placeholder_credentials(&config)?;
+    // Catch misconfigurations (missing channels, unknown strategies, …) here
+    // rather than as misrouted requests later.
+    crate::config::validate_config(&config)?;
+    let legacy = crate::key_store::legacy_key_teams(&config);
+    if !legacy.is_empty() {
+        tracing::warn!(
+            "Deprecated: team(s) {} still have a plaintext api_key; run `apex key migrate` to store them hashed",
+            legacy.join(", ")
+        );
+    }
 
-    let state = build_state(config.clone())?;
+    let state = build_state_with_providers(config.clone(), providers)?;
     let app = build_app(state.clone());
 
-    // Start config watcher
-    if config.hot_reload.watch {
+    // Carry token buckets across restarts so a restart doesn't reset every
+    // team's burst allowance; snapshot them periodically from here on.
+    // Shared buckets already outlive any one instance.
+    let persist_buckets = !state.team_rate_limiter.is_shared();
+    match state.database.load_rate_limit_state() {
+        Ok(_) if !persist_buckets => {}
+        Ok(buckets) if !buckets.is_empty() => {
+            info!("Restored {} rate limit buckets", buckets.len());
+            state.team_rate_limiter.restore(buckets);
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to restore rate limit state: {}", e),
+    }
+    if persist_buckets {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(RATE_LIMIT_SNAPSHOT_INTERVAL);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let buckets = state.team_rate_limiter.snapshot();
+                let db = state.database.clone();
+                match tokio::task::spawn_blocking(move || db.save_rate_limit_state(&buckets)).await
+                {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Failed to persist rate limit state: {}", e),
+                    Err(e) => error!("Rate limit snapshot task panicked: {}", e),
+                }
+            }
+        });
+    }
+
+    // Fetch `vault:` / `aws:` channel secrets before serving, then keep them
+    // fresh so rotated keys apply without a restart.
+    refresh_secrets(&state).await;
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let interval = state
+                    .config
+                    .read()
+                    .unwrap()
+                    .secrets
+                    .as_ref()
+                    .map_or(DEFAULT_SECRETS_REFRESH, |secrets| {
+                        Duration::from_secs(secrets.refresh_secs.max(1))
+                    });
+                tokio::time::sleep(interval).await;
+                refresh_secrets(&state).await;
+            }
+        });
+    }
+
+    // Start config watcher; with a central store the local file is only
+    // where this instance starts from.
+    if let Some(store) = config.hot_reload.store.clone() {
+        tokio::spawn(watch_config_store(path.clone(), state.clone(), store));
+    } else if config.hot_reload.watch {
         let path_clone = path.clone();
         let state_clone = state.clone();
         tokio::spawn(async move {
@@ -88,6 +319,25 @@ pub async fn run_server(path: PathBuf) -> anyhow::Result<()> {
         });
     }
 
+    #[cfg(unix)]
+    {
+        let path = path.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = reload_on_sighup(path, state).await {
+                error!("SIGHUP handler failed: {}", e);
+            }
+        });
+    }
+
+    // Fleet usage shipped through a Redis stream lands in this database.
+    if let Some(collector) = config.usage_collector.clone() {
+        tokio::spawn(crate::usage_shipping::collect(
+            collector,
+            state.database.clone(),
+        ));
+    }
+
     // Prune old usage/metrics rows in the background so the SQLite file stays
     // bounded. Runs once shortly after startup, then on a fixed interval.
     if config.retention.days > 0 {
@@ -116,11 +366,153 @@ pub async fn run_server(path: PathBuf) -> anyhow::Result<()> {
         });
     }
 
+    // Access audit events have their own retention window.
+    if let Some(audit) = config.access_audit.clone().filter(|a| a.retention_days > 0) {
+        let db = state.database.clone();
+        let data_dir = config.data_dir.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(24 * 3600));
+            loop {
+                ticker.tick().await;
+                let db = db.clone();
+                let audit = audit.clone();
+                let data_dir = data_dir.clone();
+                match tokio::task::spawn_blocking(move || {
+                    crate::access_audit::prune(&audit, &data_dir, &db)
+                })
+                .await
+                {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(n)) => info!("Retention: pruned {} access audit entries", n),
+                    Ok(Err(e)) => error!("Access audit retention failed: {}", e),
+                    Err(e) => error!("Access audit retention task panicked: {}", e),
+                }
+            }
+        });
+    }
+
+    // Team transcripts follow each team's own retention_days; teams are read
+    // on every tick so hot-reloaded policies apply.
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(24 * 3600));
+            loop {
+                ticker.tick().await;
+                let (data_dir, teams) = {
+                    let config = state.config.read().unwrap();
+                    (config.data_dir.clone(), config.teams.clone())
+                };
+                match tokio::task::spawn_blocking(move || {
+                    crate::transcripts::prune(&data_dir, &teams)
+                })
+                .await
+                {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(n)) => info!("Retention: pruned {} transcript files", n),
+                    Ok(Err(e)) => error!("Transcript retention failed: {}", e),
+                    Err(e) => error!("Transcript retention task panicked: {}", e),
+                }
+            }
+        });
+    }
+
+    // Connection warm-up: the first round finishes before the listener
+    // opens, later rounds follow `warmup.interval_secs` (re-read every tick).
+    let warmup = config.warmup.clone();
+    if let Some(settings) = &warmup {
+        crate::warmup::warm(&state, settings).await;
+    }
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut settings = warmup;
+            loop {
+                let interval = settings
+                    .as_ref()
+                    .map_or(Duration::from_secs(30), |settings| {
+                        Duration::from_secs(settings.interval_secs.max(1))
+                    });
+                tokio::time::sleep(interval).await;
+                settings = state.config.read().unwrap().warmup.clone();
+                match &settings {
+                    Some(settings) => {
+                        crate::warmup::warm(&state, settings).await;
+                    }
+                    None => state.dns_cache.clear(),
+                }
+            }
+        });
+    }
+
+    // Active health probes. The section is re-read every tick, so enabling,
+    // disabling or retuning it through hot reload needs no restart.
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let (settings, channels) = {
+                    let config = state.config.read().unwrap();
+                    (config.health.clone(), config.channels.clone())
+                };
+                let Some(settings) = settings else {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    continue;
+                };
+                let probes = channels.iter().map(|channel| {
+                    let state = &state;
+                    let settings = &settings;
+                    async move {
+                        let result = probe_channel(state, channel, settings).await;
+                        state
+                            .channel_health
+                            .record_probe(&channel.name, result, settings);
+                    }
+                });
+                futures::future::join_all(probes).await;
+                tokio::time::sleep(Duration::from_secs(settings.interval_secs.max(1))).await;
+            }
+        });
+    }
+
+    if config.metrics.enabled
+        && let Some(listen) = &config.metrics.listen
+    {
+        let addr: SocketAddr = listen.parse()?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!("Metrics listening on {}", addr);
+        let metrics_app = build_metrics_app(state.clone());
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, metrics_app).await {
+                error!("Metrics listener failed: {}", e);
+            }
+        });
+    }
+
     let addr: SocketAddr = config.global.listen.parse()?;
+
+    if let Some(tls) = &config.global.tls {
+        let tls_config = crate::tls::load(tls).await?;
+        crate::tls::spawn_reloader(tls_config.clone(), tls.clone());
+        let listener = std::net::TcpListener::bind(addr)?;
+        tracing::info!("Listening on {} (TLS)", addr);
+        crate::systemd::ready(&format!("Listening on {addr} (TLS)"));
+        crate::systemd::spawn_watchdog();
+        crate::tls::serve(listener, app, tls_config).await?;
+        return Ok(());
+    }
+
     tracing::info!("Listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    crate::systemd::ready(&format!("Listening on {addr}"));
+    crate::systemd::spawn_watchdog();
+    // Peer addresses feed per-client-IP rate limiting.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -128,28 +520,39 @@ pub async fn run_server(path: PathBuf) -> anyhow::Result<()> {
 async fn watch_config(path: PathBuf, state: Arc<AppState>) -> notify::Result<()> {
     // Watch parent directory for robust file replacement handling (atomic saves)
     let path = std::fs::canonicalize(&path)?;
-    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let parent = path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
     let filename = path
         .file_name()
         .ok_or_else(|| {
             notify::Error::new(notify::ErrorKind::Generic("Invalid config path".into()))
         })?
         .to_os_string();
+    let include_dirs = crate::config_includes::include_dirs(&path);
 
     let (tx, mut rx) = mpsc::channel(1);
 
     // Create a watcher that sends events to the channel
+    let watched_dirs = include_dirs.clone();
     let mut watcher = RecommendedWatcher::new(
         move |res: notify::Result<Event>| {
             if let Ok(event) = res {
-                // We care about any event that affects our file
-                let matches = event
-                    .paths
-                    .iter()
-                    .any(|p| p.file_name().map(|n| n == filename).unwrap_or(false));
-
+                // We care about any event that affects our file, a `*.json`
+                // in an include directory, or an include directory itself.
+                let matches = event.paths.iter().any(|p| {
+                    p.file_name().map(|n| n == filename).unwrap_or(false)
+                        || watched_dirs.iter().any(|dir| p == dir)
+                        || (p.extension().is_some_and(|ext| ext == "json")
+                            && p.parent()
+                                .is_some_and(|dir| watched_dirs.iter().any(|d| d == dir)))
+                });
+
+                // A pending signal already covers this change; never block
+                // notify's thread while the reload loop is debouncing.
                 if matches {
-                    let _ = tx.blocking_send(());
+                    let _ = tx.try_send(());
                 }
             }
         },
@@ -158,7 +561,12 @@ async fn watch_config(path: PathBuf, state: Arc<AppState>) -> notify::Result<()>
 
     // Add a path to be watched. All files and directories at that path and
     // below will be monitored for changes.
-    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+    watcher.watch(&parent, RecursiveMode::NonRecursive)?;
+    // Include directories are watched once they exist; one created later is
+    // picked up after the reload its creation triggers.
+    let mut unwatched = include_dirs;
+    unwatched
+        .retain(|dir| !dir.is_dir() || watcher.watch(dir, RecursiveMode::NonRecursive).is_err());
 
     info!("Started watching config file: {:?}", path);
 
@@ -180,55 +588,159 @@ async fn watch_config(path: PathBuf, state: Arc<AppState>) -> notify::Result<()>
 
         info!("Config file changed, reloading...");
 
-        // Reload config
-        match crate::config::load_config(&path) {
-            Ok(new_config) => {
-                if let Err(e) = crate::config::check_no_placeholder_credentials(&new_config) {
-                    error!("Refusing to apply reloaded config: {}", e);
-                    continue;
-                }
-                // Update config
-                {
-                    let mut config_guard = state.config.write().unwrap();
-                    // Preserve hot_reload config path if needed, or just overwrite
-                    // The new config from file might not have the path set in hot_reload struct if it's not in JSON
-                    // But we are reading from the same path.
-                    // Ideally we merge or just replace.
-                    // Let's replace but ensure critical internal fields are preserved if any.
-                    // Actually Config is pure data.
-
-                    // Note: If we use Arc for teams/routers/channels, deserialization creates new Arcs.
-                    // This is exactly what we want.
-                    *config_guard = new_config;
-                    // Restore the path just in case
-                    config_guard.hot_reload.config_path = path.to_string_lossy().to_string();
-                }
+        unwatched.retain(|dir| {
+            !dir.is_dir() || watcher.watch(dir, RecursiveMode::NonRecursive).is_err()
+        });
+        reload_config(&path, &state).await;
+    }
 
-                // Invalidate router cache
-                state.selector.invalidate_cache();
+    Ok(())
+}
 
-                info!("Config reloaded successfully");
-            }
+/// Apply changes to the `hot_reload.store` document: whenever the store
+/// reports one, and every `CONFIG_STORE_POLL_INTERVAL` in case a
+/// notification was missed.
+async fn watch_config_store(
+    path: PathBuf,
+    state: Arc<AppState>,
+    store: crate::config::ConfigStore,
+) {
+    let (changed_tx, mut changed) = tokio::sync::mpsc::channel(1);
+    tokio::spawn(crate::config_store::watch(store.clone(), changed_tx));
+    let mut applied = crate::config_store::fetch(&store).await.ok().flatten();
+    let mut ticker = tokio::time::interval(CONFIG_STORE_POLL_INTERVAL);
+    ticker.tick().await;
+    info!("Started watching {}", crate::config_store::describe(&store));
+    loop {
+        tokio::select! {
+            _ = changed.recv() => {}
+            _ = ticker.tick() => {}
+        }
+        let document = match crate::config_store::fetch(&store).await {
+            Ok(Some(document)) => document,
+            Ok(None) => continue,
             Err(e) => {
-                error!("Failed to reload config: {}", e);
+                tracing::warn!("Failed to read config store: {:#}", e);
+                continue;
             }
+        };
+        if applied.as_ref() == Some(&document) {
+            continue;
+        }
+        applied = Some(document.clone());
+        info!("Config store changed, reloading...");
+        let local = state.config.read().unwrap().hot_reload.clone();
+        match crate::config_store::parse(&document, &local)
+            .and_then(|config| crate::config::prepare_config(config, path.parent()))
+        {
+            Ok(config) => apply_config(&path, &state, config).await,
+            Err(e) => error!("Failed to reload config: {:#}", e),
         }
     }
+}
+
+/// Re-read the config (from `hot_reload.store` when set, else the file) and
+/// swap it in, shared by the file watcher and SIGHUP.
+async fn reload_config(path: &Path, state: &AppState) {
+    let local = state.config.read().unwrap().hot_reload.clone();
+    let loaded = match &local.store {
+        Some(store) => match crate::config_store::fetch(store).await {
+            Ok(Some(document)) => crate::config_store::parse(&document, &local)
+                .and_then(|config| crate::config::prepare_config(config, path.parent())),
+            Ok(None) => Err(anyhow::anyhow!(
+                "{} does not exist",
+                crate::config_store::describe(store)
+            )),
+            Err(e) => Err(e),
+        },
+        None => crate::config::load_config(path),
+    };
+    match loaded {
+        Ok(config) => apply_config(path, state, config).await,
+        Err(e) => error!("Failed to reload config: {:#}", e),
+    }
+}
+
+/// Swap in a reloaded config. One that still carries placeholder
+/// credentials, fails `validate_config` or names an unregistered adapter is
+/// logged and the running config is kept.
+async fn apply_config(path: &Path, state: &AppState, mut new_config: Config) {
+    if let Err(e) = crate::config::check_no_placeholder_credentials(&new_config)
+        .and_then(|()| crate::config::validate_config(&new_config))
+        .and_then(|()| state.providers.check_channels(&new_config.channels))
+    {
+        error!("Refusing to apply reloaded config: {}", e);
+        return;
+    }
+    state.secret_store.resolve(&mut new_config).await;
+    {
+        // Deserialization creates fresh Arcs for teams/routers/channels, so a
+        // plain replace is what we want; only the runtime path is carried over.
+        let mut config_guard = state.config.write().unwrap();
+        *config_guard = new_config;
+        config_guard.hot_reload.config_path = path.to_string_lossy().to_string();
+    }
+
+    let generation = state.bump_config_generation();
+    info!("Config reloaded successfully (generation {})", generation);
+}
+
+/// Fetch remote channel secrets again and apply the ones that changed.
+async fn refresh_secrets(state: &AppState) {
+    let snapshot = state.config.read().unwrap().clone();
+    if let Some(values) = state.secret_store.refresh(&snapshot).await {
+        let mut config = state.config.write().unwrap();
+        crate::secrets::apply_remote(&mut config, &values);
+    }
+}
+
+/// Reload the config whenever the process receives SIGHUP, independent of
+/// `hot_reload.watch`, so operators (and `apex gateway reload`) can force a
+/// reload deterministically.
+#[cfg(unix)]
+async fn reload_on_sighup(path: PathBuf, state: Arc<AppState>) -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
 
+    let mut hangups = signal(SignalKind::hangup())?;
+    while hangups.recv().await.is_some() {
+        info!("Received SIGHUP, reloading config...");
+        crate::systemd::reloading();
+        reload_config(&path, &state).await;
+        crate::systemd::ready("Config reloaded");
+    }
     Ok(())
 }
 
-pub fn build_state(config: Config) -> Result<Arc<AppState>, anyhow::Error> {
-    let mut builder = reqwest::Client::builder();
-    if config.global.timeouts.connect_ms > 0 {
-        builder = builder.connect_timeout(Duration::from_millis(config.global.timeouts.connect_ms));
+/// Pooled upstream client; `connect_ms` of 0 leaves connects unbounded.
+fn upstream_client(
+    connect_ms: u64,
+    dns_cache: &Arc<crate::warmup::DnsCache>,
+) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().dns_resolver(dns_cache.clone());
+    if connect_ms > 0 {
+        builder = builder.connect_timeout(Duration::from_millis(connect_ms));
     }
     // Default pool settings
-    builder = builder
+    builder
         .pool_idle_timeout(Duration::from_secs(90))
-        .tcp_nodelay(true);
+        .tcp_nodelay(true)
+        .build()
+}
+
+pub fn build_state(config: Config) -> Result<Arc<AppState>, anyhow::Error> {
+    build_state_with_providers(config, ProviderRegistry::new())
+}
 
-    let client = builder.build()?;
+/// `build_state` with a registry the embedding program has added its own
+/// adapters to (see `ProviderRegistry::register`).
+pub fn build_state_with_providers(
+    mut config: Config,
+    providers: ProviderRegistry,
+) -> Result<Arc<AppState>, anyhow::Error> {
+    config.expand_tenants();
+    providers.check_channels(&config.channels)?;
+    let dns_cache = Arc::new(crate::warmup::DnsCache::new());
+    let client = upstream_client(config.global.timeouts.connect_ms, &dns_cache)?;
 
     let database = Arc::new(Database::new(Some(config.data_dir.clone()))?);
     let gemini_replay_ttl = Duration::from_secs(
@@ -238,18 +750,58 @@ pub fn build_state(config: Config) -> Result<Arc<AppState>, anyhow::Error> {
             .ttl_hours
             .saturating_mul(60 * 60),
     );
-    let usage_logger = Arc::new(UsageLogger::new(database.clone()));
+    let team_rate_limiter = Arc::new(TeamRateLimiter::from_config(
+        config.global.rate_limit_store.as_ref(),
+    )?);
+    let mut usage_logger =
+        UsageLogger::new(database.clone()).with_rate_limiter(team_rate_limiter.clone());
+    if let Some(shipping) = &config.usage_shipping {
+        usage_logger = usage_logger.with_shipper(
+            crate::usage_shipping::UsageShipper::spawn(shipping),
+            shipping.keep_local,
+        );
+    }
+    if !config.usage_webhooks.is_empty() {
+        usage_logger = usage_logger.with_webhooks(crate::usage_webhooks::UsageWebhooks::spawn(
+            &config.usage_webhooks,
+        ));
+    }
+    let usage_logger = Arc::new(usage_logger);
+    usage_logger.set_pricing(config.pricing.clone());
+    let config_generation = Arc::new(AtomicU64::new(0));
+    let selector = RouterSelector::with_generation(config_generation.clone());
+    selector.set_pricing(&config.channels, &config.pricing);
+    let access_audit = crate::access_audit::from_config(
+        config.access_audit.as_ref(),
+        &config.data_dir,
+        database.clone(),
+    )?;
     let web_dir = config.web_dir.clone();
+    let metrics = Arc::new(MetricsState::with_buckets(&config.metrics.buckets)?);
     let config_arc = Arc::new(RwLock::new(config));
+    let channel_health = Arc::new(ChannelHealth::new());
+    channel_health.configure(config_arc.read().unwrap().health.as_ref());
+    let channel_limits = Arc::new(ChannelLimits::new());
+    channel_limits.configure(&config_arc.read().unwrap().channels);
 
     Ok(Arc::new(AppState {
         config: config_arc,
-        metrics: Arc::new(MetricsState::new()?),
-        providers: Arc::new(ProviderRegistry::new()),
-        access_audit: Arc::new(NoOpAccessAudit),
+        metrics,
+        providers: Arc::new(providers),
+        access_audit,
         rate_limiter: Arc::new(NoOpRateLimiter),
-        team_rate_limiter: Arc::new(TeamRateLimiter::new()),
-        selector: Arc::new(RouterSelector::new()),
+        team_rate_limiter,
+        selector: Arc::new(
+            selector
+                .with_health(channel_health.clone())
+                .with_limits(channel_limits.clone()),
+        ),
+        config_generation,
+        in_flight: Arc::new(AtomicUsize::new(0)),
+        channel_health,
+        canary: Arc::default(),
+        channel_limits,
+        response_cache: Arc::new(ResponseCaches::new()),
         gemini_replay: Arc::new(GeminiAnthropicReplayCache::with_persistence(
             database.clone(),
             gemini_replay_ttl,
@@ -258,13 +810,45 @@ pub fn build_state(config: Config) -> Result<Arc<AppState>, anyhow::Error> {
         usage_logger,
         database,
         web_dir,
+        channel_clients: moka::sync::Cache::new(16),
+        key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+        secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+        model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+        vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+        dns_cache,
     }))
 }
 
+/// Scrape path from `metrics.path`, falling back to `/metrics`.
+fn metrics_path(metrics: &crate::config::Metrics) -> String {
+    if metrics.path.starts_with('/') {
+        metrics.path.clone()
+    } else {
+        "/metrics".to_string()
+    }
+}
+
+/// Router for the dedicated `metrics.listen` address: only the scrape
+/// endpoint, guarded by `metrics.auth_token` instead of the global keys.
+pub fn build_metrics_app(state: Arc<AppState>) -> Router {
+    let path = metrics_path(&state.config.read().unwrap().metrics);
+    Router::new()
+        .route(&path, get(metrics_handler))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            metrics_auth,
+        ))
+        .with_state(state)
+}
+
 pub fn build_app(state: Arc<AppState>) -> Router {
     let config = state.config.read().unwrap();
     let metrics_enabled = config.metrics.enabled;
+    // With a dedicated listener the scrape endpoint moves off the main port.
+    let main_port_metrics_path =
+        (config.metrics.listen.is_none()).then(|| metrics_path(&config.metrics));
     let cors_allowed_origins = config.global.cors_allowed_origins.clone();
+    let compression = compression_layer(config.global.compression.as_ref());
     drop(config);
 
     // Model Routes (Protected by Team Auth)
@@ -272,13 +856,43 @@ pub fn build_app(state: Arc<AppState>) -> Router {
         .route("/v1/chat/completions", post(handle_openai))
         .route("/v1/completions", post(handle_openai))
         .route("/v1/embeddings", post(handle_openai))
+        .route("/v1/images/generations", post(handle_openai))
+        .route("/v1/images/edits", post(handle_openai))
+        .route("/v1/images/variations", post(handle_openai))
+        .route("/v1/audio/transcriptions", post(handle_openai))
+        .route("/v1/audio/translations", post(handle_openai))
+        .route("/v1/audio/speech", post(handle_openai))
         .route("/v1/models", get(handle_models))
         .route("/v1/messages", post(handle_anthropic))
+        .route(
+            "/v1/messages/batches",
+            get(handle_anthropic_batch_list).post(handle_anthropic_batch_create),
+        )
+        .route(
+            "/v1/messages/batches/:batch_id",
+            get(handle_anthropic_batch_get).delete(handle_anthropic_batch_delete),
+        )
+        .route(
+            "/v1/messages/batches/:batch_id/results",
+            get(handle_anthropic_batch_results),
+        )
+        .route(
+            "/v1/messages/batches/:batch_id/cancel",
+            post(handle_anthropic_batch_cancel),
+        )
         .route("/v1/responses", post(handle_openai))
+        .route("/v1/realtime", get(handle_realtime))
+        .route("/v1/*path", axum::routing::any(handle_passthrough))
         // Compatibility routes (no /v1 prefix)
         .route("/chat/completions", post(handle_openai))
         .route("/completions", post(handle_openai))
         .route("/embeddings", post(handle_openai))
+        .route("/images/generations", post(handle_openai))
+        .route("/images/edits", post(handle_openai))
+        .route("/images/variations", post(handle_openai))
+        .route("/audio/transcriptions", post(handle_openai))
+        .route("/audio/translations", post(handle_openai))
+        .route("/audio/speech", post(handle_openai))
         .route("/models", get(handle_models))
         .route("/messages", post(handle_anthropic))
         .route("/responses", post(handle_openai))
@@ -290,9 +904,21 @@ pub fn build_app(state: Arc<AppState>) -> Router {
             state.clone(),
             team_policy,
         ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ip_rate_limit,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            load_shed,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             team_auth,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            tenant_scope,
         ));
 
     let gemini_native_routes = Router::new()
@@ -303,9 +929,21 @@ pub fn build_app(state: Arc<AppState>) -> Router {
             state.clone(),
             team_policy,
         ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ip_rate_limit,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            load_shed,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             team_auth,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            tenant_scope,
         ));
 
     // Admin/System Routes (no auth required)
@@ -319,7 +957,13 @@ pub fn build_app(state: Arc<AppState>) -> Router {
         .route("/admin/teams/api_keys", get(handle_admin_teams_api_keys))
         .route(
             "/admin/teams/:team_id",
-            patch(handle_admin_update_team).delete(handle_admin_delete_team),
+            get(handle_admin_team)
+                .patch(handle_admin_update_team)
+                .delete(handle_admin_delete_team),
+        )
+        .route(
+            "/admin/teams/:team_id/pii/redactions",
+            get(handle_admin_team_pii_redactions),
         )
         // Explicit single-key reveal: returns the *unmasked* api_key for one team.
         // Separate from the masked bulk list so reveals stay auditable.
@@ -327,13 +971,28 @@ pub fn build_app(state: Arc<AppState>) -> Router {
             "/admin/teams/:team_id/api_key",
             get(handle_admin_team_reveal_api_key),
         )
+        .route(
+            "/admin/fault_injection",
+            get(handle_admin_fault_injection).put(handle_admin_update_fault_injection),
+        )
+        .route("/admin/usage/ingest", post(handle_admin_usage_ingest))
+        .route(
+            "/admin/teams/:team_id/usage/users",
+            get(handle_admin_team_end_user_usage),
+        )
+        .route(
+            "/admin/keys/revoked",
+            get(handle_admin_revoked_keys).post(handle_admin_revoke_key),
+        )
         .route(
             "/admin/routers",
             get(handle_admin_routers).post(handle_admin_create_router),
         )
         .route(
             "/admin/routers/:router_name",
-            patch(handle_admin_update_router).delete(handle_admin_delete_router),
+            get(handle_admin_router)
+                .patch(handle_admin_update_router)
+                .delete(handle_admin_delete_router),
         )
         .route(
             "/admin/channels",
@@ -344,9 +1003,14 @@ pub fn build_app(state: Arc<AppState>) -> Router {
             "/admin/channels/api_keys",
             get(handle_admin_channels_api_keys),
         )
+        .route("/admin/channels/health", get(handle_admin_channels_health))
+        .route("/admin/events", get(handle_admin_events))
+        .route("/admin/route/explain", get(handle_admin_route_explain))
         .route(
             "/admin/channels/:channel_name",
-            patch(handle_admin_update_channel).delete(handle_admin_delete_channel),
+            get(handle_admin_channel)
+                .patch(handle_admin_update_channel)
+                .delete(handle_admin_delete_channel),
         )
         .route(
             "/api/cp/provider-templates",
@@ -356,9 +1020,12 @@ pub fn build_app(state: Arc<AppState>) -> Router {
 
     // Metrics (Protected by Global API Key)
     let metrics_routes = if metrics_enabled {
+        let mut routes = Router::new();
+        if let Some(path) = &main_port_metrics_path {
+            routes = routes.route(path, get(metrics_handler));
+        }
         Some(
-            Router::new()
-                .route("/metrics", get(metrics_handler))
+            routes
                 .route("/api/usage", get(usage_api_handler))
                 .route("/api/metrics", get(metrics_api_handler))
                 .route("/api/metrics/trends", get(trends_api_handler))
@@ -368,6 +1035,9 @@ pub fn build_app(state: Arc<AppState>) -> Router {
                     get(dashboard_analytics_api_handler),
                 )
                 .route("/api/dashboard/records", get(dashboard_records_api_handler))
+                .route("/api/stats/traffic", get(stats_traffic_api_handler))
+                .route("/api/stats/spend", get(stats_spend_api_handler))
+                .route("/api/stats/errors", get(stats_errors_api_handler))
                 .layer(axum::middleware::from_fn_with_state(
                     state.clone(),
                     global_auth,
@@ -384,11 +1054,11 @@ pub fn build_app(state: Arc<AppState>) -> Router {
         app = app.merge(metrics);
     }
 
-    // Root landing page (links to the Control Plane UI).
-    // The legacy Next.js dashboard (`/dashboard`, `/_next/static/*`) has been
-    // retired; the Control Plane at `/cp` is the sole web UI. The shared
-    // `/api/dashboard/*` analytics endpoints stay in place — the Control Plane
-    // consumes them.
+    // Root landing page (links to the Control Plane UI and the dashboard).
+    // The legacy Next.js dashboard (`/_next/static/*`) has been retired; the
+    // Control Plane at `/cp` consumes the shared `/api/dashboard/*` analytics
+    // endpoints, and the built-in live dashboard at `/dashboard` polls
+    // `/api/stats/*`.
     let root_routes = Router::new()
         .route(
             "/",
@@ -449,49 +1119,99 @@ pub fn build_app(state: Arc<AppState>) -> Router {
             ),
         );
 
+    // Built-in live dashboard, compiled into the binary. Public like `/cp`;
+    // the page sends the global API key with its API calls. Served only
+    // with the `/api/stats/*` routes it polls.
+    let dashboard_routes = Router::new()
+        .route(
+            "/dashboard",
+            get(|| async { Redirect::permanent("/dashboard/") }),
+        )
+        .route("/dashboard/", get(|| async { crate::dashboard::asset("") }))
+        .route(
+            "/dashboard/*path",
+            get(
+                |axum::extract::Path(path): axum::extract::Path<String>| async move {
+                    crate::dashboard::asset(&path)
+                },
+            ),
+        );
+
     app = app.merge(root_routes);
     app = app.merge(cp_routes);
-
-    app.layer(
-        tower::ServiceBuilder::new()
-            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-            .layer(PropagateRequestIdLayer::x_request_id())
-            .layer(
-                TraceLayer::new_for_http()
-                    .make_span_with(|request: &Request<Body>| {
-                        let request_id = request
-                            .extensions()
-                            .get::<tower_http::request_id::RequestId>()
-                            .map(|id| id.header_value().to_str().unwrap_or("unknown"))
-                            .unwrap_or("unknown");
-                        let client_ip = request
-                            .headers()
-                            .get("x-forwarded-for")
-                            .and_then(|h| h.to_str().ok())
-                            .unwrap_or("unknown");
-
-                        tracing::info_span!("request",
-                            request_id = %request_id,
-                            client_ip = %client_ip,
-                            team_id = tracing::field::Empty,
-                            router_name = tracing::field::Empty,
-                            channel_name = tracing::field::Empty,
-                            method = %request.method(),
-                            uri = %request.uri(),
-                            version = ?request.version()
-                        )
-                    })
-                    .on_response(trace::DefaultOnResponse::new().level(Level::INFO)),
+    if metrics_enabled {
+        app = app.merge(dashboard_routes);
+    }
+
+    let app = app
+        .layer(
+            tower::ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(|request: &Request<Body>| {
+                            let request_id = request
+                                .extensions()
+                                .get::<tower_http::request_id::RequestId>()
+                                .map(|id| id.header_value().to_str().unwrap_or("unknown"))
+                                .unwrap_or("unknown");
+                            let client_ip = request
+                                .headers()
+                                .get("x-forwarded-for")
+                                .and_then(|h| h.to_str().ok())
+                                .unwrap_or("unknown");
+
+                            tracing::info_span!("request",
+                                request_id = %request_id,
+                                client_ip = %client_ip,
+                                team_id = tracing::field::Empty,
+                                router_name = tracing::field::Empty,
+                                channel_name = tracing::field::Empty,
+                                method = %request.method(),
+                                uri = %request.uri(),
+                                version = ?request.version()
+                            )
+                        })
+                        .on_response(trace::DefaultOnResponse::new().level(Level::INFO)),
+                ),
+        )
+        // CORS layer for dashboard frontend
+        .layer(
+            CorsLayer::new()
+                .allow_origin(build_cors_allow_origin(&cors_allowed_origins))
+                .allow_methods(tower_http::cors::Any)
+                .allow_headers(tower_http::cors::Any),
+        )
+        .layer(compression)
+        .with_state(state);
+
+    // `/t/<tenant>/...` is rewritten before routing, so every route is also
+    // reachable under a tenant prefix.
+    Router::new().fallback_service(tower::ServiceExt::map_request(app, strip_tenant_prefix))
+}
+
+/// gzip or brotli for clients whose `Accept-Encoding` allows it; a no-op
+/// unless `global.compression` is set. Event streams keep their framing and
+/// images and audio are already compressed.
+fn compression_layer(
+    compression: Option<&crate::config::Compression>,
+) -> CompressionLayer<impl Predicate + use<>> {
+    let enabled = compression.is_some();
+    let min_bytes = compression.map_or(u16::MAX, |compression| compression.min_bytes);
+    CompressionLayer::new().compress_when(
+        SizeAbove::new(min_bytes)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::SSE)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::const_new("audio/"))
+            .and(
+                move |_: StatusCode,
+                      _: axum::http::Version,
+                      _: &HeaderMap,
+                      _: &axum::http::Extensions| enabled,
             ),
     )
-    // CORS layer for dashboard frontend
-    .layer(
-        CorsLayer::new()
-            .allow_origin(build_cors_allow_origin(&cors_allowed_origins))
-            .allow_methods(tower_http::cors::Any)
-            .allow_headers(tower_http::cors::Any),
-    )
-    .with_state(state)
 }
 
 fn build_cors_allow_origin(cors_allowed_origins: &[String]) -> tower_http::cors::AllowOrigin {
@@ -521,9 +1241,10 @@ fn build_cors_allow_origin(cors_allowed_origins: &[String]) -> tower_http::cors:
 
 async fn serve_index(_state: State<Arc<AppState>>) -> Response<Body> {
     // The legacy Next.js dashboard has been retired. The root page is now a
-    // minimal landing page that points at the Control Plane UI (`/cp`). We no
-    // longer serve the dashboard's `index.html`, so the old UI stays offline
-    // even if its build artifacts remain on disk.
+    // minimal landing page that points at the Control Plane UI (`/cp`) and the
+    // built-in live dashboard (`/dashboard`). We no longer serve the old
+    // dashboard's `index.html`, so it stays offline even if its build
+    // artifacts remain on disk.
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "text/html")
@@ -546,6 +1267,7 @@ async fn serve_index(_state: State<Arc<AppState>>) -> Response<Body> {
     <div class="container">
         <h1>Apex Gateway</h1>
         <p><a href="/cp/">Go to Control Plane</a></p>
+        <p><a href="/dashboard/">Live dashboard</a></p>
     </div>
 </body>
 </html>"#,
@@ -1599,28 +2321,139 @@ async fn dashboard_records_api_handler(
     }
 }
 
-async fn handle_openai(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
-    process_request(state, req, RouteKind::Openai, None, None).await
-}
-
-async fn handle_anthropic(
+/// `GET /api/stats/traffic?window=<seconds>` — request rate, error rate and
+/// latency percentiles over the last `window` seconds (default 300), plus a
+/// per-minute series for the dashboard.
+async fn stats_traffic_api_handler(
     State(state): State<Arc<AppState>>,
-    req: Request<Body>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> Response<Body> {
-    process_request(state, req, RouteKind::Anthropic, None, None).await
+    let window = params
+        .get("window")
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(300)
+        .clamp(60, 86_400);
+    let since =
+        format_dashboard_timestamp(Local::now().naive_local() - ChronoDuration::seconds(window));
+    match state.database.get_traffic(&since) {
+        Ok(traffic) => {
+            let requests: i64 = traffic.minutes.iter().map(|m| m.requests).sum();
+            let errors: i64 = traffic.minutes.iter().map(|m| m.errors).sum();
+            let avg_latency_ms = (!traffic.latencies_ms.is_empty()).then(|| {
+                traffic.latencies_ms.iter().sum::<f64>() / traffic.latencies_ms.len() as f64
+            });
+            let json = json!({
+                "window_secs": window,
+                "requests": requests,
+                "errors": errors,
+                "requests_per_sec": requests as f64 / window as f64,
+                "error_rate": if requests > 0 { errors as f64 / requests as f64 } else { 0.0 },
+                "in_flight": state.in_flight.load(Ordering::Relaxed),
+                "latency_ms": {
+                    "avg": avg_latency_ms,
+                    "p50": traffic.latency_percentile(50.0),
+                    "p95": traffic.latency_percentile(95.0),
+                    "p99": traffic.latency_percentile(99.0),
+                },
+                "series": traffic.minutes,
+            });
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", "application/json")
+                .body(Body::from(json.to_string()))
+                .unwrap()
+        }
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap(),
+    }
 }
 
-async fn handle_gemini_native(
+/// `GET /api/stats/spend?since=<timestamp>` — tokens and cost per team since
+/// `since` (default: start of today).
+async fn stats_spend_api_handler(
     State(state): State<Arc<AppState>>,
-    axum::extract::Path(path): axum::extract::Path<String>,
-    req: Request<Body>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> Response<Body> {
-    let route = match validate_gemini_native_route(req.method(), &path) {
-        Some(route) => route,
-        None => {
-            return gemini_native_error_response(
-                StatusCode::NOT_FOUND,
-                "Gemini native endpoint is not allowlisted",
+    let since = params.get("since").cloned().unwrap_or_else(|| {
+        format_dashboard_timestamp(Local::now().date_naive().and_hms_opt(0, 0, 0).unwrap())
+    });
+    let query = UsageRecordQuery {
+        start_time: Some(since.clone()),
+        ..Default::default()
+    };
+    match state.database.get_usage_cost(&query, "team_id") {
+        Ok(teams) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({ "since": since, "data": teams }).to_string(),
+            ))
+            .unwrap(),
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap(),
+    }
+}
+
+/// `GET /api/stats/errors?limit=<n>` — the latest failed requests, newest
+/// first (default 20, at most 200).
+async fn stats_errors_api_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Response<Body> {
+    let limit = params
+        .get("limit")
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(20)
+        .clamp(1, 200);
+    match state.database.get_recent_errors(limit) {
+        Ok(errors) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "data": errors }).to_string()))
+            .unwrap(),
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap(),
+    }
+}
+
+async fn handle_openai(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
+    let start = std::time::Instant::now();
+    let response = process_request(state.clone(), req, RouteKind::Openai, None, None).await;
+    state
+        .metrics
+        .observe_request_duration("openai", response.status().as_u16(), start);
+    response
+}
+
+async fn handle_anthropic(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let start = std::time::Instant::now();
+    let response = process_request(state.clone(), req, RouteKind::Anthropic, None, None).await;
+    state
+        .metrics
+        .observe_request_duration("anthropic", response.status().as_u16(), start);
+    response
+}
+
+async fn handle_gemini_native(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let route = match validate_gemini_native_route(req.method(), &path) {
+        Some(route) => route,
+        None => {
+            return gemini_native_error_response(
+                StatusCode::NOT_FOUND,
+                "Gemini native endpoint is not allowlisted",
                 "NOT_FOUND",
             );
         }
@@ -1632,353 +2465,2474 @@ async fn handle_gemini_native(
         .extensions
         .insert(OriginalModelName(routing_model.clone()));
     let req = Request::from_parts(parts, body);
-    if route.direct_pass {
-        return process_gemini_native_direct_pass(state, req, routing_model).await;
-    }
-    process_request(state, req, RouteKind::GeminiNative, None, None).await
+    let start = std::time::Instant::now();
+    let response = if route.direct_pass {
+        process_gemini_native_direct_pass(state.clone(), req, routing_model).await
+    } else {
+        process_request(state.clone(), req, RouteKind::GeminiNative, None, None).await
+    };
+    state
+        .metrics
+        .observe_request_duration("gemini_native", response.status().as_u16(), start);
+    response
 }
 
-/// `GET /v1/models` (and `/models`). Returns the list of concrete model ids
-/// the *team* associated with the inbound API key is allowed to call, in
-/// OpenAI's list-models format. Admin / global keys are intentionally
-/// rejected here — this endpoint exists to bootstrap end-user clients, not
-/// to power admin tooling.
-async fn handle_models(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
-    let team_id = match req.extensions().get::<TeamContext>() {
-        Some(ctx) => ctx.team_id.clone(),
-        None => {
-            return error_response(
-                StatusCode::UNAUTHORIZED,
-                "Team API Key required: /v1/models only resolves models for a specific team",
-            );
-        }
-    };
+// ----- Raw pass-through ----------------------------------------------------
+//
+// `/v1/*` paths without a dedicated handler reach routers that opt in with
+// `passthrough: true`. The channel is picked by the body's `model` (as for
+// any other request), its credentials are applied, and everything else —
+// method, query, body and the upstream response — is forwarded unchanged.
+
+const PASSTHROUGH_LABEL: &str = "passthrough";
 
+async fn handle_passthrough(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let route = RouteKind::Openai;
+    let (parts, body) = req.into_parts();
     let config = state.config.read().unwrap().clone();
-    let Some(team) = config.teams.iter().find(|t| t.id == team_id) else {
-        return error_response(StatusCode::UNAUTHORIZED, "Team not found");
+    let body_limit = request_body_limit(&config.global, &parts.headers);
+    let bytes = match axum::body::to_bytes(body, body_limit).await {
+        Ok(b) => b,
+        Err(e) => return body_read_error(route, body_limit, e),
     };
-
-    // -- 1. Collect candidate model ids ------------------------------------
-    // Anything that is a literal model name in the rules of a router this
-    // team is allowed to use. Glob patterns like "*" / "deepseek-*" are
-    // skipped because OpenAI's list-models payload requires concrete ids.
-    let mut candidates: BTreeSet<String> = BTreeSet::new();
-    for router_name in &team.policy.allowed_routers {
-        let Some(router) = config.routers.iter().find(|r| &r.name == router_name) else {
-            continue;
-        };
-        for rule in &router.rules {
-            for pattern in &rule.match_spec.models {
-                if !is_glob_pattern(pattern) {
-                    candidates.insert(pattern.clone());
-                }
-            }
-        }
+    let team = match request_caller(&config, &parts, route) {
+        Ok(team) => team,
+        Err(resp) => return resp,
+    };
+    let team_id = team.map_or_else(|| "global".to_string(), |t| t.id.clone());
+    let model = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|json| json.get("model")?.as_str().map(str::to_string))
+        .or_else(|| crate::images::multipart_field(&bytes, "model"))
+        .unwrap_or_else(|| "default".to_string());
+    if let Some(team) = team
+        && !team.policy.is_model_allowed(&model)
+    {
+        tracing::warn!(
+            "Policy Failed: Model '{}' not allowed by team policy (passthrough)",
+            model
+        );
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed by team policy",
+        );
     }
-
-    // Augment with concrete model ids actually observed in the usage log for
-    // this team. This covers the common case where the only router rule is
-    // a glob (e.g. `deepseek-*`) — without history the team would otherwise
-    // see an empty list.
-    if let Ok(history) = state.database.distinct_models_for_team(&team_id) {
-        candidates.extend(history);
+    if !key_allows_model(&parts, &model) {
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed for this API key",
+        );
     }
 
-    // -- 2. Filter by team policy + verify a router can actually route it --
-    let mut entries: Vec<serde_json::Value> = Vec::with_capacity(candidates.len());
-    for model in candidates {
-        if !team.policy.is_model_allowed(&model) {
-            continue;
-        }
-
-        let Some((router_name, channel_name)) =
-            resolve_model_to_router_channel(&config, &team.policy.allowed_routers, &model, &state)
-        else {
-            continue;
-        };
-
-        let owned_by = config
-            .channels
+    let request_tenant = parts
+        .extensions
+        .get::<TenantContext>()
+        .map(|ctx| ctx.tenant_id.as_str());
+    let routers: Vec<&crate::config::Router> = match team {
+        Some(team) => team
+            .policy
+            .allowed_routers
             .iter()
-            .find(|c| c.name == channel_name)
-            .map(|c| format!("{:?}", c.provider_type).to_lowercase())
-            .unwrap_or_else(|| "apex".to_string());
+            .filter_map(|name| config.routers.iter().find(|r| r.name == *name))
+            .collect(),
+        None => config
+            .routers
+            .iter()
+            .filter(|r| config.tenant_of(&r.name) == request_tenant)
+            .collect(),
+    };
+    let selected = routers
+        .into_iter()
+        .filter(|router| router.passthrough)
+        .find_map(|router| {
+            let selection = state.selector.select_channel_with_rule(router, &model)?;
+            let channel = config
+                .channels
+                .iter()
+                .find(|c| c.name == selection.channel_name)?;
+            Some((router, channel))
+        });
+    let Some((router, channel)) = selected else {
+        return protocol_error_response(
+            route,
+            StatusCode::NOT_FOUND,
+            &format!("Unknown endpoint: {}", parts.uri.path()),
+        );
+    };
 
-        entries.push(json!({
-            "id": model,
-            "object": "model",
-            "created": 0,
-            "owned_by": owned_by,
-            "apex": {
-                "router": router_name,
-                "channel": channel_name,
-            }
-        }));
+    let keyed = state.key_pools.keyed(channel);
+    let prepared = match crate::providers::prepare_passthrough_request(
+        &state.providers,
+        &keyed,
+        parts.uri.path(),
+        parts.uri.query(),
+        &parts.headers,
+        &bytes,
+    ) {
+        Ok(prepared) => prepared,
+        Err(e) => return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    tracing::info!(
+        "Upstream Request: method={} url={} (passthrough, router={} channel={})",
+        parts.method,
+        prepared.url,
+        router.name,
+        channel.name
+    );
+    let mut request = state
+        .client
+        .request(parts.method.clone(), prepared.url)
+        .headers(prepared.headers);
+    if !bytes.is_empty() {
+        request = request.body(prepared.body);
     }
+    let request = match request.build() {
+        Ok(request) => request,
+        Err(e) => {
+            return protocol_error_response(
+                route,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &e.to_string(),
+            );
+        }
+    };
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(
-            json!({
-                "object": "list",
-                "data": entries,
-            })
-            .to_string(),
-        ))
-        .unwrap()
-}
-
-/// A pattern is "glob-like" if it contains any of the meta-characters glob
-/// would interpret. Bare literal model ids (which is what OpenAI's list
-/// endpoint must return) contain none of these.
-fn is_glob_pattern(pattern: &str) -> bool {
-    pattern.chars().any(|c| matches!(c, '*' | '?' | '[' | ']'))
-}
-
-/// Try each of the team's allowed routers; if one can route the given model
-/// to a concrete channel, return `(router_name, channel_name)`. We avoid
-/// returning models the gateway would actually 404 on.
-fn resolve_model_to_router_channel(
-    config: &Config,
-    allowed_routers: &[String],
-    model: &str,
-    state: &AppState,
-) -> Option<(String, String)> {
-    for router_name in allowed_routers {
-        let Some(router) = config.routers.iter().find(|r| &r.name == router_name) else {
-            continue;
-        };
-        if let Some(channel) = state.selector.select_channel(router, model) {
-            return Some((router.name.clone(), channel));
+    state
+        .metrics
+        .request_total
+        .with_label_values(&[PASSTHROUGH_LABEL, &router.name])
+        .inc();
+    state.database.log_request(PASSTHROUGH_LABEL, &router.name);
+    let start = std::time::Instant::now();
+    let result = execute_upstream(&state, channel, request, None).await;
+    report_pool_key(&state, channel, &keyed.api_key, &result);
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::warn!(
+                "Passthrough upstream request failed: {}",
+                format_error_chain(&e)
+            );
+            state
+                .metrics
+                .error_total
+                .with_label_values(&[PASSTHROUGH_LABEL, &router.name])
+                .inc();
+            state.database.log_error(PASSTHROUGH_LABEL, &router.name);
+            return protocol_error_response(
+                route,
+                StatusCode::BAD_GATEWAY,
+                &format!("upstream request failed: {e}"),
+            );
         }
-    }
-    None
+    };
+    let resp = match response_body_limit(&config.global, Some(router), channel, resp.headers()) {
+        Some(limit) => match limit_response_size(resp, limit).await {
+            Ok(resp) => resp,
+            Err(size) => {
+                tracing::warn!(
+                    "Response Rejected: channel '{}' returned at least {} bytes (limit {})",
+                    channel.name,
+                    size,
+                    limit
+                );
+                state
+                    .metrics
+                    .size_limit_exceeded_total
+                    .with_label_values(&[&router.name, "response"])
+                    .inc();
+                return protocol_error_response(
+                    route,
+                    StatusCode::BAD_GATEWAY,
+                    &format!("Upstream response exceeds {} bytes", limit),
+                );
+            }
+        },
+        None => resp,
+    };
+    let elapsed = start.elapsed().as_millis() as f64;
+    let response = crate::providers::convert_response(
+        resp,
+        Duration::from_millis(
+            channel
+                .effective_timeouts(&config.global.timeouts)
+                .response_ms,
+        ),
+    );
+    crate::usage::wrap_response(
+        response,
+        request_id_from_parts(&parts),
+        team_id,
+        router.name.clone(),
+        Some(PASSTHROUGH_LABEL.to_string()),
+        channel.name.clone(),
+        model,
+        state.usage_logger.clone(),
+        state.metrics.clone(),
+        Some(elapsed),
+        false,
+        crate::utils::classify_client(&parts.headers),
+    )
+    .await
 }
 
-async fn handle_admin_teams(
+// ----- Anthropic Message Batches -------------------------------------------
+//
+// Batches are stateful upstream objects, so every follow-up call must reach the
+// account that created the batch. The creating channel is recorded per batch id
+// (`message_batches` table) and reused for retrieve/results/cancel/delete.
+
+const MESSAGE_BATCHES_PATH: &str = "/v1/messages/batches";
+/// Largest `limit` a batch list request may ask for.
+const MAX_BATCH_LIST_LIMIT: usize = 100;
+/// Upstream pages one batch list request may read while skipping other
+/// teams' batches.
+const MAX_BATCH_LIST_PAGES: usize = 5;
+
+async fn handle_anthropic_batch_create(
     State(state): State<Arc<AppState>>,
     req: Request<Body>,
 ) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let headers = &parts.headers;
-
+    let route = RouteKind::Anthropic;
+    let (parts, body) = req.into_parts();
     let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, headers) {
-        return resp;
-    }
+    let body_limit = request_body_limit(&config.global, &parts.headers);
+    let bytes = match axum::body::to_bytes(body, body_limit).await {
+        Ok(b) => b,
+        Err(e) => return body_read_error(route, body_limit, e),
+    };
+    let team = match request_caller(&config, &parts, RouteKind::Anthropic) {
+        Ok(team) => team,
+        Err(resp) => return resp,
+    };
+    let team_id = team.map_or_else(|| "global".to_string(), |t| t.id.clone());
 
-    let data = config
-        .teams
-        .iter()
-        .map(|team| {
-            let rate_limit = team.policy.rate_limit.as_ref().map(|l| {
-                json!({
-                    "rpm": l.rpm,
-                    "tpm": l.tpm
-                })
-            });
-            // NOTE: api_key is intentionally NOT included in the list
-            // response. Fetch it explicitly via GET /admin/teams/api_keys.
-            json!({
-                "id": team.id,
-                "group": team.group,
-                "enabled": team.enabled.unwrap_or(true),
-                "policy": {
-                    "allowed_routers": team.policy.allowed_routers,
-                    "allowed_models": team.policy.allowed_models,
-                    "rate_limit": rate_limit
-                }
-            })
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return protocol_error_response(route, StatusCode::BAD_REQUEST, "invalid JSON body");
+    };
+    let models: Vec<String> = json
+        .get("requests")
+        .and_then(|v| v.as_array())
+        .map(|requests| {
+            requests
+                .iter()
+                .filter_map(|r| r.pointer("/params/model").and_then(|m| m.as_str()))
+                .map(str::to_string)
+                .collect()
         })
-        .collect::<Vec<_>>();
+        .unwrap_or_default();
+    // The first request's model picks the channel for the whole batch.
+    let Some(model) = models.first().cloned() else {
+        return protocol_error_response(
+            route,
+            StatusCode::BAD_REQUEST,
+            "batch requests must specify params.model",
+        );
+    };
+    if let Some(team) = team
+        && let Some(denied) = models.iter().find(|m| !team.policy.is_model_allowed(m))
+    {
+        tracing::warn!(
+            "Policy Failed: Model '{}' not allowed by team policy (batch)",
+            denied
+        );
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed by team policy",
+        );
+    }
+    if models.iter().any(|m| !key_allows_model(&parts, m)) {
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed for this API key",
+        );
+    }
+    let Some((router_name, channel)) = resolve_batch_channel(&state, &config, team, &model) else {
+        return protocol_error_response(
+            route,
+            StatusCode::NOT_FOUND,
+            "No Anthropic channel found for batch model",
+        );
+    };
+
+    if let Some(model_map) = &channel.model_map
+        && let Some(requests) = json.get_mut("requests").and_then(|v| v.as_array_mut())
+    {
+        for request in requests {
+            if let Some(value) = request.pointer_mut("/params/model")
+                && let Some(mapped) = value.as_str().and_then(|name| model_map.get(name))
+            {
+                *value = serde_json::Value::String(mapped.clone());
+            }
+        }
+    }
+    let body = match serde_json::to_vec(&json) {
+        Ok(body) => Bytes::from(body),
+        Err(e) => return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string()),
+    };
 
+    tracing::info!(
+        "Batch Create: router={} channel={} model={} requests={}",
+        router_name,
+        channel.name,
+        model,
+        models.len()
+    );
+    let (status, headers, resp_bytes) = match forward_batch_request(
+        &state,
+        channel,
+        Method::POST,
+        MESSAGE_BATCHES_PATH,
+        None,
+        &parts.headers,
+        body,
+    )
+    .await
+    {
+        Ok(resp) => resp,
+        Err(resp) => return resp,
+    };
+
+    if status.is_success()
+        && let Some(batch_id) = serde_json::from_slice::<serde_json::Value>(&resp_bytes)
+            .ok()
+            .and_then(|v| v.get("id").and_then(|id| id.as_str()).map(str::to_string))
+        && let Err(e) = state.database.record_message_batch(
+            &batch_id,
+            &team_id,
+            &router_name,
+            &channel.name,
+            &model,
+        )
+    {
+        tracing::error!("Failed to record batch affinity for {}: {}", batch_id, e);
+    }
+
+    response_from_upstream_bytes(status, &headers, resp_bytes)
+}
+
+async fn handle_anthropic_batch_list(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    let team = match request_caller(&config, &parts, RouteKind::Anthropic) {
+        Ok(team) => team,
+        Err(resp) => return resp,
+    };
+    let team_id = team.map_or_else(|| "global".to_string(), |t| t.id.clone());
+
+    // Listing is scoped to one upstream account: use the channel of the
+    // caller's most recent batch. No batches yet means an empty page.
+    let channel = match state.database.latest_message_batch_for_team(&team_id) {
+        Ok(Some(record)) => config.channels.iter().find(|c| c.name == record.channel),
+        Ok(None) => None,
+        Err(e) => {
+            return protocol_error_response(
+                RouteKind::Anthropic,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &e.to_string(),
+            );
+        }
+    };
+    let Some(channel) = channel else {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"data": [], "has_more": false, "first_id": null, "last_id": null})
+                    .to_string(),
+            ))
+            .unwrap();
+    };
+
+    let owned = match state
+        .database
+        .message_batch_ids_for_team(&team_id, &channel.name)
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            return protocol_error_response(
+                RouteKind::Anthropic,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &e.to_string(),
+            );
+        }
+    };
+
+    // The upstream account is shared by every team routed to this channel:
+    // keep only the caller's batches, and page past other teams' ones (up to
+    // MAX_BATCH_LIST_PAGES) so a filtered page doesn't end the listing early.
+    let params: Vec<(String, String)> = parts
+        .uri
+        .query()
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let limit = params
+        .iter()
+        .find(|(key, _)| key == "limit")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(20)
+        .clamp(1, MAX_BATCH_LIST_LIMIT);
+    let backwards = params.iter().any(|(key, _)| key == "before_id");
+    let cursor = if backwards { "before_id" } else { "after_id" };
+    let page_query = |position: Option<&str>| {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in params
+            .iter()
+            .filter(|(key, _)| key != "limit" && (position.is_none() || key != cursor))
+        {
+            serializer.append_pair(key, value);
+        }
+        serializer.append_pair("limit", &limit.to_string());
+        if let Some(position) = position {
+            serializer.append_pair(cursor, position);
+        }
+        serializer.finish()
+    };
+    let mut query = page_query(None);
+    let mut data: Vec<serde_json::Value> = Vec::new();
+    let mut has_more = false;
+    // Where the last upstream page ended, for a reply with none of the
+    // caller's batches in it.
+    let mut scanned: Option<serde_json::Value> = None;
+    for page_number in 1.. {
+        let (status, headers, bytes) = match forward_batch_request(
+            &state,
+            channel,
+            Method::GET,
+            MESSAGE_BATCHES_PATH,
+            Some(&query),
+            &parts.headers,
+            Bytes::new(),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(resp) => return resp,
+        };
+        if !status.is_success() {
+            return response_from_upstream_bytes(status, &headers, bytes);
+        }
+        let Ok(page) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+            return protocol_error_response(
+                RouteKind::Anthropic,
+                StatusCode::BAD_GATEWAY,
+                "upstream returned an invalid batch list",
+            );
+        };
+        let mut kept: Vec<serde_json::Value> = page["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|batch| batch["id"].as_str().is_some_and(|id| owned.contains(id)))
+            .cloned()
+            .collect();
+        if backwards {
+            kept.append(&mut data);
+            data = kept;
+        } else {
+            data.append(&mut kept);
+        }
+        has_more = page["has_more"].as_bool().unwrap_or(false);
+        let next = if backwards {
+            page["first_id"].as_str()
+        } else {
+            page["last_id"].as_str()
+        };
+        scanned = next.map(serde_json::Value::from);
+        match next {
+            Some(next) if has_more && data.len() < limit && page_number < MAX_BATCH_LIST_PAGES => {
+                query = page_query(Some(next));
+            }
+            _ => break,
+        }
+    }
+    if data.len() > limit {
+        has_more = true;
+        if backwards {
+            data.drain(..data.len() - limit);
+        } else {
+            data.truncate(limit);
+        }
+    }
+    let (first_id, last_id) = match (data.first(), data.last()) {
+        (Some(first), Some(last)) => (Some(first["id"].clone()), Some(last["id"].clone())),
+        // Only other teams' batches within the page budget: hand back where
+        // the scan stopped so the next request carries on from there.
+        _ if has_more => (scanned.clone(), scanned),
+        _ => (None, None),
+    };
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
         .body(Body::from(
             json!({
-                "object": "list",
-                "data": data
+                "data": data,
+                "has_more": has_more,
+                "first_id": first_id,
+                "last_id": last_id,
             })
             .to_string(),
         ))
         .unwrap()
 }
 
-// -------- Teams CRUD --------
-
-#[derive(serde::Deserialize, Default)]
-struct CreateTeamRequest {
-    id: String,
-    #[serde(default)]
-    api_key: Option<String>,
-    #[serde(default)]
-    group: Option<String>,
-    #[serde(default)]
-    enabled: Option<bool>,
-    #[serde(default)]
-    allowed_routers: Option<Vec<String>>,
-    #[serde(default)]
-    allowed_models: Option<Vec<String>>,
-    #[serde(default)]
-    rate_limit: Option<TeamRateLimitInput>,
+async fn handle_anthropic_batch_get(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(batch_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    forward_existing_batch(state, req, &batch_id, "").await
 }
 
-#[derive(serde::Deserialize, Default)]
-struct UpdateTeamRequest {
-    #[serde(default)]
-    group: Option<Option<String>>,
-    #[serde(default)]
-    enabled: Option<bool>,
-    #[serde(default)]
-    allowed_routers: Option<Vec<String>>,
-    /// `Some(None)` means "clear" (no allowlist → all models). `Some(Some(_))` sets.
-    /// `None` leaves the field unchanged.
-    #[serde(default, deserialize_with = "deserialize_optional_optional_vec")]
-    allowed_models: Option<Option<Vec<String>>>,
-    #[serde(default, deserialize_with = "deserialize_optional_optional_rate_limit")]
-    rate_limit: Option<Option<TeamRateLimitInput>>,
+async fn handle_anthropic_batch_results(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(batch_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    forward_existing_batch(state, req, &batch_id, "/results").await
 }
 
-#[derive(serde::Deserialize, Default, Clone)]
-struct TeamRateLimitInput {
-    #[serde(default)]
-    rpm: Option<i32>,
-    #[serde(default)]
-    tpm: Option<i32>,
+async fn handle_anthropic_batch_cancel(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(batch_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    forward_existing_batch(state, req, &batch_id, "/cancel").await
 }
 
-fn deserialize_optional_optional_vec<'de, D>(
-    deserializer: D,
-) -> Result<Option<Option<Vec<String>>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::Deserialize;
-    // Accept null (= clear) or array.
-    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Null => Ok(Some(None)),
-        serde_json::Value::Array(items) => {
-            let parsed = items
-                .into_iter()
-                .map(|item| match item {
-                    serde_json::Value::String(s) => Ok(s),
-                    other => Err(serde::de::Error::custom(format!(
-                        "allowed_models entries must be strings, got {other:?}"
-                    ))),
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-            Ok(Some(Some(parsed)))
-        }
-        other => Err(serde::de::Error::custom(format!(
-            "allowed_models must be null or an array, got {other:?}"
-        ))),
-    }
+async fn handle_anthropic_batch_delete(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(batch_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    forward_existing_batch(state, req, &batch_id, "").await
 }
 
-fn deserialize_optional_optional_rate_limit<'de, D>(
-    deserializer: D,
-) -> Result<Option<Option<TeamRateLimitInput>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::Deserialize;
-    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Null => Ok(Some(None)),
-        other => {
-            let parsed: TeamRateLimitInput =
-                serde_json::from_value(other).map_err(serde::de::Error::custom)?;
-            Ok(Some(Some(parsed)))
-        }
+async fn forward_existing_batch(
+    state: Arc<AppState>,
+    req: Request<Body>,
+    batch_id: &str,
+    suffix: &str,
+) -> Response<Body> {
+    let route = RouteKind::Anthropic;
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    let team = match request_caller(&config, &parts, RouteKind::Anthropic) {
+        Ok(team) => team,
+        Err(resp) => return resp,
+    };
+    if batch_id.is_empty()
+        || !batch_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return protocol_error_response(route, StatusCode::BAD_REQUEST, "invalid batch id");
     }
-}
 
-fn generate_team_api_key() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or(0);
-    // Hex-encoded timestamp + a small random suffix from process state.
-    // Not cryptographically strong but unique per-team for in-config usage.
-    let pid = std::process::id() as u128;
-    let entropy = nanos.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(pid);
-    format!("sk-apex-{entropy:032x}")
-}
-
-fn persist_config(config: &Config) -> Result<(), String> {
-    let path = PathBuf::from(&config.hot_reload.config_path);
-    if path.as_os_str().is_empty() {
-        return Err("hot_reload.config_path is empty".into());
+    // Team callers only see their own batches; global (admin) callers see all.
+    let record = match state.database.get_message_batch(batch_id) {
+        Ok(Some(record)) if team.is_none_or(|t| t.id == record.team_id) => record,
+        Ok(_) => return protocol_error_response(route, StatusCode::NOT_FOUND, "batch not found"),
+        Err(e) => {
+            return protocol_error_response(
+                route,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &e.to_string(),
+            );
+        }
+    };
+    let Some(channel) = config.channels.iter().find(|c| c.name == record.channel) else {
+        return protocol_error_response(
+            route,
+            StatusCode::BAD_GATEWAY,
+            &format!(
+                "channel '{}' that created this batch is no longer configured",
+                record.channel
+            ),
+        );
+    };
+
+    let path = format!("{MESSAGE_BATCHES_PATH}/{batch_id}{suffix}");
+    let (status, headers, bytes) = match forward_batch_request(
+        &state,
+        channel,
+        parts.method.clone(),
+        &path,
+        parts.uri.query(),
+        &parts.headers,
+        Bytes::new(),
+    )
+    .await
+    {
+        Ok(resp) => resp,
+        Err(resp) => return resp,
+    };
+
+    if suffix == "/results" && status.is_success() {
+        account_batch_results(&state, &record, &bytes);
     }
-    crate::config::save_config(&path, config).map_err(|e| e.to_string())
+    response_from_upstream_bytes(status, &headers, bytes)
 }
 
-/// Atomically apply a configuration mutation.
-///
-/// This is the single write path shared by every admin CRUD handler. It closes
-/// two classes of bug that arise when validation, mutation and persistence are
-/// done across separate lock acquisitions:
-///
-///   * **TOCTOU** — the closure runs while the write lock is held and validates
-///     against a private `candidate` clone of the *current* config, so a
-///     concurrent writer can't invalidate a check between validate and apply.
-///   * **memory/disk divergence** — the candidate is persisted to disk *before*
-///     it is committed to the in-memory `Config`. If the disk write fails, the
-///     live config is left untouched and the handler returns an error, instead
-///     of silently keeping an unpersisted change that vanishes on restart.
-///
-/// The closure receives `&mut Config` (the candidate) and returns either a
-/// success value (used to build the response) or an error `Response` to abort
-/// the whole operation with no change.
-///
-/// Note: the file write happens while the write lock is held. Admin mutations
-/// are rare and the proxy hot-path only takes *read* locks, so the brief stall
-/// is an acceptable tradeoff for atomicity.
-fn commit_config<T>(
+/// Resolve the caller of a batch or pass-through request: the authenticated
+/// team, or `None` for global-key callers.
+fn request_caller<'a>(
+    config: &'a Config,
+    parts: &axum::http::request::Parts,
+    route: RouteKind,
+) -> Result<Option<&'a crate::config::Team>, Response<Body>> {
+    match parts.extensions.get::<TeamContext>() {
+        Some(ctx) => config
+            .teams
+            .iter()
+            .find(|t| t.id == ctx.team_id)
+            .map(Some)
+            .ok_or_else(|| {
+                protocol_error_response(route, StatusCode::UNAUTHORIZED, "Team not found")
+            }),
+        None => enforce_global_auth(config, &parts.headers)
+            .map(|_| None)
+            .map_err(|_| protocol_error_response(route, StatusCode::UNAUTHORIZED, "unauthorized")),
+    }
+}
+
+/// Whether the managed key behind the request (if any) may call `model`.
+fn key_allows_model(parts: &axum::http::request::Parts, model: &str) -> bool {
+    parts
+        .extensions
+        .get::<TeamContext>()
+        .is_none_or(|ctx| ctx.allows_model(model))
+}
+
+/// First router (team order, else config order) whose selected channel for
+/// `model` is Anthropic-native. Batches are an Anthropic-only API.
+fn resolve_batch_channel<'a>(
     state: &AppState,
-    mutate: impl FnOnce(&mut Config) -> Result<T, Response<Body>>,
-) -> Result<T, Response<Body>> {
-    let mut guard = state.config.write().unwrap();
-    let mut candidate = guard.clone();
-    let value = mutate(&mut candidate)?;
-    if let Err(err) = persist_config(&candidate) {
-        tracing::error!("Failed to persist config change: {err}");
-        return Err(error_response(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            &format!("Failed to persist config: {err}"),
-        ));
+    config: &'a Config,
+    team: Option<&crate::config::Team>,
+    model: &str,
+) -> Option<(String, &'a crate::config::Channel)> {
+    let routers: Vec<&crate::config::Router> = match team {
+        Some(team) => team
+            .policy
+            .allowed_routers
+            .iter()
+            .filter_map(|name| config.routers.iter().find(|r| r.name == *name))
+            .collect(),
+        None => config.routers.iter().collect(),
+    };
+    routers.into_iter().find_map(|router| {
+        let selection = state.selector.select_channel_with_rule(router, model)?;
+        config
+            .channels
+            .iter()
+            .find(|c| {
+                c.name == selection.channel_name
+                    && c.provider_type == crate::config::ProviderType::Anthropic
+            })
+            .map(|channel| (router.name.clone(), channel))
+    })
+}
+
+async fn forward_batch_request(
+    state: &AppState,
+    channel: &crate::config::Channel,
+    method: Method,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, HeaderMap, Bytes), Response<Body>> {
+    let route = RouteKind::Anthropic;
+    let prepared = prepare_request(
+        &state.providers,
+        &state.key_pools.keyed(channel),
+        route,
+        &channel.base_url,
+        path,
+        query,
+        headers,
+        &body,
+    )
+    .map_err(|e| protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string()))?;
+
+    tracing::info!(
+        "Upstream Request: method={} url={} (batch)",
+        method,
+        prepared.url
+    );
+    let mut request = state
+        .client_for(channel)
+        .request(method, prepared.url)
+        .headers(prepared.headers);
+    if !body.is_empty() {
+        request = request.body(prepared.body);
+    }
+    let resp = request.send().await.map_err(|e| {
+        tracing::warn!("Batch upstream request failed: {}", format_error_chain(&e));
+        protocol_error_response(
+            route,
+            StatusCode::BAD_GATEWAY,
+            &format!("upstream request failed: {e}"),
+        )
+    })?;
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let bytes = resp.bytes().await.map_err(|e| {
+        protocol_error_response(
+            route,
+            StatusCode::BAD_GATEWAY,
+            &format!("failed to read upstream response: {e}"),
+        )
+    })?;
+    Ok((status, headers, bytes))
+}
+
+/// Sum `usage` across a batch results JSONL document.
+fn batch_results_usage(bytes: &[u8]) -> (u64, u64) {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|line| line.pointer("/result/message/usage").cloned())
+        .fold((0, 0), |(input, output), usage| {
+            (
+                input + usage["input_tokens"].as_u64().unwrap_or(0),
+                output + usage["output_tokens"].as_u64().unwrap_or(0),
+            )
+        })
+}
+
+/// Record token usage for a batch the first time its results are fetched.
+fn account_batch_results(
+    state: &AppState,
+    record: &crate::database::MessageBatchRecord,
+    bytes: &[u8],
+) {
+    match state
+        .database
+        .mark_message_batch_usage_logged(&record.batch_id)
+    {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            tracing::error!(
+                "Failed to mark batch {} as accounted: {}",
+                record.batch_id,
+                e
+            );
+            return;
+        }
+    }
+    let (input_tokens, output_tokens) = batch_results_usage(bytes);
+    let model_lower = record.model.to_lowercase();
+    state
+        .metrics
+        .token_total
+        .with_label_values(&[&record.router, &record.channel, &model_lower, "input"])
+        .inc_by(input_tokens);
+    state
+        .metrics
+        .token_total
+        .with_label_values(&[&record.router, &record.channel, &model_lower, "output"])
+        .inc_by(output_tokens);
+    state.usage_logger.log(
+        Some(&record.batch_id),
+        &record.team_id,
+        &record.router,
+        Some("batch"),
+        &record.channel,
+        &record.model,
+        input_tokens,
+        output_tokens,
+        None,
+        false,
+        &crate::utils::ClientInfo::default(),
+    );
+}
+
+/// `GET /v1/realtime?model=...`: authenticate and route like a chat request,
+/// then relay the WebSocket session to the selected channel
+/// (see `crate::realtime`). Usage is logged when the session ends.
+async fn handle_realtime(
+    State(state): State<Arc<AppState>>,
+    ws: axum::extract::ws::WebSocketUpgrade,
+    parts: axum::http::request::Parts,
+) -> Response<Body> {
+    let route = RouteKind::Openai;
+    let config = state.config.read().unwrap().clone();
+    let team = match parts.extensions.get::<TeamContext>() {
+        Some(ctx) => match config.teams.iter().find(|t| t.id == ctx.team_id) {
+            Some(team) => Some(team),
+            None => {
+                return protocol_error_response(route, StatusCode::UNAUTHORIZED, "Team not found");
+            }
+        },
+        None => {
+            if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
+                return resp;
+            }
+            None
+        }
+    };
+    let team_id = team.map_or_else(|| "global".to_string(), |t| t.id.clone());
+    let Some(model) = parts.uri.query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "model")
+            .map(|(_, value)| value.into_owned())
+    }) else {
+        return protocol_error_response(
+            route,
+            StatusCode::BAD_REQUEST,
+            "realtime sessions require a model query parameter",
+        );
+    };
+    if let Some(team) = team
+        && !team.policy.is_model_allowed(&model)
+    {
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed by team policy",
+        );
+    }
+    if !key_allows_model(&parts, &model) {
+        return protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed for this API key",
+        );
+    }
+
+    let request_tenant = parts
+        .extensions
+        .get::<TenantContext>()
+        .map(|ctx| ctx.tenant_id.as_str());
+    let routers: Vec<&crate::config::Router> = match team {
+        Some(team) => team
+            .policy
+            .allowed_routers
+            .iter()
+            .filter_map(|name| config.routers.iter().find(|r| r.name == *name))
+            .collect(),
+        None => config
+            .routers
+            .iter()
+            .filter(|r| config.tenant_of(&r.name) == request_tenant)
+            .collect(),
+    };
+    let Some((router_name, channel)) = routers.into_iter().find_map(|router| {
+        let selection = state.selector.select_channel(router, &model)?;
+        config
+            .channels
+            .iter()
+            .find(|c| c.name == selection)
+            .map(|channel| (router.name.clone(), channel.clone()))
+    }) else {
+        return protocol_error_response(
+            route,
+            StatusCode::NOT_FOUND,
+            "No matching router found for model",
+        );
+    };
+
+    let upstream_model = channel
+        .model_map
+        .as_ref()
+        .and_then(|map| map.get(&model))
+        .cloned()
+        .unwrap_or_else(|| model.clone());
+    let mut forwarded = HeaderMap::new();
+    if let Some(beta) = parts.headers.get("openai-beta") {
+        forwarded.insert("openai-beta", beta.clone());
+    }
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("model", &upstream_model)
+        .finish();
+    let prepared = match prepare_request(
+        &state.providers,
+        &state.key_pools.keyed(&channel),
+        route,
+        &channel.base_url,
+        "/v1/realtime",
+        Some(&query),
+        &forwarded,
+        &Bytes::new(),
+    ) {
+        Ok(prepared) => prepared,
+        Err(e) => return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let upstream = match crate::realtime::connect_upstream(prepared.url, &prepared.headers).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            let message = crate::realtime::describe_connect_error(&e);
+            tracing::warn!(
+                "Realtime Upstream Failed: channel={} {}",
+                channel.name,
+                message
+            );
+            return protocol_error_response(route, StatusCode::BAD_GATEWAY, &message);
+        }
+    };
+
+    tracing::info!(
+        "Realtime Session Opened: router={} channel={} model={}",
+        router_name,
+        channel.name,
+        model
+    );
+    state
+        .metrics
+        .request_total
+        .with_label_values(&[route.as_str(), &router_name])
+        .inc();
+    state.database.log_request(route.as_str(), &router_name);
+    let request_id = request_id_from_parts(&parts);
+    let client_info = crate::utils::classify_client(&parts.headers);
+    ws.on_upgrade(move |socket| async move {
+        let started = std::time::Instant::now();
+        let usage = crate::realtime::relay(socket, upstream).await;
+        tracing::info!(
+            "Realtime Session Closed: router={} channel={} input_tokens={} output_tokens={}",
+            router_name,
+            channel.name,
+            usage.input_tokens,
+            usage.output_tokens
+        );
+        let model_lower = model.to_lowercase();
+        for (kind, count) in [
+            ("input", usage.input_tokens),
+            ("output", usage.output_tokens),
+        ] {
+            state
+                .metrics
+                .token_total
+                .with_label_values(&[&router_name, &channel.name, &model_lower, kind])
+                .inc_by(count);
+        }
+        state.usage_logger.log(
+            request_id.as_deref(),
+            &team_id,
+            &router_name,
+            Some("realtime"),
+            &channel.name,
+            &model,
+            usage.input_tokens,
+            usage.output_tokens,
+            Some(started.elapsed().as_secs_f64() * 1000.0),
+            false,
+            &client_info,
+        );
+    })
+}
+
+/// `GET /v1/models` (and `/models`). Returns the list of concrete model ids
+/// the *team* associated with the inbound API key is allowed to call, in
+/// OpenAI's list-models format: literal rule patterns, models seen in the
+/// team's usage and the models its channels list (see `model_catalog`). Admin / global keys are intentionally
+/// rejected here — this endpoint exists to bootstrap end-user clients, not
+/// to power admin tooling.
+async fn handle_models(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
+    let caller = match req.extensions().get::<TeamContext>() {
+        Some(ctx) => ctx.clone(),
+        None => {
+            return error_response(
+                StatusCode::UNAUTHORIZED,
+                "Team API Key required: /v1/models only resolves models for a specific team",
+            );
+        }
+    };
+    let team_id = caller.team_id.clone();
+
+    let config = state.config.read().unwrap().clone();
+    let Some(team) = config.teams.iter().find(|t| t.id == team_id) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Team not found");
+    };
+
+    // -- 1. Collect candidate model ids ------------------------------------
+    // Anything that is a literal model name in the rules of a router this
+    // team is allowed to use. Glob patterns like "*" / "deepseek-*" are
+    // skipped because OpenAI's list-models payload requires concrete ids.
+    let mut candidates: BTreeSet<String> = BTreeSet::new();
+    for router_name in &team.policy.allowed_routers {
+        let Some(router) = config.routers.iter().find(|r| &r.name == router_name) else {
+            continue;
+        };
+        for rule in &router.rules {
+            for pattern in &rule.match_spec.models {
+                if !is_glob_pattern(pattern) {
+                    candidates.insert(pattern.clone());
+                }
+            }
+        }
+    }
+
+    // Augment with concrete model ids actually observed in the usage log for
+    // this team. This covers the common case where the only router rule is
+    // a glob (e.g. `deepseek-*`) — without history the team would otherwise
+    // see an empty list.
+    if let Ok(history) = state.database.distinct_models_for_team(&team_id) {
+        candidates.extend(history);
+    }
+
+    // And with what the channels behind those routers report themselves,
+    // so a catch-all rule still yields the provider's full model list.
+    let discovery = config.model_discovery.clone().unwrap_or_default();
+    if discovery.enabled {
+        let mut channel_names: Vec<&String> = Vec::new();
+        for router in config
+            .routers
+            .iter()
+            .filter(|r| team.policy.allowed_routers.contains(&r.name))
+        {
+            let targets = router
+                .rules
+                .iter()
+                .flat_map(|rule| rule.channels.iter())
+                .chain(router.channels.iter())
+                .map(|target| &target.name)
+                .chain(router.fallback_channel_names());
+            for name in targets {
+                if !channel_names.contains(&name) {
+                    channel_names.push(name);
+                }
+            }
+        }
+        let listings = channel_names
+            .into_iter()
+            .filter_map(|name| config.channels.iter().find(|c| &c.name == name))
+            .map(|channel| {
+                let keyed = state.key_pools.keyed(channel).into_owned();
+                let client = state.client_for(channel);
+                let discovery = &discovery;
+                let state = &state;
+                async move {
+                    state
+                        .model_catalog
+                        .channel_models(&state.providers, &client, &keyed, discovery)
+                        .await
+                }
+            });
+        for models in futures::future::join_all(listings).await {
+            candidates.extend(models.iter().cloned());
+        }
+    }
+
+    // -- 2. Filter by team policy + verify a router can actually route it --
+    let mut entries: Vec<serde_json::Value> = Vec::with_capacity(candidates.len());
+    for model in candidates {
+        if !team.policy.is_model_allowed(&model) || !caller.allows_model(&model) {
+            continue;
+        }
+
+        let Some((router_name, channel_name)) =
+            resolve_model_to_router_channel(&config, &team.policy.allowed_routers, &model, &state)
+        else {
+            continue;
+        };
+
+        let owned_by = config
+            .channels
+            .iter()
+            .find(|c| c.name == channel_name)
+            .map(|c| format!("{:?}", c.provider_type).to_lowercase())
+            .unwrap_or_else(|| "apex".to_string());
+
+        entries.push(json!({
+            "id": model,
+            "object": "model",
+            "created": 0,
+            "owned_by": owned_by,
+            "apex": {
+                "router": router_name,
+                "channel": channel_name,
+            }
+        }));
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "object": "list",
+                "data": entries,
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+/// A pattern is "glob-like" if it contains any of the meta-characters glob
+/// would interpret. Bare literal model ids (which is what OpenAI's list
+/// endpoint must return) contain none of these.
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.chars().any(|c| matches!(c, '*' | '?' | '[' | ']'))
+}
+
+/// Try each of the team's allowed routers; if one can route the given model
+/// to a concrete channel, return `(router_name, channel_name)`. We avoid
+/// returning models the gateway would actually 404 on.
+fn resolve_model_to_router_channel(
+    config: &Config,
+    allowed_routers: &[String],
+    model: &str,
+    state: &AppState,
+) -> Option<(String, String)> {
+    for router_name in allowed_routers {
+        let Some(router) = config.routers.iter().find(|r| &r.name == router_name) else {
+            continue;
+        };
+        if let Some(channel) = state.selector.select_channel(router, model) {
+            return Some((router.name.clone(), channel));
+        }
+    }
+    None
+}
+
+async fn handle_admin_teams(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let headers = &parts.headers;
+
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, headers) {
+        return resp;
+    }
+
+    let data = config
+        .teams
+        .iter()
+        .map(|team| {
+            let rate_limit = team.policy.rate_limit.as_ref().map(|l| {
+                json!({
+                    "rpm": l.rpm,
+                    "tpm": l.tpm
+                })
+            });
+            // NOTE: api_key is intentionally NOT included in the list
+            // response. Fetch it explicitly via GET /admin/teams/api_keys.
+            json!({
+                "id": team.id,
+                "group": team.group,
+                "enabled": team.enabled.unwrap_or(true),
+                "policy": {
+                    "allowed_routers": team.policy.allowed_routers,
+                    "allowed_models": team.policy.allowed_models,
+                    "rate_limit": rate_limit
+                },
+                "key_usage": team_key_usage_json(&state, team),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "object": "list",
+                "data": data
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+// -------- Teams CRUD --------
+
+#[derive(serde::Deserialize, Default)]
+struct CreateTeamRequest {
+    id: String,
+    #[serde(default)]
+    api_key: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    allowed_routers: Option<Vec<String>>,
+    #[serde(default)]
+    allowed_models: Option<Vec<String>>,
+    #[serde(default)]
+    rate_limit: Option<TeamRateLimitInput>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct UpdateTeamRequest {
+    #[serde(default)]
+    group: Option<Option<String>>,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    allowed_routers: Option<Vec<String>>,
+    /// `Some(None)` means "clear" (no allowlist → all models). `Some(Some(_))` sets.
+    /// `None` leaves the field unchanged.
+    #[serde(default, deserialize_with = "deserialize_optional_optional_vec")]
+    allowed_models: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "deserialize_optional_optional_rate_limit")]
+    rate_limit: Option<Option<TeamRateLimitInput>>,
+}
+
+#[derive(serde::Deserialize, Default, Clone)]
+struct TeamRateLimitInput {
+    #[serde(default)]
+    rpm: Option<i32>,
+    #[serde(default)]
+    tpm: Option<i32>,
+}
+
+fn deserialize_optional_optional_vec<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<Vec<String>>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    // Accept null (= clear) or array.
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Null => Ok(Some(None)),
+        serde_json::Value::Array(items) => {
+            let parsed = items
+                .into_iter()
+                .map(|item| match item {
+                    serde_json::Value::String(s) => Ok(s),
+                    other => Err(serde::de::Error::custom(format!(
+                        "allowed_models entries must be strings, got {other:?}"
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Some(Some(parsed)))
+        }
+        other => Err(serde::de::Error::custom(format!(
+            "allowed_models must be null or an array, got {other:?}"
+        ))),
+    }
+}
+
+fn deserialize_optional_optional_rate_limit<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<TeamRateLimitInput>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Null => Ok(Some(None)),
+        other => {
+            let parsed: TeamRateLimitInput =
+                serde_json::from_value(other).map_err(serde::de::Error::custom)?;
+            Ok(Some(Some(parsed)))
+        }
+    }
+}
+
+fn persist_config(config: &Config) -> Result<(), String> {
+    let path = PathBuf::from(&config.hot_reload.config_path);
+    if path.as_os_str().is_empty() {
+        return Err("hot_reload.config_path is empty".into());
+    }
+    crate::config::save_config(&path, config).map_err(|e| e.to_string())
+}
+
+/// Atomically apply a configuration mutation.
+///
+/// This is the single write path shared by every admin CRUD handler. It closes
+/// two classes of bug that arise when validation, mutation and persistence are
+/// done across separate lock acquisitions:
+///
+///   * **TOCTOU** — the closure runs while the write lock is held and validates
+///     against a private `candidate` clone of the *current* config, so a
+///     concurrent writer can't invalidate a check between validate and apply.
+///   * **memory/disk divergence** — the candidate is persisted to disk *before*
+///     it is committed to the in-memory `Config`. If the disk write fails, the
+///     live config is left untouched and the handler returns an error, instead
+///     of silently keeping an unpersisted change that vanishes on restart.
+///
+/// The closure receives `&mut Config` (the candidate) and returns either a
+/// success value (used to build the response) or an error `Response` to abort
+/// the whole operation with no change.
+///
+/// Note: the file write happens while the write lock is held. Admin mutations
+/// are rare and the proxy hot-path only takes *read* locks, so the brief stall
+/// is an acceptable tradeoff for atomicity.
+fn commit_config<T>(
+    state: &AppState,
+    mutate: impl FnOnce(&mut Config) -> Result<T, Response<Body>>,
+) -> Result<T, Response<Body>> {
+    let mut guard = state.config.write().unwrap();
+    let mut candidate = guard.clone();
+    let value = mutate(&mut candidate)?;
+    if let Err(err) = state.providers.check_channels(&candidate.channels) {
+        return Err(error_response(StatusCode::BAD_REQUEST, &err.to_string()));
+    }
+    if let Err(err) = persist_config(&candidate) {
+        tracing::error!("Failed to persist config change: {err}");
+        return Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to persist config: {err}"),
+        ));
+    }
+    let published = candidate
+        .hot_reload
+        .store
+        .clone()
+        .map(|store| (store, candidate.clone()));
+    *guard = candidate;
+    drop(guard);
+    let generation = state.bump_config_generation();
+    // The rest of the fleet picks the change up from the store.
+    if let Some((store, config)) = published {
+        tokio::spawn(async move {
+            if let Err(e) = crate::config_store::publish(&store, &config, generation).await {
+                tracing::error!(
+                    "Failed to publish config change to {}: {:#}",
+                    crate::config_store::describe(&store),
+                    e
+                );
+            }
+        });
+    }
+    Ok(value)
+}
+
+/// Request count / last-used time for the team's current key (`null` if the
+/// key has never been used).
+fn team_key_usage_json(state: &AppState, team: &crate::config::Team) -> serde_json::Value {
+    crate::key_store::usage_id(team)
+        .and_then(|id| state.database.get_key_usage(&id).ok().flatten())
+        .map(|usage| {
+            json!({
+                "request_count": usage.request_count,
+                "first_used_at": usage.first_used,
+                "last_used_at": usage.last_used,
+            })
+        })
+        .unwrap_or(serde_json::Value::Null)
+}
+
+async fn handle_admin_team(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
+        return resp;
+    }
+    let Some(team) = config.teams.iter().find(|t| t.id == team_id) else {
+        return error_response(StatusCode::NOT_FOUND, "Team not found");
+    };
+
+    let mut payload = teams_json_response(team);
+    if let Some(obj) = payload.as_object_mut() {
+        obj.insert("key_usage".to_string(), team_key_usage_json(&state, team));
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap()
+}
+
+fn teams_json_response(team: &crate::config::Team) -> serde_json::Value {
+    let rate_limit = team
+        .policy
+        .rate_limit
+        .as_ref()
+        .map(|l| json!({"rpm": l.rpm, "tpm": l.tpm}));
+    // NOTE: api_key is intentionally NOT included in the standard response.
+    // Create responses overlay it afterwards (only-time reveal); other reads
+    // go through GET /admin/teams/api_keys.
+    json!({
+        "id": team.id,
+        "group": team.group,
+        "enabled": team.enabled.unwrap_or(true),
+        "policy": {
+            "allowed_routers": team.policy.allowed_routers,
+            "allowed_models": team.policy.allowed_models,
+            "rate_limit": rate_limit,
+        }
+    })
+}
+
+async fn handle_admin_create_team(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: CreateTeamRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+
+    let id = payload.id.trim().to_string();
+    if id.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "id must not be empty");
+    }
+
+    // The team's first managed key: the operator's own secret or a new one.
+    // Only its hash is stored.
+    let name = Some("default".to_string());
+    let (key, api_key) = match payload
+        .api_key
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+    {
+        Some(api_key) => (crate::key_store::import_key(&api_key, name), api_key),
+        None => crate::key_store::new_key(name, None, vec![]),
+    };
+
+    let allowed_routers = payload.allowed_routers.unwrap_or_default();
+    let rate_limit = payload.rate_limit.map(|r| crate::config::TeamRateLimit {
+        rpm: r.rpm,
+        tpm: r.tpm,
+    });
+
+    let new_team = crate::config::Team {
+        id: id.clone(),
+        api_key: String::new(),
+        group: payload.group.and_then(|g| {
+            let g = g.trim().to_string();
+            if g.is_empty() { None } else { Some(g) }
+        }),
+        enabled: payload.enabled.or(Some(true)),
+        policy: crate::config::TeamPolicy {
+            allowed_routers,
+            allowed_models: payload.allowed_models,
+            rate_limit,
+            allow_routing_overrides: false,
+            end_user_rate_limit: None,
+            reject_unknown_models: false,
+            transcripts: None,
+            pii: None,
+            max_tokens_per_request: None,
+            max_context_tokens: None,
+            clamp_max_tokens: false,
+            priority: Default::default(),
+        },
+        keys: vec![key],
+    };
+
+    // Validate uniqueness + apply + persist atomically under the write lock.
+    if let Err(resp) = commit_config(&state, |cfg| {
+        if cfg.teams.iter().any(|t| t.id == id) {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                "A team with this id already exists",
+            ));
+        }
+        if crate::key_store::find(cfg, &api_key).is_some() {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                "A team with this api_key already exists",
+            ));
+        }
+        Arc::make_mut(&mut cfg.teams).push(new_team.clone());
+        Ok(())
+    }) {
+        return resp;
+    }
+
+    // For create only: return the *unmasked* api_key once, so the operator
+    // can record it. Subsequent reads will be masked.
+    let mut payload_value = teams_json_response(&new_team);
+    if let Some(obj) = payload_value.as_object_mut() {
+        obj.insert(
+            "api_key".to_string(),
+            serde_json::Value::String(api_key.clone()),
+        );
+        obj.insert(
+            "api_key_revealed".to_string(),
+            serde_json::Value::Bool(true),
+        );
+    }
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header("content-type", "application/json")
+        .body(Body::from(payload_value.to_string()))
+        .unwrap()
+}
+
+async fn handle_admin_update_team(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: UpdateTeamRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+
+    let updated_team = match commit_config(&state, |cfg| {
+        let teams = Arc::make_mut(&mut cfg.teams);
+        let Some(team) = teams.iter_mut().find(|t| t.id == team_id) else {
+            return Err(error_response(StatusCode::NOT_FOUND, "Team not found"));
+        };
+
+        if let Some(group) = payload.group {
+            team.group = group.and_then(|g| {
+                let trimmed = g.trim().to_string();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed)
+                }
+            });
+        }
+        if let Some(enabled) = payload.enabled {
+            team.enabled = Some(enabled);
+        }
+        if let Some(allowed_routers) = payload.allowed_routers {
+            team.policy.allowed_routers = allowed_routers;
+        }
+        if let Some(allowed_models) = payload.allowed_models {
+            team.policy.allowed_models = allowed_models;
+        }
+        if let Some(rate_limit) = payload.rate_limit {
+            team.policy.rate_limit = rate_limit.map(|r| crate::config::TeamRateLimit {
+                rpm: r.rpm,
+                tpm: r.tpm,
+            });
+        }
+
+        Ok(team.clone())
+    }) {
+        Ok(team) => team,
+        Err(resp) => return resp,
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(teams_json_response(&updated_team).to_string()))
+        .unwrap()
+}
+
+async fn handle_admin_delete_team(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    if let Err(resp) = commit_config(&state, |cfg| {
+        let teams = Arc::make_mut(&mut cfg.teams);
+        let before = teams.len();
+        teams.retain(|t| t.id != team_id);
+        if teams.len() == before {
+            return Err(error_response(StatusCode::NOT_FOUND, "Team not found"));
+        }
+        Ok(())
+    }) {
+        return resp;
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"deleted": team_id}).to_string()))
+        .unwrap()
+}
+
+async fn handle_admin_fault_injection(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
+        return resp;
+    }
+
+    let settings = config.fault_injection.unwrap_or_default();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!(settings).to_string()))
+        .unwrap()
+}
+
+/// Replace the chaos-mode settings. Send `{"enabled": false}` to switch it off.
+async fn handle_admin_update_fault_injection(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let settings: crate::config::FaultInjection = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+    let rates = [
+        settings.error_rate,
+        settings.latency_rate,
+        settings.truncate_rate,
+    ];
+    if rates.iter().any(|r| !(0.0..=1.0).contains(r)) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "fault rates must be between 0.0 and 1.0",
+        );
+    }
+
+    if let Err(resp) = commit_config(&state, |cfg| {
+        if let Some(unknown) = settings
+            .channels
+            .iter()
+            .find(|name| !cfg.channels.iter().any(|c| &c.name == *name))
+        {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Unknown channel: {unknown}"),
+            ));
+        }
+        cfg.fault_injection = Some(settings.clone());
+        Ok(())
+    }) {
+        return resp;
+    }
+    tracing::warn!(
+        "Fault injection {} via admin API",
+        if settings.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!(settings).to_string()))
+        .unwrap()
+}
+
+#[derive(serde::Deserialize)]
+struct RevokeKeyRequest {
+    key: String,
+}
+
+async fn handle_admin_revoked_keys(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
+        return resp;
+    }
+
+    let data: Vec<String> = config
+        .global
+        .revoked_keys
+        .iter()
+        .map(|k| mask_secret(k))
+        .collect();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"data": data}).to_string()))
+        .unwrap()
+}
+
+#[derive(serde::Deserialize)]
+struct UsageIngestRequest {
+    records: Vec<crate::database::UsageEntry>,
+}
+
+/// Store usage records shipped by other gateways (`usage_shipping` with an
+/// `http` target), so this instance's reports cover the fleet.
+async fn handle_admin_usage_ingest(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 16 * 1024 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: UsageIngestRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+    let count = payload.records.len();
+    let db = state.database.clone();
+    match tokio::task::spawn_blocking(move || db.insert_usage(&payload.records)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+            tracing::error!("Failed to store ingested usage: {err}");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to store usage");
+        }
+        Err(err) => {
+            tracing::error!("Usage ingest task panicked: {err}");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to store usage");
+        }
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"ingested": count}).to_string()))
+        .unwrap()
+}
+
+/// Add a key to `global.revoked_keys`. Takes effect on the next request; the
+/// owning team (if any) is left as-is so its key can be rotated separately.
+async fn handle_admin_revoke_key(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: RevokeKeyRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+    let key = payload.key.trim().to_string();
+    if key.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "key must not be empty");
+    }
+
+    let owner = match commit_config(&state, |cfg| {
+        // Managed keys (by id or plaintext) are soft-revoked in place.
+        if let Some((team, _)) = crate::key_store::revoke(cfg, &key) {
+            return Ok(Some(team));
+        }
+        if !cfg.global.is_key_revoked(&key) {
+            cfg.global.revoked_keys.push(key.clone());
+        }
+        Ok(cfg
+            .teams
+            .iter()
+            .find(|t| t.api_key == key)
+            .map(|t| t.id.clone()))
+    }) {
+        Ok(owner) => owner,
+        Err(resp) => return resp,
+    };
+    tracing::warn!("API key {} revoked via admin API", mask_secret(&key));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"revoked": mask_secret(&key), "team": owner}).to_string(),
+        ))
+        .unwrap()
+}
+
+async fn handle_admin_routers(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let headers = &parts.headers;
+
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, headers) {
+        return resp;
+    }
+
+    let data = config
+        .routers
+        .iter()
+        .filter_map(|router| serde_json::to_value(router).ok())
+        .collect::<Vec<_>>();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "object": "list",
+                "data": data
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+async fn handle_admin_router(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(router_name): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
+        return resp;
+    }
+    let Some(router) = config.routers.iter().find(|r| r.name == router_name) else {
+        return error_response(StatusCode::NOT_FOUND, "Router not found");
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(router).unwrap_or_else(|_| "{}".to_string()),
+        ))
+        .unwrap()
+}
+
+async fn handle_admin_channels_health(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
+        return resp;
+    }
+
+    let data = state
+        .channel_health
+        .snapshot(config.channels.iter().map(|c| c.name.as_str()));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "data": data }).to_string()))
+        .unwrap()
+}
+
+/// `GET /admin/events` — every finished request as a server-sent `request`
+/// event (JSON [`RequestEvent`](crate::usage::RequestEvent)). A subscriber
+/// that falls behind gets a `lagged` event with the number of skipped
+/// requests and continues from the newest.
+async fn handle_admin_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
+        return resp;
+    }
+
+    let events = futures::stream::unfold(state.usage_logger.subscribe(), |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(event) => SseEvent::default()
+                .event("request")
+                .json_data(&event)
+                .unwrap_or_default(),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => SseEvent::default()
+                .event("lagged")
+                .data(skipped.to_string()),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((Ok::<_, std::convert::Infallible>(event), rx))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Dry-run routing for `?model=` (optionally `&router=` / `&team=`) against
+/// the live config and channel health; nothing is sent upstream.
+async fn handle_admin_route_explain(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Response<Body> {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
+        return resp;
+    }
+    let Some(model) = params.get("model").filter(|m| !m.trim().is_empty()) else {
+        return error_response(StatusCode::BAD_REQUEST, "model query parameter is required");
+    };
+
+    match crate::route_explain::explain(
+        &state.selector,
+        &config,
+        model,
+        params.get("router").map(String::as_str),
+        params.get("team").map(String::as_str),
+    ) {
+        Ok(explanation) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "data": explanation }).to_string()))
+            .unwrap(),
+        Err(err) => error_response(StatusCode::NOT_FOUND, &err.to_string()),
+    }
+}
+
+async fn handle_admin_channels(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let headers = &parts.headers;
+
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, headers) {
+        return resp;
+    }
+
+    let data = config.channels.iter().map(channel_json).collect::<Vec<_>>();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "object": "list",
+                "data": data
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
+async fn handle_admin_channel(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(channel_name): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
+        return resp;
+    }
+    let Some(channel) = config.channels.iter().find(|c| c.name == channel_name) else {
+        return error_response(StatusCode::NOT_FOUND, "Channel not found");
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(channel_json(channel).to_string()))
+        .unwrap()
+}
+
+fn channel_json(channel: &crate::config::Channel) -> serde_json::Value {
+    // NOTE: api_key is intentionally NOT included. Fetch it explicitly via
+    // GET /admin/channels/api_keys.
+    json!({
+        "name": channel.name,
+        "provider_type": channel.provider_type,
+        "base_url": channel.base_url,
+        "anthropic_base_url": channel.anthropic_base_url,
+        "model_map": channel.model_map,
+        "native_api": channel.native_api,
+    })
+}
+
+// -------- Channels CRUD --------
+//
+// Channels write paths share the same shape as Teams: the in-memory `Config`
+// is mutated under the write-lock, then `persist_config` writes the new JSON
+// to disk so the change survives restart. Hot-reload picks it up too.
+//
+// Safety invariants:
+//   * name uniqueness on create
+//   * before delete, refuse if any router rule (or legacy fallback) still
+//     references the channel — silent removal would break routing at runtime.
+
+#[derive(serde::Deserialize)]
+struct CreateChannelRequest {
+    name: String,
+    provider_type: crate::config::ProviderType,
+    #[serde(default)]
+    base_url: String,
+    api_key: String,
+    #[serde(default)]
+    anthropic_base_url: Option<String>,
+    #[serde(default)]
+    headers: Option<std::collections::HashMap<String, String>>,
+    #[serde(default)]
+    model_map: Option<std::collections::HashMap<String, String>>,
+    #[serde(default)]
+    native_api: bool,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct UpdateChannelRequest {
+    #[serde(default)]
+    provider_type: Option<crate::config::ProviderType>,
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Bearer token / upstream secret. `None` leaves it unchanged.
+    /// (No way to *clear* the key via PATCH — empty key would break the
+    /// channel at runtime. Delete + recreate instead.)
+    #[serde(default)]
+    api_key: Option<String>,
+    /// `Some(None)` clears the anthropic URL; `Some(Some(_))` sets it.
+    #[serde(default, deserialize_with = "deserialize_optional_optional_string")]
+    anthropic_base_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_optional_optional_str_map")]
+    headers: Option<Option<std::collections::HashMap<String, String>>>,
+    #[serde(default, deserialize_with = "deserialize_optional_optional_str_map")]
+    model_map: Option<Option<std::collections::HashMap<String, String>>>,
+}
+
+fn deserialize_optional_optional_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Null => Ok(Some(None)),
+        serde_json::Value::String(s) => Ok(Some(Some(s))),
+        other => Err(serde::de::Error::custom(format!(
+            "expected string or null, got {other:?}"
+        ))),
+    }
+}
+
+fn deserialize_optional_optional_str_map<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<std::collections::HashMap<String, String>>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Null => Ok(Some(None)),
+        other => {
+            let parsed: std::collections::HashMap<String, String> =
+                serde_json::from_value(other).map_err(serde::de::Error::custom)?;
+            Ok(Some(Some(parsed)))
+        }
+    }
+}
+
+fn channel_json_response(channel: &crate::config::Channel) -> serde_json::Value {
+    json!({
+        "name": channel.name,
+        "provider_type": channel.provider_type,
+        "base_url": channel.base_url,
+        "anthropic_base_url": channel.anthropic_base_url,
+    })
+}
+
+/// Collect router names + rule indices that reference the given channel.
+/// Used by delete handlers to produce a helpful 409 instead of silently
+/// leaving the gateway pointing at a deleted channel.
+fn collect_channel_references(config: &Config, channel_name: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    for router in config.routers.iter() {
+        for (idx, rule) in router.rules.iter().enumerate() {
+            if rule.channels.iter().any(|c| c.name == channel_name) {
+                refs.push(format!("router '{}' rule #{}", router.name, idx + 1));
+            }
+        }
+        if router.channels.iter().any(|c| c.name == channel_name) {
+            refs.push(format!("router '{}' legacy channels", router.name));
+        }
+        if router.fallback_channel_names().any(|c| c == channel_name) {
+            refs.push(format!("router '{}' fallback", router.name));
+        }
+    }
+    refs
+}
+
+async fn handle_admin_create_channel(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: CreateChannelRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "name must not be empty");
+    }
+    if payload.base_url.trim().is_empty()
+        && payload
+            .anthropic_base_url
+            .as_deref()
+            .is_none_or(|url| url.trim().is_empty())
+    {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "base_url or anthropic_base_url must be set",
+        );
+    }
+    if payload.api_key.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "api_key must not be empty");
+    }
+
+    let new_channel = crate::config::Channel {
+        name: name.clone(),
+        provider_type: payload.provider_type,
+        base_url: payload.base_url.trim().to_string(),
+        api_key: payload.api_key,
+        anthropic_base_url: payload
+            .anthropic_base_url
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty()),
+        headers: payload.headers,
+        model_map: payload.model_map,
+        timeouts: None,
+        native_api: payload.native_api,
+        mock: None,
+        max_concurrent_requests: None,
+        max_queued_requests: None,
+        queue_timeout_ms: None,
+        api_keys: vec![],
+        key_strategy: None,
+        api_key_file: None,
+        embedding_task: None,
+        vertex: None,
+        quirks: None,
+        param_profile: None,
+        prompt_cache: None,
+        adapter: None,
+        body_limits: None,
+    };
+
+    if let Err(resp) = commit_config(&state, |cfg| {
+        if cfg.channels.iter().any(|c| c.name == name) {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                "A channel with this name already exists",
+            ));
+        }
+        Arc::make_mut(&mut cfg.channels).push(new_channel.clone());
+        Ok(())
+    }) {
+        return resp;
+    }
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header("content-type", "application/json")
+        .body(Body::from(channel_json_response(&new_channel).to_string()))
+        .unwrap()
+}
+
+async fn handle_admin_update_channel(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(channel_name): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+    };
+    let payload: UpdateChannelRequest = match serde_json::from_slice(&bytes) {
+        Ok(p) => p,
+        Err(err) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+        }
+    };
+
+    let snapshot = match commit_config(&state, |cfg| {
+        let channels = Arc::make_mut(&mut cfg.channels);
+        let Some(channel) = channels.iter_mut().find(|c| c.name == channel_name) else {
+            return Err(error_response(StatusCode::NOT_FOUND, "Channel not found"));
+        };
+
+        if let Some(pt) = payload.provider_type {
+            channel.provider_type = pt;
+        }
+        if let Some(base_url) = payload.base_url {
+            let trimmed = base_url.trim().to_string();
+            if trimmed.is_empty() {
+                return Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    "base_url must not be empty",
+                ));
+            }
+            channel.base_url = trimmed;
+        }
+        if let Some(api_key) = payload.api_key {
+            if api_key.trim().is_empty() {
+                return Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    "api_key must not be empty (omit field to keep current)",
+                ));
+            }
+            channel.api_key = api_key;
+        }
+        if let Some(anthropic) = payload.anthropic_base_url {
+            channel.anthropic_base_url = anthropic
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+        }
+        if let Some(headers) = payload.headers {
+            channel.headers = headers;
+        }
+        if let Some(model_map) = payload.model_map {
+            channel.model_map = model_map;
+        }
+
+        Ok(channel.clone())
+    }) {
+        Ok(snapshot) => snapshot,
+        Err(resp) => return resp,
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(channel_json_response(&snapshot).to_string()))
+        .unwrap()
+}
+
+async fn handle_admin_delete_channel(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(channel_name): axum::extract::Path<String>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config_snapshot = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+        return resp;
+    }
+
+    // Reference check + delete + persist atomically: a concurrent router
+    // create/update can't slip a new reference in between check and delete.
+    if let Err(resp) = commit_config(&state, |cfg| {
+        let refs = collect_channel_references(cfg, &channel_name);
+        if !refs.is_empty() {
+            return Err(Response::builder()
+                .status(StatusCode::CONFLICT)
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "error": format!(
+                            "Channel '{channel_name}' is still referenced by: {}",
+                            refs.join(", ")
+                        ),
+                        "references": refs,
+                    })
+                    .to_string(),
+                ))
+                .unwrap());
+        }
+        let channels = Arc::make_mut(&mut cfg.channels);
+        let before = channels.len();
+        channels.retain(|c| c.name != channel_name);
+        if channels.len() == before {
+            return Err(error_response(StatusCode::NOT_FOUND, "Channel not found"));
+        }
+        Ok(())
+    }) {
+        return resp;
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"deleted": channel_name}).to_string()))
+        .unwrap()
+}
+
+// -------- Routers CRUD --------
+//
+// Routers are heavier than channels: a router carries an ordered list of
+// `RouterRule` (match patterns + strategy + target channels) plus optional
+// fallback channels. The write payload mirrors the on-disk JSON shape so
+// the same blob can round-trip through `save_config`.
+//
+// Safety invariants:
+//   * name uniqueness on create
+//   * every channel referenced by any rule (or fallback / legacy channels)
+//     must already exist
+//   * before delete, refuse if any team.allowed_routers still references it
+
+#[derive(serde::Deserialize, Default)]
+struct RouterRuleInput {
+    #[serde(default)]
+    models: Vec<String>,
+    #[serde(default)]
+    channels: Vec<TargetChannelInput>,
+    #[serde(default)]
+    strategy: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default, Clone)]
+struct TargetChannelInput {
+    name: String,
+    #[serde(default = "default_target_weight")]
+    weight: u32,
+}
+
+fn default_target_weight() -> u32 {
+    1
+}
+
+#[derive(serde::Deserialize, Default)]
+struct CreateRouterRequest {
+    name: String,
+    #[serde(default)]
+    rules: Vec<RouterRuleInput>,
+    #[serde(default)]
+    fallback_channels: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct UpdateRouterRequest {
+    #[serde(default)]
+    rules: Option<Vec<RouterRuleInput>>,
+    #[serde(default)]
+    fallback_channels: Option<Vec<String>>,
+}
+
+fn router_json_response(router: &crate::config::Router) -> serde_json::Value {
+    serde_json::to_value(router).unwrap_or(serde_json::Value::Null)
+}
+
+fn build_rule(input: RouterRuleInput) -> Result<crate::config::RouterRule, String> {
+    if input.channels.is_empty() {
+        return Err("each rule must have at least one channel".into());
     }
-    *guard = candidate;
-    Ok(value)
+    let strategy = input
+        .strategy
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("round_robin")
+        .to_string();
+    match strategy.as_str() {
+        "round_robin" | "weighted" | "random" | "priority" | "least_cost" | "sticky" | "split" => {}
+        other => return Err(format!("unknown strategy '{other}'")),
+    }
+    let channels: Vec<_> = input
+        .channels
+        .into_iter()
+        .map(|c| crate::config::TargetChannel {
+            name: c.name,
+            weight: c.weight.max(1),
+        })
+        .collect();
+    if strategy == "split" && channels.iter().map(|c| c.weight).sum::<u32>() != 100 {
+        return Err("split rules need channel weights (percentages) summing to 100".into());
+    }
+    Ok(crate::config::RouterRule {
+        match_spec: crate::config::MatchSpec {
+            models: input.models,
+            ..Default::default()
+        },
+        channels,
+        strategy,
+        retries: None,
+    })
 }
 
-fn teams_json_response(team: &crate::config::Team) -> serde_json::Value {
-    let rate_limit = team
-        .policy
-        .rate_limit
-        .as_ref()
-        .map(|l| json!({"rpm": l.rpm, "tpm": l.tpm}));
-    // NOTE: api_key is intentionally NOT included in the standard response.
-    // Create responses overlay it afterwards (only-time reveal); other reads
-    // go through GET /admin/teams/api_keys.
-    json!({
-        "id": team.id,
-        "group": team.group,
-        "enabled": team.enabled.unwrap_or(true),
-        "policy": {
-            "allowed_routers": team.policy.allowed_routers,
-            "allowed_models": team.policy.allowed_models,
-            "rate_limit": rate_limit,
+/// Verify every channel referenced by a router exists. Returns the list of
+/// missing channel names (empty if all OK).
+fn missing_channels(
+    config: &Config,
+    rules: &[crate::config::RouterRule],
+    fallback: &[String],
+) -> Vec<String> {
+    let known: std::collections::HashSet<&str> =
+        config.channels.iter().map(|c| c.name.as_str()).collect();
+    let mut missing = std::collections::BTreeSet::new();
+    for rule in rules {
+        for tc in &rule.channels {
+            if !known.contains(tc.name.as_str()) {
+                missing.insert(tc.name.clone());
+            }
         }
-    })
+    }
+    for name in fallback {
+        if !known.contains(name.as_str()) {
+            missing.insert(name.clone());
+        }
+    }
+    missing.into_iter().collect()
 }
 
-async fn handle_admin_create_team(
+async fn handle_admin_create_router(
     State(state): State<Arc<AppState>>,
     req: Request<Body>,
 ) -> Response<Body> {
@@ -1988,93 +4942,96 @@ async fn handle_admin_create_team(
         return resp;
     }
 
-    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+    let bytes = match axum::body::to_bytes(body, 256 * 1024).await {
         Ok(b) => b,
         Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
     };
-    let payload: CreateTeamRequest = match serde_json::from_slice(&bytes) {
+    let payload: CreateRouterRequest = match serde_json::from_slice(&bytes) {
         Ok(p) => p,
         Err(err) => {
             return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
         }
     };
 
-    let id = payload.id.trim().to_string();
-    if id.is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "id must not be empty");
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "name must not be empty");
+    }
+    if payload.rules.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "rules must contain at least one rule",
+        );
     }
 
-    let api_key = payload
-        .api_key
-        .map(|k| k.trim().to_string())
-        .filter(|k| !k.is_empty())
-        .unwrap_or_else(generate_team_api_key);
-
-    let allowed_routers = payload.allowed_routers.unwrap_or_default();
-    let rate_limit = payload.rate_limit.map(|r| crate::config::TeamRateLimit {
-        rpm: r.rpm,
-        tpm: r.tpm,
-    });
+    let mut built_rules = Vec::with_capacity(payload.rules.len());
+    for rule in payload.rules {
+        match build_rule(rule) {
+            Ok(r) => built_rules.push(r),
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+        }
+    }
 
-    let new_team = crate::config::Team {
-        id: id.clone(),
-        api_key: api_key.clone(),
-        group: payload.group.and_then(|g| {
-            let g = g.trim().to_string();
-            if g.is_empty() { None } else { Some(g) }
-        }),
-        enabled: payload.enabled.or(Some(true)),
-        policy: crate::config::TeamPolicy {
-            allowed_routers,
-            allowed_models: payload.allowed_models,
-            rate_limit,
-        },
+    let new_router = crate::config::Router {
+        name: name.clone(),
+        rules: built_rules,
+        channels: vec![],
+        strategy: "round_robin".to_string(),
+        metadata: None,
+        fallback_channels: payload.fallback_channels,
+        fallback_tiers: vec![],
+        fallback_on: None,
+        reject_unknown_models: false,
+        max_request_bytes: None,
+        max_response_bytes: None,
+        cache: None,
+        passthrough: false,
+        sticky: None,
+        retries: None,
+        hedge_after_ms: None,
+        mirror_channel: None,
+        mirror_percent: None,
+        experiment: None,
+        canary: None,
+        model_aliases: Default::default(),
+        default_model: None,
+        reject_missing_model: false,
+        stream_requests: false,
+        vkey: None,
+        transforms: None,
     };
 
-    // Validate uniqueness + apply + persist atomically under the write lock.
+    // Name uniqueness + channel-existence validation + persist, all atomic.
     if let Err(resp) = commit_config(&state, |cfg| {
-        if cfg.teams.iter().any(|t| t.id == id) {
+        if cfg.routers.iter().any(|r| r.name == name) {
             return Err(error_response(
                 StatusCode::CONFLICT,
-                "A team with this id already exists",
+                "A router with this name already exists",
             ));
         }
-        if cfg.teams.iter().any(|t| t.api_key == api_key) {
+        let missing = missing_channels(cfg, &new_router.rules, &new_router.fallback_channels);
+        if !missing.is_empty() {
             return Err(error_response(
-                StatusCode::CONFLICT,
-                "A team with this api_key already exists",
+                StatusCode::BAD_REQUEST,
+                &format!("Unknown channels: {}", missing.join(", ")),
             ));
         }
-        Arc::make_mut(&mut cfg.teams).push(new_team.clone());
+        Arc::make_mut(&mut cfg.routers).push(new_router.clone());
         Ok(())
     }) {
         return resp;
     }
 
-    // For create only: return the *unmasked* api_key once, so the operator
-    // can record it. Subsequent reads will be masked.
-    let mut payload_value = teams_json_response(&new_team);
-    if let Some(obj) = payload_value.as_object_mut() {
-        obj.insert(
-            "api_key".to_string(),
-            serde_json::Value::String(api_key.clone()),
-        );
-        obj.insert(
-            "api_key_revealed".to_string(),
-            serde_json::Value::Bool(true),
-        );
-    }
-
     Response::builder()
         .status(StatusCode::CREATED)
         .header("content-type", "application/json")
-        .body(Body::from(payload_value.to_string()))
+        .body(Body::from(router_json_response(&new_router).to_string()))
         .unwrap()
 }
 
-async fn handle_admin_update_team(
+async fn handle_admin_update_router(
     State(state): State<Arc<AppState>>,
-    axum::extract::Path(team_id): axum::extract::Path<String>,
+    axum::extract::Path(router_name): axum::extract::Path<String>,
     req: Request<Body>,
 ) -> Response<Body> {
     let (parts, body) = req.into_parts();
@@ -2083,65 +5040,85 @@ async fn handle_admin_update_team(
         return resp;
     }
 
-    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
+    let bytes = match axum::body::to_bytes(body, 256 * 1024).await {
         Ok(b) => b,
         Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
     };
-    let payload: UpdateTeamRequest = match serde_json::from_slice(&bytes) {
+    let payload: UpdateRouterRequest = match serde_json::from_slice(&bytes) {
         Ok(p) => p,
         Err(err) => {
             return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
         }
     };
 
-    let updated_team = match commit_config(&state, |cfg| {
-        let teams = Arc::make_mut(&mut cfg.teams);
-        let Some(team) = teams.iter_mut().find(|t| t.id == team_id) else {
-            return Err(error_response(StatusCode::NOT_FOUND, "Team not found"));
-        };
-
-        if let Some(group) = payload.group {
-            team.group = group.and_then(|g| {
-                let trimmed = g.trim().to_string();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some(trimmed)
-                }
-            });
+    let mut built_rules = None;
+    if let Some(rules) = payload.rules {
+        if rules.is_empty() {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "rules must contain at least one rule",
+            );
         }
-        if let Some(enabled) = payload.enabled {
-            team.enabled = Some(enabled);
+        let mut tmp = Vec::with_capacity(rules.len());
+        for rule in rules {
+            match build_rule(rule) {
+                Ok(r) => tmp.push(r),
+                Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+            }
         }
-        if let Some(allowed_routers) = payload.allowed_routers {
-            team.policy.allowed_routers = allowed_routers;
+        built_rules = Some(tmp);
+    }
+
+    let snapshot = match commit_config(&state, |cfg| {
+        // Compute the resulting rules/fallback first, then validate channel
+        // references against the *live* config before mutating.
+        let (final_rules, final_fallback) = {
+            let Some(router) = cfg.routers.iter().find(|r| r.name == router_name) else {
+                return Err(error_response(StatusCode::NOT_FOUND, "Router not found"));
+            };
+            let final_rules = built_rules.clone().unwrap_or_else(|| router.rules.clone());
+            let final_fallback = payload
+                .fallback_channels
+                .clone()
+                .unwrap_or_else(|| router.fallback_channels.clone());
+            (final_rules, final_fallback)
+        };
+
+        let missing = missing_channels(cfg, &final_rules, &final_fallback);
+        if !missing.is_empty() {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Unknown channels: {}", missing.join(", ")),
+            ));
         }
-        if let Some(allowed_models) = payload.allowed_models {
-            team.policy.allowed_models = allowed_models;
+
+        let routers = Arc::make_mut(&mut cfg.routers);
+        let router = routers
+            .iter_mut()
+            .find(|r| r.name == router_name)
+            .expect("router existence already checked above under the same lock");
+        if let Some(rules) = built_rules {
+            router.rules = rules;
         }
-        if let Some(rate_limit) = payload.rate_limit {
-            team.policy.rate_limit = rate_limit.map(|r| crate::config::TeamRateLimit {
-                rpm: r.rpm,
-                tpm: r.tpm,
-            });
+        if let Some(fallback) = payload.fallback_channels {
+            router.fallback_channels = fallback;
         }
-
-        Ok(team.clone())
+        Ok(router.clone())
     }) {
-        Ok(team) => team,
+        Ok(snapshot) => snapshot,
         Err(resp) => return resp,
     };
 
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
-        .body(Body::from(teams_json_response(&updated_team).to_string()))
+        .body(Body::from(router_json_response(&snapshot).to_string()))
         .unwrap()
 }
 
-async fn handle_admin_delete_team(
+async fn handle_admin_delete_router(
     State(state): State<Arc<AppState>>,
-    axum::extract::Path(team_id): axum::extract::Path<String>,
+    axum::extract::Path(router_name): axum::extract::Path<String>,
     req: Request<Body>,
 ) -> Response<Body> {
     let (parts, _body) = req.into_parts();
@@ -2151,11 +5128,33 @@ async fn handle_admin_delete_team(
     }
 
     if let Err(resp) = commit_config(&state, |cfg| {
-        let teams = Arc::make_mut(&mut cfg.teams);
-        let before = teams.len();
-        teams.retain(|t| t.id != team_id);
-        if teams.len() == before {
-            return Err(error_response(StatusCode::NOT_FOUND, "Team not found"));
+        let referring_teams: Vec<String> = cfg
+            .teams
+            .iter()
+            .filter(|t| t.policy.allowed_routers.iter().any(|r| r == &router_name))
+            .map(|t| t.id.clone())
+            .collect();
+        if !referring_teams.is_empty() {
+            return Err(Response::builder()
+                .status(StatusCode::CONFLICT)
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "error": format!(
+                            "Router '{router_name}' is still in allowed_routers of: {}",
+                            referring_teams.join(", ")
+                        ),
+                        "references": referring_teams,
+                    })
+                    .to_string(),
+                ))
+                .unwrap());
+        }
+        let routers = Arc::make_mut(&mut cfg.routers);
+        let before = routers.len();
+        routers.retain(|r| r.name != router_name);
+        if routers.len() == before {
+            return Err(error_response(StatusCode::NOT_FOUND, "Router not found"));
         }
         Ok(())
     }) {
@@ -2165,1217 +5164,2017 @@ async fn handle_admin_delete_team(
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
-        .body(Body::from(json!({"deleted": team_id}).to_string()))
+        .body(Body::from(json!({"deleted": router_name}).to_string()))
         .unwrap()
 }
 
-async fn handle_admin_routers(
+// ---- masked api_key reveal endpoints --------------------------------------
+//
+// These are dedicated endpoints so the bulk list responses can stay
+// secret-free. A separate request makes it easier to:
+//   - audit who reads keys vs. who just browses the config,
+//   - extend later to require an extra confirmation / scope per key, and
+//   - keep the list endpoints cacheable.
+
+async fn handle_admin_teams_api_keys(
     State(state): State<Arc<AppState>>,
     req: Request<Body>,
 ) -> Response<Body> {
     let (parts, _body) = req.into_parts();
-    let headers = &parts.headers;
-
     let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, headers) {
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
         return resp;
     }
 
     let data = config
-        .routers
+        .teams
         .iter()
-        .filter_map(|router| serde_json::to_value(router).ok())
+        .map(|team| {
+            json!({
+                "id": team.id,
+                "api_key": crate::key_store::masked_key(team),
+            })
+        })
         .collect::<Vec<_>>();
 
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
         .body(Body::from(
-            json!({
-                "object": "list",
-                "data": data
-            })
-            .to_string(),
+            json!({ "object": "list", "data": data }).to_string(),
         ))
         .unwrap()
 }
 
-async fn handle_admin_channels(
+/// `GET /admin/teams/:team_id/usage/users?start_date=&end_date=` — token
+/// totals per end user (`user` field) within one team, for per-seat billing.
+async fn handle_admin_team_end_user_usage(
     State(state): State<Arc<AppState>>,
-    req: Request<Body>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let headers = &parts.headers;
-
     let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, headers) {
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
         return resp;
     }
-
-    let data = config
-        .channels
-        .iter()
-        .map(|channel| {
-            // NOTE: api_key is intentionally NOT included in the list
-            // response. Fetch it explicitly via GET /admin/channels/api_keys.
-            json!({
-                "name": channel.name,
-                "provider_type": channel.provider_type,
-                "base_url": channel.base_url,
-                "anthropic_base_url": channel.anthropic_base_url
-            })
-        })
-        .collect::<Vec<_>>();
-
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(
-            json!({
-                "object": "list",
-                "data": data
-            })
-            .to_string(),
-        ))
-        .unwrap()
-}
-
-// -------- Channels CRUD --------
-//
-// Channels write paths share the same shape as Teams: the in-memory `Config`
-// is mutated under the write-lock, then `persist_config` writes the new JSON
-// to disk so the change survives restart. Hot-reload picks it up too.
-//
-// Safety invariants:
-//   * name uniqueness on create
-//   * before delete, refuse if any router rule (or legacy fallback) still
-//     references the channel — silent removal would break routing at runtime.
-
-#[derive(serde::Deserialize)]
-struct CreateChannelRequest {
-    name: String,
-    provider_type: crate::config::ProviderType,
-    base_url: String,
-    api_key: String,
-    #[serde(default)]
-    anthropic_base_url: Option<String>,
-    #[serde(default)]
-    headers: Option<std::collections::HashMap<String, String>>,
-    #[serde(default)]
-    model_map: Option<std::collections::HashMap<String, String>>,
-}
-
-#[derive(serde::Deserialize, Default)]
-struct UpdateChannelRequest {
-    #[serde(default)]
-    provider_type: Option<crate::config::ProviderType>,
-    #[serde(default)]
-    base_url: Option<String>,
-    /// Bearer token / upstream secret. `None` leaves it unchanged.
-    /// (No way to *clear* the key via PATCH — empty key would break the
-    /// channel at runtime. Delete + recreate instead.)
-    #[serde(default)]
-    api_key: Option<String>,
-    /// `Some(None)` clears the anthropic URL; `Some(Some(_))` sets it.
-    #[serde(default, deserialize_with = "deserialize_optional_optional_string")]
-    anthropic_base_url: Option<Option<String>>,
-    #[serde(default, deserialize_with = "deserialize_optional_optional_str_map")]
-    headers: Option<Option<std::collections::HashMap<String, String>>>,
-    #[serde(default, deserialize_with = "deserialize_optional_optional_str_map")]
-    model_map: Option<Option<std::collections::HashMap<String, String>>>,
-}
-
-fn deserialize_optional_optional_string<'de, D>(
-    deserializer: D,
-) -> Result<Option<Option<String>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::Deserialize;
-    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Null => Ok(Some(None)),
-        serde_json::Value::String(s) => Ok(Some(Some(s))),
-        other => Err(serde::de::Error::custom(format!(
-            "expected string or null, got {other:?}"
-        ))),
+    if !config.teams.iter().any(|t| t.id == team_id) {
+        return error_response(StatusCode::NOT_FOUND, "Team not found");
     }
-}
-
-fn deserialize_optional_optional_str_map<'de, D>(
-    deserializer: D,
-) -> Result<Option<Option<std::collections::HashMap<String, String>>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::Deserialize;
-    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Null => Ok(Some(None)),
-        other => {
-            let parsed: std::collections::HashMap<String, String> =
-                serde_json::from_value(other).map_err(serde::de::Error::custom)?;
-            Ok(Some(Some(parsed)))
-        }
+    let start_date = normalize_query_filter(&params, "start_date");
+    let end_date = normalize_query_filter(&params, "end_date");
+    let query = UsageRecordQuery {
+        team_id: Some(team_id.clone()),
+        start_time: start_date.clone(),
+        end_time: end_date.clone(),
+        ..Default::default()
     }
-}
-
-fn channel_json_response(channel: &crate::config::Channel) -> serde_json::Value {
-    json!({
-        "name": channel.name,
-        "provider_type": channel.provider_type,
-        "base_url": channel.base_url,
-        "anthropic_base_url": channel.anthropic_base_url,
-    })
-}
-
-/// Collect router names + rule indices that reference the given channel.
-/// Used by delete handlers to produce a helpful 409 instead of silently
-/// leaving the gateway pointing at a deleted channel.
-fn collect_channel_references(config: &Config, channel_name: &str) -> Vec<String> {
-    let mut refs = Vec::new();
-    for router in config.routers.iter() {
-        for (idx, rule) in router.rules.iter().enumerate() {
-            if rule.channels.iter().any(|c| c.name == channel_name) {
-                refs.push(format!("router '{}' rule #{}", router.name, idx + 1));
-            }
-        }
-        if router.channels.iter().any(|c| c.name == channel_name) {
-            refs.push(format!("router '{}' legacy channels", router.name));
-        }
-        if router.fallback_channels.iter().any(|c| c == channel_name) {
-            refs.push(format!("router '{}' fallback", router.name));
-        }
+    .with_inclusive_end_date();
+    match state.database.get_end_user_usage(&query) {
+        Ok(rows) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "team_id": team_id,
+                    "start_date": start_date,
+                    "end_date": end_date,
+                    "data": rows,
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
     }
-    refs
 }
 
-async fn handle_admin_create_channel(
+/// `GET /admin/teams/:team_id/pii/redactions?start_date=&end_date=&limit=`
+/// — which PII rules masked or blocked the team's prompts, newest first.
+async fn handle_admin_team_pii_redactions(
     State(state): State<Arc<AppState>>,
-    req: Request<Body>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> Response<Body> {
-    let (parts, body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &headers) {
         return resp;
     }
-
-    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
-        Ok(b) => b,
-        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
-    };
-    let payload: CreateChannelRequest = match serde_json::from_slice(&bytes) {
-        Ok(p) => p,
-        Err(err) => {
-            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
-        }
-    };
-
-    let name = payload.name.trim().to_string();
-    if name.is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "name must not be empty");
-    }
-    if payload.base_url.trim().is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "base_url must not be empty");
-    }
-    if payload.api_key.trim().is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "api_key must not be empty");
+    if team_id != "global" && !config.teams.iter().any(|t| t.id == team_id) {
+        return error_response(StatusCode::NOT_FOUND, "Team not found");
     }
-
-    let new_channel = crate::config::Channel {
-        name: name.clone(),
-        provider_type: payload.provider_type,
-        base_url: payload.base_url.trim().to_string(),
-        api_key: payload.api_key,
-        anthropic_base_url: payload
-            .anthropic_base_url
-            .map(|v| v.trim().to_string())
-            .filter(|v| !v.is_empty()),
-        headers: payload.headers,
-        model_map: payload.model_map,
-        timeouts: None,
-    };
-
-    if let Err(resp) = commit_config(&state, |cfg| {
-        if cfg.channels.iter().any(|c| c.name == name) {
-            return Err(error_response(
-                StatusCode::CONFLICT,
-                "A channel with this name already exists",
-            ));
-        }
-        Arc::make_mut(&mut cfg.channels).push(new_channel.clone());
-        Ok(())
-    }) {
-        return resp;
+    let start_date = normalize_query_filter(&params, "start_date");
+    let end_date = normalize_query_filter(&params, "end_date");
+    let limit = params
+        .get("limit")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(100)
+        .min(1000);
+    match state.database.get_pii_redactions(
+        &team_id,
+        start_date.as_deref(),
+        end_date.as_deref(),
+        limit,
+    ) {
+        Ok(rows) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "team_id": team_id,
+                    "start_date": start_date,
+                    "end_date": end_date,
+                    "data": rows,
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
     }
-
-    Response::builder()
-        .status(StatusCode::CREATED)
-        .header("content-type", "application/json")
-        .body(Body::from(channel_json_response(&new_channel).to_string()))
-        .unwrap()
 }
 
-async fn handle_admin_update_channel(
+async fn handle_admin_team_reveal_api_key(
     State(state): State<Arc<AppState>>,
-    axum::extract::Path(channel_name): axum::extract::Path<String>,
+    axum::extract::Path(team_id): axum::extract::Path<String>,
     req: Request<Body>,
 ) -> Response<Body> {
-    let (parts, body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
         return resp;
     }
 
-    let bytes = match axum::body::to_bytes(body, 64 * 1024).await {
-        Ok(b) => b,
-        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
-    };
-    let payload: UpdateChannelRequest = match serde_json::from_slice(&bytes) {
-        Ok(p) => p,
-        Err(err) => {
-            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
-        }
-    };
+    match config.teams.iter().find(|team| team.id == team_id) {
+        // Managed keys are stored hashed and shown only when created.
+        Some(team) if team.api_key.is_empty() => error_response(
+            StatusCode::NOT_FOUND,
+            "Team has no plaintext api_key; managed keys are only shown when created",
+        ),
+        Some(team) => Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({ "id": team.id, "api_key": team.api_key }).to_string(),
+            ))
+            .unwrap(),
+        None => error_response(StatusCode::NOT_FOUND, "Team not found"),
+    }
+}
 
-    let snapshot = match commit_config(&state, |cfg| {
-        let channels = Arc::make_mut(&mut cfg.channels);
-        let Some(channel) = channels.iter_mut().find(|c| c.name == channel_name) else {
-            return Err(error_response(StatusCode::NOT_FOUND, "Channel not found"));
-        };
+async fn handle_admin_channels_api_keys(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
+        return resp;
+    }
 
-        if let Some(pt) = payload.provider_type {
-            channel.provider_type = pt;
-        }
-        if let Some(base_url) = payload.base_url {
-            let trimmed = base_url.trim().to_string();
-            if trimmed.is_empty() {
-                return Err(error_response(
-                    StatusCode::BAD_REQUEST,
-                    "base_url must not be empty",
-                ));
-            }
-            channel.base_url = trimmed;
-        }
-        if let Some(api_key) = payload.api_key {
-            if api_key.trim().is_empty() {
-                return Err(error_response(
-                    StatusCode::BAD_REQUEST,
-                    "api_key must not be empty (omit field to keep current)",
-                ));
+    let data = config
+        .channels
+        .iter()
+        .map(|channel| {
+            let mut entry = json!({
+                "name": channel.name,
+                "api_key": mask_secret(&channel.api_key),
+            });
+            if !channel.api_keys.is_empty() {
+                entry["api_keys"] = json!(
+                    channel
+                        .api_keys
+                        .iter()
+                        .map(|key| mask_secret(key))
+                        .collect::<Vec<_>>()
+                );
             }
-            channel.api_key = api_key;
-        }
-        if let Some(anthropic) = payload.anthropic_base_url {
-            channel.anthropic_base_url = anthropic
-                .map(|v| v.trim().to_string())
-                .filter(|v| !v.is_empty());
-        }
-        if let Some(headers) = payload.headers {
-            channel.headers = headers;
-        }
-        if let Some(model_map) = payload.model_map {
-            channel.model_map = model_map;
-        }
-
-        Ok(channel.clone())
-    }) {
-        Ok(snapshot) => snapshot,
-        Err(resp) => return resp,
-    };
+            entry
+        })
+        .collect::<Vec<_>>();
 
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
-        .body(Body::from(channel_json_response(&snapshot).to_string()))
+        .body(Body::from(
+            json!({ "object": "list", "data": data }).to_string(),
+        ))
         .unwrap()
 }
 
-async fn handle_admin_delete_channel(
+// providers.json embedded at build time so the provider-templates endpoint
+// always has data even when the file isn't present in the process CWD (e.g.
+// installed deployments). A runtime providers.json, when present, takes
+// precedence so operators can customize the default catalog.
+const EMBEDDED_PROVIDERS_JSON: &str = include_str!("../providers.json");
+
+#[derive(serde::Deserialize)]
+struct CpProviderFile {
+    #[serde(default)]
+    provider_templates: Vec<CpProviderTemplate>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+struct CpProviderTemplate {
+    provider_type: String,
+    base_url: String,
+    #[serde(default)]
+    anthropic_base_url: Option<String>,
+}
+
+fn load_cp_provider_templates() -> Vec<CpProviderTemplate> {
+    // Prefer a runtime providers.json next to the working directory.
+    if let Ok(cwd) = std::env::current_dir() {
+        let path = cwd.join("providers.json");
+        if let Ok(content) = std::fs::read_to_string(&path)
+            && let Ok(file) = serde_json::from_str::<CpProviderFile>(&content)
+        {
+            return file.provider_templates;
+        }
+    }
+    // Fall back to the catalog embedded at build time.
+    serde_json::from_str::<CpProviderFile>(EMBEDDED_PROVIDERS_JSON)
+        .map(|f| f.provider_templates)
+        .unwrap_or_default()
+}
+
+/// `GET /api/cp/provider-templates` — the default base_url / anthropic_base_url
+/// per provider type, used by the control plane to pre-fill the channel form.
+async fn handle_cp_provider_templates(
     State(state): State<Arc<AppState>>,
-    axum::extract::Path(channel_name): axum::extract::Path<String>,
     req: Request<Body>,
 ) -> Response<Body> {
     let (parts, _body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
+    let config = state.config.read().unwrap().clone();
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
         return resp;
     }
 
-    // Reference check + delete + persist atomically: a concurrent router
-    // create/update can't slip a new reference in between check and delete.
-    if let Err(resp) = commit_config(&state, |cfg| {
-        let refs = collect_channel_references(cfg, &channel_name);
-        if !refs.is_empty() {
-            return Err(Response::builder()
-                .status(StatusCode::CONFLICT)
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "error": format!(
-                            "Channel '{channel_name}' is still referenced by: {}",
-                            refs.join(", ")
-                        ),
-                        "references": refs,
-                    })
-                    .to_string(),
-                ))
-                .unwrap());
-        }
-        let channels = Arc::make_mut(&mut cfg.channels);
-        let before = channels.len();
-        channels.retain(|c| c.name != channel_name);
-        if channels.len() == before {
-            return Err(error_response(StatusCode::NOT_FOUND, "Channel not found"));
-        }
-        Ok(())
-    }) {
+    let data = load_cp_provider_templates();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "object": "list", "data": data }).to_string(),
+        ))
+        .unwrap()
+}
+
+async fn handle_cp_info(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
+    let (parts, _body) = req.into_parts();
+    let config = state.config.read().unwrap().clone();
+
+    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
         return resp;
     }
 
+    let info = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "listen": config.global.listen,
+        "auth_required": !config.global.auth_keys.is_empty(),
+        "auth_key_count": config.global.auth_keys.len(),
+        "cors_origins": config.global.cors_allowed_origins,
+        "timeouts": {
+            "connect_ms": config.global.timeouts.connect_ms,
+            "request_ms": config.global.timeouts.request_ms,
+            "response_ms": config.global.timeouts.response_ms,
+        },
+        "retries": {
+            "max_attempts": config.global.retries.max_attempts,
+            "backoff_ms": config.global.retries.backoff_ms,
+        },
+        "channels": config.channels.len(),
+        "routers": config.routers.len(),
+        "teams": config.teams.len(),
+        "metrics_enabled": config.metrics.enabled,
+        "hot_reload": config.hot_reload.watch,
+    });
+
     Response::builder()
         .status(StatusCode::OK)
         .header("content-type", "application/json")
-        .body(Body::from(json!({"deleted": channel_name}).to_string()))
+        .body(Body::from(info.to_string()))
         .unwrap()
 }
 
-// -------- Routers CRUD --------
-//
-// Routers are heavier than channels: a router carries an ordered list of
-// `RouterRule` (match patterns + strategy + target channels) plus optional
-// fallback channels. The write payload mirrors the on-disk JSON shape so
-// the same blob can round-trip through `save_config`.
-//
-// Safety invariants:
-//   * name uniqueness on create
-//   * every channel referenced by any rule (or fallback / legacy channels)
-//     must already exist
-//   * before delete, refuse if any team.allowed_routers still references it
+// Helpers
 
-#[derive(serde::Deserialize, Default)]
-struct RouterRuleInput {
-    #[serde(default)]
-    models: Vec<String>,
-    #[serde(default)]
-    channels: Vec<TargetChannelInput>,
-    #[serde(default)]
-    strategy: Option<String>,
-}
+#[allow(clippy::result_large_err)]
+fn enforce_global_auth(config: &Config, headers: &HeaderMap) -> Result<(), Response<Body>> {
+    let keys = &config.global.auth_keys;
+
+    // If no auth_keys configured, skip validation
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let candidates = [
+        read_auth_token(headers, "authorization"),
+        read_auth_token(headers, "x-api-key"),
+    ];
+
+    for token in candidates.into_iter().flatten() {
+        if keys.contains(&token) && !config.global.is_key_revoked(&token) {
+            return Ok(());
+        }
+    }
 
-#[derive(serde::Deserialize, Default, Clone)]
-struct TargetChannelInput {
-    name: String,
-    #[serde(default = "default_target_weight")]
-    weight: u32,
+    tracing::warn!("Auth Failed: No valid token found in Authorization or x-api-key headers.");
+    Err(error_response(StatusCode::UNAUTHORIZED, "unauthorized"))
 }
 
-fn default_target_weight() -> u32 {
-    1
+/// Usage `team_id` recorded for requests authenticated with a router's vkey.
+fn vkey_usage_id(router: &str) -> String {
+    format!("vkey:{router}")
 }
 
-#[derive(serde::Deserialize, Default)]
-struct CreateRouterRequest {
-    name: String,
-    #[serde(default)]
-    rules: Vec<RouterRuleInput>,
-    #[serde(default)]
-    fallback_channels: Vec<String>,
+/// Masked caller key recorded with access audit events.
+fn caller_key_for_audit(headers: &HeaderMap) -> Option<String> {
+    read_auth_token(headers, "authorization")
+        .or_else(|| read_auth_token(headers, "x-api-key"))
+        .map(|key| mask_secret(&key))
 }
 
-#[derive(serde::Deserialize, Default)]
-struct UpdateRouterRequest {
-    #[serde(default)]
-    rules: Option<Vec<RouterRuleInput>>,
-    #[serde(default)]
-    fallback_channels: Option<Vec<String>>,
+fn read_auth_token(headers: &HeaderMap, key: &str) -> Option<String> {
+    if let Some(val) = headers.get(key)
+        && let Ok(s) = val.to_str()
+    {
+        if key == "authorization" && s.starts_with("Bearer ") {
+            return Some(s[7..].to_string());
+        }
+        return Some(s.to_string());
+    }
+    None
 }
 
-fn router_json_response(router: &crate::config::Router) -> serde_json::Value {
-    serde_json::to_value(router).unwrap_or(serde_json::Value::Null)
+use crate::utils::mask_secret;
+
+pub(crate) fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = json!({
+        "error": {
+            "message": message,
+            "type": "invalid_request_error",
+            "param": null,
+            "code": null
+        }
+    });
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
 }
 
-fn build_rule(input: RouterRuleInput) -> Result<crate::config::RouterRule, String> {
-    if input.channels.is_empty() {
-        return Err("each rule must have at least one channel".into());
-    }
-    let strategy = input
-        .strategy
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .unwrap_or("round_robin")
-        .to_string();
-    match strategy.as_str() {
-        "round_robin" | "random" | "priority" => {}
-        other => return Err(format!("unknown strategy '{other}'")),
-    }
-    let channels = input
-        .channels
-        .into_iter()
-        .map(|c| crate::config::TargetChannel {
-            name: c.name,
-            weight: c.weight.max(1),
-        })
-        .collect();
-    Ok(crate::config::RouterRule {
-        match_spec: crate::config::MatchSpec {
-            models: input.models,
-        },
-        channels,
-        strategy,
-    })
+struct GeminiNativeRoute {
+    routing_model: String,
+    direct_pass: bool,
 }
 
-/// Verify every channel referenced by a router exists. Returns the list of
-/// missing channel names (empty if all OK).
-fn missing_channels(
-    config: &Config,
-    rules: &[crate::config::RouterRule],
-    fallback: &[String],
-) -> Vec<String> {
-    let known: std::collections::HashSet<&str> =
-        config.channels.iter().map(|c| c.name.as_str()).collect();
-    let mut missing = std::collections::BTreeSet::new();
-    for rule in rules {
-        for tc in &rule.channels {
-            if !known.contains(tc.name.as_str()) {
-                missing.insert(tc.name.clone());
-            }
+fn validate_gemini_native_route(method: &Method, path: &str) -> Option<GeminiNativeRoute> {
+    let path = path.trim_start_matches('/');
+    let segments = path.split('/').collect::<Vec<_>>();
+
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["v1beta", "models"]) => Some(gemini_native_resource_route()),
+        (&Method::GET, ["v1beta", "models", model]) if !model.contains(':') => {
+            Some(gemini_native_model_route(model))
         }
-    }
-    for name in fallback {
-        if !known.contains(name.as_str()) {
-            missing.insert(name.clone());
+        (&Method::POST, ["v1beta", "models", model_action])
+            if model_action.ends_with(":generateContent")
+                || model_action.ends_with(":streamGenerateContent") =>
+        {
+            model_action
+                .split_once(':')
+                .filter(|(model, _)| !model.is_empty())
+                .map(|(model, _)| gemini_native_model_route(model))
+        }
+        (&Method::GET, ["v1beta", "fileSearchStores"])
+        | (&Method::POST, ["v1beta", "fileSearchStores"]) => Some(gemini_native_resource_route()),
+        (&Method::GET, ["v1beta", "fileSearchStores", store])
+        | (&Method::DELETE, ["v1beta", "fileSearchStores", store])
+            if !store.contains(':') =>
+        {
+            Some(gemini_native_resource_route())
         }
+        (&Method::POST, ["v1beta", "fileSearchStores", store_action])
+        | (&Method::POST, ["upload", "v1beta", "fileSearchStores", store_action])
+            if store_action.ends_with(":uploadToFileSearchStore") =>
+        {
+            Some(GeminiNativeRoute {
+                routing_model: "gemini-native".to_string(),
+                direct_pass: true,
+            })
+        }
+        (&Method::GET, ["v1beta", "fileSearchStores", store, "operations", operation])
+        | (
+            &Method::GET,
+            [
+                "v1beta",
+                "fileSearchStores",
+                store,
+                "upload",
+                "operations",
+                operation,
+            ],
+        ) if !store.is_empty() && !operation.is_empty() => Some(gemini_native_resource_route()),
+        (&Method::POST, ["v1beta", "interactions"]) => Some(gemini_native_direct_pass_route()),
+        (&Method::GET, ["v1beta", "interactions", interaction]) if !interaction.is_empty() => {
+            Some(gemini_native_direct_pass_route())
+        }
+        _ => None,
     }
-    missing.into_iter().collect()
 }
 
-async fn handle_admin_create_router(
-    State(state): State<Arc<AppState>>,
-    req: Request<Body>,
-) -> Response<Body> {
-    let (parts, body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
-        return resp;
+fn gemini_native_resource_route() -> GeminiNativeRoute {
+    GeminiNativeRoute {
+        routing_model: "gemini-native".to_string(),
+        direct_pass: false,
     }
+}
 
-    let bytes = match axum::body::to_bytes(body, 256 * 1024).await {
-        Ok(b) => b,
-        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
-    };
-    let payload: CreateRouterRequest = match serde_json::from_slice(&bytes) {
-        Ok(p) => p,
-        Err(err) => {
-            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
-        }
-    };
-
-    let name = payload.name.trim().to_string();
-    if name.is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "name must not be empty");
-    }
-    if payload.rules.is_empty() {
-        return error_response(
-            StatusCode::BAD_REQUEST,
-            "rules must contain at least one rule",
-        );
+fn gemini_native_direct_pass_route() -> GeminiNativeRoute {
+    GeminiNativeRoute {
+        routing_model: "gemini-native".to_string(),
+        direct_pass: true,
     }
+}
 
-    let mut built_rules = Vec::with_capacity(payload.rules.len());
-    for rule in payload.rules {
-        match build_rule(rule) {
-            Ok(r) => built_rules.push(r),
-            Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
-        }
+fn gemini_native_model_route(model: &str) -> GeminiNativeRoute {
+    GeminiNativeRoute {
+        routing_model: model.to_string(),
+        direct_pass: false,
     }
+}
 
-    let new_router = crate::config::Router {
-        name: name.clone(),
-        rules: built_rules,
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: payload.fallback_channels,
-    };
-
-    // Name uniqueness + channel-existence validation + persist, all atomic.
-    if let Err(resp) = commit_config(&state, |cfg| {
-        if cfg.routers.iter().any(|r| r.name == name) {
-            return Err(error_response(
-                StatusCode::CONFLICT,
-                "A router with this name already exists",
-            ));
-        }
-        let missing = missing_channels(cfg, &new_router.rules, &new_router.fallback_channels);
-        if !missing.is_empty() {
-            return Err(error_response(
-                StatusCode::BAD_REQUEST,
-                &format!("Unknown channels: {}", missing.join(", ")),
-            ));
+fn gemini_native_resource_router_is_deterministic(
+    router: &crate::config::Router,
+    model: &str,
+) -> bool {
+    router.rules.iter().any(|rule| {
+        crate::config::TeamPolicy {
+            allowed_routers: Vec::new(),
+            allowed_models: Some(rule.match_spec.models.clone()),
+            rate_limit: None,
+            allow_routing_overrides: false,
+            end_user_rate_limit: None,
+            reject_unknown_models: false,
+            transcripts: None,
+            pii: None,
+            max_tokens_per_request: None,
+            max_context_tokens: None,
+            clamp_max_tokens: false,
+            priority: Default::default(),
         }
-        Arc::make_mut(&mut cfg.routers).push(new_router.clone());
-        Ok(())
-    }) {
-        return resp;
-    }
+        .is_model_allowed(model)
+            && rule.strategy == "priority"
+            && rule.channels.len() == 1
+    })
+}
 
+fn gemini_native_error_response(
+    status: StatusCode,
+    message: &str,
+    gemini_status: &str,
+) -> Response<Body> {
+    let body = json!({
+        "error": {
+            "code": status.as_u16(),
+            "message": message,
+            "status": gemini_status,
+        }
+    });
     Response::builder()
-        .status(StatusCode::CREATED)
+        .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(router_json_response(&new_router).to_string()))
+        .body(Body::from(body.to_string()))
         .unwrap()
 }
 
-async fn handle_admin_update_router(
-    State(state): State<Arc<AppState>>,
-    axum::extract::Path(router_name): axum::extract::Path<String>,
-    req: Request<Body>,
+/// 429 in the caller's protocol with `Retry-After`, so SDK backoff waits
+/// for the bucket to refill instead of hot-looping.
+pub(crate) fn rate_limited_response(
+    route: RouteKind,
+    retry_after: Duration,
+    message: &str,
 ) -> Response<Body> {
-    let (parts, body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
-        return resp;
-    }
-
-    let bytes = match axum::body::to_bytes(body, 256 * 1024).await {
-        Ok(b) => b,
-        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
-    };
-    let payload: UpdateRouterRequest = match serde_json::from_slice(&bytes) {
-        Ok(p) => p,
-        Err(err) => {
-            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {err}"));
+    let mut response = match route {
+        RouteKind::Openai => {
+            let body = json!({
+                "error": {
+                    "message": message,
+                    "type": "rate_limit_error",
+                    "param": null,
+                    "code": "rate_limit_exceeded"
+                }
+            });
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
         }
-    };
-
-    let mut built_rules = None;
-    if let Some(rules) = payload.rules {
-        if rules.is_empty() {
-            return error_response(
-                StatusCode::BAD_REQUEST,
-                "rules must contain at least one rule",
-            );
+        RouteKind::Anthropic => {
+            let body = json!({
+                "type": "error",
+                "error": {
+                    "type": "rate_limit_error",
+                    "message": message,
+                }
+            });
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
         }
-        let mut tmp = Vec::with_capacity(rules.len());
-        for rule in rules {
-            match build_rule(rule) {
-                Ok(r) => tmp.push(r),
-                Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
-            }
+        RouteKind::GeminiNative => {
+            protocol_error_response(route, StatusCode::TOO_MANY_REQUESTS, message)
         }
-        built_rules = Some(tmp);
+    };
+    let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    response.headers_mut().insert(
+        axum::http::header::RETRY_AFTER,
+        axum::http::HeaderValue::from(seconds),
+    );
+    response
+}
+
+/// `X-RateLimit-Remaining-Requests` / `-Tokens` for the buckets under `key`
+/// (a team, or a team's end user). `X-RateLimit-Remaining` repeats the
+/// request count, or the token count when only TPM is limited.
+pub(crate) async fn insert_remaining_headers(
+    headers: &mut HeaderMap,
+    limiter: &TeamRateLimiter,
+    key: &str,
+) {
+    let (requests, tokens) = limiter.remaining(key).await;
+    if let Some(requests) = requests {
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from(requests),
+        );
+    }
+    if let Some(tokens) = tokens {
+        headers.insert("x-ratelimit-remaining-tokens", HeaderValue::from(tokens));
     }
+    if let Some(remaining) = requests.or(tokens) {
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+    }
+}
 
-    let snapshot = match commit_config(&state, |cfg| {
-        // Compute the resulting rules/fallback first, then validate channel
-        // references against the *live* config before mutating.
-        let (final_rules, final_fallback) = {
-            let Some(router) = cfg.routers.iter().find(|r| r.name == router_name) else {
-                return Err(error_response(StatusCode::NOT_FOUND, "Router not found"));
-            };
-            let final_rules = built_rules.clone().unwrap_or_else(|| router.rules.clone());
-            let final_fallback = payload
-                .fallback_channels
-                .clone()
-                .unwrap_or_else(|| router.fallback_channels.clone());
-            (final_rules, final_fallback)
+pub(crate) fn protocol_error_response(
+    route: RouteKind,
+    status: StatusCode,
+    message: &str,
+) -> Response<Body> {
+    if matches!(route, RouteKind::GeminiNative) {
+        let gemini_status = match status {
+            StatusCode::NOT_FOUND => "NOT_FOUND",
+            StatusCode::FORBIDDEN => "PERMISSION_DENIED",
+            StatusCode::UNAUTHORIZED => "UNAUTHENTICATED",
+            StatusCode::TOO_MANY_REQUESTS => "RESOURCE_EXHAUSTED",
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => "UNAVAILABLE",
+            _ => "INVALID_ARGUMENT",
         };
+        gemini_native_error_response(status, message, gemini_status)
+    } else if matches!(route, RouteKind::Anthropic) {
+        let body = json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": message,
+            }
+        });
+        Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    } else {
+        error_response(status, message)
+    }
+}
 
-        let missing = missing_channels(cfg, &final_rules, &final_fallback);
-        if !missing.is_empty() {
-            return Err(error_response(
-                StatusCode::BAD_REQUEST,
-                &format!("Unknown channels: {}", missing.join(", ")),
-            ));
-        }
-
-        let routers = Arc::make_mut(&mut cfg.routers);
-        let router = routers
-            .iter_mut()
-            .find(|r| r.name == router_name)
-            .expect("router existence already checked above under the same lock");
-        if let Some(rules) = built_rules {
-            router.rules = rules;
-        }
-        if let Some(fallback) = payload.fallback_channels {
-            router.fallback_channels = fallback;
+/// 400 for content a guardrail blocked; OpenAI and Anthropic errors carry
+/// the rule in `error.guardrail`.
+fn guardrail_blocked_response(
+    state: &AppState,
+    route: RouteKind,
+    team_id: &str,
+    violation: &crate::guardrails::Violation,
+) -> Response<Body> {
+    let message = violation.message();
+    tracing::warn!("Guardrail Blocked: {} (team={})", message, team_id);
+    state
+        .metrics
+        .guardrail_blocked_total
+        .with_label_values(&[team_id, &violation.rule, violation.stage.as_str()])
+        .inc();
+    let body = match route {
+        RouteKind::GeminiNative => {
+            return protocol_error_response(route, StatusCode::BAD_REQUEST, &message);
         }
-        Ok(router.clone())
-    }) {
-        Ok(snapshot) => snapshot,
-        Err(resp) => return resp,
+        RouteKind::Anthropic => json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": message,
+                "guardrail": violation.details(),
+            }
+        }),
+        RouteKind::Openai => json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": "content_policy_violation",
+                "guardrail": violation.details(),
+            }
+        }),
     };
-
     Response::builder()
-        .status(StatusCode::OK)
+        .status(StatusCode::BAD_REQUEST)
         .header("content-type", "application/json")
-        .body(Body::from(router_json_response(&snapshot).to_string()))
+        .body(Body::from(body.to_string()))
         .unwrap()
 }
 
-async fn handle_admin_delete_router(
-    State(state): State<Arc<AppState>>,
-    axum::extract::Path(router_name): axum::extract::Path<String>,
-    req: Request<Body>,
-) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config_snapshot = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config_snapshot, &parts.headers) {
-        return resp;
+/// Check a non-streaming response against the guardrails that inspect
+/// responses; `Err` is the response to send instead.
+async fn guard_response(
+    state: &AppState,
+    config: &Config,
+    route: RouteKind,
+    team_id: &str,
+    response: Response<Body>,
+) -> Result<Response<Body>, Response<Body>> {
+    let Some(guardrails) = config
+        .guardrails
+        .as_ref()
+        .filter(|g| crate::guardrails::inspects(g, crate::guardrails::Stage::Response))
+    else {
+        return Ok(response);
+    };
+    if crate::utils::is_event_stream(response.headers()) {
+        return Ok(response);
+    }
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::warn!("Guardrails: failed to read response: {}", err);
+            return Ok(Response::from_parts(parts, Body::empty()));
+        }
+    };
+    if let Some(violation) = crate::guardrails::evaluate(
+        guardrails,
+        &config.channels,
+        &state.providers,
+        &state.client,
+        crate::guardrails::Stage::Response,
+        &bytes,
+    )
+    .await
+    {
+        return Err(guardrail_blocked_response(
+            state, route, team_id, &violation,
+        ));
     }
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
 
-    if let Err(resp) = commit_config(&state, |cfg| {
-        let referring_teams: Vec<String> = cfg
-            .teams
-            .iter()
-            .filter(|t| t.policy.allowed_routers.iter().any(|r| r == &router_name))
-            .map(|t| t.id.clone())
-            .collect();
-        if !referring_teams.is_empty() {
-            return Err(Response::builder()
-                .status(StatusCode::CONFLICT)
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "error": format!(
-                            "Router '{router_name}' is still in allowed_routers of: {}",
-                            referring_teams.join(", ")
-                        ),
-                        "references": referring_teams,
+fn format_error_chain(error: &dyn std::error::Error) -> String {
+    let mut parts = vec![error.to_string()];
+    let mut current = error.source();
+    while let Some(source) = current {
+        parts.push(source.to_string());
+        current = source.source();
+    }
+    parts.join(" | caused by: ")
+}
+
+fn anthropic_request_contains_tool_result(body: &Bytes) -> bool {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return false;
+    };
+
+    value
+        .get("messages")
+        .and_then(serde_json::Value::as_array)
+        .map(|messages| {
+            messages.iter().any(|message| {
+                message
+                    .get("content")
+                    .and_then(serde_json::Value::as_array)
+                    .map(|parts| {
+                        parts.iter().any(|part| {
+                            part.get("type").and_then(serde_json::Value::as_str)
+                                == Some("tool_result")
+                        })
                     })
-                    .to_string(),
-                ))
-                .unwrap());
-        }
-        let routers = Arc::make_mut(&mut cfg.routers);
-        let before = routers.len();
-        routers.retain(|r| r.name != router_name);
-        if routers.len() == before {
-            return Err(error_response(StatusCode::NOT_FOUND, "Router not found"));
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn summarize_anthropic_request(body: &Bytes) -> String {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return "invalid_json".to_string();
+    };
+    let Some(messages) = value.get("messages").and_then(serde_json::Value::as_array) else {
+        return "messages=missing".to_string();
+    };
+
+    let mut referenced_tool_use_ids = Vec::new();
+    let mut segments = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let role = message
+            .get("role")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("unknown");
+        let mut kinds = Vec::new();
+        if let Some(content) = message.get("content").and_then(serde_json::Value::as_array) {
+            for block in content {
+                let kind = block
+                    .get("type")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or("unknown");
+                kinds.push(kind.to_string());
+                if kind == "tool_result"
+                    && let Some(tool_use_id) =
+                        block.get("tool_use_id").and_then(serde_json::Value::as_str)
+                {
+                    referenced_tool_use_ids.push(tool_use_id.to_string());
+                }
+            }
         }
-        Ok(())
-    }) {
-        return resp;
+        segments.push(format!("{index}:{role}[{}]", kinds.join(",")));
     }
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(json!({"deleted": router_name}).to_string()))
-        .unwrap()
+    format!(
+        "messages={} {} tool_result_ids={:?}",
+        messages.len(),
+        segments.join(" "),
+        referenced_tool_use_ids
+    )
 }
 
-// ---- masked api_key reveal endpoints --------------------------------------
-//
-// These are dedicated endpoints so the bulk list responses can stay
-// secret-free. A separate request makes it easier to:
-//   - audit who reads keys vs. who just browses the config,
-//   - extend later to require an extra confirmation / scope per key, and
-//   - keep the list endpoints cacheable.
-
-async fn handle_admin_teams_api_keys(
-    State(state): State<Arc<AppState>>,
-    req: Request<Body>,
-) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
-        return resp;
-    }
+fn request_id_from_parts(parts: &axum::http::request::Parts) -> Option<String> {
+    parts
+        .extensions
+        .get::<tower_http::request_id::RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .map(|id| id.to_string())
+}
 
-    let data = config
-        .teams
-        .iter()
-        .map(|team| {
-            json!({
-                "id": team.id,
-                "api_key": mask_secret(&team.api_key),
-            })
-        })
-        .collect::<Vec<_>>();
+fn provider_trace_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    const TRACE_HEADERS: [&str; 5] = [
+        "x-request-id",
+        "request-id",
+        "x-trace-id",
+        "trace-id",
+        "cf-ray",
+    ];
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(
-            json!({ "object": "list", "data": data }).to_string(),
-        ))
-        .unwrap()
+    TRACE_HEADERS.iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    })
 }
 
-async fn handle_admin_team_reveal_api_key(
-    State(state): State<Arc<AppState>>,
-    axum::extract::Path(team_id): axum::extract::Path<String>,
-    req: Request<Body>,
+fn response_from_upstream_bytes(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: Bytes,
 ) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
-        return resp;
+    let mut builder = HttpResponse::builder().status(status);
+    for (name, value) in headers {
+        if crate::providers::should_forward_response_header(name) {
+            builder = builder.header(name, value);
+        }
     }
 
-    match config.teams.iter().find(|team| team.id == team_id) {
-        Some(team) => Response::builder()
-            .status(StatusCode::OK)
-            .header("content-type", "application/json")
-            .body(Body::from(
-                json!({ "id": team.id, "api_key": team.api_key }).to_string(),
-            ))
-            .unwrap(),
-        None => error_response(StatusCode::NOT_FOUND, "Team not found"),
-    }
+    builder
+        .body(Body::from(body))
+        .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid upstream response"))
 }
 
-async fn handle_admin_channels_api_keys(
-    State(state): State<Arc<AppState>>,
-    req: Request<Body>,
-) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
-        return resp;
+/// Conversation key for `sticky` rules: the router's configured header, else
+/// the request's end-user id.
+fn sticky_key(
+    router: &crate::config::Router,
+    headers: &HeaderMap,
+    client_info: &crate::utils::ClientInfo,
+) -> Option<String> {
+    router
+        .sticky
+        .as_ref()
+        .and_then(|sticky| sticky.header.as_deref())
+        .and_then(|name| headers.get(name))
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .or_else(|| client_info.end_user.clone())
+}
+
+const ROUTING_CHANNEL_HEADER: &str = "x-apex-channel";
+const ROUTING_MODEL_OVERRIDE_HEADER: &str = "x-apex-model-override";
+/// Model of a request whose body is streamed through unread (see
+/// `Router::stream_requests`); the body's `model` must match it.
+const STREAMING_MODEL_HEADER: &str = "x-apex-model";
+
+/// Per-request routing overrides supplied via `x-apex-*` headers.
+#[derive(Debug, Default)]
+struct RoutingOverrides {
+    channel: Option<String>,
+    model: Option<String>,
+}
+
+impl RoutingOverrides {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let read = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            channel: read(ROUTING_CHANNEL_HEADER),
+            model: read(ROUTING_MODEL_OVERRIDE_HEADER),
+        }
     }
 
-    let data = config
-        .channels
-        .iter()
-        .map(|channel| {
-            json!({
-                "name": channel.name,
-                "api_key": mask_secret(&channel.api_key),
-            })
-        })
-        .collect::<Vec<_>>();
-
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(
-            json!({ "object": "list", "data": data }).to_string(),
-        ))
-        .unwrap()
+    fn is_empty(&self) -> bool {
+        self.channel.is_none() && self.model.is_none()
+    }
 }
 
-// providers.json embedded at build time so the provider-templates endpoint
-// always has data even when the file isn't present in the process CWD (e.g.
-// installed deployments). A runtime providers.json, when present, takes
-// precedence so operators can customize the default catalog.
-const EMBEDDED_PROVIDERS_JSON: &str = include_str!("../providers.json");
-
-#[derive(serde::Deserialize)]
-struct CpProviderFile {
-    #[serde(default)]
-    provider_templates: Vec<CpProviderTemplate>,
+/// Model a requested alias stands for (see `Config::model_alias`). Gemini
+/// native routes carry the model in the path and are never aliased.
+fn model_alias<'a>(
+    config: &'a Config,
+    route: RouteKind,
+    router: Option<&'a crate::config::Router>,
+    model: &str,
+) -> Option<&'a str> {
+    if matches!(route, RouteKind::GeminiNative) {
+        return None;
+    }
+    config.model_alias(router, model)
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone)]
-struct CpProviderTemplate {
-    provider_type: String,
-    base_url: String,
-    #[serde(default)]
-    anthropic_base_url: Option<String>,
+/// Model `router` would route a request for `model` as: its `default_model`
+/// when the request named none, then with aliases resolved.
+fn router_model<'a>(
+    config: &'a Config,
+    route: RouteKind,
+    router: &'a crate::config::Router,
+    model: &'a str,
+    model_missing: bool,
+) -> &'a str {
+    let model = router
+        .default_model
+        .as_deref()
+        .filter(|_| model_missing)
+        .unwrap_or(model);
+    model_alias(config, route, Some(router), model).unwrap_or(model)
 }
 
-fn load_cp_provider_templates() -> Vec<CpProviderTemplate> {
-    // Prefer a runtime providers.json next to the working directory.
-    if let Ok(cwd) = std::env::current_dir() {
-        let path = cwd.join("providers.json");
-        if let Ok(content) = std::fs::read_to_string(&path)
-            && let Ok(file) = serde_json::from_str::<CpProviderFile>(&content)
-        {
-            return file.provider_templates;
-        }
+/// 403 when the team's policy or the caller's key scope excludes `model`.
+fn team_model_denied(
+    route: RouteKind,
+    policy: &crate::config::TeamPolicy,
+    ctx: &TeamContext,
+    model: &str,
+) -> Option<Response<Body>> {
+    if !policy.is_model_allowed(model) {
+        tracing::warn!(
+            "Policy Failed: Model '{}' not allowed by team policy",
+            model
+        );
+        return Some(protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed by team policy",
+        ));
     }
-    // Fall back to the catalog embedded at build time.
-    serde_json::from_str::<CpProviderFile>(EMBEDDED_PROVIDERS_JSON)
-        .map(|f| f.provider_templates)
-        .unwrap_or_default()
+    if !ctx.allows_model(model) {
+        tracing::warn!(
+            "Policy Failed: Model '{}' outside the scope of the API key",
+            model
+        );
+        return Some(protocol_error_response(
+            route,
+            StatusCode::FORBIDDEN,
+            "Model not allowed for this API key",
+        ));
+    }
+    None
 }
 
-/// `GET /api/cp/provider-templates` — the default base_url / anthropic_base_url
-/// per provider type, used by the control plane to pre-fill the channel form.
-async fn handle_cp_provider_templates(
-    State(state): State<Arc<AppState>>,
-    req: Request<Body>,
-) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config = state.config.read().unwrap().clone();
-    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
-        return resp;
+/// Replace the top-level `model` field of a JSON request body. Non-JSON bodies
+/// are returned unchanged.
+fn override_request_model(bytes: Bytes, model: &str) -> Bytes {
+    match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert(
+                "model".to_string(),
+                serde_json::Value::String(model.to_string()),
+            );
+            serde_json::to_vec(&map).map(Bytes::from).unwrap_or(bytes)
+        }
+        _ => bytes,
     }
+}
 
-    let data = load_cp_provider_templates();
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(
-            json!({ "object": "list", "data": data }).to_string(),
-        ))
-        .unwrap()
+fn truncate_for_storage(input: &str, limit: usize) -> String {
+    input.chars().take(limit).collect()
 }
 
-async fn handle_cp_info(State(state): State<Arc<AppState>>, req: Request<Body>) -> Response<Body> {
-    let (parts, _body) = req.into_parts();
-    let config = state.config.read().unwrap().clone();
+/// One upstream call made while a test request is in flight.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TraceHop {
+    pub channel: String,
+    pub url: String,
+    /// Upstream status; `None` when the call failed before a response.
+    pub status: Option<u16>,
+}
+
+tokio::task_local! {
+    /// Upstream calls of the request running under `send_test_request`.
+    static ROUTE_TRACE: Arc<std::sync::Mutex<Vec<TraceHop>>>;
+}
+
+/// Result of `send_test_request` (`apex test`).
+pub struct TestOutcome {
+    pub router: String,
+    pub hops: Vec<TraceHop>,
+    pub status: StatusCode,
+    pub latency: Duration,
+    pub body: Bytes,
+}
+
+/// Send one OpenAI chat request through the routing pipeline of a gateway
+/// built from `config`, pinned to `router` (or the first router serving the
+/// model), and report every upstream call it made. Team auth is skipped:
+/// this is for operators debugging their own config.
+pub async fn send_test_request(
+    config: Config,
+    router: Option<String>,
+    body: serde_json::Value,
+) -> anyhow::Result<TestOutcome> {
+    let model = body["model"].as_str().unwrap_or_default().to_string();
+    let state = build_state(config)?;
+    refresh_secrets(&state).await;
+    let router = {
+        let config = state.config.read().unwrap();
+        match router {
+            Some(name) => {
+                let Some(router) = config.routers.iter().find(|r| r.name == name) else {
+                    anyhow::bail!("Router '{}' not found", name);
+                };
+                if state.selector.select_channel(router, &model).is_none() {
+                    anyhow::bail!("Router '{}' has no route for model '{}'", name, model);
+                }
+                name
+            }
+            None => config
+                .routers
+                .iter()
+                .find(|r| state.selector.select_channel(r, &model).is_some())
+                .map(|r| r.name.clone())
+                .ok_or_else(|| anyhow::anyhow!("No router serves model '{}'", model))?,
+        }
+    };
 
-    if let Err(resp) = enforce_global_auth(&config, &parts.headers) {
-        return resp;
-    }
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    let hops = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let start = std::time::Instant::now();
+    let (status, body) = ROUTE_TRACE
+        .scope(hops.clone(), async {
+            let response = process_request(
+                state.clone(),
+                request,
+                RouteKind::Openai,
+                Some(router.clone()),
+                None,
+            )
+            .await;
+            let status = response.status();
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map(|body| (status, body))
+        })
+        .await?;
+    let latency = start.elapsed();
+    let hops = hops.lock().unwrap().clone();
+    Ok(TestOutcome {
+        router,
+        hops,
+        status,
+        latency,
+        body,
+    })
+}
 
-    let info = json!({
-        "version": env!("CARGO_PKG_VERSION"),
-        "listen": config.global.listen,
-        "auth_required": !config.global.auth_keys.is_empty(),
-        "auth_key_count": config.global.auth_keys.len(),
-        "cors_origins": config.global.cors_allowed_origins,
-        "timeouts": {
-            "connect_ms": config.global.timeouts.connect_ms,
-            "request_ms": config.global.timeouts.request_ms,
-            "response_ms": config.global.timeouts.response_ms,
-        },
-        "retries": {
-            "max_attempts": config.global.retries.max_attempts,
-            "backoff_ms": config.global.retries.backoff_ms,
-        },
-        "channels": config.channels.len(),
-        "routers": config.routers.len(),
-        "teams": config.teams.len(),
-        "metrics_enabled": config.metrics.enabled,
-        "hot_reload": config.hot_reload.watch,
+/// Sends an upstream request; `mock` channels are answered in-process and
+/// `fault_injection` (if enabled for the channel) may disrupt the call.
+async fn execute_upstream(
+    state: &AppState,
+    channel: &crate::config::Channel,
+    mut request: reqwest::Request,
+    deadline: Option<std::time::Instant>,
+) -> reqwest::Result<reqwest::Response> {
+    if channel.provider_type == crate::config::ProviderType::Vertex
+        && channel.adapter.is_none()
+        && crate::vertex::is_service_account(&channel.api_key)
+    {
+        match state
+            .vertex_tokens
+            .access_token(&state.client, &channel.api_key)
+            .await
+        {
+            Ok(token) => {
+                if let Ok(value) = HeaderValue::from_str(&format!("Bearer {token}")) {
+                    request.headers_mut().insert("authorization", value);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Upstream Auth Failed: channel '{}' {:#}", channel.name, e);
+                return Ok(upstream_auth_failed_response(&e));
+            }
+        }
+    }
+    let faults = state.config.read().unwrap().fault_injection.clone();
+    // Only a channel-level override bounds the wait for response headers;
+    // the global `request_ms` is left unenforced for existing deployments.
+    let headers_timeout = channel
+        .timeouts
+        .as_ref()
+        .map(|timeouts| timeouts.request_ms)
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis);
+    // A request deadline bounds the whole exchange, body included, and is
+    // passed on so an upstream gateway can budget the rest.
+    let remaining =
+        deadline.map(|deadline| deadline.saturating_duration_since(std::time::Instant::now()));
+    let headers_timeout = match (headers_timeout, remaining) {
+        (Some(limit), Some(remaining)) => Some(limit.min(remaining)),
+        (limit, remaining) => limit.or(remaining),
+    };
+    if let Some(remaining) = remaining {
+        *request.timeout_mut() = Some(request.timeout().map_or(remaining, |t| (*t).min(remaining)));
+        request.headers_mut().insert(
+            REQUEST_TIMEOUT_HEADER,
+            HeaderValue::from(remaining.as_millis() as u64),
+        );
+    }
+    let _in_flight =
+        crate::metrics::GaugeGuard::new(state.metrics.upstream_requests_in_flight.clone());
+    let url = request.url().to_string();
+    let send = async {
+        let response = async {
+            if channel.provider_type == crate::config::ProviderType::Mock {
+                return Ok(crate::mock_provider::respond(channel, request).await);
+            }
+            state.client_for(channel).execute(request).await
+        };
+        let Some(limit) = headers_timeout else {
+            return response.await;
+        };
+        match tokio::time::timeout(limit, response).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!(
+                    "Upstream Timeout: channel '{}' sent no response within {}ms",
+                    channel.name,
+                    limit.as_millis()
+                );
+                Ok(upstream_timeout_response(limit))
+            }
+        }
+    };
+    let result = match faults.filter(|f| f.applies_to(&channel.name)) {
+        Some(faults) => crate::fault_injection::apply(&faults, &channel.name, send).await,
+        None => send.await,
+    };
+    let _ = ROUTE_TRACE.try_with(|hops| {
+        hops.lock().unwrap().push(TraceHop {
+            channel: channel.name.clone(),
+            url,
+            status: result.as_ref().ok().map(|resp| resp.status().as_u16()),
+        })
     });
-
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "application/json")
-        .body(Body::from(info.to_string()))
-        .unwrap()
+    result
 }
 
-// Helpers
+/// Where a hedge for `channels[index]` goes: the next channel already queued,
+/// else the matched rule's next unused target, else an unused fallback.
+fn hedge_channel<'a>(
+    config: &'a Config,
+    router: &crate::config::Router,
+    selection: Option<&crate::router_selector::RouteSelection>,
+    channels: &[&'a crate::config::Channel],
+    index: usize,
+) -> Option<&'a crate::config::Channel> {
+    if let Some(next) = channels.get(index + 1) {
+        return Some(next);
+    }
+    let rule_targets = selection
+        .and_then(|selection| selection.rule_index)
+        .and_then(|idx| router.rules.get(idx))
+        .into_iter()
+        .flat_map(|rule| rule.channels.iter().map(|target| &target.name));
+    rule_targets
+        .chain(router.fallback_channel_names())
+        .filter(|name| !channels.iter().any(|c| &c.name == *name))
+        .find_map(|name| config.channels.iter().find(|c| &c.name == name))
+}
+
+/// End-to-end budget a client may set for its request, in milliseconds.
+const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout-ms";
+
+/// The budget asked for with `x-request-timeout-ms`, capped at `max_ms`.
+/// Missing, zero or malformed values set no deadline.
+fn request_budget(headers: &HeaderMap, max_ms: Option<u64>) -> Option<Duration> {
+    let ms = headers
+        .get(REQUEST_TIMEOUT_HEADER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|ms| *ms > 0)?;
+    Some(Duration::from_millis(max_ms.map_or(ms, |max| ms.min(max))))
+}
 
-#[allow(clippy::result_large_err)]
-fn enforce_global_auth(config: &Config, headers: &HeaderMap) -> Result<(), Response<Body>> {
-    let keys = &config.global.auth_keys;
+/// `wait`, cut short at `deadline`.
+fn within_deadline(wait: Duration, deadline: Option<std::time::Instant>) -> Duration {
+    deadline.map_or(wait, |deadline| {
+        wait.min(deadline.saturating_duration_since(std::time::Instant::now()))
+    })
+}
 
-    // If no auth_keys configured, skip validation
-    if keys.is_empty() {
-        return Ok(());
+/// A request's fallback tiers, handed out one at a time as the channels
+/// before them fail.
+struct Fallbacks<'a> {
+    config: &'a Config,
+    router: &'a crate::config::Router,
+    selector: &'a RouterSelector,
+    tiers: usize,
+    next: usize,
+    model: &'a str,
+    sticky_key: Option<&'a str>,
+    request_id: Option<&'a str>,
+}
+
+impl<'a> Fallbacks<'a> {
+    fn pending(&self) -> bool {
+        self.next < self.tiers
+    }
+
+    /// Append the next tier that has channels not yet in `channels`.
+    /// Returns false once every tier is used up.
+    fn push_next(&mut self, channels: &mut Vec<&'a crate::config::Channel>) -> bool {
+        while self.pending() {
+            let tier = self.next;
+            self.next += 1;
+            let before = channels.len();
+            let names = self.selector.order_fallback_tier(
+                self.router,
+                tier,
+                self.model,
+                self.sticky_key,
+                self.request_id,
+            );
+            for name in names {
+                match self.config.channels.iter().find(|c| c.name == name) {
+                    Some(channel) if !channels.iter().any(|c| c.name == channel.name) => {
+                        tracing::info!("Channel Resolved (Fallback tier {}): {}", tier, name);
+                        channels.push(channel);
+                    }
+                    Some(_) => {}
+                    None => tracing::warn!("Fallback channel not found: {}", name),
+                }
+            }
+            if channels.len() > before {
+                return true;
+            }
+        }
+        false
     }
+}
 
-    let candidates = [
-        read_auth_token(headers, "authorization"),
-        read_auth_token(headers, "x-api-key"),
-    ];
+/// Wait for the first chunk of a streamed upstream response. `None` when the
+/// stream fails or ends before sending one; otherwise the same response with
+/// that chunk still at its front.
+async fn first_stream_chunk(mut resp: reqwest::Response) -> Option<reqwest::Response> {
+    use futures::StreamExt;
+    use reqwest::ResponseBuilderExt;
 
-    for token in candidates.into_iter().flatten() {
-        if keys.contains(&token) {
-            return Ok(());
-        }
+    let chunk = resp.chunk().await.ok().flatten()?;
+    let mut builder = axum::http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = resp.headers().clone();
     }
-
-    tracing::warn!("Auth Failed: No valid token found in Authorization or x-api-key headers.");
-    Err(error_response(StatusCode::UNAUTHORIZED, "unauthorized"))
+    let rest = resp.bytes_stream();
+    let body = reqwest::Body::wrap_stream(
+        futures::stream::once(async move { Ok::<_, reqwest::Error>(chunk) }).chain(rest),
+    );
+    builder.body(body).ok().map(reqwest::Response::from)
 }
 
-fn read_auth_token(headers: &HeaderMap, key: &str) -> Option<String> {
-    if let Some(val) = headers.get(key)
-        && let Ok(s) = val.to_str()
+/// A duplicate of the current request, ready to send to the next channel.
+struct Hedge<'a> {
+    /// Pool key the duplicate was prepared with.
+    api_key: String,
+    request: reqwest::Request,
+    adapter: &'a dyn crate::providers::ProviderAdapter,
+    responses_compat: bool,
+    permit: crate::channel_limits::ChannelPermit,
+}
+
+/// Prepare a hedge on `channel`, or `None` when it can't take one right now
+/// (provider rate limit, no free concurrency slot) or needs per-team request
+/// state that a duplicate can't share (Gemini native, Gemini replay).
+#[allow(clippy::too_many_arguments)]
+fn build_hedge<'a>(
+    state: &'a AppState,
+    channel: &'a crate::config::Channel,
+    route: RouteKind,
+    method: &axum::http::Method,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    bytes: &Bytes,
+) -> Option<Hedge<'a>> {
+    if channel.provider_type == crate::config::ProviderType::Gemini
+        && matches!(route, RouteKind::GeminiNative | RouteKind::Anthropic)
     {
-        if key == "authorization" && s.starts_with("Bearer ") {
-            return Some(s[7..].to_string());
-        }
-        return Some(s.to_string());
-    }
-    None
+        return None;
+    }
+    let responses_compat = matches!(route, RouteKind::Openai)
+        && crate::responses_api::is_responses_path(path)
+        && !crate::responses_api::channel_supports_responses(channel);
+    let (body, upstream_path) = if responses_compat {
+        (
+            crate::responses_api::convert_responses_to_chat(bytes).ok()?,
+            crate::responses_api::CHAT_COMPLETIONS_PATH,
+        )
+    } else {
+        (bytes.clone(), path)
+    };
+    let permit = state.channel_limits.try_acquire(&channel.name)?;
+    let keyed = state.key_pools.keyed(channel);
+    let prepared = prepare_request(
+        &state.providers,
+        &keyed,
+        route,
+        &channel.base_url,
+        upstream_path,
+        query,
+        headers,
+        &body,
+    )
+    .ok()?;
+    let request = state
+        .client
+        .request(method.clone(), prepared.url)
+        .headers(prepared.headers)
+        .body(prepared.body)
+        .build()
+        .ok()?;
+    Some(Hedge {
+        api_key: keyed.api_key.clone(),
+        request,
+        adapter: state.providers.adapter_for(channel, route),
+        responses_compat,
+        permit,
+    })
 }
 
-use crate::utils::mask_secret;
-
-pub(crate) fn error_response(status: StatusCode, message: &str) -> Response<Body> {
-    let body = json!({
-        "error": {
-            "message": message,
-            "type": "invalid_request_error",
-            "param": null,
-            "code": null
+/// Shadow a sampled share of a router's requests to its `mirror_channel` in
+/// the background. The client never waits for or sees the mirrored
+/// response; its usage is logged under `matched_rule = "mirror"` without a
+/// request id, so it settles no TPM reservation.
+#[allow(clippy::too_many_arguments)]
+fn spawn_mirror(
+    state: &Arc<AppState>,
+    config: &Config,
+    router: &crate::config::Router,
+    route: RouteKind,
+    method: &axum::http::Method,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    bytes: &Bytes,
+    team_id: &str,
+    model: &str,
+    client_info: &crate::utils::ClientInfo,
+) {
+    let Some(name) = router.mirror_channel.as_deref() else {
+        return;
+    };
+    let percent = router.mirror_percent.unwrap_or(100.0);
+    if percent <= 0.0 || (percent < 100.0 && rand::random::<f64>() * 100.0 >= percent) {
+        return;
+    }
+    let Some(channel) = config.channels.iter().find(|c| c.name == name).cloned() else {
+        return;
+    };
+    let state = state.clone();
+    let router_name = router.name.clone();
+    let method = method.clone();
+    let (path, query) = (path.to_string(), query.map(str::to_string));
+    let (headers, bytes) = (headers.clone(), bytes.clone());
+    let (team_id, model, client_info) =
+        (team_id.to_string(), model.to_string(), client_info.clone());
+    let global = config.global.clone();
+    tokio::spawn(async move {
+        let count = |result: &str| {
+            state
+                .metrics
+                .mirror_total
+                .with_label_values(&[&router_name, &channel.name, result])
+                .inc();
+        };
+        if provider_rate_limited(&state, &channel).await
+            || channel_request_limit(&channel, &headers).is_some_and(|limit| bytes.len() > limit)
+        {
+            count("skipped");
+            return;
         }
+        let Some(mirror) = build_hedge(
+            &state,
+            &channel,
+            route,
+            &method,
+            &path,
+            query.as_deref(),
+            &headers,
+            &bytes,
+        ) else {
+            count("skipped");
+            return;
+        };
+        let start = std::time::Instant::now();
+        let result = execute_upstream(&state, &channel, mirror.request, None).await;
+        report_pool_key(&state, &channel, &mirror.api_key, &result);
+        let latency_ms = Some(start.elapsed().as_millis() as f64);
+        match result {
+            Ok(resp) if resp.status().is_success() => {
+                let resp = match response_body_limit(&global, None, &channel, resp.headers()) {
+                    Some(limit) => match limit_response_size(resp, limit).await {
+                        Ok(resp) => resp,
+                        Err(_) => {
+                            count("error");
+                            return;
+                        }
+                    },
+                    None => resp,
+                };
+                count("success");
+                let mut response = Response::builder().status(resp.status());
+                if let Some(content_type) = resp.headers().get("content-type") {
+                    response = response.header("content-type", content_type);
+                }
+                let Ok(response) = response.body(Body::from_stream(resp.bytes_stream())) else {
+                    return;
+                };
+                let response = crate::usage::wrap_response(
+                    response,
+                    None,
+                    team_id,
+                    router_name,
+                    Some("mirror".to_string()),
+                    channel.name.clone(),
+                    model,
+                    state.usage_logger.clone(),
+                    state.metrics.clone(),
+                    latency_ms,
+                    false,
+                    client_info,
+                )
+                .await;
+                // Drain the body so streamed usage is tallied.
+                let _ = axum::body::to_bytes(response.into_body(), usize::MAX).await;
+            }
+            result => {
+                count("error");
+                let (status, message) = match result {
+                    Ok(resp) => (
+                        resp.status().as_u16() as i64,
+                        "mirror upstream error".to_string(),
+                    ),
+                    Err(e) => (StatusCode::BAD_GATEWAY.as_u16() as i64, e.to_string()),
+                };
+                tracing::warn!(
+                    "Mirror Failed: channel '{}' status={} {}",
+                    channel.name,
+                    status,
+                    message
+                );
+                state.usage_logger.log_failure(
+                    None,
+                    &team_id,
+                    &router_name,
+                    Some("mirror"),
+                    &channel.name,
+                    &model,
+                    latency_ms,
+                    false,
+                    status,
+                    &message,
+                    None,
+                    None,
+                    &client_info,
+                );
+            }
+        }
+        drop(mirror.permit);
     });
-    Response::builder()
-        .status(status)
-        .header("content-type", "application/json")
-        .body(Body::from(body.to_string()))
-        .unwrap()
 }
 
-struct GeminiNativeRoute {
-    routing_model: String,
-    direct_pass: bool,
+/// Whether `channel`'s provider is out of budget, by the provider rate
+/// limiter or by `global.provider_rate_limits` (whose buckets live in the
+/// shared store when one is configured).
+async fn provider_rate_limited(state: &AppState, channel: &crate::config::Channel) -> bool {
+    if !state.rate_limiter.check(&channel.provider_type) {
+        return true;
+    }
+    let rpm = state
+        .config
+        .read()
+        .unwrap()
+        .global
+        .provider_rate_limits
+        .get(&channel.provider_type)
+        .map(|limit| limit.rpm);
+    match rpm {
+        Some(rpm) if rpm > 0 => state
+            .team_rate_limiter
+            .check_requests(&format!("provider:{:?}", channel.provider_type), rpm)
+            .await
+            .is_err(),
+        _ => false,
+    }
 }
 
-fn validate_gemini_native_route(method: &Method, path: &str) -> Option<GeminiNativeRoute> {
-    let path = path.trim_start_matches('/');
-    let segments = path.split('/').collect::<Vec<_>>();
+enum HedgeOutcome {
+    /// The primary's result.
+    Primary(reqwest::Result<reqwest::Response>),
+    /// The hedge answered successfully first; the primary was cancelled.
+    Hedge(reqwest::Response),
+}
 
-    match (method, segments.as_slice()) {
-        (&Method::GET, ["v1beta", "models"]) => Some(gemini_native_resource_route()),
-        (&Method::GET, ["v1beta", "models", model]) if !model.contains(':') => {
-            Some(gemini_native_model_route(model))
-        }
-        (&Method::POST, ["v1beta", "models", model_action])
-            if model_action.ends_with(":generateContent")
-                || model_action.ends_with(":streamGenerateContent") =>
-        {
-            model_action
-                .split_once(':')
-                .filter(|(model, _)| !model.is_empty())
-                .map(|(model, _)| gemini_native_model_route(model))
-        }
-        (&Method::GET, ["v1beta", "fileSearchStores"])
-        | (&Method::POST, ["v1beta", "fileSearchStores"]) => Some(gemini_native_resource_route()),
-        (&Method::GET, ["v1beta", "fileSearchStores", store])
-        | (&Method::DELETE, ["v1beta", "fileSearchStores", store])
-            if !store.contains(':') =>
-        {
-            Some(gemini_native_resource_route())
-        }
-        (&Method::POST, ["v1beta", "fileSearchStores", store_action])
-        | (&Method::POST, ["upload", "v1beta", "fileSearchStores", store_action])
-            if store_action.ends_with(":uploadToFileSearchStore") =>
-        {
-            Some(GeminiNativeRoute {
-                routing_model: "gemini-native".to_string(),
-                direct_pass: true,
-            })
-        }
-        (&Method::GET, ["v1beta", "fileSearchStores", store, "operations", operation])
-        | (
-            &Method::GET,
-            [
-                "v1beta",
-                "fileSearchStores",
-                store,
-                "upload",
-                "operations",
-                operation,
-            ],
-        ) if !store.is_empty() && !operation.is_empty() => Some(gemini_native_resource_route()),
-        (&Method::POST, ["v1beta", "interactions"]) => Some(gemini_native_direct_pass_route()),
-        (&Method::GET, ["v1beta", "interactions", interaction]) if !interaction.is_empty() => {
-            Some(gemini_native_direct_pass_route())
+/// Run `primary`; if it hasn't finished after `after`, start `hedge` as well
+/// (which yields `None` when no hedge could be sent). The first successful
+/// (2xx) response wins and the other request is dropped. When neither
+/// succeeds the primary's result is kept, so retries and fallbacks proceed
+/// as without hedging.
+async fn race_hedge<P, H>(primary: P, after: Duration, hedge: H) -> HedgeOutcome
+where
+    P: std::future::Future<Output = reqwest::Result<reqwest::Response>>,
+    H: std::future::Future<Output = Option<reqwest::Result<reqwest::Response>>>,
+{
+    let succeeded = |result: &reqwest::Result<reqwest::Response>| {
+        result.as_ref().is_ok_and(|resp| resp.status().is_success())
+    };
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => return HedgeOutcome::Primary(result),
+        _ = tokio::time::sleep(after) => {}
+    }
+    tokio::pin!(hedge);
+    tokio::select! {
+        result = &mut primary => {
+            if succeeded(&result) {
+                return HedgeOutcome::Primary(result);
+            }
+            match hedge.await {
+                Some(Ok(resp)) if resp.status().is_success() => HedgeOutcome::Hedge(resp),
+                _ => HedgeOutcome::Primary(result),
+            }
         }
-        _ => None,
+        result = &mut hedge => match result {
+            Some(Ok(resp)) if resp.status().is_success() => HedgeOutcome::Hedge(resp),
+            _ => HedgeOutcome::Primary(primary.await),
+        },
     }
 }
 
-fn gemini_native_resource_route() -> GeminiNativeRoute {
-    GeminiNativeRoute {
-        routing_model: "gemini-native".to_string(),
-        direct_pass: false,
+/// Let the channel's key pool quarantine `key` on a 401/403/429.
+fn report_pool_key(
+    state: &AppState,
+    channel: &crate::config::Channel,
+    key: &str,
+    result: &reqwest::Result<reqwest::Response>,
+) {
+    if let Ok(resp) = result {
+        state.key_pools.report(
+            channel,
+            key,
+            resp.status().as_u16(),
+            retry_after(resp.headers()),
+        );
     }
 }
 
-fn gemini_native_direct_pass_route() -> GeminiNativeRoute {
-    GeminiNativeRoute {
-        routing_model: "gemini-native".to_string(),
-        direct_pass: true,
-    }
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(axum::http::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after)
 }
 
-fn gemini_native_model_route(model: &str) -> GeminiNativeRoute {
-    GeminiNativeRoute {
-        routing_model: model.to_string(),
-        direct_pass: false,
+/// `Retry-After` as delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
     }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
 }
 
-fn gemini_native_resource_router_is_deterministic(
-    router: &crate::config::Router,
-    model: &str,
-) -> bool {
-    router.rules.iter().any(|rule| {
-        crate::config::TeamPolicy {
-            allowed_routers: Vec::new(),
-            allowed_models: Some(rule.match_spec.models.clone()),
-            rate_limit: None,
-        }
-        .is_model_allowed(model)
-            && rule.strategy == "priority"
-            && rule.channels.len() == 1
-    })
-}
-
-fn gemini_native_error_response(
-    status: StatusCode,
-    message: &str,
-    gemini_status: &str,
-) -> Response<Body> {
+/// Stand-in for an upstream that missed the channel's `request_ms`: a 504 the
+/// retry, fallback and failure-logging paths treat like any upstream error.
+fn upstream_timeout_response(limit: Duration) -> reqwest::Response {
     let body = json!({
         "error": {
-            "code": status.as_u16(),
-            "message": message,
-            "status": gemini_status,
+            "message": format!("upstream did not respond within {}ms", limit.as_millis()),
+            "type": "upstream_timeout",
         }
     });
-    Response::builder()
-        .status(status)
-        .header("content-type", "application/json")
-        .body(Body::from(body.to_string()))
-        .unwrap()
-}
-
-fn protocol_error_response(route: RouteKind, status: StatusCode, message: &str) -> Response<Body> {
-    if matches!(route, RouteKind::GeminiNative) {
-        let gemini_status = match status {
-            StatusCode::NOT_FOUND => "NOT_FOUND",
-            StatusCode::FORBIDDEN => "PERMISSION_DENIED",
-            StatusCode::UNAUTHORIZED => "UNAUTHENTICATED",
-            StatusCode::TOO_MANY_REQUESTS => "RESOURCE_EXHAUSTED",
-            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => "UNAVAILABLE",
-            _ => "INVALID_ARGUMENT",
-        };
-        gemini_native_error_response(status, message, gemini_status)
-    } else if matches!(route, RouteKind::Anthropic) {
-        let body = json!({
-            "type": "error",
-            "error": {
-                "type": "invalid_request_error",
-                "message": message,
-            }
-        });
-        Response::builder()
-            .status(status)
-            .header("content-type", "application/json")
-            .body(Body::from(body.to_string()))
-            .unwrap()
-    } else {
-        error_response(status, message)
-    }
+    let response = axum::http::Response::builder()
+        .status(StatusCode::GATEWAY_TIMEOUT)
+        .header("content-type", "application/json")
+        .body(reqwest::Body::from(body.to_string()))
+        .unwrap();
+    reqwest::Response::from(response)
 }
 
-fn format_error_chain(error: &dyn std::error::Error) -> String {
-    let mut parts = vec![error.to_string()];
-    let mut current = error.source();
-    while let Some(source) = current {
-        parts.push(source.to_string());
-        current = source.source();
-    }
-    parts.join(" | caused by: ")
+/// Response for a request whose upstream credentials could not be obtained.
+fn upstream_auth_failed_response(error: &anyhow::Error) -> reqwest::Response {
+    let body = json!({
+        "error": {
+            "message": format!("failed to obtain upstream credentials: {error:#}"),
+            "type": "upstream_auth_error",
+        }
+    });
+    let response = axum::http::Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header("content-type", "application/json")
+        .body(reqwest::Body::from(body.to_string()))
+        .unwrap();
+    reqwest::Response::from(response)
 }
 
-fn anthropic_request_contains_tool_result(body: &Bytes) -> bool {
-    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
-        return false;
+/// Embed the last user message of `body` through the router's semantic cache
+/// channel. Failures only cost the semantic lookup, so they are logged and
+/// swallowed.
+async fn semantic_cache_key(
+    state: &AppState,
+    settings: &crate::config::SemanticCacheConfig,
+    team_id: &str,
+    route: RouteKind,
+    body: &Bytes,
+) -> Option<crate::response_cache::SemanticKey> {
+    let (text, context) = crate::response_cache::semantic_parts(team_id, route, body)?;
+    let channel = state
+        .config
+        .read()
+        .unwrap()
+        .channels
+        .iter()
+        .find(|c| c.name == settings.channel)
+        .cloned();
+    let Some(channel) = channel else {
+        tracing::warn!("Semantic Cache: unknown channel {}", settings.channel);
+        return None;
     };
+    match embed_text(state, &channel, settings, &text).await {
+        Ok(embedding) => Some(crate::response_cache::SemanticKey { context, embedding }),
+        Err(e) => {
+            tracing::warn!("Semantic Cache: embedding failed: {}", e);
+            None
+        }
+    }
+}
 
+async fn embed_text(
+    state: &AppState,
+    channel: &crate::config::Channel,
+    settings: &crate::config::SemanticCacheConfig,
+    text: &str,
+) -> anyhow::Result<Vec<f32>> {
+    let body =
+        Bytes::from(serde_json::json!({ "model": settings.model, "input": text }).to_string());
+    let prepared = prepare_request(
+        &state.providers,
+        &state.key_pools.keyed(channel),
+        RouteKind::Openai,
+        &channel.base_url,
+        "/v1/embeddings",
+        None,
+        &HeaderMap::new(),
+        &body,
+    )?;
+    let request = state
+        .client
+        .post(prepared.url)
+        .headers(prepared.headers)
+        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .body(prepared.body)
+        .timeout(Duration::from_millis(settings.timeout_ms))
+        .build()?;
+    let resp = execute_upstream(state, channel, request, None).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("HTTP {}", resp.status().as_u16());
+    }
+    let value: serde_json::Value = resp.json().await?;
     value
-        .get("messages")
-        .and_then(serde_json::Value::as_array)
-        .map(|messages| {
-            messages.iter().any(|message| {
-                message
-                    .get("content")
-                    .and_then(serde_json::Value::as_array)
-                    .map(|parts| {
-                        parts.iter().any(|part| {
-                            part.get("type").and_then(serde_json::Value::as_str)
-                                == Some("tool_result")
-                        })
-                    })
-                    .unwrap_or(false)
-            })
+        .pointer("/data/0/embedding")
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_f64().map(|f| f as f32))
+                .collect()
         })
-        .unwrap_or(false)
+        .ok_or_else(|| anyhow::anyhow!("response has no data[0].embedding"))
 }
 
-fn summarize_anthropic_request(body: &Bytes) -> String {
-    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
-        return "invalid_json".to_string();
-    };
-    let Some(messages) = value.get("messages").and_then(serde_json::Value::as_array) else {
-        return "messages=missing".to_string();
-    };
+/// `GET <base_url>/<health.path>` with the channel's credentials; any 2xx
+/// within `timeout_ms` counts as healthy.
+pub(crate) async fn probe_channel(
+    state: &AppState,
+    channel: &crate::config::Channel,
+    settings: &crate::config::HealthCheckConfig,
+) -> Result<(), String> {
+    let prepared = prepare_request(
+        &state.providers,
+        &state.key_pools.keyed(channel),
+        RouteKind::Openai,
+        &channel.base_url,
+        &settings.path,
+        None,
+        &HeaderMap::new(),
+        &Bytes::new(),
+    )
+    .map_err(|e| e.to_string())?;
+    let request = state
+        .client
+        .get(prepared.url)
+        .headers(prepared.headers)
+        .timeout(Duration::from_millis(settings.timeout_ms))
+        .build()
+        .map_err(|e| e.to_string())?;
+    match execute_upstream(state, channel, request, None).await {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!("HTTP {}", resp.status().as_u16())),
+        Err(e) => Err(e.to_string()),
+    }
+}
 
-    let mut referenced_tool_use_ids = Vec::new();
-    let mut segments = Vec::new();
-    for (index, message) in messages.iter().enumerate() {
-        let role = message
-            .get("role")
-            .and_then(serde_json::Value::as_str)
-            .unwrap_or("unknown");
-        let mut kinds = Vec::new();
-        if let Some(content) = message.get("content").and_then(serde_json::Value::as_array) {
-            for block in content {
-                let kind = block
-                    .get("type")
-                    .and_then(serde_json::Value::as_str)
-                    .unwrap_or("unknown");
-                kinds.push(kind.to_string());
-                if kind == "tool_result"
-                    && let Some(tool_use_id) =
-                        block.get("tool_use_id").and_then(serde_json::Value::as_str)
-                {
-                    referenced_tool_use_ids.push(tool_use_id.to_string());
+/// Buffer a non-streaming upstream body, giving up once it exceeds `limit`
+/// bytes (`Err` carries the size seen so far). Streams pass through untouched.
+async fn limit_response_size(
+    resp: reqwest::Response,
+    limit: usize,
+) -> Result<reqwest::Response, usize> {
+    use reqwest::ResponseBuilderExt;
+
+    let is_stream = resp
+        .headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_stream {
+        return Ok(resp);
+    }
+    if let Some(length) = resp.content_length().filter(|&len| len as usize > limit) {
+        return Err(length as usize);
+    }
+
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let url = resp.url().clone();
+    let mut resp = resp;
+    let mut buffer = Vec::new();
+    loop {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => {
+                buffer.extend_from_slice(&chunk);
+                if buffer.len() > limit {
+                    return Err(buffer.len());
                 }
             }
+            Ok(None) => break,
+            // Let the adapter surface transport errors the usual way.
+            Err(_) => break,
         }
-        segments.push(format!("{index}:{role}[{}]", kinds.join(",")));
     }
 
-    format!(
-        "messages={} {} tool_result_ids={:?}",
-        messages.len(),
-        segments.join(" "),
-        referenced_tool_use_ids
-    )
+    let mut builder = axum::http::Response::builder().status(status).url(url);
+    for (name, value) in headers.iter() {
+        builder = builder.header(name, value);
+    }
+    Ok(reqwest::Response::from(
+        builder.body(reqwest::Body::from(buffer)).unwrap(),
+    ))
 }
 
-fn request_id_from_parts(parts: &axum::http::request::Parts) -> Option<String> {
-    parts
-        .extensions
-        .get::<tower_http::request_id::RequestId>()
-        .and_then(|id| id.header_value().to_str().ok())
-        .map(|id| id.to_string())
-}
+/// Who a routed request is accounted to once its router is known: the
+/// metrics, stats, access-audit and usage records every attempt writes.
+struct RequestLog<'a> {
+    state: &'a AppState,
+    route: RouteKind,
+    request_id: Option<&'a str>,
+    team_id: &'a str,
+    router_name: &'a str,
+    caller_key: Option<&'a str>,
+    client_info: &'a crate::utils::ClientInfo,
+}
+
+impl RequestLog<'_> {
+    /// Count the request against its router.
+    fn received(&self) {
+        self.state
+            .metrics
+            .request_total
+            .with_label_values(&[self.route.as_str(), self.router_name])
+            .inc();
+        self.state
+            .database
+            .log_request(self.route.as_str(), self.router_name);
+    }
+
+    /// Count the request as failed.
+    fn error(&self) {
+        self.state
+            .metrics
+            .error_total
+            .with_label_values(&[self.route.as_str(), self.router_name])
+            .inc();
+        self.state
+            .database
+            .log_error(self.route.as_str(), self.router_name);
+    }
+
+    /// Record how `channel` did, for health tracking and the access audit.
+    fn audit(&self, channel: &crate::config::Channel, success: bool) {
+        self.state.channel_health.record(&channel.name, success);
+        self.state.access_audit.record(&AuditEvent {
+            request_id: self.request_id,
+            team_id: self.team_id,
+            api_key: self.caller_key,
+            provider: &channel.provider_type,
+            channel: &channel.name,
+            route: self.route,
+            success,
+        });
+    }
 
-fn provider_trace_id_from_headers(headers: &HeaderMap) -> Option<String> {
-    const TRACE_HEADERS: [&str; 5] = [
-        "x-request-id",
-        "request-id",
-        "x-trace-id",
-        "trace-id",
-        "cf-ray",
-    ];
+    /// Time until `channel` answered.
+    fn latency(&self, channel: &crate::config::Channel, elapsed: f64) {
+        self.state
+            .metrics
+            .upstream_latency_ms
+            .with_label_values(&[self.route.as_str(), self.router_name, &channel.name])
+            .observe(elapsed);
+        self.state.database.log_latency(
+            self.route.as_str(),
+            self.router_name,
+            &channel.name,
+            elapsed,
+        );
+    }
 
-    TRACE_HEADERS.iter().find_map(|name| {
-        headers
-            .get(*name)
-            .and_then(|value| value.to_str().ok())
-            .map(|value| value.to_string())
-    })
-}
+    /// Usage record of a request that failed on `channel`.
+    #[allow(clippy::too_many_arguments)]
+    fn failure(
+        &self,
+        matched_rule: Option<&str>,
+        channel: &str,
+        model: &str,
+        latency_ms: Option<f64>,
+        fallback_triggered: bool,
+        status: StatusCode,
+        message: &str,
+        provider_trace_id: Option<&str>,
+        error_body: Option<&str>,
+    ) {
+        self.state.usage_logger.log_failure(
+            self.request_id,
+            self.team_id,
+            self.router_name,
+            matched_rule,
+            channel,
+            model,
+            latency_ms,
+            fallback_triggered,
+            status.as_u16() as i64,
+            message,
+            provider_trace_id,
+            error_body,
+            self.client_info,
+        );
+    }
 
-fn response_from_upstream_bytes(
-    status: StatusCode,
-    headers: &HeaderMap,
-    body: Bytes,
-) -> Response<Body> {
-    let mut builder = HttpResponse::builder().status(status);
-    for (name, value) in headers {
-        if crate::providers::should_forward_response_header(name) {
-            builder = builder.header(name, value);
+    /// Read the body of a failed upstream response and record the failure.
+    /// Returns what the client gets: the status, headers and body.
+    async fn upstream_error(
+        &self,
+        resp: reqwest::Response,
+        matched_rule: Option<&str>,
+        channel: &crate::config::Channel,
+        model: &str,
+        elapsed: f64,
+        fallback_triggered: bool,
+    ) -> (StatusCode, HeaderMap, Bytes) {
+        self.error();
+        let status = resp.status();
+        let provider_trace_id = provider_trace_id_from_headers(resp.headers());
+        let response_headers = resp.headers().clone();
+        let error_body_bytes = resp.bytes().await.unwrap_or_default();
+        let stored_error_body =
+            truncate_for_storage(&String::from_utf8_lossy(&error_body_bytes), 4000);
+        if !stored_error_body.is_empty() {
+            tracing::warn!("Upstream Error Body: {}", stored_error_body);
         }
+        self.failure(
+            matched_rule,
+            &channel.name,
+            model,
+            Some(elapsed),
+            fallback_triggered,
+            status,
+            status
+                .canonical_reason()
+                .unwrap_or("upstream request failed"),
+            provider_trace_id.as_deref(),
+            Some(stored_error_body.as_str()),
+        );
+        (status, response_headers, error_body_bytes)
+    }
+
+    /// Hold a successful response to the router's, channel's or global
+    /// response size limit; the 502 the client gets when it is larger.
+    #[allow(clippy::too_many_arguments)]
+    async fn limit_response(
+        &self,
+        global: &crate::config::Global,
+        router: &crate::config::Router,
+        channel: &crate::config::Channel,
+        resp: reqwest::Response,
+        matched_rule: Option<&str>,
+        model: &str,
+        elapsed: f64,
+        fallback_triggered: bool,
+    ) -> Result<reqwest::Response, Response<Body>> {
+        let Some(limit) = response_body_limit(global, Some(router), channel, resp.headers()) else {
+            return Ok(resp);
+        };
+        limit_response_size(resp, limit).await.map_err(|size| {
+            let message = format!("Upstream response exceeds {} bytes", limit);
+            tracing::warn!(
+                "Response Rejected: channel '{}' returned at least {} bytes (limit {})",
+                channel.name,
+                size,
+                limit
+            );
+            self.state
+                .metrics
+                .size_limit_exceeded_total
+                .with_label_values(&[self.router_name, "response"])
+                .inc();
+            self.error();
+            self.failure(
+                matched_rule,
+                &channel.name,
+                model,
+                Some(elapsed),
+                fallback_triggered,
+                StatusCode::BAD_GATEWAY,
+                &message,
+                None,
+                None,
+            );
+            protocol_error_response(self.route, StatusCode::BAD_GATEWAY, &message)
+        })
     }
 
-    builder
-        .body(Body::from(body))
-        .unwrap_or_else(|_| error_response(StatusCode::BAD_GATEWAY, "invalid upstream response"))
+    /// Time to the first chunk of a streamed response, and usage accounting
+    /// as the response reaches the client.
+    #[allow(clippy::too_many_arguments)]
+    async fn deliver(
+        &self,
+        response: Response<Body>,
+        matched_rule: Option<&str>,
+        channel: &crate::config::Channel,
+        model: &str,
+        start: std::time::Instant,
+        elapsed: f64,
+        fallback_triggered: bool,
+    ) -> Response<Body> {
+        let response = if crate::utils::is_event_stream(response.headers()) {
+            crate::metrics::observe_first_chunk(
+                response,
+                self.state
+                    .metrics
+                    .stream_ttfb_ms
+                    .with_label_values(&[self.router_name, &channel.name]),
+                start,
+            )
+        } else {
+            response
+        };
+        crate::usage::wrap_response(
+            response,
+            self.request_id.map(str::to_string),
+            self.team_id.to_string(),
+            self.router_name.to_string(),
+            matched_rule.map(str::to_string),
+            channel.name.clone(),
+            model.to_string(),
+            self.state.usage_logger.clone(),
+            self.state.metrics.clone(),
+            Some(elapsed),
+            fallback_triggered,
+            self.client_info.clone(),
+        )
+        .await
+    }
 }
 
-fn truncate_for_storage(input: &str, limit: usize) -> String {
-    input.chars().take(limit).collect()
+/// Why a channel can't take a request now.
+enum ChannelUnavailable {
+    RateLimited,
+    TooLarge,
+    Saturated,
+}
+
+/// Admit a request with a `body_len` byte body onto `channel`: the provider's
+/// rate limits, the channel's body limit and its concurrency limit, in that
+/// order.
+async fn admit_channel(
+    state: &AppState,
+    router_name: &str,
+    channel: &crate::config::Channel,
+    headers: &HeaderMap,
+    body_len: u64,
+) -> Result<crate::channel_limits::ChannelPermit, ChannelUnavailable> {
+    if provider_rate_limited(state, channel).await {
+        tracing::warn!("Rate Limit Exceeded: Provider {:?}", channel.provider_type);
+        return Err(ChannelUnavailable::RateLimited);
+    }
+    if let Some(limit) = channel_request_limit(channel, headers)
+        && body_len > limit as u64
+    {
+        tracing::warn!(
+            "Channel Skipped: body of {} bytes exceeds channel '{}' limit of {}",
+            body_len,
+            channel.name,
+            limit
+        );
+        state
+            .metrics
+            .size_limit_exceeded_total
+            .with_label_values(&[router_name, "request"])
+            .inc();
+        return Err(ChannelUnavailable::TooLarge);
+    }
+    let depth = state
+        .metrics
+        .channel_queue_depth
+        .with_label_values(&[&channel.name]);
+    let Some(permit) = state.channel_limits.acquire(&channel.name, depth).await else {
+        tracing::warn!(
+            "Channel Saturated: '{}' is at max_concurrent_requests, skipping",
+            channel.name
+        );
+        state
+            .metrics
+            .channel_saturated_total
+            .with_label_values(&[router_name, &channel.name])
+            .inc();
+        return Err(ChannelUnavailable::Saturated);
+    };
+    Ok(permit)
 }
 
 async fn process_request(
@@ -3385,20 +7184,68 @@ async fn process_request(
     router_name_override: Option<String>,
     path_override: Option<String>,
 ) -> Response<Body> {
+    let start = std::time::Instant::now();
+    let pass = plan_streaming_pass(
+        &state,
+        &state.config.read().unwrap(),
+        &req,
+        route,
+        router_name_override.as_deref(),
+        path_override.as_deref(),
+    );
+    if let Some(pass) = pass {
+        return process_streaming_pass(&state, req, route, pass).await;
+    }
+    let (mut response, assignment) = crate::experiments::scope(route_request(
+        state.clone(),
+        req,
+        route,
+        router_name_override,
+        path_override,
+    ))
+    .await;
+    if let Some(assignment) = assignment {
+        let labels = [assignment.router.as_str(), assignment.variant.as_str()];
+        let status_class = format!("{}xx", response.status().as_u16() / 100);
+        state
+            .metrics
+            .experiment_requests_total
+            .with_label_values(&[labels[0], labels[1], &status_class])
+            .inc();
+        state
+            .metrics
+            .experiment_latency_ms
+            .with_label_values(&labels)
+            .observe(start.elapsed().as_secs_f64() * 1000.0);
+        if let Ok(value) = HeaderValue::from_str(&assignment.variant) {
+            response
+                .headers_mut()
+                .insert(crate::experiments::VARIANT_HEADER, value);
+        }
+    }
+    response
+}
+
+async fn route_request(
+    state: Arc<AppState>,
+    req: Request<Body>,
+    route: RouteKind,
+    router_name_override: Option<String>,
+    path_override: Option<String>,
+) -> Response<Body> {
+    let arrived = std::time::Instant::now();
     let (parts, body) = req.into_parts();
-    let client_info = crate::utils::classify_client(&parts.headers);
+    let mut client_info = crate::utils::classify_client(&parts.headers);
 
     // 1. Read Body
-    let bytes = match axum::body::to_bytes(body, MAX_REQUEST_BODY_BYTES).await {
+    let body_limit = request_body_limit(&state.config.read().unwrap().global, &parts.headers);
+    let mut bytes = match axum::body::to_bytes(body, body_limit).await {
         Ok(b) => b,
-        Err(e) => {
-            tracing::error!("Request Failed: Failed to read body: {}", e);
-            return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string());
-        }
+        Err(e) => return body_read_error(route, body_limit, e),
     };
 
     // 2. Parse Model
-    let model_name = parts
+    let mut model_name = parts
         .extensions
         .get::<OriginalModelName>()
         .map(|model| model.0.clone())
@@ -3410,7 +7257,54 @@ async fn process_request(
                         .and_then(|value| value.as_str())
                         .map(|value| value.to_string())
                 })
-        });
+        })
+        .or_else(|| crate::images::multipart_field(&bytes, "model"));
+    let config = state.config.read().unwrap().clone();
+    // Retries and fallbacks all spend from the one budget.
+    let deadline = request_budget(&parts.headers, config.global.max_request_timeout_ms)
+        .map(|budget| arrived + budget);
+
+    // Per-request routing overrides (debugging aid, gated by policy / admin key)
+    let overrides = RoutingOverrides::from_headers(&parts.headers);
+    if !overrides.is_empty() {
+        let permitted = match parts.extensions.get::<TeamContext>() {
+            Some(ctx) => config
+                .teams
+                .iter()
+                .find(|t| t.id == ctx.team_id)
+                .is_some_and(|t| t.policy.allow_routing_overrides),
+            None => {
+                !config.global.auth_keys.is_empty()
+                    && enforce_global_auth(&config, &parts.headers).is_ok()
+            }
+        };
+        if !permitted {
+            tracing::warn!("Policy Failed: routing override headers not permitted for caller");
+            return protocol_error_response(
+                route,
+                StatusCode::FORBIDDEN,
+                "Routing override headers are not permitted for this caller",
+            );
+        }
+        if let Some(model) = overrides.model.as_deref() {
+            if matches!(route, RouteKind::GeminiNative) {
+                return protocol_error_response(
+                    route,
+                    StatusCode::BAD_REQUEST,
+                    "x-apex-model-override is not supported on Gemini native routes",
+                );
+            }
+            tracing::info!(
+                "Routing Override: model '{}' -> '{}'",
+                model_name.as_deref().unwrap_or("default"),
+                model
+            );
+            bytes = override_request_model(bytes, model);
+            model_name = Some(model.to_string());
+        }
+    }
+    client_info.end_user = crate::utils::extract_end_user(&bytes);
+    let model_missing = model_name.is_none();
     let model_name_str = model_name.as_deref().unwrap_or("default");
 
     // 3. Log Request with Context
@@ -3440,18 +7334,70 @@ async fn process_request(
 
     let request_id = request_id_from_parts(&parts);
     let headers = parts.headers;
+    let path = path_override.unwrap_or_else(|| parts.uri.path().to_string());
 
-    // Extract team_id for usage logging
+    // Extract team_id for usage logging; virtual-key callers are recorded
+    // as `vkey:<router>`.
+    let vkey_router = parts
+        .extensions
+        .get::<RouterKeyContext>()
+        .map(|ctx| ctx.router.clone());
     let team_id = parts
         .extensions
         .get::<crate::middleware::auth::TeamContext>()
         .map(|ctx| ctx.team_id.clone())
+        .or_else(|| vkey_router.as_ref().map(|router| vkey_usage_id(router)))
         .unwrap_or_else(|| "global".to_string());
-    let config = state.config.read().unwrap().clone();
+
+    let request_tenant = parts
+        .extensions
+        .get::<TenantContext>()
+        .map(|ctx| ctx.tenant_id.as_str());
+
+    // A router takes the request when a rule matches the model it would
+    // route it as, or when the model is missing and the router rejects that.
+    let attributes = RequestAttributes::new(&headers, &path, &team_id, &bytes);
+    let serves_request = |router: &crate::config::Router| {
+        (model_missing && router.reject_missing_model)
+            || state
+                .selector
+                .select_channel_for(
+                    router,
+                    router_model(&config, route, router, model_name_str, model_missing),
+                    None,
+                    Some(&attributes),
+                )
+                .is_some()
+    };
 
     // 2. Resolve Router
     let router_name = if let Some(name) = router_name_override {
         name
+    } else if let Some(router_name) = vkey_router {
+        // Virtual key: only its own router may serve the request.
+        let serves_model = config
+            .routers
+            .iter()
+            .find(|r| r.name == router_name)
+            .is_some_and(serves_request);
+        if !serves_model {
+            tracing::warn!(
+                "Router Resolution Failed: Router '{}' of the virtual key has no route for model '{}'",
+                router_name,
+                model_name_str
+            );
+            return protocol_error_response(
+                route,
+                StatusCode::NOT_FOUND,
+                "No matching router found for model",
+            );
+        }
+        state
+            .metrics
+            .vkey_requests_total
+            .with_label_values(&[&router_name])
+            .inc();
+        router_name
     } else if let Some(ctx) = parts.extensions.get::<TeamContext>() {
         // Team Flow
         let team = config.teams.iter().find(|t| t.id == ctx.team_id);
@@ -3460,18 +7406,55 @@ async fn process_request(
         }
         let team = team.unwrap();
 
-        // Check Allowed Models
-        let policy = &team.policy;
-        if !policy.is_model_allowed(model_name_str) {
-            tracing::warn!(
-                "Policy Failed: Model '{}' not allowed by team policy",
-                model_name_str
-            );
-            return protocol_error_response(
-                route,
-                StatusCode::FORBIDDEN,
-                "Model not allowed by team policy",
-            );
+        // Check Allowed Models (globally aliased names are checked as the
+        // model they stand for; a missing model once the router supplied it)
+        let policy = &team.policy;
+        let policy_model =
+            model_alias(&config, route, None, model_name_str).unwrap_or(model_name_str);
+        if !model_missing && let Some(denied) = team_model_denied(route, policy, ctx, policy_model)
+        {
+            return denied;
+        }
+
+        // Per-end-user limits within the team (keyed separately from the team bucket)
+        if let Some(end_user) = client_info.end_user.as_deref()
+            && let Some(limit) = policy.end_user_rate_limit.as_ref()
+        {
+            let rpm = limit.rpm.filter(|&v| v > 0).map(|v| v as u32);
+            let tpm = limit.tpm.filter(|&v| v > 0).map(|v| v as u32);
+            let bucket_key = format!("{}:user:{}", ctx.team_id, end_user);
+            let estimated = serde_json::from_slice::<serde_json::Value>(&bytes)
+                .map(|body| crate::tokens::estimate_request(parts.uri.path(), &body))
+                .unwrap_or(0)
+                .min(u32::MAX as u64) as u32;
+            if (rpm.is_some() || tpm.is_some())
+                && let Err(retry_after) = state
+                    .team_rate_limiter
+                    .check_with_retry(&bucket_key, rpm, tpm, estimated)
+                    .await
+            {
+                tracing::warn!(
+                    "Rate Limit Exceeded: Team '{}' end user '{}'",
+                    ctx.team_id,
+                    end_user
+                );
+                let mut response =
+                    rate_limited_response(route, retry_after, "End-user rate limit exceeded");
+                insert_remaining_headers(
+                    response.headers_mut(),
+                    &state.team_rate_limiter,
+                    &bucket_key,
+                )
+                .await;
+                return response;
+            }
+            if tpm.is_some()
+                && let Some(request_id) = &request_id
+            {
+                state
+                    .team_rate_limiter
+                    .reserve(request_id, &bucket_key, estimated);
+            }
         }
 
         // Check Allowed Routers (Mandatory)
@@ -3494,12 +7477,7 @@ async fn process_request(
                 .routers
                 .iter()
                 .find(|r| r.name == *r_name)
-                .filter(|router| {
-                    state
-                        .selector
-                        .select_channel(router, model_name_str)
-                        .is_some()
-                })
+                .filter(|router| serves_request(router))
                 .is_some()
             {
                 selected_router = Some(r_name.clone());
@@ -3531,14 +7509,14 @@ async fn process_request(
             };
         }
 
-        // Try to find ANY router that handles the model
+        // Try to find ANY router that handles the model (within the
+        // request's tenant, or among top-level routers outside of one)
         let mut selected_router = None;
         for router in config.routers.iter() {
-            if state
-                .selector
-                .select_channel(router, model_name_str)
-                .is_some()
-            {
+            if config.tenant_of(&router.name) != request_tenant {
+                continue;
+            }
+            if serves_request(router) {
                 selected_router = Some(router.name.clone());
                 break;
             }
@@ -3563,6 +7541,43 @@ async fn process_request(
     let Some(router) = config.routers.iter().find(|r| r.name == router_name) else {
         return protocol_error_response(route, StatusCode::NOT_FOUND, "router not found");
     };
+    if model_missing {
+        if router.reject_missing_model {
+            tracing::warn!(
+                "Request Rejected: no model given and router '{}' requires one",
+                router.name
+            );
+            return protocol_error_response(
+                route,
+                StatusCode::BAD_REQUEST,
+                "Missing required parameter: 'model'",
+            );
+        }
+        if let Some(default_model) = router.default_model.as_deref() {
+            tracing::info!(
+                "Default Model: '{}' (router={})",
+                default_model,
+                router.name
+            );
+            bytes = override_request_model(bytes, default_model);
+            model_name = Some(default_model.to_string());
+        }
+    }
+    let model_name_str = model_name.as_deref().unwrap_or("default");
+    // Resolve model aliases before any rule sees the model.
+    if let Some(target) = model_alias(&config, route, Some(router), model_name_str) {
+        tracing::info!("Model Alias: '{}' -> '{}'", model_name_str, target);
+        bytes = override_request_model(bytes, target);
+        model_name = Some(target.to_string());
+    }
+    let model_name_str = model_name.as_deref().unwrap_or("default");
+    if model_missing
+        && let Some(ctx) = parts.extensions.get::<TeamContext>()
+        && let Some(team) = config.teams.iter().find(|t| t.id == ctx.team_id)
+        && let Some(denied) = team_model_denied(route, &team.policy, ctx, model_name_str)
+    {
+        return denied;
+    }
     if matches!(route, RouteKind::GeminiNative)
         && model_name_str == "gemini-native"
         && !gemini_native_resource_router_is_deterministic(router, model_name_str)
@@ -3574,19 +7589,238 @@ async fn process_request(
         );
     }
 
+    let team_rejects_unknown = parts
+        .extensions
+        .get::<TeamContext>()
+        .and_then(|ctx| config.teams.iter().find(|t| t.id == ctx.team_id))
+        .is_some_and(|t| t.policy.reject_unknown_models);
+    if (router.reject_unknown_models || team_rejects_unknown)
+        && model_name_str != "gemini-native"
+        && !router.recognizes_model(model_name_str)
+    {
+        let allowed = router.known_model_patterns();
+        tracing::warn!(
+            "Policy Failed: Unknown model '{}' for router '{}'",
+            model_name_str,
+            router.name
+        );
+        return protocol_error_response(
+            route,
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unknown model '{}'. Allowed models: {}",
+                model_name_str,
+                if allowed.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    allowed.join(", ")
+                }
+            ),
+        );
+    }
+
+    if let Some(limit) = router.max_request_bytes
+        && bytes.len() > limit
+    {
+        tracing::warn!(
+            "Request Rejected: body of {} bytes exceeds router '{}' limit of {}",
+            bytes.len(),
+            router.name,
+            limit
+        );
+        state
+            .metrics
+            .size_limit_exceeded_total
+            .with_label_values(&[&router.name, "request"])
+            .inc();
+        return protocol_error_response(
+            route,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            &format!("Request body exceeds {} bytes", limit),
+        );
+    }
+
     tracing::info!("Router Resolved: {}", router.name);
     tracing::Span::current().record("router_name", &router.name);
+    let transforms = router.transforms.as_ref();
+    if let Some(transforms) = transforms {
+        bytes = crate::transforms::apply_to_request(&transforms.request, route, bytes);
+    }
+    let variant = router.experiment.as_ref().and_then(|experiment| {
+        let cohort_key = sticky_key(router, &headers, &client_info);
+        crate::experiments::assign(&router.name, experiment, cohort_key.as_deref())
+    });
+    if let Some(variant) = variant {
+        tracing::info!(
+            "Experiment Variant: {} (router={})",
+            variant.name,
+            router.name
+        );
+        bytes = crate::experiments::rewrite_model(variant, bytes);
+    }
+    if let Some(guardrails) = config.guardrails.as_ref()
+        && let Some(violation) = crate::guardrails::evaluate(
+            guardrails,
+            &config.channels,
+            &state.providers,
+            &state.client,
+            crate::guardrails::Stage::Request,
+            &bytes,
+        )
+        .await
+    {
+        return guardrail_blocked_response(&state, route, &team_id, &violation);
+    }
+
+    let cache_key = router
+        .cache
+        .as_ref()
+        .filter(|_| overrides.channel.is_none())
+        .and_then(|_| crate::response_cache::cache_key(&team_id, route, &bytes));
+    let mut semantic_key = None;
+    if let (Some(settings), Some(key)) = (router.cache.as_ref(), cache_key.as_deref()) {
+        let bypass = crate::response_cache::bypass_requested(&headers);
+        let mut cached = if bypass {
+            None
+        } else {
+            state.response_cache.get(&router.name, settings, key)
+        };
+        let mut result = if cached.is_some() { "hit" } else { "miss" };
+        if let Some(semantic) = settings.semantic.as_ref().filter(|_| cached.is_none()) {
+            semantic_key = semantic_cache_key(&state, semantic, &team_id, route, &bytes).await;
+            if let Some(probe) = semantic_key.as_ref().filter(|_| !bypass) {
+                cached = state.response_cache.get_similar(
+                    &router.name,
+                    settings,
+                    semantic.threshold,
+                    probe,
+                );
+                if cached.is_some() {
+                    result = "semantic_hit";
+                }
+            }
+        }
+        state
+            .metrics
+            .response_cache_total
+            .with_label_values(&[&router.name, result])
+            .inc();
+        if let Some(response) = cached {
+            tracing::info!("Response Cache Hit: router={} ({})", router.name, result);
+            state
+                .metrics
+                .request_total
+                .with_label_values(&[route.as_str(), &router_name])
+                .inc();
+            state.database.log_request(route.as_str(), &router_name);
+            state.usage_logger.log(
+                request_id.as_deref(),
+                &team_id,
+                &router_name,
+                Some("cache"),
+                "cache",
+                model_name_str,
+                0,
+                0,
+                Some(0.0),
+                false,
+                &client_info,
+            );
+            return response;
+        }
+    }
 
     // 3. Resolve Channels
+    let pinned_channel = match overrides.channel.as_deref() {
+        Some(name) => match config.channels.iter().find(|c| {
+            let scoped = request_tenant.map(|t| crate::config::tenant_scoped_name(t, name));
+            c.name == scoped.as_deref().unwrap_or(name)
+        }) {
+            Some(channel) => Some(channel),
+            None => {
+                return protocol_error_response(
+                    route,
+                    StatusCode::BAD_REQUEST,
+                    &format!("Unknown channel in x-apex-channel: {}", name),
+                );
+            }
+        },
+        None => None,
+    };
+    // A variant's channel stands in for the rules' choice; an explicit
+    // x-apex-channel still wins.
+    let variant_channel = variant
+        .and_then(|variant| variant.channel.as_deref())
+        .filter(|_| pinned_channel.is_none())
+        .and_then(|name| config.channels.iter().find(|c| c.name == name));
+    // Likewise the canary's channel, for its share of the remaining requests.
+    let canary = router.canary.as_ref().filter(|canary| {
+        pinned_channel.is_none()
+            && variant_channel.is_none()
+            && state
+                .canary
+                .route_to_canary(&router.name, canary, &state.metrics)
+    });
+    let canary_channel =
+        canary.and_then(|canary| config.channels.iter().find(|c| c.name == canary.channel));
+    let pinned_channel = pinned_channel.or(variant_channel).or(canary_channel);
     let mut channels = Vec::new();
-    let primary_selection = state
-        .selector
-        .select_channel_with_rule(router, model_name_str);
+    let sticky_key = sticky_key(router, &headers, &client_info);
+    let attributes = RequestAttributes::new(&headers, &path, &team_id, &bytes)
+        .with_request_id(request_id.as_deref());
+    let primary_selection = state.selector.select_channel_for(
+        router,
+        model_name_str,
+        sticky_key.as_deref(),
+        Some(&attributes),
+    );
     let mut matched_rule = primary_selection
         .as_ref()
         .and_then(|selection| selection.matched_rule.clone());
+    let mut fallbacks = Fallbacks {
+        config: &config,
+        router,
+        selector: &state.selector,
+        tiers: router.fallback_tier_list().len(),
+        next: 0,
+        model: model_name_str,
+        sticky_key: sticky_key.as_deref(),
+        request_id: request_id.as_deref(),
+    };
+    let fallback_on = router.fallback_on.clone().unwrap_or_default();
 
-    if let Some(selection) = primary_selection.as_ref()
+    if let Some(ch) = pinned_channel {
+        channels.push(ch);
+        // A failed canary request falls back to the channel the rules chose.
+        if canary_channel.is_some()
+            && let Some(stable) = primary_selection
+                .as_ref()
+                .and_then(|selection| {
+                    config
+                        .channels
+                        .iter()
+                        .find(|c| c.name == selection.channel_name)
+                })
+                .filter(|stable| stable.name != ch.name)
+        {
+            channels.push(stable);
+        }
+        matched_rule = Some(
+            if variant_channel.is_some() {
+                "experiment"
+            } else if canary_channel.is_some() {
+                "canary"
+            } else {
+                "override"
+            }
+            .to_string(),
+        );
+        tracing::info!(
+            "Channel Resolved (Override): {} (model={})",
+            ch.name,
+            model_name_str
+        );
+    } else if let Some(selection) = primary_selection.as_ref()
         && let Some(ch_name) = Some(selection.channel_name.as_str())
         && let Some(ch) = config.channels.iter().find(|c| c.name == ch_name)
     {
@@ -3607,18 +7841,7 @@ async fn process_request(
             model_name_str
         );
 
-        // Fallback logic
-        for fb_name in &router.fallback_channels {
-            if let Some(channel) = config.channels.iter().find(|c| c.name == *fb_name) {
-                tracing::info!("Channel Resolved (Fallback): {}", channel.name);
-                // Avoid duplicates
-                if !channels.iter().any(|c| c.name == channel.name) {
-                    channels.push(channel);
-                }
-            } else {
-                tracing::warn!("Fallback channel not found: {}", fb_name);
-            }
-        }
+        fallbacks.push_next(&mut channels);
 
         if channels.is_empty() {
             tracing::error!(
@@ -3655,38 +7878,79 @@ async fn process_request(
         );
     }
 
-    let route_label = match route {
-        RouteKind::Openai => "openai",
-        RouteKind::Anthropic => "anthropic",
-        RouteKind::GeminiNative => "gemini_native",
+    let caller_key = caller_key_for_audit(&headers);
+    let log = RequestLog {
+        state: &state,
+        route,
+        request_id: request_id.as_deref(),
+        team_id: &team_id,
+        router_name: &router_name,
+        caller_key: caller_key.as_deref(),
+        client_info: &client_info,
     };
-    state
-        .metrics
-        .request_total
-        .with_label_values(&[route_label, &router_name])
-        .inc();
-
-    // Log request to database
-    state.database.log_request(route_label, &router_name);
+    let observe_canary = |channel: &crate::config::Channel, success: bool, latency_ms: f64| {
+        if let Some(canary) = canary.filter(|canary| canary.channel == channel.name) {
+            state
+                .canary
+                .record(&router.name, canary, success, latency_ms, &state.metrics);
+        }
+    };
+    log.received();
+    if config.metrics.end_user_label
+        && let Some(end_user) = client_info.end_user.as_deref()
+    {
+        state
+            .metrics
+            .end_user_request_total
+            .with_label_values(&[&router_name, end_user])
+            .inc();
+    }
 
     // 4. Loop channels
-    let retry_on = &config.global.retries.retry_on_status;
-
-    // Extract path and query for preparation
-    let path = path_override.unwrap_or_else(|| parts.uri.path().to_string());
+    // The matched rule's retries win over the router's, which win over global.
+    let retries = primary_selection
+        .as_ref()
+        .filter(|_| pinned_channel.is_none())
+        .and_then(|selection| selection.rule_index)
+        .and_then(|idx| router.rules.get(idx))
+        .and_then(|rule| rule.retries.as_ref())
+        .or(router.retries.as_ref())
+        .unwrap_or(&config.global.retries);
+    let retry_on = &retries.retry_on_status;
+
+    // Extract query for preparation
     let query = parts.uri.query().map(|s| s.to_string());
     let is_gemini_native_upload = matches!(route, RouteKind::GeminiNative)
         && (path.contains(":uploadToFileSearchStore") || path.starts_with("/gemini/upload/"));
     let max_attempts = if is_gemini_native_upload {
         1
     } else {
-        config.global.retries.max_attempts.max(1)
+        retries.max_attempts.max(1)
     };
+    if !is_gemini_native_upload {
+        spawn_mirror(
+            &state,
+            &config,
+            router,
+            route,
+            &parts.method,
+            &path,
+            query.as_deref(),
+            &headers,
+            &bytes,
+            &team_id,
+            model_name_str,
+            &client_info,
+        );
+    }
 
     let mut index = 0;
     let mut fallback_triggered = false;
+    let mut saturated_channels = 0;
+    let mut oversized_channels = 0;
 
-    while index < channels.len() {
+    let deadline_passed = || deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline);
+    while index < channels.len() && !deadline_passed() {
         let channel = channels[index];
         tracing::Span::current().record("channel_name", &channel.name);
 
@@ -3698,29 +7962,18 @@ async fn process_request(
                 channel.name
             );
             tracing::warn!("Request Rejected: {}", message);
-            state
-                .access_audit
-                .audit(&channel.provider_type, route, false);
-            state
-                .metrics
-                .error_total
-                .with_label_values(&[route_label, &router_name])
-                .inc();
-            state.database.log_error(route_label, &router_name);
-            state.usage_logger.log_failure(
-                request_id.as_deref(),
-                &team_id,
-                &router_name,
+            log.audit(channel, false);
+            log.error();
+            log.failure(
                 matched_rule.as_deref(),
                 &channel.name,
                 model_name_str,
                 None,
                 fallback_triggered,
-                StatusCode::BAD_GATEWAY.as_u16() as i64,
+                StatusCode::BAD_GATEWAY,
                 &message,
                 None,
                 None,
-                &client_info,
             );
             return protocol_error_response(route, StatusCode::BAD_GATEWAY, &message);
         }
@@ -3740,11 +7993,30 @@ async fn process_request(
             state.database.log_fallback(&router_name, &channel.name);
         }
 
-        if !state.rate_limiter.check(&channel.provider_type) {
-            tracing::warn!("Rate Limit Exceeded: Provider {:?}", channel.provider_type);
-            index += 1;
-            continue;
-        }
+        let permit = match admit_channel(
+            &state,
+            &router_name,
+            channel,
+            &headers,
+            bytes.len() as u64,
+        )
+        .await
+        {
+            Ok(permit) => permit,
+            // Another channel may still have room.
+            Err(unavailable) => {
+                match unavailable {
+                    ChannelUnavailable::RateLimited => {}
+                    ChannelUnavailable::TooLarge => oversized_channels += 1,
+                    ChannelUnavailable::Saturated => saturated_channels += 1,
+                }
+                if index == channels.len() - 1 && fallbacks.push_next(&mut channels) {
+                    fallback_triggered = true;
+                }
+                index += 1;
+                continue;
+            }
+        };
 
         let effective_bytes = if channel.provider_type == crate::config::ProviderType::Gemini
             && matches!(route, RouteKind::Anthropic)
@@ -3754,6 +8026,26 @@ async fn process_request(
             bytes.clone()
         };
 
+        // `/v1/responses` is served through chat completions on channels
+        // without a native Responses API.
+        let responses_compat = matches!(route, RouteKind::Openai)
+            && crate::responses_api::is_responses_path(&path)
+            && !crate::responses_api::channel_supports_responses(channel);
+        let (effective_bytes, upstream_path) = if responses_compat {
+            match crate::responses_api::convert_responses_to_chat(&effective_bytes) {
+                Ok(body) => (
+                    body,
+                    crate::responses_api::CHAT_COMPLETIONS_PATH.to_string(),
+                ),
+                Err(e) => {
+                    tracing::warn!("Request Rejected: {}", e);
+                    return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string());
+                }
+            }
+        } else {
+            (effective_bytes, path.clone())
+        };
+
         let effective_model = serde_json::from_slice::<serde_json::Value>(&effective_bytes)
             .ok()
             .and_then(|value| {
@@ -3786,40 +8078,35 @@ async fn process_request(
             let request_summary = summarize_anthropic_request(&effective_bytes);
             tracing::warn!("Gemini replay rejection summary: {}", request_summary);
             tracing::warn!("Request Rejected: {}", reason);
-            state
-                .access_audit
-                .audit(&channel.provider_type, route, false);
-            state
-                .metrics
-                .error_total
-                .with_label_values(&[route_label, &router_name])
-                .inc();
-            state.database.log_error(route_label, &router_name);
-            state.usage_logger.log_failure(
-                request_id.as_deref(),
-                &team_id,
-                &router_name,
+            log.audit(channel, false);
+            log.error();
+            log.failure(
                 matched_rule.as_deref(),
                 &channel.name,
                 model_name_str,
                 None,
                 fallback_triggered,
-                StatusCode::BAD_REQUEST.as_u16() as i64,
+                StatusCode::BAD_REQUEST,
                 &reason,
                 None,
                 None,
-                &client_info,
             );
             return protocol_error_response(route, StatusCode::BAD_REQUEST, &reason);
         }
 
+        // Whether the last transport error was a timeout (`None`: no error).
+        let mut transport_timeout = None;
         for attempt in 0..max_attempts {
+            if attempt > 0 && deadline_passed() {
+                break;
+            }
+            let keyed = state.key_pools.keyed(channel);
             let prepared = match prepare_request(
                 &state.providers,
-                channel,
+                &keyed,
                 route,
                 &channel.base_url,
-                &path,
+                &upstream_path,
                 query.as_deref(),
                 &headers,
                 &effective_bytes,
@@ -3862,33 +8149,161 @@ async fn process_request(
                 max_attempts
             );
 
-            let resp_result = state.client.execute(req_built).await;
+            // Only a first attempt is hedged; retries stay on their channel.
+            let hedge = match router.hedge_after_ms {
+                Some(after_ms) if attempt == 0 && pinned_channel.is_none() => hedge_channel(
+                    &config,
+                    router,
+                    primary_selection.as_ref(),
+                    &channels,
+                    index,
+                )
+                .map(|next| (Duration::from_millis(after_ms), next)),
+                _ => None,
+            };
+            let primary = async {
+                let result = execute_upstream(&state, channel, req_built, deadline).await;
+                report_pool_key(&state, channel, &keyed.api_key, &result);
+                state.metrics.record_upstream_status(
+                    &router_name,
+                    &channel.name,
+                    model_name_str,
+                    result.as_ref().ok().map(|resp| resp.status().as_u16()),
+                );
+                result
+            };
+            let (resp_result, hedge_won) = match hedge {
+                None => (primary.await, None),
+                Some((after, hedge_channel)) => {
+                    // The hedge's rate-limit token, concurrency slot and pool
+                    // key are only taken once the primary has been silent
+                    // for `after`; `sent` keeps its adapter and permit.
+                    let mut sent = None;
+                    let secondary = async {
+                        if provider_rate_limited(&state, hedge_channel).await {
+                            return None;
+                        }
+                        let hedge = build_hedge(
+                            &state,
+                            hedge_channel,
+                            route,
+                            &parts.method,
+                            &path,
+                            query.as_deref(),
+                            &headers,
+                            &bytes,
+                        )?;
+                        tracing::warn!(
+                            "Hedge Triggered: '{}' silent for {}ms, also sending to '{}'",
+                            channel.name,
+                            after.as_millis(),
+                            hedge_channel.name
+                        );
+                        sent = Some((hedge.adapter, hedge.responses_compat, hedge.permit));
+                        let result =
+                            execute_upstream(&state, hedge_channel, hedge.request, deadline).await;
+                        report_pool_key(&state, hedge_channel, &hedge.api_key, &result);
+                        state.metrics.record_upstream_status(
+                            &router_name,
+                            &hedge_channel.name,
+                            model_name_str,
+                            result.as_ref().ok().map(|resp| resp.status().as_u16()),
+                        );
+                        Some(result)
+                    };
+                    match race_hedge(primary, after, secondary).await {
+                        HedgeOutcome::Primary(result) => {
+                            if sent.is_some() {
+                                log.audit(hedge_channel, false);
+                                state
+                                    .metrics
+                                    .hedge_total
+                                    .with_label_values(&[&router_name, &hedge_channel.name, "lost"])
+                                    .inc();
+                            }
+                            (result, None)
+                        }
+                        HedgeOutcome::Hedge(resp) => {
+                            state
+                                .metrics
+                                .hedge_total
+                                .with_label_values(&[&router_name, &hedge_channel.name, "won"])
+                                .inc();
+                            let won = sent.map(|(adapter, responses_compat, permit)| {
+                                (hedge_channel, adapter, responses_compat, permit)
+                            });
+                            (Ok(resp), won)
+                        }
+                    }
+                }
+            };
+            // A winning hedge carries on as if its channel had been selected.
+            let (channel, adapter, responses_compat) = match &hedge_won {
+                Some((hedge_channel, hedge_adapter, hedge_compat, _)) => {
+                    tracing::Span::current().record("channel_name", &hedge_channel.name);
+                    (*hedge_channel, *hedge_adapter, *hedge_compat)
+                }
+                None => (channel, adapter, responses_compat),
+            };
 
             match resp_result {
                 Ok(resp) => {
                     let elapsed = start.elapsed().as_millis() as f64;
-
-                    state
-                        .metrics
-                        .upstream_latency_ms
-                        .with_label_values(&[route_label, &router_name, &channel.name])
-                        .observe(elapsed);
-
-                    // Log latency to database
-                    state
-                        .database
-                        .log_latency(route_label, &router_name, &channel.name, elapsed);
+                    log.latency(channel, elapsed);
 
                     let status = resp.status();
+                    let resp = if status.is_success()
+                        && fallback_on.stream_aborts
+                        && crate::utils::is_event_stream(resp.headers())
+                        && (index + 1 < channels.len() || fallbacks.pending())
+                    {
+                        match first_stream_chunk(resp).await {
+                            Some(resp) => resp,
+                            None => {
+                                tracing::warn!(
+                                    "Upstream Failed: stream from '{}' ended before its first chunk",
+                                    channel.name
+                                );
+                                log.audit(channel, false);
+                                observe_canary(channel, false, elapsed);
+                                if index == channels.len() - 1 && fallbacks.push_next(&mut channels)
+                                {
+                                    fallback_triggered = true;
+                                }
+                                break;
+                            }
+                        }
+                    } else {
+                        resp
+                    };
                     if status.is_success() {
                         tracing::info!("Upstream Success: {} ({}ms)", status, elapsed);
-                        state
-                            .access_audit
-                            .audit(&channel.provider_type, route, true);
+                        log.audit(channel, true);
+                        observe_canary(channel, true, elapsed);
+                        let resp = match log
+                            .limit_response(
+                                &config.global,
+                                router,
+                                channel,
+                                resp,
+                                matched_rule.as_deref(),
+                                model_name_str,
+                                elapsed,
+                                fallback_triggered,
+                            )
+                            .await
+                        {
+                            Ok(resp) => resp,
+                            Err(rejected) => return rejected,
+                        };
                         let mut response = adapter.handle_response(
                             route,
                             resp,
-                            Duration::from_millis(config.global.timeouts.response_ms),
+                            Duration::from_millis(
+                                channel
+                                    .effective_timeouts(&config.global.timeouts)
+                                    .response_ms,
+                            ),
                         );
                         if channel.provider_type == crate::config::ProviderType::Gemini
                             && matches!(route, RouteKind::Anthropic)
@@ -3899,27 +8314,84 @@ async fn process_request(
                                 .wrap_response(team_id.clone(), effective_bytes.clone(), response)
                                 .await;
                         }
-                        return crate::usage::wrap_response(
-                            response,
-                            request_id.clone(),
-                            team_id.clone(),
-                            router_name.clone(),
-                            matched_rule.clone(),
-                            channel.name.clone(),
-                            model_name_str.to_string(),
-                            state.usage_logger.clone(),
-                            state.metrics.clone(),
-                            Some(elapsed),
-                            fallback_triggered,
-                            client_info.clone(),
+                        if responses_compat {
+                            response = crate::responses_api::convert_chat_response(response).await;
+                        }
+                        let response = log
+                            .deliver(
+                                response,
+                                matched_rule.as_deref(),
+                                channel,
+                                model_name_str,
+                                start,
+                                elapsed,
+                                fallback_triggered,
+                            )
+                            .await;
+                        let response = match transforms {
+                            Some(transforms) => {
+                                crate::transforms::apply_to_response(
+                                    &transforms.response,
+                                    route,
+                                    response,
+                                )
+                                .await
+                            }
+                            None => response,
+                        };
+                        let response = match guard_response(
+                            &state, &config, route, &team_id, response,
                         )
-                        .await;
+                        .await
+                        {
+                            Ok(response) => response,
+                            Err(blocked) => return blocked,
+                        };
+                        let response = match (router.cache.as_ref(), cache_key.clone()) {
+                            (Some(settings), Some(key)) => {
+                                state
+                                    .response_cache
+                                    .store(
+                                        &router.name,
+                                        settings,
+                                        key,
+                                        semantic_key.take(),
+                                        response,
+                                    )
+                                    .await
+                            }
+                            _ => response,
+                        };
+                        let transcripts = config
+                            .teams
+                            .iter()
+                            .find(|t| t.id == team_id)
+                            .and_then(|t| t.policy.transcripts.as_ref());
+                        let response = match hedge_won {
+                            Some((_, _, _, hedge_permit)) => hedge_permit.hold(response),
+                            None => permit.hold(response),
+                        };
+                        return match transcripts {
+                            Some(settings) => crate::transcripts::capture(
+                                response,
+                                settings,
+                                &config.data_dir,
+                                crate::transcripts::TranscriptMeta {
+                                    request_id: request_id.clone(),
+                                    team_id: team_id.clone(),
+                                    router: router_name.clone(),
+                                    channel: channel.name.clone(),
+                                    model: model_name_str.to_string(),
+                                    request: bytes.clone(),
+                                },
+                            ),
+                            None => response,
+                        };
                     }
 
                     tracing::warn!("Upstream Failed: {} ({}ms)", status, elapsed);
-                    state
-                        .access_audit
-                        .audit(&channel.provider_type, route, false);
+                    log.audit(channel, false);
+                    observe_canary(channel, false, elapsed);
 
                     // Check if retryable
                     if attempt + 1 < max_attempts {
@@ -3933,8 +8405,15 @@ async fn process_request(
                                 max_attempts,
                                 status_code
                             );
-                            tokio::time::sleep(Duration::from_millis(
-                                config.global.retries.backoff_ms,
+                            state
+                                .metrics
+                                .upstream_retries_total
+                                .with_label_values(&[&router_name, &channel.name, "status"])
+                                .inc();
+                            let wait = retry_after(resp.headers());
+                            tokio::time::sleep(within_deadline(
+                                retries.delay(attempt, wait),
+                                deadline,
                             ))
                             .await;
                             continue;
@@ -3944,65 +8423,40 @@ async fn process_request(
                     // If last channel and last attempt, return error
                     if index == channels.len() - 1 && attempt == max_attempts - 1 {
                         // Check if we can trigger fallback
-                        if !fallback_triggered && !router.fallback_channels.is_empty() {
+                        if fallback_on.status(status.as_u16()) && fallbacks.push_next(&mut channels)
+                        {
                             tracing::warn!(
                                 "Upstream Failed: Channel '{}' failed, trying fallback...",
                                 channel.name
                             );
                             fallback_triggered = true;
-                            for fb_name in &router.fallback_channels {
-                                if let Some(fb_ch) =
-                                    config.channels.iter().find(|c| c.name == *fb_name).filter(
-                                        |fb_ch| !channels.iter().any(|c| c.name == fb_ch.name),
-                                    )
-                                {
-                                    channels.push(fb_ch);
-                                }
-                            }
                             break; // Break attempt loop, proceed to next channel
                         }
 
-                        state
-                            .metrics
-                            .error_total
-                            .with_label_values(&[route_label, &router_name])
-                            .inc();
-
-                        // Log error to database
-                        state.database.log_error(route_label, &router_name);
-                        let provider_trace_id = provider_trace_id_from_headers(resp.headers());
-                        let response_headers = resp.headers().clone();
-                        let error_body_bytes = resp.bytes().await.unwrap_or_default();
-                        let provider_error_body = String::from_utf8_lossy(&error_body_bytes);
-                        let stored_error_body = truncate_for_storage(&provider_error_body, 4000);
-                        if !stored_error_body.is_empty() {
-                            tracing::warn!("Upstream Error Body: {}", stored_error_body);
-                        }
-                        state.usage_logger.log_failure(
-                            request_id.as_deref(),
-                            &team_id,
-                            &router_name,
-                            matched_rule.as_deref(),
-                            &channel.name,
-                            model_name_str,
-                            Some(elapsed),
-                            fallback_triggered,
-                            status.as_u16() as i64,
-                            status
-                                .canonical_reason()
-                                .unwrap_or("upstream request failed"),
-                            provider_trace_id.as_deref(),
-                            Some(stored_error_body.as_str()),
-                            &client_info,
-                        );
+                        let (status, response_headers, error_body_bytes) = log
+                            .upstream_error(
+                                resp,
+                                matched_rule.as_deref(),
+                                channel,
+                                model_name_str,
+                                elapsed,
+                                fallback_triggered,
+                            )
+                            .await;
 
                         // Convert error if needed (e.g. for Anthropic)
                         if matches!(route, RouteKind::Anthropic) {
                             let body = convert_openai_response_to_anthropic(error_body_bytes);
-                            return Response::builder()
+                            let mut response = Response::builder()
                                 .status(status)
                                 .body(Body::from(body))
                                 .unwrap();
+                            for (name, value) in &response_headers {
+                                if crate::providers::is_rate_limit_header(name) {
+                                    response.headers_mut().append(name, value.clone());
+                                }
+                            }
+                            return response;
                         }
                         return response_from_upstream_bytes(
                             status,
@@ -4023,16 +8477,21 @@ async fn process_request(
                         "Upstream Error: {}",
                         e
                     );
-                    state
-                        .access_audit
-                        .audit(&channel.provider_type, route, false);
+                    log.audit(channel, false);
+                    observe_canary(channel, false, start.elapsed().as_millis() as f64);
+                    transport_timeout = Some(e.is_timeout());
                     if attempt + 1 < max_attempts {
                         tracing::warn!(
                             "Retry Triggered: attempt {}/{} due to error",
                             attempt + 1,
                             max_attempts
                         );
-                        tokio::time::sleep(Duration::from_millis(config.global.retries.backoff_ms))
+                        state
+                            .metrics
+                            .upstream_retries_total
+                            .with_label_values(&[&router_name, &channel.name, "error"])
+                            .inc();
+                        tokio::time::sleep(within_deadline(retries.delay(attempt, None), deadline))
                             .await;
                         continue;
                     }
@@ -4041,59 +8500,75 @@ async fn process_request(
         }
 
         // If all attempts failed (network error), check fallback
+        let falls_back = match transport_timeout {
+            Some(true) => fallback_on.timeouts,
+            Some(false) => fallback_on.connection_errors,
+            None => true,
+        };
         if index == channels.len() - 1
-            && !fallback_triggered
-            && !router.fallback_channels.is_empty()
+            && falls_back
+            && !deadline_passed()
+            && fallbacks.push_next(&mut channels)
         {
             tracing::warn!(
                 "Upstream Failed: Channel '{}' failed (network), trying fallback...",
                 channel.name
             );
             fallback_triggered = true;
-            for fb_name in &router.fallback_channels {
-                if let Some(fb_ch) = config
-                    .channels
-                    .iter()
-                    .find(|c| c.name == *fb_name)
-                    .filter(|fb_ch| !channels.iter().any(|c| c.name == fb_ch.name))
-                {
-                    channels.push(fb_ch);
-                }
-            }
         }
 
         index += 1;
     }
 
-    state
-        .metrics
-        .error_total
-        .with_label_values(&[route_label, &router_name])
-        .inc();
+    if saturated_channels == channels.len() {
+        tracing::warn!(
+            "Channel Resolution Failed: every channel for router '{}' is at max_concurrent_requests",
+            router_name
+        );
+        log.error();
+        return protocol_error_response(
+            route,
+            StatusCode::SERVICE_UNAVAILABLE,
+            "all channels are at their concurrency limit",
+        );
+    }
+    if oversized_channels == channels.len() {
+        log.error();
+        return protocol_error_response(
+            route,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body exceeds the size limit of every channel",
+        );
+    }
 
-    // Log error to database
-    state.database.log_error(route_label, &router_name);
+    log.error();
     let last_channel = channels
         .last()
         .map(|channel| channel.name.as_str())
         .unwrap_or("unresolved");
-    state.usage_logger.log_failure(
-        request_id.as_deref(),
-        &team_id,
-        &router_name,
+    let (status, message) = if deadline_passed() {
+        tracing::warn!(
+            "Request Deadline Exceeded: {} gave up after {}ms",
+            router_name,
+            arrived.elapsed().as_millis()
+        );
+        (StatusCode::GATEWAY_TIMEOUT, "request deadline exceeded")
+    } else {
+        (StatusCode::BAD_GATEWAY, "all channels failed")
+    };
+    log.failure(
         matched_rule.as_deref(),
         last_channel,
         model_name_str,
         None,
         fallback_triggered,
-        StatusCode::BAD_GATEWAY.as_u16() as i64,
-        "all channels failed",
+        status,
+        message,
         None,
         None,
-        &client_info,
     );
 
-    protocol_error_response(route, StatusCode::BAD_GATEWAY, "all channels failed")
+    protocol_error_response(route, status, message)
 }
 
 async fn process_gemini_native_direct_pass(
@@ -4105,16 +8580,49 @@ async fn process_gemini_native_direct_pass(
     let route = RouteKind::GeminiNative;
     let route_label = "gemini_native";
     let request_id = request_id_from_parts(&parts);
+    let vkey_router = parts
+        .extensions
+        .get::<RouterKeyContext>()
+        .map(|ctx| ctx.router.clone());
     let team_id = parts
         .extensions
         .get::<crate::middleware::auth::TeamContext>()
         .map(|ctx| ctx.team_id.clone())
+        .or_else(|| vkey_router.as_ref().map(|router| vkey_usage_id(router)))
         .unwrap_or_else(|| "global".to_string());
     let headers = parts.headers.clone();
     let client_info = crate::utils::classify_client(&headers);
     let config = state.config.read().unwrap().clone();
+    let caller_key = caller_key_for_audit(&headers);
+    let audit = |channel: &crate::config::Channel, success: bool| {
+        state.channel_health.record(&channel.name, success);
+        state.access_audit.record(&AuditEvent {
+            request_id: request_id.as_deref(),
+            team_id: &team_id,
+            api_key: caller_key.as_deref(),
+            provider: &channel.provider_type,
+            channel: &channel.name,
+            route,
+            success,
+        });
+    };
 
-    let router_name = if let Some(ctx) = parts.extensions.get::<TeamContext>() {
+    let router_name = if let Some(router_name) = vkey_router {
+        let serves_model = config
+            .routers
+            .iter()
+            .find(|router| router.name == router_name)
+            .and_then(|router| state.selector.select_channel(router, &routing_model))
+            .is_some();
+        if serves_model {
+            state
+                .metrics
+                .vkey_requests_total
+                .with_label_values(&[&router_name])
+                .inc();
+        }
+        serves_model.then_some(router_name)
+    } else if let Some(ctx) = parts.extensions.get::<TeamContext>() {
         let Some(team) = config.teams.iter().find(|team| team.id == ctx.team_id) else {
             return protocol_error_response(route, StatusCode::UNAUTHORIZED, "Team not found");
         };
@@ -4126,6 +8634,13 @@ async fn process_gemini_native_direct_pass(
                 "Model not allowed by team policy",
             );
         }
+        if !ctx.allows_model(&routing_model) {
+            return protocol_error_response(
+                route,
+                StatusCode::FORBIDDEN,
+                "Model not allowed for this API key",
+            );
+        }
 
         team.policy
             .allowed_routers
@@ -4229,7 +8744,7 @@ async fn process_gemini_native_direct_pass(
         .inc();
     state.database.log_request(route_label, &router_name);
 
-    if !state.rate_limiter.check(&channel.provider_type) {
+    if provider_rate_limited(&state, channel).await {
         return protocol_error_response(route, StatusCode::TOO_MANY_REQUESTS, "rate limited");
     }
 
@@ -4268,7 +8783,14 @@ async fn process_gemini_native_direct_pass(
         }
     };
 
-    let resp = match state.client.execute(req_built).await {
+    let resp_result = execute_upstream(&state, channel, req_built, None).await;
+    state.metrics.record_upstream_status(
+        &router_name,
+        &channel.name,
+        &routing_model,
+        resp_result.as_ref().ok().map(|resp| resp.status().as_u16()),
+    );
+    let resp = match resp_result {
         Ok(resp) => resp,
         Err(err) => {
             let message = format_error_chain(&err);
@@ -4329,14 +8851,40 @@ async fn process_gemini_native_direct_pass(
         return response_from_upstream_bytes(status, &response_headers, error_body_bytes);
     }
 
-    state
-        .access_audit
-        .audit(&channel.provider_type, route, true);
+    let resp = match response_body_limit(&config.global, Some(router), channel, resp.headers()) {
+        Some(limit) => match limit_response_size(resp, limit).await {
+            Ok(resp) => resp,
+            Err(size) => {
+                tracing::warn!(
+                    "Response Rejected: channel '{}' returned at least {} bytes (limit {})",
+                    channel.name,
+                    size,
+                    limit
+                );
+                state
+                    .metrics
+                    .size_limit_exceeded_total
+                    .with_label_values(&[&router_name, "response"])
+                    .inc();
+                return protocol_error_response(
+                    route,
+                    StatusCode::BAD_GATEWAY,
+                    &format!("Upstream response exceeds {} bytes", limit),
+                );
+            }
+        },
+        None => resp,
+    };
+    audit(channel, true);
     let adapter = state.providers.adapter_for(channel, route);
     let response = adapter.handle_response(
         route,
         resp,
-        Duration::from_millis(config.global.timeouts.response_ms),
+        Duration::from_millis(
+            channel
+                .effective_timeouts(&config.global.timeouts)
+                .response_ms,
+        ),
     );
     crate::usage::wrap_response(
         response,
@@ -4355,6 +8903,462 @@ async fn process_gemini_native_direct_pass(
     .await
 }
 
+/// A request `process_request` sends on without buffering its body; owns
+/// what it needs of the config, which isn't held across the upstream call.
+struct StreamingPass {
+    global: crate::config::Global,
+    router: crate::config::Router,
+    channel: crate::config::Channel,
+    matched_rule: Option<String>,
+    model: String,
+    /// Whether the model came from `x-apex-model` rather than a parse of the
+    /// body, and is checked against the body's as it streams.
+    check_body_model: bool,
+    team_id: String,
+    /// Whether the router was chosen by the caller's virtual key.
+    vkey: bool,
+    content_length: u64,
+    path: String,
+}
+
+/// Whether `req` can skip buffering: its router opted in with
+/// `stream_requests`, the model is known without reading the body (from the
+/// PII middleware's parse or, for JSON bodies, `x-apex-model`) and nothing on
+/// the way to the upstream needs the body — no body-matching rule, alias,
+/// cache, transform, experiment, canary, hedge, mirror or guardrail, and a
+/// channel whose adapter forwards it as-is. Anything else, including requests
+/// that would be rejected, goes through `route_request` as usual.
+fn plan_streaming_pass<'a>(
+    state: &AppState,
+    config: &'a Config,
+    req: &Request<Body>,
+    route: RouteKind,
+    router_name_override: Option<&str>,
+    path_override: Option<&str>,
+) -> Option<StreamingPass> {
+    let headers = req.headers();
+    if !config.routers.iter().any(|router| router.stream_requests)
+        || !matches!(route, RouteKind::Openai | RouteKind::Anthropic)
+        || req.method() != Method::POST
+        || config.guardrails.is_some()
+        || !RoutingOverrides::from_headers(headers).is_empty()
+    {
+        return None;
+    }
+    let parsed = req
+        .extensions()
+        .get::<OriginalModelName>()
+        .map(|model| model.0.clone());
+    let check_body_model = parsed.is_none();
+    let model = parsed.or_else(|| {
+        // Only a JSON body's model can be checked as it streams.
+        let json = headers
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        headers
+            .get(STREAMING_MODEL_HEADER)
+            .filter(|_| json)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    })?;
+    // Size limits are checked up front, so the length must be declared.
+    let content_length = headers
+        .get(axum::http::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse::<u64>()
+        .ok()?;
+    if content_length > request_body_limit(&config.global, headers) as u64
+        || model_alias(config, route, None, &model).is_some()
+    {
+        return None;
+    }
+
+    let path = path_override.map_or_else(|| req.uri().path().to_string(), str::to_string);
+    let vkey_router = req
+        .extensions()
+        .get::<RouterKeyContext>()
+        .map(|ctx| ctx.router.as_str());
+    let team = req.extensions().get::<TeamContext>();
+    let team_id = team
+        .map(|ctx| ctx.team_id.clone())
+        .or_else(|| vkey_router.map(vkey_usage_id))
+        .unwrap_or_else(|| "global".to_string());
+    let attributes = RequestAttributes::new(headers, &path, &team_id, &[]);
+    // `None` when the answer depends on the body.
+    let serves = |router: &crate::config::Router| {
+        if router.rules_read_body() || model_alias(config, route, Some(router), &model).is_some() {
+            return None;
+        }
+        Some(
+            state
+                .selector
+                .select_channel_for(router, &model, None, Some(&attributes))
+                .is_some(),
+        )
+    };
+    let first_serving = |routers: Vec<&'a crate::config::Router>| {
+        for router in routers {
+            if serves(router)? {
+                return Some(router);
+            }
+        }
+        None
+    };
+
+    let router = if let Some(name) = router_name_override {
+        config.routers.iter().find(|router| router.name == name)?
+    } else if let Some(name) = vkey_router {
+        first_serving(config.routers.iter().filter(|r| r.name == name).collect())?
+    } else if let Some(ctx) = team {
+        let policy = &config.teams.iter().find(|t| t.id == ctx.team_id)?.policy;
+        if !policy.is_model_allowed(&model)
+            || !ctx.allows_model(&model)
+            || policy.reject_unknown_models
+            || policy.end_user_rate_limit.is_some()
+            || policy.transcripts.is_some()
+        {
+            return None;
+        }
+        first_serving(
+            policy
+                .allowed_routers
+                .iter()
+                .filter_map(|name| config.routers.iter().find(|r| r.name == *name))
+                .collect(),
+        )?
+    } else {
+        enforce_global_auth(config, headers).ok()?;
+        let tenant = req
+            .extensions()
+            .get::<TenantContext>()
+            .map(|ctx| ctx.tenant_id.as_str());
+        first_serving(
+            config
+                .routers
+                .iter()
+                .filter(|router| config.tenant_of(&router.name) == tenant)
+                .collect(),
+        )?
+    };
+    if !router.stream_requests
+        || router.rules_read_body()
+        || router.reject_unknown_models
+        || router.cache.is_some()
+        || router.transforms.is_some()
+        || router.experiment.is_some()
+        || router.canary.is_some()
+        || router.hedge_after_ms.is_some()
+        || router.mirror_channel.is_some()
+        || model_alias(config, route, Some(router), &model).is_some()
+        || router
+            .max_request_bytes
+            .is_some_and(|limit| content_length > limit as u64)
+    {
+        return None;
+    }
+
+    let selection = state
+        .selector
+        .select_channel_for(router, &model, None, Some(&attributes))?;
+    let channel = config
+        .channels
+        .iter()
+        .find(|channel| channel.name == selection.channel_name)?;
+    let responses_compat = matches!(route, RouteKind::Openai)
+        && crate::responses_api::is_responses_path(&path)
+        && !crate::responses_api::channel_supports_responses(channel);
+    if responses_compat
+        || !crate::providers::forwards_body(&state.providers, channel, route, &path, &model)
+        || channel_request_limit(channel, headers)
+            .is_some_and(|limit| content_length > limit as u64)
+    {
+        return None;
+    }
+    Some(StreamingPass {
+        global: config.global.clone(),
+        router: router.clone(),
+        channel: channel.clone(),
+        matched_rule: selection.matched_rule,
+        model,
+        check_body_model,
+        team_id,
+        vkey: router_name_override.is_none() && vkey_router.is_some(),
+        content_length,
+        path,
+    })
+}
+
+/// The body of a streamed request, cut short with an error — so the upstream
+/// never gets all `content_length` bytes — unless it names `model`.
+/// `mismatch` is set when it doesn't.
+fn model_checked_body(
+    body: Body,
+    model: &str,
+    content_length: u64,
+    mismatch: Arc<std::sync::atomic::AtomicBool>,
+) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static {
+    use futures::StreamExt;
+    let mut scan = crate::body_model::ModelScan::new(model);
+    let mut received = 0u64;
+    body.into_data_stream().map(move |chunk| {
+        let chunk = chunk.map_err(std::io::Error::other)?;
+        received += chunk.len() as u64;
+        if !scan.feed(&chunk) || (received >= content_length && !scan.matched()) {
+            mismatch.store(true, Ordering::Relaxed);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "request body model does not match x-apex-model",
+            ));
+        }
+        Ok(chunk)
+    })
+}
+
+/// Sends a request planned by `plan_streaming_pass`, its body streamed to
+/// the channel as it arrives. The body can only be sent once, so there is a
+/// single attempt: no retries and no fallbacks.
+async fn process_streaming_pass(
+    state: &Arc<AppState>,
+    req: Request<Body>,
+    route: RouteKind,
+    pass: StreamingPass,
+) -> Response<Body> {
+    let arrived = std::time::Instant::now();
+    let (parts, body) = req.into_parts();
+    let StreamingPass {
+        global,
+        router,
+        channel,
+        matched_rule,
+        model,
+        check_body_model,
+        team_id,
+        vkey,
+        content_length,
+        path,
+    } = pass;
+    let channel = &channel;
+    let matched_rule = matched_rule.as_deref();
+    let request_id = request_id_from_parts(&parts);
+    let headers = parts.headers;
+    let client_info = crate::utils::classify_client(&headers);
+    let deadline =
+        request_budget(&headers, global.max_request_timeout_ms).map(|budget| arrived + budget);
+    let caller_key = caller_key_for_audit(&headers);
+    let log = RequestLog {
+        state,
+        route,
+        request_id: request_id.as_deref(),
+        team_id: &team_id,
+        router_name: &router.name,
+        caller_key: caller_key.as_deref(),
+        client_info: &client_info,
+    };
+
+    tracing::info!(
+        "Request Received: {} {} [Model: {}, {} byte body streamed]",
+        parts.method,
+        parts.uri,
+        model,
+        content_length
+    );
+    tracing::info!("Router Resolved: {}", router.name);
+    tracing::Span::current().record("router_name", &router.name);
+    tracing::Span::current().record("channel_name", &channel.name);
+    if vkey {
+        state
+            .metrics
+            .vkey_requests_total
+            .with_label_values(&[&router.name])
+            .inc();
+    }
+    log.received();
+
+    let permit = match admit_channel(state, &router.name, channel, &headers, content_length).await {
+        Ok(permit) => permit,
+        Err(unavailable) => {
+            log.error();
+            let (status, message) = match unavailable {
+                ChannelUnavailable::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "rate limited"),
+                ChannelUnavailable::TooLarge => (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "request body exceeds every candidate channel's limit",
+                ),
+                ChannelUnavailable::Saturated => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "all channels are at their concurrency limit",
+                ),
+            };
+            return protocol_error_response(route, status, message);
+        }
+    };
+
+    // Prepared without a body: the adapter leaves it untouched anyway.
+    let keyed = state.key_pools.keyed(channel);
+    let query = parts.uri.query();
+    let mut prepared = match prepare_request(
+        &state.providers,
+        &keyed,
+        route,
+        &channel.base_url,
+        &path,
+        query,
+        &headers,
+        &Bytes::new(),
+    ) {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            tracing::warn!("Upstream Request Build Failed: {}", e);
+            return protocol_error_response(route, StatusCode::BAD_REQUEST, &e.to_string());
+        }
+    };
+    prepared.headers.insert(
+        axum::http::header::CONTENT_LENGTH,
+        HeaderValue::from(content_length),
+    );
+    let mismatch = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let body = if check_body_model {
+        reqwest::Body::wrap_stream(model_checked_body(
+            body,
+            &model,
+            content_length,
+            mismatch.clone(),
+        ))
+    } else {
+        reqwest::Body::wrap_stream(body.into_data_stream())
+    };
+    let request = match state
+        .client
+        .request(parts.method.clone(), prepared.url)
+        .headers(prepared.headers)
+        .body(body)
+        .build()
+    {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::warn!("Upstream Request Build Failed: {}", e);
+            return protocol_error_response(
+                route,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &e.to_string(),
+            );
+        }
+    };
+    tracing::info!(
+        "Upstream Request: method={} url={} (body streamed)",
+        request.method(),
+        request.url()
+    );
+
+    let start = std::time::Instant::now();
+    let result = execute_upstream(state, channel, request, deadline).await;
+    if mismatch.load(Ordering::Relaxed) {
+        // The client's doing, not the channel's.
+        let message = "Request body model does not match x-apex-model";
+        tracing::warn!("Request Rejected: body model is not '{}'", model);
+        log.error();
+        log.failure(
+            matched_rule,
+            &channel.name,
+            &model,
+            None,
+            false,
+            StatusCode::BAD_REQUEST,
+            message,
+            None,
+            None,
+        );
+        return protocol_error_response(route, StatusCode::BAD_REQUEST, message);
+    }
+    report_pool_key(state, channel, &keyed.api_key, &result);
+    state.metrics.record_upstream_status(
+        &router.name,
+        &channel.name,
+        &model,
+        result.as_ref().ok().map(|resp| resp.status().as_u16()),
+    );
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(e) => {
+            let message = format_error_chain(&e);
+            tracing::error!(error_chain = %message, "Upstream Error: {}", e);
+            log.audit(channel, false);
+            log.error();
+            let (status, message) =
+                if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+                    (StatusCode::GATEWAY_TIMEOUT, "request deadline exceeded")
+                } else {
+                    (StatusCode::BAD_GATEWAY, message.as_str())
+                };
+            log.failure(
+                matched_rule,
+                &channel.name,
+                &model,
+                None,
+                false,
+                status,
+                message,
+                None,
+                None,
+            );
+            return protocol_error_response(route, status, message);
+        }
+    };
+
+    let elapsed = start.elapsed().as_millis() as f64;
+    log.latency(channel, elapsed);
+    let status = resp.status();
+    if !status.is_success() {
+        tracing::warn!("Upstream Failed: {} ({}ms)", status, elapsed);
+        log.audit(channel, false);
+        let (status, response_headers, body) = log
+            .upstream_error(resp, matched_rule, channel, &model, elapsed, false)
+            .await;
+        return response_from_upstream_bytes(status, &response_headers, body);
+    }
+
+    tracing::info!("Upstream Success: {} ({}ms)", status, elapsed);
+    log.audit(channel, true);
+    let resp = match log
+        .limit_response(
+            &global,
+            &router,
+            channel,
+            resp,
+            matched_rule,
+            &model,
+            elapsed,
+            false,
+        )
+        .await
+    {
+        Ok(resp) => resp,
+        Err(rejected) => return rejected,
+    };
+    let adapter = state.providers.adapter_for(channel, route);
+    let response = adapter.handle_response(
+        route,
+        resp,
+        Duration::from_millis(channel.effective_timeouts(&global.timeouts).response_ms),
+    );
+    let response = log
+        .deliver(
+            response,
+            matched_rule,
+            channel,
+            &model,
+            start,
+            elapsed,
+            false,
+        )
+        .await;
+    permit.hold(response)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4398,19 +9402,40 @@ mod tests {
                     max_attempts: 1,
                     backoff_ms: 10,
                     retry_on_status: vec![],
+                    exponential: false,
+                    jitter: false,
+                    max_backoff_ms: None,
+                    respect_retry_after: false,
                 },
                 gemini_replay: crate::config::GeminiReplay::default(),
                 cors_allowed_origins: vec![],
+                ip_rate_limit: None,
+                trusted_proxies: vec![],
+                load_shedding: None,
+                revoked_keys: vec![],
+                tls: None,
+                model_aliases: Default::default(),
+                max_request_timeout_ms: None,
+                body_limits: None,
+                compression: None,
+                rate_limit: None,
+                provider_rate_limits: Default::default(),
+                rate_limit_store: None,
             },
             data_dir: "/tmp".to_string(),
             web_dir: "target/web".to_string(),
             metrics: crate::config::Metrics {
                 enabled: false,
                 path: "/metrics".to_string(),
+                end_user_label: false,
+                auth_token: None,
+                listen: None,
+                buckets: Default::default(),
             },
             hot_reload: crate::config::HotReload {
                 config_path: "test.json".to_string(),
                 watch: false,
+                store: None,
             },
             logging: crate::config::Logging {
                 level: "info".to_string(),
@@ -4429,6 +9454,21 @@ mod tests {
                     headers: None,
                     model_map: None,
                     timeouts: None,
+                    mock: None,
+                    native_api: false,
+                    max_concurrent_requests: None,
+                    max_queued_requests: None,
+                    queue_timeout_ms: None,
+                    api_keys: vec![],
+                    key_strategy: None,
+                    api_key_file: None,
+                    embedding_task: None,
+                    vertex: None,
+                    quirks: None,
+                    param_profile: None,
+                    prompt_cache: None,
+                    adapter: None,
+                    body_limits: None,
                 },
                 crate::config::Channel {
                     name: "test-channel-2".to_string(),
@@ -4439,6 +9479,21 @@ mod tests {
                     headers: None,
                     model_map: None,
                     timeouts: None,
+                    mock: None,
+                    native_api: false,
+                    max_concurrent_requests: None,
+                    max_queued_requests: None,
+                    queue_timeout_ms: None,
+                    api_keys: vec![],
+                    key_strategy: None,
+                    api_key_file: None,
+                    embedding_task: None,
+                    vertex: None,
+                    quirks: None,
+                    param_profile: None,
+                    prompt_cache: None,
+                    adapter: None,
+                    body_limits: None,
                 },
             ]),
             routers: Arc::new(vec![crate::config::Router {
@@ -4446,12 +9501,14 @@ mod tests {
                 rules: vec![crate::config::RouterRule {
                     match_spec: crate::config::MatchSpec {
                         models: vec!["*".to_string()],
+                        ..Default::default()
                     },
                     channels: vec![crate::config::TargetChannel {
                         name: "test-channel".to_string(),
                         weight: 1,
                     }],
                     strategy: "round_robin".to_string(),
+                    retries: None,
                 }],
                 channels: vec![crate::config::TargetChannel {
                     name: "test-channel".to_string(),
@@ -4460,7 +9517,42 @@ mod tests {
                 strategy: "round_robin".to_string(),
                 metadata: None,
                 fallback_channels: vec![],
+                fallback_tiers: vec![],
+                fallback_on: None,
+                reject_unknown_models: false,
+                max_request_bytes: None,
+                max_response_bytes: None,
+                cache: None,
+                passthrough: false,
+                sticky: None,
+                retries: None,
+                hedge_after_ms: None,
+                mirror_channel: None,
+                mirror_percent: None,
+                experiment: None,
+                canary: None,
+                model_aliases: Default::default(),
+                default_model: None,
+                reject_missing_model: false,
+                stream_requests: false,
+                vkey: None,
+                transforms: None,
             }]),
+            fault_injection: None,
+            access_audit: None,
+            tenants: vec![],
+            health: None,
+            warmup: None,
+            pricing: vec![],
+            secret_refs: Default::default(),
+            secrets: None,
+            includes: Default::default(),
+            env_overrides: Default::default(),
+            model_discovery: None,
+            guardrails: None,
+            usage_collector: None,
+            usage_webhooks: Vec::new(),
+            usage_shipping: None,
         }
     }
 
@@ -4529,6 +9621,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
 
         let req = Request::builder()
@@ -4565,6 +9669,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
 
         let req = Request::builder()
@@ -4622,6 +9738,13 @@ mod tests {
             provider_error_body: None,
             client: None,
             user_agent: None,
+            end_user: None,
+            cost: None,
+            images: 0,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            stream: false,
+            finish_reason: None,
         }];
 
         let topology = build_topology_section(&records);
@@ -4660,6 +9783,13 @@ mod tests {
                 provider_error_body: None,
                 client: None,
                 user_agent: None,
+                end_user: None,
+                cost: None,
+                images: 0,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                stream: false,
+                finish_reason: None,
             },
             DashboardUsageRecord {
                 id: 2,
@@ -4682,6 +9812,13 @@ mod tests {
                 provider_error_body: None,
                 client: None,
                 user_agent: None,
+                end_user: None,
+                cost: None,
+                images: 0,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                stream: false,
+                finish_reason: None,
             },
         ];
 
@@ -4723,6 +9860,13 @@ mod tests {
                 provider_error_body: None,
                 client: None,
                 user_agent: None,
+                end_user: None,
+                cost: None,
+                images: 0,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                stream: false,
+                finish_reason: None,
             })
             .collect::<Vec<_>>();
 
@@ -4755,10 +9899,7 @@ mod tests {
             provider_type: ProviderType::Anthropic, // Distinct provider
             base_url: "http://example.com".to_string(),
             api_key: "k2".to_string(),
-            anthropic_base_url: None,
-            headers: None,
-            model_map: None,
-            timeouts: None,
+            ..Default::default()
         });
 
         // Update router to match "gpt-4" to "ch2"
@@ -4768,12 +9909,14 @@ mod tests {
             crate::config::RouterRule {
                 match_spec: crate::config::MatchSpec {
                     models: vec!["gpt-4".to_string()],
+                    ..Default::default()
                 },
                 channels: vec![crate::config::TargetChannel {
                     name: "ch2".to_string(),
                     weight: 1,
                 }],
                 strategy: "priority".to_string(),
+                retries: None,
             },
         );
 
@@ -4796,6 +9939,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
 
         // Request with model "gpt-4" -> should go to ch2 (Anthropic)
@@ -4826,9 +9981,19 @@ mod tests {
                 allowed_routers: vec!["test-router".to_string()],
                 allowed_models: Some(vec!["gpt-4".to_string()]),
                 rate_limit: None,
+                allow_routing_overrides: false,
+                end_user_rate_limit: None,
+                reject_unknown_models: false,
+                transcripts: None,
+                pii: None,
+                max_tokens_per_request: None,
+                max_context_tokens: None,
+                clamp_max_tokens: false,
+                priority: Default::default(),
             },
             group: None,
             enabled: None,
+            keys: vec![],
         });
 
         let config_arc = Arc::new(RwLock::new(config));
@@ -4849,6 +10014,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
 
         // 1. Valid Request (Correct Key, Allowed Model)
@@ -4859,6 +10036,7 @@ mod tests {
             .header("Authorization", "Bearer sk-ap-test")
             .extension(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             })
             .body(Body::from(r#"{"model": "gpt-4"}"#))
             .unwrap();
@@ -4875,6 +10053,7 @@ mod tests {
             .header("Authorization", "Bearer sk-ap-test")
             .extension(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             })
             .body(Body::from(r#"{"model": "gpt-3.5"}"#))
             .unwrap();
@@ -4883,6 +10062,187 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::FORBIDDEN);
     }
 
+    #[tokio::test]
+    async fn routing_override_headers_require_team_permission() {
+        let mut config = create_test_config();
+        Arc::make_mut(&mut config.channels).push(Channel {
+            name: "ch2".to_string(),
+            provider_type: ProviderType::Anthropic,
+            base_url: "http://example.com".to_string(),
+            api_key: "k2".to_string(),
+            ..Default::default()
+        });
+        for (id, allow) in [("trusted", true), ("plain", false)] {
+            Arc::make_mut(&mut config.teams).push(crate::config::Team {
+                id: id.to_string(),
+                api_key: format!("sk-ap-{id}"),
+                policy: crate::config::TeamPolicy {
+                    allowed_routers: vec!["test-router".to_string()],
+                    allowed_models: None,
+                    rate_limit: None,
+                    allow_routing_overrides: allow,
+                    end_user_rate_limit: None,
+                    reject_unknown_models: false,
+                    transcripts: None,
+                    pii: None,
+                    max_tokens_per_request: None,
+                    max_context_tokens: None,
+                    clamp_max_tokens: false,
+                    priority: Default::default(),
+                },
+                group: None,
+                enabled: None,
+                keys: vec![],
+            });
+        }
+        let audit_calls = Arc::new(Mutex::new(Vec::new()));
+        let (state, _dir) = state_with_config(config);
+        let state = Arc::new(AppState {
+            access_audit: Arc::new(MockAccessAudit {
+                calls: audit_calls.clone(),
+            }),
+            ..AppState::clone(&state)
+        });
+
+        let request = |team: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("x-apex-channel", "ch2")
+                .extension(TeamContext {
+                    team_id: team.to_string(),
+                    key_models: vec![],
+                })
+                .body(Body::from(r#"{"model": "gpt-4"}"#))
+                .unwrap()
+        };
+
+        let resp = handle_openai(State(state.clone()), request("plain")).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(audit_calls.lock().unwrap().is_empty());
+
+        let _ = handle_openai(State(state.clone()), request("trusted")).await;
+        let calls = audit_calls.lock().unwrap();
+        assert_eq!(calls.last().unwrap().0, ProviderType::Anthropic);
+    }
+
+    #[tokio::test]
+    async fn unknown_model_rejected_when_router_opts_in() {
+        let mut config = create_test_config();
+        {
+            let router = &mut Arc::make_mut(&mut config.routers)[0];
+            router.reject_unknown_models = true;
+            let mut known = router.rules[0].clone();
+            known.match_spec.models = vec!["gpt-4".to_string()];
+            router.rules.insert(0, known);
+        }
+        let (state, _dir) = state_with_config(config);
+        let request = |model: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .body(Body::from(json!({ "model": model }).to_string()))
+                .unwrap()
+        };
+
+        let resp = handle_openai(State(state.clone()), request("gpt-4-typo")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&body);
+        assert!(text.contains("Allowed models: gpt-4"), "{text}");
+
+        let resp = handle_openai(State(state.clone()), request("gpt-4")).await;
+        assert_ne!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn end_user_rate_limit_is_scoped_per_user() {
+        let mut config = create_test_config();
+        Arc::make_mut(&mut config.teams).push(crate::config::Team {
+            id: "shared".to_string(),
+            api_key: "sk-ap-shared".to_string(),
+            policy: crate::config::TeamPolicy {
+                allowed_routers: vec!["test-router".to_string()],
+                allowed_models: None,
+                rate_limit: None,
+                allow_routing_overrides: false,
+                end_user_rate_limit: Some(crate::config::TeamRateLimit {
+                    rpm: Some(1),
+                    tpm: None,
+                }),
+                reject_unknown_models: false,
+                transcripts: None,
+                pii: None,
+                max_tokens_per_request: None,
+                max_context_tokens: None,
+                clamp_max_tokens: false,
+                priority: Default::default(),
+            },
+            group: None,
+            enabled: None,
+            keys: vec![],
+        });
+        let (state, _dir) = state_with_config(config);
+
+        let request = |user: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .extension(TeamContext {
+                    team_id: "shared".to_string(),
+                    key_models: vec![],
+                })
+                .body(Body::from(format!(
+                    r#"{{"model":"gpt-4","user":"{user}"}}"#
+                )))
+                .unwrap()
+        };
+
+        let first = handle_openai(State(state.clone()), request("alice")).await;
+        assert_ne!(first.status(), StatusCode::TOO_MANY_REQUESTS);
+        let second = handle_openai(State(state.clone()), request("alice")).await;
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        let other = handle_openai(State(state.clone()), request("bob")).await;
+        assert_ne!(other.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // The upstream failure for alice's first request is attributed to her.
+        let (records, _) = state
+            .database
+            .get_usage_records(Some("shared"), None, None, None, None, None, None, 10, 0)
+            .unwrap();
+        assert!(
+            records
+                .iter()
+                .any(|r| r.end_user.as_deref() == Some("alice"))
+        );
+    }
+
+    #[test]
+    fn batch_results_usage_sums_succeeded_results() {
+        let jsonl = concat!(
+            r#"{"custom_id":"a","result":{"type":"succeeded","message":{"usage":{"input_tokens":10,"output_tokens":3}}}}"#,
+            "\n",
+            r#"{"custom_id":"b","result":{"type":"errored","error":{"type":"invalid_request"}}}"#,
+            "\n",
+            r#"{"custom_id":"c","result":{"type":"succeeded","message":{"usage":{"input_tokens":5,"output_tokens":7}}}}"#,
+        );
+        assert_eq!(batch_results_usage(jsonl.as_bytes()), (15, 10));
+    }
+
+    #[test]
+    fn override_request_model_rewrites_json_model_only() {
+        let body = Bytes::from(r#"{"model":"a","messages":[]}"#);
+        let rewritten = override_request_model(body, "b");
+        let json: serde_json::Value = serde_json::from_slice(&rewritten).unwrap();
+        assert_eq!(json["model"], "b");
+        assert!(json["messages"].is_array());
+
+        let raw = Bytes::from_static(b"not json");
+        assert_eq!(override_request_model(raw.clone(), "b"), raw);
+    }
+
     // ----- /v1/models -------------------------------------------------
 
     fn build_models_state(
@@ -4899,6 +10259,26 @@ mod tests {
             strategy: "round_robin".to_string(),
             metadata: None,
             fallback_channels: vec![],
+            fallback_tiers: vec![],
+            fallback_on: None,
+            reject_unknown_models: false,
+            max_request_bytes: None,
+            max_response_bytes: None,
+            cache: None,
+            passthrough: false,
+            sticky: None,
+            retries: None,
+            hedge_after_ms: None,
+            mirror_channel: None,
+            mirror_percent: None,
+            experiment: None,
+            canary: None,
+            model_aliases: Default::default(),
+            default_model: None,
+            reject_missing_model: false,
+            stream_requests: false,
+            vkey: None,
+            transforms: None,
         });
 
         Arc::make_mut(&mut config.teams).push(crate::config::Team {
@@ -4908,9 +10288,19 @@ mod tests {
                 allowed_routers: vec!["test-router".to_string()],
                 allowed_models,
                 rate_limit: None,
+                allow_routing_overrides: false,
+                end_user_rate_limit: None,
+                reject_unknown_models: false,
+                transcripts: None,
+                pii: None,
+                max_tokens_per_request: None,
+                max_context_tokens: None,
+                clamp_max_tokens: false,
+                priority: Default::default(),
             },
             group: None,
             enabled: None,
+            keys: vec![],
         });
 
         let (dir, database) = create_test_database();
@@ -4929,6 +10319,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
         (state, dir)
     }
@@ -4956,12 +10358,14 @@ mod tests {
         crate::config::RouterRule {
             match_spec: crate::config::MatchSpec {
                 models: models.iter().map(|s| s.to_string()).collect(),
+                ..Default::default()
             },
             channels: vec![crate::config::TargetChannel {
                 name: "test-channel".to_string(),
                 weight: 1,
             }],
             strategy: "round_robin".to_string(),
+            retries: None,
         }
     }
 
@@ -4991,6 +10395,7 @@ mod tests {
             state,
             Some(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             }),
         )
         .await;
@@ -5010,6 +10415,7 @@ mod tests {
             state,
             Some(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             }),
         )
         .await;
@@ -5032,6 +10438,8 @@ mod tests {
             "gpt-4o-mini", // observed in traffic
             10,
             5,
+            0,
+            None,
             Some(120.0),
             false,
             "success",
@@ -5041,12 +10449,14 @@ mod tests {
             None,
             None,
             None,
+            None,
         );
 
         let (status, body) = fetch_models(
             state,
             Some(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             }),
         )
         .await;
@@ -5068,6 +10478,7 @@ mod tests {
             state,
             Some(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             }),
         )
         .await;
@@ -5086,6 +10497,7 @@ mod tests {
             state,
             Some(TeamContext {
                 team_id: "test-team".to_string(),
+                key_models: vec![],
             }),
         )
         .await;
@@ -5120,6 +10532,21 @@ mod tests {
                 headers: None,
                 model_map: None,
                 timeouts: None,
+                mock: None,
+                native_api: false,
+                max_concurrent_requests: None,
+                max_queued_requests: None,
+                queue_timeout_ms: None,
+                api_keys: vec![],
+                key_strategy: None,
+                api_key_file: None,
+                embedding_task: None,
+                vertex: None,
+                quirks: None,
+                param_profile: None,
+                prompt_cache: None,
+                adapter: None,
+                body_limits: None,
             });
             Ok::<_, Response<Body>>(())
         });
@@ -5138,6 +10565,8 @@ mod tests {
         // Disk persisted
         let on_disk = std::fs::read_to_string(&cfg_path).unwrap();
         assert!(on_disk.contains("\"added\""));
+        // Routing cache generation advanced
+        assert_eq!(state.config_generation.load(Ordering::Acquire), 1);
     }
 
     #[test]
@@ -5158,6 +10587,21 @@ mod tests {
                 headers: None,
                 model_map: None,
                 timeouts: None,
+                mock: None,
+                native_api: false,
+                max_concurrent_requests: None,
+                max_queued_requests: None,
+                queue_timeout_ms: None,
+                api_keys: vec![],
+                key_strategy: None,
+                api_key_file: None,
+                embedding_task: None,
+                vertex: None,
+                quirks: None,
+                param_profile: None,
+                prompt_cache: None,
+                adapter: None,
+                body_limits: None,
             });
             Ok::<_, Response<Body>>(())
         });
@@ -5177,6 +10621,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_retry_after_accepts_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let parsed = parse_retry_after(&later).unwrap();
+        assert!(parsed > Duration::from_secs(100) && parsed <= Duration::from_secs(120));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
     #[test]
     fn commit_config_aborts_without_change_when_closure_errors() {
         let dir = tempdir().unwrap();
@@ -5216,6 +10670,18 @@ mod tests {
             usage_logger: Arc::new(UsageLogger::new(database.clone())),
             database,
             web_dir: "target/web".to_string(),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            channel_health: Arc::new(ChannelHealth::new()),
+            canary: Arc::default(),
+            response_cache: Arc::new(crate::response_cache::ResponseCaches::new()),
+            channel_limits: Arc::new(crate::channel_limits::ChannelLimits::new()),
+            channel_clients: moka::sync::Cache::new(16),
+            key_pools: Arc::new(crate::key_pool::KeyPools::new()),
+            secret_store: Arc::new(crate::secret_providers::SecretStore::new()),
+            model_catalog: Arc::new(crate::model_catalog::ModelCatalog::new()),
+            vertex_tokens: Arc::new(crate::vertex::TokenCache::new()),
+            dns_cache: Arc::new(crate::warmup::DnsCache::new()),
         });
         (state, dir)
     }
@@ -33,6 +33,8 @@ pub struct ServiceDefinition {
     pub config_path: PathBuf,
     pub service_name: String,
     pub manager: ServiceManager,
+    /// Binary to run instead of `<install_dir>/current/apex`.
+    pub binary: Option<PathBuf>,
 }
 
 impl ServiceDefinition {
@@ -47,11 +49,19 @@ impl ServiceDefinition {
             config_path,
             service_name,
             manager,
+            binary: None,
         }
     }
 
+    pub fn with_binary(mut self, binary: PathBuf) -> Self {
+        self.binary = Some(binary);
+        self
+    }
+
     pub fn binary_path(&self) -> PathBuf {
-        self.install_dir.join("current").join("apex")
+        self.binary
+            .clone()
+            .unwrap_or_else(|| self.install_dir.join("current").join("apex"))
     }
 }
 
@@ -85,10 +95,13 @@ pub fn render_systemd_unit(definition: &ServiceDefinition) -> String {
          After=network-online.target\n\
          Wants=network-online.target\n\n\
          [Service]\n\
-         Type=simple\n\
+         Type=notify\n\
+         NotifyAccess=main\n\
+         WatchdogSec=30\n\
          WorkingDirectory={install_dir}\n\
          Environment=APEX_CONFIG={config_path}\n\
          ExecStart={binary} gateway run\n\
+         ExecReload=/bin/kill -HUP $MAINPID\n\
          Restart=always\n\
          RestartSec=3\n\n\
          [Install]\n\
@@ -181,6 +194,27 @@ pub fn install_service(definition: &ServiceDefinition) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Write the systemd unit for `definition` to `output`, or to
+/// `/etc/systemd/system` followed by `systemctl daemon-reload`.
+pub fn write_systemd_unit(
+    definition: &ServiceDefinition,
+    output: Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let path = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| service_path(definition));
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, render_systemd_unit(definition))
+        .with_context(|| format!("failed to write systemd unit {}", path.display()))?;
+    if output.is_none() {
+        run_command(Command::new("systemctl").arg("daemon-reload"))?;
+    }
+    Ok(path)
+}
+
 pub fn uninstall_service(definition: &ServiceDefinition) -> anyhow::Result<()> {
     let _ = stop_service(definition);
     let path = service_path(definition);
@@ -429,6 +463,9 @@ mod tests {
         assert!(unit.contains("Environment=APEX_CONFIG=/opt/apex/config.json"));
         assert!(unit.contains("WorkingDirectory=/opt/apex"));
         assert!(unit.contains("Restart=always"));
+        assert!(unit.contains("Type=notify"));
+        assert!(unit.contains("WatchdogSec=30"));
+        assert!(unit.contains("ExecReload=/bin/kill -HUP $MAINPID"));
     }
 
     #[test]
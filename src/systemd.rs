@@ -0,0 +1,110 @@
+//! systemd service notifications (`sd_notify`).
+//!
+//! Under a `Type=notify` unit systemd passes `$NOTIFY_SOCKET`; the gateway
+//! reports `READY=1` once it is listening, brackets config reloads with
+//! `RELOADING=1` / `READY=1`, and pings the watchdog when the unit sets
+//! `WatchdogSec=`. Outside systemd every call is a no-op.
+
+use std::time::Duration;
+
+/// Send `state` (newline-separated `KEY=value` assignments) to systemd.
+/// Returns whether a notification socket was there to receive it.
+#[cfg(unix)]
+pub fn notify(state: &str) -> bool {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(socket) => notify_socket(&socket.to_string_lossy(), state),
+        None => false,
+    }
+}
+
+#[cfg(unix)]
+fn notify_socket(socket: &str, state: &str) -> bool {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(sender) = UnixDatagram::unbound() else {
+        return false;
+    };
+    let sent = match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name)
+                .and_then(|addr| sender.send_to_addr(state.as_bytes(), &addr))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return false,
+        None => sender.send_to(state.as_bytes(), socket),
+    };
+    if let Err(e) = &sent {
+        tracing::warn!(
+            "Failed to notify systemd ({}): {}",
+            state.replace('\n', " "),
+            e
+        );
+    }
+    sent.is_ok()
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> bool {
+    false
+}
+
+pub fn ready(status: &str) {
+    notify(&format!("READY=1\nSTATUS={status}"));
+}
+
+pub fn reloading() {
+    notify("RELOADING=1\nSTATUS=Reloading config");
+}
+
+/// How often systemd expects a watchdog ping, when this process is the one
+/// being watched.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.parse::<u32>().ok() != Some(std::process::id())
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// Ping the watchdog at half its interval from the async runtime, so a
+/// wedged runtime gets the gateway restarted.
+pub fn spawn_watchdog() {
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval / 2);
+        loop {
+            ticks.tick().await;
+            notify("WATCHDOG=1");
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_state_to_notify_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        assert!(notify_socket(path.to_str().unwrap(), "READY=1\nSTATUS=ok"));
+
+        let mut buf = [0u8; 128];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "READY=1\nSTATUS=ok"
+        );
+        assert!(!notify_socket(
+            dir.path().join("missing.sock").to_str().unwrap(),
+            "WATCHDOG=1"
+        ));
+    }
+}
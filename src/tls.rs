@@ -0,0 +1,141 @@
+//! TLS termination for the main listener (`global.tls`).
+//!
+//! Certificates are loaded with rustls (ring provider, the same one reqwest
+//! uses) and polled for changes, so a renewed certificate is picked up
+//! without dropping connections. A renewal that fails to parse is logged and
+//! the previous certificate keeps serving.
+
+use crate::config::Tls;
+use anyhow::Context;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+/// How often the certificate and key files are checked for changes.
+const CERT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Load the PEM pair named by `tls`.
+pub async fn load(tls: &Tls) -> anyhow::Result<RustlsConfig> {
+    // Several rustls users live in this process; pick the provider explicitly.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load TLS certificate {} / key {}",
+                tls.cert_path, tls.key_path
+            )
+        })
+}
+
+/// Serve `app` over TLS on an already bound listener.
+pub async fn serve(
+    listener: std::net::TcpListener,
+    app: Router,
+    config: RustlsConfig,
+) -> std::io::Result<()> {
+    listener.set_nonblocking(true)?;
+    axum_server::from_tcp_rustls(listener, config)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+/// Tracks the modification times of the certificate files.
+pub struct CertWatcher {
+    tls: Tls,
+    seen: Option<(SystemTime, SystemTime)>,
+}
+
+impl CertWatcher {
+    pub fn new(tls: Tls) -> Self {
+        let seen = modified(&tls);
+        Self { tls, seen }
+    }
+
+    /// Reload `config` when either file changed since the last check.
+    /// Returns whether a new certificate was applied.
+    pub async fn reload_if_changed(&mut self, config: &RustlsConfig) -> anyhow::Result<bool> {
+        let current = modified(&self.tls);
+        if current.is_none() || current == self.seen {
+            return Ok(false);
+        }
+        self.seen = current;
+        config
+            .reload_from_pem_file(&self.tls.cert_path, &self.tls.key_path)
+            .await
+            .with_context(|| format!("failed to reload TLS certificate {}", self.tls.cert_path))?;
+        Ok(true)
+    }
+}
+
+/// Poll the certificate files in the background for the process lifetime.
+pub fn spawn_reloader(config: RustlsConfig, tls: Tls) {
+    let mut watcher = CertWatcher::new(tls);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(CERT_POLL_INTERVAL).await;
+            match watcher.reload_if_changed(&config).await {
+                Ok(true) => info!("TLS certificate reloaded"),
+                Ok(false) => {}
+                Err(e) => error!("{:#}", e),
+            }
+        }
+    });
+}
+
+fn modified(tls: &Tls) -> Option<(SystemTime, SystemTime)> {
+    let cert = std::fs::metadata(&tls.cert_path).ok()?.modified().ok()?;
+    let key = std::fs::metadata(&tls.key_path).ok()?.modified().ok()?;
+    Some((cert, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+    #[tokio::test]
+    async fn reloads_only_after_the_files_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let tls = Tls {
+            cert_path: dir.path().join("tls.crt").to_string_lossy().to_string(),
+            key_path: dir.path().join("tls.key").to_string_lossy().to_string(),
+        };
+        let install = |name: &str| {
+            std::fs::copy(format!("{FIXTURES}/{name}.crt"), &tls.cert_path).unwrap();
+            std::fs::copy(format!("{FIXTURES}/{name}.key"), &tls.key_path).unwrap();
+        };
+        install("first");
+        let config = load(&tls).await.unwrap();
+        let mut watcher = CertWatcher::new(tls.clone());
+        assert!(!watcher.reload_if_changed(&config).await.unwrap());
+
+        // Bump the mtime explicitly; coarse filesystem clocks could otherwise
+        // make the copy look unchanged.
+        install("second");
+        let later = SystemTime::now() + Duration::from_secs(5);
+        for path in [&tls.cert_path, &tls.key_path] {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(later)
+                .unwrap();
+        }
+        assert!(watcher.reload_if_changed(&config).await.unwrap());
+        assert!(!watcher.reload_if_changed(&config).await.unwrap());
+
+        // A broken renewal is reported and the old certificate stays.
+        std::fs::write(&tls.cert_path, "not a certificate").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&tls.cert_path)
+            .unwrap()
+            .set_modified(later + Duration::from_secs(5))
+            .unwrap();
+        assert!(watcher.reload_if_changed(&config).await.is_err());
+    }
+}
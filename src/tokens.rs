@@ -0,0 +1,358 @@
+//! Token estimates for requests, before the upstream reports real usage.
+//!
+//! The heuristic follows how each model family's tokenizer splits typical
+//! text: OpenAI's `o200k_base` / `cl100k_base` BPE vocabularies, Claude,
+//! Gemini and the SentencePiece vocabularies of open-weight models differ
+//! mostly in how many ASCII characters make a token and how densely they
+//! encode CJK and other non-ASCII scripts. Chat messages add a few tokens of
+//! framing each. The estimate is good enough to turn away plainly oversized
+//! requests and to pre-charge TPM buckets, not to bill.
+
+use serde_json::Value;
+
+/// Role and separator tokens each chat message adds.
+const MESSAGE_OVERHEAD: u64 = 4;
+/// Tokens that prime the assistant's reply.
+const REPLY_OVERHEAD: u64 = 3;
+
+/// Tokenizer family of a model, chosen from its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    /// `o200k_base`: GPT-4o, GPT-4.1, GPT-5 and the o-series.
+    O200k,
+    /// `cl100k_base`: GPT-4, GPT-3.5 and OpenAI embeddings; also the
+    /// fallback for unknown models.
+    Cl100k,
+    Claude,
+    Gemini,
+    /// SentencePiece vocabularies (Llama, Mistral, Qwen, DeepSeek, ...).
+    OpenWeight,
+}
+
+impl Family {
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let model = model.rsplit('/').next().unwrap_or(&model);
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|p| model.starts_with(p));
+        if starts(&[
+            "gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "chatgpt-", "o1", "o3", "o4",
+        ]) {
+            Family::O200k
+        } else if starts(&["claude"]) {
+            Family::Claude
+        } else if starts(&["gemini", "gemma"]) {
+            Family::Gemini
+        } else if starts(&[
+            "llama",
+            "meta-llama",
+            "mistral",
+            "mixtral",
+            "codestral",
+            "qwen",
+            "deepseek",
+            "yi-",
+            "glm",
+        ]) {
+            Family::OpenWeight
+        } else {
+            Family::Cl100k
+        }
+    }
+
+    /// Family of a JSON request: its `model`, or Gemini for native Gemini
+    /// routes (where the model is in the path).
+    pub fn for_request(path: &str, body: &Value) -> Self {
+        if path.starts_with("/gemini/") {
+            return Family::Gemini;
+        }
+        body.get("model")
+            .and_then(Value::as_str)
+            .map_or(Family::Cl100k, Family::for_model)
+    }
+
+    /// (ASCII characters per token, tokens per non-ASCII character).
+    fn ratios(self) -> (f64, f64) {
+        match self {
+            Family::O200k => (4.0, 0.8),
+            Family::Cl100k => (4.0, 1.0),
+            Family::Claude => (3.5, 1.2),
+            Family::Gemini => (4.0, 0.7),
+            Family::OpenWeight => (3.5, 1.0),
+        }
+    }
+
+    /// Estimated tokens in `text`.
+    pub fn estimate_text(self, text: &str) -> u64 {
+        let (ascii, other) = text.chars().fold((0u64, 0u64), |(ascii, other), c| {
+            if c.is_ascii() {
+                (ascii + 1, other)
+            } else {
+                (ascii, other + 1)
+            }
+        });
+        let (chars_per_token, tokens_per_char) = self.ratios();
+        (ascii as f64 / chars_per_token).ceil() as u64
+            + (other as f64 * tokens_per_char).ceil() as u64
+    }
+}
+
+/// JSON pointers a request may use for its completion limit, in the order
+/// they take precedence.
+const OUTPUT_LIMIT_FIELDS: &[&str] = &[
+    "/max_completion_tokens",
+    "/max_output_tokens",
+    "/max_tokens",
+    "/generationConfig/maxOutputTokens",
+];
+
+/// Estimated prompt tokens of a JSON request body in any supported protocol.
+pub fn estimate_prompt(family: Family, body: &Value) -> u64 {
+    let messages = ["messages", "contents", "input"]
+        .iter()
+        .filter_map(|key| body.get(key).and_then(Value::as_array))
+        .map(Vec::len)
+        .sum::<usize>() as u64;
+    family.estimate_text(&crate::guardrails::value_text(body))
+        + messages * MESSAGE_OVERHEAD
+        + REPLY_OVERHEAD
+}
+
+/// Tokens to charge a TPM bucket when a request is admitted: the estimated
+/// prompt plus the completion limit it asks for.
+pub fn estimate_request(path: &str, body: &Value) -> u64 {
+    estimate_prompt(Family::for_request(path, body), body)
+        + requested_output(body).map_or(0, |(_, n)| n)
+}
+
+/// Completion limit the request asks for, with the pointer it was read from.
+pub fn requested_output(body: &Value) -> Option<(&'static str, u64)> {
+    OUTPUT_LIMIT_FIELDS.iter().find_map(|pointer| {
+        body.pointer(pointer)
+            .and_then(Value::as_u64)
+            .map(|n| (*pointer, n))
+    })
+}
+
+/// Where a generation request on `path` sets its completion limit, or `None`
+/// for endpoints that do not generate text (embeddings, moderations, ...).
+fn output_limit_field(path: &str) -> Option<&'static str> {
+    if path.contains(":generateContent") || path.contains(":streamGenerateContent") {
+        Some("/generationConfig/maxOutputTokens")
+    } else if path.ends_with("/responses") {
+        Some("/max_output_tokens")
+    } else if path.ends_with("/chat/completions")
+        || path.ends_with("/completions")
+        || path.ends_with("/messages")
+    {
+        Some("/max_tokens")
+    } else {
+        None
+    }
+}
+
+/// A team's token limits (`max_tokens_per_request`, `max_context_tokens`,
+/// `clamp_max_tokens`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenLimits {
+    pub max_tokens: Option<u64>,
+    pub max_context: Option<u64>,
+    pub clamp: bool,
+}
+
+impl TokenLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_tokens.is_some() || self.max_context.is_some()
+    }
+}
+
+/// Check a JSON request on `path` against `limits`. Returns whether the body
+/// was changed (a clamped or added completion limit), or the reason the
+/// request is rejected.
+pub fn enforce(limits: &TokenLimits, path: &str, body: &mut Value) -> Result<bool, String> {
+    let family = Family::for_request(path, body);
+    let prompt = limits.max_context.map(|_| estimate_prompt(family, body));
+    if let (Some(max), Some(prompt)) = (limits.max_context, prompt)
+        && prompt > max
+    {
+        return Err(format!(
+            "Prompt is about {prompt} tokens, over this team's context limit of {max}"
+        ));
+    }
+    let Some(default_field) = output_limit_field(path) else {
+        return Ok(false);
+    };
+    let room = limits
+        .max_context
+        .zip(prompt)
+        .map(|(max, prompt)| max - prompt);
+    let allowed = match (limits.max_tokens, room) {
+        (Some(a), Some(b)) => a.min(b),
+        (a, b) => match a.or(b) {
+            Some(allowed) => allowed,
+            None => return Ok(false),
+        },
+    };
+    let (field, requested) = match requested_output(body) {
+        Some((field, requested)) => (field, Some(requested)),
+        None => (default_field, None),
+    };
+    let name = field.rsplit('/').next().unwrap_or(field);
+    match requested {
+        Some(requested) if requested <= allowed => Ok(false),
+        _ if allowed == 0 => Err(format!(
+            "Prompt leaves no room for a completion within this team's context limit of {}",
+            limits.max_context.unwrap_or_default()
+        )),
+        Some(requested) if !limits.clamp => Err(format!(
+            "{name} of {requested} exceeds this team's limit of {allowed} tokens"
+        )),
+        None if !limits.clamp => Ok(false),
+        _ => Ok(set_pointer(body, field, allowed)),
+    }
+}
+
+fn set_pointer(body: &mut Value, pointer: &str, value: u64) -> bool {
+    let mut slot = body;
+    for key in pointer.split('/').skip(1) {
+        let Some(object) = slot.as_object_mut() else {
+            return false;
+        };
+        slot = object
+            .entry(key)
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+    *slot = Value::from(value);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits(max_tokens: Option<u64>, max_context: Option<u64>, clamp: bool) -> TokenLimits {
+        TokenLimits {
+            max_tokens,
+            max_context,
+            clamp,
+        }
+    }
+
+    #[test]
+    fn estimates_ascii_by_length_and_cjk_per_character() {
+        assert_eq!(Family::Cl100k.estimate_text(""), 0);
+        assert_eq!(Family::Cl100k.estimate_text("hello world!"), 3);
+        assert_eq!(Family::Cl100k.estimate_text("你好世界"), 4);
+        let body = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "hello world!"}
+            ]
+        });
+        assert_eq!(estimate_prompt(Family::O200k, &body), 3 + 3 + 2 * 4 + 3);
+    }
+
+    #[test]
+    fn picks_tokenizer_family_by_model() {
+        assert_eq!(Family::for_model("gpt-4o-mini"), Family::O200k);
+        assert_eq!(Family::for_model("openai/o3-mini"), Family::O200k);
+        assert_eq!(Family::for_model("gpt-4-turbo"), Family::Cl100k);
+        assert_eq!(Family::for_model("claude-3-5-sonnet"), Family::Claude);
+        assert_eq!(
+            Family::for_model("meta-llama/Llama-3.1-70B"),
+            Family::OpenWeight
+        );
+        assert_eq!(Family::for_model("unknown"), Family::Cl100k);
+        assert_eq!(
+            Family::for_request(
+                "/gemini/v1beta/models/gemini-2.0-flash:generateContent",
+                &json!({})
+            ),
+            Family::Gemini
+        );
+
+        let text = "x".repeat(350);
+        assert_eq!(Family::Claude.estimate_text(&text), 100);
+        assert_eq!(Family::O200k.estimate_text(&text), 88);
+        assert_eq!(Family::Gemini.estimate_text("你好世界"), 3);
+    }
+
+    #[test]
+    fn request_estimate_includes_requested_completion() {
+        let body = json!({
+            "model": "gpt-4o",
+            "max_tokens": 500,
+            "messages": [{"role": "user", "content": "x".repeat(400)}]
+        });
+        assert_eq!(
+            estimate_request("/v1/chat/completions", &body),
+            100 + 4 + 3 + 500
+        );
+    }
+
+    #[test]
+    fn reads_completion_limits_from_each_protocol() {
+        assert_eq!(
+            requested_output(&json!({"max_tokens": 10, "max_completion_tokens": 20})),
+            Some(("/max_completion_tokens", 20))
+        );
+        assert_eq!(
+            requested_output(&json!({"generationConfig": {"maxOutputTokens": 64}})),
+            Some(("/generationConfig/maxOutputTokens", 64))
+        );
+        assert_eq!(requested_output(&json!({"model": "m"})), None);
+    }
+
+    #[test]
+    fn rejects_or_clamps_oversized_completions() {
+        let path = "/v1/chat/completions";
+        let mut body = json!({"messages": [], "max_tokens": 4096});
+        let err = enforce(&limits(Some(1024), None, false), path, &mut body).unwrap_err();
+        assert!(err.contains("max_tokens of 4096"), "{err}");
+
+        assert_eq!(
+            enforce(&limits(Some(1024), None, true), path, &mut body),
+            Ok(true)
+        );
+        assert_eq!(body["max_tokens"], 1024);
+
+        let mut body = json!({"contents": []});
+        let gemini = "/gemini/v1beta/models/gemini-2.0-flash:generateContent";
+        assert_eq!(
+            enforce(&limits(Some(256), None, true), gemini, &mut body),
+            Ok(true)
+        );
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 256);
+
+        let mut body = json!({"input": "text", "max_tokens": 4096});
+        assert_eq!(
+            enforce(&limits(Some(16), None, false), "/v1/embeddings", &mut body),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn context_limit_counts_prompt_and_completion() {
+        let path = "/v1/messages";
+        let prompt = json!({"messages": [{"role": "user", "content": "x".repeat(400)}]});
+        // 100 text tokens, 4 for the message, 3 for the reply.
+        assert_eq!(estimate_prompt(Family::Cl100k, &prompt), 107);
+
+        let mut body = prompt.clone();
+        let err = enforce(&limits(None, Some(100), true), path, &mut body).unwrap_err();
+        assert!(err.contains("context limit of 100"), "{err}");
+
+        let mut body = prompt.clone();
+        body["max_tokens"] = json!(500);
+        assert!(enforce(&limits(None, Some(200), false), path, &mut body).is_err());
+        assert_eq!(
+            enforce(&limits(None, Some(200), true), path, &mut body),
+            Ok(true)
+        );
+        assert_eq!(body["max_tokens"], 93);
+
+        let mut body = prompt;
+        assert!(enforce(&limits(None, Some(107), true), path, &mut body).is_err());
+    }
+}
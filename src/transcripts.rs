@@ -0,0 +1,228 @@
+//! Opt-in conversation transcripts (see `config::TranscriptSettings`).
+//!
+//! The response body is teed as it streams to the client; once the body is
+//! finished (or the client goes away) the redacted request and response are
+//! appended as one JSON line to the team's daily transcript file. Binary
+//! uploads and responses (audio, images) are recorded by size only.
+
+use crate::compliance::{PiiProcessor, process_json_content};
+use crate::config::{Compliance, Team, TranscriptSettings};
+use anyhow::Result;
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What a transcript line is about, captured before the response streams.
+pub struct TranscriptMeta {
+    pub request_id: Option<String>,
+    pub team_id: String,
+    pub router: String,
+    pub channel: String,
+    pub model: String,
+    pub request: Bytes,
+}
+
+/// Wrap `response` so its body is recorded for `meta.team_id` when done.
+pub fn capture(
+    response: Response<Body>,
+    settings: &TranscriptSettings,
+    data_dir: &str,
+    meta: TranscriptMeta,
+) -> Response<Body> {
+    let status = response.status().as_u16();
+    let binary = crate::utils::is_binary_body(response.headers()).then(|| {
+        response.headers()[axum::http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap_or("binary")
+            .to_string()
+    });
+    let (parts, body) = response.into_parts();
+    let tap = Mutex::new(Tap {
+        dir: team_dir(&transcripts_dir(data_dir), &meta.team_id),
+        settings: settings.clone(),
+        meta,
+        status,
+        body: Vec::new(),
+        binary,
+        body_len: 0,
+    });
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            let mut tap = tap.lock().unwrap_or_else(|e| e.into_inner());
+            tap.body_len += bytes.len();
+            if tap.binary.is_none() {
+                tap.body.extend_from_slice(bytes);
+            }
+        }
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// Drop transcript files older than each team's `retention_days`.
+pub fn prune(data_dir: &str, teams: &[Team]) -> Result<u64> {
+    let root = transcripts_dir(data_dir);
+    let mut removed = 0;
+    for team in teams {
+        let Some(settings) = team.policy.transcripts.as_ref() else {
+            continue;
+        };
+        removed += prune_dir(&team_dir(&root, &team.id), settings.retention_days)?;
+    }
+    Ok(removed)
+}
+
+/// Buffers the streamed body and writes the transcript line when dropped.
+struct Tap {
+    dir: PathBuf,
+    settings: TranscriptSettings,
+    meta: TranscriptMeta,
+    status: u16,
+    body: Vec<u8>,
+    /// Content type of a binary response, whose bytes are not kept.
+    binary: Option<String>,
+    body_len: usize,
+}
+
+impl Drop for Tap {
+    fn drop(&mut self) {
+        let redactor = PiiProcessor::new(&Some(Compliance {
+            enabled: true,
+            rules: self.settings.redact.clone(),
+        }));
+        let request = match std::str::from_utf8(&self.meta.request) {
+            Ok(text) => redact_json(&redactor, text),
+            Err(_) => Value::String(format!(
+                "<{} bytes of binary upload>",
+                self.meta.request.len()
+            )),
+        };
+        let body = String::from_utf8_lossy(&self.body);
+        let response = match (&self.binary, serde_json::from_str::<Value>(&body)) {
+            (Some(content_type), _) => {
+                Value::String(format!("<{} bytes of {}>", self.body_len, content_type))
+            }
+            (None, Ok(_)) => redact_json(&redactor, &body),
+            // Streams are stored as the assembled text, not raw SSE frames.
+            (None, Err(_)) => Value::String(redactor.process(&sse_text(&body)).0),
+        };
+
+        let now = chrono::Local::now();
+        let line = json!({
+            "timestamp": now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "request_id": self.meta.request_id,
+            "team_id": self.meta.team_id,
+            "router": self.meta.router,
+            "channel": self.meta.channel,
+            "model": self.meta.model,
+            "status": self.status,
+            "request": request,
+            "response": response,
+        });
+        let path = self.dir.join(format!("{}.jsonl", now.format("%Y-%m-%d")));
+        let result = std::fs::create_dir_all(&self.dir).and_then(|_| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| writeln!(file, "{line}"))
+        });
+        if let Err(err) = result {
+            tracing::error!("Transcript write to {:?} failed: {}", path, err);
+        }
+    }
+}
+
+fn redact_json(redactor: &PiiProcessor, text: &str) -> Value {
+    let (redacted, _) = process_json_content(redactor, text);
+    serde_json::from_str(&redacted).unwrap_or(Value::String(redacted))
+}
+
+/// Concatenate the text deltas of an OpenAI or Anthropic SSE stream.
+fn sse_text(body: &str) -> String {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .filter_map(|event| {
+            event
+                .pointer("/choices/0/delta/content")
+                .or_else(|| event.pointer("/delta/text"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+fn transcripts_dir(data_dir: &str) -> PathBuf {
+    match (data_dir.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(data_dir),
+    }
+    .join("transcripts")
+}
+
+/// Tenant teams (`acme/eng`) nest naturally; anything else odd is flattened.
+fn team_dir(root: &Path, team_id: &str) -> PathBuf {
+    team_id
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .fold(root.to_path_buf(), |dir, part| dir.join(part))
+}
+
+fn prune_dir(dir: &Path, retention_days: u64) -> Result<u64> {
+    if retention_days == 0 || !dir.exists() {
+        return Ok(0);
+    }
+    let cutoff = (chrono::Local::now() - chrono::Duration::days(retention_days as i64))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(date) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".jsonl"))
+        else {
+            continue;
+        };
+        if date < cutoff.as_str() {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_text_joins_openai_and_anthropic_deltas() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        assert_eq!(sse_text(body), "Hello");
+    }
+
+    #[test]
+    fn team_dir_keeps_tenant_nesting_inside_root() {
+        let root = Path::new("/data/transcripts");
+        assert_eq!(
+            team_dir(root, "acme/eng"),
+            PathBuf::from("/data/transcripts/acme/eng")
+        );
+        assert_eq!(
+            team_dir(root, "../etc"),
+            PathBuf::from("/data/transcripts/etc")
+        );
+    }
+}
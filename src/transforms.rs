@@ -0,0 +1,378 @@
+//! Per-router body rewrites (`router.transforms`).
+//!
+//! Request transforms run on the JSON request body once the router is
+//! resolved, before the response cache and every channel attempt; response
+//! transforms run on non-streaming JSON responses before they reach the
+//! client. Bodies that are not JSON (multipart uploads, SSE) pass untouched.
+//!
+//! Paths are dot separated keys with optional `[n]` / `[*]` array indexes,
+//! e.g. `temperature`, `metadata.user_id`, `choices[*].logprobs`. A leading
+//! `$.` is accepted.
+
+use crate::config::Transform;
+use crate::providers::RouteKind;
+use axum::body::{Body, Bytes};
+use axum::http::{Response, header};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    All,
+}
+
+fn parse_path(path: &str) -> anyhow::Result<Vec<Segment>> {
+    let trimmed = path.strip_prefix('$').unwrap_or(path);
+    let trimmed = trimmed.strip_prefix('.').unwrap_or(trimmed);
+    let mut segments = Vec::new();
+    for part in trimmed.split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while !rest.is_empty() {
+            let Some(end) = rest.find(']').filter(|_| rest.starts_with('[')) else {
+                anyhow::bail!("invalid path {path:?}: expected [index] after {key:?}");
+            };
+            segments.push(match &rest[1..end] {
+                "*" => Segment::All,
+                index => Segment::Index(index.parse().map_err(|_| {
+                    anyhow::anyhow!("invalid path {path:?}: {index:?} is not an array index")
+                })?),
+            });
+            rest = &rest[end + 1..];
+        }
+        if key.is_empty() && part.is_empty() {
+            anyhow::bail!("invalid path {path:?}: empty segment");
+        }
+    }
+    if segments.is_empty() {
+        anyhow::bail!("invalid path {path:?}: empty path");
+    }
+    Ok(segments)
+}
+
+/// Problems with a transform list, for `validate_config`.
+pub fn check(transforms: &[Transform]) -> Vec<String> {
+    transforms
+        .iter()
+        .filter_map(|transform| match transform {
+            Transform::Set { path, .. }
+            | Transform::Default { path, .. }
+            | Transform::Cap { path, .. }
+            | Transform::Remove { path } => parse_path(path).err().map(|e| e.to_string()),
+            Transform::SystemPrompt { .. } => None,
+        })
+        .collect()
+}
+
+/// Apply `transforms` in order to a JSON request body.
+pub fn apply_to_request(transforms: &[Transform], route: RouteKind, body: Bytes) -> Bytes {
+    if transforms.is_empty() {
+        return body;
+    }
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    for transform in transforms {
+        apply(transform, route, &mut value);
+    }
+    serde_json::to_vec(&value).map(Bytes::from).unwrap_or(body)
+}
+
+/// Apply `transforms` in order to a non-streaming JSON response.
+pub async fn apply_to_response(
+    transforms: &[Transform],
+    route: RouteKind,
+    response: Response<Body>,
+) -> Response<Body> {
+    if transforms.is_empty() || crate::utils::is_event_stream(response.headers()) {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::warn!("Response transforms: failed to read response: {}", err);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    for transform in transforms {
+        apply(transform, route, &mut value);
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let bytes = serde_json::to_vec(&value).map(Bytes::from).unwrap_or(bytes);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn apply(transform: &Transform, route: RouteKind, value: &mut Value) {
+    match transform {
+        Transform::Set { path, value: new } => {
+            visit(value, &segments(path), true, &mut |slot| {
+                *slot = new.clone()
+            });
+        }
+        Transform::Default { path, value: new } => {
+            visit(value, &segments(path), true, &mut |slot| {
+                if slot.is_null() {
+                    *slot = new.clone();
+                }
+            });
+        }
+        Transform::Cap { path, max } => {
+            visit(value, &segments(path), false, &mut |slot| {
+                if slot.as_f64().zip(max.as_f64()).is_some_and(|(n, m)| n > m) {
+                    *slot = Value::Number(max.clone());
+                }
+            });
+        }
+        Transform::Remove { path } => remove(value, &segments(path)),
+        Transform::SystemPrompt { text, append } => {
+            inject_system_prompt(value, route, text, *append)
+        }
+    }
+}
+
+/// Paths are checked by `validate_config`; an invalid one matches nothing.
+fn segments(path: &str) -> Vec<Segment> {
+    parse_path(path).unwrap_or_default()
+}
+
+/// Call `f` on every value `path` selects. With `create`, missing object
+/// keys (and a missing final key) are created as `null` first.
+fn visit(value: &mut Value, path: &[Segment], create: bool, f: &mut dyn FnMut(&mut Value)) {
+    let Some((first, rest)) = path.split_first() else {
+        f(value);
+        return;
+    };
+    match first {
+        Segment::Key(key) => {
+            if create && value.is_null() {
+                *value = Value::Object(Map::new());
+            }
+            let Some(object) = value.as_object_mut() else {
+                return;
+            };
+            let child = if create {
+                Some(object.entry(key.clone()).or_insert(Value::Null))
+            } else {
+                object.get_mut(key)
+            };
+            if let Some(child) = child {
+                visit(child, rest, create, f);
+            }
+        }
+        Segment::Index(index) => {
+            if let Some(child) = value.as_array_mut().and_then(|items| items.get_mut(*index)) {
+                visit(child, rest, create, f);
+            }
+        }
+        Segment::All => {
+            for child in value.as_array_mut().into_iter().flatten() {
+                visit(child, rest, create, f);
+            }
+        }
+    }
+}
+
+fn remove(value: &mut Value, path: &[Segment]) {
+    let Some((last, parent)) = path.split_last() else {
+        return;
+    };
+    visit(value, parent, false, &mut |slot| match (last, slot) {
+        (Segment::Key(key), Value::Object(object)) => {
+            object.remove(key);
+        }
+        (Segment::Index(index), Value::Array(items)) if *index < items.len() => {
+            items.remove(*index);
+        }
+        (Segment::All, Value::Array(items)) => items.clear(),
+        _ => {}
+    });
+}
+
+/// Add `text` to the request's system prompt, in whichever shape the route
+/// uses: an OpenAI `system` message, Anthropic `system` or Gemini
+/// `systemInstruction`.
+fn inject_system_prompt(value: &mut Value, route: RouteKind, text: &str, append: bool) {
+    let Some(request) = value.as_object_mut() else {
+        return;
+    };
+    let join = |existing: &str| {
+        if append {
+            format!("{existing}\n\n{text}")
+        } else {
+            format!("{text}\n\n{existing}")
+        }
+    };
+    match route {
+        RouteKind::Openai => {
+            let Some(messages) = request.get_mut("messages").and_then(Value::as_array_mut) else {
+                return;
+            };
+            let system = messages.iter_mut().find(|m| {
+                matches!(m["role"].as_str(), Some("system" | "developer"))
+                    && m["content"].is_string()
+            });
+            match system {
+                Some(message) => {
+                    let content = join(message["content"].as_str().unwrap_or_default());
+                    message["content"] = Value::String(content);
+                }
+                None => messages.insert(0, json!({"role": "system", "content": text})),
+            }
+        }
+        RouteKind::Anthropic => {
+            let block = json!({"type": "text", "text": text});
+            match request.get_mut("system") {
+                Some(Value::String(existing)) => *existing = join(existing),
+                Some(Value::Array(blocks)) if append => blocks.push(block),
+                Some(Value::Array(blocks)) => blocks.insert(0, block),
+                _ => {
+                    request.insert("system".to_string(), Value::String(text.to_string()));
+                }
+            }
+        }
+        RouteKind::GeminiNative => {
+            let part = json!({"text": text});
+            let instruction = request
+                .entry("systemInstruction")
+                .or_insert_with(|| json!({"parts": []}));
+            if let Some(parts) = instruction
+                .as_object_mut()
+                .map(|i| i.entry("parts").or_insert_with(|| json!([])))
+                .and_then(Value::as_array_mut)
+            {
+                if append {
+                    parts.push(part);
+                } else {
+                    parts.insert(0, part);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transforms(value: Value) -> Vec<Transform> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn run(list: Value, route: RouteKind, body: Value) -> Value {
+        let out = apply_to_request(&transforms(list), route, Bytes::from(body.to_string()));
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[test]
+    fn parses_keys_and_indexes() {
+        assert_eq!(
+            parse_path("$.choices[*].message.content").unwrap(),
+            vec![
+                Segment::Key("choices".into()),
+                Segment::All,
+                Segment::Key("message".into()),
+                Segment::Key("content".into()),
+            ]
+        );
+        assert_eq!(
+            parse_path("messages[0]").unwrap(),
+            vec![Segment::Key("messages".into()), Segment::Index(0)]
+        );
+        assert!(parse_path("messages[x]").is_err());
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path("").is_err());
+    }
+
+    #[test]
+    fn sets_defaults_caps_and_removes() {
+        let out = run(
+            json!([
+                {"op": "default", "path": "temperature", "value": 0.2},
+                {"op": "default", "path": "top_p", "value": 0.9},
+                {"op": "cap", "path": "max_tokens", "max": 1024},
+                {"op": "set", "path": "metadata.source", "value": "apex"},
+                {"op": "remove", "path": "logit_bias"},
+                {"op": "remove", "path": "messages[*].name"}
+            ]),
+            RouteKind::Openai,
+            json!({
+                "model": "m",
+                "top_p": 0.5,
+                "max_tokens": 4096,
+                "logit_bias": {"1": 2},
+                "messages": [{"role": "user", "name": "bob", "content": "hi"}]
+            }),
+        );
+        assert_eq!(
+            out,
+            json!({
+                "model": "m",
+                "temperature": 0.2,
+                "top_p": 0.5,
+                "max_tokens": 1024,
+                "metadata": {"source": "apex"},
+                "messages": [{"role": "user", "content": "hi"}]
+            })
+        );
+    }
+
+    #[test]
+    fn cap_leaves_missing_and_smaller_values_alone() {
+        let list = json!([{"op": "cap", "path": "max_tokens", "max": 1024}]);
+        assert_eq!(
+            run(list.clone(), RouteKind::Openai, json!({"max_tokens": 10})),
+            json!({"max_tokens": 10})
+        );
+        assert_eq!(run(list, RouteKind::Openai, json!({})), json!({}));
+    }
+
+    #[test]
+    fn system_prompt_follows_each_protocol() {
+        let list = json!([{"op": "system_prompt", "text": "Be brief."}]);
+        assert_eq!(
+            run(
+                list.clone(),
+                RouteKind::Openai,
+                json!({"messages": [{"role": "user", "content": "hi"}]})
+            )["messages"][0],
+            json!({"role": "system", "content": "Be brief."})
+        );
+        assert_eq!(
+            run(
+                list.clone(),
+                RouteKind::Openai,
+                json!({"messages": [{"role": "system", "content": "You help."}]})
+            )["messages"][0]["content"],
+            "Be brief.\n\nYou help."
+        );
+        assert_eq!(
+            run(
+                json!([{"op": "system_prompt", "text": "Be brief.", "append": true}]),
+                RouteKind::Anthropic,
+                json!({"system": [{"type": "text", "text": "You help."}]})
+            )["system"][1],
+            json!({"type": "text", "text": "Be brief."})
+        );
+        assert_eq!(
+            run(list, RouteKind::GeminiNative, json!({"contents": []}))["systemInstruction"],
+            json!({"parts": [{"text": "Be brief."}]})
+        );
+    }
+
+    #[test]
+    fn non_json_bodies_pass_through() {
+        let body = Bytes::from_static(b"--boundary\r\n");
+        let list = transforms(json!([{"op": "remove", "path": "model"}]));
+        assert_eq!(
+            apply_to_request(&list, RouteKind::Openai, body.clone()),
+            body
+        );
+    }
+}
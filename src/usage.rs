@@ -1,21 +1,166 @@
-use crate::database::Database;
-use crate::metrics::MetricsState;
+use crate::config::{ModelPrice, model_pattern_matches};
+use crate::database::{Database, UsageEntry};
+use crate::metrics::{GaugeGuard, MetricsState};
+use crate::middleware::ratelimit::TeamRateLimiter;
+use crate::usage_shipping::UsageShipper;
+use crate::usage_webhooks::UsageWebhooks;
 use anyhow::Result;
 use axum::body::{Body, Bytes};
 use axum::response::Response;
 use futures::Stream;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::broadcast;
+
+/// Events a slow `/admin/events` subscriber may fall behind by before it
+/// skips ahead.
+const EVENT_BUFFER: usize = 1024;
 
 pub struct UsageLogger {
     db: Arc<Database>,
+    pricing: RwLock<Arc<Vec<ModelPrice>>>,
+    /// Team TPM buckets to settle with each request's real token count.
+    rate_limiter: Option<Arc<TeamRateLimiter>>,
+    /// Sends every record to a usage collector (`usage_shipping`).
+    shipper: Option<UsageShipper>,
+    /// Records also go to `db`; only ever false while shipping.
+    keep_local: bool,
+    /// POSTs every record to the `usage_webhooks` endpoints.
+    webhooks: Option<UsageWebhooks>,
+    /// Live feed of finished requests for `/admin/events`.
+    events: broadcast::Sender<RequestEvent>,
+}
+
+/// Summary of one finished request, as streamed by `/admin/events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestEvent {
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub team_id: String,
+    pub router: String,
+    pub channel: String,
+    pub model: String,
+    /// `success`, `fallback`, `error` or `fallback_error`.
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+impl From<&UsageEntry> for RequestEvent {
+    fn from(entry: &UsageEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp.clone(),
+            request_id: entry.request_id.clone(),
+            team_id: entry.team_id.clone(),
+            router: entry.router.clone(),
+            channel: entry.channel.clone(),
+            model: entry.model.clone(),
+            status: entry.status.clone(),
+            status_code: entry.status_code,
+            latency_ms: entry.latency_ms,
+            input_tokens: entry.input_tokens,
+            output_tokens: entry.output_tokens,
+        }
+    }
 }
 
 impl UsageLogger {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            pricing: RwLock::new(Arc::new(Vec::new())),
+            rate_limiter: None,
+            shipper: None,
+            keep_local: true,
+            webhooks: None,
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Receive a [`RequestEvent`] for every request logged from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<RequestEvent> {
+        self.events.subscribe()
+    }
+
+    /// Settle TPM reservations in `limiter` as usage is logged.
+    pub fn with_rate_limiter(mut self, limiter: Arc<TeamRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Ship records through `shipper`, keeping local copies if `keep_local`.
+    pub fn with_shipper(mut self, shipper: UsageShipper, keep_local: bool) -> Self {
+        self.shipper = Some(shipper);
+        self.keep_local = keep_local;
+        self
+    }
+
+    /// Also deliver every record to `webhooks`.
+    pub fn with_webhooks(mut self, webhooks: UsageWebhooks) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    fn record(&self, mut entry: UsageEntry) {
+        // Emitted inside the request span, so log lines carry request_id.
+        tracing::info!(
+            target: "apex::usage",
+            team_id = %entry.team_id,
+            channel = %entry.channel,
+            model = %entry.model,
+            status = %entry.status,
+            status_code = entry.status_code,
+            input_tokens = entry.input_tokens,
+            output_tokens = entry.output_tokens,
+            latency_ms = entry.latency_ms,
+            stream = entry.stream,
+            finish_reason = entry.finish_reason.as_deref(),
+            "usage recorded"
+        );
+        if let Some(shipper) = &self.shipper {
+            entry.instance = Some(shipper.instance().to_string());
+        }
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(RequestEvent::from(&entry));
+        }
+        if self.keep_local {
+            let _ = self.db.insert_usage(std::slice::from_ref(&entry));
+        }
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.send(&entry);
+        }
+        if let Some(shipper) = &self.shipper {
+            shipper.send(entry);
+        }
+    }
+
+    /// Swap in the `pricing` table (at startup and on every config reload).
+    pub fn set_pricing(&self, pricing: Vec<ModelPrice>) {
+        *self.pricing.write().unwrap() = Arc::new(pricing);
+    }
+
+    /// Dollar cost of a request, or `None` when no pricing entry matches.
+    pub fn cost(
+        &self,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        images: u64,
+    ) -> Option<f64> {
+        let pricing = self.pricing.read().unwrap().clone();
+        pricing
+            .iter()
+            .find(|price| model_pattern_matches(&price.model, model))
+            .map(|price| price.cost(input_tokens, output_tokens, images))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -33,29 +178,71 @@ impl UsageLogger {
         fallback_triggered: bool,
         client_info: &crate::utils::ClientInfo,
     ) {
-        self.db.log_usage(
+        self.log_with_extras(
             request_id,
             team_id,
             router,
             matched_rule,
             channel,
             model,
-            input_tokens as i64,
-            output_tokens as i64,
+            input_tokens,
+            output_tokens,
+            ExtraUsage::default(),
             latency_ms,
             fallback_triggered,
-            if fallback_triggered {
+            client_info,
+        );
+    }
+
+    /// [`Self::log`] for responses that also generated images or reported
+    /// prompt cache tokens.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_with_extras(
+        &self,
+        request_id: Option<&str>,
+        team_id: &str,
+        router: &str,
+        matched_rule: Option<&str>,
+        channel: &str,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        extra: ExtraUsage,
+        latency_ms: Option<f64>,
+        fallback_triggered: bool,
+        client_info: &crate::utils::ClientInfo,
+    ) {
+        if let (Some(limiter), Some(request_id)) = (&self.rate_limiter, request_id) {
+            limiter.settle(request_id, input_tokens + output_tokens);
+        }
+        self.record(UsageEntry {
+            input_tokens: input_tokens as i64,
+            output_tokens: output_tokens as i64,
+            images: extra.images as i64,
+            cache_read_tokens: extra.cache_read_tokens as i64,
+            cache_creation_tokens: extra.cache_creation_tokens as i64,
+            cost: self.cost(model, input_tokens, output_tokens, extra.images),
+            stream: extra.stream,
+            finish_reason: extra.finish_reason,
+            status: if fallback_triggered {
                 "fallback"
             } else {
                 "success"
-            },
-            Some(200),
-            None,
-            None,
-            None,
-            client_info.client.as_deref(),
-            client_info.user_agent.as_deref(),
-        );
+            }
+            .to_string(),
+            status_code: Some(200),
+            ..UsageEntry::new(
+                request_id,
+                team_id,
+                router,
+                matched_rule,
+                channel,
+                model,
+                latency_ms,
+                fallback_triggered,
+                client_info,
+            )
+        });
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -75,32 +262,46 @@ impl UsageLogger {
         provider_error_body: Option<&str>,
         client_info: &crate::utils::ClientInfo,
     ) {
-        self.db.log_usage(
-            request_id,
-            team_id,
-            router,
-            matched_rule,
-            channel,
-            model,
-            0,
-            0,
-            latency_ms,
-            fallback_triggered,
-            if fallback_triggered {
+        self.record(UsageEntry {
+            status: if fallback_triggered {
                 "fallback_error"
             } else {
                 "error"
-            },
-            Some(status_code),
-            Some(error_message),
-            provider_trace_id,
-            provider_error_body,
-            client_info.client.as_deref(),
-            client_info.user_agent.as_deref(),
-        );
+            }
+            .to_string(),
+            status_code: Some(status_code),
+            error_message: Some(error_message.to_string()),
+            provider_trace_id: provider_trace_id.map(str::to_string),
+            provider_error_body: provider_error_body.map(str::to_string),
+            ..UsageEntry::new(
+                request_id,
+                team_id,
+                router,
+                matched_rule,
+                channel,
+                model,
+                latency_ms,
+                fallback_triggered,
+                client_info,
+            )
+        });
     }
 }
 
+/// What a response reported besides input and output tokens.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtraUsage {
+    pub images: u64,
+    /// Prompt tokens served from the provider's prompt cache.
+    pub cache_read_tokens: u64,
+    /// Prompt tokens written to the provider's prompt cache.
+    pub cache_creation_tokens: u64,
+    /// Sent to the client as a stream (SSE or binary chunks).
+    pub stream: bool,
+    /// Why generation stopped (`stop`, `length`, `end_turn`, `tool_calls`, …).
+    pub finish_reason: Option<String>,
+}
+
 struct UsageTrackerState {
     request_id: Option<String>,
     team_id: String,
@@ -112,10 +313,21 @@ struct UsageTrackerState {
     metrics: Arc<MetricsState>,
     input_tokens: u64,
     output_tokens: u64,
+    extra: ExtraUsage,
     latency_ms: Option<f64>,
     fallback_triggered: bool,
     client_info: crate::utils::ClientInfo,
     accumulated_data: String,
+    /// When the response headers were handed to the wrapper.
+    started: Instant,
+    /// First and latest SSE events that carried generated output.
+    first_output_at: Option<Instant>,
+    last_output_at: Option<Instant>,
+    /// Request span the response was produced in; streamed bodies finish
+    /// outside it.
+    span: tracing::Span,
+    /// Experiment cohort of the request, for per-variant token counts.
+    experiment: Option<crate::experiments::Assignment>,
 }
 
 impl UsageTrackerState {
@@ -143,10 +355,16 @@ impl UsageTrackerState {
             metrics,
             input_tokens: 0,
             output_tokens: 0,
+            extra: ExtraUsage::default(),
             latency_ms,
             fallback_triggered,
             client_info: crate::utils::ClientInfo::default(),
             accumulated_data: String::new(),
+            started: Instant::now(),
+            first_output_at: None,
+            last_output_at: None,
+            span: tracing::Span::current(),
+            experiment: crate::experiments::current(),
         }
     }
 
@@ -180,12 +398,20 @@ impl UsageTrackerState {
                 return;
             }
             if let Ok(json) = serde_json::from_str::<Value>(data) {
+                if carries_output(&json) {
+                    let now = Instant::now();
+                    self.first_output_at.get_or_insert(now);
+                    self.last_output_at = Some(now);
+                }
                 self.extract_usage(&json);
             }
         }
     }
 
     fn extract_usage(&mut self, json: &Value) {
+        if let Some(reason) = finish_reason(json) {
+            self.extra.finish_reason = Some(reason.to_string());
+        }
         // OpenAI / Generic / Anthropic message_delta
         if let Some(usage) = json.get("usage") {
             if let Some(prompt) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
@@ -201,6 +427,25 @@ impl UsageTrackerState {
             if let Some(output) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
                 self.output_tokens += output;
             }
+            // Embeddings APIs that only report a total: it is all input.
+            if usage.get("prompt_tokens").is_none()
+                && usage.get("input_tokens").is_none()
+                && usage.get("completion_tokens").is_none()
+                && let Some(total) = usage.get("total_tokens").and_then(|v| v.as_u64())
+            {
+                self.input_tokens = total;
+            }
+            self.extract_cache_tokens(usage);
+        }
+
+        // Groq streams put the final usage under `x_groq`.
+        if let Some(usage) = json.pointer("/x_groq/usage") {
+            if let Some(prompt) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
+                self.input_tokens = prompt;
+            }
+            if let Some(completion) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
+                self.output_tokens = completion;
+            }
         }
 
         // Anthropic message_start (usage is inside message object)
@@ -213,8 +458,25 @@ impl UsageTrackerState {
             if let Some(output) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
                 self.output_tokens += output;
             }
+            self.extract_cache_tokens(usage);
+        }
+
+        // Responses API stream: totals arrive once, on `response.completed`.
+        if json.get("type").and_then(|v| v.as_str()) == Some("response.completed")
+            && let Some(usage) = json.pointer("/response/usage")
+        {
+            if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
+                self.input_tokens = input;
+            }
+            if let Some(output) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
+                self.output_tokens = output;
+            }
+            self.extract_cache_tokens(usage);
         }
 
+        // Images API: billed per image on top of any token usage.
+        self.extra.images = crate::images::generated_images(json);
+
         // Gemini native generateContent / streamGenerateContent.
         if let Some(usage) = json.get("usageMetadata") {
             if let Some(input) = usage
@@ -231,6 +493,56 @@ impl UsageTrackerState {
             {
                 self.output_tokens = output;
             }
+            if let Some(cached) = usage
+                .get("cachedContentTokenCount")
+                .or_else(|| usage.get("cached_content_token_count"))
+                .and_then(|v| v.as_u64())
+            {
+                self.extra.cache_read_tokens = cached;
+            }
+        }
+    }
+
+    /// Prompt cache counts: Anthropic reports them next to `input_tokens`
+    /// (cumulative, so they are set rather than added), OpenAI under
+    /// `prompt_tokens_details` / `input_tokens_details`.
+    fn extract_cache_tokens(&mut self, usage: &Value) {
+        if let Some(read) = usage
+            .get("cache_read_input_tokens")
+            .or_else(|| usage.pointer("/prompt_tokens_details/cached_tokens"))
+            .or_else(|| usage.pointer("/input_tokens_details/cached_tokens"))
+            .and_then(|v| v.as_u64())
+        {
+            self.extra.cache_read_tokens = read;
+        }
+        if let Some(creation) = usage
+            .get("cache_creation_input_tokens")
+            .and_then(|v| v.as_u64())
+        {
+            self.extra.cache_creation_tokens = creation;
+        }
+    }
+
+    /// Record time to first token and the output rate between the first and
+    /// last output events; single-event streams have no measurable rate.
+    fn observe_throughput(&self) {
+        let (Some(first), Some(last)) = (self.first_output_at, self.last_output_at) else {
+            return;
+        };
+        let model_lower = self.model.to_lowercase();
+        let labels = [self.router.as_str(), self.channel.as_str(), &model_lower];
+        let ttft_ms = self.latency_ms.unwrap_or(0.0)
+            + first.duration_since(self.started).as_secs_f64() * 1000.0;
+        self.metrics
+            .stream_ttft_ms
+            .with_label_values(&labels)
+            .observe(ttft_ms);
+        let generating = last.duration_since(first).as_secs_f64();
+        if self.output_tokens > 0 && generating > 0.0 {
+            self.metrics
+                .stream_tokens_per_second
+                .with_label_values(&labels)
+                .observe(self.output_tokens as f64 / generating);
         }
     }
 
@@ -245,27 +557,89 @@ impl UsageTrackerState {
                 .token_total
                 .with_label_values(&[&self.router, &self.channel, &model_lower, "output"])
                 .inc_by(self.output_tokens);
+            for (kind, count) in [
+                ("cache_read", self.extra.cache_read_tokens),
+                ("cache_creation", self.extra.cache_creation_tokens),
+            ] {
+                if count > 0 {
+                    self.metrics
+                        .token_total
+                        .with_label_values(&[&self.router, &self.channel, &model_lower, kind])
+                        .inc_by(count);
+                }
+            }
+            if let Some(assignment) = &self.experiment {
+                for (kind, count) in [("input", self.input_tokens), ("output", self.output_tokens)]
+                {
+                    self.metrics
+                        .experiment_tokens_total
+                        .with_label_values(&[&assignment.router, &assignment.variant, kind])
+                        .inc_by(count);
+                }
+            }
         }
 
-        self.logger.log(
-            self.request_id.as_deref(),
-            &self.team_id,
-            &self.router,
-            self.matched_rule.as_deref(),
-            &self.channel,
-            &self.model,
-            self.input_tokens,
-            self.output_tokens,
-            self.latency_ms,
-            self.fallback_triggered,
-            &self.client_info,
-        );
+        self.span.in_scope(|| {
+            self.logger.log_with_extras(
+                self.request_id.as_deref(),
+                &self.team_id,
+                &self.router,
+                self.matched_rule.as_deref(),
+                &self.channel,
+                &self.model,
+                self.input_tokens,
+                self.output_tokens,
+                self.extra.clone(),
+                self.latency_ms,
+                self.fallback_triggered,
+                &self.client_info,
+            )
+        });
     }
 }
 
+/// Stop reason of a chat response or of the stream event that ends one:
+/// OpenAI `finish_reason`, Anthropic `stop_reason`, Gemini `finishReason`.
+fn finish_reason(json: &Value) -> Option<&str> {
+    json.pointer("/choices/0/finish_reason")
+        .or_else(|| json.pointer("/delta/stop_reason"))
+        .or_else(|| json.get("stop_reason"))
+        .or_else(|| json.pointer("/candidates/0/finishReason"))
+        .and_then(Value::as_str)
+}
+
+/// Whether a streamed event carries generated text or tool-call output, as
+/// opposed to role headers, usage-only events and pings.
+fn carries_output(json: &Value) -> bool {
+    let non_empty = |value: Option<&Value>| match value {
+        Some(Value::String(text)) => !text.is_empty(),
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    };
+    if let Some(choices) = json.get("choices").and_then(Value::as_array) {
+        return choices.iter().any(|choice| {
+            let delta = choice.get("delta");
+            ["content", "reasoning_content", "tool_calls"]
+                .iter()
+                .any(|key| non_empty(delta.and_then(|d| d.get(key))))
+        });
+    }
+    match json.get("type").and_then(Value::as_str) {
+        Some("content_block_delta") => return true,
+        Some(kind) if kind.starts_with("response.") => return kind.ends_with(".delta"),
+        _ => {}
+    }
+    json.pointer("/candidates/0/content/parts")
+        .and_then(Value::as_array)
+        .is_some_and(|parts| !parts.is_empty())
+}
+
 pub struct UsageStream<S> {
     inner: S,
     state: Arc<Mutex<UsageTrackerState>>,
+    /// Parse chunks as SSE for usage; binary bodies are only counted as done.
+    parse_events: bool,
+    _active: GaugeGuard,
 }
 
 impl<S, E> Stream for UsageStream<S>
@@ -278,7 +652,9 @@ where
         let poll = Pin::new(&mut self.inner).poll_next(cx);
         match poll {
             Poll::Ready(Some(Ok(bytes))) => {
-                if let Ok(mut state) = self.state.lock() {
+                if self.parse_events
+                    && let Ok(mut state) = self.state.lock()
+                {
                     state.process_chunk(&bytes, true);
                 }
                 Poll::Ready(Some(Ok(bytes)))
@@ -286,6 +662,9 @@ where
             Poll::Ready(None) => {
                 // Stream finished
                 if let Ok(state) = self.state.lock() {
+                    if self.parse_events {
+                        state.observe_throughput();
+                    }
                     state.flush();
                 }
                 Poll::Ready(None)
@@ -316,10 +695,13 @@ pub async fn wrap_response(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.contains("text/event-stream"))
         .unwrap_or(false);
+    // Audio and other binary bodies stream through unbuffered; the request
+    // is logged (without tokens) once the body has been sent.
+    let is_binary = crate::utils::is_binary_body(response.headers());
 
     let (parts, body) = response.into_parts();
 
-    if is_sse {
+    if is_sse || is_binary {
         let mut tracker = UsageTrackerState::new(
             team_id,
             request_id,
@@ -333,16 +715,20 @@ pub async fn wrap_response(
             fallback_triggered,
         );
         tracker.client_info = client_info;
+        tracker.extra.stream = true;
+        let active = GaugeGuard::new(tracker.metrics.active_streams.clone());
         let state = Arc::new(Mutex::new(tracker));
         let stream = body.into_data_stream();
         let usage_stream = UsageStream {
             inner: stream,
             state,
+            parse_events: is_sse,
+            _active: active,
         };
         Response::from_parts(parts, Body::from_stream(usage_stream))
     } else {
-        // Non-SSE: read full body
-        let bytes = match axum::body::to_bytes(body, 10 * 1024 * 1024).await {
+        // Non-SSE: read full body (already held under the response size cap)
+        let bytes = match axum::body::to_bytes(body, usize::MAX).await {
             Ok(b) => b,
             Err(_) => return Response::from_parts(parts, Body::empty()), // Should not happen often
         };
@@ -417,6 +803,54 @@ mod tests {
         assert_eq!(tracker.output_tokens, 10);
     }
 
+    #[test]
+    fn test_extract_usage_total_only_counts_as_input() {
+        let (_dir, logger) = create_test_logger();
+        let metrics = create_test_metrics();
+
+        let mut tracker = UsageTrackerState::new(
+            "team1".to_string(),
+            None,
+            "r1".to_string(),
+            None,
+            "c1".to_string(),
+            "jina-embeddings-v3".to_string(),
+            logger,
+            metrics,
+            None,
+            false,
+        );
+
+        tracker.extract_usage(&serde_json::json!({"usage": {"total_tokens": 12}}));
+        assert_eq!(tracker.input_tokens, 12);
+        assert_eq!(tracker.output_tokens, 0);
+    }
+
+    #[test]
+    fn test_extract_usage_groq_stream_chunk() {
+        let (_dir, logger) = create_test_logger();
+        let metrics = create_test_metrics();
+
+        let mut tracker = UsageTrackerState::new(
+            "team1".to_string(),
+            None,
+            "r1".to_string(),
+            None,
+            "groq".to_string(),
+            "llama-3.3-70b-versatile".to_string(),
+            logger,
+            metrics,
+            None,
+            false,
+        );
+
+        tracker.process_sse_line(
+            r#"data: {"choices":[{"delta":{},"finish_reason":"stop"}],"x_groq":{"usage":{"prompt_tokens":9,"completion_tokens":4}}}"#,
+        );
+        assert_eq!(tracker.input_tokens, 9);
+        assert_eq!(tracker.output_tokens, 4);
+    }
+
     #[test]
     fn test_extract_usage_anthropic_message_start() {
         let (_dir, logger) = create_test_logger();
@@ -450,6 +884,134 @@ mod tests {
         assert_eq!(tracker.output_tokens, 1);
     }
 
+    #[test]
+    fn test_stream_throughput_is_observed_from_output_events() {
+        let (_dir, logger) = create_test_logger();
+        let metrics = create_test_metrics();
+
+        let mut tracker = UsageTrackerState::new(
+            "team1".to_string(),
+            None,
+            "r1".to_string(),
+            None,
+            "c1".to_string(),
+            "GPT-4o".to_string(),
+            logger,
+            metrics.clone(),
+            Some(100.0),
+            false,
+        );
+
+        tracker.process_sse_line(r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#);
+        assert!(tracker.first_output_at.is_none());
+        tracker.process_sse_line(r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#);
+        tracker.process_sse_line(r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#);
+        tracker.process_sse_line(
+            r#"data: {"choices":[],"usage":{"prompt_tokens":3,"completion_tokens":40}}"#,
+        );
+        let last = tracker.last_output_at.unwrap();
+        tracker.first_output_at = Some(last - std::time::Duration::from_secs(2));
+        tracker.started = last - std::time::Duration::from_secs(3);
+        tracker.observe_throughput();
+
+        let rendered = metrics.render().unwrap();
+        for line in [
+            r#"apex_stream_ttft_ms_sum{channel="c1",model="gpt-4o",router="r1"} 1100"#,
+            r#"apex_stream_tokens_per_second_sum{channel="c1",model="gpt-4o",router="r1"} 20"#,
+        ] {
+            assert!(rendered.contains(line), "missing {line} in\n{rendered}");
+        }
+    }
+
+    #[test]
+    fn test_stream_flag_and_finish_reason_are_logged() {
+        let (dir, logger) = create_test_logger();
+        let metrics = create_test_metrics();
+
+        let mut tracker = UsageTrackerState::new(
+            "team1".to_string(),
+            Some("req-9".to_string()),
+            "r1".to_string(),
+            None,
+            "claude".to_string(),
+            "claude-sonnet-4".to_string(),
+            logger,
+            metrics,
+            Some(120.0),
+            false,
+        );
+        tracker.extra.stream = true;
+        tracker.process_sse_line(r#"data: {"type":"message_delta","delta":{"stop_reason":"max_tokens"},"usage":{"output_tokens":8}}"#);
+        tracker.flush();
+
+        let db = Database::new(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        let (records, _) = db
+            .get_usage_records(None, None, None, None, None, None, None, 10, 0)
+            .unwrap();
+        assert_eq!(records[0].request_id.as_deref(), Some("req-9"));
+        assert_eq!(records[0].team_id, "team1");
+        assert_eq!(records[0].status_code, Some(200));
+        assert_eq!(records[0].latency_ms, Some(120.0));
+        assert!(records[0].stream);
+        assert_eq!(records[0].finish_reason.as_deref(), Some("max_tokens"));
+    }
+
+    #[test]
+    fn test_cache_tokens_are_logged_and_counted() {
+        let (dir, logger) = create_test_logger();
+        let metrics = create_test_metrics();
+
+        let mut tracker = UsageTrackerState::new(
+            "team1".to_string(),
+            None,
+            "r1".to_string(),
+            None,
+            "claude".to_string(),
+            "claude-sonnet-4".to_string(),
+            logger,
+            metrics.clone(),
+            None,
+            false,
+        );
+        tracker.process_sse_line(
+            r#"data: {"type":"message_start","message":{"usage":{"input_tokens":12,"cache_creation_input_tokens":0,"cache_read_input_tokens":2048,"output_tokens":1}}}"#,
+        );
+        tracker.process_sse_line(
+            r#"data: {"type":"message_delta","usage":{"output_tokens":30,"cache_read_input_tokens":2048}}"#,
+        );
+        assert_eq!(tracker.extra.cache_read_tokens, 2048);
+        tracker.flush();
+
+        let db = Database::new(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        let (records, _) = db
+            .get_usage_records(None, None, None, None, None, None, None, 10, 0)
+            .unwrap();
+        assert_eq!(records[0].cache_read_tokens, 2048);
+        assert_eq!(records[0].cache_creation_tokens, 0);
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(
+            r#"apex_token_total{channel="claude",model="claude-sonnet-4",router="r1",type="cache_read"} 2048"#
+        ));
+        assert!(!rendered.contains(r#"type="cache_creation""#));
+
+        let mut openai = UsageTrackerState::new(
+            "team1".to_string(),
+            None,
+            "r1".to_string(),
+            None,
+            "c1".to_string(),
+            "gpt-4o".to_string(),
+            create_test_logger().1,
+            metrics,
+            None,
+            false,
+        );
+        openai.extract_usage(&serde_json::json!({
+            "usage": {"prompt_tokens": 2100, "completion_tokens": 5, "prompt_tokens_details": {"cached_tokens": 1920}}
+        }));
+        assert_eq!(openai.extra.cache_read_tokens, 1920);
+    }
+
     #[test]
     fn test_flush_logs_success_even_without_usage_tokens() {
         let (dir, logger) = create_test_logger();
@@ -654,4 +1216,80 @@ mod tests {
             "usage should be persisted in SQLite"
         );
     }
+
+    #[test]
+    fn test_usage_cost_uses_first_matching_price() {
+        let (dir, logger) = create_test_logger();
+        logger.set_pricing(vec![
+            ModelPrice {
+                model: "gpt-4o-mini".to_string(),
+                input_per_1k: 0.15,
+                output_per_1k: 0.6,
+                per_image: 0.0,
+            },
+            ModelPrice {
+                model: "gpt-4o*".to_string(),
+                input_per_1k: 2.5,
+                output_per_1k: 10.0,
+                per_image: 0.0,
+            },
+        ]);
+        let client = crate::utils::ClientInfo::default();
+        for model in ["GPT-4o-mini", "gpt-4o-2024-08-06", "claude-sonnet"] {
+            logger.log(
+                None, "team1", "r1", None, "c1", model, 1000, 500, None, false, &client,
+            );
+        }
+
+        let db = Database::new(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        let rows = db
+            .get_usage_cost(&crate::database::UsageRecordQuery::default(), "model")
+            .unwrap();
+        let cost = |model: &str| rows.iter().find(|row| row.key == model).unwrap();
+        assert!((cost("gpt-4o-2024-08-06").cost - 7.5).abs() < 1e-9);
+        assert!((cost("gpt-4o-mini").cost - 0.45).abs() < 1e-9);
+        assert_eq!(cost("claude-sonnet").cost, 0.0);
+        assert_eq!(cost("claude-sonnet").unpriced_requests, 1);
+        assert_eq!(rows[0].key, "gpt-4o-2024-08-06");
+        assert!(db.get_usage_cost(&Default::default(), "end_user").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_image_responses_are_counted_and_priced_per_image() {
+        let (dir, logger) = create_test_logger();
+        logger.set_pricing(vec![ModelPrice {
+            model: "dall-e-3".to_string(),
+            input_per_1k: 0.0,
+            output_per_1k: 0.0,
+            per_image: 0.04,
+        }]);
+        let response = Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"created":1,"data":[{"url":"https://a"},{"url":"https://b"}]}"#,
+            ))
+            .unwrap();
+        wrap_response(
+            response,
+            None,
+            "team1".to_string(),
+            "r1".to_string(),
+            None,
+            "c1".to_string(),
+            "dall-e-3".to_string(),
+            logger,
+            create_test_metrics(),
+            None,
+            false,
+            crate::utils::ClientInfo::default(),
+        )
+        .await;
+
+        let db = Database::new(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        let rows = db
+            .get_usage_cost(&crate::database::UsageRecordQuery::default(), "model")
+            .unwrap();
+        assert_eq!(rows[0].images, 2);
+        assert!((rows[0].cost - 0.08).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,221 @@
+//! Usage shipping (`usage_shipping`) and collection (`usage_collector`).
+//!
+//! Behind a load balancer each gateway only sees its own traffic. A shipper
+//! batches every usage record and sends it to a central collector, either
+//! another gateway's `POST /admin/usage/ingest` or a Redis stream that a
+//! gateway with `usage_collector` drains, so `apex usage` against the
+//! collector's database covers the whole fleet. Batches are retried until
+//! the collector accepts them; records are only dropped (with a warning)
+//! when the in-memory queue overflows.
+
+use crate::config::{UsageCollector, UsageShipping, UsageShippingTarget};
+use crate::database::{Database, UsageEntry};
+use redis::aio::MultiplexedConnection;
+use redis::streams::StreamReadReply;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Records waiting to be shipped before new ones are dropped.
+const QUEUE_CAPACITY: usize = 10_000;
+/// Wait between attempts when the collector is unreachable.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Records read from the stream per round trip.
+const COLLECT_BATCH: usize = 500;
+/// How long one stream read waits for new records.
+const COLLECT_BLOCK_MS: usize = 5000;
+
+pub struct UsageShipper {
+    instance: String,
+    queue: mpsc::Sender<UsageEntry>,
+}
+
+impl UsageShipper {
+    /// Start shipping in the background (needs a tokio runtime).
+    pub fn spawn(config: &UsageShipping) -> Self {
+        let (queue, records) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(ship_loop(config.clone(), records));
+        Self {
+            instance: config.instance.clone().unwrap_or_else(host_name),
+            queue,
+        }
+    }
+
+    /// Name recorded with this instance's records.
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    pub fn send(&self, entry: UsageEntry) {
+        if self.queue.try_send(entry).is_err() {
+            tracing::warn!("Usage shipping queue full, dropping a usage record");
+        }
+    }
+}
+
+async fn ship_loop(config: UsageShipping, mut records: mpsc::Receiver<UsageEntry>) {
+    let client = reqwest::Client::new();
+    let mut redis = None;
+    let flush_interval = Duration::from_millis(config.flush_interval_ms);
+    let batch_size = config.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut open = true;
+    while open {
+        // Wait for a first record, then gather more for up to one interval.
+        match records.recv().await {
+            Some(entry) => batch.push(entry),
+            None => break,
+        }
+        let deadline = tokio::time::Instant::now() + flush_interval;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, records.recv()).await {
+                Ok(Some(entry)) => batch.push(entry),
+                Ok(None) => {
+                    open = false;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        while let Err(e) = ship(&config.target, &client, &mut redis, &batch).await {
+            tracing::warn!("Failed to ship {} usage records: {:#}", batch.len(), e);
+            redis = None;
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+        batch.clear();
+    }
+}
+
+async fn ship(
+    target: &UsageShippingTarget,
+    client: &reqwest::Client,
+    redis: &mut Option<MultiplexedConnection>,
+    batch: &[UsageEntry],
+) -> anyhow::Result<()> {
+    match target {
+        UsageShippingTarget::Http { url, api_key } => {
+            let mut request = client
+                .post(url)
+                .timeout(REQUEST_TIMEOUT)
+                .json(&json!({"records": batch}));
+            if let Some(api_key) = api_key {
+                request = request.bearer_auth(api_key);
+            }
+            request.send().await?.error_for_status()?;
+        }
+        UsageShippingTarget::RedisStream {
+            url,
+            stream,
+            max_len,
+        } => {
+            if redis.is_none() {
+                let connection = redis::Client::open(url.as_str())?
+                    .get_multiplexed_async_connection()
+                    .await?;
+                *redis = Some(connection);
+            }
+            let connection = redis.as_mut().expect("connected above");
+            let mut pipe = redis::pipe();
+            for entry in batch {
+                let command = pipe.cmd("XADD").arg(stream);
+                if let Some(max_len) = max_len {
+                    command.arg("MAXLEN").arg("~").arg(max_len);
+                }
+                command
+                    .arg("*")
+                    .arg("record")
+                    .arg(serde_json::to_string(entry)?)
+                    .ignore();
+            }
+            tokio::time::timeout(REQUEST_TIMEOUT, pipe.query_async::<()>(connection)).await??;
+        }
+    }
+    Ok(())
+}
+
+/// Drain the `usage_collector` stream into `db`, reconnecting as needed.
+pub async fn collect(config: UsageCollector, db: Arc<Database>) {
+    loop {
+        if let Err(e) = collect_stream(&config, &db).await {
+            tracing::warn!(
+                "Usage collector on stream '{}' failed: {:#}",
+                config.stream,
+                e
+            );
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn collect_stream(config: &UsageCollector, db: &Arc<Database>) -> anyhow::Result<()> {
+    let mut connection = redis::Client::open(config.url.as_str())?
+        .get_multiplexed_async_connection()
+        .await?;
+    let created: redis::RedisResult<()> = redis::cmd("XGROUP")
+        .arg("CREATE")
+        .arg(&config.stream)
+        .arg(&config.group)
+        .arg("0")
+        .arg("MKSTREAM")
+        .query_async(&mut connection)
+        .await;
+    if let Err(e) = created
+        && e.code() != Some("BUSYGROUP")
+    {
+        return Err(e.into());
+    }
+    let consumer = host_name();
+    // Records read but not acknowledged before a restart come first.
+    let mut from = "0";
+    loop {
+        let reply: StreamReadReply = redis::cmd("XREADGROUP")
+            .arg("GROUP")
+            .arg(&config.group)
+            .arg(&consumer)
+            .arg("COUNT")
+            .arg(COLLECT_BATCH)
+            .arg("BLOCK")
+            .arg(COLLECT_BLOCK_MS)
+            .arg("STREAMS")
+            .arg(&config.stream)
+            .arg(from)
+            .query_async(&mut connection)
+            .await?;
+        let ids: Vec<_> = reply.keys.into_iter().flat_map(|key| key.ids).collect();
+        if ids.is_empty() {
+            from = ">";
+            continue;
+        }
+        let entries: Vec<UsageEntry> = ids
+            .iter()
+            .filter_map(|id| {
+                let record: String = id.get("record")?;
+                serde_json::from_str(&record)
+                    .inspect_err(|e| {
+                        tracing::warn!("Skipping malformed usage record {}: {}", id.id, e)
+                    })
+                    .ok()
+            })
+            .collect();
+        let db = db.clone();
+        tokio::task::spawn_blocking(move || db.insert_usage(&entries)).await??;
+        let mut ack = redis::cmd("XACK");
+        ack.arg(&config.stream).arg(&config.group);
+        for id in &ids {
+            ack.arg(&id.id);
+        }
+        ack.query_async::<()>(&mut connection).await?;
+    }
+}
+
+/// This machine's name, for records that name no instance.
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "apex".to_string())
+}
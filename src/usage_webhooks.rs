@@ -0,0 +1,200 @@
+//! Usage webhooks (`usage_webhooks`).
+//!
+//! Every usage record is POSTed as JSON (the same fields as a
+//! `usage_records` row) to each configured URL, so billing systems can follow
+//! usage without polling the database or exports. Each webhook has its own
+//! queue and delivers in order; a failed delivery is retried with
+//! exponential backoff up to `max_retries` times, then dropped with a
+//! warning.
+//!
+//! With a `secret`, requests carry `X-Apex-Timestamp` (unix seconds) and
+//! `X-Apex-Signature: sha256=<hex>`, the HMAC-SHA256 of
+//! `"<timestamp>.<body>"`, so receivers can reject forged or replayed calls.
+
+use crate::config::UsageWebhook;
+use crate::database::UsageEntry;
+use ring::hmac;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Records waiting for delivery before new ones are dropped.
+const QUEUE_CAPACITY: usize = 10_000;
+/// First retry delay, doubled on every further attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub const SIGNATURE_HEADER: &str = "x-apex-signature";
+pub const TIMESTAMP_HEADER: &str = "x-apex-timestamp";
+
+pub struct UsageWebhooks {
+    queues: Vec<(String, mpsc::Sender<UsageEntry>)>,
+}
+
+impl UsageWebhooks {
+    /// Start one delivery task per webhook (needs a tokio runtime).
+    pub fn spawn(webhooks: &[UsageWebhook]) -> Self {
+        let client = reqwest::Client::new();
+        let queues = webhooks
+            .iter()
+            .map(|webhook| {
+                let (queue, records) = mpsc::channel(QUEUE_CAPACITY);
+                tokio::spawn(deliver_loop(webhook.clone(), client.clone(), records));
+                (webhook.url.clone(), queue)
+            })
+            .collect();
+        Self { queues }
+    }
+
+    pub fn send(&self, entry: &UsageEntry) {
+        for (url, queue) in &self.queues {
+            if queue.try_send(entry.clone()).is_err() {
+                tracing::warn!("Usage webhook queue for {url} full, dropping a usage record");
+            }
+        }
+    }
+}
+
+async fn deliver_loop(
+    webhook: UsageWebhook,
+    client: reqwest::Client,
+    mut records: mpsc::Receiver<UsageEntry>,
+) {
+    while let Some(entry) = records.recv().await {
+        let body = match serde_json::to_vec(&entry) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to encode usage record for webhook: {e}");
+                continue;
+            }
+        };
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        while let Err(e) = deliver(&webhook, &client, &body).await {
+            if attempt >= webhook.max_retries {
+                tracing::warn!(
+                    "Dropping usage record for webhook {} after {} attempts: {:#}",
+                    webhook.url,
+                    attempt + 1,
+                    e
+                );
+                break;
+            }
+            attempt += 1;
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+async fn deliver(
+    webhook: &UsageWebhook,
+    client: &reqwest::Client,
+    body: &[u8],
+) -> anyhow::Result<()> {
+    let mut request = client
+        .post(&webhook.url)
+        .timeout(Duration::from_millis(webhook.timeout_ms))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_vec());
+    if let Some(secret) = &webhook.secret {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        request = request
+            .header(SIGNATURE_HEADER, sign(secret, &timestamp, body))
+            .header(TIMESTAMP_HEADER, timestamp);
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// `sha256=<hex>` HMAC of `"<timestamp>.<body>"` under `secret`.
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(timestamp.as_bytes());
+    context.update(b".");
+    context.update(body);
+    let tag = context.sign();
+    let hex: String = tag.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use axum::body::Bytes;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        // echo -n '1700000000.{}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", "1700000000", b"{}"),
+            "sha256=b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163"
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_endpoint_accepts_and_signs_deliveries() {
+        let received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>> = Arc::default();
+        let calls = Arc::new(Mutex::new(0));
+        let app = axum::Router::new().route(
+            "/usage",
+            post({
+                let received = received.clone();
+                let calls = calls.clone();
+                move |headers: HeaderMap, body: Bytes| async move {
+                    let mut calls = calls.lock().unwrap();
+                    *calls += 1;
+                    if *calls == 1 {
+                        return (StatusCode::SERVICE_UNAVAILABLE, Json(()));
+                    }
+                    received.lock().unwrap().push((headers, body));
+                    (StatusCode::OK, Json(()))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let webhooks = UsageWebhooks::spawn(&[UsageWebhook {
+            url: format!("http://{addr}/usage"),
+            secret: Some("s3cret".to_string()),
+            max_retries: 3,
+            timeout_ms: 1000,
+        }]);
+        let entry = UsageEntry {
+            team_id: "team-a".to_string(),
+            model: "gpt-4o".to_string(),
+            input_tokens: 12,
+            output_tokens: 34,
+            cost: Some(0.5),
+            latency_ms: Some(120.0),
+            ..UsageEntry::default()
+        };
+        webhooks.send(&entry);
+
+        for _ in 0..100 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let received = received.lock().unwrap();
+        let (headers, body) = received.first().expect("delivered after a retry");
+        let record: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(record["team_id"], "team-a");
+        assert_eq!(record["model"], "gpt-4o");
+        assert_eq!(record["output_tokens"], 34);
+        assert_eq!(record["cost"], 0.5);
+        let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign("s3cret", timestamp, body)
+        );
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+}
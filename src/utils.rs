@@ -45,6 +45,17 @@ pub fn mask_secret(value: &str) -> String {
     format!("{prefix}…{suffix}")
 }
 
+/// Stable, non-reversible identifier for an API key (FNV-1a, hex), used to
+/// key persisted per-key statistics without storing the secret itself.
+pub fn key_fingerprint(key: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
 /// A normalized label for the calling tool/client, plus the raw User-Agent.
 ///
 /// Derived from request headers so the dashboard can break usage down by tool
@@ -54,6 +65,50 @@ pub fn mask_secret(value: &str) -> String {
 pub struct ClientInfo {
     pub client: Option<String>,
     pub user_agent: Option<String>,
+    /// End user behind a shared team key, from the request body (OpenAI
+    /// `user` / Anthropic `metadata.user_id`). Filled in once the body is read.
+    pub end_user: Option<String>,
+}
+
+const END_USER_MAX_LEN: usize = 128;
+
+/// Extract the end-user identifier from a JSON request body: OpenAI `user`
+/// or Anthropic `metadata.user_id`. Truncated to keep storage bounded.
+pub fn extract_end_user(body: &[u8]) -> Option<String> {
+    let json = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    json.get("user")
+        .and_then(|v| v.as_str())
+        .or_else(|| {
+            json.get("metadata")
+                .and_then(|m| m.get("user_id"))
+                .and_then(|v| v.as_str())
+        })
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.chars().take(END_USER_MAX_LEN).collect())
+}
+
+/// Whether a body is opaque bytes (audio, images, files) rather than JSON,
+/// text or an SSE stream. Such bodies are streamed through untouched instead
+/// of being buffered for usage extraction, caching or transcripts.
+pub fn is_binary_body(headers: &HeaderMap) -> bool {
+    let Some(content_type) = header_lower(headers, axum::http::header::CONTENT_TYPE.as_str())
+    else {
+        return false;
+    };
+    !(content_type.contains("json") || content_type.starts_with("text/"))
+}
+
+/// Whether a response is an SSE stream.
+pub fn is_event_stream(headers: &HeaderMap) -> bool {
+    header_lower(headers, axum::http::header::CONTENT_TYPE.as_str())
+        .is_some_and(|content_type| content_type.contains("text/event-stream"))
+}
+
+/// Whether a request carries a `multipart/form-data` upload.
+pub fn is_multipart(headers: &HeaderMap) -> bool {
+    header_lower(headers, axum::http::header::CONTENT_TYPE.as_str())
+        .is_some_and(|content_type| content_type.starts_with("multipart/form-data"))
 }
 
 fn header_lower(headers: &HeaderMap, name: &str) -> Option<String> {
@@ -138,6 +193,7 @@ pub fn classify_client(headers: &HeaderMap) -> ClientInfo {
         } else {
             Some(ua_raw.chars().take(256).collect())
         },
+        end_user: None,
     }
 }
 
@@ -153,6 +209,36 @@ mod tests {
         classify_client(&headers)
     }
 
+    #[test]
+    fn test_extract_end_user() {
+        assert_eq!(
+            extract_end_user(br#"{"model":"gpt-4","user":"alice"}"#).as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            extract_end_user(br#"{"metadata":{"user_id":"bob"}}"#).as_deref(),
+            Some("bob")
+        );
+        assert_eq!(extract_end_user(br#"{"user":"  "}"#), None);
+        assert_eq!(extract_end_user(b"not json"), None);
+    }
+
+    #[test]
+    fn test_body_content_types() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(axum::http::header::CONTENT_TYPE, value.parse().unwrap());
+            headers
+        };
+        assert!(is_binary_body(&headers("audio/mpeg")));
+        assert!(is_binary_body(&headers("application/octet-stream")));
+        assert!(!is_binary_body(&headers("application/json; charset=utf-8")));
+        assert!(!is_binary_body(&headers("text/event-stream")));
+        assert!(!is_binary_body(&HeaderMap::new()));
+        assert!(is_multipart(&headers("multipart/form-data; boundary=x")));
+        assert!(!is_multipart(&headers("application/json")));
+    }
+
     #[test]
     fn test_classify_client() {
         assert_eq!(
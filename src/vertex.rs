@@ -0,0 +1,309 @@
+//! Google Vertex AI channels (`provider_type: "vertex"`).
+//!
+//! The channel's `api_key` (usually read through `api_key_file`) holds a
+//! service-account JSON key, or a ready-made OAuth access token.
+//! Service-account keys are exchanged at the key's `token_uri` for access
+//! tokens (JWT bearer grant signed with the key), which are cached and
+//! refreshed shortly before they expire.
+//!
+//! Requests go to the regional endpoint of the channel's `vertex.location`
+//! unless `base_url` is set. Chat is sent to Gemini's native
+//! `generateContent` under
+//! `v1/projects/{project}/locations/{location}/publishers/google/models/{model}`;
+//! other OpenAI paths go to Vertex's OpenAI-compatible `endpoints/openapi`.
+
+use crate::config::Channel;
+use anyhow::{Context, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use ring::rand::SystemRandom;
+use ring::signature::{RSA_PKCS1_SHA256, RsaKeyPair};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_LOCATION: &str = "us-central1";
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+/// Lifetime requested for the signed assertion (Google's maximum).
+const ASSERTION_TTL_SECS: i64 = 3600;
+/// Tokens are refreshed this long before they expire.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    token_uri: Option<String>,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+fn service_account(credentials: &str) -> anyhow::Result<Option<ServiceAccount>> {
+    if !credentials.trim_start().starts_with('{') {
+        return Ok(None);
+    }
+    let account =
+        serde_json::from_str(credentials).context("invalid Vertex service-account key")?;
+    Ok(Some(account))
+}
+
+/// Whether `credentials` is a service-account key rather than an access token.
+pub fn is_service_account(credentials: &str) -> bool {
+    credentials.trim_start().starts_with('{')
+}
+
+/// Project requests are billed to: `vertex.project_id`, else the one named
+/// in the service-account key.
+pub fn project_id(channel: &Channel) -> anyhow::Result<String> {
+    if let Some(project) = channel.vertex.as_ref().and_then(|v| v.project_id.clone()) {
+        return Ok(project);
+    }
+    service_account(&channel.api_key)?
+        .and_then(|account| account.project_id)
+        .ok_or_else(|| {
+            anyhow!(
+                "Vertex channel '{}' needs vertex.project_id or a service-account key with project_id",
+                channel.name
+            )
+        })
+}
+
+pub fn location(channel: &Channel) -> &str {
+    channel
+        .vertex
+        .as_ref()
+        .map(|v| v.location.as_str())
+        .unwrap_or(DEFAULT_LOCATION)
+}
+
+/// `base_url`, or the endpoint of the channel's region when it is empty.
+pub fn endpoint(channel: &Channel, base_url: &str) -> String {
+    if !base_url.trim().is_empty() {
+        return base_url.trim_end_matches('/').to_string();
+    }
+    match location(channel) {
+        "global" => "https://aiplatform.googleapis.com".to_string(),
+        region => format!("https://{region}-aiplatform.googleapis.com"),
+    }
+}
+
+fn location_path(channel: &Channel) -> anyhow::Result<String> {
+    Ok(format!(
+        "v1/projects/{}/locations/{}",
+        project_id(channel)?,
+        location(channel)
+    ))
+}
+
+/// Path of a Gemini model method (`generateContent`, …).
+pub fn model_path(channel: &Channel, model: &str, method: &str) -> anyhow::Result<String> {
+    Ok(format!(
+        "{}/publishers/google/models/{model}:{method}",
+        location_path(channel)?
+    ))
+}
+
+/// Path of an OpenAI-style request on the OpenAI-compatible endpoint.
+pub fn openapi_path(channel: &Channel, path: &str) -> anyhow::Result<String> {
+    let path = path.trim_start_matches('/');
+    let path = path.strip_prefix("v1/").unwrap_or(path);
+    Ok(format!(
+        "{}/endpoints/openapi/{path}",
+        location_path(channel)?
+    ))
+}
+
+/// Access tokens of service-account keys, keyed by account.
+#[derive(Default)]
+pub struct TokenCache {
+    /// Held across an exchange, so concurrent requests wait for one token.
+    tokens: Mutex<HashMap<String, CachedToken>>,
+}
+
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+impl TokenCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Access token for `credentials`: the credentials themselves when they
+    /// are a token, otherwise a cached or freshly exchanged one.
+    pub async fn access_token(
+        &self,
+        client: &reqwest::Client,
+        credentials: &str,
+    ) -> anyhow::Result<String> {
+        let Some(account) = service_account(credentials)? else {
+            return Ok(credentials.trim().to_string());
+        };
+        let mut tokens = self.tokens.lock().await;
+        if let Some(cached) = tokens.get(&account.client_email)
+            && cached.refresh_at > Instant::now()
+        {
+            return Ok(cached.token.clone());
+        }
+        let (token, expires_in) = exchange(client, &account).await?;
+        tokens.insert(
+            account.client_email.clone(),
+            CachedToken {
+                token: token.clone(),
+                refresh_at: Instant::now()
+                    + Duration::from_secs(expires_in).saturating_sub(REFRESH_MARGIN),
+            },
+        );
+        Ok(token)
+    }
+}
+
+/// Trade a signed assertion for `(access_token, expires_in)`.
+async fn exchange(
+    client: &reqwest::Client,
+    account: &ServiceAccount,
+) -> anyhow::Result<(String, u64)> {
+    let token_uri = account.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+    let assertion = assertion(account, token_uri, chrono::Utc::now().timestamp())?;
+    let form = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer")
+        .append_pair("assertion", &assertion)
+        .finish();
+    let response = client
+        .post(token_uri)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(form)
+        .send()
+        .await
+        .with_context(|| format!("token request to {token_uri} failed"))?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        bail!(
+            "token exchange for {} returned {status}: {}",
+            account.client_email,
+            body["error_description"].as_str().unwrap_or_default()
+        );
+    }
+    let token = body["access_token"]
+        .as_str()
+        .ok_or_else(|| anyhow!("token response has no access_token"))?;
+    Ok((
+        token.to_string(),
+        body["expires_in"].as_u64().unwrap_or(3600),
+    ))
+}
+
+/// RS256-signed JWT asserting `account` for the cloud-platform scope.
+fn assertion(account: &ServiceAccount, audience: &str, now: i64) -> anyhow::Result<String> {
+    let header = json!({"alg": "RS256", "typ": "JWT"});
+    let claims = json!({
+        "iss": account.client_email,
+        "scope": SCOPE,
+        "aud": audience,
+        "iat": now,
+        "exp": now + ASSERTION_TTL_SECS,
+    });
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let key = RsaKeyPair::from_pkcs8(&pem_to_der(&account.private_key)?)
+        .map_err(|e| anyhow!("invalid service-account private key: {e}"))?;
+    let mut signature = vec![0; key.public().modulus_len()];
+    key.sign(
+        &RSA_PKCS1_SHA256,
+        &SystemRandom::new(),
+        signing_input.as_bytes(),
+        &mut signature,
+    )
+    .map_err(|_| anyhow!("failed to sign the token request"))?;
+    Ok(format!(
+        "{signing_input}.{}",
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+/// DER bytes of a PEM `PRIVATE KEY` block.
+fn pem_to_der(pem: &str) -> anyhow::Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+        .collect();
+    STANDARD
+        .decode(body)
+        .context("service-account private key is not valid PEM")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VertexSettings;
+
+    fn channel(api_key: &str, vertex: Option<VertexSettings>) -> Channel {
+        let mut channel: Channel = serde_json::from_value(json!({
+            "name": "vertex",
+            "provider_type": "vertex",
+            "base_url": "",
+            "api_key": api_key
+        }))
+        .unwrap();
+        channel.vertex = vertex;
+        channel
+    }
+
+    #[test]
+    fn builds_regional_publisher_and_openapi_paths() {
+        let key = json!({
+            "client_email": "apex@proj.iam.gserviceaccount.com",
+            "private_key": "",
+            "project_id": "proj"
+        })
+        .to_string();
+        let vertex = channel(&key, None);
+        assert_eq!(
+            endpoint(&vertex, ""),
+            "https://us-central1-aiplatform.googleapis.com"
+        );
+        assert_eq!(
+            model_path(&vertex, "gemini-2.0-flash", "generateContent").unwrap(),
+            "v1/projects/proj/locations/us-central1/publishers/google/models/gemini-2.0-flash:generateContent"
+        );
+
+        let settings = VertexSettings {
+            project_id: Some("other".to_string()),
+            location: "global".to_string(),
+        };
+        let global = channel("ya29.token", Some(settings));
+        assert_eq!(endpoint(&global, ""), "https://aiplatform.googleapis.com");
+        assert_eq!(
+            endpoint(&global, "http://127.0.0.1:9/"),
+            "http://127.0.0.1:9"
+        );
+        assert_eq!(
+            openapi_path(&global, "/v1/embeddings").unwrap(),
+            "v1/projects/other/locations/global/endpoints/openapi/embeddings"
+        );
+
+        let err = project_id(&channel("ya29.token", None)).unwrap_err();
+        assert!(err.to_string().contains("vertex.project_id"));
+    }
+
+    #[tokio::test]
+    async fn access_tokens_pass_through_unchanged() {
+        let cache = TokenCache::new();
+        let token = cache
+            .access_token(&reqwest::Client::new(), " ya29.token\n")
+            .await
+            .unwrap();
+        assert_eq!(token, "ya29.token");
+        assert!(!is_service_account("ya29.token"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Connection warm-up: channel hosts are resolved ahead of time and a pooled
+//! connection to each is opened (and kept open), so the first request after a
+//! deploy or an idle period doesn't pay for DNS, TCP and TLS setup.
+
+use crate::config::{Channel, ProviderType, WarmupConfig};
+use crate::server::AppState;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Resolver for the upstream clients. Hosts resolved by the last warm-up are
+/// answered from memory; any other host is looked up as usual.
+#[derive(Debug, Default)]
+pub struct DnsCache {
+    hosts: RwLock<HashMap<String, Vec<SocketAddr>>>,
+}
+
+impl DnsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Addresses served for `host` from now on.
+    pub fn insert(&self, host: &str, addrs: Vec<SocketAddr>) {
+        self.hosts.write().unwrap().insert(host.to_string(), addrs);
+    }
+
+    /// Keep only `hosts`, so hosts no channel uses anymore are looked up
+    /// normally again.
+    fn retain(&self, hosts: &HashSet<String>) {
+        self.hosts
+            .write()
+            .unwrap()
+            .retain(|host, _| hosts.contains(host));
+    }
+
+    pub fn clear(&self) {
+        self.hosts.write().unwrap().clear();
+    }
+
+    fn len(&self) -> usize {
+        self.hosts.read().unwrap().len()
+    }
+}
+
+impl reqwest::dns::Resolve for DnsCache {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let cached = self.hosts.read().unwrap().get(name.as_str()).cloned();
+        Box::pin(async move {
+            let addrs = match cached {
+                Some(addrs) => addrs,
+                None => lookup(name.as_str()).await?,
+            };
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Addresses for `host`; the port is filled in by the connector.
+async fn lookup(host: &str) -> std::io::Result<Vec<SocketAddr>> {
+    Ok(tokio::net::lookup_host((host, 0)).await?.collect())
+}
+
+/// Scheme, host and port of every upstream URL the channels send requests
+/// to, each with the first channel using it (its client owns the pool).
+pub fn channel_origins(channels: &[Channel]) -> Vec<(url::Url, &Channel)> {
+    let mut seen = HashSet::new();
+    let mut origins = Vec::new();
+    for channel in channels {
+        if channel.provider_type == ProviderType::Mock {
+            continue;
+        }
+        let urls = std::iter::once(channel.base_url.as_str())
+            .chain(channel.anthropic_base_url.as_deref())
+            .filter_map(|url| url::Url::parse(url.trim()).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+        for url in urls {
+            let mut origin = url.clone();
+            origin.set_path("/");
+            origin.set_query(None);
+            origin.set_fragment(None);
+            let connect_ms = channel.timeouts.as_ref().map(|t| t.connect_ms);
+            if seen.insert((origin.to_string(), connect_ms)) {
+                origins.push((origin, channel));
+            }
+        }
+    }
+    origins
+}
+
+/// Resolve every channel host into `state.dns_cache` and open a connection
+/// to each origin. Returns how many origins answered.
+pub async fn warm(state: &AppState, settings: &WarmupConfig) -> usize {
+    let channels = state.config.read().unwrap().channels.clone();
+    let origins = channel_origins(&channels);
+
+    let hosts: HashSet<String> = origins
+        .iter()
+        .filter_map(|(url, _)| match url.host()? {
+            url::Host::Domain(domain) => Some(domain.to_string()),
+            _ => None,
+        })
+        .collect();
+    let lookups = hosts.iter().map(|host| async move {
+        match lookup(host).await {
+            Ok(addrs) if !addrs.is_empty() => state.dns_cache.insert(host, addrs),
+            // Keep the last good answer through a resolver hiccup.
+            Ok(_) => tracing::warn!("Warm-up: '{}' resolved to no addresses", host),
+            Err(e) => tracing::warn!("Warm-up: failed to resolve '{}': {}", host, e),
+        }
+    });
+    futures::future::join_all(lookups).await;
+    state.dns_cache.retain(&hosts);
+
+    let timeout = Duration::from_millis(settings.timeout_ms);
+    let connects = origins.iter().map(|(origin, channel)| async move {
+        // Any answer means the connection is up and back in the pool.
+        match state
+            .client_for(channel)
+            .head(origin.clone())
+            .timeout(timeout)
+            .send()
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::warn!("Warm-up: failed to connect to {}: {}", origin, e);
+                false
+            }
+        }
+    });
+    let warmed = futures::future::join_all(connects)
+        .await
+        .into_iter()
+        .filter(|ok| *ok)
+        .count();
+    tracing::debug!(
+        "Warm-up: {} host(s) resolved, {}/{} origin(s) connected",
+        state.dns_cache.len(),
+        warmed,
+        origins.len()
+    );
+    warmed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, provider_type: ProviderType, base_url: &str) -> Channel {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "provider_type": provider_type,
+            "base_url": base_url,
+            "api_key": "k",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn channel_origins_are_deduplicated_per_host() {
+        let mut claude = channel("claude", ProviderType::Anthropic, "");
+        claude.anthropic_base_url = Some("https://api.anthropic.com/v1".to_string());
+        let channels = vec![
+            channel("a", ProviderType::Openai, "https://api.openai.com/v1"),
+            channel("b", ProviderType::Openai, "https://api.openai.com/v1/"),
+            channel("local", ProviderType::Openai, "http://127.0.0.1:8000"),
+            channel("mock", ProviderType::Mock, "mock://local"),
+            claude,
+        ];
+
+        let origins: Vec<String> = channel_origins(&channels)
+            .into_iter()
+            .map(|(origin, _)| origin.to_string())
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                "https://api.openai.com/",
+                "http://127.0.0.1:8000/",
+                "https://api.anthropic.com/"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_hosts_are_served_without_a_lookup() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|| async { "warm" });
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let cache = std::sync::Arc::new(DnsCache::new());
+        cache.insert("upstream.invalid", vec![addr]);
+        let client = reqwest::Client::builder()
+            .dns_resolver(cache)
+            .build()
+            .unwrap();
+        let body = client
+            .get(format!("http://upstream.invalid:{}/", addr.port()))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "warm");
+    }
+}
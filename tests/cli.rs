@@ -157,6 +157,24 @@ fn test_config_validate_success_and_failure() {
         .assert()
         .failure()
         .stderr(predicate::str::contains("Config file not found"));
+
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["routers"] = serde_json::json!([{
+        "name": "r1",
+        "rules": [{ "match": { "models": ["*"] }, "channels": [{ "name": "ghost" }] }]
+    }]);
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+    raw_apex_cmd()
+        .arg("-c")
+        .arg(config_str)
+        .arg("config")
+        .arg("validate")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "routers[name=r1].rules[0].channels references channel \"ghost\"",
+        ));
 }
 
 #[test]
@@ -557,6 +575,321 @@ fn test_team_lifecycle_is_noninteractive() {
     assert_eq!(json["teams"].as_array().unwrap().len(), 0);
 }
 
+#[test]
+fn test_team_add_issues_a_hashed_key_that_can_be_revoked() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    let output = apex_cmd(config_str)
+        .args([
+            "team",
+            "add",
+            "--id",
+            "leaky-team",
+            "--routers",
+            "default-router",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let added: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let key = added["data"]["api_key"].as_str().unwrap().to_string();
+    assert!(key.starts_with("sk-ap-"));
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(!content.contains(&key));
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(json["teams"][0]["api_key"], "");
+    assert!(
+        json["teams"][0]["keys"][0]["hash"]
+            .as_str()
+            .unwrap()
+            .starts_with("sha256:")
+    );
+
+    apex_cmd(config_str)
+        .arg("key")
+        .arg("revoke")
+        .arg(&key)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team 'leaky-team'"));
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert!(json["teams"][0]["keys"][0]["revoked_at"].is_string());
+    assert_eq!(json["teams"][0]["id"], "leaky-team");
+}
+
+#[test]
+fn test_key_migrate_hashes_plaintext_team_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["teams"] = serde_json::json!([{
+        "id": "old-team",
+        "api_key": "sk-old-plaintext",
+        "policy": {"allowed_routers": ["default-router"]}
+    }]);
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+    apex_cmd(config_str)
+        .args(["key", "migrate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old-team"));
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(!content.contains("sk-old-plaintext"));
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(json["teams"][0]["api_key"], "");
+    assert_eq!(json["teams"][0]["keys"][0]["name"], "legacy");
+    assert_eq!(json["teams"][0]["keys"][0]["prefix"], "sk-old-p");
+
+    apex_cmd(config_str)
+        .args(["key", "migrate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No plaintext team keys"));
+}
+
+#[test]
+fn test_key_create_rejects_overflowing_expiry() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    apex_cmd(config_str)
+        .args(["team", "add", "--id", "t", "--routers", "default-router"])
+        .assert()
+        .success();
+    apex_cmd(config_str)
+        .args(["key", "create", "--team", "t", "--expires", "99999999d"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid expiry"));
+}
+
+#[test]
+fn test_key_create_list_and_soft_revoke() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    apex_cmd(config_str)
+        .args([
+            "team",
+            "add",
+            "--id",
+            "ci-team",
+            "--routers",
+            "default-router",
+        ])
+        .assert()
+        .success();
+
+    let output = apex_cmd(config_str)
+        .args([
+            "key",
+            "create",
+            "--team",
+            "ci-team",
+            "--name",
+            "ci",
+            "--expires",
+            "30d",
+            "--models",
+            "gpt-4o-mini",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let created: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let secret = created["data"]["key"].as_str().unwrap().to_string();
+    let key_id = created["data"]["record"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Only the hash is stored.
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(!content.contains(&secret));
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    // Next to the key the team was created with.
+    let stored = &json["teams"][0]["keys"][1];
+    assert!(stored["hash"].as_str().unwrap().starts_with("sha256:"));
+    assert_eq!(stored["models"][0], "gpt-4o-mini");
+
+    apex_cmd(config_str)
+        .args(["key", "list", "--team", "ci-team"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(&key_id))
+        .stdout(predicate::str::contains("active"));
+
+    apex_cmd(config_str)
+        .args(["key", "revoke", &key_id])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team 'ci-team' revoked"));
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert!(json["teams"][0]["keys"][0]["revoked_at"].is_null());
+    assert!(json["teams"][0]["keys"][1]["revoked_at"].is_string());
+    assert!(
+        json["global"]["revoked_keys"]
+            .as_array()
+            .is_none_or(|k| k.is_empty())
+    );
+
+    apex_cmd(config_str)
+        .args(["key", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("revoked"));
+}
+
+#[test]
+fn test_test_command_reports_channel_and_response() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["data_dir"] =
+        serde_json::Value::String(temp_dir.path().join("data").to_string_lossy().into_owned());
+    json["channels"] = serde_json::json!([{
+        "name": "mock-a",
+        "provider_type": "mock",
+        "base_url": "mock://local",
+        "api_key": "",
+        "mock": { "content": "pong" }
+    }]);
+    json["routers"] = serde_json::json!([{
+        "name": "r1",
+        "rules": [{
+            "match": { "models": ["gpt-*"] },
+            "channels": [{ "name": "mock-a", "weight": 1 }]
+        }]
+    }]);
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+    apex_cmd(config_str)
+        .args([
+            "test", "--router", "r1", "--model", "gpt-4", "--prompt", "ping",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Router:        r1"))
+        .stdout(predicate::str::contains("Channel:       mock-a"))
+        .stdout(predicate::str::contains("pong"));
+
+    let output = apex_cmd(config_str)
+        .args(["test", "--model", "gpt-4", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["data"]["router"], "r1");
+    assert_eq!(result["data"]["channel"], "mock-a");
+    assert_eq!(result["data"]["status"], 200);
+
+    apex_cmd(config_str)
+        .args(["test", "--router", "r1", "--model", "claude-3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no route for model 'claude-3'"));
+}
+
+#[test]
+fn test_route_explain_prints_router_rule_and_channel() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["routers"] = serde_json::json!([
+        {
+            "name": "openai",
+            "rules": [{ "match": { "models": ["gpt-*"] }, "channels": [{ "name": "oa" }] }]
+        },
+        {
+            "name": "claude",
+            "rules": [{
+                "match": { "models": ["claude-*"] },
+                "channels": [{ "name": "primary", "weight": 3 }, { "name": "backup", "weight": 1 }],
+                "strategy": "priority"
+            }]
+        }
+    ]);
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+    apex_cmd(config_str)
+        .args(["route", "explain", "--model", "claude-3-5-sonnet"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Router:   claude"))
+        .stdout(predicate::str::contains("Channel:  primary"))
+        .stdout(predicate::str::contains("Router 'openai': no rule matches"))
+        .stdout(predicate::str::contains("75.0%"));
+
+    apex_cmd(config_str)
+        .args([
+            "route", "explain", "--model", "gpt-4", "--router", "missing",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Router 'missing' not found"));
+}
+
+#[test]
+fn test_team_show_reports_unused_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    apex_cmd(config_str)
+        .arg("team")
+        .arg("add")
+        .arg("--id")
+        .arg("idle-team")
+        .arg("--routers")
+        .arg("default-router")
+        .assert()
+        .success();
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["data_dir"] =
+        serde_json::Value::String(temp_dir.path().join("data").to_string_lossy().into_owned());
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+    let output = apex_cmd(config_str)
+        .args(["team", "show", "idle-team", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let body = stdout_json(&output);
+    assert_eq!(body["command"], "team.show");
+    assert_eq!(body["data"]["id"], "idle-team");
+    assert!(body["data"]["key_usage"].is_null());
+}
+
 #[test]
 fn test_team_subcommand_accepts_global_config_after_subcommand() {
     let temp_dir = TempDir::new().unwrap();
@@ -934,3 +1267,163 @@ fn test_minimax_anthropic_default() {
     assert!(channels[0]["protocol"].is_null());
     assert_eq!(channels[0]["base_url"], "https://api.minimax.io/anthropic");
 }
+
+#[test]
+fn test_usage_by_user_json_contract() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+
+    apex_cmd(config_str).arg("init").assert().success();
+    let mut json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    json["data_dir"] =
+        serde_json::Value::String(temp_dir.path().join("data").to_string_lossy().into_owned());
+    fs::write(&config_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+
+    let output = apex_cmd(config_str)
+        .args(["usage", "--by-user", "--json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let body = stdout_json(&output);
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["command"], "usage.by_user");
+
+    let output = apex_cmd(config_str)
+        .args(["usage", "--by-user", "--team", "demo-team", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let body = stdout_json(&output);
+    assert_eq!(body["ok"], true);
+    assert_eq!(body["data"], serde_json::json!([]));
+
+    // A bare `--end` date covers that whole day.
+    let db = apex::database::Database::new(Some(
+        temp_dir.path().join("data").to_string_lossy().into_owned(),
+    ))
+    .unwrap();
+    db.log_usage(
+        None,
+        "demo-team",
+        "r",
+        None,
+        "c",
+        "m",
+        10,
+        2,
+        0,
+        Some(0.5),
+        None,
+        false,
+        "success",
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("alice"),
+    );
+    drop(db);
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let output = apex_cmd(config_str)
+        .args(["usage", "--by-user", "--team", "demo-team", "--json"])
+        .args(["--end", &today])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let body = stdout_json(&output);
+    assert_eq!(body["data"][0]["end_user"], "alice");
+    assert_eq!(body["data"][0]["input_tokens"], 10);
+    assert_eq!(body["data"][0]["cost"], 0.5);
+}
+
+#[test]
+fn test_env_overrides_apply_on_load_and_are_not_saved() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+    apex_cmd(config_str).arg("init").assert().success();
+
+    apex_cmd(config_str)
+        .env("APEX_GLOBAL__LISTEN", "not-an-address")
+        .arg("config")
+        .arg("validate")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "global.listen = \"not-an-address\"",
+        ));
+
+    apex_cmd(config_str)
+        .env("APEX_GLOBAL__LISTEN", "0.0.0.0:8080")
+        .env("APEX_LOGGING__LEVEL", "debug")
+        .arg("channel")
+        .arg("add")
+        .arg("--name")
+        .arg("c1")
+        .arg("--provider")
+        .arg("openai")
+        .arg("--base-url")
+        .arg("u1")
+        .arg("--api-key")
+        .arg("k1")
+        .assert()
+        .success();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    assert_eq!(saved["global"]["listen"], "0.0.0.0:12356");
+    assert_eq!(saved["logging"]["level"], "info");
+    assert_eq!(saved["channels"][0]["name"], "c1");
+}
+
+#[test]
+fn test_gateway_install_systemd_writes_unit_for_current_binary() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("apex.json");
+    let config_str = config_path.to_str().unwrap();
+    let unit_path = temp_dir.path().join("units").join("apex-gw.service");
+
+    apex_cmd(config_str)
+        .args([
+            "gateway",
+            "install-systemd",
+            "--name",
+            "apex-gw",
+            "--output",
+        ])
+        .arg(&unit_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("apex init"));
+
+    apex_cmd(config_str).arg("init").assert().success();
+    apex_cmd(config_str)
+        .args([
+            "gateway",
+            "install-systemd",
+            "--name",
+            "apex-gw",
+            "--output",
+        ])
+        .arg(&unit_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote systemd unit"));
+
+    let unit = fs::read_to_string(&unit_path).unwrap();
+    let binary = fs::canonicalize(env!("CARGO_BIN_EXE_apex")).unwrap();
+    let config = fs::canonicalize(&config_path).unwrap();
+    assert!(
+        unit.contains(&format!("ExecStart={} gateway run", binary.display())),
+        "{unit}"
+    );
+    assert!(
+        unit.contains(&format!("Environment=APEX_CONFIG={}", config.display())),
+        "{unit}"
+    );
+    assert!(unit.contains("Type=notify"), "{unit}");
+}
@@ -30,17 +30,38 @@ pub fn base_config() -> Config {
                 max_attempts: 3,
                 backoff_ms: 100,
                 retry_on_status: vec![500, 502, 503, 504],
+                exponential: false,
+                jitter: false,
+                max_backoff_ms: None,
+                respect_retry_after: false,
             },
             gemini_replay: apex::config::GeminiReplay::default(),
             cors_allowed_origins: vec![],
+            ip_rate_limit: None,
+            trusted_proxies: vec![],
+            load_shedding: None,
+            revoked_keys: vec![],
+            tls: None,
+            model_aliases: Default::default(),
+            max_request_timeout_ms: None,
+            body_limits: None,
+            compression: None,
+            rate_limit: None,
+            provider_rate_limits: Default::default(),
+            rate_limit_store: None,
         },
         metrics: Metrics {
             enabled: true,
             path: "/metrics".to_string(),
+            end_user_label: false,
+            auth_token: None,
+            listen: None,
+            buckets: Default::default(),
         },
         hot_reload: HotReload {
             config_path: "config.json".to_string(),
             watch: false,
+            store: None,
         },
         logging: apex::config::Logging {
             level: "info".to_string(),
@@ -53,29 +74,69 @@ pub fn base_config() -> Config {
         routers: std::sync::Arc::new(vec![]),
         compliance: None,
         retention: Default::default(),
+        fault_injection: None,
+        access_audit: None,
+        tenants: vec![],
+        health: None,
+        warmup: None,
+        pricing: vec![],
+        secret_refs: Default::default(),
+        secrets: None,
+        includes: Default::default(),
+        env_overrides: Default::default(),
+        model_discovery: None,
+        guardrails: None,
+        usage_collector: None,
+        usage_webhooks: Vec::new(),
+        usage_shipping: None,
     }
 }
 
-pub async fn spawn_upstream_ok() -> SocketAddr {
-    spawn_upstream_status(StatusCode::OK, r#"{"id":"test","object":"chat.completion","created":1677652288,"choices":[{"index":0,"message":{"role":"assistant","content":"Hello from upstream"},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":12,"total_tokens":21}}"#).await
+/// A channel with every optional setting unset. Tests override the fields
+/// they exercise with struct-update syntax: `Channel { .., ..test_channel(..) }`.
+pub fn test_channel(name: &str, provider_type: ProviderType, base_url: &str) -> Channel {
+    Channel {
+        name: name.to_string(),
+        provider_type,
+        base_url: base_url.to_string(),
+        ..Default::default()
+    }
 }
 
-pub async fn spawn_upstream_status(status: StatusCode, body: &'static str) -> SocketAddr {
+/// An in-process `mock` channel that answers `from <name>`.
+pub fn mock_channel(name: &str) -> Channel {
+    Channel {
+        mock: Some(apex::config::MockSettings {
+            content: Some(format!("from {name}")),
+            ..Default::default()
+        }),
+        ..test_channel(name, ProviderType::Mock, "mock://local")
+    }
+}
+
+/// Serve `app` on a free local port: a stand-in upstream provider, or a
+/// second gateway for tests that talk across instances.
+pub async fn spawn_upstream(app: axum::Router) -> SocketAddr {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+pub async fn spawn_upstream_ok() -> SocketAddr {
+    spawn_upstream_status(StatusCode::OK, r#"{"id":"test","object":"chat.completion","created":1677652288,"choices":[{"index":0,"message":{"role":"assistant","content":"Hello from upstream"},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":12,"total_tokens":21}}"#).await
+}
 
-    let app = axum::Router::new().fallback(move || async move {
+pub async fn spawn_upstream_status(status: StatusCode, body: &'static str) -> SocketAddr {
+    spawn_upstream(axum::Router::new().fallback(move || async move {
         (
             status,
             axum::response::Json(serde_json::from_str::<serde_json::Value>(body).unwrap()),
         )
-    });
-
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
-
-    addr
+    }))
+    .await
 }
 
 pub async fn spawn_upstream_models() -> SocketAddr {
@@ -93,8 +154,6 @@ pub async fn spawn_upstream_capture(
     status: StatusCode,
     body: &'static str,
 ) -> (SocketAddr, Arc<Mutex<Vec<CapturedRequest>>>) {
-    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let addr = listener.local_addr().unwrap();
     let captures = Arc::new(Mutex::new(Vec::new()));
     let captures_for_app = Arc::clone(&captures);
 
@@ -113,11 +172,7 @@ pub async fn spawn_upstream_capture(
         }
     });
 
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
-
-    (addr, captures)
+    (spawn_upstream(app).await, captures)
 }
 
 pub async fn ensure_upstream_ok(addr: SocketAddr, path: &str) {
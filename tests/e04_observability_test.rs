@@ -3,7 +3,7 @@ use apex::config::{
     Channel, MatchSpec, Metrics, ProviderType, Router as GatewayRouter, RouterRule, TargetChannel,
     Team,
 };
-use apex::server::{build_app, build_state};
+use apex::server::{build_app, build_metrics_app, build_state};
 use axum::body::Body;
 use axum::http::StatusCode;
 use common::*;
@@ -19,6 +19,10 @@ async fn test_observability_metrics() {
     config.metrics = Metrics {
         enabled: true,
         path: "/metrics".to_string(),
+        end_user_label: false,
+        auth_token: None,
+        listen: None,
+        buckets: Default::default(),
     };
 
     // Channel & Router
@@ -27,27 +31,24 @@ async fn test_observability_metrics() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     // 1. Send a request to generate metrics
@@ -60,11 +61,9 @@ async fn test_observability_metrics() {
         api_key: "sk-test".to_string(),
         policy: apex::config::TeamPolicy {
             allowed_routers: vec!["test_router".to_string()],
-            allowed_models: None,
-            rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -98,4 +97,69 @@ async fn test_observability_metrics() {
         "Should contain requests total metric"
     );
     assert!(body.contains("test_router"), "Should contain router label");
+    for name in [
+        "apex_tokio_alive_tasks",
+        "apex_active_streams 0",
+        "apex_upstream_requests_in_flight 0",
+    ] {
+        assert!(body.contains(name), "missing {name}");
+    }
+    #[cfg(target_os = "linux")]
+    {
+        assert!(!body.contains("apex_process_resident_memory_bytes 0\n"));
+        assert!(body.contains("apex_process_open_fds"));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn dedicated_metrics_listener_serves_only_the_scrape_path() {
+    let mut config = base_config();
+    config.global.auth_keys = vec!["sk-global-key".to_string()];
+    config.metrics = Metrics {
+        enabled: true,
+        path: "/internal/metrics".to_string(),
+        end_user_label: false,
+        listen: Some("127.0.0.1:0".to_string()),
+        auth_token: Some("scrape-token".to_string()),
+        buckets: Default::default(),
+    };
+    let state = build_state(config).unwrap();
+
+    let get = |uri: &str, token: Option<&str>| {
+        let mut req = axum::http::Request::builder().method("GET").uri(uri);
+        if let Some(token) = token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+
+    // The main port no longer exposes the scrape endpoint.
+    let app = build_app(state.clone());
+    let resp = app
+        .clone()
+        .oneshot(get("/internal/metrics", Some("sk-global-key")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let metrics_app = build_metrics_app(state);
+    let resp = metrics_app
+        .clone()
+        .oneshot(get("/internal/metrics", None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = metrics_app
+        .clone()
+        .oneshot(get("/internal/metrics", Some("scrape-token")))
+        .await
+        .unwrap();
+    let (status, body) = response_text(resp).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("apex_tokio_alive_tasks"));
+    let resp = metrics_app
+        .oneshot(get("/v1/models", Some("scrape-token")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
@@ -22,12 +22,11 @@ async fn test_rule_based_routing_priority() {
         id: "test-team".to_string(),
         api_key: "sk-test".to_string(),
         policy: apex::config::TeamPolicy {
-            allowed_routers: vec!["main_router".to_string()],
-            allowed_models: None, // Allow all models
+            allowed_routers: vec!["main_router".to_string()], // Allow all models
             rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     // Channels
@@ -36,53 +35,50 @@ async fn test_rule_based_routing_priority() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream_a),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
     std::sync::Arc::make_mut(&mut config.channels).push(Channel {
         name: "channel_b".to_string(),
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream_b),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router with Rules
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
-        name: "main_router".to_string(),
-        channels: vec![],                    // Legacy field empty
+        name: "main_router".to_string(),     // Legacy field empty
         strategy: "round_robin".to_string(), // Default strategy ignored by rules
         metadata: None,
-        fallback_channels: vec![],
         rules: vec![
             // Rule 1: Exact match "gpt-4" -> Channel A
             RouterRule {
                 match_spec: MatchSpec {
                     models: vec!["gpt-4".to_string()],
+                    ..Default::default()
                 },
                 channels: vec![TargetChannel {
                     name: "channel_a".to_string(),
                     weight: 1,
                 }],
                 strategy: "priority".to_string(),
+                retries: None,
             },
             // Rule 2: Glob match "gpt-*" -> Channel B
             RouterRule {
                 match_spec: MatchSpec {
                     models: vec!["gpt-*".to_string()],
+                    ..Default::default()
                 },
                 channels: vec![TargetChannel {
                     name: "channel_b".to_string(),
                     weight: 1,
                 }],
                 strategy: "priority".to_string(),
+                retries: None,
             },
         ],
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -38,28 +38,25 @@ async fn test_pii_masking_email() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
     let state = build_state(config).expect("Failed to build state");
     let app = build_app(state);
@@ -114,28 +111,25 @@ async fn test_pii_blocking_credit_card() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
     let state = build_state(config).expect("Failed to build state");
     let app = build_app(state);
@@ -194,28 +188,25 @@ async fn test_pii_disabled() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -264,28 +255,25 @@ async fn test_pii_absent_noop() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -344,28 +332,25 @@ async fn test_pii_multiple_detections() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -416,28 +401,25 @@ async fn test_pii_custom_rule() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -491,28 +473,25 @@ async fn test_pii_masking_anthropic_route() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "test_router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -572,28 +551,25 @@ async fn test_router_uses_original_model_when_compliance_rewrites_body() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-test".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "exact-router".to_string(),
-        channels: vec![],
         strategy: "priority".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["gpt-4".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "test_channel".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
+        ..Default::default()
     });
 
     let state = build_state(config).expect("Failed to build state");
@@ -654,14 +654,11 @@ APEX_UPSTREAM_1_MODEL=reload-primary-model
         name: "reload_secondary".to_string(),
         provider_type: ProviderType::Openai,
         base_url: second_upstream.base_url(),
-        api_key: String::new(),
-        anthropic_base_url: None,
-        headers: None,
         model_map: Some(std::collections::HashMap::from([(
             "apex-test-chat".to_string(),
             "reload-secondary-model".to_string(),
         )])),
-        timeouts: None,
+        ..Default::default()
     });
     std::sync::Arc::make_mut(&mut config.routers)[0].rules[0].channels = vec![TargetChannel {
         name: "reload_secondary".to_string(),
@@ -194,3 +194,100 @@ async fn test_hot_reload_routing() {
     // So rx1 should be empty now.
     assert!(rx1.try_recv().is_err());
 }
+
+async fn spawn_upstream(port: u16) -> tokio::sync::mpsc::Receiver<()> {
+    let (tx, rx) = tokio::sync::mpsc::channel(10);
+    let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await;
+            let _ = tx.send(()).await;
+            let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK";
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    rx
+}
+
+fn router_file(channel: &str) -> String {
+    serde_json::json!({
+        "name": "default",
+        "rules": [{
+            "match": { "models": ["*"] },
+            "channels": [{ "name": channel, "weight": 1 }],
+            "strategy": "priority"
+        }]
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_hot_reload_watches_include_directories() {
+    let mut rx1 = spawn_upstream(9181).await;
+    let mut rx2 = spawn_upstream(9182).await;
+
+    // Main file without channels or routers; they live in channels/ and routers/.
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    fs::write(
+        &config_path,
+        r#"{
+            "version": "1",
+            "global": {
+                "listen": "127.0.0.1:9180",
+                "auth": { "mode": "none", "keys": null },
+                "timeouts": { "connect_ms": 1000, "request_ms": 3000, "response_ms": 3000 },
+                "retries": { "max_attempts": 1, "backoff_ms": 0, "retry_on_status": [] }
+            },
+            "hot_reload": { "config_path": "", "watch": true },
+            "metrics": { "enabled": false, "path": "/metrics" }
+        }"#,
+    )
+    .unwrap();
+    fs::create_dir(dir.path().join("channels")).unwrap();
+    fs::create_dir(dir.path().join("routers")).unwrap();
+    for (name, port) in [("channel1", 9181), ("channel2", 9182)] {
+        let channel = serde_json::json!({
+            "name": name,
+            "provider_type": "openai",
+            "base_url": format!("http://127.0.0.1:{port}"),
+            "api_key": "sk-test"
+        });
+        fs::write(
+            dir.path().join(format!("channels/{name}.json")),
+            channel.to_string(),
+        )
+        .unwrap();
+    }
+    let router_path = dir.path().join("routers/default.json");
+    fs::write(&router_path, router_file("channel1")).unwrap();
+
+    let path_clone = config_path.clone();
+    let handle = tokio::spawn(async move {
+        if let Err(e) = run_server(path_clone).await {
+            eprintln!("Run server failed: {:?}", e);
+        }
+    });
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(!handle.is_finished(), "Server exited early!");
+
+    let client = reqwest::Client::new();
+    let send = || {
+        client
+            .post("http://127.0.0.1:9180/v1/chat/completions")
+            .body(r#"{"model": "gpt-4"}"#)
+            .send()
+    };
+    assert!(send().await.unwrap().status().is_success());
+    assert!(rx1.try_recv().is_ok());
+    assert!(rx2.try_recv().is_err());
+
+    // Editing only the router include file triggers a reload.
+    fs::write(&router_path, router_file("channel2")).unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert!(send().await.unwrap().status().is_success());
+    assert!(rx2.try_recv().is_ok());
+    assert!(rx1.try_recv().is_err());
+}
@@ -22,10 +22,7 @@ async fn test_team_allowed_models_case_insensitive() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router
@@ -34,17 +31,16 @@ async fn test_team_allowed_models_case_insensitive() {
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Team with Uppercase Model Config
@@ -55,9 +51,9 @@ async fn test_team_allowed_models_case_insensitive() {
             allowed_routers: vec!["r1".to_string()],
             allowed_models: Some(vec!["GPT-4".to_string()]), // Uppercase config
             rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -94,10 +90,7 @@ async fn test_team_allowed_models_glob() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router
@@ -106,17 +99,16 @@ async fn test_team_allowed_models_glob() {
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Team with Glob Pattern
@@ -127,9 +119,9 @@ async fn test_team_allowed_models_glob() {
             allowed_routers: vec!["r1".to_string()],
             allowed_models: Some(vec!["gpt-*".to_string()]), // Glob pattern
             rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -166,10 +158,7 @@ async fn test_team_policy_enforcement() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router
@@ -178,17 +167,16 @@ async fn test_team_policy_enforcement() {
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Team that ONLY allows gpt-4
@@ -198,10 +186,9 @@ async fn test_team_policy_enforcement() {
         policy: TeamPolicy {
             allowed_routers: vec!["r1".to_string()],
             allowed_models: Some(vec!["gpt-4".to_string()]),
-            rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -239,10 +226,7 @@ async fn test_team_auth_x_api_key() {
         provider_type: ProviderType::Anthropic, // Anthropic provider
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router
@@ -251,17 +235,16 @@ async fn test_team_auth_x_api_key() {
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Team
@@ -271,10 +254,9 @@ async fn test_team_auth_x_api_key() {
         policy: TeamPolicy {
             allowed_routers: vec!["r1".to_string()],
             allowed_models: Some(vec!["claude-3".to_string()]),
-            rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -312,27 +294,23 @@ async fn test_team_auth_x_api_key_policy() {
         provider_type: ProviderType::Anthropic,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "r1".to_string(),
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Team
@@ -343,9 +321,9 @@ async fn test_team_auth_x_api_key_policy() {
             allowed_routers: vec!["r1".to_string()],
             allowed_models: Some(vec!["claude-3".to_string()]), // Only allow claude-3
             rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -388,10 +366,7 @@ async fn test_invalid_key_rejection() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
 
     // Router
@@ -400,17 +375,16 @@ async fn test_invalid_key_rejection() {
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     // Add a Team (so config.teams is not empty)
@@ -419,11 +393,9 @@ async fn test_invalid_key_rejection() {
         api_key: "sk-valid-team".to_string(),
         policy: TeamPolicy {
             allowed_routers: vec!["r1".to_string()],
-            allowed_models: None,
-            rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();
@@ -494,11 +466,9 @@ async fn test_valid_global_key_acceptance() {
         api_key: "sk-global-key".to_string(),
         policy: TeamPolicy {
             allowed_routers: vec!["r1".to_string()],
-            allowed_models: None,
-            rate_limit: None,
+            ..Default::default()
         },
-        group: None,
-        enabled: None,
+        ..Default::default()
     });
 
     // Channel & Router (Standard)
@@ -507,27 +477,23 @@ async fn test_valid_global_key_acceptance() {
         provider_type: ProviderType::Openai,
         base_url: base_url(upstream),
         api_key: "sk-upstream".to_string(),
-        anthropic_base_url: None,
-        headers: None,
-        model_map: None,
-        timeouts: None,
+        ..Default::default()
     });
     std::sync::Arc::make_mut(&mut config.routers).push(GatewayRouter {
         name: "r1".to_string(),
         rules: vec![RouterRule {
             match_spec: MatchSpec {
                 models: vec!["*".to_string()],
+                ..Default::default()
             },
             channels: vec![TargetChannel {
                 name: "primary".to_string(),
                 weight: 1,
             }],
             strategy: "priority".to_string(),
+            retries: None,
         }],
-        channels: vec![],
-        strategy: "round_robin".to_string(),
-        metadata: None,
-        fallback_channels: vec![],
+        ..Default::default()
     });
 
     let state = build_state(config).unwrap();